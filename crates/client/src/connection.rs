@@ -1,19 +1,33 @@
 use futures_util::{SinkExt, StreamExt};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio_tungstenite::tungstenite::Message;
 use uuid::Uuid;
 
 use types::{ClientMessage, ServerMessage};
 
+type Streams = Arc<Mutex<HashMap<String, mpsc::UnboundedSender<ServerMessage>>>>;
+
 pub struct Connection {
   tx: mpsc::UnboundedSender<(ClientMessage, oneshot::Sender<ServerMessage>)>,
   sub_rx: Arc<Mutex<mpsc::UnboundedReceiver<ServerMessage>>>,
+  /// Receivers of streamed query results, by request ID
+  streams: Streams,
 }
 
 impl Connection {
   pub async fn connect(url: &str) -> Result<Self, anyhow::Error> {
+    Self::open(url, None).await
+  }
+
+  /// Connect to a server with authentication enabled
+  pub async fn connect_with_token(url: &str, token: &str) -> Result<Self, anyhow::Error> {
+    Self::open(url, Some(token)).await
+  }
+
+  async fn open(url: &str, token: Option<&str>) -> Result<Self, anyhow::Error> {
     let ws_url = if url.starts_with("ws://") {
       url.into()
     } else {
@@ -22,11 +36,32 @@ impl Connection {
     let (ws, _) = tokio_tungstenite::connect_async(&ws_url).await?;
     let (mut sink, mut stream) = ws.split();
 
+    // The token must be the first message; wait for the verdict
+    if let Some(token) = token {
+      let auth = serde_json::json!({"type": "Auth", "token": token});
+      sink.send(Message::Text(auth.to_string().into())).await?;
+      loop {
+        let Some(Ok(Message::Text(text))) = stream.next().await else {
+          anyhow::bail!("Connection closed during authentication");
+        };
+        let reply: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+        match reply.get("type").and_then(|t| t.as_str()) {
+          Some("AuthSuccess") => break,
+          Some("AuthFailure") => anyhow::bail!(
+            "Authentication failed: {}",
+            reply.get("error").and_then(|e| e.as_str()).unwrap_or("")
+          ),
+          _ => continue,
+        }
+      }
+    }
+
     let (req_tx, mut req_rx) =
       mpsc::unbounded_channel::<(ClientMessage, oneshot::Sender<ServerMessage>)>();
     let (sub_tx, sub_rx) = mpsc::unbounded_channel();
     let pending: Arc<Mutex<HashMap<String, oneshot::Sender<ServerMessage>>>> =
       Arc::new(Mutex::new(HashMap::new()));
+    let streams = Streams::default();
 
     let pending2 = pending.clone();
     tokio::spawn(async move {
@@ -42,21 +77,36 @@ impl Connection {
       }
     });
 
+    let streams2 = streams.clone();
     tokio::spawn(async move {
       while let Some(Ok(Message::Text(text))) = stream.next().await {
         if let Ok(msg) = serde_json::from_str::<ServerMessage>(&text) {
           let id = match &msg {
-            ServerMessage::Change { .. } => {
+            ServerMessage::Change { .. } | ServerMessage::Diff { .. } => {
               let _ = sub_tx.send(msg);
               continue;
             }
             ServerMessage::Result { id, .. }
-            | ServerMessage::Subscribed { id }
+            | ServerMessage::ResultChunk { id, .. }
+            | ServerMessage::ResultEnd { id, .. }
+            | ServerMessage::Subscribed { id, .. }
             | ServerMessage::Unsubscribed { id }
             | ServerMessage::ProjectSelected { id, .. }
             | ServerMessage::Error { id, .. }
-            | ServerMessage::Pong { id } => id.clone(),
+            | ServerMessage::Pong { id }
+            | ServerMessage::Hello { id, .. } => id.clone(),
           };
+          let mut streams = streams2.lock().await;
+          if let Some(tx) = streams.get(&id) {
+            let last = !matches!(msg, ServerMessage::ResultChunk { .. });
+            let _ = tx.send(msg);
+            if last {
+              streams.remove(&id);
+              pending.lock().await.remove(&id);
+            }
+            continue;
+          }
+          drop(streams);
           if let Some(tx) = pending.lock().await.remove(&id) {
             let _ = tx.send(msg);
           }
@@ -67,6 +117,7 @@ impl Connection {
     Ok(Self {
       tx: req_tx,
       sub_rx: Arc::new(Mutex::new(sub_rx)),
+      streams,
     })
   }
 
@@ -84,6 +135,74 @@ impl Connection {
       .send(ClientMessage::Query {
         id: Uuid::new_v4().to_string(),
         query: q.into(),
+        cache: false,
+        cache_ttl: None,
+        params: Vec::new(),
+        stream: false,
+      })
+      .await
+  }
+
+  /// Run a query whose filter refers to `params` as `$1`, `$2`, ...
+  pub async fn query_with_params(
+    &self,
+    q: &str,
+    params: Vec<serde_json::Value>,
+  ) -> Result<ServerMessage, anyhow::Error> {
+    self
+      .send(ClientMessage::Query {
+        id: Uuid::new_v4().to_string(),
+        query: q.into(),
+        cache: false,
+        cache_ttl: None,
+        params,
+        stream: false,
+      })
+      .await
+  }
+
+  /// Run a query with its rows streamed back: `ResultChunk` messages, then
+  /// `ResultEnd` or `Error`, after which the receiver closes
+  pub async fn query_stream(
+    &self,
+    q: &str,
+  ) -> Result<mpsc::UnboundedReceiver<ServerMessage>, anyhow::Error> {
+    let id = Uuid::new_v4().to_string();
+    let (tx, rx) = mpsc::unbounded_channel();
+    self.streams.lock().await.insert(id.clone(), tx);
+    // Replies go to the stream; the one-shot reply channel is never used
+    let (reply_tx, _) = oneshot::channel();
+    self
+      .tx
+      .send((
+        ClientMessage::Query {
+          id,
+          query: q.into(),
+          cache: false,
+          cache_ttl: None,
+          params: Vec::new(),
+          stream: true,
+        },
+        reply_tx,
+      ))
+      .map_err(|_| anyhow::anyhow!("closed"))?;
+    Ok(rx)
+  }
+
+  /// Run a read query through the server's query cache
+  pub async fn query_cached(
+    &self,
+    q: &str,
+    ttl: Option<Duration>,
+  ) -> Result<ServerMessage, anyhow::Error> {
+    self
+      .send(ClientMessage::Query {
+        id: Uuid::new_v4().to_string(),
+        query: q.into(),
+        cache: true,
+        cache_ttl: ttl.map(|t| t.as_secs()),
+        params: Vec::new(),
+        stream: false,
       })
       .await
   }
@@ -93,6 +212,19 @@ impl Connection {
       .send(ClientMessage::Subscribe {
         id: Uuid::new_v4().to_string(),
         query: q.into(),
+        read_your_writes: false,
+        ids: Vec::new(),
+      })
+      .await
+  }
+
+  /// Watch a query's result; its `diff` messages arrive through
+  /// `recv_change`. Needs protocol version 3, negotiated with `hello`.
+  pub async fn watch(&self, q: &str) -> Result<ServerMessage, anyhow::Error> {
+    self
+      .send(ClientMessage::Watch {
+        id: Uuid::new_v4().to_string(),
+        query: q.into(),
       })
       .await
   }
@@ -2,13 +2,14 @@ use axum::extract::Request;
 use axum::{
   body::Body,
   extract::{
+    multipart::MultipartError,
     ws::{Message, WebSocket, WebSocketUpgrade},
-    Multipart, Path, Query, State,
+    ConnectInfo, DefaultBodyLimit, Multipart, Path, Query, State,
   },
-  http::{header, HeaderMap, StatusCode},
+  http::{header, HeaderMap, HeaderValue, Method, StatusCode},
   middleware::Next,
   response::{Html, IntoResponse, Response},
-  routing::{delete, get, post, put},
+  routing::{delete, get, patch, post, put},
   Json, Router,
 };
 use futures_util::{SinkExt, StreamExt};
@@ -17,25 +18,42 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
 use sha2::Sha256;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::{broadcast, mpsc, RwLock};
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::services::{ServeDir, ServeFile};
 use uuid::Uuid;
 
 use super::auth;
+use super::notify::{self, AccountNotification};
+use super::oidc::{self, OidcVerifier};
 use crate::cache::CacheStore;
-use crate::db::{AdminRole, AdminUser, ApiTokenInfo, DatabaseBackend, SqlDialect};
+use crate::db::{
+  insert_idempotent, sanitize::count_statements, validate_collection_name,
+  validate_idempotency_key, ActiveQuery, AdminRole, AdminTokenPurpose, AdminUser, ApiTokenInfo,
+  CollectionDefaults, CollectionStats, DatabaseBackend, DocumentCount, DocumentEdit,
+  IdempotentInsert, ListenerHeartbeat, ProjectLimits, RawSqlResult, ReadOptions,
+  SoftDeleteSettings, SqlDialect, WriteOptions, MAX_IDEMPOTENCY_KEY_LEN,
+};
 use crate::features::{FeatureInfo, FeatureRegistry};
-use crate::query::{QueryEngine, QueryEnginePool};
+use crate::query::{
+  bind_params, QueryEngine, QueryEnginePool, ResultSchema, StructuredCompiler, WarmupQuery,
+  WarmupReport,
+};
 use crate::security::headers::SecurityHeadersLayer;
-use crate::server::{MessageHandler, RateLimiter, ServerConfig};
+use crate::server::{
+  accept_trace_id, apply_inexact_numbers, current_trace_id, decode_client_message, new_trace_id,
+  queue_message, with_trace_id, ConnectionInfo, Connections, CorsOrigins, LimitsSection,
+  Maintenance, MaintenanceSettings, MessageHandler, RateLimitError, RateLimiter, ServerConfig,
+  ServerListener, ServerTls, WsClients, SEND_TIMEOUT, TRACE_ID_HEADER,
+};
 use crate::subscriptions::SubscriptionManager;
-use crate::types::{ClientMessage, ServerMessage, DEFAULT_PROJECT_ID};
+use crate::types::{
+  Change, Document, ErrorCode, ServerMessage, StructuredFilter, StructuredQuery, DEFAULT_PROJECT_ID,
+};
 
 type Backend = Arc<dyn DatabaseBackend>;
-type WsClients = Arc<RwLock<HashMap<Uuid, mpsc::UnboundedSender<ServerMessage>>>>;
 
 /// Log entry for streaming to clients
 #[derive(Clone, Serialize, Debug)]
@@ -44,6 +62,9 @@ pub struct LogEntry {
   pub level: String,
   pub target: String,
   pub message: String,
+  /// Correlation ID of the request that produced the entry
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub trace_id: Option<String>,
 }
 
 /// Shared application state
@@ -61,6 +82,18 @@ pub struct AppState {
   pub feature_registry: Arc<FeatureRegistry>,
   pub shutdown_tx: Option<broadcast::Sender<()>>,
   pub rate_limiter: Arc<RateLimiter>,
+  /// Updated by the backend's change listener while it runs
+  pub listener_heartbeat: Arc<ListenerHeartbeat>,
+  /// Refuses data-plane requests while the server is in maintenance mode
+  pub maintenance: Maintenance,
+  /// Open WebSocket and TCP connections across all servers
+  pub connections: Connections,
+  /// Verifies JWTs from the external OIDC provider, when configured
+  pub oidc: Option<Arc<OidcVerifier>>,
+  /// Origins allowed to open WebSocket connections, shared with the CORS layer
+  pub cors_origins: CorsOrigins,
+  /// When each user last asked for a password reset
+  pub reset_requests: Arc<Mutex<HashMap<String, std::time::Instant>>>,
 }
 
 /// Global log broadcaster - initialized once and used throughout the app
@@ -85,6 +118,7 @@ pub fn emit_log(level: &str, target: &str, message: &str) {
     level: level.to_string(),
     target: target.to_string(),
     message: message.to_string(),
+    trace_id: current_trace_id(),
   };
   // Ignore send errors (no subscribers)
   let _ = tx.send(entry);
@@ -100,6 +134,10 @@ pub struct AdminServer {
   config: ServerConfig,
   feature_registry: Arc<FeatureRegistry>,
   rate_limiter: Arc<RateLimiter>,
+  tls: Option<Arc<ServerTls>>,
+  cors_origins: CorsOrigins,
+  maintenance: Maintenance,
+  connections: Connections,
 }
 
 impl AdminServer {
@@ -114,6 +152,7 @@ impl AdminServer {
     feature_registry: Arc<FeatureRegistry>,
     rate_limiter: Arc<RateLimiter>,
   ) -> Self {
+    let cors_origins = CorsOrigins::new(config.server.cors_origins.clone());
     Self {
       backend,
       subs,
@@ -123,18 +162,54 @@ impl AdminServer {
       config,
       feature_registry,
       rate_limiter,
+      tls: None,
+      cors_origins,
+      maintenance: Maintenance::default(),
+      connections: Connections::default(),
     }
   }
 
+  /// Serve the admin UI and REST API over HTTPS
+  pub fn with_tls(mut self, tls: Option<Arc<ServerTls>>) -> Self {
+    self.tls = tls;
+    self
+  }
+
+  /// Share CORS origins with the daemon so config reloads take effect
+  pub fn with_cors_origins(mut self, cors_origins: CorsOrigins) -> Self {
+    self.cors_origins = cors_origins;
+    self
+  }
+
+  /// Share the maintenance switch with the daemon's other servers
+  pub fn with_maintenance(mut self, maintenance: Maintenance) -> Self {
+    self.maintenance = maintenance;
+    self
+  }
+
+  /// Share the connection registry with the daemon's other servers
+  pub fn with_connections(mut self, connections: Connections) -> Self {
+    self.connections = connections;
+    self
+  }
+
   pub async fn run(mut self, addr: &str) -> Result<(), anyhow::Error> {
     let dialect = self.backend.dialect();
     let ws_clients: WsClients = Arc::new(RwLock::new(HashMap::new()));
     let log_tx = get_log_broadcaster();
+    let oidc = if self.config.auth.oidc.enabled {
+      Some(Arc::new(OidcVerifier::new(self.config.auth.oidc.clone())?))
+    } else {
+      None
+    };
 
     let state = AppState {
       dialect,
-      engine: Arc::new(Mutex::new(QueryEngine::new(dialect))),
+      engine: Arc::new(Mutex::new(
+        QueryEngine::new(dialect).with_max_filter_depth(self.config.limits.max_filter_depth),
+      )),
       engine_pool: self.engine_pool,
+      listener_heartbeat: self.backend.change_listener_heartbeat(),
       backend: self.backend,
       start_time: std::time::Instant::now(),
       subs: self.subs.clone(),
@@ -144,6 +219,11 @@ impl AdminServer {
       feature_registry: self.feature_registry.clone(),
       shutdown_tx: Some(self.shutdown_tx.clone()),
       rate_limiter: self.rate_limiter.clone(),
+      maintenance: self.maintenance.clone(),
+      connections: self.connections.clone(),
+      oidc,
+      cors_origins: self.cors_origins.clone(),
+      reset_requests: Arc::new(Mutex::new(HashMap::new())),
     };
 
     // Spawn task to forward subscription changes to WebSocket clients
@@ -152,9 +232,7 @@ impl AdminServer {
     tokio::spawn(async move {
       let mut rx = subs.subscribe_to_outgoing();
       while let Ok((client_id, msg)) = rx.recv().await {
-        if let Some(tx) = clients.read().await.get(&client_id) {
-          let _ = tx.send(msg);
-        }
+        queue_message(&clients, client_id, msg).await;
       }
     });
 
@@ -170,17 +248,36 @@ impl AdminServer {
             .route("/login", get(serve_login_page))
             // Setup API - only works when no tokens exist
             .route("/api/setup", post(api_setup_token))
-            // User authentication endpoints - public
-            .route("/api/auth/status", get(api_auth_status))
-            .route("/api/auth/setup", post(api_auth_setup))
-            .route("/api/auth/login", post(api_auth_login))
-            .route("/api/auth/logout", post(api_auth_logout))
-            .route("/api/auth/change-password", post(api_auth_change_password));
+            .layer(body_limit(self.config.limits.max_request_body_bytes));
+
+    // User authentication endpoints (public, but rate limited)
+    let auth_routes = Router::new()
+      .route("/api/auth/status", get(api_auth_status))
+      .route("/api/auth/me", get(api_auth_me))
+      .route("/api/auth/setup", post(api_auth_setup))
+      .route("/api/auth/login", post(api_auth_login))
+      .route("/api/auth/oidc", post(api_auth_oidc))
+      .route("/api/auth/logout", post(api_auth_logout))
+      .route("/api/auth/change-password", post(api_auth_change_password))
+      .route("/api/auth/forgot", post(api_auth_forgot))
+      .route("/api/auth/reset", post(api_auth_reset))
+      .route(
+        "/api/auth/verify-email/send",
+        post(api_auth_send_verification),
+      )
+      .route("/api/auth/verify-email", post(api_auth_verify_email))
+      .layer(body_limit(self.config.limits.max_request_body_bytes))
+      .layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        rate_limit_middleware,
+      ));
+    app = app.merge(auth_routes);
 
     // Admin API routes (protected by admin auth)
     let admin_routes = Router::new()
       .route("/api/settings", get(api_get_settings))
       .route("/api/settings", put(api_update_settings))
+      .route("/api/tokens", post(api_create_default_token))
       .route("/api/projects/{project_id}/tokens", get(api_list_tokens))
       .route("/api/projects/{project_id}/tokens", post(api_create_token))
       .route("/api/projects/{project_id}/tokens/{id}", delete(api_delete_token))
@@ -197,9 +294,22 @@ impl AdminServer {
         "/api/settings/protocols",
         get(api_get_protocol_settings).put(api_update_protocol_settings),
       )
+      // Maintenance mode
+      .route(
+        "/api/settings/maintenance",
+        get(api_get_maintenance).put(api_update_maintenance),
+      )
       // Server control
       .route("/api/server/restart", post(api_restart_server))
       .route("/api/server/health", get(api_health_check))
+      // Document change history
+      .route(
+        "/api/collections/{name}/documents/{id}/history",
+        get(api_document_history),
+      )
+      // Open data connections
+      .route("/api/connections", get(api_list_connections))
+      .route("/api/connections/{id}", delete(api_disconnect_connection))
       // CORS settings
       .route(
         "/api/settings/cors",
@@ -221,7 +331,10 @@ impl AdminServer {
       .route("/api/s3/buckets/{bucket}/objects", get(api_list_bucket_objects))
       .route("/api/s3/buckets/{bucket}/objects/{*key}", delete(api_delete_bucket_object))
       .route("/api/s3/buckets/{bucket}/download/{*key}", get(api_download_object))
-      .route("/api/s3/buckets/{bucket}/upload", post(api_upload_object))
+      .route(
+        "/api/s3/buckets/{bucket}/upload",
+        post(api_upload_object).layer(body_limit(self.config.limits.max_upload_bytes)),
+      )
       // Proxy test endpoints
       .route("/api/s3/test-connection", post(api_test_storage_connection))
       .route("/api/cache/test-connection", post(api_test_cache_connection))
@@ -232,6 +345,7 @@ impl AdminServer {
       )
       .route("/api/cache/stats", get(api_get_cache_stats))
       .route("/api/cache/flush", post(api_flush_cache))
+      .route("/api/cache/warm", post(api_warm_cache))
       // Backup management
       .route(
         "/api/backup/settings",
@@ -240,11 +354,27 @@ impl AdminServer {
       .route("/api/backup/list", get(api_list_backups))
       .route("/api/backup/create", post(api_create_backup))
       .route("/api/backup/{id}", delete(api_delete_backup))
+      .route("/api/backup/{id}/restore", post(api_restore_backup))
+      // Webhook management
+      .route(
+        "/api/webhooks/settings",
+        get(api_get_webhook_settings).put(api_update_webhook_settings),
+      )
       // User management (owner only)
       .route("/api/users", get(api_list_users))
       .route("/api/users", post(api_create_user))
       .route("/api/users/{id}", delete(api_delete_user))
       .route("/api/users/{id}/role", put(api_update_user_role))
+      // Raw SQL (owner only, disabled by default)
+      .route("/api/sql", post(api_raw_sql))
+      // Running database queries (owner only)
+      .route("/api/queries/active", get(api_active_queries))
+      .route("/api/queries/{pid}/cancel", post(api_cancel_query))
+      // Index rebuilds (owner only)
+      .route(
+        "/api/collections/{name}/reindex",
+        post(api_reindex_collection),
+      )
       // Project management
       .route("/api/projects", get(api_list_projects))
       .route("/api/projects", post(api_create_project))
@@ -262,6 +392,13 @@ impl AdminServer {
         delete(api_remove_project_member),
       )
       .route("/api/projects/{id}/select", post(api_select_project))
+      .route(
+        "/api/projects/{id}/limits",
+        get(api_get_project_limits)
+          .put(api_set_project_limits)
+          .delete(api_delete_project_limits),
+      )
+      .layer(body_limit(self.config.limits.max_request_body_bytes))
       .layer(axum::middleware::from_fn_with_state(
         state.clone(),
         admin_auth_middleware,
@@ -279,17 +416,55 @@ impl AdminServer {
         .route("/api/collections", get(api_collections))
         .route("/api/collections/{name}", get(api_collection_docs))
         .route("/api/collections/{name}", delete(api_drop_collection))
+        .route(
+          "/api/collections/{name}/rename",
+          post(api_rename_collection),
+        )
+        .route("/api/collections/{name}/copy", post(api_copy_collection))
+        .route("/api/collections/{name}/stats", get(api_collection_stats))
+        .route(
+          "/api/collections/{name}/soft-delete",
+          get(api_get_soft_delete)
+            .put(api_set_soft_delete)
+            .delete(api_disable_soft_delete),
+        )
+        .route(
+          "/api/collections/{name}/defaults",
+          get(api_get_collection_defaults)
+            .put(api_set_collection_defaults)
+            .delete(api_delete_collection_defaults),
+        )
+        .route(
+          "/api/kv/{namespace}/{key}",
+          get(api_get_kv).put(api_set_kv).delete(api_delete_kv),
+        )
+        .route("/api/collections/{name}/findOne", get(api_find_one))
+        .route("/api/collections/{name}/getMany", post(api_get_many))
         .route("/api/collections/{name}/documents", post(api_insert_doc))
         .route("/api/collections/{name}/documents/{id}", get(api_get_doc))
         .route(
           "/api/collections/{name}/documents/{id}",
-          put(api_update_doc),
+          put(api_update_doc).patch(api_patch_doc),
         )
         .route(
           "/api/collections/{name}/documents/{id}",
           delete(api_delete_doc),
         )
+        .route(
+          "/api/collections/{name}/documents/{id}/restore",
+          post(api_restore_doc),
+        )
+        .route("/api/collections/{name}/deleteMany", post(api_delete_many))
         .route("/api/query", post(api_query))
+        .layer(axum::middleware::from_fn_with_state(
+          state.clone(),
+          slow_request_middleware,
+        ))
+        .layer(body_limit(self.config.limits.max_document_bytes))
+        .layer(axum::middleware::from_fn_with_state(
+          state.clone(),
+          maintenance_middleware,
+        ))
         .layer(axum::middleware::from_fn_with_state(
           state.clone(),
           rate_limit_middleware,
@@ -305,43 +480,54 @@ impl AdminServer {
     // Log streaming WebSocket (admin only, protected by auth)
     app = app.route("/ws/logs", get(ws_logs_handler));
 
-    // Build CORS layer based on config
-    let cors = if self.config.server.cors_origins.is_empty()
-      || self.config.server.cors_origins.iter().any(|o| o == "*")
-    {
-      CorsLayer::permissive()
-    } else {
-      let origins: Vec<_> = self
-        .config
-        .server
-        .cors_origins
-        .iter()
-        .filter_map(|o| o.parse().ok())
-        .collect();
-      CorsLayer::new()
-        .allow_origin(origins)
-        .allow_methods(Any)
-        .allow_headers(Any)
-    };
+    // CORS origins are checked per request so they can be reloaded
+    let cors_origins = self.cors_origins.clone();
+    let cors = CorsLayer::new()
+      .allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+        origin
+          .to_str()
+          .map(|o| cors_origins.allows(o))
+          .unwrap_or(false)
+      }))
+      .allow_methods(Any)
+      .allow_headers(Any)
+      .expose_headers(Any);
+
+    let security_headers = &self.config.server.security_headers;
+    let security_headers = SecurityHeadersLayer::new(
+      &security_headers.content_security_policy,
+      &security_headers.frame_options,
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid server.security_headers value: {}", e))?;
 
     // Serve WASM bundle from target/admin, fallback to index.html for SPA routing
     let app = app
       .fallback_service(
         ServeDir::new("target/admin").not_found_service(ServeFile::new("target/admin/index.html")),
       )
-      .layer(SecurityHeadersLayer)
+      .layer(axum::middleware::from_fn(pretty_json_middleware))
+      .layer(security_headers)
       .layer(cors)
+      .layer(axum::middleware::from_fn_with_state(
+        self.config.limits.max_header_bytes,
+        request_size_middleware,
+      ))
+      .layer(axum::middleware::from_fn(trace_middleware))
       .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    tracing::info!("Admin UI at http://{}", addr);
+    let listener = ServerListener::bind(addr, self.tls.clone()).await?;
+    let scheme = if listener.is_tls() { "https" } else { "http" };
+    tracing::info!("Admin UI at {}://{}", scheme, addr);
 
-    axum::serve(listener, app.into_make_service())
-      .with_graceful_shutdown(async move {
-        let _ = self.shutdown_rx.recv().await;
-        tracing::info!("Admin server shutting down");
-      })
-      .await?;
+    axum::serve(
+      listener,
+      app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+      let _ = self.shutdown_rx.recv().await;
+      tracing::info!("Admin server shutting down");
+    })
+    .await?;
     Ok(())
   }
 }
@@ -686,29 +872,159 @@ async fn health_check() -> StatusCode {
   StatusCode::OK
 }
 
-/// Readiness probe - returns 200 if database is accessible
+/// Readiness probe - returns 200 if database is accessible and the change
+/// listener is running (a dead listener silently breaks subscriptions)
 async fn readiness_check(State(state): State<AppState>) -> StatusCode {
+  if !state.listener_heartbeat.is_alive() {
+    tracing::warn!(
+      "Change listener heartbeat is stale (last beat: {:?})",
+      state.listener_heartbeat.last_beat()
+    );
+    return StatusCode::SERVICE_UNAVAILABLE;
+  }
   match state.backend.list_collections(DEFAULT_PROJECT_ID).await {
     Ok(_) => StatusCode::OK,
     Err(_) => StatusCode::SERVICE_UNAVAILABLE,
   }
 }
 
+/// Resolve the project a REST data request runs against.
+///
+/// API tokens are bound to a single project; an `X-Project-Id` header may only
+/// name that project. Admin sessions may pick any project they are a member of
+/// (system owners may pick any project). Without a header, the default project
+/// is used only when the choice is unambiguous. The request counts against
+/// the project's rate limit, if it has one.
+async fn resolve_project(state: &AppState, headers: &HeaderMap) -> Result<Uuid, AppError> {
+  let project_id = select_request_project(state, headers).await?;
+  state.rate_limiter.check_project_request(project_id)?;
+  Ok(project_id)
+}
+
+async fn select_request_project(state: &AppState, headers: &HeaderMap) -> Result<Uuid, AppError> {
+  let requested = match headers.get("X-Project-Id") {
+    Some(v) => Some(
+      v.to_str()
+        .ok()
+        .and_then(|s| s.trim().parse::<Uuid>().ok())
+        .ok_or_else(|| AppError::BadRequest("Invalid X-Project-Id header".to_string()))?,
+    ),
+    None => None,
+  };
+
+  let Some(token) = extract_token_from_headers(headers) else {
+    if state.config.auth.enabled && requested.is_some_and(|p| p != DEFAULT_PROJECT_ID) {
+      return Err(AppError::Unauthorized(
+        "Authentication required to select a project".to_string(),
+      ));
+    }
+    return ensure_project_exists(state, requested.unwrap_or(DEFAULT_PROJECT_ID)).await;
+  };
+
+  // Admin session: validate against the user's project memberships
+  if let Some(session_token) = token.strip_prefix("session_") {
+    let session_hash = auth::hash_session_token(session_token);
+    let (_, user) = state
+      .backend
+      .validate_admin_session(&session_hash)
+      .await?
+      .ok_or_else(|| AppError::Unauthorized("Invalid session".to_string()))?;
+
+    if let Some(project_id) = requested {
+      if user.role != AdminRole::Owner
+        && state
+          .backend
+          .get_user_project_role(project_id, user.id)
+          .await?
+          .is_none()
+      {
+        return Err(AppError::Forbidden(
+          "Not a member of this project".to_string(),
+        ));
+      }
+      return ensure_project_exists(state, project_id).await;
+    }
+
+    if user.role == AdminRole::Owner {
+      return Ok(DEFAULT_PROJECT_ID);
+    }
+    let projects = state.backend.list_user_projects(user.id).await?;
+    return match projects.as_slice() {
+      [] if user.role == AdminRole::Member => Err(AppError::Forbidden(
+        "Not a member of any project".to_string(),
+      )),
+      [] => Ok(DEFAULT_PROJECT_ID),
+      [only] => Ok(only.id),
+      _ => Err(AppError::BadRequest(
+        "Multiple projects available, set the X-Project-Id header".to_string(),
+      )),
+    };
+  }
+
+  // Admin token: unrestricted
+  if let Some(ref admin_token) = state.config.auth.admin_token {
+    if !admin_token.is_empty() && crate::security::constant_time_compare(&token, admin_token) {
+      return ensure_project_exists(state, requested.unwrap_or(DEFAULT_PROJECT_ID)).await;
+    }
+  }
+
+  // API token: bound to the project it was created in
+  let bound = state
+    .backend
+    .validate_token(&hash_token(&token))
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid token".to_string()))?;
+  match requested {
+    Some(project_id) if project_id != bound => Err(AppError::Forbidden(
+      "Token is not valid for this project".to_string(),
+    )),
+    _ => Ok(bound),
+  }
+}
+
+/// API token or admin user behind a request, recorded for `$user` defaults.
+/// None without credentials or with the admin token.
+async fn request_author(state: &AppState, headers: &HeaderMap) -> Result<Option<Uuid>, AppError> {
+  let Some(token) = extract_token_from_headers(headers) else {
+    return Ok(None);
+  };
+  if let Some(session_token) = token.strip_prefix("session_") {
+    let session_hash = auth::hash_session_token(session_token);
+    let session = state.backend.validate_admin_session(&session_hash).await?;
+    return Ok(session.map(|(_, user)| user.id));
+  }
+  if let Some(ref admin_token) = state.config.auth.admin_token {
+    if !admin_token.is_empty() && crate::security::constant_time_compare(&token, admin_token) {
+      return Ok(None);
+    }
+  }
+  let token = state.backend.get_token_by_hash(&hash_token(&token)).await?;
+  Ok(token.map(|t| t.id))
+}
+
+/// Check that a non-default project exists before running against it
+async fn ensure_project_exists(state: &AppState, project_id: Uuid) -> Result<Uuid, AppError> {
+  if project_id != DEFAULT_PROJECT_ID && state.backend.get_project(project_id).await?.is_none() {
+    return Err(AppError::NotFound("Project not found".to_string()));
+  }
+  Ok(project_id)
+}
+
 async fn api_collections(
   State(state): State<AppState>,
+  headers: HeaderMap,
 ) -> Result<Json<Vec<CollectionInfo>>, AppError> {
-  let names = state.backend.list_collections(DEFAULT_PROJECT_ID).await?;
-  let mut collections = Vec::with_capacity(names.len());
-  for name in names {
-    let docs = state
-      .backend
-      .list(DEFAULT_PROJECT_ID, &name, None, None, None, None)
-      .await?;
-    collections.push(CollectionInfo {
+  let project_id = resolve_project(&state, &headers).await?;
+  let collections = state
+    .backend
+    .collection_counts(project_id)
+    .await?
+    .into_iter()
+    .map(|(name, count)| CollectionInfo {
       name,
-      count: docs.len(),
-    });
-  }
+      count: count as usize,
+    })
+    .collect();
   Ok(Json(collections))
 }
 
@@ -722,156 +1038,1006 @@ struct CollectionInfo {
 struct ListQuery {
   limit: Option<usize>,
   offset: Option<usize>,
+  /// Include soft-deleted documents
+  #[serde(default)]
+  with_deleted: bool,
+  /// Wrap the page in `data`, next to the `total` number of documents
+  #[serde(default)]
+  include_total: bool,
+  /// Allow an estimated total for large collections
+  #[serde(default)]
+  approximate: bool,
+}
+
+/// Limit to fetch for a REST listing: the requested one, or one past
+/// `max_result_rows` so a cut result can be detected
+fn result_fetch_limit(limits: &LimitsSection, requested: Option<usize>) -> Option<usize> {
+  requested.or((limits.max_result_rows > 0).then(|| limits.max_result_rows + 1))
+}
+
+/// Respond with a listing fetched with `result_fetch_limit`, cutting it down to
+/// `max_result_rows` and flagging it with `X-Result-Truncated` if needed.
+/// With `with_schema` the documents go under `data`, next to their inferred
+/// field metadata under `schema`. A `total` goes next to them the same way.
+fn result_rows_response(
+  limits: &LimitsSection,
+  requested: Option<usize>,
+  mut docs: Vec<Document>,
+  with_schema: bool,
+  total: Option<DocumentCount>,
+) -> Result<Response, AppError> {
+  let truncated =
+    requested.is_none() && limits.max_result_rows > 0 && docs.len() > limits.max_result_rows;
+  if truncated {
+    docs.truncate(limits.max_result_rows);
+  }
+  let body = if with_schema || total.is_some() {
+    let mut body = serde_json::json!({ "data": docs });
+    if with_schema {
+      body["schema"] = serde_json::to_value(ResultSchema::infer(&docs))?;
+    }
+    if let Some(count) = total {
+      body["total"] = count.total.into();
+      body["approximate"] = count.approximate.into();
+    }
+    body
+  } else {
+    serde_json::to_value(docs)?
+  };
+  if truncated {
+    return Ok(([("X-Result-Truncated", "true")], Json(body)).into_response());
+  }
+  Ok(Json(body).into_response())
 }
 
 async fn api_collection_docs(
   State(state): State<AppState>,
+  headers: HeaderMap,
   Path(name): Path<String>,
   Query(q): Query<ListQuery>,
-) -> Result<Json<serde_json::Value>, AppError> {
+) -> Result<Response, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let limits = &state.config.limits;
+  let read = ReadOptions {
+    with_deleted: q.with_deleted,
+  };
   // Use database-level pagination for better performance
   let docs = state
     .backend
-    .list(DEFAULT_PROJECT_ID, &name, None, None, q.limit, q.offset)
+    .list_with_options(
+      project_id,
+      &name,
+      None,
+      None,
+      &[],
+      &[],
+      result_fetch_limit(limits, q.limit),
+      q.offset,
+      read,
+    )
     .await?;
-  Ok(Json(serde_json::to_value(docs)?))
+  let total = if q.include_total {
+    Some(
+      state
+        .backend
+        .count_documents(project_id, &name, None, &[], q.approximate, read)
+        .await?,
+    )
+  } else {
+    None
+  };
+  result_rows_response(limits, q.limit, docs, false, total)
 }
 
-async fn api_drop_collection(
+async fn api_collection_stats(
   State(state): State<AppState>,
+  headers: HeaderMap,
   Path(name): Path<String>,
-) -> Result<Json<serde_json::Value>, AppError> {
-  let docs = state
-    .backend
-    .list(DEFAULT_PROJECT_ID, &name, None, None, None, None)
-    .await?;
-  let mut deleted = 0;
-  for doc in docs {
-    state
-      .backend
-      .delete(DEFAULT_PROJECT_ID, &name, doc.id)
-      .await?;
-    deleted += 1;
+) -> Result<Json<CollectionStats>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_collection_name(&name).map_err(|e| AppError::BadRequest(e.to_string()))?;
+  let stats = state.backend.collection_stats(project_id, &name).await?;
+  if stats.document_count == 0 {
+    return Err(AppError::NotFound(format!(
+      "Collection '{}' not found",
+      name
+    )));
   }
-  Ok(Json(serde_json::json!({ "deleted": deleted })))
+  Ok(Json(stats))
 }
 
-async fn api_insert_doc(
+/// GET /api/collections/{name}/soft-delete - Soft-delete settings of a collection
+async fn api_get_soft_delete(
   State(state): State<AppState>,
+  headers: HeaderMap,
   Path(name): Path<String>,
-  Json(data): Json<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-  let doc = state
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_collection_name(&name).map_err(|e| AppError::BadRequest(e.to_string()))?;
+  let settings = state.backend.get_soft_delete(project_id, &name).await?;
+  Ok(Json(serde_json::json!({
+    "enabled": settings.is_some(),
+    "retention_secs": settings.map(|s| s.retention_secs),
+  })))
+}
+
+/// PUT /api/collections/{name}/soft-delete - Enable soft delete for a collection
+async fn api_set_soft_delete(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+  Json(settings): Json<SoftDeleteSettings>,
+) -> Result<Json<SoftDeleteSettings>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_collection_name(&name).map_err(|e| AppError::BadRequest(e.to_string()))?;
+  state
     .backend
-    .insert(DEFAULT_PROJECT_ID, &name, data)
+    .set_soft_delete(project_id, &name, settings)
     .await?;
   emit_log(
     "info",
     "squirreldb::api",
-    &format!("Document inserted in '{}': {}", name, doc.id),
+    &format!(
+      "Soft delete enabled for '{}' (retention {}s)",
+      name, settings.retention_secs
+    ),
   );
-  Ok(Json(serde_json::to_value(doc)?))
+  Ok(Json(settings))
 }
 
-async fn api_get_doc(
+/// DELETE /api/collections/{name}/soft-delete - Make deletes remove documents again
+async fn api_disable_soft_delete(
   State(state): State<AppState>,
-  Path((name, id)): Path<(String, String)>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-  let id = id
-    .parse()
-    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
-  let doc = state.backend.get(DEFAULT_PROJECT_ID, &name, id).await?;
-  match doc {
-    Some(d) => Ok(Json(serde_json::to_value(d)?)),
-    None => Err(AppError::NotFound("Not found".to_string())),
+  let project_id = resolve_project(&state, &headers).await?;
+  if !state.backend.disable_soft_delete(project_id, &name).await? {
+    return Err(AppError::NotFound(format!(
+      "Soft delete is not enabled for '{}'",
+      name
+    )));
   }
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!("Soft delete disabled for '{}'", name),
+  );
+  Ok(Json(serde_json::json!({ "disabled": true })))
 }
 
-async fn api_update_doc(
+/// GET /api/collections/{name}/defaults - Default values of a collection
+async fn api_get_collection_defaults(
   State(state): State<AppState>,
-  Path((name, id)): Path<(String, String)>,
-  Json(data): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, AppError> {
-  let id = id
-    .parse()
-    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
-  let doc = state
+  headers: HeaderMap,
+  Path(name): Path<String>,
+) -> Result<Json<CollectionDefaults>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_collection_name(&name).map_err(|e| AppError::BadRequest(e.to_string()))?;
+  let defaults = state
     .backend
-    .update(DEFAULT_PROJECT_ID, &name, id, data)
+    .get_collection_defaults(project_id, &name)
     .await?;
-  match doc {
-    Some(d) => Ok(Json(serde_json::to_value(d)?)),
-    None => Err(AppError::NotFound("Not found".to_string())),
-  }
+  Ok(Json(defaults.unwrap_or_default()))
 }
 
-async fn api_delete_doc(
+/// PUT /api/collections/{name}/defaults - Replace the default values of a collection
+async fn api_set_collection_defaults(
   State(state): State<AppState>,
-  Path((name, id)): Path<(String, String)>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+  Json(defaults): Json<CollectionDefaults>,
+) -> Result<Json<CollectionDefaults>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_collection_name(&name).map_err(|e| AppError::BadRequest(e.to_string()))?;
+  defaults
+    .validate()
+    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+  state
+    .backend
+    .set_collection_defaults(project_id, &name, &defaults)
+    .await?;
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!("Defaults set for '{}': {} field(s)", name, defaults.0.len()),
+  );
+  Ok(Json(defaults))
+}
+
+/// DELETE /api/collections/{name}/defaults - Stop filling in default values
+async fn api_delete_collection_defaults(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-  let id = id
-    .parse()
-    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
-  let doc = state.backend.delete(DEFAULT_PROJECT_ID, &name, id).await?;
-  match doc {
-    Some(d) => {
-      emit_log(
-        "info",
-        "squirreldb::api",
-        &format!("Document deleted from '{}': {}", name, id),
-      );
-      Ok(Json(serde_json::to_value(d)?))
+  let project_id = resolve_project(&state, &headers).await?;
+  if !state
+    .backend
+    .delete_collection_defaults(project_id, &name)
+    .await?
+  {
+    return Err(AppError::NotFound(format!(
+      "Collection '{}' has no defaults",
+      name
+    )));
+  }
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!("Defaults removed for '{}'", name),
+  );
+  Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
+/// Longest key-value namespace or key
+const MAX_KV_NAME_LEN: usize = 255;
+
+fn validate_kv_path(namespace: &str, key: &str) -> Result<(), AppError> {
+  for (what, name) in [("Namespace", namespace), ("Key", key)] {
+    if name.is_empty() || name.len() > MAX_KV_NAME_LEN {
+      return Err(AppError::BadRequest(format!(
+        "{} must be 1-{} bytes",
+        what, MAX_KV_NAME_LEN
+      )));
     }
-    None => Err(AppError::NotFound("Not found".to_string())),
   }
+  Ok(())
 }
 
 #[derive(Deserialize)]
-struct QueryRequest {
-  query: String,
+struct KvOptions {
+  /// Seconds until the value expires
+  ttl: Option<u64>,
 }
 
-async fn api_query(
+/// GET /api/kv/{namespace}/{key} - A stored client value
+async fn api_get_kv(
   State(state): State<AppState>,
-  Json(req): Json<QueryRequest>,
+  headers: HeaderMap,
+  Path((namespace, key)): Path<(String, String)>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-  emit_log(
-    "debug",
-    "squirreldb::query",
-    &format!("Executing query: {}", req.query),
-  );
-
-  // Parse query while holding lock, then execute without lock
-  let spec = {
-    let engine = state.engine.lock();
-    engine.parse_query(&req.query)?
-  };
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_kv_path(&namespace, &key)?;
+  state
+    .backend
+    .get_kv(project_id, &namespace, &key)
+    .await?
+    .map(Json)
+    .ok_or_else(|| AppError::NotFound(format!("Key '{}/{}' not found", namespace, key)))
+}
 
-  let sql_filter = spec.filter.as_ref().and_then(|f| f.compiled_sql.as_deref());
-  let project_id = spec.project_id.unwrap_or(DEFAULT_PROJECT_ID);
-  let docs = state
+/// PUT /api/kv/{namespace}/{key} - Store any JSON value, optionally with `?ttl=<secs>`
+async fn api_set_kv(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path((namespace, key)): Path<(String, String)>,
+  Query(options): Query<KvOptions>,
+  Json(value): Json<serde_json::Value>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_kv_path(&namespace, &key)?;
+  if options.ttl == Some(0) {
+    return Err(AppError::BadRequest("ttl must be at least 1 second".into()));
+  }
+  state.rate_limiter.check_document(&value)?;
+  let ttl = options.ttl.map(std::time::Duration::from_secs);
+  state
     .backend
-    .list(
-      project_id,
-      &spec.table,
-      sql_filter,
-      spec.order_by.as_ref(),
-      spec.limit,
-      spec.offset,
-    )
+    .set_kv(project_id, &namespace, &key, &value, ttl)
     .await?;
-
   emit_log(
     "info",
-    "squirreldb::query",
-    &format!("Query on '{}' returned {} results", spec.table, docs.len()),
+    "squirreldb::api",
+    &format!("Key '{}/{}' set", namespace, key),
   );
-  Ok(Json(serde_json::to_value(&docs)?))
+  Ok(Json(value))
 }
 
-// =============================================================================
-// Auth Middleware
-// =============================================================================
+/// DELETE /api/kv/{namespace}/{key} - Remove a stored client value
+async fn api_delete_kv(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path((namespace, key)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_kv_path(&namespace, &key)?;
+  if !state
+    .backend
+    .delete_kv(project_id, &namespace, &key)
+    .await?
+  {
+    return Err(AppError::NotFound(format!(
+      "Key '{}/{}' not found",
+      namespace, key
+    )));
+  }
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!("Key '{}/{}' deleted", namespace, key),
+  );
+  Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
+async fn api_drop_collection(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let deleted = state.backend.drop_collection(project_id, &name).await?;
+  Ok(Json(serde_json::json!({ "deleted": deleted })))
+}
+
+#[derive(Deserialize)]
+struct CollectionTargetRequest {
+  to: String,
+}
+
+/// Check that `from` exists and `to` is a valid, unused collection name
+async fn check_collection_target(
+  state: &AppState,
+  project_id: Uuid,
+  from: &str,
+  to: &str,
+) -> Result<(), AppError> {
+  validate_collection_name(to).map_err(|e| AppError::BadRequest(e.to_string()))?;
+  let collections = state.backend.list_collections(project_id).await?;
+  if !collections.iter().any(|c| c == from) {
+    return Err(AppError::NotFound(format!(
+      "Collection '{}' not found",
+      from
+    )));
+  }
+  if collections.iter().any(|c| c == to) {
+    return Err(AppError::BadRequest(format!(
+      "Collection '{}' already exists",
+      to
+    )));
+  }
+  Ok(())
+}
+
+async fn api_rename_collection(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+  Json(req): Json<CollectionTargetRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  check_collection_target(&state, project_id, &name, &req.to).await?;
+  let renamed = state
+    .backend
+    .rename_collection(project_id, &name, &req.to)
+    .await?;
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!(
+      "Collection '{}' renamed to '{}' ({} documents)",
+      name, req.to, renamed
+    ),
+  );
+  Ok(Json(serde_json::json!({ "renamed": renamed })))
+}
+
+async fn api_copy_collection(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+  Json(req): Json<CollectionTargetRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  check_collection_target(&state, project_id, &name, &req.to).await?;
+  let copied = state
+    .backend
+    .copy_collection(
+      project_id,
+      &name,
+      &req.to,
+      state.rate_limiter.document_quota(project_id),
+    )
+    .await
+    .map_err(quota_error)?;
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!(
+      "Collection '{}' copied to '{}' ({} documents)",
+      name, req.to, copied
+    ),
+  );
+  Ok(Json(serde_json::json!({ "copied": copied })))
+}
+
+/// Parse a JSON document from a request body, applying the
+/// `limits.inexact_numbers` policy before numbers can lose precision
+fn parse_document_body(state: &AppState, body: &str) -> Result<serde_json::Value, AppError> {
+  let body = apply_inexact_numbers(body, state.rate_limiter.inexact_numbers())
+    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+  serde_json::from_str(&body).map_err(|e| AppError::BadRequest(format!("Invalid JSON: {}", e)))
+}
+
+/// Request header making an insert idempotent
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set when an insert returns the result of an earlier request
+const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+async fn api_insert_doc(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+  Query(mut options): Query<WriteOptions>,
+  body: String,
+) -> Result<Response, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  options.author = request_author(&state, &headers).await?;
+  let data = parse_document_body(&state, &body)?;
+  state.rate_limiter.check_document(&data)?;
+  options.max_documents = state.rate_limiter.document_quota(project_id);
+  let Some(key) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+    let doc = state
+      .backend
+      .insert_with_options(project_id, &name, data, options)
+      .await
+      .map_err(quota_error)?;
+    log_insert(&name, &doc);
+    return Ok(Json(serde_json::to_value(doc)?).into_response());
+  };
+
+  let key = key
+    .to_str()
+    .ok()
+    .filter(|k| validate_idempotency_key(k).is_ok())
+    .ok_or_else(|| {
+      AppError::BadRequest(format!(
+        "Idempotency-Key must be 1 to {} visible ASCII characters",
+        MAX_IDEMPOTENCY_KEY_LEN
+      ))
+    })?;
+  let ttl = state.rate_limiter.idempotency_key_ttl();
+  match insert_idempotent(
+    state.backend.as_ref(),
+    project_id,
+    &name,
+    data,
+    options,
+    key,
+    ttl,
+  )
+  .await
+  .map_err(quota_error)?
+  {
+    IdempotentInsert::Inserted(doc, _) => {
+      log_insert(&name, &doc);
+      Ok(Json(serde_json::to_value(doc)?).into_response())
+    }
+    IdempotentInsert::Replayed(response) => {
+      Ok(([(IDEMPOTENT_REPLAYED_HEADER, "true")], Json(response)).into_response())
+    }
+    IdempotentInsert::InProgress => Err(AppError::Conflict(
+      "An insert with this Idempotency-Key is still in progress".into(),
+    )),
+  }
+}
+
+fn log_insert(collection: &str, doc: &Document) {
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!("Document inserted in '{}': {}", collection, doc.id),
+  );
+}
+
+/// Strong ETag for a document revision, from its ID, update time and contents
+fn document_etag(doc: &Document) -> String {
+  let mut hasher = Sha256::new();
+  hasher.update(doc.id.as_bytes());
+  hasher.update(doc.updated_at.to_rfc3339().as_bytes());
+  hasher.update(serde_json::to_vec(&doc.data).unwrap_or_default());
+  let digest = hasher.finalize();
+  format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// Whether an If-None-Match header value matches `etag` (weak comparison)
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+  let etag = etag.trim_start_matches("W/");
+  if_none_match
+    .split(',')
+    .map(str::trim)
+    .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+async fn api_get_doc(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path((name, id)): Path<(String, String)>,
+  Query(options): Query<ReadOptions>,
+) -> Result<Response, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let id = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
+  let doc = state
+    .backend
+    .get_with_options(project_id, &name, id, options)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Not found".to_string()))?;
+
+  let etag = document_etag(&doc);
+  let not_modified = headers
+    .get(header::IF_NONE_MATCH)
+    .and_then(|v| v.to_str().ok())
+    .is_some_and(|v| etag_matches(v, &etag));
+  if not_modified {
+    return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+  }
+  Ok(([(header::ETAG, etag)], Json(serde_json::to_value(doc)?)).into_response())
+}
+
+#[derive(Deserialize)]
+struct FindOneQuery {
+  /// Structured filter as JSON, e.g. `{"email": "a@example.com"}`
+  filter: Option<String>,
+}
+
+/// GET /api/collections/{name}/findOne - First document matching a structured filter
+async fn api_find_one(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+  Query(q): Query<FindOneQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let filter = q
+    .filter
+    .as_deref()
+    .map(serde_json::from_str::<StructuredFilter>)
+    .transpose()
+    .map_err(|e| AppError::BadRequest(format!("Invalid filter: {}", e)))?;
+
+  let query = StructuredQuery {
+    table: name,
+    filter,
+    sort: None,
+    limit: Some(1),
+    skip: None,
+    changes: None,
+    select: None,
+    with_deleted: false,
+  };
+  let spec = StructuredCompiler::new(state.dialect)
+    .with_max_depth(state.config.limits.max_filter_depth)
+    .compile(&query)
+    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+  state
+    .backend
+    .field_encryption()
+    .check_query(state.dialect, &spec)
+    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+  let sql_filter = spec.filter.as_ref().and_then(|f| f.compiled_sql.as_deref());
+  let doc = state
+    .backend
+    .list(
+      project_id,
+      &spec.table,
+      None,
+      sql_filter,
+      None,
+      Some(1),
+      None,
+    )
+    .await?
+    .into_iter()
+    .next();
+  match doc {
+    Some(d) => Ok(Json(serde_json::to_value(d)?)),
+    None => Err(AppError::NotFound("Not found".to_string())),
+  }
+}
+
+/// Most document IDs accepted by one getMany request
+const MAX_GET_MANY_IDS: usize = 1000;
+
+#[derive(Deserialize)]
+struct GetManyRequest {
+  ids: Vec<String>,
+}
+
+/// POST /api/collections/{name}/getMany - Documents by ID in one query.
+/// `documents` follows the order of `ids`, with null for missing documents.
+async fn api_get_many(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+  Query(options): Query<ReadOptions>,
+  Json(req): Json<GetManyRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  if req.ids.len() > MAX_GET_MANY_IDS {
+    return Err(AppError::BadRequest(format!(
+      "At most {} ids can be fetched at once",
+      MAX_GET_MANY_IDS
+    )));
+  }
+  let ids = req
+    .ids
+    .iter()
+    .map(|id| {
+      id.parse()
+        .map_err(|_| AppError::BadRequest(format!("Invalid UUID: {}", id)))
+    })
+    .collect::<Result<Vec<Uuid>, _>>()?;
+  let unique: Vec<Uuid> = {
+    let mut seen = HashSet::new();
+    ids.iter().copied().filter(|id| seen.insert(*id)).collect()
+  };
+  let found: HashMap<Uuid, Document> = state
+    .backend
+    .get_many(project_id, &name, &unique, options)
+    .await?
+    .into_iter()
+    .map(|doc| (doc.id, doc))
+    .collect();
+  let missing: Vec<String> = unique
+    .iter()
+    .filter(|id| !found.contains_key(id))
+    .map(|id| id.to_string())
+    .collect();
+  let documents: Vec<Option<&Document>> = ids.iter().map(|id| found.get(id)).collect();
+  Ok(Json(serde_json::json!({
+    "documents": documents,
+    "missing": missing,
+  })))
+}
+
+#[derive(Deserialize)]
+struct UpdateOptions {
+  /// Keep the document's current `updated_at`
+  #[serde(default)]
+  no_touch: bool,
+  /// Fill in the collection's default values for missing fields
+  #[serde(default)]
+  apply_defaults: bool,
+}
+
+async fn api_update_doc(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path((name, id)): Path<(String, String)>,
+  Query(options): Query<UpdateOptions>,
+  body: String,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let id = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
+  let data = parse_document_body(&state, &body)?;
+  state.rate_limiter.check_document(&data)?;
+  let options = WriteOptions {
+    no_touch: options.no_touch,
+    apply_defaults: options.apply_defaults,
+    author: if options.apply_defaults {
+      request_author(&state, &headers).await?
+    } else {
+      None
+    },
+    ..Default::default()
+  };
+  let doc = state
+    .backend
+    .update_with_options(project_id, &name, id, data, options)
+    .await?;
+  match doc {
+    Some(d) => Ok(Json(serde_json::to_value(d)?)),
+    None => Err(AppError::NotFound("Not found".to_string())),
+  }
+}
+
+/// Content type of an RFC 6902 JSON Patch body
+const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+
+/// Content type of an RFC 7396 JSON Merge Patch body
+const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+#[derive(Deserialize)]
+struct PatchOptions {
+  /// Keep the document's current `updated_at`
+  #[serde(default)]
+  no_touch: bool,
+}
+
+/// PATCH /api/collections/{name}/documents/{id} - Apply a JSON Patch or a
+/// JSON Merge Patch to a document, chosen by `Content-Type`. All changes
+/// apply or none do.
+async fn api_patch_doc(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path((name, id)): Path<(String, String)>,
+  Query(options): Query<PatchOptions>,
+  body: String,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let id = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
+  let content_type = headers
+    .get(header::CONTENT_TYPE)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.split(';').next())
+    .unwrap_or_default()
+    .trim();
+  let limiter = state.rate_limiter.clone();
+  let edit: DocumentEdit = if content_type.eq_ignore_ascii_case(JSON_PATCH_CONTENT_TYPE) {
+    let patch: json_patch::Patch = serde_json::from_value(parse_document_body(&state, &body)?)
+      .map_err(|e| AppError::BadRequest(format!("Invalid JSON Patch: {}", e)))?;
+    Box::new(move |mut data| {
+      json_patch::patch(&mut data, &patch)?;
+      limiter.check_document(&data)?;
+      Ok(data)
+    })
+  } else if content_type.eq_ignore_ascii_case(MERGE_PATCH_CONTENT_TYPE) {
+    // Objects merge recursively and null removes a field
+    let patch = parse_document_body(&state, &body)?;
+    Box::new(move |mut data| {
+      json_patch::merge(&mut data, &patch);
+      limiter.check_document(&data)?;
+      Ok(data)
+    })
+  } else {
+    return Err(AppError::BadRequest(format!(
+      "Unsupported patch format '{}': use {} or {}",
+      content_type, JSON_PATCH_CONTENT_TYPE, MERGE_PATCH_CONTENT_TYPE
+    )));
+  };
+  let options = WriteOptions {
+    no_touch: options.no_touch,
+    ..Default::default()
+  };
+  let doc = state
+    .backend
+    .edit_document(project_id, &name, id, edit, options)
+    .await
+    .map_err(patch_error)?;
+  match doc {
+    Some(d) => Ok(Json(serde_json::to_value(d)?)),
+    None => Err(AppError::NotFound("Not found".to_string())),
+  }
+}
+
+/// Response for a patch that couldn't be applied: a failed `test` or a path
+/// that doesn't exist is a conflict with the document's current state
+fn patch_error(e: anyhow::Error) -> AppError {
+  if let Some(e) = e.downcast_ref::<json_patch::PatchError>() {
+    return AppError::Conflict(format!("Patch not applied: {}", e));
+  }
+  quota_error(e)
+}
+
+/// Surface a document quota refusal from the backend as 429
+fn quota_error(e: anyhow::Error) -> AppError {
+  match e.downcast::<RateLimitError>() {
+    Ok(e) => e.into(),
+    Err(e) => AppError::Internal(e),
+  }
+}
+
+async fn api_delete_doc(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path((name, id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let id = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
+  let doc = state.backend.delete(project_id, &name, id).await?;
+  match doc {
+    Some(d) => {
+      emit_log(
+        "info",
+        "squirreldb::api",
+        &format!("Document deleted from '{}': {}", name, id),
+      );
+      Ok(Json(serde_json::to_value(d)?))
+    }
+    None => Err(AppError::NotFound("Not found".to_string())),
+  }
+}
+
+/// POST /api/collections/{name}/documents/{id}/restore - Bring back a soft-deleted document
+async fn api_restore_doc(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path((name, id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let id = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
+  let doc = state
+    .backend
+    .restore_deleted(project_id, &name, id)
+    .await?
+    .ok_or_else(|| AppError::NotFound("No deleted document with this ID".to_string()))?;
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!("Document restored in '{}': {}", name, id),
+  );
+  Ok(Json(serde_json::to_value(doc)?))
+}
+
+/// Changes returned by one history request when no limit is given
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+/// Most changes returned by one history request
+const MAX_HISTORY_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+  /// Only changes with a higher ID, to page through long histories
+  #[serde(default)]
+  after: i64,
+  limit: Option<usize>,
+}
+
+/// GET /api/collections/{name}/documents/{id}/history - Changes to a document
+/// still in the change queue, oldest first. Admin only, since it exposes old
+/// versions of the data.
+async fn api_document_history(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path((name, id)): Path<(String, String)>,
+  Query(q): Query<HistoryQuery>,
+) -> Result<Json<Vec<Change>>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  let id = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid UUID".into()))?;
+  let limit = q
+    .limit
+    .unwrap_or(DEFAULT_HISTORY_LIMIT)
+    .clamp(1, MAX_HISTORY_LIMIT);
+  let changes = state
+    .backend
+    .document_history(project_id, &name, id, q.after, limit)
+    .await?;
+  Ok(Json(changes))
+}
+
+/// Most document IDs accepted by one deleteMany request
+const MAX_DELETE_MANY_IDS: usize = 10_000;
+
+#[derive(Deserialize)]
+struct DeleteManyRequest {
+  ids: Vec<String>,
+}
+
+async fn api_delete_many(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+  Json(req): Json<DeleteManyRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  if req.ids.len() > MAX_DELETE_MANY_IDS {
+    return Err(AppError::BadRequest(format!(
+      "At most {} ids can be deleted at once",
+      MAX_DELETE_MANY_IDS
+    )));
+  }
+  let mut seen = HashSet::new();
+  let mut ids: Vec<Uuid> = Vec::with_capacity(req.ids.len());
+  for id in &req.ids {
+    let id = id
+      .parse()
+      .map_err(|_| AppError::BadRequest(format!("Invalid UUID: {}", id)))?;
+    if seen.insert(id) {
+      ids.push(id);
+    }
+  }
+  let deleted: HashSet<Uuid> = state
+    .backend
+    .delete_many(project_id, &name, &ids)
+    .await?
+    .into_iter()
+    .collect();
+  let not_found: Vec<String> = ids
+    .iter()
+    .filter(|id| !deleted.contains(id))
+    .map(|id| id.to_string())
+    .collect();
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!("{} documents deleted from '{}'", deleted.len(), name),
+  );
+  Ok(Json(serde_json::json!({
+    "deleted": deleted.len(),
+    "not_found": not_found,
+  })))
+}
+
+#[derive(Deserialize)]
+struct QueryRequest {
+  query: String,
+  /// Values for `$1`, `$2`, ... in the query's filter
+  #[serde(default)]
+  params: Vec<serde_json::Value>,
+  /// Wrap the result as `{data, schema}` with inferred field metadata
+  #[serde(default)]
+  schema: bool,
+}
+
+async fn api_query(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Json(req): Json<QueryRequest>,
+) -> Result<Response, AppError> {
+  let project_id = resolve_project(&state, &headers).await?;
+  emit_log(
+    "debug",
+    "squirreldb::query",
+    &format!("Executing query: {}", req.query),
+  );
+
+  // Parse query while holding lock, then execute without lock
+  let spec = {
+    let engine = state.engine.lock();
+    engine
+      .parse_query(&req.query)
+      .map_err(|e| AppError::InvalidQuery(e.to_string()))?
+  };
+  state
+    .backend
+    .field_encryption()
+    .check_query(state.dialect, &spec)
+    .map_err(|e| AppError::InvalidQuery(e.to_string()))?;
+
+  let sql_filter = spec.filter.as_ref().and_then(|f| f.compiled_sql.as_deref());
+  let params = match spec.filter.as_ref().filter(|f| f.compiled_sql.is_some()) {
+    Some(filter) => {
+      bind_params(filter, &req.params).map_err(|e| AppError::InvalidQuery(e.to_string()))?
+    }
+    None => Vec::new(),
+  };
+  let limit = result_fetch_limit(&state.config.limits, spec.limit);
+  state
+    .engine_pool
+    .check_cost(&spec, &params, limit, project_id, state.backend.as_ref())
+    .await
+    .map_err(|e| AppError::InvalidQuery(e.to_string()))?;
+  let docs = state
+    .backend
+    .list_with_options(
+      project_id,
+      &spec.table,
+      spec.projection.as_deref(),
+      sql_filter,
+      &params,
+      &spec.order_by,
+      limit,
+      spec.offset,
+      ReadOptions {
+        with_deleted: spec.with_deleted,
+      },
+    )
+    .await?;
+
+  emit_log(
+    "info",
+    "squirreldb::query",
+    &format!("Query on '{}' returned {} results", spec.table, docs.len()),
+  );
+  result_rows_response(&state.config.limits, spec.limit, docs, req.schema, None)
+}
+
+// =============================================================================
+// Auth Middleware
+// =============================================================================
 
 /// Hash a token using SHA-256
 fn hash_token(token: &str) -> String {
@@ -936,8 +2102,15 @@ async fn admin_auth_middleware(
       // Check if it's a session token (starts with "session_")
       if let Some(session_token) = t.strip_prefix("session_") {
         let session_hash = auth::hash_session_token(session_token);
-        if let Ok(Some(_)) = state.backend.validate_admin_session(&session_hash).await {
-          return next.run(req).await;
+        if let Ok(Some((_, user))) = state.backend.validate_admin_session(&session_hash).await {
+          return run_for_user(&user, req, next).await;
+        }
+      }
+
+      // Check if it's a JWT from the OIDC provider (if configured)
+      if state.oidc.is_some() && oidc::is_jwt(&t) {
+        if let Ok(user) = oidc_user(&state, &t).await {
+          return run_for_user(&user, req, next).await;
         }
       }
 
@@ -968,6 +2141,69 @@ async fn admin_auth_middleware(
   }
 }
 
+/// Pass an admin route on for a signed-in user, refusing `member` users
+/// everything but their projects
+async fn run_for_user(user: &AdminUser, req: Request, next: Next) -> Response {
+  if user.role == AdminRole::Member && !member_may_access(req.method(), req.uri().path()) {
+    return AppError::Forbidden("Admin access required".to_string()).into_response();
+  }
+  next.run(req).await
+}
+
+/// Admin routes open to `member` users: reading and selecting projects, and
+/// document history. The handlers check project membership themselves.
+fn member_may_access(method: &Method, path: &str) -> bool {
+  if let Some(rest) = path.strip_prefix("/api/projects") {
+    return match *method {
+      Method::GET => !rest.ends_with("/tokens"),
+      Method::POST => rest.ends_with("/select"),
+      _ => false,
+    };
+  }
+  *method == Method::GET && path.starts_with("/api/collections/") && path.ends_with("/history")
+}
+
+/// Request body limit of `max` bytes, with 0 lifting the limit
+fn body_limit(max: usize) -> DefaultBodyLimit {
+  match max {
+    0 => DefaultBodyLimit::disable(),
+    max => DefaultBodyLimit::max(max),
+  }
+}
+
+/// Refuse requests whose headers add up to more than `max_header_bytes`, and
+/// give the plain-text 413 that body extractors answer with over the limit
+/// the same JSON error body as every other error
+async fn request_size_middleware(
+  State(max_header_bytes): State<usize>,
+  req: Request,
+  next: Next,
+) -> Response {
+  let header_bytes: usize = req
+    .headers()
+    .iter()
+    .map(|(name, value)| name.as_str().len() + value.len())
+    .sum();
+  if max_header_bytes > 0 && header_bytes > max_header_bytes {
+    return AppError::PayloadTooLarge(format!(
+      "Request headers are {} bytes, over the limit of {}",
+      header_bytes, max_header_bytes
+    ))
+    .into_response();
+  }
+
+  let response = next.run(req).await;
+  let is_json = response
+    .headers()
+    .get(header::CONTENT_TYPE)
+    .and_then(|v| v.to_str().ok())
+    .is_some_and(|v| v.starts_with("application/json"));
+  if response.status() == StatusCode::PAYLOAD_TOO_LARGE && !is_json {
+    return AppError::PayloadTooLarge("Request body is too large".into()).into_response();
+  }
+  response
+}
+
 /// Rate limiting middleware for admin API routes
 /// Extracts client IP and checks against the rate limiter
 async fn rate_limit_middleware(
@@ -975,51 +2211,184 @@ async fn rate_limit_middleware(
   req: Request,
   next: Next,
 ) -> Response {
-  // Extract client IP from headers (X-Forwarded-For, X-Real-IP) or socket
-  let ip = extract_client_ip(&req);
+  let ip = extract_client_ip(&state, &req);
 
   // Check rate limit
-  if let Err(e) = state.rate_limiter.check_request(ip) {
-    return (
+  let checked = state.rate_limiter.check_request(ip);
+  let status = state.rate_limiter.request_status(ip);
+  let mut response = match checked {
+    Ok(()) => next.run(req).await,
+    Err(e) => (
       StatusCode::TOO_MANY_REQUESTS,
       [(header::RETRY_AFTER, "1")],
       Json(serde_json::json!({
         "error": "Rate limit exceeded",
-        "message": e.to_string()
+        "message": e.to_string(),
+        "trace_id": current_trace_id(),
       })),
     )
-      .into_response();
+      .into_response(),
+  };
+
+  // Let clients see their budget so they can slow down before a 429
+  if let Some(status) = status {
+    let headers = response.headers_mut();
+    headers.insert("x-ratelimit-limit", HeaderValue::from(status.limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(status.remaining));
+    headers.insert(
+      "x-ratelimit-reset",
+      HeaderValue::from(status.reset.as_secs_f64().ceil() as u64),
+    );
   }
+  response
+}
 
-  next.run(req).await
+/// Log REST requests slower than `limits.slow_query_ms`, like WebSocket and
+/// TCP messages
+async fn slow_request_middleware(
+  State(state): State<AppState>,
+  req: Request,
+  next: Next,
+) -> Response {
+  let started = std::time::Instant::now();
+  let method = req.method().clone();
+  let path = req.uri().path().to_string();
+  let response = next.run(req).await;
+  let elapsed = started.elapsed();
+  if state.rate_limiter.is_slow(elapsed) {
+    tracing::warn!(
+      "Slow request {} {} took {}ms",
+      method,
+      path,
+      elapsed.as_millis()
+    );
+  }
+  response
+}
+
+/// Refuse data-plane requests while the server is in maintenance mode.
+/// `GET`/`HEAD` requests and queries count as reads; `/api/status` is always
+/// answered so clients can watch for the end of maintenance.
+async fn maintenance_middleware(
+  State(state): State<AppState>,
+  req: Request,
+  next: Next,
+) -> Response {
+  let path = req.uri().path();
+  if path == "/api/status" {
+    return next.run(req).await;
+  }
+  let read = matches!(*req.method(), Method::GET | Method::HEAD)
+    || path == "/api/query"
+    || path.ends_with("/getMany");
+  match state.maintenance.check(!read) {
+    Some(refusal) => {
+      let mut response = AppError::ServiceUnavailable(refusal.message).into_response();
+      if let Ok(value) = HeaderValue::from_str(&refusal.retry_after.as_secs().to_string()) {
+        response.headers_mut().insert(header::RETRY_AFTER, value);
+      }
+      response
+    }
+    None => next.run(req).await,
+  }
+}
+
+/// Whether the query string asks for indented JSON (`pretty`, `pretty=true`
+/// or `pretty=1`)
+fn wants_pretty(query: Option<&str>) -> bool {
+  query
+    .unwrap_or_default()
+    .split('&')
+    .any(|pair| matches!(pair, "pretty" | "pretty=true" | "pretty=1"))
+}
+
+/// Re-indent JSON responses for requests with `?pretty=true`. Everything
+/// else, including every response without the flag, passes through as is.
+async fn pretty_json_middleware(req: Request, next: Next) -> Response {
+  if !wants_pretty(req.uri().query()) {
+    return next.run(req).await;
+  }
+  let response = next.run(req).await;
+  let is_json = response
+    .headers()
+    .get(header::CONTENT_TYPE)
+    .and_then(|v| v.to_str().ok())
+    .is_some_and(|v| v.starts_with("application/json"));
+  if !is_json {
+    return response;
+  }
+
+  let (mut parts, body) = response.into_parts();
+  let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+    return AppError::Internal(anyhow::anyhow!("Failed to read response body")).into_response();
+  };
+  let body = serde_json::from_slice::<serde_json::Value>(&bytes)
+    .ok()
+    .and_then(|value| serde_json::to_vec_pretty(&value).ok())
+    .unwrap_or_else(|| bytes.to_vec());
+  parts.headers.remove(header::CONTENT_LENGTH);
+  Response::from_parts(parts, Body::from(body))
+}
+
+/// Run each request under a trace ID, echoed in the `X-Trace-Id` header.
+/// A well-formed ID supplied by the client or a proxy is kept.
+async fn trace_middleware(req: Request, next: Next) -> Response {
+  let trace_id = req
+    .headers()
+    .get(TRACE_ID_HEADER)
+    .and_then(|v| v.to_str().ok())
+    .and_then(accept_trace_id)
+    .unwrap_or_else(new_trace_id);
+  let mut response = with_trace_id(trace_id.clone(), next.run(req)).await;
+  if let Ok(value) = header::HeaderValue::from_str(&trace_id) {
+    response.headers_mut().insert(TRACE_ID_HEADER, value);
+  }
+  response
 }
 
 /// Extract client IP from request headers or connection info
-fn extract_client_ip(req: &Request) -> std::net::IpAddr {
+fn extract_client_ip(state: &AppState, req: &Request) -> std::net::IpAddr {
+  let peer = req
+    .extensions()
+    .get::<ConnectInfo<std::net::SocketAddr>>()
+    .map(|ConnectInfo(addr)| addr.ip())
+    .unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
+  client_ip(state, peer, req.headers())
+}
+
+/// Address of the client: the socket peer, or the address a trusted proxy
+/// (`server.trusted_proxies`) forwarded. Forwarding headers from any other
+/// peer are ignored, since clients can set them freely.
+fn client_ip(state: &AppState, peer: std::net::IpAddr, headers: &HeaderMap) -> std::net::IpAddr {
+  if state.config.server.trusted_proxies.contains(&peer) {
+    client_ip_from_headers(headers).unwrap_or(peer)
+  } else {
+    peer
+  }
+}
+
+fn client_ip_from_headers(headers: &HeaderMap) -> Option<std::net::IpAddr> {
   // Try X-Forwarded-For first (common for proxies/load balancers)
-  if let Some(forwarded) = req.headers().get("X-Forwarded-For") {
+  if let Some(forwarded) = headers.get("X-Forwarded-For") {
     if let Ok(s) = forwarded.to_str() {
       // Take the first IP in the chain (original client)
       if let Some(ip_str) = s.split(',').next() {
         if let Ok(ip) = ip_str.trim().parse() {
-          return ip;
+          return Some(ip);
         }
       }
     }
   }
 
   // Try X-Real-IP (nginx)
-  if let Some(real_ip) = req.headers().get("X-Real-IP") {
+  if let Some(real_ip) = headers.get("X-Real-IP") {
     if let Ok(s) = real_ip.to_str() {
       if let Ok(ip) = s.parse() {
-        return ip;
+        return Some(ip);
       }
     }
   }
-
-  // Fallback to localhost if no IP can be extracted
-  // In production, you should ensure connection info is available
-  std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST)
+  None
 }
 
 // =============================================================================
@@ -1039,6 +2408,7 @@ struct AdminUserResponse {
   id: String,
   username: String,
   email: Option<String>,
+  email_verified: bool,
   role: String,
   created_at: String,
 }
@@ -1049,6 +2419,7 @@ impl From<AdminUser> for AdminUserResponse {
       id: u.id.to_string(),
       username: u.username,
       email: u.email,
+      email_verified: u.email_verified,
       role: u.role.to_string(),
       created_at: u.created_at.to_rfc3339(),
     }
@@ -1092,11 +2463,116 @@ async fn api_auth_status(
   }))
 }
 
+/// Who the current credentials belong to
+#[derive(Serialize)]
+struct AuthMeResponse {
+  /// "session", "admin_token", "api_token" or "none" (auth disabled)
+  auth_type: &'static str,
+  user: Option<AdminUserResponse>,
+  projects: Vec<ProjectMembershipResponse>,
+  token: Option<TokenScopeResponse>,
+  session_expires_at: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ProjectMembershipResponse {
+  id: String,
+  name: String,
+  role: String,
+}
+
+#[derive(Serialize)]
+struct TokenScopeResponse {
+  project_id: String,
+  project_name: Option<String>,
+}
+
+impl AuthMeResponse {
+  fn new(auth_type: &'static str) -> Self {
+    Self {
+      auth_type,
+      user: None,
+      projects: Vec::new(),
+      token: None,
+      session_expires_at: None,
+    }
+  }
+}
+
+/// GET /api/auth/me - Describe the current session or token
+async fn api_auth_me(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+) -> Result<Json<AuthMeResponse>, AppError> {
+  let Some(token) = extract_token_from_headers(&headers) else {
+    if !state.config.auth.enabled {
+      return Ok(Json(AuthMeResponse::new("none")));
+    }
+    return Err(AppError::Unauthorized(
+      "Authentication required".to_string(),
+    ));
+  };
+
+  if let Some(session_token) = token.strip_prefix("session_") {
+    let session_hash = auth::hash_session_token(session_token);
+    let (session, user) = state
+      .backend
+      .validate_admin_session(&session_hash)
+      .await?
+      .ok_or_else(|| AppError::Unauthorized("Invalid session".to_string()))?;
+
+    let mut projects = Vec::new();
+    for project in state.backend.list_user_projects(user.id).await? {
+      let role = state
+        .backend
+        .get_user_project_role(project.id, user.id)
+        .await?
+        .unwrap_or_default();
+      projects.push(ProjectMembershipResponse {
+        id: project.id.to_string(),
+        name: project.name,
+        role: role.to_string(),
+      });
+    }
+
+    return Ok(Json(AuthMeResponse {
+      user: Some(user.into()),
+      projects,
+      session_expires_at: Some(session.expires_at.to_rfc3339()),
+      ..AuthMeResponse::new("session")
+    }));
+  }
+
+  if let Some(ref admin_token) = state.config.auth.admin_token {
+    if !admin_token.is_empty() && crate::security::constant_time_compare(&token, admin_token) {
+      return Ok(Json(AuthMeResponse::new("admin_token")));
+    }
+  }
+
+  let project_id = state
+    .backend
+    .validate_token(&hash_token(&token))
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid token".to_string()))?;
+  let project_name = state.backend.get_project(project_id).await?.map(|p| p.name);
+
+  Ok(Json(AuthMeResponse {
+    token: Some(TokenScopeResponse {
+      project_id: project_id.to_string(),
+      project_name,
+    }),
+    ..AuthMeResponse::new("api_token")
+  }))
+}
+
 #[derive(Deserialize)]
 struct SetupRequest {
   username: String,
   email: Option<String>,
   password: String,
+  /// Project to create for the new owner (default: `auth.default_project`)
+  #[serde(default)]
+  project: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -1128,7 +2604,7 @@ async fn api_auth_setup(
   }
 
   // Hash password
-  let password_hash = auth::hash_password(&req.password)
+  let password_hash = auth::hash_password(&req.password, &state.config.auth.password_hashing)
     .map_err(|e| AppError::Internal(anyhow::anyhow!("Password hash error: {}", e)))?;
 
   // Create owner user
@@ -1141,6 +2617,17 @@ async fn api_auth_setup(
       AdminRole::Owner,
     )
     .await?;
+  // The owner exists either way; a missing project can be created later.
+  // With an owner given, only the database can fail here.
+  if let Err(AppError::Internal(e)) =
+    token_project(&state, req.project.as_deref(), Some(user.id)).await
+  {
+    emit_log(
+      "warn",
+      "squirreldb::admin",
+      &format!("Default project was not created: {}", e),
+    );
+  }
 
   // Create session
   let session_token = auth::generate_session_token();
@@ -1180,6 +2667,27 @@ async fn api_auth_login(
     return Err(AppError::Unauthorized("Invalid credentials".to_string()));
   }
 
+  // Upgrade a hash made with weaker settings than the current config
+  let hashing = &state.config.auth.password_hashing;
+  if auth::needs_rehash(&password_hash, hashing) {
+    let rehashed = match auth::hash_password(&req.password, hashing) {
+      Ok(hash) => {
+        state
+          .backend
+          .update_admin_user_password(&user.id, &hash)
+          .await
+      }
+      Err(e) => Err(e),
+    };
+    if let Err(e) = rehashed {
+      emit_log(
+        "warn",
+        "squirreldb::auth",
+        &format!("Failed to rehash password of '{}': {}", user.username, e),
+      );
+    }
+  }
+
   // Create session
   let session_token = auth::generate_session_token();
   let session_hash = auth::hash_session_token(&session_token);
@@ -1189,41 +2697,316 @@ async fn api_auth_login(
     .create_admin_session(user.id, &session_hash, expires_at)
     .await?;
 
-  Ok(Json(LoginResponse {
-    token: format!("session_{}", session_token),
-    user: user.into(),
-  }))
+  Ok(Json(LoginResponse {
+    token: format!("session_{}", session_token),
+    user: user.into(),
+  }))
+}
+
+/// Admin user for an OIDC provider's JWT, found by issuer and subject and
+/// created on first sign-in when auto-provisioning is on. A username already
+/// taken by another user is refused rather than linked.
+async fn oidc_user(state: &AppState, token: &str) -> Result<AdminUser, AppError> {
+  let verifier = state
+    .oidc
+    .as_ref()
+    .ok_or_else(|| AppError::BadRequest("OIDC sign-in is not configured".to_string()))?;
+  let identity = verifier
+    .verify(token)
+    .await
+    .map_err(|e| AppError::Unauthorized(format!("Invalid OIDC token: {}", e)))?;
+
+  if let Some(user) = state
+    .backend
+    .get_admin_user_by_oidc(&identity.issuer, &identity.subject)
+    .await?
+  {
+    return Ok(user);
+  }
+  if !verifier.auto_provision() {
+    return Err(AppError::Unauthorized(
+      "No admin user for this identity".to_string(),
+    ));
+  }
+  let created = state
+    .backend
+    .create_oidc_admin_user(
+      &identity.username,
+      identity.email.as_deref(),
+      auth::EXTERNAL_PASSWORD_HASH,
+      verifier.default_role(),
+      &identity.issuer,
+      &identity.subject,
+    )
+    .await;
+  match created {
+    Ok(user) => {
+      emit_log(
+        "info",
+        "squirreldb::auth",
+        &format!("Provisioned admin user '{}' from OIDC", user.username),
+      );
+      Ok(user)
+    }
+    // A concurrent first sign-in may have created the user already
+    Err(e) => match state
+      .backend
+      .get_admin_user_by_oidc(&identity.issuer, &identity.subject)
+      .await?
+    {
+      Some(user) => Ok(user),
+      None
+        if state
+          .backend
+          .get_admin_user_by_username(&identity.username)
+          .await?
+          .is_some() =>
+      {
+        Err(AppError::Conflict(format!(
+          "Username '{}' belongs to another admin user",
+          identity.username
+        )))
+      }
+      None => Err(e.into()),
+    },
+  }
+}
+
+#[derive(Deserialize)]
+struct OidcLoginRequest {
+  id_token: String,
+}
+
+/// POST /api/auth/oidc - Exchange an OIDC provider's JWT for a session
+async fn api_auth_oidc(
+  State(state): State<AppState>,
+  Json(req): Json<OidcLoginRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+  let user = oidc_user(&state, &req.id_token).await?;
+
+  let session_token = auth::generate_session_token();
+  let session_hash = auth::hash_session_token(&session_token);
+  let expires_at = chrono::Utc::now() + chrono::Duration::days(30);
+  state
+    .backend
+    .create_admin_session(user.id, &session_hash, expires_at)
+    .await?;
+
+  Ok(Json(LoginResponse {
+    token: format!("session_{}", session_token),
+    user: user.into(),
+  }))
+}
+
+/// POST /api/auth/logout - Logout (invalidate session)
+async fn api_auth_logout(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, AppError> {
+  if let Some(token) = extract_token_from_headers(&headers) {
+    if let Some(session_token) = token.strip_prefix("session_") {
+      let session_hash = auth::hash_session_token(session_token);
+      if let Ok(Some((session, _))) = state.backend.validate_admin_session(&session_hash).await {
+        state.backend.delete_admin_session(session.id).await?;
+      }
+    }
+  }
+  Ok(Json(serde_json::json!({"message": "Logged out"})))
+}
+
+#[derive(Deserialize)]
+struct ChangePasswordRequest {
+  current_password: String,
+  new_password: String,
+}
+
+/// POST /api/auth/change-password - Change current user's password
+async fn api_auth_change_password(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Json(req): Json<ChangePasswordRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  // Get current user from session
+  let token = extract_token_from_headers(&headers)
+    .ok_or_else(|| AppError::Unauthorized("Not logged in".to_string()))?;
+  let session_token = token
+    .strip_prefix("session_")
+    .ok_or_else(|| AppError::Unauthorized("Invalid session".to_string()))?;
+  let session_hash = auth::hash_session_token(session_token);
+  let (_, user) = state
+    .backend
+    .validate_admin_session(&session_hash)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid session".to_string()))?;
+
+  // Get user with password hash for verification
+  let (_, password_hash) = state
+    .backend
+    .get_admin_user_by_username(&user.username)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+  // Verify current password
+  if !auth::verify_password(&req.current_password, &password_hash) {
+    return Err(AppError::Unauthorized(
+      "Current password is incorrect".to_string(),
+    ));
+  }
+
+  // Validate new password
+  if req.new_password.len() < 8 {
+    return Err(AppError::BadRequest(
+      "New password must be at least 8 characters".to_string(),
+    ));
+  }
+
+  // Hash and update password
+  let new_hash = auth::hash_password(&req.new_password, &state.config.auth.password_hashing)
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("Password hash error: {}", e)))?;
+  state
+    .backend
+    .update_admin_user_password(&user.id, &new_hash)
+    .await?;
+
+  Ok(Json(
+    serde_json::json!({"message": "Password changed successfully"}),
+  ))
+}
+
+/// Issue a single-use token for `user` and hand it to the notification hooks
+async fn send_account_token(
+  state: &AppState,
+  user: &AdminUser,
+  purpose: AdminTokenPurpose,
+  ttl_secs: u64,
+) -> Result<(), anyhow::Error> {
+  let token = auth::generate_session_token();
+  let expires_at = chrono::Utc::now() + chrono::Duration::seconds(ttl_secs as i64);
+  state
+    .backend
+    .create_admin_auth_token(
+      user.id,
+      purpose,
+      &auth::hash_session_token(&token),
+      expires_at,
+    )
+    .await?;
+  notify::notify(
+    &state.config.auth.notifications,
+    &AccountNotification::new(purpose, user, &token, expires_at),
+  )
+  .await
+}
+
+#[derive(Deserialize)]
+struct ForgotPasswordRequest {
+  username: String,
+}
+
+/// Record a password reset request for `username`, returning false if one
+/// was made within `reset_cooldown_secs`
+fn claim_reset_request(state: &AppState, username: &str) -> bool {
+  let cooldown = std::time::Duration::from_secs(state.config.auth.reset_cooldown_secs);
+  let now = std::time::Instant::now();
+  let mut requests = state.reset_requests.lock();
+  requests.retain(|_, at| now.duration_since(*at) < cooldown);
+  if requests.contains_key(username) {
+    return false;
+  }
+  requests.insert(username.to_string(), now);
+  true
 }
 
-/// POST /api/auth/logout - Logout (invalidate session)
-async fn api_auth_logout(
+/// POST /api/auth/forgot - Send a password reset token to the notification
+/// hooks. The lookup and delivery run in the background so the response is
+/// the same, and as fast, whether or not the user exists.
+async fn api_auth_forgot(
   State(state): State<AppState>,
-  headers: HeaderMap,
+  Json(req): Json<ForgotPasswordRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-  if let Some(token) = extract_token_from_headers(&headers) {
-    if let Some(session_token) = token.strip_prefix("session_") {
-      let session_hash = auth::hash_session_token(session_token);
-      if let Ok(Some((session, _))) = state.backend.validate_admin_session(&session_hash).await {
-        state.backend.delete_admin_session(session.id).await?;
+  if !state.config.auth.notifications.is_configured() {
+    return Err(AppError::ServiceUnavailable(
+      "Password reset is not configured".to_string(),
+    ));
+  }
+  let username = req.username.trim().to_lowercase();
+  if claim_reset_request(&state, &username) {
+    tokio::spawn(async move {
+      let sent = match state.backend.get_admin_user_by_username(&username).await {
+        Ok(Some((user, _))) => {
+          send_account_token(
+            &state,
+            &user,
+            AdminTokenPurpose::PasswordReset,
+            state.config.auth.reset_token_ttl_secs,
+          )
+          .await
+        }
+        Ok(None) => Ok(()),
+        Err(e) => Err(e),
+      };
+      if let Err(e) = sent {
+        emit_log(
+          "warn",
+          "squirreldb::auth",
+          &format!("Failed to send password reset for '{}': {}", username, e),
+        );
       }
-    }
+    });
   }
-  Ok(Json(serde_json::json!({"message": "Logged out"})))
+  Ok(Json(serde_json::json!({
+    "message": "If the user exists, a reset token has been sent"
+  })))
 }
 
 #[derive(Deserialize)]
-struct ChangePasswordRequest {
-  current_password: String,
+struct ResetPasswordRequest {
+  token: String,
   new_password: String,
 }
 
-/// POST /api/auth/change-password - Change current user's password
-async fn api_auth_change_password(
+/// POST /api/auth/reset - Set a new password with a reset token. Signs the
+/// user out everywhere.
+async fn api_auth_reset(
+  State(state): State<AppState>,
+  Json(req): Json<ResetPasswordRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  if req.new_password.len() < 8 {
+    return Err(AppError::BadRequest(
+      "New password must be at least 8 characters".to_string(),
+    ));
+  }
+  let user_id = state
+    .backend
+    .consume_admin_auth_token(
+      AdminTokenPurpose::PasswordReset,
+      &auth::hash_session_token(&req.token),
+    )
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+
+  let new_hash = auth::hash_password(&req.new_password, &state.config.auth.password_hashing)
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("Password hash error: {}", e)))?;
+  state
+    .backend
+    .update_admin_user_password(&user_id, &new_hash)
+    .await?;
+  state
+    .backend
+    .delete_admin_sessions_for_user(user_id)
+    .await?;
+
+  Ok(Json(
+    serde_json::json!({"message": "Password has been reset"}),
+  ))
+}
+
+/// POST /api/auth/verify-email/send - Send a verification token for the
+/// current user's email
+async fn api_auth_send_verification(
   State(state): State<AppState>,
   headers: HeaderMap,
-  Json(req): Json<ChangePasswordRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-  // Get current user from session
   let token = extract_token_from_headers(&headers)
     .ok_or_else(|| AppError::Unauthorized("Not logged in".to_string()))?;
   let session_token = token
@@ -1236,40 +3019,55 @@ async fn api_auth_change_password(
     .await?
     .ok_or_else(|| AppError::Unauthorized("Invalid session".to_string()))?;
 
-  // Get user with password hash for verification
-  let (_, password_hash) = state
-    .backend
-    .get_admin_user_by_username(&user.username)
-    .await?
-    .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
-
-  // Verify current password
-  if !auth::verify_password(&req.current_password, &password_hash) {
-    return Err(AppError::Unauthorized(
-      "Current password is incorrect".to_string(),
+  if user.email.is_none() {
+    return Err(AppError::BadRequest(
+      "User has no email address".to_string(),
     ));
   }
-
-  // Validate new password
-  if req.new_password.len() < 8 {
+  if user.email_verified {
     return Err(AppError::BadRequest(
-      "New password must be at least 8 characters".to_string(),
+      "Email is already verified".to_string(),
     ));
   }
-
-  // Hash and update password
-  let new_hash = auth::hash_password(&req.new_password)
-    .map_err(|e| AppError::Internal(anyhow::anyhow!("Password hash error: {}", e)))?;
-  state
-    .backend
-    .update_admin_user_password(&user.id, &new_hash)
-    .await?;
-
+  if !state.config.auth.notifications.is_configured() {
+    return Err(AppError::ServiceUnavailable(
+      "Email verification is not configured".to_string(),
+    ));
+  }
+  send_account_token(
+    &state,
+    &user,
+    AdminTokenPurpose::EmailVerification,
+    state.config.auth.verification_token_ttl_secs,
+  )
+  .await?;
   Ok(Json(
-    serde_json::json!({"message": "Password changed successfully"}),
+    serde_json::json!({"message": "Verification token sent"}),
   ))
 }
 
+#[derive(Deserialize)]
+struct VerifyEmailRequest {
+  token: String,
+}
+
+/// POST /api/auth/verify-email - Confirm an email with a verification token
+async fn api_auth_verify_email(
+  State(state): State<AppState>,
+  Json(req): Json<VerifyEmailRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let user_id = state
+    .backend
+    .consume_admin_auth_token(
+      AdminTokenPurpose::EmailVerification,
+      &auth::hash_session_token(&req.token),
+    )
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+  state.backend.set_admin_user_email_verified(user_id).await?;
+  Ok(Json(serde_json::json!({"message": "Email verified"})))
+}
+
 // =============================================================================
 // User Management API (owner only)
 // =============================================================================
@@ -1293,14 +3091,38 @@ async fn require_owner(state: &AppState, headers: &HeaderMap) -> Result<AdminUse
   Ok(user)
 }
 
-/// GET /api/users - List all admin users (owner only)
+#[derive(Deserialize)]
+struct ListUsersQuery {
+  limit: Option<usize>,
+  offset: Option<usize>,
+  search: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ListUsersResponse {
+  users: Vec<AdminUserResponse>,
+  total: u64,
+  limit: Option<usize>,
+  offset: usize,
+}
+
+/// GET /api/users - List admin users, optionally paged and filtered (owner only)
 async fn api_list_users(
   State(state): State<AppState>,
   headers: HeaderMap,
-) -> Result<Json<Vec<AdminUserResponse>>, AppError> {
+  Query(q): Query<ListUsersQuery>,
+) -> Result<Json<ListUsersResponse>, AppError> {
   require_owner(&state, &headers).await?;
-  let users = state.backend.list_admin_users().await?;
-  Ok(Json(users.into_iter().map(|u| u.into()).collect()))
+  let (users, total) = state
+    .backend
+    .list_admin_users_paged(q.search.as_deref(), q.limit, q.offset)
+    .await?;
+  Ok(Json(ListUsersResponse {
+    users: users.into_iter().map(|u| u.into()).collect(),
+    total,
+    limit: q.limit,
+    offset: q.offset.unwrap_or(0),
+  }))
 }
 
 #[derive(Deserialize)]
@@ -1334,7 +3156,7 @@ async fn api_create_user(
     .map_err(|_| AppError::BadRequest("Invalid role".to_string()))?;
 
   // Hash password
-  let password_hash = auth::hash_password(&body.password)
+  let password_hash = auth::hash_password(&body.password, &state.config.auth.password_hashing)
     .map_err(|e| AppError::Internal(anyhow::anyhow!("Password hash error: {}", e)))?;
 
   // Create user
@@ -1418,6 +3240,178 @@ async fn api_update_user_role(
   Ok(Json(serde_json::json!({"updated": true})))
 }
 
+// =============================================================================
+// Raw SQL API (owner only)
+// =============================================================================
+
+#[derive(Deserialize)]
+struct RawSqlRequest {
+  sql: String,
+  #[serde(default)]
+  params: Vec<serde_json::Value>,
+}
+
+/// Run a maintenance statement the query language can't express. Disabled
+/// unless `server.raw_sql.enabled`; every attempt is written to the audit log.
+async fn api_raw_sql(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Json(req): Json<RawSqlRequest>,
+) -> Result<Json<RawSqlResult>, AppError> {
+  let settings = &state.config.server.raw_sql;
+  if !settings.enabled {
+    return Err(AppError::Forbidden(
+      "Raw SQL is disabled (server.raw_sql.enabled)".to_string(),
+    ));
+  }
+  let user = require_owner(&state, &headers).await?;
+  if let Err(e) = state
+    .rate_limiter
+    .check_user_request(user.id, settings.requests_per_minute)
+  {
+    audit_raw_sql(&user, &req, &format!("rejected: {}", e));
+    return Err(e.into());
+  }
+
+  let statements = count_statements(&req.sql);
+  let rejected = match statements {
+    0 => Some("No SQL statement given"),
+    1 => None,
+    _ if !settings.allow_multiple_statements => Some("Multiple statements are not allowed"),
+    _ if !req.params.is_empty() => Some("Parameters can only be bound to a single statement"),
+    _ => None,
+  };
+  if let Some(reason) = rejected {
+    audit_raw_sql(&user, &req, &format!("rejected: {}", reason));
+    return Err(AppError::BadRequest(reason.to_string()));
+  }
+
+  let started = std::time::Instant::now();
+  let run = async {
+    if statements > 1 {
+      state.backend.execute_raw_sql_batch(&req.sql).await
+    } else {
+      state
+        .backend
+        .execute_raw_sql(&req.sql, &req.params, state.config.limits.max_result_rows)
+        .await
+    }
+  };
+  let result = match state.rate_limiter.query_timeout() {
+    Some(timeout) => tokio::time::timeout(timeout, run)
+      .await
+      .unwrap_or_else(|_| Err(anyhow::anyhow!("Statement timed out after {:?}", timeout))),
+    None => run.await,
+  };
+  let outcome = match &result {
+    Ok(r) => format!(
+      "{} rows, {} affected in {:?}",
+      r.rows.len(),
+      r.rows_affected,
+      started.elapsed()
+    ),
+    Err(e) => format!("failed: {}", e),
+  };
+  audit_raw_sql(&user, &req, &outcome);
+  let elapsed = started.elapsed();
+  if state.rate_limiter.is_slow(elapsed) {
+    tracing::warn!(
+      "Slow SQL statement by '{}' took {}ms",
+      user.username,
+      elapsed.as_millis()
+    );
+  }
+  result
+    .map(Json)
+    .map_err(|e| AppError::BadRequest(e.to_string()))
+}
+
+/// GET /api/queries/active - Statements running on the server's database connections
+async fn api_active_queries(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+) -> Result<Json<Vec<ActiveQuery>>, AppError> {
+  require_owner(&state, &headers).await?;
+  Ok(Json(state.backend.list_active_queries().await?))
+}
+
+/// POST /api/queries/{pid}/cancel - Cancel a running statement
+async fn api_cancel_query(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(pid): Path<i32>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let user = require_owner(&state, &headers).await?;
+  if !state.backend.cancel_query(pid).await? {
+    return Err(AppError::NotFound(format!(
+      "No running query with pid {}",
+      pid
+    )));
+  }
+  let message = format!("Query on pid {} cancelled by '{}'", pid, user.username);
+  tracing::warn!(target: "squirreldb::audit", "{}", message);
+  emit_log("warn", "squirreldb::audit", &message);
+  Ok(Json(serde_json::json!({ "cancelled": true })))
+}
+
+/// POST /api/collections/{name}/reindex - Rebuild the document indexes.
+/// Collections share one table and its indexes, so every collection
+/// benefits; the name only has to refer to an existing collection.
+async fn api_reindex_collection(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let user = require_owner(&state, &headers).await?;
+  let project_id = resolve_project(&state, &headers).await?;
+  validate_collection_name(&name).map_err(|e| AppError::BadRequest(e.to_string()))?;
+  let count = state
+    .backend
+    .count_documents(project_id, &name, None, &[], true, ReadOptions::default())
+    .await?;
+  if count.total == 0 {
+    return Err(AppError::NotFound(format!(
+      "Collection '{}' not found",
+      name
+    )));
+  }
+
+  let message = format!("Reindex of '{}' started by '{}'", name, user.username);
+  tracing::warn!(target: "squirreldb::audit", "{}", message);
+  emit_log("warn", "squirreldb::audit", &message);
+  let started = std::time::Instant::now();
+  let indexes = state.backend.reindex_documents().await?;
+  let duration_ms = started.elapsed().as_millis() as u64;
+  emit_log(
+    "info",
+    "squirreldb::admin",
+    &format!(
+      "Reindexed {} indexes for '{}' in {} ms",
+      indexes.len(),
+      name,
+      duration_ms
+    ),
+  );
+  Ok(Json(serde_json::json!({
+    "collection": name,
+    "indexes": indexes,
+    "duration_ms": duration_ms,
+  })))
+}
+
+/// Log a raw SQL attempt. Parameter values are left out as they may hold secrets.
+fn audit_raw_sql(user: &AdminUser, req: &RawSqlRequest, outcome: &str) {
+  let message = format!(
+    "Raw SQL by '{}' ({} params) {}: {}",
+    user.username,
+    req.params.len(),
+    outcome,
+    req.sql
+  );
+  tracing::warn!(target: "squirreldb::audit", "{}", message);
+  emit_log("warn", "squirreldb::audit", &message);
+}
+
 // =============================================================================
 // Settings API
 // =============================================================================
@@ -1488,6 +3482,56 @@ async fn api_list_tokens(
 #[derive(Deserialize)]
 struct CreateTokenRequest {
   name: String,
+  /// Name of the project for the token, where the route doesn't give one
+  #[serde(default)]
+  project: Option<String>,
+}
+
+/// Project for a token whose route names none: the project called
+/// `requested`, else `auth.default_project`, else the built-in default
+/// project. A named project that doesn't exist is created for `owner`, or
+/// for the first owner account when not given.
+async fn token_project(
+  state: &AppState,
+  requested: Option<&str>,
+  owner: Option<Uuid>,
+) -> Result<Uuid, AppError> {
+  let Some(name) = requested
+    .or(state.config.auth.default_project.as_deref())
+    .map(str::trim)
+    .filter(|name| !name.is_empty())
+  else {
+    return Ok(DEFAULT_PROJECT_ID);
+  };
+  if let Some(project) = state.backend.get_project_by_name(name).await? {
+    return Ok(project.id);
+  }
+
+  let owner = match owner {
+    Some(owner) => Some(owner),
+    None => state
+      .backend
+      .list_admin_users()
+      .await?
+      .into_iter()
+      .find(|u| u.role == AdminRole::Owner)
+      .map(|u| u.id),
+  };
+  // Projects belong to an admin user, so a token-only setup can only use
+  // projects that already exist
+  let owner = owner.ok_or_else(|| {
+    AppError::BadRequest(format!(
+      "Project '{}' does not exist and there is no owner account to create it for",
+      name
+    ))
+  })?;
+  let project = state.backend.create_project(name, None, owner).await?;
+  emit_log(
+    "info",
+    "squirreldb::admin",
+    &format!("Created project '{}' for new tokens", name),
+  );
+  Ok(project.id)
 }
 
 #[derive(Serialize)]
@@ -1518,10 +3562,10 @@ async fn api_setup_token(
   let token = generate_token();
   let token_hash = hash_token(&token);
 
-  // Store in database (default project)
+  let project_id = token_project(&state, req.project.as_deref(), None).await?;
   let info = state
     .backend
-    .create_token(DEFAULT_PROJECT_ID, &req.name, &token_hash)
+    .create_token(project_id, &req.name, &token_hash)
     .await?;
 
   emit_log(
@@ -1534,6 +3578,26 @@ async fn api_setup_token(
   Ok(Json(CreateTokenResponse { token, info }))
 }
 
+/// POST /api/tokens - Create a token in the project named in the body or
+/// the configured default project
+async fn api_create_default_token(
+  State(state): State<AppState>,
+  Json(req): Json<CreateTokenRequest>,
+) -> Result<Json<CreateTokenResponse>, AppError> {
+  if req.name.is_empty() {
+    return Err(AppError::BadRequest("Token name is required".into()));
+  }
+  let project_id = token_project(&state, req.project.as_deref(), None).await?;
+
+  let token = generate_token();
+  let token_hash = hash_token(&token);
+  let info = state
+    .backend
+    .create_token(project_id, &req.name, &token_hash)
+    .await?;
+  Ok(Json(CreateTokenResponse { token, info }))
+}
+
 async fn api_create_token(
   State(state): State<AppState>,
   Path(project_id): Path<String>,
@@ -1658,6 +3722,89 @@ async fn api_toggle_feature(
   })))
 }
 
+// =============================================================================
+// Maintenance Mode API
+// =============================================================================
+
+async fn api_get_maintenance(State(state): State<AppState>) -> Json<MaintenanceSettings> {
+  Json(state.maintenance.settings())
+}
+
+/// PUT /api/settings/maintenance - Turn maintenance mode on or off
+async fn api_update_maintenance(
+  State(state): State<AppState>,
+  Json(settings): Json<MaintenanceSettings>,
+) -> Result<Json<MaintenanceSettings>, AppError> {
+  state
+    .maintenance
+    .save(state.backend.as_ref(), settings.clone())
+    .await?;
+
+  emit_log(
+    "warn",
+    "squirreldb::admin",
+    &if !settings.enabled {
+      "Maintenance mode disabled".to_string()
+    } else if settings.block_reads {
+      "Maintenance mode enabled: reads and writes are refused".to_string()
+    } else {
+      "Maintenance mode enabled: writes are refused".to_string()
+    },
+  );
+  Ok(Json(settings))
+}
+
+// =============================================================================
+// Connections API
+// =============================================================================
+
+#[derive(Serialize)]
+struct ConnectionResponse {
+  #[serde(flatten)]
+  info: ConnectionInfo,
+  subscriptions: usize,
+}
+
+/// GET /api/connections - Open WebSocket and TCP connections
+async fn api_list_connections(State(state): State<AppState>) -> Json<Vec<ConnectionResponse>> {
+  Json(
+    state
+      .connections
+      .list()
+      .into_iter()
+      .map(|info| ConnectionResponse {
+        subscriptions: state.subs.subscription_count(info.id),
+        info,
+      })
+      .collect(),
+  )
+}
+
+/// DELETE /api/connections/{id} - Force a client off the server
+async fn api_disconnect_connection(
+  State(state): State<AppState>,
+  Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  let id: Uuid = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid connection ID".to_string()))?;
+  let info = state
+    .connections
+    .get(id)
+    .ok_or_else(|| AppError::NotFound(format!("Connection {} not found", id)))?;
+  state.connections.disconnect(id);
+
+  emit_log(
+    "warn",
+    "squirreldb::admin",
+    &format!(
+      "Disconnecting {} client {} from {}",
+      info.protocol, id, info.ip
+    ),
+  );
+  Ok(Json(serde_json::json!({ "message": "Connection closed" })))
+}
+
 // =============================================================================
 // Auth Settings API
 // =============================================================================
@@ -2154,6 +4301,12 @@ async fn api_update_storage_settings(
     .unwrap_or(current_min_part);
 
   settings.insert("port".to_string(), serde_json::json!(port));
+  // Store relative paths resolved against data_dir, like the config file's
+  let storage_path = state
+    .config
+    .resolve_path(&storage_path)
+    .to_string_lossy()
+    .into_owned();
   settings.insert("storage_path".to_string(), serde_json::json!(storage_path));
   settings.insert("region".to_string(), serde_json::json!(region));
   settings.insert(
@@ -2248,6 +4401,22 @@ async fn api_update_storage_settings(
     serde_json::json!(proxy_force_path_style),
   );
 
+  // Validate the merged settings before persisting so a bad save can't break storage
+  let proxy_secret_access_key = settings
+    .get("proxy_secret_access_key")
+    .and_then(|v| v.as_str())
+    .map(String::from)
+    .unwrap_or_default();
+  let proxy_config = crate::storage::config::ProxyConfig {
+    endpoint: proxy_endpoint,
+    access_key_id: proxy_access_key_id,
+    secret_access_key: proxy_secret_access_key,
+    region: proxy_region,
+    bucket_prefix: proxy_bucket_prefix,
+    force_path_style: proxy_force_path_style,
+  };
+  validate_storage_settings(&mode, &storage_path, proxy_config).await?;
+
   // Save settings to database
   let settings_json = serde_json::Value::Object(settings.clone());
   state
@@ -2297,6 +4466,60 @@ async fn api_update_storage_settings(
   })))
 }
 
+/// Check that storage settings are usable before they are persisted.
+/// Builtin mode requires a writable storage path; proxy mode requires
+/// credentials and a reachable endpoint.
+async fn validate_storage_settings(
+  mode: &str,
+  storage_path: &str,
+  proxy: crate::storage::config::ProxyConfig,
+) -> Result<(), AppError> {
+  use crate::storage::backend::StorageBackend;
+  use crate::storage::config::StorageMode;
+  use crate::storage::S3ProxyClient;
+
+  let mode: StorageMode = mode.parse().map_err(AppError::BadRequest)?;
+
+  match mode {
+    StorageMode::Builtin => {
+      let path = std::path::Path::new(storage_path);
+      tokio::fs::create_dir_all(path).await.map_err(|e| {
+        AppError::BadRequest(format!(
+          "Storage path '{}' cannot be created: {}",
+          storage_path, e
+        ))
+      })?;
+
+      let probe = path.join(format!(".sqrl_write_test_{}", Uuid::new_v4()));
+      tokio::fs::write(&probe, b"ok").await.map_err(|e| {
+        AppError::BadRequest(format!(
+          "Storage path '{}' is not writable: {}",
+          storage_path, e
+        ))
+      })?;
+      let _ = tokio::fs::remove_file(&probe).await;
+    }
+    StorageMode::Proxy => {
+      if !proxy.is_configured() {
+        return Err(AppError::BadRequest(
+          "Proxy mode requires access_key_id and secret_access_key".to_string(),
+        ));
+      }
+
+      let client = S3ProxyClient::new(proxy)
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to create client: {}", e)))?;
+
+      client
+        .test_connection()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Connection failed: {}", e)))?;
+    }
+  }
+
+  Ok(())
+}
+
 #[derive(Serialize)]
 struct StorageBucketResponse {
   name: String,
@@ -2764,6 +4987,12 @@ async fn api_update_cache_settings(
     "snapshot_enabled".to_string(),
     serde_json::json!(snapshot_enabled),
   );
+  // Store relative paths resolved against data_dir, like the config file's
+  let snapshot_path = state
+    .config
+    .resolve_path(&snapshot_path)
+    .to_string_lossy()
+    .into_owned();
   settings_map.insert(
     "snapshot_path".to_string(),
     serde_json::json!(snapshot_path),
@@ -2919,6 +5148,46 @@ async fn api_flush_cache(
   Err(AppError::BadRequest("Cache is not running".to_string()))
 }
 
+/// Most queries accepted by one warm request
+const MAX_WARM_QUERIES: usize = 1000;
+
+#[derive(Deserialize)]
+struct WarmCacheRequest {
+  queries: Vec<WarmupQuery>,
+}
+
+/// POST /api/cache/warm - Run queries and cache their results
+async fn api_warm_cache(
+  State(state): State<AppState>,
+  Json(body): Json<WarmCacheRequest>,
+) -> Result<Json<WarmupReport>, AppError> {
+  if !state.engine_pool.query_cache().is_enabled() {
+    return Err(AppError::BadRequest("Cache is not running".to_string()));
+  }
+  if body.queries.len() > MAX_WARM_QUERIES {
+    return Err(AppError::BadRequest(format!(
+      "At most {} queries can be warmed per request",
+      MAX_WARM_QUERIES
+    )));
+  }
+
+  let report = state
+    .engine_pool
+    .warm_all(&body.queries, state.backend.as_ref())
+    .await;
+  emit_log(
+    "info",
+    "squirreldb::admin",
+    &format!(
+      "Cache warmed via admin API: {} warmed, {} skipped, {} failed",
+      report.warmed,
+      report.skipped,
+      report.errors.len()
+    ),
+  );
+  Ok(Json(report))
+}
+
 // =============================================================================
 // Backup API
 // =============================================================================
@@ -2927,16 +5196,27 @@ async fn api_flush_cache(
 struct BackupSettingsResponse {
   enabled: bool,
   interval: u64,
+  schedule: Option<String>,
   retention: u32,
   local_path: String,
+  storage_bucket: String,
   storage_path: String,
+  incremental: bool,
+  full_every: u32,
+  compression: crate::server::BackupCompression,
+  /// Whether an encryption key is configured (the key itself is never returned)
+  encrypted: bool,
   last_backup: Option<String>,
   next_backup: Option<String>,
   storage_enabled: bool,
 }
 
 async fn api_get_backup_settings(State(state): State<AppState>) -> Json<BackupSettingsResponse> {
-  let backup_config = &state.config.backup;
+  // Settings saved from the admin UI take precedence over the config file
+  let mut backup_config = state.config.backup.clone();
+  if let Ok(Some((_, settings))) = state.backend.get_feature_settings("backup").await {
+    crate::backup::apply_settings(&mut backup_config, &settings);
+  }
   let storage_enabled = state.feature_registry.is_enabled("storage");
 
   // Get last/next backup times from the feature if running
@@ -2959,9 +5239,15 @@ async fn api_get_backup_settings(State(state): State<AppState>) -> Json<BackupSe
   Json(BackupSettingsResponse {
     enabled: state.feature_registry.is_enabled("backup"),
     interval: backup_config.interval,
+    schedule: backup_config.schedule,
     retention: backup_config.retention,
-    local_path: backup_config.local_path.clone(),
-    storage_path: backup_config.storage_path.clone(),
+    local_path: backup_config.local_path,
+    storage_bucket: backup_config.storage_bucket,
+    storage_path: backup_config.storage_path,
+    incremental: backup_config.incremental,
+    full_every: backup_config.full_every,
+    compression: backup_config.compression,
+    encrypted: backup_config.encryption_key.is_some(),
     last_backup,
     next_backup,
     storage_enabled,
@@ -2971,9 +5257,16 @@ async fn api_get_backup_settings(State(state): State<AppState>) -> Json<BackupSe
 #[derive(Deserialize)]
 struct UpdateBackupSettingsReq {
   interval: Option<u64>,
+  /// Cron expression; an empty string switches back to interval mode
+  schedule: Option<String>,
   retention: Option<u32>,
   local_path: Option<String>,
+  storage_bucket: Option<String>,
   storage_path: Option<String>,
+  incremental: Option<bool>,
+  /// Take a full baseline every N backups in incremental mode
+  full_every: Option<u32>,
+  compression: Option<crate::server::BackupCompression>,
 }
 
 async fn api_update_backup_settings(
@@ -2991,17 +5284,54 @@ async fn api_update_backup_settings(
   // Merge updates
   let mut settings = current_settings.clone();
   if let Some(interval) = req.interval {
+    if interval == 0 {
+      return Err(AppError::BadRequest(
+        "Backup interval must be at least 1 second".to_string(),
+      ));
+    }
     settings["interval"] = serde_json::json!(interval);
   }
+  if let Some(schedule) = req.schedule {
+    let schedule = schedule.trim();
+    if schedule.is_empty() {
+      settings["schedule"] = serde_json::Value::Null;
+    } else {
+      crate::backup::parse_cron(schedule).map_err(|e| AppError::BadRequest(e.to_string()))?;
+      settings["schedule"] = serde_json::json!(schedule);
+    }
+  }
   if let Some(retention) = req.retention {
     settings["retention"] = serde_json::json!(retention);
   }
   if let Some(local_path) = req.local_path {
     settings["local_path"] = serde_json::json!(local_path);
   }
+  if let Some(storage_bucket) = req.storage_bucket {
+    let storage_bucket = storage_bucket.trim();
+    if storage_bucket.is_empty() {
+      return Err(AppError::BadRequest(
+        "storage_bucket must not be empty".to_string(),
+      ));
+    }
+    settings["storage_bucket"] = serde_json::json!(storage_bucket);
+  }
   if let Some(storage_path) = req.storage_path {
     settings["storage_path"] = serde_json::json!(storage_path);
   }
+  if let Some(incremental) = req.incremental {
+    settings["incremental"] = serde_json::json!(incremental);
+  }
+  if let Some(full_every) = req.full_every {
+    if full_every == 0 {
+      return Err(AppError::BadRequest(
+        "full_every must be at least 1".to_string(),
+      ));
+    }
+    settings["full_every"] = serde_json::json!(full_every);
+  }
+  if let Some(compression) = req.compression {
+    settings["compression"] = serde_json::json!(compression);
+  }
 
   // Save to database
   let enabled = state.feature_registry.is_enabled("backup");
@@ -3013,13 +5343,38 @@ async fn api_update_backup_settings(
 
   emit_log("info", "squirreldb::admin", "Backup settings updated");
 
+  // Restart the scheduler so next_backup reflects the new schedule
+  if enabled {
+    let feature_state = Arc::new(crate::features::AppState {
+      backend: state.backend.clone(),
+      engine_pool: state.engine_pool.clone(),
+      config: state.config.clone(),
+    });
+    state
+      .feature_registry
+      .restart("backup", feature_state)
+      .await
+      .map_err(AppError::Internal)?;
+  }
+
   Ok(Json(serde_json::json!({
     "message": "Backup settings updated",
     "settings": settings,
-    "restart_required": true
+    "restarted": enabled
   })))
 }
 
+/// Server config with backup settings saved from the admin UI applied, so
+/// manual operations see the same bucket, paths and chain as scheduled backups
+async fn backup_config(state: &AppState) -> ServerConfig {
+  let mut config = state.config.clone();
+  if let Ok(Some((_, settings))) = state.backend.get_feature_settings("backup").await {
+    crate::backup::apply_settings(&mut config.backup, &settings);
+    config.resolve_paths();
+  }
+  config
+}
+
 #[derive(Serialize)]
 struct BackupInfoResponse {
   id: String,
@@ -3028,6 +5383,9 @@ struct BackupInfoResponse {
   created_at: String,
   backend: String,
   location: String,
+  kind: String,
+  compression: String,
+  encrypted: bool,
 }
 
 async fn api_list_backups(
@@ -3039,7 +5397,7 @@ async fn api_list_backups(
       .downcast_ref::<crate::backup::BackupFeature>()
     {
       let backups = backup_feature
-        .list_backups(&state.config)
+        .list_backups(&state.backend, &backup_config(&state).await)
         .await
         .map_err(|e| AppError::Internal(anyhow::anyhow!("{}", e)))?;
 
@@ -3052,6 +5410,9 @@ async fn api_list_backups(
           created_at: b.created_at.to_rfc3339(),
           backend: b.backend,
           location: b.location,
+          kind: b.kind,
+          compression: b.compression,
+          encrypted: b.encrypted,
         })
         .collect();
 
@@ -3067,19 +5428,15 @@ async fn api_list_backups(
     if let Ok(mut entries) = tokio::fs::read_dir(&local_path).await {
       while let Ok(Some(entry)) = entries.next_entry().await {
         let path = entry.path();
-        if path.extension().is_some_and(|ext| ext == "sql") {
+        let filename = path
+          .file_name()
+          .unwrap_or_default()
+          .to_string_lossy()
+          .to_string();
+        if crate::backup::is_backup_file(&filename) {
           if let Ok(metadata) = entry.metadata().await {
-            let filename = path
-              .file_name()
-              .unwrap_or_default()
-              .to_string_lossy()
-              .to_string();
             backups.push(BackupInfoResponse {
-              id: filename
-                .split('_')
-                .next_back()
-                .unwrap_or("unknown")
-                .replace(".sql", ""),
+              id: crate::backup::backup_id_from_filename(&filename),
               filename: filename.clone(),
               size: metadata.len() as i64,
               created_at: metadata
@@ -3089,6 +5446,13 @@ async fn api_list_backups(
                 .unwrap_or_default(),
               backend: "unknown".to_string(),
               location: path.to_string_lossy().to_string(),
+              kind: crate::backup::BackupKind::from_filename(&filename)
+                .as_str()
+                .to_string(),
+              compression: crate::server::BackupCompression::from_filename(&filename)
+                .as_str()
+                .to_string(),
+              encrypted: crate::backup::is_encrypted(&filename),
             });
           }
         }
@@ -3108,7 +5472,7 @@ async fn api_create_backup(
       .downcast_ref::<crate::backup::BackupFeature>()
     {
       let backup = backup_feature
-        .create_backup(&state.backend, &state.config)
+        .create_backup(&state.backend, &backup_config(&state).await)
         .await
         .map_err(|e| AppError::Internal(anyhow::anyhow!("{}", e)))?;
 
@@ -3125,6 +5489,9 @@ async fn api_create_backup(
         created_at: backup.created_at.to_rfc3339(),
         backend: backup.backend,
         location: backup.location,
+        kind: backup.kind,
+        compression: backup.compression,
+        encrypted: backup.encrypted,
       }));
     }
   }
@@ -3144,7 +5511,7 @@ async fn api_delete_backup(
       .downcast_ref::<crate::backup::BackupFeature>()
     {
       let deleted = backup_feature
-        .delete_backup(&state.config, &id)
+        .delete_backup(&state.backend, &backup_config(&state).await, &id)
         .await
         .map_err(|e| AppError::Internal(anyhow::anyhow!("{}", e)))?;
 
@@ -3166,6 +5533,174 @@ async fn api_delete_backup(
   ))
 }
 
+#[derive(Deserialize)]
+struct RestoreBackupRequest {
+  /// Must be true; guards against accidental restores
+  #[serde(default)]
+  confirm: bool,
+  /// Replace existing documents instead of refusing
+  #[serde(default)]
+  force: bool,
+}
+
+async fn api_restore_backup(
+  Path(id): Path<String>,
+  State(state): State<AppState>,
+  Json(req): Json<RestoreBackupRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  if !req.confirm {
+    return Err(AppError::BadRequest(
+      "Restoring a backup overwrites data; set \"confirm\": true to proceed".to_string(),
+    ));
+  }
+
+  if let Some(feature) = state.feature_registry.get("backup") {
+    if let Some(backup_feature) = feature
+      .as_any()
+      .downcast_ref::<crate::backup::BackupFeature>()
+    {
+      if !req.force && !crate::backup::database_is_empty(&state.backend).await? {
+        return Err(AppError::BadRequest(
+          "Database is not empty; set \"force\": true to replace existing documents".to_string(),
+        ));
+      }
+
+      let summary = backup_feature
+        .restore_backup(&state.backend, &backup_config(&state).await, &id, req.force)
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("{}", e)))?
+        .ok_or_else(|| AppError::NotFound(format!("Backup '{}' not found", id)))?;
+
+      // Cached query results may reference replaced documents
+      state.engine_pool.invalidate_cache();
+
+      emit_log(
+        "info",
+        "squirreldb::admin",
+        &format!("Backup restored: {} ({} documents)", id, summary.documents),
+      );
+      return Ok(Json(serde_json::json!({
+        "restored": true,
+        "id": id,
+        "summary": summary
+      })));
+    }
+  }
+
+  Err(AppError::BadRequest(
+    "Backup feature is not available".to_string(),
+  ))
+}
+
+// =============================================================================
+// Webhook API
+// =============================================================================
+
+/// Placeholder returned instead of webhook secrets
+const WEBHOOK_SECRET_MASK: &str = "********";
+
+#[derive(Serialize)]
+struct WebhookSettingsResponse {
+  enabled: bool,
+  settings: crate::webhooks::WebhookSettings,
+  delivered: u64,
+  failed: u64,
+}
+
+/// Load stored webhook settings (defaults if none are saved)
+async fn load_webhook_settings(
+  state: &AppState,
+) -> Result<crate::webhooks::WebhookSettings, AppError> {
+  match state.backend.get_feature_settings("webhooks").await? {
+    Some((_, value)) if !value.is_null() => serde_json::from_value(value)
+      .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid webhook settings: {}", e))),
+    _ => Ok(crate::webhooks::WebhookSettings::default()),
+  }
+}
+
+async fn api_get_webhook_settings(
+  State(state): State<AppState>,
+) -> Result<Json<WebhookSettingsResponse>, AppError> {
+  let mut settings = load_webhook_settings(&state).await?;
+  for endpoint in &mut settings.endpoints {
+    if endpoint.secret.is_some() {
+      endpoint.secret = Some(WEBHOOK_SECRET_MASK.to_string());
+    }
+  }
+
+  let (delivered, failed) = state
+    .feature_registry
+    .get("webhooks")
+    .and_then(|f| {
+      f.as_any()
+        .downcast_ref::<crate::webhooks::WebhookFeature>()
+        .map(|wf| (wf.delivered(), wf.failed()))
+    })
+    .unwrap_or((0, 0));
+
+  Ok(Json(WebhookSettingsResponse {
+    enabled: state.feature_registry.is_enabled("webhooks"),
+    settings,
+    delivered,
+    failed,
+  }))
+}
+
+async fn api_update_webhook_settings(
+  State(state): State<AppState>,
+  Json(mut req): Json<crate::webhooks::WebhookSettings>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  req.validate().map_err(AppError::BadRequest)?;
+
+  // Keep existing secrets for endpoints submitted with the masked placeholder
+  let current = load_webhook_settings(&state).await?;
+  for endpoint in &mut req.endpoints {
+    if endpoint.secret.as_deref() == Some(WEBHOOK_SECRET_MASK) {
+      endpoint.secret = current
+        .endpoints
+        .iter()
+        .find(|e| e.url == endpoint.url)
+        .and_then(|e| e.secret.clone());
+    }
+  }
+
+  let enabled = state.feature_registry.is_enabled("webhooks");
+  let settings = serde_json::to_value(&req)?;
+  state
+    .backend
+    .update_feature_settings("webhooks", enabled, settings)
+    .await
+    .map_err(AppError::Internal)?;
+
+  emit_log(
+    "info",
+    "squirreldb::admin",
+    &format!(
+      "Webhook settings updated ({} endpoint(s))",
+      req.endpoints.len()
+    ),
+  );
+
+  // Restart the feature so the new endpoints take effect
+  if enabled {
+    let feature_state = Arc::new(crate::features::AppState {
+      backend: state.backend.clone(),
+      engine_pool: state.engine_pool.clone(),
+      config: state.config.clone(),
+    });
+    state
+      .feature_registry
+      .restart("webhooks", feature_state)
+      .await
+      .map_err(AppError::Internal)?;
+  }
+
+  Ok(Json(serde_json::json!({
+    "message": "Webhook settings updated",
+    "restarted": enabled
+  })))
+}
+
 // =============================================================================
 // WebSocket Handler
 // =============================================================================
@@ -3173,18 +5708,126 @@ async fn api_delete_backup(
 #[derive(Deserialize)]
 struct WsAuthParams {
   token: Option<String>,
+  /// Project for a data connection, like the REST `X-Project-Id` header
+  project_id: Option<Uuid>,
 }
 
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> Response {
-  // Data WebSocket - no auth required (auth is only for admin UI)
-  ws.on_upgrade(move |socket| handle_ws_connection(socket, state))
-    .into_response()
+/// Refuse WebSocket upgrades from browser origins outside `server.cors_origins`
+fn check_ws_origin(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+  let origin = headers
+    .get(header::ORIGIN)
+    .map(|v| v.to_str().unwrap_or_default());
+  let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+  if state.cors_origins.allows_upgrade(origin, host) {
+    Ok(())
+  } else {
+    tracing::warn!(
+      "WebSocket upgrade rejected from origin {}",
+      origin.unwrap_or_default()
+    );
+    Err(AppError::Forbidden("Origin not allowed".into()))
+  }
+}
+
+async fn ws_handler(
+  ws: WebSocketUpgrade,
+  headers: HeaderMap,
+  ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+  Query(params): Query<WsAuthParams>,
+  State(state): State<AppState>,
+) -> Response {
+  if let Err(e) = check_ws_origin(&state, &headers) {
+    return e.into_response();
+  }
+  let (project, author) = match ws_credentials(&state, &headers, params).await {
+    Ok(credentials) => credentials,
+    Err(e) => return e.into_response(),
+  };
+
+  // Count the connection against the connection limits until it closes
+  let ip = client_ip(&state, peer.ip(), &headers);
+  let permit = match state.rate_limiter.acquire_connection(ip) {
+    Ok(permit) => permit,
+    Err(e) => {
+      tracing::warn!("WebSocket connection rejected: {}", e);
+      return AppError::from(e).into_response();
+    }
+  };
+
+  // Oversized frames are rejected by the protocol layer, which closes the connection
+  let max_message_size = state.rate_limiter.max_message_size();
+  let ws = if max_message_size > 0 {
+    ws.max_message_size(max_message_size)
+      .max_frame_size(max_message_size)
+  } else {
+    ws
+  };
+
+  ws.on_upgrade(move |socket| async move {
+    handle_ws_connection(socket, state, ip, project, author).await;
+    drop(permit);
+  })
+  .into_response()
+}
+
+/// Project and author of a data WebSocket, by the same rules as REST
+/// requests. The token may also be passed as `?token=` and the project as
+/// `?project_id=`, since browsers can't set headers on an upgrade. The
+/// project is None when the connection may select any project: auth is
+/// off, or the admin token or an owner's session was given. Without
+/// credentials and with auth on, the connection stays on the default
+/// project.
+async fn ws_credentials(
+  state: &AppState,
+  headers: &HeaderMap,
+  params: WsAuthParams,
+) -> Result<(Option<Uuid>, Option<Uuid>), AppError> {
+  let mut headers = headers.clone();
+  if let Some(token) = params.token {
+    let value = HeaderValue::from_str(&format!("Bearer {}", token))
+      .map_err(|_| AppError::Unauthorized("Invalid token".to_string()))?;
+    headers.insert(header::AUTHORIZATION, value);
+  }
+  if let Some(value) = params
+    .project_id
+    .and_then(|p| HeaderValue::from_str(&p.to_string()).ok())
+  {
+    headers.insert("X-Project-Id", value);
+  }
+
+  let project = select_request_project(state, &headers).await?;
+  let author = request_author(state, &headers).await?;
+  if !state.config.auth.enabled || unrestricted_credentials(state, &headers).await? {
+    return Ok((None, author));
+  }
+  Ok((Some(project), author))
+}
+
+/// Whether the request carries the admin token or an owner's session
+async fn unrestricted_credentials(state: &AppState, headers: &HeaderMap) -> Result<bool, AppError> {
+  let Some(token) = extract_token_from_headers(headers) else {
+    return Ok(false);
+  };
+  if let Some(session_token) = token.strip_prefix("session_") {
+    let session_hash = auth::hash_session_token(session_token);
+    let session = state.backend.validate_admin_session(&session_hash).await?;
+    return Ok(session.is_some_and(|(_, user)| user.role == AdminRole::Owner));
+  }
+  Ok(state.config.auth.admin_token.as_ref().is_some_and(|admin| {
+    !admin.is_empty() && crate::security::constant_time_compare(&token, admin)
+  }))
 }
 
-async fn handle_ws_connection(socket: WebSocket, state: AppState) {
+async fn handle_ws_connection(
+  socket: WebSocket,
+  state: AppState,
+  ip: std::net::IpAddr,
+  project: Option<Uuid>,
+  author: Option<Uuid>,
+) {
   let client_id = Uuid::new_v4();
   let (mut sink, mut stream) = socket.split();
-  let (tx, mut rx) = mpsc::unbounded_channel();
+  let (tx, mut rx) = mpsc::channel(state.rate_limiter.max_send_queue());
 
   // Register client
   state.ws_clients.write().await.insert(client_id, tx);
@@ -3193,28 +5836,60 @@ async fn handle_ws_connection(socket: WebSocket, state: AppState) {
     state.backend.clone(),
     state.subs.clone(),
     state.engine_pool.clone(),
-  );
-
-  // Task to send messages to client
+  )
+  .with_project(project)
+  .with_author(author)
+  .with_max_document_bytes(state.rate_limiter.max_document_bytes())
+  .with_max_document_depth(state.rate_limiter.max_document_depth())
+  .with_idempotency_ttl(state.rate_limiter.idempotency_key_ttl())
+  .with_rate_limiter(state.rate_limiter.clone())
+  .with_maintenance(state.maintenance.clone())
+  .with_connections(state.connections.clone());
+  let connection = state
+    .connections
+    .register(client_id, "websocket", ip, handler.project_id());
+
+  // Task to send messages to client; a stalled reader ends the connection
   let clients = state.ws_clients.clone();
-  let send_task = tokio::spawn(async move {
+  let mut send_task = tokio::spawn(async move {
     while let Some(msg) = rx.recv().await {
       if let Ok(json) = serde_json::to_string(&msg) {
-        if sink.send(Message::Text(json.into())).await.is_err() {
-          break;
+        let send = sink.send(Message::Text(json.into()));
+        match tokio::time::timeout(SEND_TIMEOUT, send).await {
+          Ok(Ok(())) => {}
+          _ => break,
         }
       }
     }
   });
 
-  // Process incoming messages
-  while let Some(Ok(msg)) = stream.next().await {
+  // Process incoming messages until the client or the send side goes away
+  loop {
+    let msg = tokio::select! {
+      msg = stream.next() => match msg {
+        Some(Ok(msg)) => msg,
+        _ => break,
+      },
+      _ = &mut send_task => break,
+      _ = connection.disconnected() => {
+        tracing::info!("WebSocket client {} disconnected by an admin", client_id);
+        break;
+      }
+    };
+
     if let Message::Text(text) = msg {
-      if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
-        let resp = handler.handle(client_id, client_msg).await;
-        if let Some(tx) = clients.read().await.get(&client_id) {
-          let _ = tx.send(resp);
+      let resp = match decode_client_message(&text, state.rate_limiter.inexact_numbers()) {
+        Ok(client_msg) => {
+          let trace_id = new_trace_id();
+          with_trace_id(trace_id.clone(), handler.handle(client_id, client_msg))
+            .await
+            .with_trace_id(&trace_id)
         }
+        // Answer malformed messages instead of leaving the client waiting
+        Err(error_msg) => error_msg,
+      };
+      if !queue_message(&clients, client_id, resp).await {
+        break;
       }
     }
   }
@@ -3231,9 +5906,14 @@ async fn handle_ws_connection(socket: WebSocket, state: AppState) {
 
 async fn ws_logs_handler(
   ws: WebSocketUpgrade,
+  headers: HeaderMap,
   Query(params): Query<WsAuthParams>,
   State(state): State<AppState>,
 ) -> Response {
+  if let Err(e) = check_ws_origin(&state, &headers) {
+    return e.into_response();
+  }
+
   // Check auth if enabled (admin-only access)
   if state.config.auth.enabled {
     match params.token {
@@ -3525,9 +6205,80 @@ async fn api_delete_project(
   if !deleted {
     return Err(AppError::NotFound("Project not found".to_string()));
   }
+  state.rate_limiter.remove_project_limits(project_id);
   Ok(Json(serde_json::json!({"deleted": true})))
 }
 
+/// GET /api/projects/:id/limits - Get a project's quotas (owner only)
+async fn api_get_project_limits(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  require_owner(&state, &headers).await?;
+  let project_id: Uuid = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid project ID".to_string()))?;
+  ensure_project_exists(&state, project_id).await?;
+
+  let limits = state.backend.get_project_limits(project_id).await?;
+  let documents = state.backend.count_project_documents(project_id).await?;
+  Ok(Json(serde_json::json!({
+    "project_id": project_id,
+    "limits": limits,
+    "documents": documents,
+  })))
+}
+
+/// PUT /api/projects/:id/limits - Set a project's quotas (owner only)
+async fn api_set_project_limits(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(id): Path<String>,
+  Json(limits): Json<ProjectLimits>,
+) -> Result<Json<ProjectLimits>, AppError> {
+  let user = require_owner(&state, &headers).await?;
+  let project_id: Uuid = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid project ID".to_string()))?;
+  ensure_project_exists(&state, project_id).await?;
+
+  state
+    .backend
+    .set_project_limits(project_id, &limits)
+    .await?;
+  state.rate_limiter.set_project_limits(project_id, limits);
+  emit_log(
+    "info",
+    "squirreldb::api",
+    &format!(
+      "Project {} limits set by {}: {} req/s, burst {}, {} documents",
+      project_id,
+      user.username,
+      limits.requests_per_second,
+      limits.burst_size,
+      limits.max_documents
+    ),
+  );
+  Ok(Json(limits))
+}
+
+/// DELETE /api/projects/:id/limits - Remove a project's quotas (owner only)
+async fn api_delete_project_limits(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+  require_owner(&state, &headers).await?;
+  let project_id: Uuid = id
+    .parse()
+    .map_err(|_| AppError::BadRequest("Invalid project ID".to_string()))?;
+
+  let deleted = state.backend.delete_project_limits(project_id).await?;
+  state.rate_limiter.remove_project_limits(project_id);
+  Ok(Json(serde_json::json!({"deleted": deleted})))
+}
+
 /// GET /api/projects/:id/members - List project members
 async fn api_list_project_members(
   State(state): State<AppState>,
@@ -3891,6 +6642,14 @@ async fn api_download_object(
   )
 }
 
+/// Map a multipart read error, keeping 413 for uploads over the body limit
+fn multipart_error(context: &'static str) -> impl Fn(MultipartError) -> AppError {
+  move |e| match e.status() {
+    StatusCode::PAYLOAD_TOO_LARGE => AppError::PayloadTooLarge(format!("{}: {}", context, e)),
+    _ => AppError::BadRequest(format!("{}: {}", context, e)),
+  }
+}
+
 async fn api_upload_object(
   State(state): State<AppState>,
   Path(bucket): Path<String>,
@@ -3901,7 +6660,7 @@ async fn api_upload_object(
   while let Some(field) = multipart
     .next_field()
     .await
-    .map_err(|e| AppError::BadRequest(format!("Failed to read multipart field: {}", e)))?
+    .map_err(multipart_error("Failed to read multipart field"))?
   {
     let name = field.name().unwrap_or("file").to_string();
     let filename = field.file_name().map(String::from);
@@ -3913,7 +6672,7 @@ async fn api_upload_object(
     let data = field
       .bytes()
       .await
-      .map_err(|e| AppError::BadRequest(format!("Failed to read file data: {}", e)))?;
+      .map_err(multipart_error("Failed to read file data"))?;
 
     // Use filename or field name as key
     let key = filename.unwrap_or_else(|| name.clone());
@@ -4092,6 +6851,29 @@ enum AppError {
   BadRequest(String),
   Unauthorized(String),
   Forbidden(String),
+  PayloadTooLarge(String),
+  TooManyRequests(String),
+  ServiceUnavailable(String),
+  Conflict(String),
+  InvalidQuery(String),
+}
+
+impl AppError {
+  /// Protocol error code for this error, shared with `ServerMessage::Error`
+  fn code(&self) -> ErrorCode {
+    match self {
+      Self::Internal(_) => ErrorCode::Internal,
+      Self::NotFound(_) => ErrorCode::NotFound,
+      Self::BadRequest(_) => ErrorCode::BadRequest,
+      Self::Unauthorized(_) => ErrorCode::Unauthorized,
+      Self::Forbidden(_) => ErrorCode::Forbidden,
+      Self::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+      Self::TooManyRequests(_) => ErrorCode::RateLimited,
+      Self::ServiceUnavailable(_) => ErrorCode::Unavailable,
+      Self::Conflict(_) => ErrorCode::Conflict,
+      Self::InvalidQuery(_) => ErrorCode::InvalidQuery,
+    }
+  }
 }
 
 impl From<anyhow::Error> for AppError {
@@ -4100,6 +6882,22 @@ impl From<anyhow::Error> for AppError {
   }
 }
 
+impl From<RateLimitError> for AppError {
+  fn from(e: RateLimitError) -> Self {
+    match e {
+      RateLimitError::PayloadTooLarge { .. } => Self::PayloadTooLarge(e.to_string()),
+      RateLimitError::DocumentTooDeep { .. } => Self::BadRequest(e.to_string()),
+      RateLimitError::RateLimited { .. }
+      | RateLimitError::UserRateLimited { .. }
+      | RateLimitError::ProjectRateLimited { .. }
+      | RateLimitError::ProjectQuotaExceeded { .. }
+      | RateLimitError::TooManyConnections { .. } => Self::TooManyRequests(e.to_string()),
+      RateLimitError::TooManyConnectionsTotal { .. } => Self::ServiceUnavailable(e.to_string()),
+      e => Self::Internal(e.into()),
+    }
+  }
+}
+
 impl From<serde_json::Error> for AppError {
   fn from(e: serde_json::Error) -> Self {
     Self::Internal(e.into())
@@ -4108,13 +6906,104 @@ impl From<serde_json::Error> for AppError {
 
 impl IntoResponse for AppError {
   fn into_response(self) -> Response {
+    let code = self.code();
     let (status, msg) = match self {
       Self::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
       Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
       Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
       Self::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
       Self::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+      Self::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg),
+      Self::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
+      Self::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+      Self::Conflict(msg) => (StatusCode::CONFLICT, msg),
+      Self::InvalidQuery(msg) => (StatusCode::BAD_REQUEST, msg),
+    };
+    let body = match current_trace_id() {
+      Some(trace_id) => serde_json::json!({ "error": msg, "code": code, "trace_id": trace_id }),
+      None => serde_json::json!({ "error": msg, "code": code }),
+    };
+    (status, Json(body)).into_response()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_document_etag_changes_with_revision() {
+    let mut doc = Document {
+      id: Uuid::new_v4(),
+      project_id: DEFAULT_PROJECT_ID,
+      collection: "users".to_string(),
+      data: serde_json::json!({"name": "Alice"}),
+      created_at: chrono::Utc::now(),
+      updated_at: chrono::Utc::now(),
+      deleted_at: None,
     };
-    (status, Json(serde_json::json!({ "error": msg }))).into_response()
+    let etag = document_etag(&doc);
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+    assert_eq!(etag, document_etag(&doc));
+
+    doc.data = serde_json::json!({"name": "Bob"});
+    assert_ne!(etag, document_etag(&doc));
+  }
+
+  #[tokio::test]
+  async fn test_app_error_body_has_code() {
+    let cases = [
+      (
+        AppError::NotFound("gone".into()),
+        StatusCode::NOT_FOUND,
+        "not_found",
+      ),
+      (
+        AppError::InvalidQuery("bad".into()),
+        StatusCode::BAD_REQUEST,
+        "invalid_query",
+      ),
+      (
+        AppError::TooManyRequests("slow down".into()),
+        StatusCode::TOO_MANY_REQUESTS,
+        "rate_limited",
+      ),
+      (
+        AppError::Internal(anyhow::anyhow!("boom")),
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "internal",
+      ),
+    ];
+    for (err, status, code) in cases {
+      let resp = err.into_response();
+      assert_eq!(resp.status(), status);
+      let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+      let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+      assert_eq!(body["code"], code);
+      assert!(body["error"].is_string());
+    }
+  }
+
+  #[test]
+  fn test_wants_pretty() {
+    assert!(wants_pretty(Some("pretty=true")));
+    assert!(wants_pretty(Some("limit=10&pretty")));
+    assert!(wants_pretty(Some("pretty=1&offset=5")));
+    assert!(!wants_pretty(Some("pretty=false")));
+    assert!(!wants_pretty(Some("prettyish=true")));
+    assert!(!wants_pretty(None));
+  }
+
+  #[test]
+  fn test_etag_matches() {
+    let etag = "\"abc\"";
+    assert!(etag_matches("\"abc\"", etag));
+    assert!(etag_matches("W/\"abc\"", etag));
+    assert!(etag_matches("\"x\", \"abc\"", etag));
+    assert!(etag_matches("*", etag));
+    assert!(!etag_matches("\"abcd\"", etag));
+    assert!(!etag_matches("", etag));
   }
 }
@@ -372,6 +372,24 @@ pub async fn run_query(query: &str) -> Result<serde_json::Value, String> {
   .await
 }
 
+/// Run a query and get `{data, schema}`, with field metadata inferred by the server
+#[cfg(feature = "csr")]
+pub async fn run_query_with_schema(query: &str) -> Result<serde_json::Value, String> {
+  #[derive(Serialize)]
+  struct QueryReq {
+    query: String,
+    schema: bool,
+  }
+  post_with_auth(
+    "/api/query",
+    &QueryReq {
+      query: query.to_string(),
+      schema: true,
+    },
+  )
+  .await
+}
+
 #[cfg(feature = "csr")]
 pub async fn create_table(name: &str) -> Result<serde_json::Value, String> {
   // Create table by inserting and deleting a dummy doc, or use a dedicated endpoint
@@ -533,7 +551,12 @@ pub async fn change_password(
 
 #[cfg(feature = "csr")]
 pub async fn fetch_admin_users() -> Result<Vec<AdminUserInfo>, String> {
-  fetch_with_auth("/api/users").await
+  #[derive(serde::Deserialize)]
+  struct UserPage {
+    users: Vec<AdminUserInfo>,
+  }
+  let page: UserPage = fetch_with_auth("/api/users").await?;
+  Ok(page.users)
 }
 
 #[cfg(feature = "csr")]
@@ -1041,6 +1064,7 @@ pub async fn fetch_backup_settings() -> Result<BackupSettings, String> {
 #[cfg(feature = "csr")]
 pub async fn update_backup_settings(
   interval: Option<u64>,
+  schedule: Option<String>,
   retention: Option<u32>,
   local_path: Option<String>,
   storage_path: Option<String>,
@@ -1050,6 +1074,8 @@ pub async fn update_backup_settings(
     #[serde(skip_serializing_if = "Option::is_none")]
     interval: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     retention: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     local_path: Option<String>,
@@ -1060,6 +1086,7 @@ pub async fn update_backup_settings(
     "/api/backup/settings",
     &UpdateReq {
       interval,
+      schedule,
       retention,
       local_path,
       storage_path,
@@ -1082,3 +1109,12 @@ pub async fn create_backup() -> Result<BackupInfo, String> {
 pub async fn delete_backup(id: &str) -> Result<serde_json::Value, String> {
   delete_with_auth(&format!("/api/backup/{}", id)).await
 }
+
+#[cfg(feature = "csr")]
+pub async fn restore_backup(id: &str, force: bool) -> Result<serde_json::Value, String> {
+  post_with_auth(
+    &format!("/api/backup/{}/restore", id),
+    &serde_json::json!({ "confirm": true, "force": force }),
+  )
+  .await
+}
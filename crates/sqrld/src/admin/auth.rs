@@ -2,27 +2,116 @@
 
 use argon2::{
   password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-  Argon2,
+  Algorithm, Argon2, Params, Version,
 };
+use scrypt::Scrypt;
 use sha2::{Digest, Sha256};
 
-/// Hash a password using Argon2id
-pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+use crate::server::{PasswordAlgorithm, PasswordHashingSection};
+
+/// Password hash stored for users who sign in through an external provider.
+/// It is not a valid hash, so password login never succeeds for them.
+pub const EXTERNAL_PASSWORD_HASH: &str = "!external";
+
+/// Hash a password with the configured algorithm and cost
+pub fn hash_password(
+  password: &str,
+  config: &PasswordHashingSection,
+) -> Result<String, anyhow::Error> {
   let salt = SaltString::generate(&mut OsRng);
-  let argon2 = Argon2::default();
-  let hash = argon2.hash_password(password.as_bytes(), &salt)?;
-  Ok(hash.to_string())
+  let hash = match config.algorithm {
+    PasswordAlgorithm::Argon2id => {
+      let params = Params::new(
+        config.argon2_memory_kib,
+        config.argon2_iterations,
+        config.argon2_parallelism,
+        None,
+      )
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+      Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .to_string()
+    }
+    PasswordAlgorithm::Bcrypt => bcrypt::hash(password, config.bcrypt_cost)?,
+    PasswordAlgorithm::Scrypt => {
+      let params = scrypt::Params::new(
+        config.scrypt_log_n,
+        config.scrypt_r,
+        config.scrypt_p,
+        scrypt::Params::RECOMMENDED_LEN,
+      )
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+      Scrypt
+        .hash_password_customized(password.as_bytes(), None, None, params, &salt)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .to_string()
+    }
+  };
+  Ok(hash)
 }
 
-/// Verify a password against an Argon2 hash
+/// Verify a password against an Argon2, bcrypt or scrypt hash, using the
+/// parameters stored in the hash
 pub fn verify_password(password: &str, hash: &str) -> bool {
+  if hash.starts_with("$2") {
+    return bcrypt::verify(password, hash).unwrap_or(false);
+  }
   let parsed_hash = match PasswordHash::new(hash) {
     Ok(h) => h,
     Err(_) => return false,
   };
-  Argon2::default()
-    .verify_password(password.as_bytes(), &parsed_hash)
-    .is_ok()
+  match parsed_hash.algorithm.as_str() {
+    "scrypt" => Scrypt
+      .verify_password(password.as_bytes(), &parsed_hash)
+      .is_ok(),
+    _ => Argon2::default()
+      .verify_password(password.as_bytes(), &parsed_hash)
+      .is_ok(),
+  }
+}
+
+/// Whether a stored hash uses another algorithm or weaker parameters than
+/// `config`, and should be replaced once the password is known
+pub fn needs_rehash(hash: &str, config: &PasswordHashingSection) -> bool {
+  match config.algorithm {
+    PasswordAlgorithm::Argon2id => phc_hash_weaker(
+      hash,
+      "argon2id",
+      &[
+        ("m", config.argon2_memory_kib),
+        ("t", config.argon2_iterations),
+        ("p", config.argon2_parallelism),
+      ],
+    ),
+    PasswordAlgorithm::Scrypt => phc_hash_weaker(
+      hash,
+      "scrypt",
+      &[
+        ("ln", u32::from(config.scrypt_log_n)),
+        ("r", config.scrypt_r),
+        ("p", config.scrypt_p),
+      ],
+    ),
+    // $2b$<cost>$<salt and hash>
+    PasswordAlgorithm::Bcrypt => hash
+      .strip_prefix("$2")
+      .and_then(|rest| rest.get(2..4))
+      .and_then(|cost| cost.parse::<u32>().ok())
+      .map_or(true, |cost| cost < config.bcrypt_cost),
+  }
+}
+
+/// Whether a PHC string hash uses another algorithm or any parameter below
+/// its minimum
+fn phc_hash_weaker(hash: &str, algorithm: &str, minimums: &[(&str, u32)]) -> bool {
+  let Ok(parsed_hash) = PasswordHash::new(hash) else {
+    return true;
+  };
+  parsed_hash.algorithm.as_str() != algorithm
+    || minimums
+      .iter()
+      .any(|&(name, min)| parsed_hash.params.get_decimal(name).unwrap_or(0) < min)
 }
 
 /// Generate a random session token
@@ -47,11 +136,49 @@ mod tests {
   #[test]
   fn test_password_hash_and_verify() {
     let password = "test_password_123!";
-    let hash = hash_password(password).unwrap();
+    let hash = hash_password(password, &PasswordHashingSection::default()).unwrap();
     assert!(verify_password(password, &hash));
     assert!(!verify_password("wrong_password", &hash));
   }
 
+  #[test]
+  fn test_password_algorithms_and_rehash() {
+    let password = "test_password_123!";
+    let weak = [
+      PasswordHashingSection {
+        argon2_memory_kib: 8192,
+        argon2_iterations: 1,
+        ..Default::default()
+      },
+      PasswordHashingSection {
+        algorithm: PasswordAlgorithm::Bcrypt,
+        bcrypt_cost: 4,
+        ..Default::default()
+      },
+      PasswordHashingSection {
+        algorithm: PasswordAlgorithm::Scrypt,
+        scrypt_log_n: 10,
+        ..Default::default()
+      },
+    ];
+    for config in &weak {
+      let hash = hash_password(password, config).unwrap();
+      assert!(verify_password(password, &hash));
+      assert!(!verify_password("wrong_password", &hash));
+      assert!(!needs_rehash(&hash, config));
+
+      // Raising the cost marks old hashes for rehashing; they still verify
+      let stronger = PasswordHashingSection {
+        algorithm: config.algorithm,
+        ..Default::default()
+      };
+      assert!(needs_rehash(&hash, &stronger));
+    }
+
+    let hash = hash_password(password, &weak[1]).unwrap();
+    assert!(needs_rehash(&hash, &PasswordHashingSection::default()));
+  }
+
   #[test]
   fn test_session_token() {
     let token = generate_session_token();
@@ -253,6 +253,7 @@ fn CreateUserForm(
             prop:value=role
             on:change=move |ev| set_role.set(event_target_value(&ev))
           >
+            <option value="member">"Member"</option>
             <option value="admin">"Admin"</option>
             <option value="owner">"Owner"</option>
           </select>
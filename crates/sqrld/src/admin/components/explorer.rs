@@ -14,6 +14,9 @@ pub fn Explorer() -> impl IntoView {
   let (results, set_results) = create_signal::<Option<String>>(None);
   let (running, set_running) = create_signal(false);
   let (result_count, set_result_count) = create_signal::<Option<usize>>(None);
+  // Columns inferred by the server across the whole result, and the rows
+  let (columns, set_columns) = create_signal::<Vec<String>>(Vec::new());
+  let (rows, set_rows) = create_signal::<Vec<serde_json::Value>>(Vec::new());
 
   let run_query = move |_| {
     let q = query.get().trim().to_string();
@@ -25,16 +28,31 @@ pub fn Explorer() -> impl IntoView {
     set_running.set(true);
     set_results.set(None);
     set_result_count.set(None);
+    set_columns.set(Vec::new());
+    set_rows.set(Vec::new());
     let state = state.clone();
 
     spawn_local(async move {
-      match apiclient::run_query(&q).await {
+      match apiclient::run_query_with_schema(&q).await {
         Ok(val) => {
+          let data = val.get("data").cloned().unwrap_or(val.clone());
+          let fields: Vec<String> = val["schema"]["fields"]
+            .as_array()
+            .map(|fields| {
+              fields
+                .iter()
+                .filter_map(|f| f["name"].as_str().map(String::from))
+                .collect()
+            })
+            .unwrap_or_default();
+
           // Count results if it's an array
-          let count = val.as_array().map(|arr| arr.len());
+          let count = data.as_array().map(|arr| arr.len());
           set_result_count.set(count);
+          set_columns.set(fields);
+          set_rows.set(data.as_array().cloned().unwrap_or_default());
 
-          let formatted = serde_json::to_string_pretty(&val).unwrap_or_else(|_| val.to_string());
+          let formatted = serde_json::to_string_pretty(&data).unwrap_or_else(|_| data.to_string());
           set_results.set(Some(formatted));
         }
         Err(e) => {
@@ -117,6 +135,8 @@ pub fn Explorer() -> impl IntoView {
                   set_query.set(String::new());
                   set_results.set(None);
                   set_result_count.set(None);
+                  set_columns.set(Vec::new());
+                  set_rows.set(Vec::new());
                 }
               >
                 <Icon name="x" size=14/>
@@ -135,6 +155,31 @@ pub fn Explorer() -> impl IntoView {
             </div>
             <div class="results-content">
               {move || match results.get() {
+                Some(_) if !columns.get().is_empty() => view! {
+                  <table class="data-table">
+                    <thead>
+                      <tr>
+                        <th>"id"</th>
+                        {columns.get().into_iter().map(|c| view! { <th>{c}</th> }).collect_view()}
+                      </tr>
+                    </thead>
+                    <tbody>
+                      {rows.get().into_iter().map(|row| {
+                        let cells = columns
+                          .get()
+                          .into_iter()
+                          .map(|c| view! { <td>{cell_text(&row["data"][c.as_str()])}</td> })
+                          .collect_view();
+                        view! {
+                          <tr>
+                            <td>{cell_text(&row["id"])}</td>
+                            {cells}
+                          </tr>
+                        }
+                      }).collect_view()}
+                    </tbody>
+                  </table>
+                }.into_view(),
                 Some(r) => view! { <pre class="results-json">{r}</pre> }.into_view(),
                 None => view! {
                   <div class="results-placeholder">
@@ -150,3 +195,12 @@ pub fn Explorer() -> impl IntoView {
     </section>
   }
 }
+
+/// Table cell text for a field value; missing fields and nulls render empty
+fn cell_text(value: &serde_json::Value) -> String {
+  match value {
+    serde_json::Value::Null => String::new(),
+    serde_json::Value::String(s) => s.clone(),
+    other => other.to_string(),
+  }
+}
@@ -581,7 +581,8 @@ pub fn GeneralSettings() -> impl IntoView {
                 <span class="backup-info-value">
                   {move || {
                     if backup_settings.get().storage_enabled {
-                      format!("S3: /{}", backup_settings.get().storage_path)
+                      let settings = backup_settings.get();
+                      format!("S3: {}/{}", settings.storage_bucket, settings.storage_path)
                     } else {
                       backup_settings.get().local_path.clone()
                     }
@@ -3,6 +3,10 @@
 mod api;
 #[cfg(feature = "server")]
 mod auth;
+#[cfg(feature = "server")]
+mod notify;
+#[cfg(feature = "server")]
+mod oidc;
 
 // CSR components (only compiled for WASM)
 #[cfg(feature = "csr")]
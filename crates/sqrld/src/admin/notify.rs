@@ -0,0 +1,82 @@
+//! Delivery of password reset and email verification tokens.
+//!
+//! SquirrelDB has no mailer. Each token is handed to the hooks configured in
+//! `auth.notifications` instead: the server log and/or a webhook that
+//! forwards it to the user by whatever channel the operator runs.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::{AdminTokenPurpose, AdminUser};
+use crate::server::AuthNotificationsSection;
+use crate::webhooks::{sign_payload, SIGNATURE_HEADER};
+
+/// How long a notification webhook may take to respond
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body POSTed to the notification webhook
+#[derive(Debug, Serialize)]
+pub struct AccountNotification<'a> {
+  pub event: AdminTokenPurpose,
+  pub user_id: Uuid,
+  pub username: &'a str,
+  pub email: Option<&'a str>,
+  pub token: &'a str,
+  pub expires_at: DateTime<Utc>,
+}
+
+impl<'a> AccountNotification<'a> {
+  pub fn new(
+    event: AdminTokenPurpose,
+    user: &'a AdminUser,
+    token: &'a str,
+    expires_at: DateTime<Utc>,
+  ) -> Self {
+    Self {
+      event,
+      user_id: user.id,
+      username: &user.username,
+      email: user.email.as_deref(),
+      token,
+      expires_at,
+    }
+  }
+}
+
+/// Send a token through every configured hook
+pub async fn notify(
+  config: &AuthNotificationsSection,
+  notification: &AccountNotification<'_>,
+) -> Result<(), anyhow::Error> {
+  if !config.is_configured() {
+    anyhow::bail!("No auth notification hook is configured");
+  }
+  if config.log {
+    tracing::info!(
+      "{} token for '{}': {} (expires {})",
+      notification.event.as_str(),
+      notification.username,
+      notification.token,
+      notification.expires_at.to_rfc3339()
+    );
+  }
+  if let Some(url) = &config.webhook_url {
+    let body = serde_json::to_vec(notification)?;
+    let mut request = reqwest::Client::new()
+      .post(url)
+      .timeout(WEBHOOK_TIMEOUT)
+      .header(reqwest::header::CONTENT_TYPE, "application/json")
+      .header("X-Squirrel-Event", notification.event.as_str());
+    if let Some(secret) = &config.webhook_secret {
+      request = request.header(SIGNATURE_HEADER, sign_payload(secret, &body));
+    }
+    let response = request.body(body).send().await?;
+    if !response.status().is_success() {
+      anyhow::bail!("Notification webhook returned HTTP {}", response.status());
+    }
+  }
+  Ok(())
+}
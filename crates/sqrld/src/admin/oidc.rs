@@ -0,0 +1,208 @@
+//! External sign-in with OpenID Connect.
+//!
+//! A JWT issued by the configured provider is accepted by the admin API in
+//! place of a session. Its signature is checked against the provider's JSON
+//! Web Key Set, which is cached and refetched when it expires or when a token
+//! names a key it doesn't contain (the provider rotated its keys).
+//!
+//! Identities map to admin users by issuer and subject, which the provider
+//! never reassigns. The username claim only names users when they are
+//! provisioned.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use parking_lot::RwLock;
+use serde_json::{Map, Value};
+
+use crate::db::AdminRole;
+use crate::server::OidcSection;
+
+/// How long fetching the key set may take
+const JWKS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Minimum time between refetches caused by unknown key IDs, so forged
+/// tokens can't make the server hammer the provider
+const MIN_JWKS_REFETCH: Duration = Duration::from_secs(60);
+
+/// Whether a bearer token is shaped like a JWT (three dot-separated parts)
+pub fn is_jwt(token: &str) -> bool {
+  token.split('.').count() == 3
+}
+
+/// Who a verified token belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcIdentity {
+  /// `iss` claim, the configured issuer
+  pub issuer: String,
+  /// `sub` claim, stable for the account at the provider
+  pub subject: String,
+  /// Lowercased value of the username claim
+  pub username: String,
+  pub email: Option<String>,
+}
+
+/// Verifies provider-issued JWTs
+pub struct OidcVerifier {
+  config: OidcSection,
+  default_role: AdminRole,
+  client: reqwest::Client,
+  /// Last fetched key set and when it was fetched
+  keys: RwLock<Option<(Instant, Arc<JwkSet>)>>,
+}
+
+impl OidcVerifier {
+  pub fn new(config: OidcSection) -> Result<Self, anyhow::Error> {
+    if config.issuer.is_empty() || config.jwks_url.is_empty() {
+      anyhow::bail!("auth.oidc needs both issuer and jwks_url");
+    }
+    if config.audience.as_deref().unwrap_or_default().is_empty() {
+      anyhow::bail!("auth.oidc needs an audience, the client ID tokens are issued to");
+    }
+    let default_role = config
+      .default_role
+      .parse()
+      .map_err(|e| anyhow::anyhow!("Invalid auth.oidc.default_role: {}", e))?;
+    Ok(Self {
+      config,
+      default_role,
+      client: reqwest::Client::new(),
+      keys: RwLock::new(None),
+    })
+  }
+
+  /// Create admin users for unknown identities on first sign-in
+  pub fn auto_provision(&self) -> bool {
+    self.config.auto_provision
+  }
+
+  /// Role of auto-provisioned users
+  pub fn default_role(&self) -> AdminRole {
+    self.default_role
+  }
+
+  /// Check a token's signature, issuer, audience and expiry, and read the
+  /// identity from its claims
+  pub async fn verify(&self, token: &str) -> Result<OidcIdentity, anyhow::Error> {
+    let header = decode_header(token)?;
+    if matches!(
+      header.alg,
+      Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+      anyhow::bail!("Symmetric signing algorithms are not accepted");
+    }
+    let key = self.decoding_key(header.kid.as_deref()).await?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&self.config.issuer]);
+    validation.set_audience(&[self.config.audience.as_deref().unwrap_or_default()]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    let claims = decode::<Map<String, Value>>(token, &key, &validation)?.claims;
+
+    let subject = claims
+      .get("sub")
+      .and_then(Value::as_str)
+      .filter(|s| !s.is_empty())
+      .ok_or_else(|| anyhow::anyhow!("Token has no 'sub' claim"))?
+      .to_string();
+
+    let username = claims
+      .get(&self.config.username_claim)
+      .and_then(Value::as_str)
+      .map(|s| s.trim().to_lowercase())
+      .filter(|s| !s.is_empty())
+      .ok_or_else(|| anyhow::anyhow!("Token has no '{}' claim", self.config.username_claim))?;
+    let email = claims
+      .get(&self.config.email_claim)
+      .and_then(Value::as_str)
+      .map(str::to_string);
+    Ok(OidcIdentity {
+      issuer: self.config.issuer.clone(),
+      subject,
+      username,
+      email,
+    })
+  }
+
+  /// Key for `kid`, from the cache when it is fresh and has the key
+  async fn decoding_key(&self, kid: Option<&str>) -> Result<DecodingKey, anyhow::Error> {
+    let cached = self.keys.read().clone();
+    if let Some((fetched_at, keys)) = cached {
+      let age = fetched_at.elapsed();
+      match find_key(&keys, kid) {
+        Some(jwk) if age < Duration::from_secs(self.config.jwks_cache_secs) => {
+          return Ok(DecodingKey::from_jwk(jwk)?);
+        }
+        None if age < MIN_JWKS_REFETCH => anyhow::bail!("Unknown signing key"),
+        _ => {}
+      }
+    }
+
+    let body = self
+      .client
+      .get(&self.config.jwks_url)
+      .timeout(JWKS_TIMEOUT)
+      .send()
+      .await?
+      .error_for_status()?
+      .bytes()
+      .await?;
+    let keys: Arc<JwkSet> = Arc::new(serde_json::from_slice(&body)?);
+    *self.keys.write() = Some((Instant::now(), keys.clone()));
+    let jwk = find_key(&keys, kid).ok_or_else(|| anyhow::anyhow!("Unknown signing key"))?;
+    Ok(DecodingKey::from_jwk(jwk)?)
+  }
+}
+
+/// The key named by `kid`, or the only key when the token names none
+fn find_key<'a>(keys: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+  match kid {
+    Some(kid) => keys.find(kid),
+    None if keys.keys.len() == 1 => keys.keys.first(),
+    None => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_is_jwt() {
+    assert!(is_jwt("eyJhbGciOiJSUzI1NiJ9.eyJzdWIiOiIxIn0.c2ln"));
+    assert!(!is_jwt("sqrl_0123456789abcdef"));
+    assert!(!is_jwt("session_0123456789abcdef"));
+  }
+
+  #[test]
+  fn test_verifier_config() {
+    let config = OidcSection {
+      enabled: true,
+      issuer: "https://id.example.com".into(),
+      jwks_url: "https://id.example.com/.well-known/jwks.json".into(),
+      audience: Some("squirreldb".into()),
+      ..Default::default()
+    };
+    let verifier = OidcVerifier::new(config.clone()).unwrap();
+    assert_eq!(verifier.default_role(), AdminRole::Member);
+    assert!(!verifier.auto_provision());
+
+    assert!(OidcVerifier::new(OidcSection {
+      audience: None,
+      ..config.clone()
+    })
+    .is_err());
+    assert!(OidcVerifier::new(OidcSection {
+      default_role: "superuser".into(),
+      ..config.clone()
+    })
+    .is_err());
+    assert!(OidcVerifier::new(OidcSection {
+      jwks_url: String::new(),
+      ..config
+    })
+    .is_err());
+  }
+}
@@ -201,9 +201,21 @@ pub struct CacheStats {
 pub struct BackupSettings {
   pub enabled: bool,
   pub interval: u64,
+  #[serde(default)]
+  pub schedule: Option<String>,
   pub retention: u32,
   pub local_path: String,
+  #[serde(default = "default_storage_bucket")]
+  pub storage_bucket: String,
   pub storage_path: String,
+  #[serde(default)]
+  pub incremental: bool,
+  #[serde(default = "default_full_every")]
+  pub full_every: u32,
+  #[serde(default = "default_compression")]
+  pub compression: String,
+  #[serde(default)]
+  pub encrypted: bool,
   pub last_backup: Option<String>,
   pub next_backup: Option<String>,
   pub storage_enabled: bool,
@@ -214,9 +226,15 @@ impl Default for BackupSettings {
     Self {
       enabled: false,
       interval: 3600,
+      schedule: None,
       retention: 7,
       local_path: "./backup".to_string(),
+      storage_bucket: default_storage_bucket(),
       storage_path: "backups".to_string(),
+      incremental: false,
+      full_every: default_full_every(),
+      compression: default_compression(),
+      encrypted: false,
       last_backup: None,
       next_backup: None,
       storage_enabled: false,
@@ -224,6 +242,18 @@ impl Default for BackupSettings {
   }
 }
 
+fn default_storage_bucket() -> String {
+  "backups".to_string()
+}
+
+fn default_full_every() -> u32 {
+  24
+}
+
+fn default_compression() -> String {
+  "none".to_string()
+}
+
 /// Backup info for listing
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BackupInfo {
@@ -233,6 +263,12 @@ pub struct BackupInfo {
   pub created_at: String,
   pub backend: String,
   pub location: String,
+  #[serde(default)]
+  pub kind: String,
+  #[serde(default)]
+  pub compression: String,
+  #[serde(default)]
+  pub encrypted: bool,
 }
 
 /// API token info
@@ -0,0 +1,417 @@
+//! Portable archives of a whole database, for moving between backends
+//!
+//! An archive is a zstd-compressed stream of JSON lines: a header, then
+//! admin users, projects, API tokens, soft-delete settings and documents, in
+//! that order. Nothing in it depends on the SQL dialect, so an archive
+//! exported from PostgreSQL imports into SQLite and the other way around.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::codec::ZSTD_LEVEL;
+use super::restore::{database_is_empty, project_ids};
+use crate::db::{AdminRole, AdminUser, DatabaseBackend, ReadOptions, SoftDeleteSettings};
+use crate::types::{Document, Project, DEFAULT_PROJECT_ID};
+
+/// Identifies the first line of an archive
+const ARCHIVE_FORMAT: &str = "squirreldb-archive";
+/// Newest archive version this build reads and the one it writes
+const ARCHIVE_VERSION: u32 = 1;
+/// Documents between progress log lines
+const PROGRESS_INTERVAL: usize = 10_000;
+
+/// What an export wrote or an import applied
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArchiveSummary {
+  pub projects: usize,
+  pub collections: usize,
+  pub documents: usize,
+  pub users: usize,
+  pub tokens: usize,
+}
+
+/// One line of an archive
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ArchiveRecord {
+  Header {
+    format: String,
+    version: u32,
+    created_at: DateTime<Utc>,
+  },
+  User {
+    user: AdminUser,
+    password_hash: String,
+  },
+  Project {
+    project: Project,
+  },
+  Token {
+    project_id: Uuid,
+    name: String,
+    token_hash: String,
+  },
+  SoftDelete {
+    project_id: Uuid,
+    collection: String,
+    settings: SoftDeleteSettings,
+  },
+  Document {
+    document: Document,
+  },
+}
+
+fn write_record(out: &mut impl Write, record: &ArchiveRecord) -> Result<(), anyhow::Error> {
+  serde_json::to_writer(&mut *out, record)?;
+  out.write_all(b"\n")?;
+  Ok(())
+}
+
+/// Write every project, collection and document to `out`. Soft-deleted
+/// documents are included. Admin users and API tokens are only written with
+/// `include_auth`, as they carry password and token hashes.
+pub async fn export_archive<W: Write>(
+  backend: &Arc<dyn DatabaseBackend>,
+  out: W,
+  include_auth: bool,
+) -> Result<ArchiveSummary, anyhow::Error> {
+  let mut out = zstd::Encoder::new(out, ZSTD_LEVEL)?;
+  let mut summary = ArchiveSummary::default();
+
+  write_record(
+    &mut out,
+    &ArchiveRecord::Header {
+      format: ARCHIVE_FORMAT.to_string(),
+      version: ARCHIVE_VERSION,
+      created_at: Utc::now(),
+    },
+  )?;
+
+  if include_auth {
+    for user in backend.list_admin_users().await? {
+      let Some((user, password_hash)) = backend.get_admin_user_by_username(&user.username).await?
+      else {
+        continue;
+      };
+      write_record(
+        &mut out,
+        &ArchiveRecord::User {
+          user,
+          password_hash,
+        },
+      )?;
+      summary.users += 1;
+    }
+  }
+
+  let projects = project_ids(backend).await?;
+  for project in backend.list_projects().await? {
+    write_record(&mut out, &ArchiveRecord::Project { project })?;
+  }
+  summary.projects = projects.len();
+
+  if include_auth {
+    for project_id in &projects {
+      for (token, token_hash) in backend.list_token_hashes(*project_id).await? {
+        write_record(
+          &mut out,
+          &ArchiveRecord::Token {
+            project_id: token.project_id,
+            name: token.name,
+            token_hash,
+          },
+        )?;
+        summary.tokens += 1;
+      }
+    }
+  }
+
+  for (project_id, collection, settings) in backend.list_soft_delete().await? {
+    write_record(
+      &mut out,
+      &ArchiveRecord::SoftDelete {
+        project_id,
+        collection,
+        settings,
+      },
+    )?;
+  }
+
+  let with_deleted = ReadOptions { with_deleted: true };
+  for project_id in &projects {
+    for collection in backend.list_collections(*project_id).await? {
+      let documents = backend
+        .list_with_options(
+          *project_id,
+          &collection,
+          None,
+          None,
+          &[],
+          &[],
+          None,
+          None,
+          with_deleted,
+        )
+        .await?;
+      summary.collections += 1;
+      for document in documents {
+        write_record(&mut out, &ArchiveRecord::Document { document })?;
+        summary.documents += 1;
+        if summary.documents % PROGRESS_INTERVAL == 0 {
+          tracing::info!("Exported {} documents", summary.documents);
+        }
+      }
+    }
+  }
+
+  out.finish()?.flush()?;
+  Ok(summary)
+}
+
+/// Load an archive written by [`export_archive`].
+///
+/// Refuses a non-empty database unless `force` is set, in which case
+/// documents with the same ID are replaced and all others are kept. Projects,
+/// users and tokens that already exist (by name) are reused. On backends
+/// without project management, documents keep their original project ID.
+pub async fn import_archive<R: Read>(
+  backend: &Arc<dyn DatabaseBackend>,
+  input: R,
+  force: bool,
+) -> Result<ArchiveSummary, anyhow::Error> {
+  import_records(backend, input, force)
+    .await
+    .map(|(summary, _)| summary)
+}
+
+/// Import an archive, also returning the projects its documents went into
+async fn import_records<R: Read>(
+  backend: &Arc<dyn DatabaseBackend>,
+  input: R,
+  force: bool,
+) -> Result<(ArchiveSummary, HashSet<Uuid>), anyhow::Error> {
+  let mut lines = BufReader::new(zstd::Decoder::new(input)?).lines();
+
+  let header = lines
+    .next()
+    .transpose()?
+    .ok_or_else(|| anyhow::anyhow!("Archive is empty"))?;
+  match serde_json::from_str(&header) {
+    Ok(ArchiveRecord::Header {
+      format, version, ..
+    }) if format == ARCHIVE_FORMAT => {
+      if version > ARCHIVE_VERSION {
+        anyhow::bail!(
+          "Archive version {} is newer than this server supports ({})",
+          version,
+          ARCHIVE_VERSION
+        );
+      }
+    }
+    _ => anyhow::bail!("Not a SquirrelDB archive"),
+  }
+
+  if !force && !database_is_empty(backend).await? {
+    anyhow::bail!("Database is not empty; use force to import anyway");
+  }
+
+  let mut summary = ArchiveSummary::default();
+  // Old ID -> ID in this database
+  let mut users: HashMap<Uuid, Uuid> = HashMap::new();
+  let mut projects: HashMap<Uuid, Uuid> = HashMap::new();
+  let mut collections = HashSet::new();
+  let mut users_supported = true;
+
+  for (n, line) in lines.enumerate() {
+    let line = line?;
+    if line.is_empty() {
+      continue;
+    }
+    let record: ArchiveRecord = serde_json::from_str(&line)
+      .map_err(|e| anyhow::anyhow!("Invalid archive record on line {}: {}", n + 2, e))?;
+    match record {
+      ArchiveRecord::Header { .. } => anyhow::bail!("Unexpected header on line {}", n + 2),
+      ArchiveRecord::User {
+        user,
+        password_hash,
+      } => {
+        if !users_supported {
+          continue;
+        }
+        if let Some((existing, _)) = backend.get_admin_user_by_username(&user.username).await? {
+          users.insert(user.id, existing.id);
+          continue;
+        }
+        match backend
+          .create_admin_user(
+            &user.username,
+            user.email.as_deref(),
+            &password_hash,
+            user.role,
+          )
+          .await
+        {
+          Ok(created) => {
+            if user.email_verified {
+              backend.set_admin_user_email_verified(created.id).await?;
+            }
+            users.insert(user.id, created.id);
+            summary.users += 1;
+          }
+          Err(e) => {
+            tracing::warn!("Skipping admin users: {}", e);
+            users_supported = false;
+          }
+        }
+      }
+      ArchiveRecord::Project { project } => {
+        let id = import_project(backend, &project, &users).await?;
+        projects.insert(project.id, id);
+        summary.projects += 1;
+      }
+      ArchiveRecord::Token {
+        project_id,
+        name,
+        token_hash,
+      } => {
+        let project_id = projects.get(&project_id).copied().unwrap_or(project_id);
+        match backend.create_token(project_id, &name, &token_hash).await {
+          Ok(_) => summary.tokens += 1,
+          Err(e) => tracing::warn!("Skipping API token '{}': {}", name, e),
+        }
+      }
+      ArchiveRecord::SoftDelete {
+        project_id,
+        collection,
+        settings,
+      } => {
+        let project_id = projects.get(&project_id).copied().unwrap_or(project_id);
+        backend
+          .set_soft_delete(project_id, &collection, settings)
+          .await?;
+      }
+      ArchiveRecord::Document { mut document } => {
+        if let Some(id) = projects.get(&document.project_id) {
+          document.project_id = *id;
+        }
+        backend.restore_document(&document).await?;
+        collections.insert((document.project_id, document.collection));
+        summary.documents += 1;
+        if summary.documents % PROGRESS_INTERVAL == 0 {
+          tracing::info!("Imported {} documents", summary.documents);
+        }
+      }
+    }
+  }
+
+  summary.collections = collections.len();
+  let projects = collections.into_iter().map(|(p, _)| p).collect();
+  Ok((summary, projects))
+}
+
+/// Copy everything from `source` into the empty database `target` through a
+/// temporary archive, users and API tokens included. Document IDs and
+/// timestamps are kept. Fails if the document count in `target` afterwards
+/// doesn't match what was read from `source`.
+pub async fn migrate_backend(
+  source: &Arc<dyn DatabaseBackend>,
+  target: &Arc<dyn DatabaseBackend>,
+) -> Result<ArchiveSummary, anyhow::Error> {
+  let path = std::env::temp_dir().join(format!("sqrld-migrate-{}.sqrl", Uuid::new_v4()));
+  let result = migrate_via(source, target, &path).await;
+  let _ = std::fs::remove_file(&path);
+  result
+}
+
+async fn migrate_via(
+  source: &Arc<dyn DatabaseBackend>,
+  target: &Arc<dyn DatabaseBackend>,
+  path: &Path,
+) -> Result<ArchiveSummary, anyhow::Error> {
+  let file = BufWriter::new(File::create(path)?);
+  let exported = export_archive(source, file, true).await?;
+  tracing::info!(
+    "Read {} documents in {} collections from the source",
+    exported.documents,
+    exported.collections
+  );
+
+  let (imported, projects) = import_records(target, File::open(path)?, false).await?;
+  let mut stored = 0;
+  for project_id in projects {
+    stored += target.count_project_documents(project_id).await?;
+  }
+  if imported.documents != exported.documents || stored != exported.documents as u64 {
+    anyhow::bail!(
+      "Document counts don't match: {} exported, {} imported, {} in the target",
+      exported.documents,
+      imported.documents,
+      stored
+    );
+  }
+  if imported.tokens != exported.tokens {
+    tracing::warn!(
+      "{} of {} API tokens were imported",
+      imported.tokens,
+      exported.tokens
+    );
+  }
+  if imported.users != exported.users {
+    tracing::warn!(
+      "{} of {} admin users were imported",
+      imported.users,
+      exported.users
+    );
+  }
+  Ok(imported)
+}
+
+/// ID of the project in this database: an existing project with the same
+/// name, or a newly created one
+async fn import_project(
+  backend: &Arc<dyn DatabaseBackend>,
+  project: &Project,
+  users: &HashMap<Uuid, Uuid>,
+) -> Result<Uuid, anyhow::Error> {
+  if project.id == DEFAULT_PROJECT_ID {
+    return Ok(DEFAULT_PROJECT_ID);
+  }
+  if let Some(existing) = backend.get_project_by_name(&project.name).await? {
+    return Ok(existing.id);
+  }
+
+  let owner = match users.get(&project.owner_id) {
+    Some(id) => Some(*id),
+    None => backend
+      .list_admin_users()
+      .await?
+      .into_iter()
+      .find(|u| u.role == AdminRole::Owner)
+      .map(|u| u.id),
+  };
+  let created = match owner {
+    Some(owner) => backend
+      .create_project(&project.name, project.description.as_deref(), owner)
+      .await
+      .map_err(|e| e.to_string()),
+    None => Err("no owner account".to_string()),
+  };
+  match created {
+    Ok(created) => Ok(created.id),
+    Err(e) => {
+      tracing::warn!(
+        "Project '{}' was not created ({}); its documents keep project ID {}",
+        project.name,
+        e,
+        project.id
+      );
+      Ok(project.id)
+    }
+  }
+}
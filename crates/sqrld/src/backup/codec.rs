@@ -0,0 +1,189 @@
+//! Backup file compression and encryption
+//!
+//! Backups are compressed first and then encrypted. Reading detects both from
+//! the file contents, so restores work regardless of the current settings.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use std::io::{Read, Write};
+
+use crate::server::{BackupCompression, BackupSection};
+
+/// Prefix of encrypted backup files, followed by a 12-byte nonce
+const ENCRYPTION_MAGIC: &[u8] = b"SQRLENC1";
+const NONCE_LEN: usize = 12;
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Zstandard compression level
+pub(super) const ZSTD_LEVEL: i32 = 3;
+
+/// Key used to encrypt backup files
+pub type BackupKey = [u8; 32];
+
+impl BackupCompression {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::None => "none",
+      Self::Gzip => "gzip",
+      Self::Zstd => "zstd",
+    }
+  }
+
+  /// Filename suffix added after `.sql`
+  pub fn extension(self) -> &'static str {
+    match self {
+      Self::None => "",
+      Self::Gzip => ".gz",
+      Self::Zstd => ".zst",
+    }
+  }
+
+  /// Determine the compression from a backup filename
+  pub fn from_filename(filename: &str) -> Self {
+    let name = filename
+      .strip_suffix(ENCRYPTED_EXTENSION)
+      .unwrap_or(filename);
+    if name.ends_with(".gz") {
+      Self::Gzip
+    } else if name.ends_with(".zst") {
+      Self::Zstd
+    } else {
+      Self::None
+    }
+  }
+}
+
+impl std::str::FromStr for BackupCompression {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "none" | "" => Ok(Self::None),
+      "gzip" | "gz" => Ok(Self::Gzip),
+      "zstd" | "zst" => Ok(Self::Zstd),
+      other => Err(anyhow::anyhow!(
+        "Unknown backup compression '{}' (expected none, gzip or zstd)",
+        other
+      )),
+    }
+  }
+}
+
+/// Filename suffix for encrypted backups
+pub const ENCRYPTED_EXTENSION: &str = ".enc";
+
+/// Full filename extension for a backup, e.g. `.sql.gz.enc`
+pub fn backup_extension(compression: BackupCompression, encrypted: bool) -> String {
+  format!(
+    ".sql{}{}",
+    compression.extension(),
+    if encrypted { ENCRYPTED_EXTENSION } else { "" }
+  )
+}
+
+/// Check whether a filename looks like a backup file
+pub fn is_backup_file(filename: &str) -> bool {
+  filename.starts_with("squirreldb_") && filename.contains(".sql")
+}
+
+/// Check whether a backup filename is encrypted
+pub fn is_encrypted(filename: &str) -> bool {
+  filename.ends_with(ENCRYPTED_EXTENSION)
+}
+
+/// Backup ID from a filename (the part after the timestamp)
+pub fn backup_id_from_filename(filename: &str) -> String {
+  let stem = filename.split('.').next().unwrap_or(filename);
+  stem.split('_').next_back().unwrap_or("unknown").to_string()
+}
+
+/// Parse a hex-encoded 256-bit encryption key
+pub fn parse_encryption_key(hex_key: &str) -> Result<BackupKey, anyhow::Error> {
+  let bytes = hex::decode(hex_key.trim())
+    .map_err(|_| anyhow::anyhow!("Backup encryption key must be hex-encoded"))?;
+  bytes
+    .try_into()
+    .map_err(|_| anyhow::anyhow!("Backup encryption key must be 32 bytes (64 hex characters)"))
+}
+
+/// Encryption key from backup settings, if configured
+pub fn encryption_key(section: &BackupSection) -> Result<Option<BackupKey>, anyhow::Error> {
+  match section.encryption_key.as_deref().map(str::trim) {
+    Some(key) if !key.is_empty() => parse_encryption_key(key).map(Some),
+    _ => Ok(None),
+  }
+}
+
+/// Compress and optionally encrypt backup SQL
+pub fn encode_backup(
+  sql: &str,
+  compression: BackupCompression,
+  key: Option<&BackupKey>,
+) -> Result<Vec<u8>, anyhow::Error> {
+  let data = match compression {
+    BackupCompression::None => sql.as_bytes().to_vec(),
+    BackupCompression::Gzip => {
+      let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+      encoder.write_all(sql.as_bytes())?;
+      encoder.finish()?
+    }
+    BackupCompression::Zstd => zstd::encode_all(sql.as_bytes(), ZSTD_LEVEL)?,
+  };
+
+  let Some(key) = key else {
+    return Ok(data);
+  };
+
+  let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+  let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+  let ciphertext = cipher
+    .encrypt(&nonce, data.as_slice())
+    .map_err(|_| anyhow::anyhow!("Failed to encrypt backup"))?;
+
+  let mut out = Vec::with_capacity(ENCRYPTION_MAGIC.len() + NONCE_LEN + ciphertext.len());
+  out.extend_from_slice(ENCRYPTION_MAGIC);
+  out.extend_from_slice(&nonce);
+  out.extend_from_slice(&ciphertext);
+  Ok(out)
+}
+
+/// Decrypt and decompress a backup file, detecting both from its contents
+pub fn decode_backup(data: &[u8], key: Option<&BackupKey>) -> Result<String, anyhow::Error> {
+  let decrypted;
+  let data = if let Some(rest) = data.strip_prefix(ENCRYPTION_MAGIC) {
+    let key = key.ok_or_else(|| {
+      anyhow::anyhow!("Backup is encrypted but no backup encryption key is configured")
+    })?;
+    if rest.len() < NONCE_LEN {
+      anyhow::bail!("Encrypted backup is truncated");
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    decrypted = cipher
+      .decrypt(Nonce::from_slice(nonce), ciphertext)
+      .map_err(|_| anyhow::anyhow!("Failed to decrypt backup (wrong key or corrupted file)"))?;
+    decrypted.as_slice()
+  } else {
+    data
+  };
+
+  let mut sql = String::new();
+  if data.starts_with(GZIP_MAGIC) {
+    flate2::read::GzDecoder::new(data).read_to_string(&mut sql)?;
+  } else if data.starts_with(ZSTD_MAGIC) {
+    sql = String::from_utf8(zstd::decode_all(data)?)?;
+  } else {
+    sql = String::from_utf8(data.to_vec())?;
+  }
+  Ok(sql)
+}
+
+/// Read and decode a backup file
+pub async fn read_backup_file(
+  path: &std::path::Path,
+  key: Option<&BackupKey>,
+) -> Result<String, anyhow::Error> {
+  let data = tokio::fs::read(path).await?;
+  decode_backup(&data, key)
+}
@@ -0,0 +1,323 @@
+//! Incremental backups built from the change queue
+//!
+//! An incremental backup holds the current state of every document touched
+//! since the previous backup's change ID: an `INSERT` for documents that still
+//! exist and a `DELETE` for ones that were removed. Restoring replays the
+//! latest full baseline and then each incremental in order.
+
+use chrono::Utc;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::codec::{is_backup_file, read_backup_file, BackupKey};
+use crate::db::DatabaseBackend;
+use crate::server::{BackupSection, ServerConfig};
+
+/// Number of change queue entries fetched per page
+const CHANGE_PAGE_SIZE: usize = 1000;
+
+/// Whether a backup is a full dump or a delta on top of one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupKind {
+  Full,
+  Incremental,
+}
+
+impl BackupKind {
+  /// Filename prefix for this kind of backup
+  pub fn filename_prefix(self) -> &'static str {
+    match self {
+      Self::Full => "squirreldb_backup_",
+      Self::Incremental => "squirreldb_incremental_",
+    }
+  }
+
+  /// Determine the kind from a backup filename
+  pub fn from_filename(filename: &str) -> Self {
+    if filename.starts_with(Self::Incremental.filename_prefix()) {
+      Self::Incremental
+    } else {
+      Self::Full
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::Full => "full",
+      Self::Incremental => "incremental",
+    }
+  }
+}
+
+/// Metadata from the comment header of a backup file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupHeader {
+  pub kind: BackupKind,
+  /// Change ID the delta starts after (incremental backups only)
+  pub since_change_id: Option<i64>,
+  /// Highest change ID covered by the backup
+  pub change_id: Option<i64>,
+}
+
+/// Parse the comment header of a backup file.
+///
+/// Backups written before incremental support have no change IDs and are
+/// treated as full backups.
+pub fn parse_backup_header(sql: &str) -> BackupHeader {
+  let mut header = BackupHeader {
+    kind: BackupKind::Full,
+    since_change_id: None,
+    change_id: None,
+  };
+
+  for line in sql.lines() {
+    let line = line.trim();
+    if line.is_empty() {
+      break;
+    }
+    if line == "-- SquirrelDB Incremental Backup" {
+      header.kind = BackupKind::Incremental;
+    } else if let Some(id) = line.strip_prefix("-- Since-Change-Id: ") {
+      header.since_change_id = id.trim().parse().ok();
+    } else if let Some(id) = line.strip_prefix("-- Change-Id: ") {
+      header.change_id = id.trim().parse().ok();
+    }
+  }
+
+  header
+}
+
+/// Position in the backup chain, used to decide the kind of the next backup
+#[derive(Debug, Clone, Default)]
+pub struct BackupChain {
+  /// Highest change ID covered by the latest backup
+  pub last_change_id: Option<i64>,
+  /// Incremental backups written since the latest full baseline
+  pub since_full: u32,
+  loaded: bool,
+}
+
+impl BackupChain {
+  /// Record a completed backup
+  pub fn record(&mut self, kind: BackupKind, change_id: i64) {
+    self.last_change_id = Some(change_id);
+    self.since_full = match kind {
+      BackupKind::Full => 0,
+      BackupKind::Incremental => self.since_full + 1,
+    };
+    self.loaded = true;
+  }
+
+  /// Whether the chain was recorded or picked up from existing backups
+  pub fn is_loaded(&self) -> bool {
+    self.loaded
+  }
+
+  /// Pick up the chain from existing local backups (once, after startup)
+  pub async fn load_local(&mut self, local_path: &Path, key: Option<&BackupKey>) {
+    if self.loaded {
+      return;
+    }
+    self.loaded = true;
+
+    let files = match local_backup_files(local_path).await {
+      Ok(files) => files,
+      Err(e) => {
+        tracing::warn!("Could not scan existing backups: {}", e);
+        return;
+      }
+    };
+    let Some(latest) = files.last() else {
+      return;
+    };
+
+    let sql = match read_backup_file(latest, key).await {
+      Ok(sql) => Some(sql),
+      Err(e) => {
+        tracing::warn!("Could not read latest backup {:?}: {}", latest, e);
+        None
+      }
+    };
+    let kinds: Vec<BackupKind> = files.iter().map(|path| backup_kind(path)).collect();
+    self.resume(&kinds, sql.as_deref());
+  }
+
+  /// Pick up the chain from existing backups, given their kinds (oldest
+  /// first) and the contents of the newest one
+  pub fn resume(&mut self, kinds: &[BackupKind], latest: Option<&str>) {
+    self.loaded = true;
+    if let Some(sql) = latest {
+      self.last_change_id = parse_backup_header(sql).change_id;
+    }
+    self.since_full = kinds
+      .iter()
+      .rev()
+      .take_while(|kind| **kind == BackupKind::Incremental)
+      .count() as u32;
+  }
+
+  /// Decide whether the next backup can be incremental.
+  ///
+  /// Falls back to a full backup when incremental mode is off, no baseline is
+  /// known, a new baseline is due, or the change queue was pruned past the
+  /// last backed-up change.
+  pub fn next_kind(&self, section: &BackupSection, range: Option<(i64, i64)>) -> BackupKind {
+    if !section.incremental || self.since_full + 1 >= section.full_every.max(1) {
+      return BackupKind::Full;
+    }
+    let Some(last) = self.last_change_id else {
+      return BackupKind::Full;
+    };
+    match range {
+      Some((min, _)) if min > last + 1 => BackupKind::Full,
+      _ => BackupKind::Incremental,
+    }
+  }
+}
+
+/// Kind of a backup file, from its filename
+pub(crate) fn backup_kind(path: &Path) -> BackupKind {
+  BackupKind::from_filename(&path.file_name().unwrap_or_default().to_string_lossy())
+}
+
+/// Sort key for a backup filename: the timestamp after the kind prefix
+pub(crate) fn backup_stamp(filename: &str) -> String {
+  filename
+    .strip_prefix(BackupKind::from_filename(filename).filename_prefix())
+    .unwrap_or(filename)
+    .chars()
+    .take("YYYYMMDD_HHMMSS".len())
+    .collect()
+}
+
+/// Index of the full backup an entry builds on, given backup kinds oldest first
+pub(crate) fn chain_start(kinds: &[BackupKind], end: usize) -> Option<usize> {
+  kinds[..=end]
+    .iter()
+    .rposition(|kind| *kind == BackupKind::Full)
+}
+
+/// Indices of backups (oldest first) outside the retention window.
+///
+/// Retention counts full backups; incrementals are kept or removed together
+/// with the baseline they build on.
+pub(crate) fn expired_backups(kinds: &[BackupKind], retention: u32) -> Vec<usize> {
+  let mut expired = Vec::new();
+  let mut fulls = 0;
+  for (i, kind) in kinds.iter().enumerate().rev() {
+    if fulls >= retention {
+      expired.push(i);
+    } else if *kind == BackupKind::Full {
+      fulls += 1;
+    }
+  }
+  expired
+}
+
+/// Local backup files, oldest first
+pub(crate) async fn local_backup_files(local_path: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+  let mut files = Vec::new();
+  if !local_path.exists() {
+    return Ok(files);
+  }
+
+  let mut entries = tokio::fs::read_dir(local_path).await?;
+  while let Some(entry) = entries.next_entry().await? {
+    let path = entry.path();
+    let name = entry.file_name().to_string_lossy().to_string();
+    if is_backup_file(&name) {
+      // The timestamp only has second precision, so break ties with the
+      // modification time
+      let stamp = backup_stamp(&name);
+      let modified = entry.metadata().await?.modified().ok();
+      files.push((stamp, modified, path));
+    }
+  }
+
+  files.sort();
+  Ok(files.into_iter().map(|(_, _, path)| path).collect())
+}
+
+/// Generate an incremental backup covering changes after `since_change_id`.
+///
+/// Returns the SQL and the highest change ID it covers.
+pub async fn generate_incremental_sql(
+  backend: &Arc<dyn DatabaseBackend>,
+  config: &ServerConfig,
+  since_change_id: i64,
+) -> Result<(String, i64), anyhow::Error> {
+  // Read the upper bound first; later changes are picked up by the next run
+  let change_id = backend
+    .change_id_range()
+    .await?
+    .map(|(_, max)| max)
+    .unwrap_or(since_change_id)
+    .max(since_change_id);
+
+  let mut touched = BTreeSet::new();
+  let mut after = since_change_id;
+  'pages: loop {
+    let changes = backend.list_changes(after, CHANGE_PAGE_SIZE).await?;
+    let Some(last) = changes.last() else {
+      break;
+    };
+    after = last.id;
+    for change in changes {
+      if change.id > change_id {
+        break 'pages;
+      }
+      touched.insert((change.project_id, change.collection, change.document_id));
+    }
+  }
+
+  let project_names: HashMap<Uuid, String> = backend
+    .list_projects()
+    .await?
+    .into_iter()
+    .map(|p| (p.id, p.name))
+    .collect();
+
+  let mut sql = String::new();
+  sql.push_str("-- SquirrelDB Incremental Backup\n");
+  sql.push_str(&format!("-- Created: {}\n", Utc::now().to_rfc3339()));
+  sql.push_str(&format!("-- Backend: {:?}\n", config.backend));
+  sql.push_str(&format!("-- Since-Change-Id: {}\n", since_change_id));
+  sql.push_str(&format!("-- Change-Id: {}\n", change_id));
+  sql.push_str("-- \n\n");
+
+  let mut current_project = None;
+  for (project_id, collection, id) in touched {
+    if current_project != Some(project_id) {
+      let name = project_names
+        .get(&project_id)
+        .cloned()
+        .unwrap_or_else(|| project_id.to_string());
+      sql.push_str(&format!("-- Project: {} ({})\n", name, project_id));
+      current_project = Some(project_id);
+    }
+
+    match backend.get(project_id, &collection, id).await? {
+      Some(doc) => {
+        let data_json = serde_json::to_string(&doc.data)?;
+        sql.push_str(&format!(
+          "INSERT INTO {} (id, data, created_at, updated_at) VALUES ('{}', '{}', '{}', '{}');\n",
+          collection,
+          doc.id,
+          data_json.replace('\'', "''"),
+          doc.created_at.to_rfc3339(),
+          doc.updated_at.to_rfc3339()
+        ));
+      }
+      None => {
+        sql.push_str(&format!(
+          "DELETE FROM {} WHERE id = '{}';\n",
+          collection, id
+        ));
+      }
+    }
+  }
+
+  Ok((sql, change_id))
+}
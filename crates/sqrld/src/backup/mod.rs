@@ -1,3 +1,22 @@
+mod archive;
+mod codec;
+mod incremental;
+mod remote;
+mod restore;
+mod schedule;
+mod seed;
 mod service;
 
+pub use archive::{export_archive, import_archive, migrate_backend, ArchiveSummary};
+pub use codec::{
+  backup_extension, backup_id_from_filename, decode_backup, encode_backup, encryption_key,
+  is_backup_file, is_encrypted, parse_encryption_key, read_backup_file, BackupKey,
+};
+pub use incremental::{parse_backup_header, BackupChain, BackupHeader, BackupKind};
+pub use restore::{
+  database_is_empty, parse_backup_entries, parse_backup_sql, restore_backup_chain,
+  restore_backup_sql, BackupEntry, RestoreSummary,
+};
+pub use schedule::{apply_settings, parse_cron, BackupSchedule};
+pub use seed::seed_database;
 pub use service::BackupFeature;
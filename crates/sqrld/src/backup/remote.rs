@@ -0,0 +1,212 @@
+//! Backups stored as objects through the storage feature
+//!
+//! Objects are registered in the storage metadata tables like regular S3
+//! uploads, so they show up in bucket listings and can be downloaded with any
+//! S3 client.
+
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::codec::{decode_backup, is_backup_file, BackupKey};
+use super::incremental::{backup_stamp, BackupChain, BackupKind};
+use crate::db::DatabaseBackend;
+use crate::server::BackupSection;
+use crate::storage::{StorageBackend, StorageObject};
+
+/// Number of objects fetched per listing page
+const LIST_PAGE_SIZE: i32 = 1000;
+
+/// Content type of uploaded backup objects
+const BACKUP_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Backups under the configured bucket and prefix
+pub(crate) struct RemoteBackups<'a> {
+  storage: &'a Arc<dyn StorageBackend>,
+  db: &'a Arc<dyn DatabaseBackend>,
+  bucket: &'a str,
+  prefix: String,
+}
+
+impl<'a> RemoteBackups<'a> {
+  pub fn new(
+    storage: &'a Arc<dyn StorageBackend>,
+    db: &'a Arc<dyn DatabaseBackend>,
+    section: &'a BackupSection,
+  ) -> Self {
+    let prefix = section.storage_path.trim_matches('/');
+    Self {
+      storage,
+      db,
+      bucket: &section.storage_bucket,
+      prefix: if prefix.is_empty() {
+        String::new()
+      } else {
+        format!("{}/", prefix)
+      },
+    }
+  }
+
+  /// Object key for a backup filename
+  pub fn key(&self, filename: &str) -> String {
+    format!("{}{}", self.prefix, filename)
+  }
+
+  /// Backup filename of an object
+  pub fn filename<'o>(&self, object: &'o StorageObject) -> &'o str {
+    object.key.strip_prefix(&self.prefix).unwrap_or(&object.key)
+  }
+
+  /// Location reported for an object key
+  pub fn location(&self, key: &str) -> String {
+    format!("s3://{}/{}", self.bucket, key)
+  }
+
+  /// Create the bucket if needed, returning whether it is versioned
+  async fn ensure_bucket(&self) -> Result<bool, anyhow::Error> {
+    if let Some(bucket) = self.db.get_storage_bucket(self.bucket).await? {
+      return Ok(bucket.versioning_enabled);
+    }
+    self.storage.init_bucket(self.bucket).await?;
+    self.db.create_storage_bucket(self.bucket, None).await?;
+    tracing::info!("Created backup bucket: {}", self.bucket);
+    Ok(false)
+  }
+
+  /// Upload a backup file, returning its location
+  pub async fn upload(&self, filename: &str, data: &[u8]) -> Result<String, anyhow::Error> {
+    let versioned = self.ensure_bucket().await?;
+    let key = self.key(filename);
+    let version_id = Uuid::new_v4();
+
+    let (storage_path, etag, size) = self
+      .storage
+      .write_object(self.bucket, &key, version_id, data)
+      .await?;
+
+    let metadata = serde_json::json!({ "squirreldb-backup": "true" });
+    if versioned {
+      self
+        .db
+        .create_storage_object_with_stats(
+          self.bucket,
+          &key,
+          version_id,
+          &etag,
+          size,
+          BACKUP_CONTENT_TYPE,
+          &storage_path,
+          metadata,
+        )
+        .await?;
+    } else if let Some(old_path) = self
+      .db
+      .replace_storage_object(
+        self.bucket,
+        &key,
+        version_id,
+        &etag,
+        size,
+        BACKUP_CONTENT_TYPE,
+        &storage_path,
+        metadata,
+      )
+      .await?
+    {
+      let _ = self.storage.delete_object(&old_path).await;
+    }
+
+    Ok(self.location(&key))
+  }
+
+  /// Backup objects directly under the prefix, oldest first
+  pub async fn list(&self) -> Result<Vec<StorageObject>, anyhow::Error> {
+    if self.db.get_storage_bucket(self.bucket).await?.is_none() {
+      return Ok(Vec::new());
+    }
+
+    let mut objects = Vec::new();
+    let mut token: Option<String> = None;
+    loop {
+      let (page, truncated, next) = self
+        .db
+        .list_storage_objects(
+          self.bucket,
+          Some(&self.prefix),
+          None,
+          LIST_PAGE_SIZE,
+          token.as_deref(),
+        )
+        .await?;
+      objects.extend(page.into_iter().filter(|object| {
+        let name = self.filename(object);
+        object.key.starts_with(&self.prefix) && !name.contains('/') && is_backup_file(name)
+      }));
+      match next {
+        Some(next) if truncated => token = Some(next),
+        _ => break,
+      }
+    }
+
+    // Filenames only have second precision, so break ties with upload time
+    objects.sort_by_cached_key(|object| {
+      (
+        backup_stamp(self.filename(object)),
+        object.created_at,
+        object.key.clone(),
+      )
+    });
+    Ok(objects)
+  }
+
+  /// Read a backup object's contents
+  pub async fn read(&self, object: &StorageObject) -> Result<Vec<u8>, anyhow::Error> {
+    Ok(self.storage.read_object(&object.storage_path).await?)
+  }
+
+  /// Delete a backup object
+  pub async fn delete(&self, object: &StorageObject) -> Result<(), anyhow::Error> {
+    if let Some((storage_path, _)) = self
+      .db
+      .delete_storage_object_with_stats(self.bucket, &object.key, Some(object.version_id))
+      .await?
+    {
+      self.storage.delete_object(&storage_path).await?;
+    }
+    Ok(())
+  }
+
+  /// Pick up the chain from existing remote backups (once, after startup)
+  pub async fn load_chain(&self, chain: &mut BackupChain, key: Option<&BackupKey>) {
+    if chain.is_loaded() {
+      return;
+    }
+
+    let objects = match self.list().await {
+      Ok(objects) => objects,
+      Err(e) => {
+        tracing::warn!("Could not list existing backups: {}", e);
+        chain.resume(&[], None);
+        return;
+      }
+    };
+    let sql = match objects.last() {
+      Some(latest) => match self
+        .read(latest)
+        .await
+        .and_then(|data| decode_backup(&data, key))
+      {
+        Ok(sql) => Some(sql),
+        Err(e) => {
+          tracing::warn!("Could not read latest backup {}: {}", latest.key, e);
+          None
+        }
+      },
+      None => None,
+    };
+    let kinds: Vec<BackupKind> = objects
+      .iter()
+      .map(|object| BackupKind::from_filename(self.filename(object)))
+      .collect();
+    chain.resume(&kinds, sql.as_deref());
+  }
+}
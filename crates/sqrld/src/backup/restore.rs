@@ -0,0 +1,278 @@
+//! Restore documents from SQL backups produced by `BackupFeature`
+
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::incremental::{parse_backup_header, BackupKind};
+use crate::db::DatabaseBackend;
+use crate::types::{Document, DEFAULT_PROJECT_ID};
+
+/// Outcome of a restore
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct RestoreSummary {
+  /// Backup files applied (the baseline plus any incrementals)
+  pub backups: usize,
+  pub projects: usize,
+  pub collections: usize,
+  pub documents: usize,
+  /// Documents removed by incremental backups
+  pub deleted: usize,
+}
+
+/// A statement in a backup file
+#[derive(Debug, Clone)]
+pub enum BackupEntry {
+  /// Insert or replace a document
+  Upsert(Document),
+  /// Remove a document (incremental backups only)
+  Delete {
+    project_id: Uuid,
+    collection: String,
+    id: Uuid,
+  },
+}
+
+/// Parse the statements out of a backup SQL file.
+///
+/// Each statement belongs to the project named by the preceding
+/// `-- Project: <name> (<id>)` comment.
+pub fn parse_backup_entries(sql: &str) -> Result<Vec<BackupEntry>, anyhow::Error> {
+  let mut project_id = DEFAULT_PROJECT_ID;
+  let mut entries = Vec::new();
+
+  for (line_no, line) in sql.lines().enumerate() {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix("-- Project: ") {
+      let id = rest
+        .rsplit_once('(')
+        .and_then(|(_, id)| id.strip_suffix(')'))
+        .ok_or_else(|| anyhow::anyhow!("Line {}: malformed project header", line_no + 1))?;
+      project_id = id
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Line {}: invalid project id '{}'", line_no + 1, id))?;
+      continue;
+    }
+
+    let entry = if let Some(rest) = line.strip_prefix("INSERT INTO ") {
+      parse_insert(rest, project_id).map(BackupEntry::Upsert)
+    } else if let Some(rest) = line.strip_prefix("DELETE FROM ") {
+      parse_delete(rest, project_id)
+    } else {
+      continue;
+    };
+    entries.push(entry.map_err(|e| anyhow::anyhow!("Line {}: {}", line_no + 1, e))?);
+  }
+
+  Ok(entries)
+}
+
+/// Parse the documents out of a full backup SQL dump
+pub fn parse_backup_sql(sql: &str) -> Result<Vec<Document>, anyhow::Error> {
+  parse_backup_entries(sql)?
+    .into_iter()
+    .map(|entry| match entry {
+      BackupEntry::Upsert(doc) => Ok(doc),
+      BackupEntry::Delete { .. } => Err(anyhow::anyhow!("Unexpected DELETE in a full backup")),
+    })
+    .collect()
+}
+
+/// Parse `<collection> WHERE id = '..';`
+fn parse_delete(rest: &str, project_id: Uuid) -> Result<BackupEntry, anyhow::Error> {
+  let (collection, id) = rest
+    .strip_suffix("';")
+    .and_then(|r| r.split_once(" WHERE id = '"))
+    .ok_or_else(|| anyhow::anyhow!("unrecognized DELETE statement"))?;
+
+  Ok(BackupEntry::Delete {
+    project_id,
+    collection: collection.to_string(),
+    id: id.parse()?,
+  })
+}
+
+/// Parse `<collection> (id, data, created_at, updated_at) VALUES ('..', '..', '..', '..');`
+fn parse_insert(rest: &str, project_id: Uuid) -> Result<Document, anyhow::Error> {
+  let (collection, values) = rest
+    .split_once(" (id, data, created_at, updated_at) VALUES (")
+    .ok_or_else(|| anyhow::anyhow!("unrecognized INSERT statement"))?;
+  let values = values
+    .strip_suffix(");")
+    .ok_or_else(|| anyhow::anyhow!("unterminated INSERT statement"))?;
+
+  let fields = split_sql_strings(values)?;
+  let [id, data, created_at, updated_at] = fields.as_slice() else {
+    return Err(anyhow::anyhow!("expected 4 values, found {}", fields.len()));
+  };
+
+  Ok(Document {
+    id: id.parse()?,
+    project_id,
+    collection: collection.to_string(),
+    data: serde_json::from_str(data)?,
+    created_at: DateTime::parse_from_rfc3339(created_at)?.with_timezone(&Utc),
+    updated_at: DateTime::parse_from_rfc3339(updated_at)?.with_timezone(&Utc),
+    deleted_at: None,
+  })
+}
+
+/// Split a comma-separated list of single-quoted SQL strings, unescaping `''`
+fn split_sql_strings(input: &str) -> Result<Vec<String>, anyhow::Error> {
+  let mut values = Vec::new();
+  let mut chars = input.chars().peekable();
+
+  loop {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+      chars.next();
+    }
+    if chars.next() != Some('\'') {
+      return Err(anyhow::anyhow!("expected quoted value"));
+    }
+
+    let mut value = String::new();
+    loop {
+      match chars.next() {
+        Some('\'') if chars.peek() == Some(&'\'') => {
+          chars.next();
+          value.push('\'');
+        }
+        Some('\'') => break,
+        Some(c) => value.push(c),
+        None => return Err(anyhow::anyhow!("unterminated quoted value")),
+      }
+    }
+    values.push(value);
+
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+      chars.next();
+    }
+    match chars.next() {
+      Some(',') => continue,
+      None => break,
+      Some(c) => return Err(anyhow::anyhow!("unexpected '{}' between values", c)),
+    }
+  }
+
+  Ok(values)
+}
+
+/// Projects to inspect (the default project is always included)
+pub(super) async fn project_ids(
+  backend: &Arc<dyn DatabaseBackend>,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+  let mut ids: Vec<Uuid> = backend
+    .list_projects()
+    .await?
+    .into_iter()
+    .map(|p| p.id)
+    .collect();
+  if !ids.contains(&DEFAULT_PROJECT_ID) {
+    ids.push(DEFAULT_PROJECT_ID);
+  }
+  Ok(ids)
+}
+
+/// Check whether the database holds any documents
+pub async fn database_is_empty(backend: &Arc<dyn DatabaseBackend>) -> Result<bool, anyhow::Error> {
+  for project_id in project_ids(backend).await? {
+    for collection in backend.list_collections(project_id).await? {
+      if !backend
+        .list(project_id, &collection, None, None, None, Some(1), None)
+        .await?
+        .is_empty()
+      {
+        return Ok(false);
+      }
+    }
+  }
+  Ok(true)
+}
+
+/// Restore a backup SQL dump into the database.
+///
+/// Refuses to touch a non-empty database unless `force` is set, in which case
+/// all existing documents are removed first so the result matches the backup.
+/// The removal and the restore happen in one transaction.
+pub async fn restore_backup_sql(
+  backend: &Arc<dyn DatabaseBackend>,
+  sql: &str,
+  force: bool,
+) -> Result<RestoreSummary, anyhow::Error> {
+  restore_backup_chain(backend, &[sql], force).await
+}
+
+/// Restore a full backup followed by its incremental backups, oldest first.
+///
+/// Each incremental must start at the change ID the previous backup ended at,
+/// otherwise the chain is rejected before anything is written. The chain is
+/// replayed in memory and only its end state is written, in one transaction
+/// together with the wipe of a forced restore.
+pub async fn restore_backup_chain(
+  backend: &Arc<dyn DatabaseBackend>,
+  chain: &[&str],
+  force: bool,
+) -> Result<RestoreSummary, anyhow::Error> {
+  // Parse everything up front so a bad file leaves the database untouched
+  let mut parsed = Vec::with_capacity(chain.len());
+  let mut last_change_id = None;
+  for (i, sql) in chain.iter().enumerate() {
+    let header = parse_backup_header(sql);
+    match (i, header.kind) {
+      (0, BackupKind::Incremental) => {
+        anyhow::bail!("Backup chain must start with a full backup")
+      }
+      (0, BackupKind::Full) => {}
+      (_, BackupKind::Full) => anyhow::bail!("Backup chain contains more than one full backup"),
+      (_, BackupKind::Incremental) => {
+        if last_change_id.is_none() || header.since_change_id != last_change_id {
+          anyhow::bail!(
+            "Incremental backup {} does not continue the previous backup",
+            i
+          );
+        }
+      }
+    }
+    last_change_id = header.change_id;
+    parsed.push(parse_backup_entries(sql)?);
+  }
+
+  let wipe = !database_is_empty(backend).await?;
+  if wipe && !force {
+    anyhow::bail!("Database is not empty; use force to overwrite existing documents");
+  }
+
+  // The database starts out empty, so a delete only undoes an earlier upsert
+  let backups = parsed.len();
+  let mut documents: HashMap<(Uuid, String, Uuid), Document> = HashMap::new();
+  let mut deleted = 0;
+  for entry in parsed.into_iter().flatten() {
+    match entry {
+      BackupEntry::Upsert(doc) => {
+        documents.insert((doc.project_id, doc.collection.clone(), doc.id), doc);
+      }
+      BackupEntry::Delete {
+        project_id,
+        collection,
+        id,
+      } => {
+        if documents.remove(&(project_id, collection, id)).is_some() {
+          deleted += 1;
+        }
+      }
+    }
+  }
+  let projects: HashSet<_> = documents.keys().map(|(p, _, _)| *p).collect();
+  let collections: HashSet<_> = documents.keys().map(|(p, c, _)| (*p, c.clone())).collect();
+  let docs: Vec<Document> = documents.into_values().collect();
+  backend.restore_documents(&docs, wipe).await?;
+
+  Ok(RestoreSummary {
+    backups,
+    projects: projects.len(),
+    collections: collections.len(),
+    documents: docs.len(),
+    deleted,
+  })
+}
@@ -0,0 +1,99 @@
+//! Backup scheduling (fixed interval or cron expression)
+
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+use crate::server::BackupSection;
+
+/// When automatic backups run
+#[derive(Debug, Clone)]
+pub enum BackupSchedule {
+  /// Every N seconds
+  Interval(u64),
+  /// On a cron schedule (evaluated in UTC)
+  Cron(Box<cron::Schedule>),
+}
+
+impl BackupSchedule {
+  /// Build the schedule from backup settings; a cron `schedule` overrides `interval`
+  pub fn from_section(section: &BackupSection) -> Result<Self, anyhow::Error> {
+    match section.schedule.as_deref().map(str::trim) {
+      Some(expr) if !expr.is_empty() => Ok(Self::Cron(Box::new(parse_cron(expr)?))),
+      _ => Ok(Self::Interval(section.interval.max(1))),
+    }
+  }
+
+  /// Next backup time strictly after `after`
+  pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match self {
+      Self::Interval(secs) => Some(after + chrono::Duration::seconds(*secs as i64)),
+      Self::Cron(schedule) => schedule.after(&after).next(),
+    }
+  }
+}
+
+impl std::fmt::Display for BackupSchedule {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Interval(secs) => write!(f, "every {}s", secs),
+      Self::Cron(schedule) => write!(f, "cron '{}'", schedule),
+    }
+  }
+}
+
+/// Parse a cron expression.
+///
+/// Accepts standard 5-field expressions (`min hour day month weekday`) as well
+/// as the 6/7-field form with seconds (and year) used by the `cron` crate.
+pub fn parse_cron(expr: &str) -> Result<cron::Schedule, anyhow::Error> {
+  let expr = expr.trim();
+  let normalized = if expr.split_whitespace().count() == 5 {
+    format!("0 {}", expr)
+  } else {
+    expr.to_string()
+  };
+  cron::Schedule::from_str(&normalized)
+    .map_err(|e| anyhow::anyhow!("Invalid cron expression '{}': {}", expr, e))
+}
+
+/// Apply stored backup feature settings over the configured defaults
+pub fn apply_settings(section: &mut BackupSection, settings: &serde_json::Value) {
+  if let Some(interval) = settings.get("interval").and_then(|v| v.as_u64()) {
+    section.interval = interval;
+  }
+  if let Some(retention) = settings.get("retention").and_then(|v| v.as_u64()) {
+    section.retention = retention as u32;
+  }
+  if let Some(local_path) = settings.get("local_path").and_then(|v| v.as_str()) {
+    section.local_path = local_path.to_string();
+  }
+  if let Some(storage_bucket) = settings.get("storage_bucket").and_then(|v| v.as_str()) {
+    section.storage_bucket = storage_bucket.to_string();
+  }
+  if let Some(storage_path) = settings.get("storage_path").and_then(|v| v.as_str()) {
+    section.storage_path = storage_path.to_string();
+  }
+  if let Some(incremental) = settings.get("incremental").and_then(|v| v.as_bool()) {
+    section.incremental = incremental;
+  }
+  if let Some(full_every) = settings.get("full_every").and_then(|v| v.as_u64()) {
+    section.full_every = full_every as u32;
+  }
+  if let Some(compression) = settings
+    .get("compression")
+    .and_then(|v| v.as_str())
+    .and_then(|v| v.parse().ok())
+  {
+    section.compression = compression;
+  }
+  match settings.get("schedule") {
+    Some(serde_json::Value::String(s)) if !s.trim().is_empty() => {
+      section.schedule = Some(s.clone());
+    }
+    // An explicit null/empty string switches back to interval mode
+    Some(serde_json::Value::Null) | Some(serde_json::Value::String(_)) => {
+      section.schedule = None;
+    }
+    _ => {}
+  }
+}
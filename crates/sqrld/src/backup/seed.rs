@@ -0,0 +1,114 @@
+//! Starting data for development and test databases
+//!
+//! A seed file is loaded once, when the server starts against an empty
+//! database. It is either a portable archive written by `sqrld export`, or
+//! newline-delimited JSON with one document per line:
+//!
+//! ```text
+//! {"collection": "users", "data": {"name": "Alice"}}
+//! {"collection": "users", "id": "7c9e6679-7425-40de-944b-e07fc1f90ae7", "data": {"name": "Bob"}}
+//! ```
+
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::archive::{import_archive, ArchiveSummary};
+use super::restore::database_is_empty;
+use crate::db::DatabaseBackend;
+use crate::types::{Document, DEFAULT_PROJECT_ID};
+
+/// First bytes of a zstd frame, which every archive starts with
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// One line of an NDJSON seed file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeedDocument {
+  collection: String,
+  data: serde_json::Value,
+  /// Keep this ID instead of generating one
+  #[serde(default)]
+  id: Option<Uuid>,
+  #[serde(default)]
+  project_id: Option<Uuid>,
+}
+
+/// Load the seed file at `path` if the database holds no documents.
+/// Returns None, without reading the file, when there is data already.
+pub async fn seed_database(
+  backend: &Arc<dyn DatabaseBackend>,
+  path: &Path,
+) -> Result<Option<ArchiveSummary>, anyhow::Error> {
+  if !database_is_empty(backend).await? {
+    return Ok(None);
+  }
+  let mut file = File::open(path)
+    .map_err(|e| anyhow::anyhow!("Can't open seed file {}: {}", path.display(), e))?;
+  let mut magic = [0u8; 4];
+  let is_archive = file.read_exact(&mut magic).is_ok() && magic == ZSTD_MAGIC;
+  let file = File::open(path)?;
+  let summary = if is_archive {
+    import_archive(backend, file, false).await?
+  } else {
+    seed_ndjson(backend, file).await?
+  };
+  Ok(Some(summary))
+}
+
+/// Insert the documents of an NDJSON seed. Every line is parsed before the
+/// first insert, so a malformed file leaves the database empty.
+async fn seed_ndjson<R: Read>(
+  backend: &Arc<dyn DatabaseBackend>,
+  input: R,
+) -> Result<ArchiveSummary, anyhow::Error> {
+  let mut documents = Vec::new();
+  for (n, line) in BufReader::new(input).lines().enumerate() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let document: SeedDocument = serde_json::from_str(&line)
+      .map_err(|e| anyhow::anyhow!("Invalid seed document on line {}: {}", n + 1, e))?;
+    documents.push(document);
+  }
+
+  let mut summary = ArchiveSummary::default();
+  let mut collections = HashSet::new();
+  let mut projects = HashSet::new();
+  for seed in documents {
+    let project_id = seed.project_id.unwrap_or(DEFAULT_PROJECT_ID);
+    match seed.id {
+      Some(id) => {
+        let now = Utc::now();
+        backend
+          .restore_document(&Document {
+            id,
+            project_id,
+            collection: seed.collection.clone(),
+            data: seed.data,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+          })
+          .await?;
+      }
+      None => {
+        backend
+          .insert(project_id, &seed.collection, seed.data)
+          .await?;
+      }
+    }
+    projects.insert(project_id);
+    collections.insert((project_id, seed.collection));
+    summary.documents += 1;
+  }
+  summary.collections = collections.len();
+  summary.projects = projects.len();
+  Ok(summary)
+}
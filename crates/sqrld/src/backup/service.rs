@@ -1,20 +1,34 @@
 //! Database backup service
 //!
-//! Automatically backs up the database at configurable intervals.
-//! Stores backups to S3 Storage (if enabled) or local filesystem.
+//! Automatically backs up the database at a configurable interval or cron schedule.
+//! Uploads backups to a bucket through the storage feature (if enabled) or
+//! writes them to the local filesystem. With `incremental` enabled, runs
+//! between full baselines only capture changes.
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, Mutex, RwLock};
 use uuid::Uuid;
 
+use super::codec::{
+  backup_extension, backup_id_from_filename, decode_backup, encode_backup, encryption_key,
+  is_backup_file, is_encrypted, read_backup_file,
+};
+use super::incremental::{
+  backup_kind, chain_start, expired_backups, generate_incremental_sql, local_backup_files,
+  BackupChain, BackupKind,
+};
+use super::remote::RemoteBackups;
+use super::restore::{restore_backup_chain, RestoreSummary};
+use super::schedule::{apply_settings, BackupSchedule};
 use crate::db::DatabaseBackend;
 use crate::features::{AppState, Feature};
-use crate::server::{BackendType, ServerConfig};
+use crate::server::{BackendType, BackupCompression, ServerConfig};
 use crate::storage::StorageBackend;
+use crate::types::DEFAULT_PROJECT_ID;
 
 /// Information about a backup
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -25,15 +39,21 @@ pub struct BackupInfo {
   pub created_at: DateTime<Utc>,
   pub backend: String,
   pub location: String,
+  /// "full" or "incremental"
+  pub kind: String,
+  /// "none", "gzip" or "zstd"
+  pub compression: String,
+  pub encrypted: bool,
 }
 
 /// Backup feature for automatic database backups
 pub struct BackupFeature {
   running: AtomicBool,
   shutdown_tx: RwLock<Option<mpsc::Sender<()>>>,
-  last_backup: RwLock<Option<DateTime<Utc>>>,
-  next_backup: RwLock<Option<DateTime<Utc>>>,
+  last_backup: Arc<RwLock<Option<DateTime<Utc>>>>,
+  next_backup: Arc<RwLock<Option<DateTime<Utc>>>>,
   storage_backend: RwLock<Option<Arc<dyn StorageBackend>>>,
+  chain: Arc<Mutex<BackupChain>>,
 }
 
 impl Default for BackupFeature {
@@ -47,9 +67,10 @@ impl BackupFeature {
     Self {
       running: AtomicBool::new(false),
       shutdown_tx: RwLock::new(None),
-      last_backup: RwLock::new(None),
-      next_backup: RwLock::new(None),
+      last_backup: Arc::new(RwLock::new(None)),
+      next_backup: Arc::new(RwLock::new(None)),
       storage_backend: RwLock::new(None),
+      chain: Arc::new(Mutex::new(BackupChain::default())),
     }
   }
 
@@ -85,144 +106,47 @@ impl BackupFeature {
     backend: &Arc<dyn DatabaseBackend>,
     config: &ServerConfig,
   ) -> Result<BackupInfo, anyhow::Error> {
-    let timestamp = Utc::now();
-    let backup_id = Uuid::new_v4().to_string();
-    let filename = format!(
-      "squirreldb_backup_{}_{}.sql",
-      timestamp.format("%Y%m%d_%H%M%S"),
-      &backup_id[..8]
-    );
-
-    // Generate backup data
-    let backup_data = generate_backup_sql(backend, config).await?;
-    let size = backup_data.len() as i64;
-
-    // Get storage backend if available
-    let storage = {
-      let guard = self.storage_backend.read().await;
-      guard.clone()
-    };
-
-    // Determine storage location
-    let location = if let Some(ref storage_backend) = storage {
-      // Store to S3 storage in /backups folder
-      let key = format!("{}/{}", config.backup.storage_path, filename);
-
-      // Ensure backups bucket exists
-      if let Err(e) = storage_backend.init_bucket("backups").await {
-        tracing::warn!("Could not create backups bucket (may already exist): {}", e);
-      }
-
-      storage_backend
-        .write_object("backups", &key, Uuid::new_v4(), backup_data.as_bytes())
-        .await?;
-
-      format!("s3://backups/{}", key)
-    } else {
-      // Store to local filesystem
-      let local_path = PathBuf::from(&config.backup.local_path);
-      tokio::fs::create_dir_all(&local_path).await?;
-
-      let file_path = local_path.join(&filename);
-      tokio::fs::write(&file_path, backup_data.as_bytes()).await?;
+    let storage = self.storage().await;
 
-      file_path.to_string_lossy().to_string()
-    };
+    let info = write_backup(backend, config, storage.as_ref(), &self.chain).await?;
 
-    // Update last backup time
+    // Update last backup time (the next run is owned by the scheduler)
     {
       let mut guard = self.last_backup.write().await;
-      *guard = Some(timestamp);
-    }
-
-    // Schedule next backup
-    {
-      let next = timestamp + chrono::Duration::seconds(config.backup.interval as i64);
-      let mut guard = self.next_backup.write().await;
-      *guard = Some(next);
+      *guard = Some(info.created_at);
     }
 
     // Clean up old backups
-    self.cleanup_old_backups(config).await?;
-
-    let info = BackupInfo {
-      id: backup_id,
-      filename,
-      size,
-      created_at: timestamp,
-      backend: match config.backend {
-        BackendType::Postgres => "postgres".to_string(),
-        BackendType::Sqlite => "sqlite".to_string(),
-      },
-      location,
-    };
-
-    tracing::info!("Backup created: {} ({} bytes)", info.filename, info.size);
+    cleanup_old_backups(backend, config, storage.as_ref()).await?;
 
     Ok(info)
   }
 
-  /// Clean up old backups based on retention policy
-  async fn cleanup_old_backups(&self, config: &ServerConfig) -> Result<(), anyhow::Error> {
-    // Get storage backend if available
-    let storage = {
-      let guard = self.storage_backend.read().await;
-      guard.clone()
-    };
-
-    if storage.is_some() {
-      // Storage mode cleanup is handled via list_backups and manual deletion
-      // Future enhancement: implement storage listing in StorageBackend trait
-      tracing::debug!("Storage mode backup cleanup deferred");
-    } else {
-      // Clean up local backups
-      let local_path = PathBuf::from(&config.backup.local_path);
-
-      if local_path.exists() {
-        let mut entries = Vec::new();
-        let mut dir = tokio::fs::read_dir(&local_path).await?;
-        while let Some(entry) = dir.next_entry().await? {
-          entries.push(entry);
-        }
-
-        // Sort by name (contains timestamp) descending
-        entries.sort_by_key(|e| std::cmp::Reverse(e.file_name()));
-
-        // Delete backups beyond retention limit
-        for entry in entries.iter().skip(config.backup.retention as usize) {
-          let path = entry.path();
-          if path.extension().is_some_and(|ext| ext == "sql") {
-            if let Err(e) = tokio::fs::remove_file(&path).await {
-              tracing::warn!("Failed to delete old backup {:?}: {}", path, e);
-            } else {
-              tracing::info!("Deleted old backup: {:?}", path);
-            }
-          }
-        }
-      }
-    }
-
-    Ok(())
+  /// Get the storage backend backups are uploaded to, if any
+  async fn storage(&self) -> Option<Arc<dyn StorageBackend>> {
+    self.storage_backend.read().await.clone()
   }
 
   /// List all backups
   pub async fn list_backups(
     &self,
+    backend: &Arc<dyn DatabaseBackend>,
     config: &ServerConfig,
   ) -> Result<Vec<BackupInfo>, anyhow::Error> {
     let mut backups = Vec::new();
 
-    // Get storage backend if available
-    let storage = {
-      let guard = self.storage_backend.read().await;
-      guard.clone()
-    };
-
-    if storage.is_some() {
-      // For storage mode, listing requires StorageBackend list_objects support
-      // For now, return an empty list with a note
-      tracing::debug!("Storage mode backup listing not yet implemented");
-      // Future enhancement: add list_objects to StorageBackend trait
+    if let Some(storage) = self.storage().await {
+      // List backup objects in the storage bucket
+      let remote = RemoteBackups::new(&storage, backend, &config.backup);
+      for object in remote.list().await? {
+        let filename = remote.filename(&object).to_string();
+        backups.push(backup_info(
+          &filename,
+          object.size,
+          "s3",
+          remote.location(&object.key),
+        ));
+      }
     } else {
       // List local backups
       let local_path = PathBuf::from(&config.backup.local_path);
@@ -232,29 +156,19 @@ impl BackupFeature {
 
         while let Some(entry) = entries.next_entry().await? {
           let path = entry.path();
-          if path.extension().is_some_and(|ext| ext == "sql") {
+          let filename = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+          if is_backup_file(&filename) {
             let metadata = entry.metadata().await?;
-            let filename = path
-              .file_name()
-              .unwrap_or_default()
-              .to_string_lossy()
-              .to_string();
-
-            // Parse timestamp from filename
-            let created_at = parse_backup_timestamp(&filename);
-
-            backups.push(BackupInfo {
-              id: filename
-                .split('_')
-                .next_back()
-                .unwrap_or("unknown")
-                .replace(".sql", ""),
-              filename: filename.clone(),
-              size: metadata.len() as i64,
-              created_at,
-              backend: "local".to_string(),
-              location: path.to_string_lossy().to_string(),
-            });
+            backups.push(backup_info(
+              &filename,
+              metadata.len() as i64,
+              "local",
+              path.to_string_lossy().to_string(),
+            ));
           }
         }
       }
@@ -269,47 +183,145 @@ impl BackupFeature {
   /// Delete a specific backup
   pub async fn delete_backup(
     &self,
+    backend: &Arc<dyn DatabaseBackend>,
     config: &ServerConfig,
     backup_id: &str,
   ) -> Result<bool, anyhow::Error> {
-    // Get storage backend if available
-    let storage = {
-      let guard = self.storage_backend.read().await;
-      guard.clone()
-    };
-
-    if storage.is_some() {
-      // For storage mode, we need to know the full path
-      // This is a limitation without list_objects support
-      tracing::warn!("Storage mode backup deletion not fully implemented");
-      return Ok(false);
+    if let Some(storage) = self.storage().await {
+      let remote = RemoteBackups::new(&storage, backend, &config.backup);
+      let objects = remote.list().await?;
+      let Some(object) = objects
+        .iter()
+        .find(|object| matches_backup_id(remote.filename(object), backup_id))
+      else {
+        return Ok(false);
+      };
+
+      remote.delete(object).await?;
+      tracing::info!("Deleted storage backup: {}", object.key);
+      return Ok(true);
     }
 
     // For local backups, search the directory
-    let local_path = PathBuf::from(&config.backup.local_path);
+    let Some(path) = find_local_backup(config, backup_id).await? else {
+      return Ok(false);
+    };
 
-    if local_path.exists() {
-      let mut entries = tokio::fs::read_dir(&local_path).await?;
-
-      while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
-        let filename = path
-          .file_name()
-          .unwrap_or_default()
-          .to_string_lossy()
-          .to_string();
-
-        // Check if this backup matches the ID
-        if filename.contains(backup_id) && filename.ends_with(".sql") {
-          tokio::fs::remove_file(&path).await?;
-          tracing::info!("Deleted local backup: {}", filename);
-          return Ok(true);
-        }
+    tokio::fs::remove_file(&path).await?;
+    tracing::info!("Deleted local backup: {}", path.display());
+    Ok(true)
+  }
+
+  /// Restore a backup into the database, returning `None` if it doesn't exist.
+  ///
+  /// Fails if the database already holds documents unless `force` is set.
+  pub async fn restore_backup(
+    &self,
+    backend: &Arc<dyn DatabaseBackend>,
+    config: &ServerConfig,
+    backup_id: &str,
+    force: bool,
+  ) -> Result<Option<RestoreSummary>, anyhow::Error> {
+    let key = encryption_key(&config.backup)?;
+
+    // An incremental backup is restored on top of its baseline and the
+    // incrementals between them
+    let mut chain = Vec::new();
+    if let Some(storage) = self.storage().await {
+      let remote = RemoteBackups::new(&storage, backend, &config.backup);
+      let objects = remote.list().await?;
+      let Some(end) = objects
+        .iter()
+        .position(|object| matches_backup_id(remote.filename(object), backup_id))
+      else {
+        return Ok(None);
+      };
+      let kinds: Vec<BackupKind> = objects
+        .iter()
+        .map(|object| BackupKind::from_filename(remote.filename(object)))
+        .collect();
+      let start = chain_start(&kinds, end)
+        .ok_or_else(|| anyhow::anyhow!("No full backup found before {}", backup_id))?;
+
+      tracing::info!("Restoring backup: {}", remote.location(&objects[end].key));
+      for object in &objects[start..=end] {
+        chain.push(decode_backup(&remote.read(object).await?, key.as_ref())?);
+      }
+    } else {
+      let Some(path) = find_local_backup(config, backup_id).await? else {
+        return Ok(None);
+      };
+
+      let files = local_backup_files(&PathBuf::from(&config.backup.local_path)).await?;
+      let end = files
+        .iter()
+        .position(|f| *f == path)
+        .ok_or_else(|| anyhow::anyhow!("Backup not found: {}", backup_id))?;
+      let kinds: Vec<BackupKind> = files.iter().map(|f| backup_kind(f)).collect();
+      let start = chain_start(&kinds, end)
+        .ok_or_else(|| anyhow::anyhow!("No full backup found before {}", backup_id))?;
+
+      tracing::info!("Restoring backup: {}", path.display());
+      for file in &files[start..=end] {
+        chain.push(read_backup_file(file, key.as_ref()).await?);
       }
     }
 
-    Ok(false)
+    let chain: Vec<&str> = chain.iter().map(String::as_str).collect();
+    let summary = restore_backup_chain(backend, &chain, force).await?;
+    tracing::info!(
+      "Backup restored from {} file(s): {} document(s) in {} collection(s)",
+      summary.backups,
+      summary.documents,
+      summary.collections
+    );
+
+    Ok(Some(summary))
+  }
+}
+
+/// Backup info for a listed file
+fn backup_info(filename: &str, size: i64, backend: &str, location: String) -> BackupInfo {
+  BackupInfo {
+    id: backup_id_from_filename(filename),
+    filename: filename.to_string(),
+    size,
+    created_at: parse_backup_timestamp(filename),
+    backend: backend.to_string(),
+    location,
+    kind: BackupKind::from_filename(filename).as_str().to_string(),
+    compression: BackupCompression::from_filename(filename)
+      .as_str()
+      .to_string(),
+    encrypted: is_encrypted(filename),
+  }
+}
+
+/// Find a local backup file by ID
+async fn find_local_backup(
+  config: &ServerConfig,
+  backup_id: &str,
+) -> Result<Option<PathBuf>, anyhow::Error> {
+  let local_path = PathBuf::from(&config.backup.local_path);
+  if backup_id.is_empty() || !local_path.exists() {
+    return Ok(None);
+  }
+
+  let mut entries = tokio::fs::read_dir(&local_path).await?;
+  while let Some(entry) = entries.next_entry().await? {
+    let path = entry.path();
+    let filename = path
+      .file_name()
+      .unwrap_or_default()
+      .to_string_lossy()
+      .to_string();
+
+    if matches_backup_id(&filename, backup_id) {
+      return Ok(Some(path));
+    }
   }
+
+  Ok(None)
 }
 
 #[async_trait]
@@ -327,6 +339,16 @@ impl Feature for BackupFeature {
       return Ok(());
     }
 
+    // Apply settings saved from the admin UI over the config file
+    let mut config = state.config.clone();
+    if let Ok(Some((_, settings))) = state.backend.get_feature_settings("backup").await {
+      apply_settings(&mut config.backup, &settings);
+      config.resolve_paths();
+    }
+    let schedule = BackupSchedule::from_section(&config.backup)?;
+    // Fail fast on a malformed key rather than on the first scheduled run
+    encryption_key(&config.backup)?;
+
     self.running.store(true, Ordering::SeqCst);
 
     let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
@@ -335,74 +357,45 @@ impl Feature for BackupFeature {
       *guard = Some(shutdown_tx);
     }
 
-    // Set initial next backup time
-    let interval = state.config.backup.interval;
-    {
-      let next = Utc::now() + chrono::Duration::seconds(interval as i64);
-      let mut guard = self.next_backup.write().await;
-      *guard = Some(next);
-    }
-
     let backend = state.backend.clone();
-    let config = state.config.clone();
+    let last_backup = self.last_backup.clone();
+    let next_backup = self.next_backup.clone();
+    let chain = self.chain.clone();
 
     // Get storage backend for the spawned task
-    let storage = {
-      let guard = self.storage_backend.read().await;
-      guard.clone()
-    };
+    let storage = self.storage().await;
 
     // Spawn backup task
     tokio::spawn(async move {
       tracing::info!(
-        "Backup service started (interval: {}s, retention: {})",
-        config.backup.interval,
+        "Backup service started (schedule: {}, retention: {})",
+        schedule,
         config.backup.retention
       );
 
       loop {
+        let Some(next) = schedule.next_after(Utc::now()) else {
+          tracing::warn!("Backup schedule has no upcoming runs, stopping scheduler");
+          *next_backup.write().await = None;
+          break;
+        };
+        *next_backup.write().await = Some(next);
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+
         tokio::select! {
-          _ = tokio::time::sleep(tokio::time::Duration::from_secs(config.backup.interval)) => {
-            // Perform backup
-            let timestamp = Utc::now();
-            let backup_id = Uuid::new_v4().to_string();
-            let filename = format!(
-              "squirreldb_backup_{}_{}.sql",
-              timestamp.format("%Y%m%d_%H%M%S"),
-              &backup_id[..8]
-            );
-
-            tracing::info!("Starting scheduled backup: {}", filename);
-
-            // Generate backup data
-            match generate_backup_sql(&backend, &config).await {
-              Ok(backup_data) => {
-                let result: Result<(), anyhow::Error> = if let Some(ref sb) = storage {
-                  let key = format!("{}/{}", config.backup.storage_path, filename);
-                  sb.write_object("backups", &key, Uuid::new_v4(), backup_data.as_bytes())
-                    .await
-                    .map(|_| ())
-                    .map_err(|e| anyhow::anyhow!("Storage error: {}", e))
-                } else {
-                  let local_path = PathBuf::from(&config.backup.local_path);
-                  let _ = tokio::fs::create_dir_all(&local_path).await;
-                  let file_path = local_path.join(&filename);
-                  tokio::fs::write(&file_path, backup_data.as_bytes())
-                    .await
-                    .map_err(|e| anyhow::anyhow!("Failed to write backup: {}", e))
-                };
-
-                match result {
-                  Ok(_) => {
-                    tracing::info!("Scheduled backup completed: {}", filename);
-                  }
-                  Err(e) => {
-                    tracing::error!("Scheduled backup failed: {}", e);
-                  }
+          _ = tokio::time::sleep(wait) => {
+            tracing::info!("Starting scheduled backup");
+
+            match write_backup(&backend, &config, storage.as_ref(), &chain).await {
+              Ok(info) => {
+                *last_backup.write().await = Some(info.created_at);
+                tracing::info!("Scheduled backup completed: {}", info.filename);
+                if let Err(e) = cleanup_old_backups(&backend, &config, storage.as_ref()).await {
+                  tracing::warn!("Failed to clean up old backups: {}", e);
                 }
               }
               Err(e) => {
-                tracing::error!("Failed to generate backup data: {}", e);
+                tracing::error!("Scheduled backup failed: {}", e);
               }
             }
           }
@@ -430,6 +423,7 @@ impl Feature for BackupFeature {
     if let Some(tx) = tx {
       let _ = tx.send(()).await;
     }
+    *self.next_backup.write().await = None;
 
     tracing::info!("Backup feature stopped");
     Ok(())
@@ -444,10 +438,19 @@ impl Feature for BackupFeature {
   }
 }
 
+/// Check whether a backup file has exactly the given ID, the last part of
+/// its filename
+fn matches_backup_id(filename: &str, backup_id: &str) -> bool {
+  is_backup_file(filename)
+    && !backup_id.is_empty()
+    && backup_id_from_filename(filename) == backup_id
+}
+
 /// Parse backup timestamp from filename
 fn parse_backup_timestamp(filename: &str) -> DateTime<Utc> {
-  // Format: squirreldb_backup_YYYYMMDD_HHMMSS_XXXXXXXX.sql
-  if let Some(rest) = filename.strip_prefix("squirreldb_backup_") {
+  // Format: squirreldb_{backup,incremental}_YYYYMMDD_HHMMSS_<id>.sql[.gz|.zst][.enc]
+  let prefix = BackupKind::from_filename(filename).filename_prefix();
+  if let Some(rest) = filename.strip_prefix(prefix) {
     let parts: Vec<&str> = rest.split('_').collect();
     if parts.len() >= 2 {
       if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(
@@ -461,34 +464,186 @@ fn parse_backup_timestamp(filename: &str) -> DateTime<Utc> {
   Utc::now()
 }
 
-/// Helper function to generate backup data
+/// Write a full or incremental backup and advance the backup chain
+async fn write_backup(
+  backend: &Arc<dyn DatabaseBackend>,
+  config: &ServerConfig,
+  storage: Option<&Arc<dyn StorageBackend>>,
+  chain: &Mutex<BackupChain>,
+) -> Result<BackupInfo, anyhow::Error> {
+  let key = encryption_key(&config.backup)?;
+
+  // Hold the chain for the whole run so concurrent backups can't interleave
+  let mut chain = chain.lock().await;
+  match storage {
+    Some(storage) => {
+      RemoteBackups::new(storage, backend, &config.backup)
+        .load_chain(&mut chain, key.as_ref())
+        .await
+    }
+    None => {
+      chain
+        .load_local(&PathBuf::from(&config.backup.local_path), key.as_ref())
+        .await
+    }
+  }
+
+  let range = backend.change_id_range().await?;
+  let kind = chain.next_kind(&config.backup, range);
+  let (backup_data, change_id) = match (kind, chain.last_change_id) {
+    (BackupKind::Incremental, Some(since)) => {
+      generate_incremental_sql(backend, config, since).await?
+    }
+    _ => generate_backup_sql(backend, config).await?,
+  };
+
+  let timestamp = Utc::now();
+  let backup_id = Uuid::new_v4().to_string();
+  let compression = config.backup.compression;
+  let filename = format!(
+    "{}{}_{}{}",
+    kind.filename_prefix(),
+    timestamp.format("%Y%m%d_%H%M%S"),
+    backup_id,
+    backup_extension(compression, key.is_some())
+  );
+  let backup_data = encode_backup(&backup_data, compression, key.as_ref())?;
+  let size = backup_data.len() as i64;
+
+  // Determine storage location
+  let location = if let Some(storage) = storage {
+    // Upload to the configured bucket and prefix
+    RemoteBackups::new(storage, backend, &config.backup)
+      .upload(&filename, &backup_data)
+      .await?
+  } else {
+    // Store to local filesystem
+    let local_path = PathBuf::from(&config.backup.local_path);
+    tokio::fs::create_dir_all(&local_path).await?;
+
+    let file_path = local_path.join(&filename);
+    tokio::fs::write(&file_path, &backup_data).await?;
+
+    file_path.to_string_lossy().to_string()
+  };
+
+  chain.record(kind, change_id);
+
+  let info = BackupInfo {
+    id: backup_id,
+    filename,
+    size,
+    created_at: timestamp,
+    backend: match config.backend {
+      BackendType::Postgres => "postgres".to_string(),
+      BackendType::Sqlite => "sqlite".to_string(),
+      BackendType::Memory => "memory".to_string(),
+    },
+    location,
+    kind: kind.as_str().to_string(),
+    compression: compression.as_str().to_string(),
+    encrypted: key.is_some(),
+  };
+
+  tracing::info!(
+    "Backup created: {} ({}, {} bytes)",
+    info.filename,
+    info.kind,
+    info.size
+  );
+
+  Ok(info)
+}
+
+/// Clean up old backups based on retention policy.
+///
+/// Retention counts full backups; incrementals are kept or removed together
+/// with the baseline they build on.
+async fn cleanup_old_backups(
+  backend: &Arc<dyn DatabaseBackend>,
+  config: &ServerConfig,
+  storage: Option<&Arc<dyn StorageBackend>>,
+) -> Result<(), anyhow::Error> {
+  if let Some(storage) = storage {
+    let remote = RemoteBackups::new(storage, backend, &config.backup);
+    let objects = remote.list().await?;
+    let kinds: Vec<BackupKind> = objects
+      .iter()
+      .map(|object| BackupKind::from_filename(remote.filename(object)))
+      .collect();
+    for i in expired_backups(&kinds, config.backup.retention) {
+      let object = &objects[i];
+      if let Err(e) = remote.delete(object).await {
+        tracing::warn!("Failed to delete old backup {}: {}", object.key, e);
+      } else {
+        tracing::info!("Deleted old backup: {}", object.key);
+      }
+    }
+    return Ok(());
+  }
+
+  // Clean up local backups
+  let files = local_backup_files(&PathBuf::from(&config.backup.local_path)).await?;
+  let kinds: Vec<BackupKind> = files.iter().map(|path| backup_kind(path)).collect();
+  for i in expired_backups(&kinds, config.backup.retention) {
+    let path = &files[i];
+    if let Err(e) = tokio::fs::remove_file(path).await {
+      tracing::warn!("Failed to delete old backup {:?}: {}", path, e);
+    } else {
+      tracing::info!("Deleted old backup: {:?}", path);
+    }
+  }
+
+  Ok(())
+}
+
+/// Helper function to generate a full backup, returning it with the highest
+/// change ID it covers
 async fn generate_backup_sql(
   backend: &Arc<dyn DatabaseBackend>,
   config: &ServerConfig,
-) -> Result<String, anyhow::Error> {
+) -> Result<(String, i64), anyhow::Error> {
+  // Read before dumping so changes made during the dump are replayed by the
+  // next incremental backup
+  let change_id = backend
+    .change_id_range()
+    .await?
+    .map(|(_, max)| max)
+    .unwrap_or(0);
+
   let mut sql = String::new();
 
   sql.push_str("-- SquirrelDB Backup\n");
   sql.push_str(&format!("-- Created: {}\n", Utc::now().to_rfc3339()));
   sql.push_str(&format!("-- Backend: {:?}\n", config.backend));
+  sql.push_str(&format!("-- Change-Id: {}\n", change_id));
   sql.push_str("-- \n\n");
 
-  let projects = backend.list_projects().await?;
+  let mut projects: Vec<(Uuid, String)> = backend
+    .list_projects()
+    .await?
+    .into_iter()
+    .map(|p| (p.id, p.name))
+    .collect();
+  // Backends without project tables still store documents in the default project
+  if !projects.iter().any(|(id, _)| *id == DEFAULT_PROJECT_ID) {
+    projects.push((DEFAULT_PROJECT_ID, "default".to_string()));
+  }
 
   sql.push_str("-- Projects\n");
-  for project in &projects {
-    sql.push_str(&format!("-- Project: {} ({})\n", project.name, project.id));
+  for (project_id, project_name) in &projects {
+    sql.push_str(&format!("-- Project: {} ({})\n", project_name, project_id));
 
-    let collections = backend.list_collections(project.id).await?;
+    let collections = backend.list_collections(*project_id).await?;
 
     for collection in &collections {
       sql.push_str(&format!(
         "\n-- Collection: {}.{}\n",
-        project.name, collection
+        project_name, collection
       ));
 
       let docs = backend
-        .list(project.id, collection, None, None, None, None)
+        .list(*project_id, collection, None, None, None, None, None)
         .await?;
 
       for doc in docs {
@@ -505,5 +660,5 @@ async fn generate_backup_sql(
     }
   }
 
-  Ok(sql)
+  Ok((sql, change_id))
 }
@@ -0,0 +1,424 @@
+//! `sqrld bench`: a load test against a running server.
+//!
+//! Workers connect over WebSocket with the client `Connection` and run a mix
+//! of inserts, queries and updates against a collection created for the run.
+//! Every document the run inserted is deleted afterwards, which drops the
+//! collection, even when the run fails part way.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use client::Connection;
+use parking_lot::Mutex;
+use rand::Rng;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::types::{ClientMessage, ServerMessage};
+
+/// Documents inserted before timing starts, so queries and updates have data
+const SEED_DOCUMENTS: usize = 100;
+
+/// Share of operations that are inserts, queries and updates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationMix {
+  pub insert: u32,
+  pub query: u32,
+  pub update: u32,
+}
+
+impl Default for OperationMix {
+  fn default() -> Self {
+    Self {
+      insert: 40,
+      query: 40,
+      update: 20,
+    }
+  }
+}
+
+impl FromStr for OperationMix {
+  type Err = String;
+
+  /// Parse `insert:query:update` weights, e.g. `40:40:20`
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let weights = s
+      .split(':')
+      .map(|w| w.trim().parse::<u32>())
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|_| format!("Invalid mix '{}': expected insert:query:update", s))?;
+    let [insert, query, update] = weights[..] else {
+      return Err(format!("Invalid mix '{}': expected insert:query:update", s));
+    };
+    if insert + query + update == 0 {
+      return Err("Mix weights can't all be zero".into());
+    }
+    Ok(Self {
+      insert,
+      query,
+      update,
+    })
+  }
+}
+
+impl OperationMix {
+  fn pick(&self, rng: &mut impl Rng) -> Operation {
+    let roll = rng.gen_range(0..self.insert + self.query + self.update);
+    if roll < self.insert {
+      Operation::Insert
+    } else if roll < self.insert + self.query {
+      Operation::Query
+    } else {
+      Operation::Update
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+  Insert,
+  Query,
+  Update,
+}
+
+const OPERATIONS: [Operation; 3] = [Operation::Insert, Operation::Query, Operation::Update];
+
+impl Operation {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Self::Insert => "insert",
+      Self::Query => "query",
+      Self::Update => "update",
+    }
+  }
+}
+
+/// What to run
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+  /// WebSocket address of the server
+  pub url: String,
+  /// API or admin token, when the server has auth enabled
+  pub token: Option<String>,
+  /// Total operations across all connections
+  pub operations: usize,
+  /// Connections running operations in parallel
+  pub concurrency: usize,
+  pub mix: OperationMix,
+}
+
+/// Latencies of one kind of operation
+#[derive(Debug, Default)]
+pub struct OperationStats {
+  latencies: Vec<Duration>,
+  pub errors: usize,
+}
+
+impl OperationStats {
+  pub fn count(&self) -> usize {
+    self.latencies.len()
+  }
+
+  /// Latency below which `p` percent of operations finished
+  pub fn percentile(&self, p: f64) -> Duration {
+    percentile(&self.latencies, p)
+  }
+}
+
+/// Result of a run
+#[derive(Debug)]
+pub struct BenchReport {
+  pub collection: String,
+  pub elapsed: Duration,
+  pub insert: OperationStats,
+  pub query: OperationStats,
+  pub update: OperationStats,
+}
+
+impl BenchReport {
+  fn stats(&self, op: Operation) -> &OperationStats {
+    match op {
+      Operation::Insert => &self.insert,
+      Operation::Query => &self.query,
+      Operation::Update => &self.update,
+    }
+  }
+
+  fn stats_mut(&mut self, op: Operation) -> &mut OperationStats {
+    match op {
+      Operation::Insert => &mut self.insert,
+      Operation::Query => &mut self.query,
+      Operation::Update => &mut self.update,
+    }
+  }
+
+  /// Successful operations per second
+  pub fn throughput(&self) -> f64 {
+    let total: usize = OPERATIONS.iter().map(|op| self.stats(*op).count()).sum();
+    total as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+  }
+
+  /// Human-readable summary
+  pub fn render(&self) -> String {
+    let mut out = format!(
+      "{:<8} {:>8} {:>7} {:>10} {:>10} {:>10} {:>10}\n",
+      "op", "count", "errors", "p50 ms", "p95 ms", "p99 ms", "max ms"
+    );
+    for op in OPERATIONS {
+      let stats = self.stats(op);
+      out.push_str(&format!(
+        "{:<8} {:>8} {:>7} {:>10.2} {:>10.2} {:>10.2} {:>10.2}\n",
+        op.as_str(),
+        stats.count(),
+        stats.errors,
+        millis(stats.percentile(50.0)),
+        millis(stats.percentile(95.0)),
+        millis(stats.percentile(99.0)),
+        millis(stats.percentile(100.0)),
+      ));
+    }
+    out.push_str(&format!(
+      "\n{:.0} ops/s over {:.2}s\n",
+      self.throughput(),
+      self.elapsed.as_secs_f64()
+    ));
+    out
+  }
+}
+
+fn millis(d: Duration) -> f64 {
+  d.as_secs_f64() * 1000.0
+}
+
+/// Nearest-rank percentile of unsorted samples (zero when there are none)
+fn percentile(samples: &[Duration], p: f64) -> Duration {
+  if samples.is_empty() {
+    return Duration::ZERO;
+  }
+  let mut sorted = samples.to_vec();
+  sorted.sort_unstable();
+  let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+  sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Run the benchmark and clean up its collection
+pub async fn run(options: BenchOptions) -> Result<BenchReport, anyhow::Error> {
+  let concurrency = options.concurrency.max(1);
+  let mut connections = Vec::with_capacity(concurrency);
+  for _ in 0..concurrency {
+    let conn = match &options.token {
+      Some(token) => Connection::connect_with_token(&options.url, token).await?,
+      None => Connection::connect(&options.url).await?,
+    };
+    connections.push(Arc::new(conn));
+  }
+
+  let collection = format!("bench_{}", &Uuid::new_v4().simple().to_string()[..12]);
+  let ids = Arc::new(Mutex::new(Vec::new()));
+  let result = measure(&options, &connections, &collection, &ids).await;
+
+  let ids = std::mem::take(&mut *ids.lock());
+  let cleanup = drop_documents(&connections[0], &collection, &ids).await;
+  let report = result?;
+  cleanup?;
+  Ok(report)
+}
+
+async fn measure(
+  options: &BenchOptions,
+  connections: &[Arc<Connection>],
+  collection: &str,
+  ids: &Arc<Mutex<Vec<Uuid>>>,
+) -> Result<BenchReport, anyhow::Error> {
+  for n in 0..SEED_DOCUMENTS {
+    let id = insert(&connections[0], collection, n).await?;
+    ids.lock().push(id);
+  }
+
+  let per_worker = options.operations / connections.len();
+  let extra = options.operations % connections.len();
+  let started = Instant::now();
+  let mut workers = Vec::with_capacity(connections.len());
+  for (i, conn) in connections.iter().enumerate() {
+    let conn = conn.clone();
+    let ids = ids.clone();
+    let collection = collection.to_string();
+    let mix = options.mix;
+    let count = per_worker + usize::from(i < extra);
+    workers.push(tokio::spawn(async move {
+      let mut samples = Vec::with_capacity(count);
+      for n in 0..count {
+        let op = mix.pick(&mut rand::thread_rng());
+        let start = Instant::now();
+        let ok = match op {
+          Operation::Insert => match insert(&conn, &collection, n).await {
+            Ok(id) => {
+              ids.lock().push(id);
+              true
+            }
+            Err(_) => false,
+          },
+          Operation::Query => query(&conn, &collection).await.is_ok(),
+          Operation::Update => {
+            let target = {
+              let ids = ids.lock();
+              ids[rand::thread_rng().gen_range(0..ids.len())]
+            };
+            update(&conn, &collection, target, n).await.is_ok()
+          }
+        };
+        samples.push((op, ok, start.elapsed()));
+      }
+      samples
+    }));
+  }
+
+  let mut report = BenchReport {
+    collection: collection.to_string(),
+    elapsed: Duration::ZERO,
+    insert: OperationStats::default(),
+    query: OperationStats::default(),
+    update: OperationStats::default(),
+  };
+  for worker in workers {
+    for (op, ok, latency) in worker.await? {
+      let stats = report.stats_mut(op);
+      if ok {
+        stats.latencies.push(latency);
+      } else {
+        stats.errors += 1;
+      }
+    }
+  }
+  report.elapsed = started.elapsed();
+  Ok(report)
+}
+
+/// The data of a successful reply, or the server's error
+fn result_data(reply: ServerMessage) -> Result<serde_json::Value, anyhow::Error> {
+  match reply {
+    ServerMessage::Result { data, .. } => Ok(data),
+    ServerMessage::Error { error, .. } => Err(anyhow::anyhow!(error)),
+    other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+  }
+}
+
+/// Body of the `n`th document a worker writes
+fn document(n: usize) -> serde_json::Value {
+  json!({
+    "n": n,
+    "score": rand::thread_rng().gen_range(0..1000),
+    "name": format!("doc {}", n),
+  })
+}
+
+async fn insert(conn: &Connection, collection: &str, n: usize) -> Result<Uuid, anyhow::Error> {
+  let data = result_data(
+    conn
+      .send(ClientMessage::Insert {
+        id: Uuid::new_v4().to_string(),
+        collection: collection.to_string(),
+        data: document(n),
+        idempotency_key: None,
+        preserve_created_at: None,
+        no_touch: false,
+      })
+      .await?,
+  )?;
+  data
+    .get("id")
+    .and_then(|id| id.as_str())
+    .and_then(|id| id.parse().ok())
+    .ok_or_else(|| anyhow::anyhow!("Insert reply has no document id"))
+}
+
+async fn query(conn: &Connection, collection: &str) -> Result<(), anyhow::Error> {
+  let q = format!(
+    "db.table(\"{}\").filter(r => r.score > {}).limit(10).run()",
+    collection,
+    rand::thread_rng().gen_range(0..1000)
+  );
+  result_data(conn.query(&q).await?).map(|_| ())
+}
+
+async fn update(
+  conn: &Connection,
+  collection: &str,
+  document_id: Uuid,
+  n: usize,
+) -> Result<(), anyhow::Error> {
+  let reply = conn
+    .send(ClientMessage::Update {
+      id: Uuid::new_v4().to_string(),
+      collection: collection.to_string(),
+      document_id,
+      data: document(n),
+      no_touch: false,
+      apply_defaults: false,
+    })
+    .await?;
+  result_data(reply).map(|_| ())
+}
+
+/// Delete every document the run inserted
+async fn drop_documents(
+  conn: &Connection,
+  collection: &str,
+  ids: &[Uuid],
+) -> Result<(), anyhow::Error> {
+  let mut failed = 0;
+  for id in ids {
+    let reply = conn
+      .send(ClientMessage::Delete {
+        id: Uuid::new_v4().to_string(),
+        collection: collection.to_string(),
+        document_id: *id,
+      })
+      .await?;
+    if result_data(reply).is_err() {
+      failed += 1;
+    }
+  }
+  if failed > 0 {
+    anyhow::bail!(
+      "Could not delete {} document(s) from '{}'",
+      failed,
+      collection
+    );
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_operation_mix() {
+    assert_eq!(
+      "50:30:20".parse::<OperationMix>().unwrap(),
+      OperationMix {
+        insert: 50,
+        query: 30,
+        update: 20
+      }
+    );
+    assert!("50:50".parse::<OperationMix>().is_err());
+    assert!("0:0:0".parse::<OperationMix>().is_err());
+    assert!("a:b:c".parse::<OperationMix>().is_err());
+
+    let reads_only: OperationMix = "0:1:0".parse().unwrap();
+    let mut rng = rand::thread_rng();
+    assert!((0..100).all(|_| reads_only.pick(&mut rng) == Operation::Query));
+  }
+
+  #[test]
+  fn test_percentile() {
+    let samples: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+    assert_eq!(percentile(&samples, 50.0), Duration::from_millis(50));
+    assert_eq!(percentile(&samples, 99.0), Duration::from_millis(99));
+    assert_eq!(percentile(&samples, 100.0), Duration::from_millis(100));
+    assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+  }
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
 
-use super::config::format_memory_size;
+use super::config::{format_memory_size, CacheDebugConfig};
 use super::entry::CacheValue;
 use super::events::CacheSubscriptionManager;
 use super::resp::RespValue;
@@ -15,6 +15,7 @@ pub struct CommandContext {
   pub store: Arc<InMemoryCacheStore>,
   pub subscriptions: Arc<CacheSubscriptionManager>,
   pub client_id: Uuid,
+  pub debug: CacheDebugConfig,
 }
 
 /// Execute a Redis command
@@ -48,6 +49,9 @@ pub async fn execute_command(ctx: &CommandContext, cmd: &str, args: &[String]) -
     "PSUBSCRIBE" => cmd_psubscribe(ctx, args),
     "UNSUBSCRIBE" => cmd_unsubscribe(ctx, args),
     "PUNSUBSCRIBE" => cmd_punsubscribe(ctx, args),
+    "DEBUG" => cmd_debug(ctx, args).await,
+    "OBJECT" => cmd_object(ctx, args),
+    "MEMORY" => cmd_memory(ctx, args),
     "CLIENT" => cmd_client(args),
     "CONFIG" => cmd_config(args),
     "COMMAND" => cmd_command(),
@@ -614,6 +618,69 @@ fn cmd_punsubscribe(ctx: &CommandContext, args: &[String]) -> RespValue {
   }
 }
 
+async fn cmd_debug(ctx: &CommandContext, args: &[String]) -> RespValue {
+  if !ctx.debug.commands {
+    return RespValue::error("ERR DEBUG commands are disabled (caching.debug.commands)");
+  }
+  let subcommand = args.first().map(|s| s.to_uppercase());
+  match subcommand.as_deref() {
+    Some("SLEEP") => {
+      if args.len() != 2 {
+        return RespValue::error("ERR wrong number of arguments for 'debug|sleep' command");
+      }
+      let delay = match args[1].parse::<f64>().map(Duration::try_from_secs_f64) {
+        Ok(Ok(delay)) => delay,
+        _ => return RespValue::error("ERR value is not a valid float"),
+      };
+      tokio::time::sleep(delay).await;
+      RespValue::ok()
+    }
+    Some(other) => RespValue::error(&format!("ERR unknown subcommand '{}'", other)),
+    None => RespValue::error("ERR wrong number of arguments for 'debug' command"),
+  }
+}
+
+fn cmd_object(ctx: &CommandContext, args: &[String]) -> RespValue {
+  let subcommand = args.first().map(|s| s.to_uppercase());
+  match subcommand.as_deref() {
+    Some("ENCODING") => {
+      if args.len() != 2 {
+        return RespValue::error("ERR wrong number of arguments for 'object|encoding' command");
+      }
+      match ctx.store.peek(&args[1]) {
+        Some(entry) => RespValue::bulk(entry.value.encoding()),
+        None => RespValue::null_bulk(),
+      }
+    }
+    Some(other) => RespValue::error(&format!("ERR unknown subcommand '{}'", other)),
+    None => RespValue::error("ERR wrong number of arguments for 'object' command"),
+  }
+}
+
+fn cmd_memory(ctx: &CommandContext, args: &[String]) -> RespValue {
+  let subcommand = args.first().map(|s| s.to_uppercase());
+  match subcommand.as_deref() {
+    Some("USAGE") => {
+      // SAMPLES is accepted for compatibility; sizes are tracked per entry
+      let valid = match args.len() {
+        2 => true,
+        4 => args[2].eq_ignore_ascii_case("SAMPLES") && args[3].parse::<u64>().is_ok(),
+        _ => false,
+      };
+      if !valid {
+        return RespValue::error("ERR syntax error");
+      }
+      // Same per-entry size that counts towards max_memory
+      match ctx.store.peek(&args[1]) {
+        Some(entry) => RespValue::integer(entry.size as i64),
+        None => RespValue::null_bulk(),
+      }
+    }
+    Some(other) => RespValue::error(&format!("ERR unknown subcommand '{}'", other)),
+    None => RespValue::error("ERR wrong number of arguments for 'memory' command"),
+  }
+}
+
 fn cmd_client(args: &[String]) -> RespValue {
   let subcommand = args.first().map(|s| s.to_uppercase());
   match subcommand.as_deref() {
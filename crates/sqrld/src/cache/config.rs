@@ -1,8 +1,10 @@
 //! Cache configuration
 
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use super::store::EvictionPolicy;
+use crate::query::WarmupQuery;
 
 /// Cache mode: builtin in-memory or proxy to external Redis
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -118,6 +120,42 @@ pub struct CacheConfig {
   /// Proxy configuration (used in proxy mode)
   #[serde(default)]
   pub proxy: CacheProxyConfig,
+
+  /// Testing aids (builtin mode)
+  #[serde(default)]
+  pub debug: CacheDebugConfig,
+
+  /// Queries run and cached whenever the cache starts
+  #[serde(default)]
+  pub warmup: Vec<WarmupQuery>,
+}
+
+/// Cache testing aids, disabled by default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheDebugConfig {
+  /// Accept DEBUG commands
+  #[serde(default)]
+  pub commands: bool,
+
+  /// Artificial delay added before a command runs, in milliseconds
+  #[serde(default)]
+  pub latency_ms: u64,
+
+  /// Percentage of commands (0-100) that get the delay
+  #[serde(default)]
+  pub latency_percent: f64,
+}
+
+impl CacheDebugConfig {
+  /// Delay to inject before the next command, if it is one of the chosen
+  /// percentage
+  pub fn injected_latency(&self) -> Option<Duration> {
+    if self.latency_ms == 0 || self.latency_percent <= 0.0 {
+      return None;
+    }
+    (rand::random::<f64>() * 100.0 < self.latency_percent)
+      .then(|| Duration::from_millis(self.latency_ms))
+  }
 }
 
 /// Snapshot persistence configuration
@@ -162,6 +200,8 @@ impl Default for CacheConfig {
       snapshot: CacheSnapshotConfig::default(),
       mode: CacheMode::default(),
       proxy: CacheProxyConfig::default(),
+      debug: CacheDebugConfig::default(),
+      warmup: Vec::new(),
     }
   }
 }
@@ -187,6 +227,12 @@ impl From<&crate::server::CachingSection> for CacheConfig {
       },
       mode: CacheMode::default(),
       proxy: CacheProxyConfig::default(),
+      debug: CacheDebugConfig {
+        commands: section.debug.commands,
+        latency_ms: section.debug.latency_ms,
+        latency_percent: section.debug.latency_percent,
+      },
+      warmup: section.warmup.clone(),
     }
   }
 }
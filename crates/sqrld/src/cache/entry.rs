@@ -84,6 +84,16 @@ impl CacheValue {
     }
   }
 
+  /// Name of the value's representation, as reported by OBJECT ENCODING
+  pub fn encoding(&self) -> &'static str {
+    match self {
+      CacheValue::Null => "null",
+      CacheValue::String(_) => "string",
+      CacheValue::Integer(_) => "int",
+      CacheValue::Json(_) => "json",
+    }
+  }
+
   pub fn as_str(&self) -> Option<&str> {
     match self {
       CacheValue::String(s) => Some(s),
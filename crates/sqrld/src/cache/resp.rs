@@ -53,10 +53,16 @@ impl RespValue {
   /// Encode to RESP wire format
   pub fn encode(&self) -> Vec<u8> {
     let mut buf = Vec::new();
-    self.write_to(&mut buf).unwrap();
+    self.encode_into(&mut buf);
     buf
   }
 
+  /// Append the RESP wire format to `buf`
+  pub fn encode_into(&self, buf: &mut Vec<u8>) {
+    // Writing to a Vec cannot fail
+    self.write_to(buf).unwrap();
+  }
+
   fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
     match self {
       RespValue::SimpleString(s) => {
@@ -136,7 +142,9 @@ impl std::fmt::Display for RespError {
 
 impl std::error::Error for RespError {}
 
-/// RESP protocol parser
+/// RESP protocol parser. Pipelined input is parsed one value at a time;
+/// consumed bytes are only dropped when more data is fed, so draining a large
+/// pipeline stays linear.
 pub struct RespParser {
   buffer: Vec<u8>,
   pos: usize,
@@ -158,9 +166,18 @@ impl RespParser {
 
   /// Add data to the parse buffer
   pub fn feed(&mut self, data: &[u8]) {
+    if self.pos > 0 {
+      self.buffer.drain(..self.pos);
+      self.pos = 0;
+    }
     self.buffer.extend_from_slice(data);
   }
 
+  /// Whether unparsed bytes remain in the buffer
+  pub fn has_pending(&self) -> bool {
+    self.pos < self.buffer.len()
+  }
+
   /// Try to parse the next value from the buffer
   pub fn parse(&mut self) -> Result<Option<RespValue>, RespError> {
     if self.pos >= self.buffer.len() {
@@ -169,12 +186,7 @@ impl RespParser {
 
     let start_pos = self.pos;
     match self.parse_value() {
-      Ok(value) => {
-        // Successfully parsed, remove consumed bytes
-        self.buffer.drain(..self.pos);
-        self.pos = 0;
-        Ok(Some(value))
-      }
+      Ok(value) => Ok(Some(value)),
       Err(RespError::Incomplete) => {
         // Reset position, wait for more data
         self.pos = start_pos;
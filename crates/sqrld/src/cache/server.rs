@@ -11,13 +11,14 @@ use tokio::sync::oneshot;
 use uuid::Uuid;
 
 use super::commands::{execute_command, CommandContext};
-use super::config::{CacheConfig, CacheMode, CacheProxyConfig};
+use super::config::{CacheConfig, CacheDebugConfig, CacheMode, CacheProxyConfig};
 use super::events::CacheSubscriptionManager;
 use super::proxy::RedisProxyClient;
 use super::resp::{extract_command, RespParser, RespValue};
 use super::snapshot::{run_expiration_task, run_snapshot_task, SnapshotManager};
 use super::store::{CacheStore, InMemoryCacheStore};
 use crate::features::{AppState, Feature};
+use crate::query::QueryCache;
 
 /// Cache feature implementation
 pub struct CacheFeature {
@@ -27,6 +28,8 @@ pub struct CacheFeature {
   subscriptions: RwLock<Option<Arc<CacheSubscriptionManager>>>,
   shutdown_tx: RwLock<Option<oneshot::Sender<()>>>,
   running: RwLock<bool>,
+  /// Query cache backed by this feature's store while it runs
+  query_cache: RwLock<Option<Arc<QueryCache>>>,
 }
 
 impl CacheFeature {
@@ -38,6 +41,7 @@ impl CacheFeature {
       subscriptions: RwLock::new(None),
       shutdown_tx: RwLock::new(None),
       running: RwLock::new(false),
+      query_cache: RwLock::new(None),
     }
   }
 
@@ -177,6 +181,8 @@ impl Feature for CacheFeature {
           },
           mode,
           proxy,
+          debug: self.config.read().debug.clone(),
+          warmup: self.config.read().warmup.clone(),
         }
       } else {
         self.config.read().clone()
@@ -194,6 +200,31 @@ impl Feature for CacheFeature {
       }
     }
 
+    // Back the query cache with the new store. Results kept from a previous
+    // run (snapshot or proxy) may predate writes made while it was down.
+    let query_cache = state.engine_pool.query_cache();
+    query_cache.set_store(self.get_active_store());
+    query_cache.invalidate_all().await;
+    *self.query_cache.write() = Some(query_cache);
+
+    let queries = self.config.read().warmup.clone();
+    if !queries.is_empty() {
+      let backend = state.backend.clone();
+      let engine_pool = state.engine_pool.clone();
+      tokio::spawn(async move {
+        let report = engine_pool.warm_all(&queries, backend.as_ref()).await;
+        tracing::info!(
+          "Cache warmup: {} warmed, {} skipped, {} failed",
+          report.warmed,
+          report.skipped,
+          report.errors.len()
+        );
+        for e in &report.errors {
+          tracing::warn!("Cache warmup query {} failed: {}", e.index, e.error);
+        }
+      });
+    }
+
     *self.running.write() = true;
     Ok(())
   }
@@ -220,6 +251,11 @@ impl Feature for CacheFeature {
       }
     }
 
+    // Stop serving cached query results
+    if let Some(query_cache) = self.query_cache.write().take() {
+      query_cache.set_store(None);
+    }
+
     // Clear state
     *self.store.write() = None;
     *self.proxy_store.write() = None;
@@ -309,6 +345,7 @@ impl CacheFeature {
     // Spawn accept loop
     let accept_store = store.clone();
     let accept_subs = subscriptions.clone();
+    let accept_debug = config.debug.clone();
     tokio::spawn(async move {
       loop {
         tokio::select! {
@@ -317,8 +354,11 @@ impl CacheFeature {
               Ok((socket, addr)) => {
                 let client_store = accept_store.clone();
                 let client_subs = accept_subs.clone();
+                let client_debug = accept_debug.clone();
                 tokio::spawn(async move {
-                  if let Err(e) = handle_client(socket, addr, client_store, client_subs).await {
+                  if let Err(e) =
+                    handle_client(socket, addr, client_store, client_subs, client_debug).await
+                  {
                     tracing::debug!("Client {} error: {}", addr, e);
                   }
                 });
@@ -377,17 +417,20 @@ async fn handle_client(
   addr: SocketAddr,
   store: Arc<InMemoryCacheStore>,
   subscriptions: Arc<CacheSubscriptionManager>,
+  debug: CacheDebugConfig,
 ) -> Result<(), anyhow::Error> {
   tracing::debug!("Cache client connected: {}", addr);
 
   let client_id = Uuid::new_v4();
   let mut parser = RespParser::new();
-  let mut buf = [0u8; 4096];
+  let mut buf = vec![0u8; 16 * 1024];
+  let mut replies = Vec::new();
 
   let ctx = CommandContext {
     store,
     subscriptions: subscriptions.clone(),
     client_id,
+    debug,
   };
 
   loop {
@@ -398,20 +441,39 @@ async fn handle_client(
 
     parser.feed(&buf[..n]);
 
-    // Process all complete commands in buffer
-    while let Some(value) = parser.parse()? {
+    // Run every complete command in the buffer, in order, then send all the
+    // replies in one write so pipelined clients get them together
+    replies.clear();
+    loop {
+      let value = match parser.parse() {
+        Ok(Some(value)) => value,
+        Ok(None) => break,
+        Err(e) => {
+          RespValue::error(&format!("ERR Protocol error: {}", e)).encode_into(&mut replies);
+          socket.write_all(&replies).await?;
+          subscriptions.remove_client(client_id);
+          return Err(e.into());
+        }
+      };
       let response = if let Some((cmd, args)) = extract_command(&value) {
         if cmd == "QUIT" {
-          socket.write_all(&RespValue::ok().encode()).await?;
+          RespValue::ok().encode_into(&mut replies);
+          socket.write_all(&replies).await?;
           subscriptions.remove_client(client_id);
           return Ok(());
         }
+        if let Some(delay) = ctx.debug.injected_latency() {
+          tokio::time::sleep(delay).await;
+        }
         execute_command(&ctx, &cmd, &args).await
       } else {
         RespValue::error("ERR invalid command format")
       };
+      response.encode_into(&mut replies);
+    }
 
-      socket.write_all(&response.encode()).await?;
+    if !replies.is_empty() {
+      socket.write_all(&replies).await?;
     }
   }
 
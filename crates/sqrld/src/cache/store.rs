@@ -251,6 +251,13 @@ impl InMemoryCacheStore {
     }
   }
 
+  /// Look up an entry without touching it or counting a hit or miss, for
+  /// introspection commands
+  pub fn peek(&self, key: &str) -> Option<CacheEntry> {
+    let data = self.data.read();
+    data.get(key).filter(|e| !e.is_expired()).cloned()
+  }
+
   /// Get all entries for snapshot
   pub fn snapshot_entries(&self) -> Vec<SnapshotEntry> {
     let data = self.data.read();
@@ -303,6 +310,11 @@ impl InMemoryCacheStore {
           if let Ok(i) = s.parse::<i64>() {
             let new_val = i + delta;
             entry.value = CacheValue::Integer(new_val);
+            // The value is now stored as an integer; keep its size in step
+            let size = entry.value.approximate_size() + key.len();
+            self.memory_used.fetch_add(size, Ordering::Relaxed);
+            self.memory_used.fetch_sub(entry.size, Ordering::Relaxed);
+            entry.size = size;
             entry.touch();
             Ok(new_val)
           } else {
@@ -1,11 +1,25 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
-use crate::storage::{MultipartPart, MultipartUpload, ObjectAcl, StorageBucket, StorageObject};
-use crate::types::{Change, Document, OrderBySpec, Project, ProjectMember, ProjectRole};
+use super::defaults::CollectionDefaults;
+use super::encryption::FieldEncryption;
+use super::idempotency::IdempotencyClaim;
+use super::projection::ProjectionField;
+use super::sanitize::{validate_distance, validate_geo_point, validate_identifier};
+use crate::server::RateLimitError;
+use crate::storage::{
+  MultipartPart, MultipartUpload, ObjectAcl, ObjectTags, StorageBucket, StorageObject,
+};
+use crate::types::{
+  Change, Document, GeoNear, GeoPoint, OrderBySpec, OrderDirection, Project, ProjectMember,
+  ProjectRole,
+};
 
 /// API token metadata (without the actual secret)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +36,9 @@ pub struct ApiTokenInfo {
 pub enum AdminRole {
   Owner,
   Admin,
+  /// Signs in, but only reaches the projects it is a member of and none of
+  /// the server-wide settings
+  Member,
 }
 
 impl std::fmt::Display for AdminRole {
@@ -29,6 +46,7 @@ impl std::fmt::Display for AdminRole {
     match self {
       Self::Owner => write!(f, "owner"),
       Self::Admin => write!(f, "admin"),
+      Self::Member => write!(f, "member"),
     }
   }
 }
@@ -39,6 +57,7 @@ impl std::str::FromStr for AdminRole {
     match s.to_lowercase().as_str() {
       "owner" => Ok(Self::Owner),
       "admin" => Ok(Self::Admin),
+      "member" => Ok(Self::Member),
       _ => Err(format!("Invalid role: {}", s)),
     }
   }
@@ -50,10 +69,30 @@ pub struct AdminUser {
   pub id: Uuid,
   pub username: String,
   pub email: Option<String>,
+  /// Whether `email` was confirmed with a verification token
+  #[serde(default)]
+  pub email_verified: bool,
   pub role: AdminRole,
   pub created_at: DateTime<Utc>,
 }
 
+/// What a single-use admin auth token can be redeemed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminTokenPurpose {
+  PasswordReset,
+  EmailVerification,
+}
+
+impl AdminTokenPurpose {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::PasswordReset => "password_reset",
+      Self::EmailVerification => "email_verification",
+    }
+  }
+}
+
 /// Admin session info
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdminSession {
@@ -62,6 +101,412 @@ pub struct AdminSession {
   pub expires_at: DateTime<Utc>,
 }
 
+/// Mean Earth radius used for geo distances
+pub const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Documents sampled to compute field frequencies for collection stats
+pub const STATS_SAMPLE_SIZE: usize = 1000;
+
+/// Number of top-level fields reported in collection stats
+pub const STATS_TOP_FIELDS: usize = 20;
+
+/// Estimates below this are counted exactly even when an approximate count
+/// was asked for, as counting a small collection is cheap
+pub const APPROXIMATE_COUNT_MIN: u64 = 10_000;
+
+/// Number of documents matched by a listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentCount {
+  pub total: u64,
+  /// `total` is the query planner's estimate rather than a count
+  pub approximate: bool,
+}
+
+/// Storage statistics for a collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionStats {
+  pub document_count: u64,
+  /// Stored size of the document data (JSONB size on PostgreSQL, JSON text on SQLite)
+  pub total_bytes: u64,
+  pub avg_document_bytes: f64,
+  /// Documents the field frequencies were computed from
+  pub sampled: u64,
+  /// Most common top-level fields in the sample
+  pub fields: Vec<FieldFrequency>,
+}
+
+/// Timestamp control for a single insert or update. The default lets the
+/// server set `created_at` and `updated_at` to the current time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteOptions {
+  /// Insert with this `created_at` instead of now (keeps history on import)
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub preserve_created_at: Option<DateTime<Utc>>,
+  /// Leave `updated_at` alone: an update keeps the previous value and an
+  /// insert uses `created_at`
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub no_touch: bool,
+  /// Fill in the collection's defaults on update too (inserts always do)
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub apply_defaults: bool,
+  /// API token or admin user making the write, used for `$user` defaults.
+  /// Set by the server from the request's credentials, never by clients.
+  #[serde(skip)]
+  pub author: Option<Uuid>,
+  /// Refuse an insert that would leave the project with more documents
+  /// (0 = no quota). Checked in the insert's transaction. Set by the server
+  /// from the project's limits, never by clients.
+  #[serde(skip)]
+  pub max_documents: u64,
+}
+
+impl WriteOptions {
+  /// `created_at` and `updated_at` for a new document inserted at `now`
+  pub fn insert_timestamps(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let created_at = self.preserve_created_at.unwrap_or(now);
+    let updated_at = if self.no_touch { created_at } else { now };
+    (created_at, updated_at)
+  }
+}
+
+/// Fail with [`RateLimitError::ProjectQuotaExceeded`] if a project holding
+/// `documents` documents is over a quota of `max_documents` (0 = no quota)
+pub(crate) fn check_document_quota(
+  project_id: Uuid,
+  documents: u64,
+  max_documents: u64,
+) -> Result<(), anyhow::Error> {
+  if max_documents > 0 && documents > max_documents {
+    return Err(
+      RateLimitError::ProjectQuotaExceeded {
+        project_id,
+        limit: max_documents,
+      }
+      .into(),
+    );
+  }
+  Ok(())
+}
+
+/// A write to a single document, for [`DatabaseBackend::write_document`]
+#[derive(Debug, Clone)]
+pub enum DocumentWrite {
+  Insert {
+    data: serde_json::Value,
+    options: WriteOptions,
+  },
+  Update {
+    id: Uuid,
+    data: serde_json::Value,
+    options: WriteOptions,
+  },
+  Delete {
+    id: Uuid,
+  },
+  /// Clear `deleted_at` on a soft-deleted document
+  Restore {
+    id: Uuid,
+  },
+}
+
+/// Documents of a streamed list, in the batches they were read from the
+/// database
+pub type DocumentBatches =
+  futures_util::stream::BoxStream<'static, Result<Vec<Document>, anyhow::Error>>;
+
+/// Read control for a single get or list. The default skips soft-deleted
+/// documents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadOptions {
+  /// Also return soft-deleted documents, with `deleted_at` set
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub with_deleted: bool,
+}
+
+/// Soft-delete settings of a collection, stored in `soft_delete_collections`.
+/// While set, deletes mark documents with `deleted_at` instead of removing them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SoftDeleteSettings {
+  /// Seconds a soft-deleted document is kept before it is purged for good
+  /// (0 = keep until restored)
+  #[serde(default)]
+  pub retention_secs: u64,
+}
+
+/// Quotas for one project, stored in `project_limits`. Zero means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectLimits {
+  /// Requests per second across all of the project's clients
+  #[serde(default)]
+  pub requests_per_second: u32,
+  /// Requests that may be made at once before the rate applies (0 = same as
+  /// `requests_per_second`)
+  #[serde(default)]
+  pub burst_size: u32,
+  /// Documents the project may hold across all collections
+  #[serde(default)]
+  pub max_documents: u64,
+}
+
+/// Result of a raw SQL statement
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RawSqlResult {
+  pub columns: Vec<String>,
+  pub rows: Vec<Vec<serde_json::Value>>,
+  /// Rows inserted, updated or deleted by statements that return no rows
+  pub rows_affected: u64,
+  /// More rows were produced than returned
+  pub truncated: bool,
+}
+
+/// A statement running on one of the server's database connections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveQuery {
+  /// Backend process ID, used to cancel the statement
+  pub pid: i32,
+  pub query: String,
+  pub started_at: DateTime<Utc>,
+  pub duration_ms: u64,
+  /// What the statement is waiting on, e.g. `Lock` or `IO`
+  pub wait_event: Option<String>,
+}
+
+/// How often a top-level field appears in the sampled documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldFrequency {
+  pub field: String,
+  pub count: u64,
+  /// Fraction of sampled documents containing the field
+  pub frequency: f64,
+}
+
+impl CollectionStats {
+  pub fn new(
+    document_count: u64,
+    total_bytes: u64,
+    sampled: u64,
+    field_counts: Vec<(String, u64)>,
+  ) -> Self {
+    let ratio = |n: u64, of: u64| if of == 0 { 0.0 } else { n as f64 / of as f64 };
+    Self {
+      document_count,
+      total_bytes,
+      avg_document_bytes: ratio(total_bytes, document_count),
+      sampled,
+      fields: field_counts
+        .into_iter()
+        .map(|(field, count)| FieldFrequency {
+          field,
+          count,
+          frequency: ratio(count, sampled),
+        })
+        .collect(),
+    }
+  }
+}
+
+/// How long the change listener's cleanup task keeps change_queue rows.
+/// A row goes once it is both outside the newest `max_entries` and older
+/// than `max_age`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeRetention {
+  pub max_entries: u32,
+  pub max_age: Duration,
+  pub cleanup_interval: Duration,
+}
+
+impl Default for ChangeRetention {
+  fn default() -> Self {
+    Self {
+      max_entries: 10_000,
+      max_age: Duration::from_secs(3600),
+      cleanup_interval: Duration::from_secs(300),
+    }
+  }
+}
+
+/// Periodic `ANALYZE`, and optionally `VACUUM`, of the busiest tables, run
+/// next to the change listener. PostgreSQL only; without it, table upkeep is
+/// left to autovacuum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableMaintenance {
+  pub interval: Duration,
+  pub vacuum: bool,
+}
+
+/// Compression of large documents. PostgreSQL only: the `data` column of a
+/// row larger than `threshold_bytes` is compressed by TOAST and decompressed
+/// transparently on read, so SQL filters, indexes and change capture keep
+/// working on plain JSONB. Smaller rows are stored as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocumentCompression {
+  pub threshold_bytes: u32,
+  pub method: CompressionMethod,
+}
+
+impl DocumentCompression {
+  /// Smallest and largest row size PostgreSQL accepts as `toast_tuple_target`
+  pub const MIN_THRESHOLD: u32 = 128;
+  pub const MAX_THRESHOLD: u32 = 8160;
+
+  pub fn new(threshold_bytes: u32, method: CompressionMethod) -> Result<Self, anyhow::Error> {
+    if !(Self::MIN_THRESHOLD..=Self::MAX_THRESHOLD).contains(&threshold_bytes) {
+      anyhow::bail!(
+        "Compression threshold must be {}-{} bytes",
+        Self::MIN_THRESHOLD,
+        Self::MAX_THRESHOLD
+      );
+    }
+    Ok(Self {
+      threshold_bytes,
+      method,
+    })
+  }
+}
+
+/// Algorithm PostgreSQL compresses large values with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionMethod {
+  /// Built in, always available
+  Pglz,
+  /// Faster; needs PostgreSQL 14+ built with lz4
+  Lz4,
+}
+
+impl CompressionMethod {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Pglz => "pglz",
+      Self::Lz4 => "lz4",
+    }
+  }
+}
+
+impl std::str::FromStr for CompressionMethod {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "pglz" => Ok(Self::Pglz),
+      "lz4" => Ok(Self::Lz4),
+      _ => Err(format!("Invalid compression method: {}", s)),
+    }
+  }
+}
+
+/// Checkout behaviour of the PostgreSQL connection pool. PostgreSQL only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolSettings {
+  pub recycling: PoolRecycling,
+  /// Connections opened at startup, before the first request needs them
+  pub min_idle: usize,
+  /// Longest wait for a free connection when all are in use
+  pub wait_timeout: Option<Duration>,
+  /// Longest time opening a new connection may take
+  pub create_timeout: Option<Duration>,
+  /// Longest time the check of a returned connection may take
+  pub recycle_timeout: Option<Duration>,
+}
+
+impl Default for PoolSettings {
+  fn default() -> Self {
+    Self {
+      recycling: PoolRecycling::Verified,
+      min_idle: 0,
+      wait_timeout: Some(Duration::from_secs(30)),
+      create_timeout: Some(Duration::from_secs(10)),
+      recycle_timeout: Some(Duration::from_secs(5)),
+    }
+  }
+}
+
+/// How a pooled connection is checked before it is handed out again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolRecycling {
+  /// Only check that the client hasn't seen the connection close. A
+  /// connection the server dropped silently fails the next query on it.
+  Fast,
+  /// Also run an empty query first, replacing the connection if it fails
+  Verified,
+}
+
+impl PoolRecycling {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Fast => "fast",
+      Self::Verified => "verified",
+    }
+  }
+}
+
+impl std::str::FromStr for PoolRecycling {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "fast" => Ok(Self::Fast),
+      "verified" => Ok(Self::Verified),
+      _ => Err(format!("Invalid pool recycling method: {}", s)),
+    }
+  }
+}
+
+/// Heartbeat of the background change listener, updated on every poll cycle
+#[derive(Debug)]
+pub struct ListenerHeartbeat {
+  poll_interval: Duration,
+  /// Unix millis of the last beat (0 = never)
+  last_beat: AtomicI64,
+}
+
+impl ListenerHeartbeat {
+  /// Shortest age at which a heartbeat counts as stale, so a single slow poll
+  /// doesn't flap readiness for fast-polling listeners
+  const MIN_STALE_AFTER: Duration = Duration::from_secs(5);
+
+  pub fn new(poll_interval: Duration) -> Self {
+    Self {
+      poll_interval,
+      last_beat: AtomicI64::new(0),
+    }
+  }
+
+  /// Record a completed poll cycle
+  pub fn beat(&self) {
+    self
+      .last_beat
+      .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+  }
+
+  /// Time of the last completed poll cycle
+  pub fn last_beat(&self) -> Option<DateTime<Utc>> {
+    match self.last_beat.load(Ordering::Relaxed) {
+      0 => None,
+      millis => DateTime::from_timestamp_millis(millis),
+    }
+  }
+
+  /// Age after which the listener is considered dead
+  pub fn stale_after(&self) -> Duration {
+    (self.poll_interval * 2).max(Self::MIN_STALE_AFTER)
+  }
+
+  /// Whether the listener has completed a poll cycle recently
+  pub fn is_alive(&self) -> bool {
+    self.last_beat().is_some_and(|last| {
+      (Utc::now() - last)
+        .to_std()
+        .map_or(true, |age| age <= self.stale_after())
+    })
+  }
+}
+
+/// Computes a document's new data from its current data
+pub type DocumentEdit =
+  Box<dyn FnOnce(serde_json::Value) -> Result<serde_json::Value, anyhow::Error> + Send>;
+
+/// Statement parameters that come before a filter's own in `list` queries
+/// (project ID and collection), so filter parameter `$1` is bound third
+pub const FILTER_PARAM_OFFSET: usize = 2;
+
 /// SQL dialect for query compilation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SqlDialect {
@@ -99,11 +544,104 @@ impl SqlDialect {
     }
   }
 
+  /// Generate SQL for accessing a JSON field as a JSON value
+  pub fn json_value(&self, field: &str) -> String {
+    match self {
+      Self::Postgres => format!("data{}", self.field_to_path(field).replace("->>", "->")),
+      Self::Sqlite => format!("(data -> '$.{}')", field),
+    }
+  }
+
+  /// Generate SQL comparing a JSON field with statement parameter `index`
+  /// (1-based). The value is bound as JSONB on Postgres and as a plain SQL
+  /// value on SQLite, so one statement serves every parameter type.
+  pub fn json_param_compare(&self, field: &str, op: &str, index: usize) -> String {
+    match self {
+      Self::Postgres => format!("{} {} ${}::jsonb", self.json_value(field), op, index),
+      Self::Sqlite => format!("json_extract(data, '$.{}') {} ?{}", field, op, index),
+    }
+  }
+
+  /// Generate SQL building a JSON object from projected and computed fields
+  pub fn json_projection(&self, fields: &[ProjectionField]) -> String {
+    let pairs = fields
+      .iter()
+      .map(|f| format!("'{}', {}", f.name, f.to_sql(*self)))
+      .collect::<Vec<_>>()
+      .join(", ");
+    match self {
+      Self::Postgres => format!("jsonb_build_object({})", pairs),
+      Self::Sqlite => format!("json_object({})", pairs),
+    }
+  }
+
   /// Generate SQL for ordering by a JSON field
   pub fn json_order(&self, field: &str) -> String {
     self.json_text(field)
   }
 
+  /// Generate SQL for the great-circle distance in meters between a
+  /// `{ lat, lng }` field and a fixed point (haversine formula)
+  pub fn geo_distance(&self, field: &str, point: GeoPoint) -> Result<String, anyhow::Error> {
+    validate_geo_point(point)?;
+    let lat = self.json_numeric(&format!("{}.lat", field));
+    let lng = self.json_numeric(&format!("{}.lng", field));
+    let a = format!(
+      "power(sin(radians({lat} - ({lat0})) / 2), 2) + {cos0} * cos(radians({lat})) * power(sin(radians({lng} - ({lng0})) / 2), 2)",
+      lat = lat,
+      lng = lng,
+      lat0 = point.lat,
+      lng0 = point.lng,
+      cos0 = point.lat.to_radians().cos(),
+    );
+    // Rounding can push sqrt(a) a hair above 1, which asin rejects
+    let clamped = match self {
+      Self::Postgres => format!("least(sqrt({}), 1.0)", a),
+      Self::Sqlite => format!("min(sqrt({}), 1.0)", a),
+    };
+    Ok(format!(
+      "({} * asin({}))",
+      2.0 * EARTH_RADIUS_METERS,
+      clamped
+    ))
+  }
+
+  /// Generate SQL matching `{ lat, lng }` fields within `max_distance` meters
+  pub fn geo_near(&self, field: &str, near: &GeoNear) -> Result<String, anyhow::Error> {
+    validate_distance(near.max_distance)?;
+    Ok(format!(
+      "{} <= {}",
+      self.geo_distance(field, near.point())?,
+      near.max_distance
+    ))
+  }
+
+  /// Generate the ORDER BY clause for listing documents, sorting by each
+  /// key in turn.
+  ///
+  /// Rows are tie-broken by `created_at, id` in the direction of the last
+  /// key, and ordered by those alone when no order is given, so repeated
+  /// calls return rows in the same order and offset pagination neither
+  /// repeats nor skips documents.
+  pub fn order_by(&self, order: &[OrderBySpec]) -> Result<String, anyhow::Error> {
+    let mut sql = String::from(" ORDER BY ");
+    let mut dir = "ASC";
+    for o in order {
+      validate_identifier(&o.field)?;
+      dir = match o.direction {
+        OrderDirection::Asc => "ASC",
+        OrderDirection::Desc => "DESC",
+      };
+      let key = match o.near {
+        Some(point) => self.geo_distance(&o.field, point)?,
+        None => self.json_text(&o.field),
+      };
+      sql.push_str(&format!("{} {}, ", key, dir));
+    }
+    sql.push_str(&format!("created_at {dir}, id {dir}", dir = dir));
+    Ok(sql)
+  }
+
   /// Convert a dotted field path to SQL JSON path syntax
   fn field_to_path(&self, field: &str) -> String {
     match self {
@@ -135,6 +673,9 @@ impl SqlDialect {
 pub trait DatabaseBackend: Send + Sync {
   fn dialect(&self) -> SqlDialect;
 
+  /// Field-level encryption applied to stored documents
+  fn field_encryption(&self) -> Arc<FieldEncryption>;
+
   async fn init_schema(&self) -> Result<(), anyhow::Error>;
   async fn drop_schema(&self) -> Result<(), anyhow::Error>;
 
@@ -166,6 +707,28 @@ pub trait DatabaseBackend: Send + Sync {
 
   async fn delete_project(&self, id: Uuid) -> Result<bool, anyhow::Error>;
 
+  /// Quotas configured for a project, if any
+  async fn get_project_limits(
+    &self,
+    project_id: Uuid,
+  ) -> Result<Option<ProjectLimits>, anyhow::Error>;
+
+  /// Quotas of every project that has them
+  async fn list_project_limits(&self) -> Result<Vec<(Uuid, ProjectLimits)>, anyhow::Error>;
+
+  /// Create or replace a project's quotas
+  async fn set_project_limits(
+    &self,
+    project_id: Uuid,
+    limits: &ProjectLimits,
+  ) -> Result<(), anyhow::Error>;
+
+  /// Remove a project's quotas. Returns whether any were set.
+  async fn delete_project_limits(&self, project_id: Uuid) -> Result<bool, anyhow::Error>;
+
+  /// Documents stored in a project across all collections
+  async fn count_project_documents(&self, project_id: Uuid) -> Result<u64, anyhow::Error>;
+
   // =========================================================================
   // Project Membership Methods
   // =========================================================================
@@ -211,12 +774,37 @@ pub trait DatabaseBackend: Send + Sync {
     collection: &str,
     data: serde_json::Value,
   ) -> Result<Document, anyhow::Error>;
+  /// Insert with explicit timestamp handling
+  async fn insert_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    data: serde_json::Value,
+    options: WriteOptions,
+  ) -> Result<Document, anyhow::Error>;
   async fn get(
     &self,
     project_id: Uuid,
     collection: &str,
     id: Uuid,
   ) -> Result<Option<Document>, anyhow::Error>;
+  /// Get with explicit read handling (`with_deleted` finds soft-deleted documents)
+  async fn get_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+    options: ReadOptions,
+  ) -> Result<Option<Document>, anyhow::Error>;
+  /// Get the documents with the given IDs in one query. IDs that don't
+  /// exist are left out and the result is in no particular order.
+  async fn get_many(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    ids: &[Uuid],
+    options: ReadOptions,
+  ) -> Result<Vec<Document>, anyhow::Error>;
   async fn update(
     &self,
     project_id: Uuid,
@@ -224,25 +812,309 @@ pub trait DatabaseBackend: Send + Sync {
     id: Uuid,
     data: serde_json::Value,
   ) -> Result<Option<Document>, anyhow::Error>;
+  /// Update with explicit timestamp handling (`no_touch` keeps `updated_at`)
+  async fn update_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+    data: serde_json::Value,
+    options: WriteOptions,
+  ) -> Result<Option<Document>, anyhow::Error>;
+  /// Replace a document's data with `edit` applied to its current data, with
+  /// the row locked so no other write lands in between. Returns None if the
+  /// document doesn't exist. If `edit` fails the document is left unchanged
+  /// and its error is returned as is. Only `options.no_touch` is used.
+  async fn edit_document(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+    edit: DocumentEdit,
+    options: WriteOptions,
+  ) -> Result<Option<Document>, anyhow::Error>;
+  /// Delete a document, or mark it deleted if the collection has soft delete
+  /// enabled. Either way subscribers see a delete.
   async fn delete(
     &self,
     project_id: Uuid,
     collection: &str,
     id: Uuid,
   ) -> Result<Option<Document>, anyhow::Error>;
+  /// Apply a single-document write like `insert_with_options`,
+  /// `update_with_options`, `delete` or `restore_deleted`. With
+  /// `track_change` the ID of the change the write recorded is read in the
+  /// write's own transaction, so no other write to the document can come
+  /// between; otherwise it is None. Returns None if there is no document to
+  /// update, delete or restore.
+  async fn write_document(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    write: DocumentWrite,
+    track_change: bool,
+  ) -> Result<Option<(Document, Option<i64>)>, anyhow::Error>;
+  /// Delete the documents with the given IDs in one transaction. Returns the
+  /// IDs that were deleted; each deletion is recorded as a change. In a
+  /// soft-delete collection the documents are marked instead, like `delete`.
+  async fn delete_many(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    ids: &[Uuid],
+  ) -> Result<Vec<Uuid>, anyhow::Error>;
+  /// Delete every document of a collection with a single statement. In a
+  /// soft-delete collection the documents are marked instead, like `delete`.
+  /// The change triggers still record a delete per document, so subscribers
+  /// holding the documents can drop them. Returns the number deleted.
+  async fn drop_collection(&self, project_id: Uuid, collection: &str)
+    -> Result<u64, anyhow::Error>;
   async fn list(
     &self,
     project_id: Uuid,
     collection: &str,
+    projection: Option<&[String]>,
     filter: Option<&str>,
     order: Option<&OrderBySpec>,
     limit: Option<usize>,
     offset: Option<usize>,
   ) -> Result<Vec<Document>, anyhow::Error>;
+  /// List with explicit read handling (`with_deleted` includes soft-deleted
+  /// documents). `params` are bound to the filter's placeholders, which start
+  /// after the first `FILTER_PARAM_OFFSET` statement parameters.
+  async fn list_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+  ) -> Result<Vec<Document>, anyhow::Error>;
+  /// The documents `list_with_options` returns, read `batch_size` at a time
+  /// as the stream is polled. PostgreSQL reads from a server-side cursor, so
+  /// memory use doesn't grow with the number of matching rows; SQLite reads
+  /// the whole result first.
+  async fn list_stream(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+    batch_size: usize,
+  ) -> Result<DocumentBatches, anyhow::Error>;
+  /// Estimated cost of the `list_with_options` query with these arguments,
+  /// without running it. PostgreSQL returns the planner's total cost;
+  /// SQLite returns the number of documents it expects to read.
+  async fn estimate_list_cost(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+  ) -> Result<f64, anyhow::Error>;
+  /// Documents `list_with_options` would return without a limit or offset.
+  /// With `approximate`, backends with planner statistics may return an
+  /// estimate instead when it is at least `APPROXIMATE_COUNT_MIN`.
+  async fn count_documents(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    approximate: bool,
+    options: ReadOptions,
+  ) -> Result<DocumentCount, anyhow::Error>;
   async fn list_collections(&self, project_id: Uuid) -> Result<Vec<String>, anyhow::Error>;
+  /// Every collection of a project with its number of live documents, in
+  /// one grouped query. Collections holding only soft-deleted documents are
+  /// listed with a count of zero.
+  async fn collection_counts(&self, project_id: Uuid) -> Result<Vec<(String, u64)>, anyhow::Error>;
+  /// Document count, storage size and the most common top-level fields
+  /// (from a sample of `STATS_SAMPLE_SIZE` documents)
+  async fn collection_stats(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<CollectionStats, anyhow::Error>;
+  /// Move every document of `from` into the empty collection `to` in one
+  /// transaction. Subscribers see a delete from `from` and an insert into `to`
+  /// for each document. Returns the number of documents moved.
+  async fn rename_collection(
+    &self,
+    project_id: Uuid,
+    from: &str,
+    to: &str,
+  ) -> Result<u64, anyhow::Error>;
+  /// Copy every document of `from` into the empty collection `to` in one
+  /// transaction, giving the copies new IDs. Nothing is copied if the
+  /// project would then hold more than `max_documents` documents (0 = no
+  /// quota). Returns the number copied.
+  async fn copy_collection(
+    &self,
+    project_id: Uuid,
+    from: &str,
+    to: &str,
+    max_documents: u64,
+  ) -> Result<u64, anyhow::Error>;
+  /// Insert or replace a document verbatim, keeping its ID and timestamps (used by restore)
+  async fn restore_document(&self, doc: &Document) -> Result<(), anyhow::Error>;
+  /// Write documents verbatim in one transaction, like `restore_document`,
+  /// after deleting every document in the database when `wipe` is set.
+  /// Nothing changes if any write fails.
+  async fn restore_documents(&self, docs: &[Document], wipe: bool) -> Result<(), anyhow::Error>;
+
+  // Soft delete
+  /// Soft-delete settings of a collection (None if deletes remove documents)
+  async fn get_soft_delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<Option<SoftDeleteSettings>, anyhow::Error>;
+  /// Every collection with soft delete enabled, as (project, collection, settings)
+  async fn list_soft_delete(
+    &self,
+  ) -> Result<Vec<(Uuid, String, SoftDeleteSettings)>, anyhow::Error>;
+  /// Enable soft delete for a collection, or change its settings
+  async fn set_soft_delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    settings: SoftDeleteSettings,
+  ) -> Result<(), anyhow::Error>;
+  /// Go back to removing documents on delete. Documents already soft-deleted
+  /// stay hidden until restored or purged. Returns false if it wasn't enabled.
+  async fn disable_soft_delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<bool, anyhow::Error>;
+
+  // Collection defaults
+  /// Values inserts fill in for fields a document leaves out (None if unset)
+  async fn get_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<Option<CollectionDefaults>, anyhow::Error>;
+  /// Set a collection's defaults, replacing any previous ones
+  async fn set_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    defaults: &CollectionDefaults,
+  ) -> Result<(), anyhow::Error>;
+  /// Remove a collection's defaults. Returns false if it had none.
+  async fn delete_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<bool, anyhow::Error>;
+
+  // Client key-value store
+  /// Value stored under `namespace`/`key` (None if unset or expired)
+  async fn get_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+  ) -> Result<Option<serde_json::Value>, anyhow::Error>;
+  /// Store a value, replacing any previous one. With a `ttl` it expires after
+  /// that long, otherwise it is kept until deleted.
+  async fn set_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+    value: &serde_json::Value,
+    ttl: Option<Duration>,
+  ) -> Result<(), anyhow::Error>;
+  /// Remove a value. Returns false if none was stored.
+  async fn delete_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+  ) -> Result<bool, anyhow::Error>;
+  /// Delete expired values. Returns the number deleted.
+  async fn purge_expired_kv(&self) -> Result<u64, anyhow::Error>;
+
+  /// Clear `deleted_at` on a soft-deleted document. Subscribers see it inserted
+  /// again. Returns None if no such soft-deleted document exists.
+  async fn restore_deleted(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+  ) -> Result<Option<Document>, anyhow::Error>;
+  /// Remove documents of a collection soft-deleted more than `retention`
+  /// ago. Returns the number removed.
+  async fn purge_deleted(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    retention: Duration,
+  ) -> Result<u64, anyhow::Error>;
+
+  // Idempotency keys (see `insert_idempotent`)
+  /// Claim `key` for a new request. A key claimed more than `ttl` ago is
+  /// treated as unused.
+  async fn claim_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+    ttl: Duration,
+  ) -> Result<IdempotencyClaim, anyhow::Error>;
+  /// Store the response of the request that claimed `key`
+  async fn complete_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+    response: &serde_json::Value,
+  ) -> Result<(), anyhow::Error>;
+  /// Forget a claimed key after its request failed
+  async fn release_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+  ) -> Result<(), anyhow::Error>;
+  /// Delete keys claimed more than `ttl` ago. Returns the number deleted.
+  async fn purge_idempotency_keys(&self, ttl: Duration) -> Result<u64, anyhow::Error>;
+
+  /// Changes recorded after `after_id`, oldest first
+  async fn list_changes(&self, after_id: i64, limit: usize) -> Result<Vec<Change>, anyhow::Error>;
+  /// Lowest and highest change IDs still in the change queue (None if empty)
+  async fn change_id_range(&self) -> Result<Option<(i64, i64)>, anyhow::Error>;
+  /// Up to `limit` changes still recorded for a document after `after_id`,
+  /// oldest first
+  async fn document_history(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    document_id: Uuid,
+    after_id: i64,
+    limit: usize,
+  ) -> Result<Vec<Change>, anyhow::Error>;
 
   fn subscribe_changes(&self) -> broadcast::Receiver<Change>;
   async fn start_change_listener(&self) -> Result<(), anyhow::Error>;
+  /// Heartbeat the change listener updates while it is running
+  fn change_listener_heartbeat(&self) -> Arc<ListenerHeartbeat>;
 
   // Token management methods (project-scoped)
   async fn create_token(
@@ -253,7 +1125,18 @@ pub trait DatabaseBackend: Send + Sync {
   ) -> Result<ApiTokenInfo, anyhow::Error>;
   async fn delete_token(&self, project_id: Uuid, id: Uuid) -> Result<bool, anyhow::Error>;
   async fn list_tokens(&self, project_id: Uuid) -> Result<Vec<ApiTokenInfo>, anyhow::Error>;
+  /// A project's API tokens with their hashes, for carrying them over to
+  /// another database
+  async fn list_token_hashes(
+    &self,
+    project_id: Uuid,
+  ) -> Result<Vec<(ApiTokenInfo, String)>, anyhow::Error>;
   async fn validate_token(&self, token_hash: &str) -> Result<Option<Uuid>, anyhow::Error>;
+  /// The API token with this hash, for callers that need more than its project
+  async fn get_token_by_hash(
+    &self,
+    token_hash: &str,
+  ) -> Result<Option<ApiTokenInfo>, anyhow::Error>;
 
   // Subscription filter methods for PostgreSQL-side filtering
   /// Register a subscription filter in the database for efficient server-side filtering
@@ -294,6 +1177,35 @@ pub trait DatabaseBackend: Send + Sync {
   /// Release a connection slot for an IP address
   async fn connection_release(&self, ip: std::net::IpAddr) -> Result<(), anyhow::Error>;
 
+  // =========================================================================
+  // Raw SQL (owner-only escape hatch)
+  // =========================================================================
+
+  /// Run a single statement with `params` bound to its placeholders ($1 on
+  /// PostgreSQL, ?1 on SQLite). At most `max_rows` rows are returned (0 = all).
+  async fn execute_raw_sql(
+    &self,
+    sql: &str,
+    params: &[serde_json::Value],
+    max_rows: usize,
+  ) -> Result<RawSqlResult, anyhow::Error>;
+
+  /// Run several `;`-separated statements without parameters. No rows are returned.
+  async fn execute_raw_sql_batch(&self, sql: &str) -> Result<RawSqlResult, anyhow::Error>;
+
+  /// Statements currently running on this server's own connections, oldest
+  /// first. Empty on backends without separate server processes.
+  async fn list_active_queries(&self) -> Result<Vec<ActiveQuery>, anyhow::Error>;
+
+  /// Cancel the statement running on connection `pid`. Returns false if `pid`
+  /// isn't running a statement for this server.
+  async fn cancel_query(&self, pid: i32) -> Result<bool, anyhow::Error>;
+
+  /// Rebuild every index of the documents table, which all collections
+  /// share, one at a time and without blocking writes where the backend
+  /// supports it. Returns the names of the rebuilt indexes.
+  async fn reindex_documents(&self) -> Result<Vec<String>, anyhow::Error>;
+
   // =========================================================================
   // Object Storage Methods
   // =========================================================================
@@ -395,6 +1307,30 @@ pub trait DatabaseBackend: Send + Sync {
     acl: ObjectAcl,
   ) -> Result<(), anyhow::Error>;
 
+  /// Tags of the latest version of an object
+  async fn get_storage_object_tags(
+    &self,
+    bucket: &str,
+    key: &str,
+  ) -> Result<ObjectTags, anyhow::Error>;
+
+  /// Replace the tags of the latest version of an object
+  async fn set_storage_object_tags(
+    &self,
+    bucket: &str,
+    key: &str,
+    tags: &ObjectTags,
+  ) -> Result<(), anyhow::Error>;
+
+  /// Latest objects under `prefix` carrying all of `tags`, for lifecycle
+  /// rules that filter by tag
+  async fn list_storage_objects_with_tags(
+    &self,
+    bucket: &str,
+    prefix: Option<&str>,
+    tags: &ObjectTags,
+  ) -> Result<Vec<StorageObject>, anyhow::Error>;
+
   /// List objects in a bucket
   async fn list_storage_objects(
     &self,
@@ -515,9 +1451,37 @@ pub trait DatabaseBackend: Send + Sync {
   /// Get admin user by ID
   async fn get_admin_user(&self, id: Uuid) -> Result<Option<AdminUser>, anyhow::Error>;
 
+  /// Admin user linked to an OIDC identity by its issuer and subject
+  async fn get_admin_user_by_oidc(
+    &self,
+    issuer: &str,
+    subject: &str,
+  ) -> Result<Option<AdminUser>, anyhow::Error>;
+
+  /// Create an admin user linked to an OIDC identity. Fails if the username
+  /// is taken; identities are never linked to existing users.
+  async fn create_oidc_admin_user(
+    &self,
+    username: &str,
+    email: Option<&str>,
+    password_hash: &str,
+    role: AdminRole,
+    issuer: &str,
+    subject: &str,
+  ) -> Result<AdminUser, anyhow::Error>;
+
   /// List all admin users
   async fn list_admin_users(&self) -> Result<Vec<AdminUser>, anyhow::Error>;
 
+  /// List admin users matching `search` (username/email substring, case
+  /// insensitive), one page at a time. Also returns the total number of matches.
+  async fn list_admin_users_paged(
+    &self,
+    search: Option<&str>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+  ) -> Result<(Vec<AdminUser>, u64), anyhow::Error>;
+
   /// Delete an admin user
   async fn delete_admin_user(&self, id: Uuid) -> Result<bool, anyhow::Error>;
 
@@ -531,6 +1495,31 @@ pub trait DatabaseBackend: Send + Sync {
     password_hash: &str,
   ) -> Result<bool, anyhow::Error>;
 
+  /// Mark an admin user's email as verified
+  async fn set_admin_user_email_verified(&self, id: Uuid) -> Result<bool, anyhow::Error>;
+
+  // =========================================================================
+  // Admin Auth Tokens (password reset, email verification)
+  // =========================================================================
+
+  /// Store a single-use token, replacing the user's unused tokens for the
+  /// same purpose
+  async fn create_admin_auth_token(
+    &self,
+    user_id: Uuid,
+    purpose: AdminTokenPurpose,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+  ) -> Result<(), anyhow::Error>;
+
+  /// Redeem a token, returning the user it was issued to. The token is
+  /// removed, so it works once; an expired token returns None.
+  async fn consume_admin_auth_token(
+    &self,
+    purpose: AdminTokenPurpose,
+    token_hash: &str,
+  ) -> Result<Option<Uuid>, anyhow::Error>;
+
   // =========================================================================
   // Admin Sessions
   // =========================================================================
@@ -555,7 +1544,7 @@ pub trait DatabaseBackend: Send + Sync {
   /// Delete all sessions for a user (logout everywhere)
   async fn delete_admin_sessions_for_user(&self, user_id: Uuid) -> Result<u64, anyhow::Error>;
 
-  /// Clean up expired sessions
+  /// Clean up expired sessions and admin auth tokens
   async fn cleanup_expired_sessions(&self) -> Result<u64, anyhow::Error>;
 
   // =========================================================================
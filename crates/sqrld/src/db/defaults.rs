@@ -0,0 +1,132 @@
+//! Collection default values.
+//!
+//! Each collection can declare values for top-level fields that inserts fill
+//! in when a document leaves them out. Two string values are resolved per
+//! write: `"$now"` becomes the current time and `"$user"` the ID of the API
+//! token or admin user making the write. Updates only apply defaults when
+//! asked to with `WriteOptions::apply_defaults`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use super::backend::{DatabaseBackend, WriteOptions};
+
+/// Default value resolved to the time of the write (RFC 3339)
+pub const DEFAULT_NOW: &str = "$now";
+
+/// Default value resolved to the ID of the token or user making the write
+pub const DEFAULT_USER: &str = "$user";
+
+/// Field -> default value of a collection, stored in `collection_defaults`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CollectionDefaults(pub Map<String, Value>);
+
+impl CollectionDefaults {
+  /// Reject empty or nested field names and `$` strings other than the
+  /// dynamic values
+  pub fn validate(&self) -> Result<(), anyhow::Error> {
+    for (field, value) in &self.0 {
+      if field.is_empty() || field.contains('.') {
+        anyhow::bail!("Default field '{}' must be a top-level field name", field);
+      }
+      if let Some(s) = value.as_str() {
+        if s.starts_with('$') && s != DEFAULT_NOW && s != DEFAULT_USER {
+          anyhow::bail!(
+            "Unknown dynamic default '{}' for field '{}' (use {} or {})",
+            s,
+            field,
+            DEFAULT_NOW,
+            DEFAULT_USER
+          );
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Fill in the fields `data` leaves out. A `$user` default is skipped
+  /// when the write has no author.
+  pub fn apply(&self, data: &mut Value, now: DateTime<Utc>, author: Option<Uuid>) {
+    let Some(doc) = data.as_object_mut() else {
+      return;
+    };
+    for (field, value) in &self.0 {
+      if doc.contains_key(field) {
+        continue;
+      }
+      let value = match value.as_str() {
+        Some(DEFAULT_NOW) => now.to_rfc3339().into(),
+        Some(DEFAULT_USER) => match author {
+          Some(author) => author.to_string().into(),
+          None => continue,
+        },
+        _ => value.clone(),
+      };
+      doc.insert(field.clone(), value);
+    }
+  }
+}
+
+/// Apply a collection's defaults to a document about to be written. Inserts
+/// always get them, updates only with `options.apply_defaults`.
+pub(crate) async fn with_defaults(
+  backend: &dyn DatabaseBackend,
+  project_id: Uuid,
+  collection: &str,
+  mut data: Value,
+  options: &WriteOptions,
+  insert: bool,
+) -> Result<Value, anyhow::Error> {
+  if !insert && !options.apply_defaults {
+    return Ok(data);
+  }
+  if let Some(defaults) = backend
+    .get_collection_defaults(project_id, collection)
+    .await?
+  {
+    defaults.apply(&mut data, Utc::now(), options.author);
+  }
+  Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn defaults(value: Value) -> CollectionDefaults {
+    serde_json::from_value(value).unwrap()
+  }
+
+  #[test]
+  fn test_apply_fills_missing_fields() {
+    let defaults = defaults(json!({
+      "status": "pending",
+      "created_at": "$now",
+      "created_by": "$user",
+    }));
+    let now = Utc::now();
+    let author = Uuid::new_v4();
+
+    let mut data = json!({"status": "done"});
+    defaults.apply(&mut data, now, Some(author));
+    assert_eq!(data["status"], "done");
+    assert_eq!(data["created_at"], now.to_rfc3339());
+    assert_eq!(data["created_by"], author.to_string());
+
+    let mut data = json!({"title": "x"});
+    defaults.apply(&mut data, now, None);
+    assert_eq!(data["status"], "pending");
+    assert!(data.get("created_by").is_none());
+  }
+
+  #[test]
+  fn test_validate() {
+    assert!(defaults(json!({"a": 1, "b": "$now"})).validate().is_ok());
+    assert!(defaults(json!({"a": "$today"})).validate().is_err());
+    assert!(defaults(json!({"a.b": 1})).validate().is_err());
+  }
+}
@@ -0,0 +1,475 @@
+//! Field-level encryption.
+//!
+//! Configured fields of a collection are encrypted with AES-256-GCM before
+//! documents are stored and decrypted when they are read back, including in
+//! change events. An encrypted value is stored as `{"$encrypted": "<hex>"}`
+//! (nonce followed by ciphertext of the value's JSON), so values written
+//! before a field was configured stay readable as plaintext.
+//!
+//! The ciphertext is bound to the collection, field path and document ID it
+//! was written for, and only the configured paths are ever decrypted, so a
+//! value copied to another field or document can't be read back. Clients
+//! can't write the `$encrypted` form themselves.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use super::backend::SqlDialect;
+use super::projection::ProjectionField;
+use super::sanitize::validate_identifier;
+use crate::server::EncryptionSection;
+use crate::types::{Change, Document, OrderBySpec, QuerySpec};
+
+/// Key of the object that replaces an encrypted value
+pub const ENCRYPTED_MARKER: &str = "$encrypted";
+
+const NONCE_LEN: usize = 12;
+
+/// Encrypts and decrypts the configured fields of each collection
+#[derive(Default)]
+pub struct FieldEncryption {
+  /// None when no key is configured
+  cipher: Option<Aes256Gcm>,
+  /// Collection -> dotted paths of its encrypted fields
+  fields: HashMap<String, Vec<String>>,
+}
+
+impl FieldEncryption {
+  pub fn new(key: &[u8; 32], fields: HashMap<String, Vec<String>>) -> Self {
+    Self {
+      cipher: Some(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))),
+      fields,
+    }
+  }
+
+  /// Build from the `encryption` config section
+  pub fn from_config(section: &EncryptionSection) -> Result<Self, anyhow::Error> {
+    for (collection, paths) in &section.collections {
+      for path in paths {
+        if path
+          .split('.')
+          .any(|part| validate_identifier(part).is_err())
+        {
+          anyhow::bail!(
+            "Invalid encrypted field '{}' for collection '{}'",
+            path,
+            collection
+          );
+        }
+      }
+    }
+    let key = match section.key.as_deref().map(str::trim) {
+      Some(key) if !key.is_empty() => key,
+      _ if section.collections.values().all(Vec::is_empty) => return Ok(Self::default()),
+      _ => anyhow::bail!("encryption.collections is set but encryption.key is not"),
+    };
+    let key: [u8; 32] = hex::decode(key)
+      .map_err(|_| anyhow::anyhow!("Encryption key must be hex-encoded"))?
+      .try_into()
+      .map_err(|_| anyhow::anyhow!("Encryption key must be 32 bytes (64 hex characters)"))?;
+    Ok(Self::new(&key, section.collections.clone()))
+  }
+
+  /// Dotted paths of the encrypted fields of `collection`
+  pub fn encrypted_fields(&self, collection: &str) -> &[String] {
+    self
+      .fields
+      .get(collection)
+      .map(Vec::as_slice)
+      .unwrap_or_default()
+  }
+
+  /// Encrypt the configured fields of document `id` about to be stored.
+  /// Missing and null fields are left alone. Data that already contains an
+  /// encrypted value is rejected, since only this process may produce one.
+  pub fn encrypt(
+    &self,
+    collection: &str,
+    id: Uuid,
+    mut data: Value,
+  ) -> Result<Value, anyhow::Error> {
+    let Some(cipher) = &self.cipher else {
+      return Ok(data);
+    };
+    let paths = self.encrypted_fields(collection);
+    if !paths.is_empty() && contains_encrypted(&data) {
+      anyhow::bail!(
+        "Objects with a single '{}' key are reserved for encrypted fields",
+        ENCRYPTED_MARKER
+      );
+    }
+    for path in paths {
+      let Some(value) = field_mut(&mut data, path) else {
+        continue;
+      };
+      if value.is_null() {
+        continue;
+      }
+      let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+      let payload = Payload {
+        msg: &serde_json::to_vec(value)?,
+        aad: associated_data(collection, path, id).as_bytes(),
+      };
+      let ciphertext = cipher
+        .encrypt(&nonce, payload)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt field '{}'", path))?;
+      let mut sealed = nonce.to_vec();
+      sealed.extend_from_slice(&ciphertext);
+      let mut marker = serde_json::Map::new();
+      marker.insert(ENCRYPTED_MARKER.to_string(), hex::encode(sealed).into());
+      *value = Value::Object(marker);
+    }
+    Ok(data)
+  }
+
+  /// Decrypt the configured fields of document `id`. A projected nested
+  /// field is found under its dotted path as a top-level key.
+  pub fn decrypt(&self, collection: &str, id: Uuid, data: &mut Value) -> Result<(), anyhow::Error> {
+    let Some(cipher) = &self.cipher else {
+      return Ok(());
+    };
+    for path in self.encrypted_fields(collection) {
+      let value = match field_mut(data, path) {
+        Some(value) => value,
+        None => match data.as_object_mut().and_then(|o| o.get_mut(path.as_str())) {
+          Some(value) => value,
+          None => continue,
+        },
+      };
+      if !is_encrypted(value) {
+        continue;
+      }
+      let sealed = value[ENCRYPTED_MARKER]
+        .as_str()
+        .and_then(|s| hex::decode(s).ok())
+        .filter(|s| s.len() > NONCE_LEN)
+        .ok_or_else(|| anyhow::anyhow!("Encrypted field '{}' is corrupted", path))?;
+      let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+      let payload = Payload {
+        msg: ciphertext,
+        aad: associated_data(collection, path, id).as_bytes(),
+      };
+      let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| {
+          anyhow::anyhow!(
+            "Failed to decrypt field '{}' (wrong key or corrupted value)",
+            path
+          )
+        })?;
+      *value = serde_json::from_slice(&plaintext)?;
+    }
+    Ok(())
+  }
+
+  pub fn decrypt_document(&self, mut doc: Document) -> Result<Document, anyhow::Error> {
+    self.decrypt(&doc.collection, doc.id, &mut doc.data)?;
+    Ok(doc)
+  }
+
+  /// Decrypt the old and new data of a change
+  pub fn decrypt_change(&self, mut change: Change) -> Result<Change, anyhow::Error> {
+    for data in [&mut change.old_data, &mut change.new_data]
+      .into_iter()
+      .flatten()
+    {
+      self.decrypt(&change.collection, change.document_id, data)?;
+    }
+    Ok(change)
+  }
+
+  /// Decrypt a change and send it to subscribers. A change that can't be
+  /// decrypted is logged and dropped instead of being sent as ciphertext.
+  pub fn broadcast_change(&self, tx: &broadcast::Sender<Change>, change: Change) {
+    let id = change.id;
+    match self.decrypt_change(change) {
+      Ok(change) => {
+        let _ = tx.send(change);
+      }
+      Err(e) => tracing::warn!("Dropping change {}: {}", id, e),
+    }
+  }
+
+  /// Reject renaming or copying a collection with encrypted fields, or into
+  /// one. Their ciphertext is bound to the collection and document ID.
+  pub fn check_move(&self, from: &str, to: &str) -> Result<(), anyhow::Error> {
+    if self.cipher.is_some() {
+      if let Some(collection) = [from, to]
+        .into_iter()
+        .find(|c| !self.encrypted_fields(c).is_empty())
+      {
+        anyhow::bail!(
+          "Collection '{}' has encrypted fields and can't be renamed or copied",
+          collection
+        );
+      }
+    }
+    Ok(())
+  }
+
+  /// Reject a projection that computes from or renames an encrypted field,
+  /// which would only ever see or return ciphertext
+  pub fn check_projection(
+    &self,
+    collection: &str,
+    projection: &[ProjectionField],
+  ) -> Result<(), anyhow::Error> {
+    for path in self.encrypted_fields(collection) {
+      for field in projection {
+        let plain = field.is_field() && field.fields() == [field.name.as_str()];
+        let used = field
+          .fields()
+          .iter()
+          .any(|f| within(f, path) || within(path, f));
+        if used && !plain {
+          anyhow::bail!("Field '{}' is encrypted and can't be computed on", path);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Reject a compiled SQL filter or ordering that uses an encrypted field,
+  /// which would only ever see ciphertext
+  pub fn check_sql(
+    &self,
+    dialect: SqlDialect,
+    collection: &str,
+    filter: Option<&str>,
+    order: &[OrderBySpec],
+  ) -> Result<(), anyhow::Error> {
+    for path in self.encrypted_fields(collection) {
+      let filtered = filter.is_some_and(|sql| match dialect {
+        SqlDialect::Postgres => {
+          sql.contains(&dialect.json_text(path)) || sql.contains(&dialect.json_value(path))
+        }
+        SqlDialect::Sqlite => {
+          sql.contains(&format!("'$.{}'", path)) || sql.contains(&format!("'$.{}.", path))
+        }
+      });
+      let ordered = order.iter().any(|o| within(&o.field, path));
+      if filtered || ordered {
+        anyhow::bail!("Field '{}' is encrypted and can't be queried on", path);
+      }
+    }
+    Ok(())
+  }
+
+  /// Reject a query whose filter (compiled or JS) or ordering uses an
+  /// encrypted field
+  pub fn check_query(&self, dialect: SqlDialect, spec: &QuerySpec) -> Result<(), anyhow::Error> {
+    let filter = spec.filter.as_ref();
+    self.check_sql(
+      dialect,
+      &spec.table,
+      filter.and_then(|f| f.compiled_sql.as_deref()),
+      &spec.order_by,
+    )?;
+    if let Some(js) = filter.map(|f| f.js_code.as_str()) {
+      self.check_script(&spec.table, js)?;
+    }
+    Ok(())
+  }
+
+  /// Reject a JS filter that reads an encrypted field
+  pub fn check_script(&self, collection: &str, js: &str) -> Result<(), anyhow::Error> {
+    for path in self.encrypted_fields(collection) {
+      let dotted = format!(".{}", path);
+      let used = js.match_indices(&dotted).any(|(i, m)| {
+        !js[i + m.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+      }) || (!path.contains('.')
+        && (js.contains(&format!("[\"{}\"]", path)) || js.contains(&format!("['{}']", path))));
+      if used {
+        anyhow::bail!("Field '{}' is encrypted and can't be queried on", path);
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Whether `value` is an encrypted value
+fn is_encrypted(value: &Value) -> bool {
+  value
+    .as_object()
+    .is_some_and(|o| o.len() == 1 && o.get(ENCRYPTED_MARKER).is_some_and(Value::is_string))
+}
+
+/// Whether an encrypted value appears anywhere in `value`
+fn contains_encrypted(value: &Value) -> bool {
+  match value {
+    Value::Object(map) => is_encrypted(value) || map.values().any(contains_encrypted),
+    Value::Array(items) => items.iter().any(contains_encrypted),
+    _ => false,
+  }
+}
+
+/// Associated data binding a ciphertext to where it was written
+fn associated_data(collection: &str, path: &str, id: Uuid) -> String {
+  format!("{}\0{}\0{}", collection, path, id)
+}
+
+/// The value at a dotted path, if present
+fn field_mut<'a>(data: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+  path
+    .split('.')
+    .try_fold(data, |value, part| value.as_object_mut()?.get_mut(part))
+}
+
+/// Whether dotted path `field` is `path` or inside it
+fn within(field: &str, path: &str) -> bool {
+  field == path
+    || field
+      .strip_prefix(path)
+      .is_some_and(|rest| rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn encryption() -> FieldEncryption {
+    FieldEncryption::new(
+      &[7; 32],
+      HashMap::from([(
+        "users".to_string(),
+        vec!["ssn".to_string(), "address.street".to_string()],
+      )]),
+    )
+  }
+
+  #[test]
+  fn test_field_encryption_roundtrip() {
+    let enc = encryption();
+    let id = Uuid::new_v4();
+    let data = json!({"name": "Alice", "ssn": "123-45-6789", "address": {"street": "Main St", "city": "Oslo"}});
+    let stored = enc.encrypt("users", id, data.clone()).unwrap();
+    assert!(is_encrypted(&stored["ssn"]));
+    assert!(is_encrypted(&stored["address"]["street"]));
+    assert_eq!(stored["name"], "Alice");
+    assert_eq!(stored["address"]["city"], "Oslo");
+
+    let mut read = stored.clone();
+    enc.decrypt("users", id, &mut read).unwrap();
+    assert_eq!(read, data);
+
+    // A projected nested field is decrypted under its dotted key
+    let mut projected = json!({"address.street": stored["address"]["street"].clone()});
+    enc.decrypt("users", id, &mut projected).unwrap();
+    assert_eq!(projected["address.street"], "Main St");
+
+    // Other collections and plaintext values pass through
+    let other = json!({"ssn": "1"});
+    assert_eq!(enc.encrypt("orders", id, other.clone()).unwrap(), other);
+    let mut plain = json!({"ssn": "1"});
+    enc.decrypt("users", id, &mut plain).unwrap();
+    assert_eq!(plain, other);
+  }
+
+  #[test]
+  fn test_field_encryption_rejects_client_ciphertext() {
+    let enc = encryption();
+    let id = Uuid::new_v4();
+    let stored = enc.encrypt("users", id, json!({"ssn": "1"})).unwrap();
+
+    // Ciphertext can't be written back, at any path
+    assert!(enc.encrypt("users", id, stored.clone()).is_err());
+    let nested = json!({"notes": [{"$encrypted": "00"}]});
+    assert!(enc.encrypt("users", id, nested).is_err());
+
+    // Only configured paths are decrypted
+    let mut moved = json!({"notes": stored["ssn"].clone()});
+    enc.decrypt("users", id, &mut moved).unwrap();
+    assert!(is_encrypted(&moved["notes"]));
+
+    // Ciphertext is bound to its document, field and collection
+    for (collection, id) in [("users", Uuid::new_v4()), ("admins", id)] {
+      let mut read = stored.clone();
+      let enc = FieldEncryption::new(
+        &[7; 32],
+        HashMap::from([(collection.to_string(), vec!["ssn".to_string()])]),
+      );
+      assert!(enc.decrypt(collection, id, &mut read).is_err());
+    }
+    let mut read = json!({"address": {"street": stored["ssn"].clone()}});
+    assert!(enc.decrypt("users", id, &mut read).is_err());
+  }
+
+  #[test]
+  fn test_field_encryption_wrong_key() {
+    let id = Uuid::new_v4();
+    let stored = encryption()
+      .encrypt("users", id, json!({"ssn": "1"}))
+      .unwrap();
+    let mut read = stored;
+    let other = FieldEncryption::new(
+      &[8; 32],
+      HashMap::from([("users".to_string(), vec!["ssn".to_string()])]),
+    );
+    assert!(other.decrypt("users", id, &mut read).is_err());
+  }
+
+  #[test]
+  fn test_field_encryption_rejects_queries() {
+    let enc = encryption();
+    for dialect in [SqlDialect::Postgres, SqlDialect::Sqlite] {
+      let sql = format!("{} = 'x'", dialect.json_text("ssn"));
+      assert!(enc.check_sql(dialect, "users", Some(&sql), &[]).is_err());
+      let sql = format!("{} = 'x'", dialect.json_text("address.street"));
+      assert!(enc.check_sql(dialect, "users", Some(&sql), &[]).is_err());
+      let sql = format!("{} = 'x'", dialect.json_text("name"));
+      assert!(enc.check_sql(dialect, "users", Some(&sql), &[]).is_ok());
+      let sql = format!("{} = 'x'", dialect.json_text("ssn"));
+      assert!(enc.check_sql(dialect, "orders", Some(&sql), &[]).is_ok());
+    }
+
+    assert!(enc.check_script("users", "doc => doc.ssn === '1'").is_err());
+    assert!(enc
+      .check_script("users", "doc => doc[\"ssn\"] === '1'")
+      .is_err());
+    assert!(enc
+      .check_script("users", "doc => doc.ssn_hint === '1'")
+      .is_ok());
+    assert!(enc
+      .check_script("users", "doc => doc.address.city === 'Oslo'")
+      .is_ok());
+
+    let projection = |fields: &[&str]| {
+      let fields = fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+      crate::db::projection::parse_projection(&fields).unwrap()
+    };
+    assert!(enc
+      .check_projection("users", &projection(&["ssn", "address", "address.street"]))
+      .is_ok());
+    assert!(enc
+      .check_projection("users", &projection(&["ssn as id_number"]))
+      .is_err());
+    assert!(enc
+      .check_projection(
+        "users",
+        &projection(&["concat(name, address.street) as label"])
+      )
+      .is_err());
+  }
+
+  #[test]
+  fn test_field_encryption_config() {
+    let section = EncryptionSection {
+      key: None,
+      collections: HashMap::from([("users".to_string(), vec!["ssn".to_string()])]),
+    };
+    assert!(FieldEncryption::from_config(&section).is_err());
+
+    let section = EncryptionSection {
+      key: Some("ab".repeat(32)),
+      ..section
+    };
+    let enc = FieldEncryption::from_config(&section).unwrap();
+    assert_eq!(enc.encrypted_fields("users"), ["ssn"]);
+    assert!(FieldEncryption::from_config(&EncryptionSection::default()).is_ok());
+  }
+}
@@ -0,0 +1,114 @@
+//! Idempotency keys for document inserts.
+//!
+//! A client that retries an insert with the same key gets the response of the
+//! first attempt instead of a duplicate document. Keys are scoped to a project
+//! and collection and expire after a TTL. A key is claimed before the insert
+//! runs and completed with the response afterwards, so a concurrent retry sees
+//! it as in progress rather than inserting again.
+
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use super::{DatabaseBackend, DocumentWrite, WriteOptions};
+use crate::types::Document;
+
+/// Longest idempotency key accepted
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// How long keys are remembered unless configured otherwise
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// State of an idempotency key when a request claims it
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdempotencyClaim {
+  /// First use: run the operation, then complete or release the key
+  Claimed,
+  /// Already used: the stored response of the first request
+  Completed(serde_json::Value),
+  /// A request with the same key has not finished yet
+  InProgress,
+}
+
+/// Outcome of an insert carrying an idempotency key
+#[derive(Debug, Clone)]
+pub enum IdempotentInsert {
+  /// The document was inserted by this request, with the ID of the change
+  /// the insert recorded
+  Inserted(Document, Option<i64>),
+  /// The key was used before; the response of that request
+  Replayed(serde_json::Value),
+  /// A request with the same key is still running
+  InProgress,
+}
+
+/// Check that an idempotency key is non-empty and not too long
+pub fn validate_idempotency_key(key: &str) -> Result<(), anyhow::Error> {
+  if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+    anyhow::bail!(
+      "Idempotency key must be 1 to {} bytes",
+      MAX_IDEMPOTENCY_KEY_LEN
+    );
+  }
+  Ok(())
+}
+
+/// Insert a document at most once per key. If the insert fails the key is
+/// released so the client can retry. If the key can't be completed after a
+/// successful insert it is released too, rather than left in progress until
+/// it expires, and the insert is still reported.
+pub async fn insert_idempotent(
+  backend: &dyn DatabaseBackend,
+  project_id: Uuid,
+  collection: &str,
+  data: serde_json::Value,
+  options: WriteOptions,
+  key: &str,
+  ttl: Duration,
+) -> Result<IdempotentInsert, anyhow::Error> {
+  validate_idempotency_key(key)?;
+  match backend
+    .claim_idempotency_key(project_id, collection, key, ttl)
+    .await?
+  {
+    IdempotencyClaim::Completed(response) => return Ok(IdempotentInsert::Replayed(response)),
+    IdempotencyClaim::InProgress => return Ok(IdempotentInsert::InProgress),
+    IdempotencyClaim::Claimed => {}
+  }
+
+  let write = DocumentWrite::Insert { data, options };
+  let inserted = backend
+    .write_document(project_id, collection, write, true)
+    .await
+    .and_then(|written| written.ok_or_else(|| anyhow::anyhow!("Insert returned no document")));
+  let (doc, change_id) = match inserted {
+    Ok(inserted) => inserted,
+    Err(e) => {
+      release_key(backend, project_id, collection, key).await;
+      return Err(e);
+    }
+  };
+  let completed = match serde_json::to_value(&doc) {
+    Ok(response) => {
+      backend
+        .complete_idempotency_key(project_id, collection, key, &response)
+        .await
+    }
+    Err(e) => Err(e.into()),
+  };
+  if let Err(e) = completed {
+    tracing::warn!("Failed to complete idempotency key: {}", e);
+    release_key(backend, project_id, collection, key).await;
+  }
+  Ok(IdempotentInsert::Inserted(doc, change_id))
+}
+
+/// Release a claimed key, logging rather than returning a failure
+async fn release_key(backend: &dyn DatabaseBackend, project_id: Uuid, collection: &str, key: &str) {
+  if let Err(e) = backend
+    .release_idempotency_key(project_id, collection, key)
+    .await
+  {
+    tracing::warn!("Failed to release idempotency key: {}", e);
+  }
+}
@@ -1,12 +1,31 @@
 mod backend;
+mod defaults;
+mod encryption;
+mod idempotency;
 mod postgres;
+mod projection;
 pub mod sanitize;
 mod sqlite;
 
-pub use backend::{AdminRole, AdminSession, AdminUser, ApiTokenInfo, DatabaseBackend, SqlDialect};
+pub use backend::{
+  ActiveQuery, AdminRole, AdminSession, AdminTokenPurpose, AdminUser, ApiTokenInfo,
+  ChangeRetention, CollectionStats, CompressionMethod, DatabaseBackend, DocumentBatches,
+  DocumentCompression, DocumentCount, DocumentEdit, DocumentWrite, FieldFrequency,
+  ListenerHeartbeat, PoolRecycling, PoolSettings, ProjectLimits, RawSqlResult, ReadOptions,
+  SoftDeleteSettings, SqlDialect, TableMaintenance, WriteOptions, APPROXIMATE_COUNT_MIN,
+  EARTH_RADIUS_METERS, FILTER_PARAM_OFFSET, STATS_SAMPLE_SIZE, STATS_TOP_FIELDS,
+};
+pub use defaults::{CollectionDefaults, DEFAULT_NOW, DEFAULT_USER};
+pub use encryption::{FieldEncryption, ENCRYPTED_MARKER};
+pub use idempotency::{
+  insert_idempotent, validate_idempotency_key, IdempotencyClaim, IdempotentInsert,
+  DEFAULT_IDEMPOTENCY_TTL, MAX_IDEMPOTENCY_KEY_LEN,
+};
 pub use postgres::PostgresBackend;
+pub use projection::{parse_projection, ProjectionField};
 pub use sanitize::{
-  escape_string, validate_collection_name, validate_identifier, validate_limit,
-  validate_order_direction, SqlSanitizeError,
+  escape_string, validate_collection_name, validate_distance, validate_filter_sql,
+  validate_geo_point, validate_identifier, validate_limit, validate_order_direction,
+  validate_projection, ReservedCollections, SqlSanitizeError, INTERNAL_TABLE_NAMES,
 };
 pub use sqlite::SqliteBackend;
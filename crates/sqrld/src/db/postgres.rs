@@ -1,18 +1,39 @@
 use async_trait::async_trait;
-use deadpool_postgres::{Config, ManagerConfig, Pool, RecyclingMethod, Runtime};
-use tokio::sync::broadcast;
-use tokio_postgres::NoTls;
+use deadpool_postgres::{
+  Config, ManagerConfig, Pool, PoolConfig, RecyclingMethod, Runtime, Timeouts,
+};
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{NoTls, Statement};
 use uuid::Uuid;
 
+use super::backend::check_document_quota;
 use super::backend::{
-  AdminRole, AdminSession, AdminUser, ApiTokenInfo, DatabaseBackend, SqlDialect,
-  StorageAccessKeyInfo,
+  ActiveQuery, AdminRole, AdminSession, AdminTokenPurpose, AdminUser, ApiTokenInfo,
+  ChangeRetention, CollectionStats, DatabaseBackend, DocumentBatches, DocumentCompression,
+  DocumentCount, DocumentEdit, DocumentWrite, ListenerHeartbeat, PoolRecycling, PoolSettings,
+  ProjectLimits, RawSqlResult, ReadOptions, SoftDeleteSettings, SqlDialect, StorageAccessKeyInfo,
+  TableMaintenance, WriteOptions, APPROXIMATE_COUNT_MIN, STATS_SAMPLE_SIZE, STATS_TOP_FIELDS,
+};
+use super::defaults::{with_defaults, CollectionDefaults};
+use super::encryption::FieldEncryption;
+use super::idempotency::IdempotencyClaim;
+use super::projection::parse_projection;
+use super::sanitize::{
+  validate_collection_name, validate_filter_sql, validate_limit, ReservedCollections,
+};
+use crate::storage::{
+  MultipartPart, MultipartUpload, ObjectAcl, ObjectTags, StorageBucket, StorageObject,
 };
-use super::sanitize::{validate_collection_name, validate_identifier, validate_limit};
-use crate::storage::{MultipartPart, MultipartUpload, ObjectAcl, StorageBucket, StorageObject};
 use crate::types::{
-  Change, ChangeOperation, Document, OrderBySpec, OrderDirection, Project, ProjectMember,
-  ProjectRole, DEFAULT_PROJECT_ID,
+  Change, ChangeOperation, Document, OrderBySpec, Project, ProjectMember, ProjectRole,
+  DEFAULT_PROJECT_ID,
 };
 
 /// Pipe trait for method chaining
@@ -24,6 +45,49 @@ trait Pipe: Sized {
 
 impl<T> Pipe for T {}
 
+/// How often change_queue is polled as a fallback for missed notifications
+const CHANGE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Maximum number of changes fetched per fallback poll query
+const CHANGE_POLL_BATCH: i64 = 1000;
+
+/// Distinct SQL texts kept prepared when no size is configured
+const DEFAULT_STATEMENT_CACHE_SIZE: usize = 256;
+
+/// First delay before reconnecting a dropped LISTEN connection
+const LISTEN_RETRY_INITIAL: Duration = Duration::from_secs(1);
+
+/// Upper bound for the reconnect delay
+const LISTEN_RETRY_MAX: Duration = Duration::from_secs(30);
+
+/// Largest accepted connection pool size
+const MAX_POOL_SIZE: usize = 1000;
+
+/// How long a streamed list waits for its consumer to take the next batch
+/// before its cursor is closed and its connection returned to the pool
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// `application_name` of pooled connections unless the URL sets one. Active
+/// queries are those of connections with the server's application name.
+const APPLICATION_NAME: &str = "squirreldb";
+
+/// Running statements of this server's connections, excluding the caller's
+const OWN_ACTIVE_QUERIES: &str = "FROM pg_stat_activity
+  WHERE datname = current_database()
+    AND application_name = current_setting('application_name')
+    AND state = 'active' AND pid <> pg_backend_pid()";
+
+/// Tables with the most churn, covered by table maintenance
+const MAINTENANCE_TABLES: &[&str] = &["documents", "change_queue"];
+
+/// Delay before the given reconnect attempt (1-based), doubling each time
+fn listen_retry_delay(attempt: u32) -> Duration {
+  let factor = 1u32 << attempt.saturating_sub(1).min(16);
+  LISTEN_RETRY_INITIAL
+    .saturating_mul(factor)
+    .min(LISTEN_RETRY_MAX)
+}
+
 const SCHEMA: &str = r#"
 -- JavaScript-friendly UUID alias
 CREATE OR REPLACE FUNCTION uuid() RETURNS UUID AS $$
@@ -36,7 +100,8 @@ CREATE TABLE IF NOT EXISTS documents (
     collection VARCHAR(255) NOT NULL,
     data JSONB NOT NULL,
     created_at TIMESTAMPTZ DEFAULT NOW(),
-    updated_at TIMESTAMPTZ DEFAULT NOW()
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ
 );
 CREATE INDEX IF NOT EXISTS idx_documents_collection ON documents(collection);
 CREATE INDEX IF NOT EXISTS idx_documents_data ON documents USING GIN(data);
@@ -50,6 +115,46 @@ BEGIN
 END $$;
 CREATE INDEX IF NOT EXISTS idx_documents_project ON documents(project_id);
 CREATE INDEX IF NOT EXISTS idx_documents_project_collection ON documents(project_id, collection);
+CREATE INDEX IF NOT EXISTS idx_documents_list_order ON documents(project_id, collection, created_at, id);
+
+-- Migration: Add deleted_at (soft delete) to existing documents table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = 'documents' AND column_name = 'deleted_at') THEN
+        ALTER TABLE documents ADD COLUMN deleted_at TIMESTAMPTZ;
+    END IF;
+END $$;
+CREATE INDEX IF NOT EXISTS idx_documents_deleted ON documents(project_id, collection, deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Collections whose deletes set documents.deleted_at instead of removing rows
+CREATE TABLE IF NOT EXISTS soft_delete_collections (
+    project_id UUID NOT NULL,
+    collection VARCHAR(255) NOT NULL,
+    retention_secs BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (project_id, collection)
+);
+
+-- Values inserts fill in for fields a document leaves out
+CREATE TABLE IF NOT EXISTS collection_defaults (
+    project_id UUID NOT NULL,
+    collection VARCHAR(255) NOT NULL,
+    defaults JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (project_id, collection)
+);
+
+-- Small JSON values clients store by namespace and key
+CREATE TABLE IF NOT EXISTS client_kv (
+    project_id UUID NOT NULL,
+    namespace VARCHAR(255) NOT NULL,
+    key VARCHAR(255) NOT NULL,
+    value JSONB NOT NULL,
+    expires_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (project_id, namespace, key)
+);
+CREATE INDEX IF NOT EXISTS idx_client_kv_expires ON client_kv(expires_at) WHERE expires_at IS NOT NULL;
 
 -- Optimized change_queue with delta storage and fillfactor for INSERT-heavy workload
 CREATE TABLE IF NOT EXISTS change_queue (
@@ -76,6 +181,7 @@ END $$;
 CREATE INDEX IF NOT EXISTS idx_change_queue_project ON change_queue(project_id);
 CREATE INDEX IF NOT EXISTS idx_change_queue_collection ON change_queue(collection);
 CREATE INDEX IF NOT EXISTS idx_change_queue_changed_at ON change_queue(changed_at);
+CREATE INDEX IF NOT EXISTS idx_change_queue_document ON change_queue(document_id, id);
 
 -- Function to compute delta between two JSONB objects (only top-level keys that changed)
 CREATE OR REPLACE FUNCTION sqrl_json_delta(old_data JSONB, new_data JSONB) RETURNS JSONB AS $$
@@ -114,10 +220,41 @@ DECLARE
     change_id BIGINT;
     computed_delta JSONB;
 BEGIN
+    -- Soft-deleted documents are gone as far as subscribers know, so their
+    -- purges and renames are not announced
+    IF TG_OP = 'DELETE' THEN
+        IF OLD.deleted_at IS NOT NULL THEN
+            RETURN OLD;
+        END IF;
+    ELSIF TG_OP = 'UPDATE' THEN
+        IF OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NOT NULL THEN
+            RETURN NEW;
+        END IF;
+    END IF;
+
     IF TG_OP = 'INSERT' THEN
         INSERT INTO change_queue (project_id, collection, document_id, operation, new_data)
         VALUES (NEW.project_id, NEW.collection, NEW.id, 'INSERT', NEW.data)
         RETURNING id INTO change_id;
+    ELSIF TG_OP = 'UPDATE' AND OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN
+        -- Soft delete
+        INSERT INTO change_queue (project_id, collection, document_id, operation, old_data)
+        VALUES (OLD.project_id, OLD.collection, OLD.id, 'DELETE', OLD.data)
+        RETURNING id INTO change_id;
+    ELSIF TG_OP = 'UPDATE' AND OLD.deleted_at IS NOT NULL THEN
+        -- Restore from soft delete
+        INSERT INTO change_queue (project_id, collection, document_id, operation, new_data)
+        VALUES (NEW.project_id, NEW.collection, NEW.id, 'INSERT', NEW.data)
+        RETURNING id INTO change_id;
+    ELSIF TG_OP = 'UPDATE' AND OLD.collection IS DISTINCT FROM NEW.collection THEN
+        -- A collection rename: the document leaves one collection and enters another
+        INSERT INTO change_queue (project_id, collection, document_id, operation, old_data)
+        VALUES (OLD.project_id, OLD.collection, OLD.id, 'DELETE', OLD.data)
+        RETURNING id INTO change_id;
+        PERFORM pg_notify('doc_changes', change_id::text);
+        INSERT INTO change_queue (project_id, collection, document_id, operation, new_data)
+        VALUES (NEW.project_id, NEW.collection, NEW.id, 'INSERT', NEW.data)
+        RETURNING id INTO change_id;
     ELSIF TG_OP = 'UPDATE' THEN
         -- Compute delta for UPDATE operations
         computed_delta := sqrl_json_delta(OLD.data, NEW.data);
@@ -411,6 +548,15 @@ CREATE TABLE IF NOT EXISTS storage_objects (
 CREATE INDEX IF NOT EXISTS idx_storage_objects_bucket_key ON storage_objects(bucket, key);
 CREATE INDEX IF NOT EXISTS idx_storage_objects_latest ON storage_objects(bucket, key) WHERE is_latest = TRUE;
 
+-- Migration: Add object tags to existing storage_objects table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = 'storage_objects' AND column_name = 'tags') THEN
+        ALTER TABLE storage_objects ADD COLUMN tags JSONB NOT NULL DEFAULT '{}';
+    END IF;
+END $$;
+CREATE INDEX IF NOT EXISTS idx_storage_objects_tags ON storage_objects USING GIN(tags) WHERE is_latest = TRUE;
+
 -- Multipart Uploads
 CREATE TABLE IF NOT EXISTS storage_multipart_uploads (
     upload_id UUID PRIMARY KEY DEFAULT uuid(),
@@ -458,10 +604,24 @@ CREATE TABLE IF NOT EXISTS admin_users (
     email VARCHAR(255),
     password_hash VARCHAR(255) NOT NULL,
     role VARCHAR(20) NOT NULL DEFAULT 'admin',
-    created_at TIMESTAMPTZ DEFAULT NOW()
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    email_verified_at TIMESTAMPTZ
 );
 CREATE INDEX IF NOT EXISTS idx_admin_users_username ON admin_users(username);
 
+-- Migration: Add email verification to existing admin_users table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = 'admin_users' AND column_name = 'email_verified_at') THEN
+        ALTER TABLE admin_users ADD COLUMN email_verified_at TIMESTAMPTZ;
+    END IF;
+END $$;
+
+-- Migration: Link admin users to OIDC identities by issuer and subject
+ALTER TABLE admin_users ADD COLUMN IF NOT EXISTS oidc_issuer TEXT;
+ALTER TABLE admin_users ADD COLUMN IF NOT EXISTS oidc_subject TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_admin_users_oidc ON admin_users(oidc_issuer, oidc_subject) WHERE oidc_subject IS NOT NULL;
+
 -- Admin sessions
 CREATE TABLE IF NOT EXISTS admin_sessions (
     id UUID PRIMARY KEY DEFAULT uuid(),
@@ -473,6 +633,16 @@ CREATE TABLE IF NOT EXISTS admin_sessions (
 CREATE INDEX IF NOT EXISTS idx_admin_sessions_token ON admin_sessions(session_token_hash);
 CREATE INDEX IF NOT EXISTS idx_admin_sessions_expires ON admin_sessions(expires_at);
 
+-- Single-use password reset and email verification tokens (hashed)
+CREATE TABLE IF NOT EXISTS admin_auth_tokens (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES admin_users(id) ON DELETE CASCADE,
+    purpose VARCHAR(20) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS idx_admin_auth_tokens_user ON admin_auth_tokens(user_id, purpose);
+
 -- Projects table
 CREATE TABLE IF NOT EXISTS projects (
     id UUID PRIMARY KEY DEFAULT uuid(),
@@ -497,6 +667,26 @@ CREATE TABLE IF NOT EXISTS project_members (
 CREATE INDEX IF NOT EXISTS idx_project_members_project ON project_members(project_id);
 CREATE INDEX IF NOT EXISTS idx_project_members_user ON project_members(user_id);
 
+-- Insert idempotency keys; response is NULL while the insert is running
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    project_id UUID NOT NULL,
+    collection VARCHAR(255) NOT NULL,
+    key VARCHAR(255) NOT NULL,
+    response JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (project_id, collection, key)
+);
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_created ON idempotency_keys(created_at);
+
+-- Per-project quotas (0 = unlimited)
+CREATE TABLE IF NOT EXISTS project_limits (
+    project_id UUID PRIMARY KEY,
+    requests_per_second INTEGER NOT NULL DEFAULT 0,
+    burst_size INTEGER NOT NULL DEFAULT 0,
+    max_documents BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
 -- Create default project if none exists (runs on schema init if admin user exists)
 INSERT INTO projects (id, name, description, owner_id)
 SELECT
@@ -722,25 +912,579 @@ $$ LANGUAGE plpgsql;
 
 pub struct PostgresBackend {
   pool: Pool,
+  /// Connections opened by `init_schema` and left idle in the pool
+  min_idle: usize,
   url: String,
   change_tx: broadcast::Sender<Change>,
+  heartbeat: Arc<ListenerHeartbeat>,
+  statements: PreparedStatements,
+  encryption: Arc<FieldEncryption>,
+  retention: ChangeRetention,
+  maintenance: Option<TableMaintenance>,
+  compression: Option<DocumentCompression>,
+  reserved: ReservedCollections,
+  /// Streamed lists each hold a connection while open; at most half the
+  /// pool may, so other queries can still get one
+  streams: Arc<Semaphore>,
+}
+
+/// SQL texts with prepared statements, least recently used first.
+///
+/// Each pooled connection prepares a statement the first time it runs its
+/// SQL and keeps it in the connection's statement cache. Once `capacity`
+/// texts are tracked, running a new one evicts the least recently used,
+/// which is then dropped (and closed on the server) on every connection.
+struct PreparedStatements(Option<Mutex<LruCache<String, ()>>>);
+
+impl PreparedStatements {
+  /// Track up to `capacity` SQL texts; 0 disables preparing
+  fn new(capacity: usize) -> Self {
+    Self(NonZeroUsize::new(capacity).map(|n| Mutex::new(LruCache::new(n))))
+  }
+
+  fn enabled(&self) -> bool {
+    self.0.is_some()
+  }
+
+  /// Mark `sql` as most recently used, returning the SQL text it evicted
+  fn touch(&self, sql: &str) -> Option<String> {
+    let mut lru = self.0.as_ref()?.lock();
+    if lru.get(sql).is_some() {
+      return None;
+    }
+    lru.push(sql.to_string(), ()).map(|(evicted, _)| evicted)
+  }
 }
 
 impl PostgresBackend {
-  pub fn new(url: &str, _max_connections: usize) -> Result<Self, anyhow::Error> {
+  pub fn new(url: &str, max_connections: usize) -> Result<Self, anyhow::Error> {
+    Self::with_pool_settings(url, max_connections, PoolSettings::default())
+  }
+
+  /// Connect with the given pool size and checkout behaviour
+  pub fn with_pool_settings(
+    url: &str,
+    max_connections: usize,
+    settings: PoolSettings,
+  ) -> Result<Self, anyhow::Error> {
+    if !(1..=MAX_POOL_SIZE).contains(&max_connections) {
+      anyhow::bail!(
+        "Pool size must be between 1 and {}, got {}",
+        MAX_POOL_SIZE,
+        max_connections
+      );
+    }
+    if settings.min_idle > max_connections {
+      anyhow::bail!(
+        "Minimum idle connections ({}) exceed the pool size ({})",
+        settings.min_idle,
+        max_connections
+      );
+    }
     let mut cfg = Config::new();
     cfg.url = Some(url.into());
+    if !url.contains("application_name=") {
+      cfg.application_name = Some(APPLICATION_NAME.into());
+    }
     cfg.manager = Some(ManagerConfig {
-      recycling_method: RecyclingMethod::Fast,
+      recycling_method: match settings.recycling {
+        PoolRecycling::Fast => RecyclingMethod::Fast,
+        PoolRecycling::Verified => RecyclingMethod::Verified,
+      },
+    });
+    cfg.pool = Some(PoolConfig {
+      max_size: max_connections,
+      timeouts: Timeouts {
+        wait: settings.wait_timeout,
+        create: settings.create_timeout,
+        recycle: settings.recycle_timeout,
+      },
+      ..PoolConfig::default()
     });
     let pool = cfg.create_pool(Some(Runtime::Tokio1), NoTls)?;
     let (change_tx, _) = broadcast::channel(1024);
     Ok(Self {
       pool,
+      min_idle: settings.min_idle,
       url: url.into(),
       change_tx,
+      heartbeat: Arc::new(ListenerHeartbeat::new(CHANGE_POLL_INTERVAL)),
+      statements: PreparedStatements::new(DEFAULT_STATEMENT_CACHE_SIZE),
+      encryption: Arc::default(),
+      retention: ChangeRetention::default(),
+      maintenance: None,
+      compression: None,
+      reserved: ReservedCollections::default(),
+      streams: Arc::new(Semaphore::new((max_connections / 2).max(1))),
     })
   }
+
+  /// Open connections until `min_idle` are idle in the pool, so the first
+  /// requests after startup don't wait for connection setup
+  async fn warm_pool(&self) -> Result<(), anyhow::Error> {
+    let idle = self.pool.status().available;
+    let mut opened = Vec::with_capacity(self.min_idle.saturating_sub(idle));
+    for _ in idle..self.min_idle {
+      opened.push(self.pool.get().await?);
+    }
+    Ok(())
+  }
+
+  /// Encrypt and decrypt document fields as configured
+  pub fn with_field_encryption(mut self, encryption: Arc<FieldEncryption>) -> Self {
+    self.encryption = encryption;
+    self
+  }
+
+  /// Refuse inserts into, and copies or renames to, these collection names
+  pub fn with_reserved_collections(mut self, reserved: ReservedCollections) -> Self {
+    self.reserved = reserved;
+    self
+  }
+
+  /// Keep change_queue rows as configured
+  pub fn with_change_retention(mut self, retention: ChangeRetention) -> Self {
+    self.retention = retention;
+    self
+  }
+
+  /// Periodically ANALYZE (and optionally VACUUM) the busiest tables
+  pub fn with_table_maintenance(mut self, maintenance: Option<TableMaintenance>) -> Self {
+    self.maintenance = maintenance;
+    self
+  }
+
+  /// Compress large documents as configured. Applied by `init_schema`;
+  /// without it the table's compression settings are left as they are.
+  pub fn with_compression(mut self, compression: Option<DocumentCompression>) -> Self {
+    self.compression = compression;
+    self
+  }
+
+  /// Document from a row, with encrypted fields decrypted
+  fn document(&self, row: &tokio_postgres::Row) -> Result<Document, anyhow::Error> {
+    self.encryption.decrypt_document(document_from_row(row))
+  }
+
+  /// SELECT for a list of documents, with `$1` the project and `$2` the
+  /// collection, followed by the filter's parameters
+  #[allow(clippy::too_many_arguments)]
+  fn list_sql(
+    &self,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+  ) -> Result<String, anyhow::Error> {
+    // Validate collection name to prevent injection
+    validate_collection_name(collection)?;
+    self
+      .encryption
+      .check_sql(SqlDialect::Postgres, collection, filter, order)?;
+
+    // Build only the requested fields in the database
+    let data = match projection {
+      Some(fields) => {
+        let fields = parse_projection(fields)?;
+        self.encryption.check_projection(collection, &fields)?;
+        SqlDialect::Postgres.json_projection(&fields)
+      }
+      None => "data".to_string(),
+    };
+    let mut sql = format!(
+      "SELECT id, project_id, collection, {}, created_at, updated_at, deleted_at FROM documents WHERE project_id = $1 AND collection = $2",
+      data
+    );
+    if !options.with_deleted {
+      sql.push_str(" AND deleted_at IS NULL");
+    }
+
+    // Filter comes from the query compiler; check it only touches `data`
+    if let Some(f) = filter {
+      validate_filter_sql(f)?;
+      sql.push_str(&format!(" AND ({})", f));
+    }
+
+    // Field names are validated by the dialect to prevent injection
+    sql.push_str(&SqlDialect::Postgres.order_by(order)?);
+
+    if let Some(l) = limit {
+      // Validate limit is within bounds
+      validate_limit(l)?;
+      sql.push_str(&format!(" LIMIT {}", l));
+    }
+
+    if let Some(o) = offset {
+      // Validate offset is within bounds
+      if o > 1_000_000 {
+        anyhow::bail!("Offset too large (max 1000000)");
+      }
+      sql.push_str(&format!(" OFFSET {}", o));
+    }
+
+    Ok(sql)
+  }
+
+  /// Keep prepared statements for up to `size` distinct SQL texts (0 disables)
+  pub fn with_statement_cache_size(mut self, size: usize) -> Self {
+    self.statements = PreparedStatements::new(size);
+    self
+  }
+
+  /// Prepare `sql` on `client`, reusing the connection's statement if it
+  /// already ran it
+  async fn prepare(
+    &self,
+    client: &deadpool_postgres::Object,
+    sql: &str,
+  ) -> Result<Statement, anyhow::Error> {
+    if !self.statements.enabled() {
+      return Ok(client.prepare(sql).await?);
+    }
+    if let Some(evicted) = self.statements.touch(sql) {
+      self.pool.manager().statement_caches.remove(&evicted, &[]);
+    }
+    Ok(client.prepare_cached(sql).await?)
+  }
+
+  /// Run a statement that writes one document and returns its row. With
+  /// `track_change` or a `quota` (project, max documents) it runs in a
+  /// transaction that reads the ID of the change recorded for the document,
+  /// or checks the quota after the write. The write holds the document's row
+  /// lock until commit, so that change is this write's.
+  async fn write_row(
+    &self,
+    sql: &str,
+    params: &[&(dyn ToSql + Sync)],
+    track_change: bool,
+    quota: Option<(Uuid, u64)>,
+  ) -> Result<Option<(Document, Option<i64>)>, anyhow::Error> {
+    let mut client = self.pool.get().await?;
+    let stmt = self.prepare(&client, sql).await?;
+    if !track_change && quota.is_none() {
+      let row = client.query_opt(&stmt, params).await?;
+      return row.map(|r| Ok((self.document(&r)?, None))).transpose();
+    }
+
+    let tx = client.transaction().await?;
+    let Some(row) = tx.query_opt(&stmt, params).await? else {
+      return Ok(None);
+    };
+    if let Some((project_id, max_documents)) = quota {
+      check_quota_in(&tx, project_id, max_documents).await?;
+    }
+    let doc = self.document(&row)?;
+    if !track_change {
+      tx.commit().await?;
+      return Ok(Some((doc, None)));
+    }
+    let change_id = tx
+      .query_one(
+        "SELECT MAX(id) FROM change_queue WHERE document_id = $1",
+        &[&doc.id],
+      )
+      .await?
+      .get(0);
+    tx.commit().await?;
+    Ok(Some((doc, change_id)))
+  }
+
+  /// Run a statement that fills the empty collection `to` from `from`
+  /// (parameters: project, from, to) in a transaction, rolled back if the
+  /// project ends up with more than `max_documents` documents (0 = no quota)
+  async fn fill_collection(
+    &self,
+    project_id: Uuid,
+    from: &str,
+    to: &str,
+    sql: &str,
+    max_documents: u64,
+  ) -> Result<u64, anyhow::Error> {
+    validate_collection_name(from)?;
+    validate_collection_name(to)?;
+    self.reserved.check(to)?;
+    if from == to {
+      anyhow::bail!("Source and target collection are the same");
+    }
+    self.encryption.check_move(from, to)?;
+
+    let mut client = self.pool.get().await?;
+    let tx = client.transaction().await?;
+    let exists: bool = tx
+      .query_one(
+        "SELECT EXISTS(SELECT 1 FROM documents WHERE project_id = $1 AND collection = $2)",
+        &[&project_id, &to],
+      )
+      .await?
+      .get(0);
+    if exists {
+      anyhow::bail!("Collection '{}' already exists", to);
+    }
+    let count = tx.execute(sql, &[&project_id, &from, &to]).await?;
+    if max_documents > 0 {
+      check_quota_in(&tx, project_id, max_documents).await?;
+    }
+    tx.commit().await?;
+    Ok(count)
+  }
+}
+
+/// Check a project's document quota at the end of a write transaction.
+/// Locking the project's limits row first serializes quota-checked writes to
+/// the project, so the count includes every write committed before this one
+/// and none can commit in between.
+async fn check_quota_in(
+  tx: &deadpool_postgres::Transaction<'_>,
+  project_id: Uuid,
+  max_documents: u64,
+) -> Result<(), anyhow::Error> {
+  tx.execute(
+    "SELECT 1 FROM project_limits WHERE project_id = $1 FOR UPDATE",
+    &[&project_id],
+  )
+  .await?;
+  let documents: i64 = tx
+    .query_one(
+      "SELECT COUNT(*) FROM documents WHERE project_id = $1",
+      &[&project_id],
+    )
+    .await?
+    .get(0);
+  check_document_quota(project_id, documents as u64, max_documents)
+}
+
+/// Dedicated LISTEN connection forwarding change notifications
+struct ListenConnection {
+  /// Dropping the client closes the connection
+  _client: tokio_postgres::Client,
+  /// Finishes when the connection is lost
+  task: JoinHandle<Result<(), anyhow::Error>>,
+}
+
+/// Change IDs already delivered to subscribers.
+///
+/// The fallback poll advances `watermark` through change_queue in order,
+/// while notifications deliver individual changes ahead of it. Only the poll
+/// moves the watermark, so changes committed without a notification (e.g.
+/// while the LISTEN connection was down) are never skipped.
+#[derive(Debug, Default)]
+struct DeliveredChanges {
+  watermark: i64,
+  ahead: std::collections::BTreeSet<i64>,
+}
+
+impl DeliveredChanges {
+  fn new(watermark: i64) -> Self {
+    Self {
+      watermark,
+      ahead: Default::default(),
+    }
+  }
+
+  /// Record a change delivered from a notification; false if already delivered
+  fn notified(&mut self, id: i64) -> bool {
+    id > self.watermark && self.ahead.insert(id)
+  }
+
+  /// Record a change seen by the poll; false if a notification delivered it
+  fn polled(&mut self, id: i64) -> bool {
+    let new = !self.ahead.remove(&id);
+    self.watermark = self.watermark.max(id);
+    self.ahead = self.ahead.split_off(&(self.watermark + 1));
+    new
+  }
+}
+
+/// ILIKE pattern matching `search` anywhere, with wildcards escaped
+fn contains_pattern(search: &str) -> String {
+  let mut pattern = String::with_capacity(search.len() + 2);
+  pattern.push('%');
+  for c in search.chars() {
+    if matches!(c, '%' | '_' | '\\') {
+      pattern.push('\\');
+    }
+    pattern.push(c);
+  }
+  pattern.push('%');
+  pattern
+}
+
+/// Bind a JSON value as a parameter of the type PostgreSQL inferred for it
+fn pg_param(
+  ty: &Type,
+  value: &serde_json::Value,
+) -> Result<Box<dyn ToSql + Sync + Send>, anyhow::Error> {
+  fn bind<T: ToSql + Sync + Send + 'static>(
+    ty: &Type,
+    value: &serde_json::Value,
+    convert: impl FnOnce(&serde_json::Value) -> Option<T>,
+  ) -> Result<Box<dyn ToSql + Sync + Send>, anyhow::Error> {
+    if value.is_null() {
+      return Ok(Box::new(None::<T>));
+    }
+    let converted =
+      convert(value).ok_or_else(|| anyhow::anyhow!("Parameter {} is not a valid {}", value, ty))?;
+    Ok(Box::new(Some(converted)))
+  }
+
+  match *ty {
+    Type::BOOL => bind(ty, value, serde_json::Value::as_bool),
+    Type::INT2 => bind(ty, value, |v| {
+      v.as_i64().and_then(|n| i16::try_from(n).ok())
+    }),
+    Type::INT4 => bind(ty, value, |v| {
+      v.as_i64().and_then(|n| i32::try_from(n).ok())
+    }),
+    Type::INT8 => bind(ty, value, serde_json::Value::as_i64),
+    Type::FLOAT4 => bind(ty, value, |v| v.as_f64().map(|n| n as f32)),
+    Type::FLOAT8 => bind(ty, value, serde_json::Value::as_f64),
+    Type::JSON | Type::JSONB => bind(ty, value, |v| Some(v.clone())),
+    Type::UUID => bind(ty, value, |v| {
+      v.as_str().and_then(|s| s.parse::<Uuid>().ok())
+    }),
+    Type::TIMESTAMPTZ => bind(ty, value, |v| {
+      v.as_str()
+        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map(|t| t.with_timezone(&chrono::Utc))
+    }),
+    Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => {
+      bind(ty, value, |v| match v {
+        serde_json::Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+      })
+    }
+    _ => anyhow::bail!(
+      "Unsupported parameter type {}; cast the placeholder, e.g. $1::text",
+      ty
+    ),
+  }
+}
+
+/// Convert a result column to JSON
+fn pg_value(row: &tokio_postgres::Row, i: usize) -> Result<serde_json::Value, anyhow::Error> {
+  use serde_json::Value;
+
+  let column = &row.columns()[i];
+  let value = match *column.type_() {
+    Type::BOOL => row.try_get::<_, Option<bool>>(i)?.map(Value::from),
+    Type::INT2 => row.try_get::<_, Option<i16>>(i)?.map(Value::from),
+    Type::INT4 => row.try_get::<_, Option<i32>>(i)?.map(Value::from),
+    Type::INT8 => row.try_get::<_, Option<i64>>(i)?.map(Value::from),
+    Type::OID => row.try_get::<_, Option<u32>>(i)?.map(Value::from),
+    Type::FLOAT4 => row.try_get::<_, Option<f32>>(i)?.map(Value::from),
+    Type::FLOAT8 => row.try_get::<_, Option<f64>>(i)?.map(Value::from),
+    Type::JSON | Type::JSONB => row.try_get::<_, Option<Value>>(i)?,
+    Type::UUID => row
+      .try_get::<_, Option<Uuid>>(i)?
+      .map(|u| Value::from(u.to_string())),
+    Type::TIMESTAMPTZ => row
+      .try_get::<_, Option<chrono::DateTime<chrono::Utc>>>(i)?
+      .map(|t| Value::from(t.to_rfc3339())),
+    Type::TIMESTAMP => row
+      .try_get::<_, Option<chrono::NaiveDateTime>>(i)?
+      .map(|t| Value::from(t.to_string())),
+    Type::DATE => row
+      .try_get::<_, Option<chrono::NaiveDate>>(i)?
+      .map(|d| Value::from(d.to_string())),
+    Type::BYTEA => row
+      .try_get::<_, Option<Vec<u8>>>(i)?
+      .map(|b| Value::from(hex::encode(b))),
+    Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => {
+      row.try_get::<_, Option<String>>(i)?.map(Value::from)
+    }
+    ref ty => anyhow::bail!(
+      "Column '{}' has unsupported type {}; cast it, e.g. {}::text",
+      column.name(),
+      ty,
+      column.name()
+    ),
+  };
+  Ok(value.unwrap_or(Value::Null))
+}
+
+fn admin_user_from_row(row: &tokio_postgres::Row) -> AdminUser {
+  AdminUser {
+    id: row.get(0),
+    username: row.get(1),
+    email: row.get(2),
+    role: row.get::<_, String>(3).parse().unwrap_or(AdminRole::Admin),
+    created_at: row.get(4),
+    email_verified: row.get(5),
+  }
+}
+
+/// Document selected as `id, project_id, collection, data, created_at,
+/// updated_at, deleted_at`
+fn document_from_row(r: &tokio_postgres::Row) -> Document {
+  Document {
+    id: r.get(0),
+    project_id: r.get(1),
+    collection: r.get(2),
+    data: r.get(3),
+    created_at: r.get(4),
+    updated_at: r.get(5),
+    deleted_at: r.get(6),
+  }
+}
+
+/// Limits stored in three columns starting at `start`
+fn project_limits_from_row(row: &tokio_postgres::Row, start: usize) -> ProjectLimits {
+  ProjectLimits {
+    requests_per_second: row.get::<_, i32>(start).max(0) as u32,
+    burst_size: row.get::<_, i32>(start + 1).max(0) as u32,
+    max_documents: row.get::<_, i64>(start + 2).max(0) as u64,
+  }
+}
+
+fn change_from_row(row: &tokio_postgres::Row) -> Option<Change> {
+  let operation = row.get::<_, String>(4).parse::<ChangeOperation>().ok()?;
+  Some(Change {
+    id: row.get(0),
+    project_id: row.get::<_, Option<Uuid>>(1).unwrap_or(DEFAULT_PROJECT_ID),
+    collection: row.get(2),
+    document_id: row.get(3),
+    operation,
+    old_data: row.get(5),
+    new_data: row.get(6),
+    changed_at: row.get(7),
+  })
+}
+
+/// Open a dedicated connection and LISTEN for change notifications
+async fn listen_for_changes(
+  url: &str,
+  tx: &mpsc::UnboundedSender<i64>,
+) -> Result<ListenConnection, anyhow::Error> {
+  let (listen_client, mut listen_connection) = tokio_postgres::connect(url, NoTls).await?;
+
+  // Poll connection and extract notifications
+  let tx = tx.clone();
+  let task = tokio::spawn(async move {
+    loop {
+      match futures_util::future::poll_fn(|cx| listen_connection.poll_message(cx)).await {
+        Some(Ok(tokio_postgres::AsyncMessage::Notification(n))) => {
+          if let Ok(change_id) = n.payload().parse::<i64>() {
+            let _ = tx.send(change_id);
+          }
+        }
+        Some(Ok(_)) => {}
+        Some(Err(e)) => return Err(e.into()),
+        None => return Ok(()),
+      }
+    }
+  });
+
+  if let Err(e) = listen_client.execute("LISTEN doc_changes", &[]).await {
+    task.abort();
+    return Err(e.into());
+  }
+  Ok(ListenConnection {
+    _client: listen_client,
+    task,
+  })
 }
 
 #[async_trait]
@@ -749,8 +1493,23 @@ impl DatabaseBackend for PostgresBackend {
     SqlDialect::Postgres
   }
 
+  fn field_encryption(&self) -> Arc<FieldEncryption> {
+    self.encryption.clone()
+  }
+
   async fn init_schema(&self) -> Result<(), anyhow::Error> {
-    self.pool.get().await?.batch_execute(SCHEMA).await?;
+    let client = self.pool.get().await?;
+    client.batch_execute(SCHEMA).await?;
+    if let Some(compression) = &self.compression {
+      client.batch_execute(&compression_sql(compression)).await?;
+      tracing::info!(
+        "Compressing documents over {} bytes with {}",
+        compression.threshold_bytes,
+        compression.method.as_str()
+      );
+    }
+    drop(client);
+    self.warm_pool().await?;
     tracing::info!("PostgreSQL schema initialized");
     Ok(())
   }
@@ -922,49 +1681,140 @@ impl DatabaseBackend for PostgresBackend {
   }
 
   async fn delete_project(&self, id: Uuid) -> Result<bool, anyhow::Error> {
-    let result = self
-      .pool
-      .get()
-      .await?
+    let client = self.pool.get().await?;
+    let result = client
       .execute("DELETE FROM projects WHERE id = $1", &[&id])
       .await?;
+    client
+      .execute("DELETE FROM project_limits WHERE project_id = $1", &[&id])
+      .await?;
+    client
+      .execute(
+        "DELETE FROM soft_delete_collections WHERE project_id = $1",
+        &[&id],
+      )
+      .await?;
     Ok(result > 0)
   }
 
-  // =========================================================================
-  // Project Membership Methods
-  // =========================================================================
-
-  async fn add_project_member(
+  async fn get_project_limits(
     &self,
     project_id: Uuid,
-    user_id: Uuid,
-    role: ProjectRole,
-  ) -> Result<ProjectMember, anyhow::Error> {
-    let role_str = role.to_string();
+  ) -> Result<Option<ProjectLimits>, anyhow::Error> {
     let row = self
       .pool
       .get()
       .await?
-      .query_one(
-        "INSERT INTO project_members (project_id, user_id, role)
-         VALUES ($1, $2, $3)
-         ON CONFLICT (project_id, user_id) DO UPDATE SET role = $3
-         RETURNING id, project_id, user_id, role, created_at",
-        &[&project_id, &user_id, &role_str],
+      .query_opt(
+        "SELECT requests_per_second, burst_size, max_documents FROM project_limits WHERE project_id = $1",
+        &[&project_id],
       )
       .await?;
-    Ok(ProjectMember {
-      id: row.get(0),
-      project_id: row.get(1),
-      user_id: row.get(2),
-      role: row.get::<_, String>(3).parse().unwrap_or_default(),
-      created_at: row.get(4),
-    })
+    Ok(row.map(|r| project_limits_from_row(&r, 0)))
   }
 
-  async fn remove_project_member(
-    &self,
+  async fn list_project_limits(&self) -> Result<Vec<(Uuid, ProjectLimits)>, anyhow::Error> {
+    let rows = self
+      .pool
+      .get()
+      .await?
+      .query(
+        "SELECT project_id, requests_per_second, burst_size, max_documents FROM project_limits",
+        &[],
+      )
+      .await?;
+    Ok(
+      rows
+        .iter()
+        .map(|r| (r.get(0), project_limits_from_row(r, 1)))
+        .collect(),
+    )
+  }
+
+  async fn set_project_limits(
+    &self,
+    project_id: Uuid,
+    limits: &ProjectLimits,
+  ) -> Result<(), anyhow::Error> {
+    let rate = limits.requests_per_second.min(i32::MAX as u32) as i32;
+    let burst = limits.burst_size.min(i32::MAX as u32) as i32;
+    let max_documents = limits.max_documents.min(i64::MAX as u64) as i64;
+    self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "INSERT INTO project_limits (project_id, requests_per_second, burst_size, max_documents, updated_at)
+         VALUES ($1, $2, $3, $4, NOW())
+         ON CONFLICT (project_id) DO UPDATE SET requests_per_second = EXCLUDED.requests_per_second,
+           burst_size = EXCLUDED.burst_size, max_documents = EXCLUDED.max_documents,
+           updated_at = NOW()",
+        &[&project_id, &rate, &burst, &max_documents],
+      )
+      .await?;
+    Ok(())
+  }
+
+  async fn delete_project_limits(&self, project_id: Uuid) -> Result<bool, anyhow::Error> {
+    let n = self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "DELETE FROM project_limits WHERE project_id = $1",
+        &[&project_id],
+      )
+      .await?;
+    Ok(n > 0)
+  }
+
+  async fn count_project_documents(&self, project_id: Uuid) -> Result<u64, anyhow::Error> {
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_one(
+        "SELECT COUNT(*) FROM documents WHERE project_id = $1",
+        &[&project_id],
+      )
+      .await?;
+    Ok(row.get::<_, i64>(0) as u64)
+  }
+
+  // =========================================================================
+  // Project Membership Methods
+  // =========================================================================
+
+  async fn add_project_member(
+    &self,
+    project_id: Uuid,
+    user_id: Uuid,
+    role: ProjectRole,
+  ) -> Result<ProjectMember, anyhow::Error> {
+    let role_str = role.to_string();
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_one(
+        "INSERT INTO project_members (project_id, user_id, role)
+         VALUES ($1, $2, $3)
+         ON CONFLICT (project_id, user_id) DO UPDATE SET role = $3
+         RETURNING id, project_id, user_id, role, created_at",
+        &[&project_id, &user_id, &role_str],
+      )
+      .await?;
+    Ok(ProjectMember {
+      id: row.get(0),
+      project_id: row.get(1),
+      user_id: row.get(2),
+      role: row.get::<_, String>(3).parse().unwrap_or_default(),
+      created_at: row.get(4),
+    })
+  }
+
+  async fn remove_project_member(
+    &self,
     project_id: Uuid,
     user_id: Uuid,
   ) -> Result<bool, anyhow::Error> {
@@ -990,7 +1840,7 @@ impl DatabaseBackend for PostgresBackend {
       .await?
       .query(
         "SELECT pm.id, pm.project_id, pm.user_id, pm.role, pm.created_at,
-                u.id, u.username, u.email, u.role, u.created_at
+                u.id, u.username, u.email, u.role, u.created_at, u.email_verified_at IS NOT NULL
          FROM project_members pm
          JOIN admin_users u ON pm.user_id = u.id
          WHERE pm.project_id = $1
@@ -1015,6 +1865,7 @@ impl DatabaseBackend for PostgresBackend {
             email: r.get(7),
             role: r.get::<_, String>(8).parse().unwrap_or(AdminRole::Admin),
             created_at: r.get(9),
+            email_verified: r.get(10),
           };
           (member, user)
         })
@@ -1083,23 +1934,102 @@ impl DatabaseBackend for PostgresBackend {
     collection: &str,
     data: serde_json::Value,
   ) -> Result<Document, anyhow::Error> {
-    // Validate collection name (defense in depth - query is parameterized)
-    validate_collection_name(collection)?;
+    self
+      .insert_with_options(project_id, collection, data, WriteOptions::default())
+      .await
+  }
 
-    // Let PostgreSQL generate UUID and timestamps via DEFAULTs, use RETURNING to get them back
-    let row = self.pool.get().await?.query_one(
-      "INSERT INTO documents (project_id, collection, data) VALUES ($1, $2, $3) RETURNING id, project_id, collection, data, created_at, updated_at",
-      &[&project_id, &collection, &data],
-    ).await?;
+  async fn insert_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    data: serde_json::Value,
+    options: WriteOptions,
+  ) -> Result<Document, anyhow::Error> {
+    self
+      .write_document(
+        project_id,
+        collection,
+        DocumentWrite::Insert { data, options },
+        false,
+      )
+      .await?
+      .map(|(doc, _)| doc)
+      .ok_or_else(|| anyhow::anyhow!("Insert returned no document"))
+  }
 
-    Ok(Document {
-      id: row.get(0),
-      project_id: row.get(1),
-      collection: row.get(2),
-      data: row.get(3),
-      created_at: row.get(4),
-      updated_at: row.get(5),
-    })
+  async fn write_document(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    write: DocumentWrite,
+    track_change: bool,
+  ) -> Result<Option<(Document, Option<i64>)>, anyhow::Error> {
+    // Validate collection name (defense in depth - query is parameterized)
+    validate_collection_name(collection)?;
+    match write {
+      DocumentWrite::Insert { data, options } => {
+        self.reserved.check(collection)?;
+        let data = with_defaults(self, project_id, collection, data, &options, true).await?;
+        // The ID is generated here since encrypted fields are bound to it
+        let id = Uuid::new_v4();
+        let data = self.encryption.encrypt(collection, id, data)?;
+
+        // Let PostgreSQL generate any timestamp not supplied, use RETURNING to get them back
+        self.write_row(
+          "INSERT INTO documents (id, project_id, collection, data, created_at, updated_at) \
+           VALUES ($6, $1, $2, $3, COALESCE($4::timestamptz, NOW()), CASE WHEN $5 THEN COALESCE($4::timestamptz, NOW()) ELSE NOW() END) \
+           RETURNING id, project_id, collection, data, created_at, updated_at, deleted_at",
+          &[
+            &project_id,
+            &collection,
+            &data,
+            &options.preserve_created_at,
+            &options.no_touch,
+            &id,
+          ],
+          track_change,
+          (options.max_documents > 0).then_some((project_id, options.max_documents)),
+        )
+        .await
+      }
+      DocumentWrite::Update { id, data, options } => {
+        let data = with_defaults(self, project_id, collection, data, &options, false).await?;
+        let data = self.encryption.encrypt(collection, id, data)?;
+
+        // Let PostgreSQL generate updated_at via NOW() unless the update must not touch it
+        self.write_row(
+          "UPDATE documents SET data = $1, updated_at = CASE WHEN $5 THEN updated_at ELSE NOW() END WHERE project_id = $2 AND collection = $3 AND id = $4 AND deleted_at IS NULL RETURNING id, project_id, collection, data, created_at, updated_at, deleted_at",
+          &[&data, &project_id, &collection, &id, &options.no_touch],
+          track_change,
+          None,
+        )
+        .await
+      }
+      DocumentWrite::Delete { id } => {
+        let sql = if self
+          .get_soft_delete(project_id, collection)
+          .await?
+          .is_some()
+        {
+          "UPDATE documents SET deleted_at = NOW() WHERE project_id = $1 AND collection = $2 AND id = $3 AND deleted_at IS NULL RETURNING id, project_id, collection, data, created_at, updated_at, deleted_at"
+        } else {
+          "DELETE FROM documents WHERE project_id = $1 AND collection = $2 AND id = $3 AND deleted_at IS NULL RETURNING id, project_id, collection, data, created_at, updated_at, deleted_at"
+        };
+        self
+          .write_row(sql, &[&project_id, &collection, &id], track_change, None)
+          .await
+      }
+      DocumentWrite::Restore { id } => {
+        self.write_row(
+          "UPDATE documents SET deleted_at = NULL WHERE project_id = $1 AND collection = $2 AND id = $3 AND deleted_at IS NOT NULL RETURNING id, project_id, collection, data, created_at, updated_at, deleted_at",
+          &[&project_id, &collection, &id],
+          track_change,
+          None,
+        )
+        .await
+      }
+    }
   }
 
   async fn get(
@@ -1107,199 +2037,1015 @@ impl DatabaseBackend for PostgresBackend {
     project_id: Uuid,
     collection: &str,
     id: Uuid,
+  ) -> Result<Option<Document>, anyhow::Error> {
+    self
+      .get_with_options(project_id, collection, id, ReadOptions::default())
+      .await
+  }
+
+  async fn get_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+    options: ReadOptions,
   ) -> Result<Option<Document>, anyhow::Error> {
     // Validate collection name (defense in depth - query is parameterized)
     validate_collection_name(collection)?;
 
-    let row = self.pool.get().await?.query_opt(
-      "SELECT id, project_id, collection, data, created_at, updated_at FROM documents WHERE project_id = $1 AND collection = $2 AND id = $3",
-      &[&project_id, &collection, &id],
+    let client = self.pool.get().await?;
+    let stmt = self.prepare(
+      &client,
+      "SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE project_id = $1 AND collection = $2 AND id = $3 AND ($4 OR deleted_at IS NULL)",
     ).await?;
-    Ok(row.map(|r| Document {
-      id: r.get(0),
-      project_id: r.get(1),
-      collection: r.get(2),
-      data: r.get(3),
-      created_at: r.get(4),
-      updated_at: r.get(5),
-    }))
+    let row = client
+      .query_opt(
+        &stmt,
+        &[&project_id, &collection, &id, &options.with_deleted],
+      )
+      .await?;
+    row.as_ref().map(|r| self.document(r)).transpose()
   }
 
-  async fn update(
+  async fn get_many(
     &self,
     project_id: Uuid,
     collection: &str,
-    id: Uuid,
-    data: serde_json::Value,
-  ) -> Result<Option<Document>, anyhow::Error> {
-    // Validate collection name (defense in depth - query is parameterized)
+    ids: &[Uuid],
+    options: ReadOptions,
+  ) -> Result<Vec<Document>, anyhow::Error> {
     validate_collection_name(collection)?;
 
-    // Let PostgreSQL generate updated_at via NOW()
-    let row = self.pool.get().await?.query_opt(
-      "UPDATE documents SET data = $1, updated_at = NOW() WHERE project_id = $2 AND collection = $3 AND id = $4 RETURNING id, project_id, collection, data, created_at, updated_at",
-      &[&data, &project_id, &collection, &id],
+    let client = self.pool.get().await?;
+    let stmt = self.prepare(
+      &client,
+      "SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE project_id = $1 AND collection = $2 AND id = ANY($3) AND ($4 OR deleted_at IS NULL)",
     ).await?;
-    Ok(row.map(|r| Document {
-      id: r.get(0),
-      project_id: r.get(1),
-      collection: r.get(2),
-      data: r.get(3),
-      created_at: r.get(4),
-      updated_at: r.get(5),
-    }))
+    let rows = client
+      .query(
+        &stmt,
+        &[&project_id, &collection, &ids, &options.with_deleted],
+      )
+      .await?;
+    rows.iter().map(|r| self.document(r)).collect()
   }
 
-  async fn delete(
+  async fn update(
     &self,
     project_id: Uuid,
     collection: &str,
     id: Uuid,
+    data: serde_json::Value,
   ) -> Result<Option<Document>, anyhow::Error> {
-    // Validate collection name (defense in depth - query is parameterized)
-    validate_collection_name(collection)?;
+    self
+      .update_with_options(project_id, collection, id, data, WriteOptions::default())
+      .await
+  }
 
-    let row = self.pool.get().await?.query_opt(
-      "DELETE FROM documents WHERE project_id = $1 AND collection = $2 AND id = $3 RETURNING id, project_id, collection, data, created_at, updated_at",
-      &[&project_id, &collection, &id],
-    ).await?;
-    Ok(row.map(|r| Document {
-      id: r.get(0),
-      project_id: r.get(1),
-      collection: r.get(2),
-      data: r.get(3),
-      created_at: r.get(4),
-      updated_at: r.get(5),
-    }))
+  async fn update_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+    data: serde_json::Value,
+    options: WriteOptions,
+  ) -> Result<Option<Document>, anyhow::Error> {
+    let write = DocumentWrite::Update { id, data, options };
+    let written = self
+      .write_document(project_id, collection, write, false)
+      .await?;
+    Ok(written.map(|(doc, _)| doc))
   }
 
-  async fn list(
+  async fn edit_document(
     &self,
     project_id: Uuid,
     collection: &str,
-    filter: Option<&str>,
-    order: Option<&OrderBySpec>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-  ) -> Result<Vec<Document>, anyhow::Error> {
-    // Validate collection name to prevent injection
+    id: Uuid,
+    edit: DocumentEdit,
+    options: WriteOptions,
+  ) -> Result<Option<Document>, anyhow::Error> {
     validate_collection_name(collection)?;
 
-    let mut sql =
-      "SELECT id, project_id, collection, data, created_at, updated_at FROM documents WHERE project_id = $1 AND collection = $2"
-        .to_string();
+    let mut client = self.pool.get().await?;
+    let tx = client.transaction().await?;
+    let Some(row) = tx
+      .query_opt(
+        "SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE project_id = $1 AND collection = $2 AND id = $3 AND deleted_at IS NULL FOR UPDATE",
+        &[&project_id, &collection, &id],
+      )
+      .await?
+    else {
+      return Ok(None);
+    };
+    let current = self.document(&row)?;
+    let data = self
+      .encryption
+      .encrypt(collection, id, edit(current.data)?)?;
+    let row = tx
+      .query_one(
+        "UPDATE documents SET data = $1, updated_at = CASE WHEN $5 THEN updated_at ELSE NOW() END WHERE project_id = $2 AND collection = $3 AND id = $4 RETURNING id, project_id, collection, data, created_at, updated_at, deleted_at",
+        &[&data, &project_id, &collection, &id, &options.no_touch],
+      )
+      .await?;
+    tx.commit().await?;
+    self.document(&row).map(Some)
+  }
 
-    // Filter is pre-validated by query compiler - only append if present
-    // The compiler ensures only safe SQL is generated
-    if let Some(f) = filter {
-      sql.push_str(" AND ");
-      sql.push_str(f);
-    }
+  async fn restore_document(&self, doc: &Document) -> Result<(), anyhow::Error> {
+    validate_collection_name(&doc.collection)?;
+    let data = self
+      .encryption
+      .encrypt(&doc.collection, doc.id, doc.data.clone())?;
 
-    if let Some(o) = order {
-      // Validate field name to prevent injection
-      validate_identifier(&o.field)?;
-      let dir = if o.direction == OrderDirection::Desc {
-        "DESC"
-      } else {
-        "ASC"
-      };
-      sql.push_str(&format!(" ORDER BY data->>'{}' {}", o.field, dir));
-    }
+    self.pool.get().await?.execute(
+      "INSERT INTO documents (id, project_id, collection, data, created_at, updated_at, deleted_at) VALUES ($1, $2, $3, $4, $5, $6, $7) \
+       ON CONFLICT (id) DO UPDATE SET project_id = EXCLUDED.project_id, collection = EXCLUDED.collection, data = EXCLUDED.data, created_at = EXCLUDED.created_at, updated_at = EXCLUDED.updated_at, deleted_at = EXCLUDED.deleted_at",
+      &[&doc.id, &doc.project_id, &doc.collection, &data, &doc.created_at, &doc.updated_at, &doc.deleted_at],
+    ).await?;
+    Ok(())
+  }
 
-    if let Some(l) = limit {
-      // Validate limit is within bounds
-      validate_limit(l)?;
-      sql.push_str(&format!(" LIMIT {}", l));
+  async fn restore_documents(&self, docs: &[Document], wipe: bool) -> Result<(), anyhow::Error> {
+    let mut client = self.pool.get().await?;
+    let tx = client.transaction().await?;
+    if wipe {
+      tx.execute("DELETE FROM documents", &[]).await?;
     }
-
-    if let Some(o) = offset {
-      // Validate offset is within bounds
-      if o > 1_000_000 {
-        anyhow::bail!("Offset too large (max 1000000)");
-      }
-      sql.push_str(&format!(" OFFSET {}", o));
+    let stmt = tx
+      .prepare(
+        "INSERT INTO documents (id, project_id, collection, data, created_at, updated_at, deleted_at) VALUES ($1, $2, $3, $4, $5, $6, $7) \
+         ON CONFLICT (id) DO UPDATE SET project_id = EXCLUDED.project_id, collection = EXCLUDED.collection, data = EXCLUDED.data, created_at = EXCLUDED.created_at, updated_at = EXCLUDED.updated_at, deleted_at = EXCLUDED.deleted_at",
+      )
+      .await?;
+    for doc in docs {
+      validate_collection_name(&doc.collection)?;
+      let data = self
+        .encryption
+        .encrypt(&doc.collection, doc.id, doc.data.clone())?;
+      tx.execute(
+        &stmt,
+        &[
+          &doc.id,
+          &doc.project_id,
+          &doc.collection,
+          &data,
+          &doc.created_at,
+          &doc.updated_at,
+          &doc.deleted_at,
+        ],
+      )
+      .await?;
     }
+    tx.commit().await?;
+    Ok(())
+  }
+
+  async fn get_soft_delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<Option<SoftDeleteSettings>, anyhow::Error> {
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_opt(
+        "SELECT retention_secs FROM soft_delete_collections WHERE project_id = $1 AND collection = $2",
+        &[&project_id, &collection],
+      )
+      .await?;
+    Ok(row.map(|r| SoftDeleteSettings {
+      retention_secs: r.get::<_, i64>(0).max(0) as u64,
+    }))
+  }
 
+  async fn list_soft_delete(
+    &self,
+  ) -> Result<Vec<(Uuid, String, SoftDeleteSettings)>, anyhow::Error> {
     let rows = self
       .pool
       .get()
       .await?
-      .query(&sql, &[&project_id, &collection])
+      .query(
+        "SELECT project_id, collection, retention_secs FROM soft_delete_collections ORDER BY project_id, collection",
+        &[],
+      )
       .await?;
     Ok(
       rows
-        .into_iter()
-        .map(|r| Document {
-          id: r.get(0),
-          project_id: r.get(1),
-          collection: r.get(2),
-          data: r.get(3),
-          created_at: r.get(4),
-          updated_at: r.get(5),
+        .iter()
+        .map(|r| {
+          (
+            r.get(0),
+            r.get(1),
+            SoftDeleteSettings {
+              retention_secs: r.get::<_, i64>(2).max(0) as u64,
+            },
+          )
         })
         .collect(),
     )
   }
 
-  async fn list_collections(&self, project_id: Uuid) -> Result<Vec<String>, anyhow::Error> {
-    let rows = self
+  async fn set_soft_delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    settings: SoftDeleteSettings,
+  ) -> Result<(), anyhow::Error> {
+    validate_collection_name(collection)?;
+
+    let retention_secs = settings.retention_secs.min(i64::MAX as u64) as i64;
+    self
       .pool
       .get()
       .await?
-      .query(
-        "SELECT DISTINCT collection FROM documents WHERE project_id = $1 ORDER BY collection",
-        &[&project_id],
+      .execute(
+        "INSERT INTO soft_delete_collections (project_id, collection, retention_secs, updated_at)
+         VALUES ($1, $2, $3, NOW())
+         ON CONFLICT (project_id, collection) DO UPDATE SET retention_secs = EXCLUDED.retention_secs,
+           updated_at = NOW()",
+        &[&project_id, &collection, &retention_secs],
       )
       .await?;
-    Ok(rows.into_iter().map(|r| r.get(0)).collect())
-  }
-
-  fn subscribe_changes(&self) -> broadcast::Receiver<Change> {
-    self.change_tx.subscribe()
+    Ok(())
   }
 
-  async fn start_change_listener(&self) -> Result<(), anyhow::Error> {
-    // Get the notification stream from the connection
-    let (tx_notifications, mut rx_notifications) = tokio::sync::mpsc::unbounded_channel::<i64>();
+  async fn disable_soft_delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<bool, anyhow::Error> {
+    let n = self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "DELETE FROM soft_delete_collections WHERE project_id = $1 AND collection = $2",
+        &[&project_id, &collection],
+      )
+      .await?;
+    Ok(n > 0)
+  }
 
-    // Create a dedicated connection for listening to notifications
-    let (listen_client, mut listen_connection) = tokio_postgres::connect(&self.url, NoTls).await?;
+  async fn get_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<Option<CollectionDefaults>, anyhow::Error> {
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_opt(
+        "SELECT defaults FROM collection_defaults WHERE project_id = $1 AND collection = $2",
+        &[&project_id, &collection],
+      )
+      .await?;
+    Ok(row.map(|r| serde_json::from_value(r.get(0))).transpose()?)
+  }
+
+  async fn set_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    defaults: &CollectionDefaults,
+  ) -> Result<(), anyhow::Error> {
+    validate_collection_name(collection)?;
+    defaults.validate()?;
+
+    let defaults = serde_json::to_value(defaults)?;
+    self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "INSERT INTO collection_defaults (project_id, collection, defaults, updated_at)
+         VALUES ($1, $2, $3, NOW())
+         ON CONFLICT (project_id, collection) DO UPDATE SET defaults = EXCLUDED.defaults,
+           updated_at = NOW()",
+        &[&project_id, &collection, &defaults],
+      )
+      .await?;
+    Ok(())
+  }
+
+  async fn delete_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<bool, anyhow::Error> {
+    let n = self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "DELETE FROM collection_defaults WHERE project_id = $1 AND collection = $2",
+        &[&project_id, &collection],
+      )
+      .await?;
+    Ok(n > 0)
+  }
+
+  async fn get_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+  ) -> Result<Option<serde_json::Value>, anyhow::Error> {
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_opt(
+        "SELECT value FROM client_kv WHERE project_id = $1 AND namespace = $2 AND key = $3
+           AND (expires_at IS NULL OR expires_at > NOW())",
+        &[&project_id, &namespace, &key],
+      )
+      .await?;
+    Ok(row.map(|r| r.get(0)))
+  }
+
+  async fn set_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+    value: &serde_json::Value,
+    ttl: Option<Duration>,
+  ) -> Result<(), anyhow::Error> {
+    let ttl_secs = ttl.map(|t| t.as_secs_f64());
+    self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "INSERT INTO client_kv (project_id, namespace, key, value, expires_at, updated_at)
+         VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5), NOW())
+         ON CONFLICT (project_id, namespace, key) DO UPDATE SET value = EXCLUDED.value,
+           expires_at = EXCLUDED.expires_at, updated_at = NOW()",
+        &[&project_id, &namespace, &key, value, &ttl_secs],
+      )
+      .await?;
+    Ok(())
+  }
+
+  async fn delete_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+  ) -> Result<bool, anyhow::Error> {
+    let n = self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "DELETE FROM client_kv WHERE project_id = $1 AND namespace = $2 AND key = $3
+           AND (expires_at IS NULL OR expires_at > NOW())",
+        &[&project_id, &namespace, &key],
+      )
+      .await?;
+    Ok(n > 0)
+  }
+
+  async fn purge_expired_kv(&self) -> Result<u64, anyhow::Error> {
+    let n = self
+      .pool
+      .get()
+      .await?
+      .execute("DELETE FROM client_kv WHERE expires_at <= NOW()", &[])
+      .await?;
+    Ok(n)
+  }
+
+  async fn restore_deleted(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+  ) -> Result<Option<Document>, anyhow::Error> {
+    let write = DocumentWrite::Restore { id };
+    let written = self
+      .write_document(project_id, collection, write, false)
+      .await?;
+    Ok(written.map(|(doc, _)| doc))
+  }
+
+  async fn purge_deleted(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    retention: Duration,
+  ) -> Result<u64, anyhow::Error> {
+    let retention_secs = retention.as_secs_f64();
+    let n = self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "DELETE FROM documents WHERE project_id = $1 AND collection = $2 AND deleted_at < NOW() - make_interval(secs => $3)",
+        &[&project_id, &collection, &retention_secs],
+      )
+      .await?;
+    Ok(n)
+  }
+
+  async fn delete_many(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    ids: &[Uuid],
+  ) -> Result<Vec<Uuid>, anyhow::Error> {
+    validate_collection_name(collection)?;
+
+    let sql = if self
+      .get_soft_delete(project_id, collection)
+      .await?
+      .is_some()
+    {
+      "UPDATE documents SET deleted_at = NOW() WHERE project_id = $1 AND collection = $2 AND id = ANY($3) AND deleted_at IS NULL RETURNING id"
+    } else {
+      "DELETE FROM documents WHERE project_id = $1 AND collection = $2 AND id = ANY($3) RETURNING id"
+    };
+    let rows = self
+      .pool
+      .get()
+      .await?
+      .query(sql, &[&project_id, &collection, &ids])
+      .await?;
+    Ok(rows.iter().map(|r| r.get(0)).collect())
+  }
+
+  async fn drop_collection(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<u64, anyhow::Error> {
+    validate_collection_name(collection)?;
+
+    let sql = if self
+      .get_soft_delete(project_id, collection)
+      .await?
+      .is_some()
+    {
+      "UPDATE documents SET deleted_at = NOW() WHERE project_id = $1 AND collection = $2 AND deleted_at IS NULL"
+    } else {
+      "DELETE FROM documents WHERE project_id = $1 AND collection = $2"
+    };
+    Ok(
+      self
+        .pool
+        .get()
+        .await?
+        .execute(sql, &[&project_id, &collection])
+        .await?,
+    )
+  }
+
+  async fn claim_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+    ttl: Duration,
+  ) -> Result<IdempotencyClaim, anyhow::Error> {
+    let ttl_secs = ttl.as_secs_f64();
+    let mut client = self.pool.get().await?;
+    let tx = client.transaction().await?;
+    tx.execute(
+      "DELETE FROM idempotency_keys WHERE project_id = $1 AND collection = $2 AND key = $3 AND created_at < NOW() - make_interval(secs => $4)",
+      &[&project_id, &collection, &key, &ttl_secs],
+    )
+    .await?;
+    let claimed = tx
+      .execute(
+        "INSERT INTO idempotency_keys (project_id, collection, key) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        &[&project_id, &collection, &key],
+      )
+      .await?;
+    let claim = if claimed == 1 {
+      IdempotencyClaim::Claimed
+    } else {
+      let row = tx
+        .query_one(
+          "SELECT response FROM idempotency_keys WHERE project_id = $1 AND collection = $2 AND key = $3",
+          &[&project_id, &collection, &key],
+        )
+        .await?;
+      match row.get::<_, Option<serde_json::Value>>(0) {
+        Some(response) => IdempotencyClaim::Completed(response),
+        None => IdempotencyClaim::InProgress,
+      }
+    };
+    tx.commit().await?;
+    Ok(claim)
+  }
+
+  async fn complete_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+    response: &serde_json::Value,
+  ) -> Result<(), anyhow::Error> {
+    self.pool.get().await?.execute(
+      "UPDATE idempotency_keys SET response = $4 WHERE project_id = $1 AND collection = $2 AND key = $3",
+      &[&project_id, &collection, &key, response],
+    ).await?;
+    Ok(())
+  }
+
+  async fn release_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+  ) -> Result<(), anyhow::Error> {
+    self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "DELETE FROM idempotency_keys WHERE project_id = $1 AND collection = $2 AND key = $3",
+        &[&project_id, &collection, &key],
+      )
+      .await?;
+    Ok(())
+  }
+
+  async fn purge_idempotency_keys(&self, ttl: Duration) -> Result<u64, anyhow::Error> {
+    let ttl_secs = ttl.as_secs_f64();
+    let n = self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "DELETE FROM idempotency_keys WHERE created_at < NOW() - make_interval(secs => $1)",
+        &[&ttl_secs],
+      )
+      .await?;
+    Ok(n)
+  }
+
+  async fn delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+  ) -> Result<Option<Document>, anyhow::Error> {
+    let write = DocumentWrite::Delete { id };
+    let written = self
+      .write_document(project_id, collection, write, false)
+      .await?;
+    Ok(written.map(|(doc, _)| doc))
+  }
+
+  async fn list(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    order: Option<&OrderBySpec>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+  ) -> Result<Vec<Document>, anyhow::Error> {
+    self
+      .list_with_options(
+        project_id,
+        collection,
+        projection,
+        filter,
+        &[],
+        order.map(std::slice::from_ref).unwrap_or_default(),
+        limit,
+        offset,
+        ReadOptions::default(),
+      )
+      .await
+  }
+
+  async fn list_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+  ) -> Result<Vec<Document>, anyhow::Error> {
+    let sql = self.list_sql(
+      collection, projection, filter, order, limit, offset, options,
+    )?;
+    let mut bound: Vec<&(dyn ToSql + Sync)> = vec![&project_id, &collection];
+    bound.extend(params.iter().map(|p| p as &(dyn ToSql + Sync)));
+    let client = self.pool.get().await?;
+    let stmt = self.prepare(&client, &sql).await?;
+    let rows = client.query(&stmt, &bound).await?;
+    rows.iter().map(|r| self.document(r)).collect()
+  }
+
+  async fn list_stream(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+    batch_size: usize,
+  ) -> Result<DocumentBatches, anyhow::Error> {
+    let sql = self.list_sql(
+      collection, projection, filter, order, limit, offset, options,
+    )?;
+    let batch_size = batch_size.clamp(1, i32::MAX as usize) as i32;
+    let collection = collection.to_string();
+    let params = params.to_vec();
+    let encryption = self.encryption.clone();
+    let slot = self.streams.clone().try_acquire_owned().map_err(|_| {
+      anyhow::anyhow!(
+        "Too many streamed queries in progress, retry later or query without streaming"
+      )
+    })?;
+    let client = self.pool.get().await?;
 
-    // Spawn a task to process notifications
-    let tx_notif = tx_notifications;
+    // One batch in flight: the next is fetched once the consumer took this one
+    let (tx, rx) = mpsc::channel(1);
     tokio::spawn(async move {
-      // Poll connection and extract notifications
-      loop {
-        match futures_util::future::poll_fn(|cx| listen_connection.poll_message(cx)).await {
-          Some(Ok(tokio_postgres::AsyncMessage::Notification(n))) => {
-            if let Ok(change_id) = n.payload().parse::<i64>() {
-              let _ = tx_notif.send(change_id);
-            }
-          }
-          Some(Ok(_)) => {}
-          Some(Err(e)) => {
-            tracing::error!("PostgreSQL notification error: {}", e);
+      let read = async {
+        // Both are released when reading ends, before an error is reported
+        let _slot = slot;
+        let mut client = client;
+        // A portal is a cursor over the statement's rows, kept open for the
+        // transaction. Dropping the transaction rolls it back and closes it.
+        let txn = client.build_transaction().read_only(true).start().await?;
+        let mut bound: Vec<&(dyn ToSql + Sync)> = vec![&project_id, &collection];
+        bound.extend(params.iter().map(|p| p as &(dyn ToSql + Sync)));
+        let portal = txn.bind(sql.as_str(), &bound).await?;
+        loop {
+          let rows = txn.query_portal(&portal, batch_size).await?;
+          let last = rows.len() < batch_size as usize;
+          let docs = rows
+            .iter()
+            .map(|r| encryption.decrypt_document(document_from_row(r)))
+            .collect::<Result<Vec<_>, _>>()?;
+          // Stop early when the consumer went away
+          let sent = docs.is_empty()
+            || tokio::time::timeout(STREAM_IDLE_TIMEOUT, tx.send(Ok(docs)))
+              .await
+              .map_err(|_| {
+                anyhow::anyhow!(
+                  "Streamed query cancelled: the next batch wasn't read within {} seconds",
+                  STREAM_IDLE_TIMEOUT.as_secs()
+                )
+              })?
+              .is_ok();
+          if last || !sent {
             break;
           }
-          None => break,
         }
+        Ok::<_, anyhow::Error>(())
+      };
+      if let Err(e) = read.await {
+        let _ = tx.send(Err(e)).await;
       }
     });
 
-    listen_client.execute("LISTEN doc_changes", &[]).await?;
+    Ok(Box::pin(futures_util::stream::unfold(
+      rx,
+      |mut rx| async move { rx.recv().await.map(|batch| (batch, rx)) },
+    )))
+  }
+
+  async fn estimate_list_cost(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+  ) -> Result<f64, anyhow::Error> {
+    let sql = self.list_sql(collection, None, filter, order, limit, offset, options)?;
+    let mut bound: Vec<&(dyn ToSql + Sync)> = vec![&project_id, &collection];
+    bound.extend(params.iter().map(|p| p as &(dyn ToSql + Sync)));
+    let plan = self
+      .pool
+      .get()
+      .await?
+      .query_one(&format!("EXPLAIN (FORMAT JSON) {}", sql), &bound)
+      .await?;
+    let plan: serde_json::Value = plan.get(0);
+    plan[0]["Plan"]["Total Cost"]
+      .as_f64()
+      .ok_or_else(|| anyhow::anyhow!("EXPLAIN returned no cost"))
+  }
+
+  async fn count_documents(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    approximate: bool,
+    options: ReadOptions,
+  ) -> Result<DocumentCount, anyhow::Error> {
+    validate_collection_name(collection)?;
+    self
+      .encryption
+      .check_sql(SqlDialect::Postgres, collection, filter, &[])?;
+
+    let mut conditions = String::from("project_id = $1 AND collection = $2");
+    if !options.with_deleted {
+      conditions.push_str(" AND deleted_at IS NULL");
+    }
+    if let Some(f) = filter {
+      validate_filter_sql(f)?;
+      conditions.push_str(&format!(" AND ({})", f));
+    }
+    let mut bound: Vec<&(dyn ToSql + Sync)> = vec![&project_id, &collection];
+    bound.extend(params.iter().map(|p| p as &(dyn ToSql + Sync)));
+    let client = self.pool.get().await?;
+
+    if approximate {
+      // The planner scales the table's reltuples by the selectivity of the
+      // conditions, without reading any rows
+      let plan = client
+        .query_one(
+          &format!(
+            "EXPLAIN (FORMAT JSON) SELECT 1 FROM documents WHERE {}",
+            conditions
+          ),
+          &bound,
+        )
+        .await?;
+      let plan: serde_json::Value = plan.get(0);
+      let estimate = plan[0]["Plan"]["Plan Rows"].as_f64().unwrap_or(0.0) as u64;
+      if estimate >= APPROXIMATE_COUNT_MIN {
+        return Ok(DocumentCount {
+          total: estimate,
+          approximate: true,
+        });
+      }
+    }
+
+    let sql = format!("SELECT COUNT(*) FROM documents WHERE {}", conditions);
+    let stmt = self.prepare(&client, &sql).await?;
+    let row = client.query_one(&stmt, &bound).await?;
+    Ok(DocumentCount {
+      total: row.get::<_, i64>(0) as u64,
+      approximate: false,
+    })
+  }
+
+  async fn list_collections(&self, project_id: Uuid) -> Result<Vec<String>, anyhow::Error> {
+    let rows = self
+      .pool
+      .get()
+      .await?
+      .query(
+        "SELECT DISTINCT collection FROM documents WHERE project_id = $1 ORDER BY collection",
+        &[&project_id],
+      )
+      .await?;
+    Ok(rows.into_iter().map(|r| r.get(0)).collect())
+  }
+
+  async fn collection_counts(&self, project_id: Uuid) -> Result<Vec<(String, u64)>, anyhow::Error> {
+    let rows = self
+      .pool
+      .get()
+      .await?
+      .query(
+        "SELECT collection, COUNT(*) - COUNT(deleted_at) FROM documents WHERE project_id = $1 GROUP BY collection ORDER BY collection",
+        &[&project_id],
+      )
+      .await?;
+    Ok(
+      rows
+        .into_iter()
+        .map(|r| (r.get(0), r.get::<_, i64>(1) as u64))
+        .collect(),
+    )
+  }
+
+  async fn collection_stats(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<CollectionStats, anyhow::Error> {
+    validate_collection_name(collection)?;
+
+    let client = self.pool.get().await?;
+    let totals = client
+      .query_one(
+        "SELECT COUNT(*), COALESCE(SUM(pg_column_size(data)), 0)::BIGINT FROM documents WHERE project_id = $1 AND collection = $2 AND deleted_at IS NULL",
+        &[&project_id, &collection],
+      )
+      .await?;
+    let count: i64 = totals.get(0);
+    let bytes: i64 = totals.get(1);
+
+    let rows = client
+      .query(
+        "WITH sample AS (SELECT data FROM documents WHERE project_id = $1 AND collection = $2 AND deleted_at IS NULL ORDER BY random() LIMIT $3) \
+         SELECT key, COUNT(*) FROM sample, jsonb_object_keys(CASE WHEN jsonb_typeof(data) = 'object' THEN data ELSE '{}' END) AS key \
+         GROUP BY key ORDER BY COUNT(*) DESC, key LIMIT $4",
+        &[
+          &project_id,
+          &collection,
+          &(STATS_SAMPLE_SIZE as i64),
+          &(STATS_TOP_FIELDS as i64),
+        ],
+      )
+      .await?;
+    let fields = rows
+      .iter()
+      .map(|r| (r.get::<_, String>(0), r.get::<_, i64>(1) as u64))
+      .collect();
+
+    let sampled = count.min(STATS_SAMPLE_SIZE as i64);
+    Ok(CollectionStats::new(
+      count as u64,
+      bytes as u64,
+      sampled as u64,
+      fields,
+    ))
+  }
+
+  async fn rename_collection(
+    &self,
+    project_id: Uuid,
+    from: &str,
+    to: &str,
+  ) -> Result<u64, anyhow::Error> {
+    self
+      .fill_collection(
+        project_id,
+        from,
+        to,
+        "UPDATE documents SET collection = $3 WHERE project_id = $1 AND collection = $2",
+        0,
+      )
+      .await
+  }
+
+  async fn copy_collection(
+    &self,
+    project_id: Uuid,
+    from: &str,
+    to: &str,
+    max_documents: u64,
+  ) -> Result<u64, anyhow::Error> {
+    self.fill_collection(
+      project_id,
+      from,
+      to,
+      "INSERT INTO documents (project_id, collection, data, created_at, updated_at) SELECT project_id, $3, data, NOW(), NOW() FROM documents WHERE project_id = $1 AND collection = $2 AND deleted_at IS NULL",
+      max_documents,
+    ).await
+  }
+
+  async fn list_changes(&self, after_id: i64, limit: usize) -> Result<Vec<Change>, anyhow::Error> {
+    let rows = self.pool.get().await?.query(
+      "SELECT id, project_id, collection, document_id, operation, old_data, new_data, changed_at FROM change_queue WHERE id > $1 ORDER BY id LIMIT $2",
+      &[&after_id, &(limit as i64)],
+    ).await?;
+
+    rows
+      .iter()
+      .filter_map(change_from_row)
+      .map(|c| self.encryption.decrypt_change(c))
+      .collect()
+  }
+
+  async fn change_id_range(&self) -> Result<Option<(i64, i64)>, anyhow::Error> {
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_one("SELECT MIN(id), MAX(id) FROM change_queue", &[])
+      .await?;
+    let min: Option<i64> = row.get(0);
+    let max: Option<i64> = row.get(1);
+    Ok(min.zip(max))
+  }
+
+  async fn document_history(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    document_id: Uuid,
+    after_id: i64,
+    limit: usize,
+  ) -> Result<Vec<Change>, anyhow::Error> {
+    let rows = self
+      .pool
+      .get()
+      .await?
+      .query(
+        "SELECT id, project_id, collection, document_id, operation, old_data, new_data, changed_at FROM change_queue
+         WHERE document_id = $3 AND project_id = $1 AND collection = $2 AND id > $4
+         ORDER BY id LIMIT $5",
+        &[&project_id, &collection, &document_id, &after_id, &(limit as i64)],
+      )
+      .await?;
+
+    rows
+      .iter()
+      .filter_map(change_from_row)
+      .map(|c| self.encryption.decrypt_change(c))
+      .collect()
+  }
+
+  fn subscribe_changes(&self) -> broadcast::Receiver<Change> {
+    self.change_tx.subscribe()
+  }
+
+  fn change_listener_heartbeat(&self) -> Arc<ListenerHeartbeat> {
+    self.heartbeat.clone()
+  }
+
+  async fn start_change_listener(&self) -> Result<(), anyhow::Error> {
+    // Get the notification stream from the connection
+    let (tx_notifications, mut rx_notifications) = mpsc::unbounded_channel::<i64>();
+    // Signalled after a reconnect so changes from the gap are picked up at once
+    let catch_up = Arc::new(tokio::sync::Notify::new());
+
+    // Create a dedicated connection for listening to notifications
+    let mut listen_connection = listen_for_changes(&self.url, &tx_notifications).await?;
     tracing::info!("PostgreSQL LISTEN/NOTIFY change listener started");
 
+    // Reconnect the listen connection whenever it drops; the fallback poll
+    // below keeps changes flowing in the meantime
+    let url = self.url.clone();
+    let reconnected = catch_up.clone();
+    tokio::spawn(async move {
+      loop {
+        match (&mut listen_connection.task).await {
+          Ok(Ok(())) => tracing::warn!("PostgreSQL notification connection closed"),
+          Ok(Err(e)) => tracing::error!("PostgreSQL notification error: {}", e),
+          Err(e) => tracing::error!("PostgreSQL notification task failed: {}", e),
+        }
+        let mut attempt = 0;
+        listen_connection = loop {
+          attempt += 1;
+          let delay = listen_retry_delay(attempt);
+          tokio::time::sleep(delay).await;
+          match listen_for_changes(&url, &tx_notifications).await {
+            Ok(connection) => break connection,
+            Err(e) => tracing::warn!(
+              "PostgreSQL LISTEN reconnect attempt {} failed (next in {:?}): {}",
+              attempt,
+              listen_retry_delay(attempt + 1),
+              e
+            ),
+          }
+        };
+        tracing::info!(
+          "PostgreSQL LISTEN connection re-established after {} attempt(s)",
+          attempt
+        );
+        reconnected.notify_one();
+      }
+    });
+
     let tx = self.change_tx.clone();
     let pool = self.pool.clone();
+    let heartbeat = self.heartbeat.clone();
+    let encryption = self.encryption.clone();
+
+    // Only changes made from now on are delivered
+    let start_id: i64 = self
+      .pool
+      .get()
+      .await?
+      .query_one("SELECT COALESCE(MAX(id), 0) FROM change_queue", &[])
+      .await?
+      .get(0);
 
     tokio::spawn(async move {
-      let mut last_id: i64 = 0;
+      let mut delivered = DeliveredChanges::new(start_id);
+      let mut poll = tokio::time::interval(CHANGE_POLL_INTERVAL);
+      poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
       loop {
         tokio::select! {
           // Process notifications immediately (< 1ms latency)
           Some(change_id) = rx_notifications.recv() => {
+            if change_id <= delivered.watermark {
+              continue;
+            }
             // Fetch the specific change by ID
             let Ok(conn) = pool.get().await else { continue };
             let Ok(rows) = conn.query(
@@ -1307,70 +3053,60 @@ impl DatabaseBackend for PostgresBackend {
               &[&change_id]
             ).await else { continue };
 
-            for row in rows {
-              let id: i64 = row.get(0);
-              let Ok(op) = row.get::<_, String>(4).parse::<ChangeOperation>() else {
-                continue;
-              };
-              let _ = tx.send(Change {
-                id,
-                project_id: row.get::<_, Option<Uuid>>(1).unwrap_or(DEFAULT_PROJECT_ID),
-                collection: row.get(2),
-                document_id: row.get(3),
-                operation: op,
-                old_data: row.get(5),
-                new_data: row.get(6),
-                changed_at: row.get(7),
-              });
-              if id > last_id {
-                last_id = id;
+            for change in rows.iter().filter_map(change_from_row) {
+              if delivered.notified(change.id) {
+                encryption.broadcast_change(&tx, change);
               }
             }
+            heartbeat.beat();
+            continue;
           }
-          // Fallback polling every 5s to catch any missed notifications
-          _ = tokio::time::sleep(tokio::time::Duration::from_secs(5)) => {
-            let Ok(conn) = pool.get().await else { continue };
-            let Ok(rows) = conn.query(
-              "SELECT id, project_id, collection, document_id, operation, old_data, new_data, changed_at FROM change_queue WHERE id > $1 ORDER BY id LIMIT 100",
-              &[&last_id]
-            ).await else { continue };
+          // Fallback polling to catch any missed notifications
+          _ = poll.tick() => {}
+          _ = catch_up.notified() => {}
+        }
 
-            for row in rows {
-              let id: i64 = row.get(0);
-              let Ok(op) = row.get::<_, String>(4).parse::<ChangeOperation>() else {
-                continue;
-              };
-              let _ = tx.send(Change {
-                id,
-                project_id: row.get::<_, Option<Uuid>>(1).unwrap_or(DEFAULT_PROJECT_ID),
-                collection: row.get(2),
-                document_id: row.get(3),
-                operation: op,
-                old_data: row.get(5),
-                new_data: row.get(6),
-                changed_at: row.get(7),
-              });
-              last_id = id;
+        // Walk change_queue from the watermark up to the newest change
+        loop {
+          let Ok(conn) = pool.get().await else { break };
+          let Ok(rows) = conn.query(
+            "SELECT id, project_id, collection, document_id, operation, old_data, new_data, changed_at FROM change_queue WHERE id > $1 ORDER BY id LIMIT $2",
+            &[&delivered.watermark, &CHANGE_POLL_BATCH]
+          ).await else { break };
+
+          for row in &rows {
+            let id: i64 = row.get(0);
+            if !delivered.polled(id) {
+              continue;
+            }
+            if let Some(change) = change_from_row(row) {
+              encryption.broadcast_change(&tx, change);
             }
           }
+          heartbeat.beat();
+          if (rows.len() as i64) < CHANGE_POLL_BATCH {
+            break;
+          }
         }
       }
     });
 
     // Spawn cleanup task using PostgreSQL function for efficient cleanup
     let cleanup_pool = self.pool.clone();
+    let retention = self.retention;
+    let max_entries = i32::try_from(retention.max_entries).unwrap_or(i32::MAX);
+    let max_age_secs = retention.max_age.as_secs_f64();
     tokio::spawn(async move {
       loop {
-        // Run cleanup every 5 minutes
-        tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        tokio::time::sleep(retention.cleanup_interval).await;
         let Ok(conn) = cleanup_pool.get().await else {
           continue;
         };
         // Use PostgreSQL function for atomic, efficient cleanup
         let result = conn
           .query_one(
-            "SELECT sqrl_cleanup_change_queue(10000, INTERVAL '1 hour')",
-            &[],
+            "SELECT sqrl_cleanup_change_queue($1, make_interval(secs => $2))",
+            &[&max_entries, &max_age_secs],
           )
           .await;
         if let Ok(row) = result {
@@ -1382,6 +3118,39 @@ impl DatabaseBackend for PostgresBackend {
       }
     });
 
+    if let Some(maintenance) = self.maintenance {
+      let pool = self.pool.clone();
+      tokio::spawn(async move {
+        let command = if maintenance.vacuum {
+          "VACUUM (ANALYZE)"
+        } else {
+          "ANALYZE"
+        };
+        loop {
+          tokio::time::sleep(maintenance.interval).await;
+          let conn = match pool.get().await {
+            Ok(conn) => conn,
+            Err(e) => {
+              tracing::warn!("Table maintenance skipped: {}", e);
+              continue;
+            }
+          };
+          for table in MAINTENANCE_TABLES {
+            let started = Instant::now();
+            match conn.batch_execute(&format!("{} {}", command, table)).await {
+              Ok(()) => tracing::info!(
+                "{} {} took {} ms",
+                command,
+                table,
+                started.elapsed().as_millis()
+              ),
+              Err(e) => tracing::warn!("{} {} failed: {}", command, table, e),
+            }
+          }
+        }
+      });
+    }
+
     Ok(())
   }
 
@@ -1422,29 +3191,79 @@ impl DatabaseBackend for PostgresBackend {
     Ok(result > 0)
   }
 
-  async fn list_tokens(&self, project_id: Uuid) -> Result<Vec<ApiTokenInfo>, anyhow::Error> {
+  async fn list_tokens(&self, project_id: Uuid) -> Result<Vec<ApiTokenInfo>, anyhow::Error> {
+    let rows = self
+      .pool
+      .get()
+      .await?
+      .query(
+        "SELECT id, project_id, name, created_at FROM api_tokens WHERE project_id = $1 ORDER BY created_at DESC",
+        &[&project_id],
+      )
+      .await?;
+    Ok(
+      rows
+        .into_iter()
+        .map(|r| ApiTokenInfo {
+          id: r.get(0),
+          project_id: r.get(1),
+          name: r.get(2),
+          created_at: r.get(3),
+        })
+        .collect(),
+    )
+  }
+
+  async fn list_token_hashes(
+    &self,
+    project_id: Uuid,
+  ) -> Result<Vec<(ApiTokenInfo, String)>, anyhow::Error> {
     let rows = self
       .pool
       .get()
       .await?
       .query(
-        "SELECT id, project_id, name, created_at FROM api_tokens WHERE project_id = $1 ORDER BY created_at DESC",
+        "SELECT id, project_id, name, created_at, token_hash FROM api_tokens WHERE project_id = $1 ORDER BY created_at",
         &[&project_id],
       )
       .await?;
     Ok(
       rows
         .into_iter()
-        .map(|r| ApiTokenInfo {
-          id: r.get(0),
-          project_id: r.get(1),
-          name: r.get(2),
-          created_at: r.get(3),
+        .map(|r| {
+          let info = ApiTokenInfo {
+            id: r.get(0),
+            project_id: r.get(1),
+            name: r.get(2),
+            created_at: r.get(3),
+          };
+          (info, r.get(4))
         })
         .collect(),
     )
   }
 
+  async fn get_token_by_hash(
+    &self,
+    token_hash: &str,
+  ) -> Result<Option<ApiTokenInfo>, anyhow::Error> {
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_opt(
+        "SELECT id, project_id, name, created_at FROM api_tokens WHERE token_hash = $1",
+        &[&token_hash],
+      )
+      .await?;
+    Ok(row.map(|r| ApiTokenInfo {
+      id: r.get(0),
+      project_id: r.get(1),
+      name: r.get(2),
+      created_at: r.get(3),
+    }))
+  }
+
   async fn validate_token(&self, token_hash: &str) -> Result<Option<Uuid>, anyhow::Error> {
     let row = self
       .pool
@@ -1557,6 +3376,151 @@ impl DatabaseBackend for PostgresBackend {
     Ok(())
   }
 
+  // =========================================================================
+  // Raw SQL
+  // =========================================================================
+
+  async fn execute_raw_sql(
+    &self,
+    sql: &str,
+    params: &[serde_json::Value],
+    max_rows: usize,
+  ) -> Result<RawSqlResult, anyhow::Error> {
+    use futures_util::TryStreamExt;
+
+    let client = self.pool.get().await?;
+    let stmt = client.prepare(sql).await?;
+    if stmt.params().len() != params.len() {
+      anyhow::bail!(
+        "Statement takes {} parameter(s), got {}",
+        stmt.params().len(),
+        params.len()
+      );
+    }
+    let bound = stmt
+      .params()
+      .iter()
+      .zip(params)
+      .map(|(ty, value)| pg_param(ty, value))
+      .collect::<Result<Vec<_>, _>>()?;
+    let bound = bound.iter().map(|p| p.as_ref() as &(dyn ToSql + Sync));
+
+    if stmt.columns().is_empty() {
+      let rows_affected = client.execute_raw(&stmt, bound).await?;
+      return Ok(RawSqlResult {
+        rows_affected,
+        ..Default::default()
+      });
+    }
+
+    let mut result = RawSqlResult {
+      columns: stmt
+        .columns()
+        .iter()
+        .map(|c| c.name().to_string())
+        .collect(),
+      ..Default::default()
+    };
+    // Stream rows so a large result stops at max_rows
+    let rows = client.query_raw(&stmt, bound).await?;
+    futures_util::pin_mut!(rows);
+    while let Some(row) = rows.try_next().await? {
+      if max_rows > 0 && result.rows.len() == max_rows {
+        result.truncated = true;
+        break;
+      }
+      result.rows.push(
+        (0..row.len())
+          .map(|i| pg_value(&row, i))
+          .collect::<Result<Vec<_>, _>>()?,
+      );
+    }
+    Ok(result)
+  }
+
+  async fn execute_raw_sql_batch(&self, sql: &str) -> Result<RawSqlResult, anyhow::Error> {
+    use tokio_postgres::SimpleQueryMessage;
+
+    let messages = self.pool.get().await?.simple_query(sql).await?;
+    // SELECTs also complete with a row count; only count writes
+    let mut rows_affected = 0;
+    let mut returned_rows = false;
+    for message in &messages {
+      match message {
+        SimpleQueryMessage::CommandComplete(n) => {
+          if !returned_rows {
+            rows_affected += n;
+          }
+          returned_rows = false;
+        }
+        _ => returned_rows = true,
+      }
+    }
+    Ok(RawSqlResult {
+      rows_affected,
+      ..Default::default()
+    })
+  }
+
+  async fn list_active_queries(&self) -> Result<Vec<ActiveQuery>, anyhow::Error> {
+    let sql = format!(
+      "SELECT pid, query, query_start,
+         (EXTRACT(EPOCH FROM NOW() - query_start) * 1000)::float8, wait_event_type
+       {} ORDER BY query_start",
+      OWN_ACTIVE_QUERIES
+    );
+    let rows = self.pool.get().await?.query(&sql, &[]).await?;
+    Ok(
+      rows
+        .iter()
+        .map(|row| ActiveQuery {
+          pid: row.get(0),
+          query: row.get(1),
+          started_at: row.get(2),
+          duration_ms: row.get::<_, f64>(3).max(0.0) as u64,
+          wait_event: row.get(4),
+        })
+        .collect(),
+    )
+  }
+
+  async fn cancel_query(&self, pid: i32) -> Result<bool, anyhow::Error> {
+    let sql = format!(
+      "SELECT pg_cancel_backend(pid) {} AND pid = $1",
+      OWN_ACTIVE_QUERIES
+    );
+    let row = self.pool.get().await?.query_opt(&sql, &[&pid]).await?;
+    Ok(row.is_some_and(|r| r.get(0)))
+  }
+
+  async fn reindex_documents(&self) -> Result<Vec<String>, anyhow::Error> {
+    let conn = self.pool.get().await?;
+    let indexes = conn
+      .query(
+        "SELECT indexname, format('%I.%I', schemaname, indexname) FROM pg_indexes
+         WHERE tablename = 'documents' AND schemaname = current_schema()
+         ORDER BY indexname",
+        &[],
+      )
+      .await?;
+    let mut rebuilt = Vec::with_capacity(indexes.len());
+    for row in indexes {
+      let (name, quoted): (String, String) = (row.get(0), row.get(1));
+      let started = Instant::now();
+      // CONCURRENTLY builds a new copy next to the old index, so reads and
+      // writes carry on; it can't run inside a transaction
+      conn
+        .batch_execute(&format!("REINDEX INDEX CONCURRENTLY {}", quoted))
+        .await
+        .map_err(|e| anyhow::anyhow!("Reindexing {} failed: {}", name, e))?;
+      tracing::info!("Reindexed {} in {} ms", name, started.elapsed().as_millis());
+      rebuilt.push(name);
+    }
+    // Expression indexes only get planner statistics from ANALYZE
+    conn.batch_execute("ANALYZE documents").await?;
+    Ok(rebuilt)
+  }
+
   // =========================================================================
   // S3 Storage Methods
   // =========================================================================
@@ -1893,6 +3857,92 @@ impl DatabaseBackend for PostgresBackend {
     Ok(())
   }
 
+  async fn get_storage_object_tags(
+    &self,
+    bucket: &str,
+    key: &str,
+  ) -> Result<ObjectTags, anyhow::Error> {
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_opt(
+        "SELECT tags FROM storage_objects WHERE bucket = $1 AND key = $2 AND is_latest = TRUE",
+        &[&bucket, &key],
+      )
+      .await?;
+    Ok(
+      row
+        .map(|r| serde_json::from_value(r.get::<_, serde_json::Value>(0)))
+        .transpose()?
+        .unwrap_or_default(),
+    )
+  }
+
+  async fn set_storage_object_tags(
+    &self,
+    bucket: &str,
+    key: &str,
+    tags: &ObjectTags,
+  ) -> Result<(), anyhow::Error> {
+    let tags_json = serde_json::to_value(tags)?;
+    self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "UPDATE storage_objects SET tags = $3 WHERE bucket = $1 AND key = $2 AND is_latest = TRUE",
+        &[&bucket, &key, &tags_json],
+      )
+      .await?;
+    Ok(())
+  }
+
+  async fn list_storage_objects_with_tags(
+    &self,
+    bucket: &str,
+    prefix: Option<&str>,
+    tags: &ObjectTags,
+  ) -> Result<Vec<StorageObject>, anyhow::Error> {
+    let prefix = prefix.unwrap_or("");
+    let tags_json = serde_json::to_value(tags)?;
+    let rows = self
+      .pool
+      .get()
+      .await?
+      .query(
+        "SELECT bucket, key, version_id, is_latest, etag, size, content_type, storage_path, metadata, acl, is_delete_marker, created_at
+         FROM storage_objects
+         WHERE bucket = $1 AND left(key, length($2)) = $2 AND is_latest = TRUE AND is_delete_marker = FALSE
+           AND tags @> $3
+         ORDER BY key",
+        &[&bucket, &prefix, &tags_json],
+      )
+      .await?;
+
+    Ok(
+      rows
+        .into_iter()
+        .map(|r| StorageObject {
+          bucket: r.get(0),
+          key: r.get(1),
+          version_id: r.get(2),
+          is_latest: r.get(3),
+          etag: r.get(4),
+          size: r.get(5),
+          content_type: r.get(6),
+          storage_path: r.get(7),
+          metadata: r.get(8),
+          acl: r
+            .get::<_, serde_json::Value>(9)
+            .pipe(|v| serde_json::from_value(v).unwrap_or_default()),
+          is_delete_marker: r.get(10),
+          created_at: r.get(11),
+        })
+        .collect(),
+    )
+  }
+
   async fn list_storage_objects(
     &self,
     bucket: &str,
@@ -2266,17 +4316,11 @@ impl DatabaseBackend for PostgresBackend {
       .query_one(
         "INSERT INTO admin_users (username, email, password_hash, role)
          VALUES ($1, $2, $3, $4)
-         RETURNING id, username, email, role, created_at",
+         RETURNING id, username, email, role, created_at, email_verified_at IS NOT NULL",
         &[&username, &email, &password_hash, &role_str],
       )
       .await?;
-    Ok(AdminUser {
-      id: row.get(0),
-      username: row.get(1),
-      email: row.get(2),
-      role: row.get::<_, String>(3).parse().unwrap_or(AdminRole::Admin),
-      created_at: row.get(4),
-    })
+    Ok(admin_user_from_row(&row))
   }
 
   async fn get_admin_user_by_username(
@@ -2288,7 +4332,7 @@ impl DatabaseBackend for PostgresBackend {
       .get()
       .await?
       .query(
-        "SELECT id, username, email, role, created_at, password_hash FROM admin_users WHERE username = $1",
+        "SELECT id, username, email, role, created_at, email_verified_at IS NOT NULL, password_hash FROM admin_users WHERE username = $1",
         &[&username],
       )
       .await?;
@@ -2296,14 +4340,8 @@ impl DatabaseBackend for PostgresBackend {
       return Ok(None);
     }
     let row = &rows[0];
-    let user = AdminUser {
-      id: row.get(0),
-      username: row.get(1),
-      email: row.get(2),
-      role: row.get::<_, String>(3).parse().unwrap_or(AdminRole::Admin),
-      created_at: row.get(4),
-    };
-    let password_hash: String = row.get(5);
+    let user = admin_user_from_row(row);
+    let password_hash: String = row.get(6);
     Ok(Some((user, password_hash)))
   }
 
@@ -2313,21 +4351,59 @@ impl DatabaseBackend for PostgresBackend {
       .get()
       .await?
       .query(
-        "SELECT id, username, email, role, created_at FROM admin_users WHERE id = $1",
+        "SELECT id, username, email, role, created_at, email_verified_at IS NOT NULL FROM admin_users WHERE id = $1",
         &[&id],
       )
       .await?;
-    if rows.is_empty() {
-      return Ok(None);
-    }
-    let row = &rows[0];
-    Ok(Some(AdminUser {
-      id: row.get(0),
-      username: row.get(1),
-      email: row.get(2),
-      role: row.get::<_, String>(3).parse().unwrap_or(AdminRole::Admin),
-      created_at: row.get(4),
-    }))
+    Ok(rows.first().map(admin_user_from_row))
+  }
+
+  async fn get_admin_user_by_oidc(
+    &self,
+    issuer: &str,
+    subject: &str,
+  ) -> Result<Option<AdminUser>, anyhow::Error> {
+    let rows = self
+      .pool
+      .get()
+      .await?
+      .query(
+        "SELECT id, username, email, role, created_at, email_verified_at IS NOT NULL FROM admin_users WHERE oidc_issuer = $1 AND oidc_subject = $2",
+        &[&issuer, &subject],
+      )
+      .await?;
+    Ok(rows.first().map(admin_user_from_row))
+  }
+
+  async fn create_oidc_admin_user(
+    &self,
+    username: &str,
+    email: Option<&str>,
+    password_hash: &str,
+    role: AdminRole,
+    issuer: &str,
+    subject: &str,
+  ) -> Result<AdminUser, anyhow::Error> {
+    let role_str = role.to_string();
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_one(
+        "INSERT INTO admin_users (username, email, password_hash, role, oidc_issuer, oidc_subject)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING id, username, email, role, created_at, email_verified_at IS NOT NULL",
+        &[
+          &username,
+          &email,
+          &password_hash,
+          &role_str,
+          &issuer,
+          &subject,
+        ],
+      )
+      .await?;
+    Ok(admin_user_from_row(&row))
   }
 
   async fn list_admin_users(&self) -> Result<Vec<AdminUser>, anyhow::Error> {
@@ -2336,22 +4412,45 @@ impl DatabaseBackend for PostgresBackend {
       .get()
       .await?
       .query(
-        "SELECT id, username, email, role, created_at FROM admin_users ORDER BY created_at",
+        "SELECT id, username, email, role, created_at, email_verified_at IS NOT NULL FROM admin_users ORDER BY created_at",
         &[],
       )
       .await?;
-    Ok(
-      rows
-        .iter()
-        .map(|row| AdminUser {
-          id: row.get(0),
-          username: row.get(1),
-          email: row.get(2),
-          role: row.get::<_, String>(3).parse().unwrap_or(AdminRole::Admin),
-          created_at: row.get(4),
-        })
-        .collect(),
-    )
+    Ok(rows.iter().map(admin_user_from_row).collect())
+  }
+
+  async fn list_admin_users_paged(
+    &self,
+    search: Option<&str>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+  ) -> Result<(Vec<AdminUser>, u64), anyhow::Error> {
+    let pattern = search
+      .map(str::trim)
+      .filter(|s| !s.is_empty())
+      .map(contains_pattern);
+    let limit = limit.map(|l| l as i64);
+    let offset = offset.unwrap_or(0) as i64;
+    let filter = "$1::text IS NULL OR username ILIKE $1 OR email ILIKE $1";
+
+    let client = self.pool.get().await?;
+    let total: i64 = client
+      .query_one(
+        &format!("SELECT COUNT(*) FROM admin_users WHERE {}", filter),
+        &[&pattern],
+      )
+      .await?
+      .get(0);
+    let rows = client
+      .query(
+        &format!(
+          "SELECT id, username, email, role, created_at, email_verified_at IS NOT NULL FROM admin_users WHERE {} ORDER BY created_at, id LIMIT $2 OFFSET $3",
+          filter
+        ),
+        &[&pattern, &limit, &offset],
+      )
+      .await?;
+    Ok((rows.iter().map(admin_user_from_row).collect(), total as u64))
   }
 
   async fn delete_admin_user(&self, id: Uuid) -> Result<bool, anyhow::Error> {
@@ -2395,6 +4494,62 @@ impl DatabaseBackend for PostgresBackend {
     Ok(result > 0)
   }
 
+  async fn set_admin_user_email_verified(&self, id: Uuid) -> Result<bool, anyhow::Error> {
+    let result = self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "UPDATE admin_users SET email_verified_at = NOW() WHERE id = $1 AND email IS NOT NULL",
+        &[&id],
+      )
+      .await?;
+    Ok(result > 0)
+  }
+
+  // =========================================================================
+  // Admin Auth Tokens (password reset, email verification)
+  // =========================================================================
+
+  async fn create_admin_auth_token(
+    &self,
+    user_id: Uuid,
+    purpose: AdminTokenPurpose,
+    token_hash: &str,
+    expires_at: chrono::DateTime<chrono::Utc>,
+  ) -> Result<(), anyhow::Error> {
+    self
+      .pool
+      .get()
+      .await?
+      .execute(
+        "WITH replaced AS (DELETE FROM admin_auth_tokens WHERE user_id = $1 AND purpose = $2)
+         INSERT INTO admin_auth_tokens (user_id, purpose, token_hash, expires_at)
+         VALUES ($1, $2, $3, $4)",
+        &[&user_id, &purpose.as_str(), &token_hash, &expires_at],
+      )
+      .await?;
+    Ok(())
+  }
+
+  async fn consume_admin_auth_token(
+    &self,
+    purpose: AdminTokenPurpose,
+    token_hash: &str,
+  ) -> Result<Option<Uuid>, anyhow::Error> {
+    let row = self
+      .pool
+      .get()
+      .await?
+      .query_opt(
+        "DELETE FROM admin_auth_tokens WHERE token_hash = $1 AND purpose = $2
+         RETURNING user_id, expires_at > NOW()",
+        &[&token_hash, &purpose.as_str()],
+      )
+      .await?;
+    Ok(row.filter(|r| r.get::<_, bool>(1)).map(|r| r.get(0)))
+  }
+
   // =========================================================================
   // Admin Sessions
   // =========================================================================
@@ -2432,7 +4587,8 @@ impl DatabaseBackend for PostgresBackend {
       .get()
       .await?
       .query(
-        "SELECT s.id, s.user_id, s.expires_at, u.id, u.username, u.email, u.role, u.created_at
+        "SELECT s.id, s.user_id, s.expires_at, u.id, u.username, u.email, u.role, u.created_at,
+                u.email_verified_at IS NOT NULL
          FROM admin_sessions s
          JOIN admin_users u ON s.user_id = u.id
          WHERE s.session_token_hash = $1 AND s.expires_at > NOW()",
@@ -2454,6 +4610,7 @@ impl DatabaseBackend for PostgresBackend {
       email: row.get(5),
       role: row.get::<_, String>(6).parse().unwrap_or(AdminRole::Admin),
       created_at: row.get(7),
+      email_verified: row.get(8),
     };
     Ok(Some((session, user)))
   }
@@ -2479,13 +4636,17 @@ impl DatabaseBackend for PostgresBackend {
   }
 
   async fn cleanup_expired_sessions(&self) -> Result<u64, anyhow::Error> {
-    let result = self
-      .pool
-      .get()
-      .await?
+    let client = self.pool.get().await?;
+    let sessions = client
       .execute("DELETE FROM admin_sessions WHERE expires_at <= NOW()", &[])
       .await?;
-    Ok(result)
+    let tokens = client
+      .execute(
+        "DELETE FROM admin_auth_tokens WHERE expires_at <= NOW()",
+        &[],
+      )
+      .await?;
+    Ok(sessions + tokens)
   }
 
   // =========================================================================
@@ -2635,10 +4796,123 @@ impl DatabaseBackend for PostgresBackend {
   }
 }
 
+/// Statements that make TOAST compress `documents.data` once a row is larger
+/// than the threshold. Rows already stored are compressed when next written.
+fn compression_sql(compression: &DocumentCompression) -> String {
+  format!(
+    "ALTER TABLE documents SET (toast_tuple_target = {});
+     ALTER TABLE documents ALTER COLUMN data SET COMPRESSION {};",
+    compression.threshold_bytes,
+    compression.method.as_str()
+  )
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_listen_retry_delay_backs_off() {
+    assert_eq!(listen_retry_delay(1), Duration::from_secs(1));
+    assert_eq!(listen_retry_delay(2), Duration::from_secs(2));
+    assert_eq!(listen_retry_delay(4), Duration::from_secs(8));
+    assert_eq!(listen_retry_delay(6), LISTEN_RETRY_MAX);
+    assert_eq!(listen_retry_delay(u32::MAX), LISTEN_RETRY_MAX);
+  }
+
+  #[test]
+  fn test_compression_sql() {
+    use super::super::backend::CompressionMethod;
+
+    let compression = DocumentCompression::new(4096, CompressionMethod::Lz4).unwrap();
+    let sql = compression_sql(&compression);
+    assert!(sql.contains("toast_tuple_target = 4096"));
+    assert!(sql.contains("SET COMPRESSION lz4"));
+
+    assert!(DocumentCompression::new(64, CompressionMethod::Pglz).is_err());
+    assert!(DocumentCompression::new(10_000, CompressionMethod::Pglz).is_err());
+    assert_eq!("PGLZ".parse(), Ok(CompressionMethod::Pglz));
+    assert!("zstd".parse::<CompressionMethod>().is_err());
+  }
+
+  #[test]
+  fn test_pool_settings() {
+    let settings = PoolSettings {
+      recycling: PoolRecycling::Fast,
+      wait_timeout: None,
+      ..PoolSettings::default()
+    };
+    let backend =
+      PostgresBackend::with_pool_settings("postgres://localhost/test", 4, settings).unwrap();
+    assert_eq!(backend.pool.status().max_size, 4);
+    let timeouts = backend.pool.timeouts();
+    assert_eq!(timeouts.wait, None);
+    assert_eq!(timeouts.create, Some(Duration::from_secs(10)));
+    assert_eq!(timeouts.recycle, Some(Duration::from_secs(5)));
+
+    assert_eq!("Verified".parse(), Ok(PoolRecycling::Verified));
+    assert!("slow".parse::<PoolRecycling>().is_err());
+  }
+
+  #[test]
+  fn test_pool_size() {
+    let url = "postgres://localhost/test";
+    let backend = PostgresBackend::new(url, 37).unwrap();
+    assert_eq!(backend.pool.status().max_size, 37);
+
+    assert!(PostgresBackend::new(url, 0).is_err());
+    assert!(PostgresBackend::new(url, MAX_POOL_SIZE + 1).is_err());
+    let settings = PoolSettings {
+      min_idle: 5,
+      ..PoolSettings::default()
+    };
+    assert!(PostgresBackend::with_pool_settings(url, 5, settings).is_ok());
+    assert!(PostgresBackend::with_pool_settings(url, 4, settings).is_err());
+  }
+
+  #[test]
+  fn test_prepared_statements_evict_least_recently_used() {
+    let statements = PreparedStatements::new(2);
+    assert_eq!(statements.touch("SELECT 1"), None);
+    assert_eq!(statements.touch("SELECT 2"), None);
+    assert_eq!(statements.touch("SELECT 1"), None);
+    assert_eq!(statements.touch("SELECT 3"), Some("SELECT 2".to_string()));
+    assert_eq!(statements.touch("SELECT 2"), Some("SELECT 1".to_string()));
+
+    let disabled = PreparedStatements::new(0);
+    assert!(!disabled.enabled());
+    assert_eq!(disabled.touch("SELECT 1"), None);
+  }
+
+  #[test]
+  fn test_delivered_changes_poll_does_not_skip_gap() {
+    let mut delivered = DeliveredChanges::new(10);
+
+    // Changes 11 and 12 were committed while LISTEN was down; 13 was notified
+    assert!(delivered.notified(13));
+    assert!(!delivered.notified(13));
+    assert_eq!(delivered.watermark, 10);
+
+    assert!(delivered.polled(11));
+    assert!(delivered.polled(12));
+    assert!(!delivered.polled(13));
+    assert_eq!(delivered.watermark, 13);
+    assert!(delivered.ahead.is_empty());
+
+    // Late notifications for polled changes are ignored
+    assert!(!delivered.notified(12));
+    assert!(delivered.notified(15));
+    assert!(delivered.polled(14));
+    assert_eq!(delivered.ahead.len(), 1);
+  }
+
+  #[test]
+  fn test_contains_pattern_escapes_wildcards() {
+    assert_eq!(contains_pattern("alice"), "%alice%");
+    assert_eq!(contains_pattern("50%_off"), "%50\\%\\_off%");
+    assert_eq!(contains_pattern("a\\b"), "%a\\\\b%");
+  }
+
   #[test]
   fn test_schema_defines_uuid_function() {
     assert!(
@@ -0,0 +1,675 @@
+//! Projections: the fields a query returns instead of whole documents.
+//!
+//! Each entry is either a field path (`name`, `address.city`) or a computed
+//! field with an alias, built from a small allowlist of expressions:
+//!
+//! - arithmetic on numeric fields and literals: `age * 12 as age_months`
+//! - string concatenation: `concat(first, ' ', last) as full_name`
+//!
+//! Anything else is rejected when the query is compiled. Expressions compile
+//! to SQL so the database builds the result, and can also be evaluated in
+//! process for queries whose filter runs in JavaScript.
+
+use serde_json::Value;
+
+use super::backend::SqlDialect;
+use super::sanitize::{validate_identifier, SqlSanitizeError, MAX_PROJECTION_FIELDS};
+
+/// Maximum length of a single projection entry
+const MAX_EXPRESSION_LENGTH: usize = 1024;
+
+/// Maximum nesting depth of a computed expression
+const MAX_EXPRESSION_DEPTH: usize = 16;
+
+/// A projected field: its key in the result and how it is computed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectionField {
+  pub name: String,
+  expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+  Field(String),
+  Number(String),
+  Text(String),
+  Neg(Box<Expr>),
+  Arith(Box<Expr>, ArithOp, Box<Expr>),
+  Concat(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArithOp {
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Mod,
+}
+
+impl ArithOp {
+  fn symbol(self) -> &'static str {
+    match self {
+      Self::Add => "+",
+      Self::Sub => "-",
+      Self::Mul => "*",
+      Self::Div => "/",
+      Self::Mod => "%",
+    }
+  }
+}
+
+impl Expr {
+  fn is_numeric(&self) -> bool {
+    matches!(
+      self,
+      Self::Field(_) | Self::Number(_) | Self::Neg(_) | Self::Arith(..)
+    )
+  }
+}
+
+/// Parse and validate every entry of a projection
+pub fn parse_projection(fields: &[String]) -> Result<Vec<ProjectionField>, SqlSanitizeError> {
+  if fields.is_empty() {
+    return Err(SqlSanitizeError::EmptyProjection);
+  }
+  if fields.len() > MAX_PROJECTION_FIELDS {
+    return Err(SqlSanitizeError::TooManyProjectionFields(fields.len()));
+  }
+  fields.iter().map(|f| ProjectionField::parse(f)).collect()
+}
+
+impl ProjectionField {
+  /// Parse a field path or `<expression> as <name>`
+  pub fn parse(input: &str) -> Result<Self, SqlSanitizeError> {
+    if input.len() > MAX_EXPRESSION_LENGTH {
+      return Err(invalid(format!(
+        "expression longer than {} characters",
+        MAX_EXPRESSION_LENGTH
+      )));
+    }
+
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+      tokens: &tokens,
+      pos: 0,
+      depth: 0,
+    };
+    let expr = parser.expr()?;
+
+    let name = match parser.next() {
+      None => match &expr {
+        Expr::Field(field) => field.clone(),
+        _ => {
+          return Err(invalid(format!(
+            "computed field '{}' needs a name (... as <name>)",
+            input.trim()
+          )))
+        }
+      },
+      Some(Token::Ident(kw)) if kw.eq_ignore_ascii_case("as") => match parser.next() {
+        Some(Token::Ident(alias)) if !alias.contains('.') => {
+          validate_identifier(alias)?;
+          alias.clone()
+        }
+        _ => return Err(invalid("expected a field name after 'as'".into())),
+      },
+      Some(token) => return Err(invalid(format!("unexpected {}", token))),
+    };
+    if let Some(token) = parser.next() {
+      return Err(invalid(format!("unexpected {}", token)));
+    }
+
+    Ok(Self { name, expr })
+  }
+
+  /// Whether this is a plain field rather than a computed one
+  pub fn is_field(&self) -> bool {
+    matches!(self.expr, Expr::Field(_))
+  }
+
+  /// Document fields the value is computed from
+  pub fn fields(&self) -> Vec<&str> {
+    let mut fields = Vec::new();
+    collect_fields(&self.expr, &mut fields);
+    fields
+  }
+
+  /// SQL expression producing the field's value from the `data` column
+  pub fn to_sql(&self, dialect: SqlDialect) -> String {
+    match &self.expr {
+      Expr::Field(field) => dialect.json_value(field),
+      Expr::Text(_) | Expr::Concat(_) => text_sql(&self.expr, dialect),
+      expr => numeric_sql(expr, dialect),
+    }
+  }
+
+  /// Compute the field's value from a document's data
+  pub fn eval(&self, data: &Value) -> Value {
+    match &self.expr {
+      Expr::Field(field) => lookup(data, field).cloned().unwrap_or_default(),
+      Expr::Text(_) | Expr::Concat(_) => Value::String(eval_text(&self.expr, data)),
+      expr => eval_numeric(expr, data).map_or(Value::Null, Num::into_json),
+    }
+  }
+}
+
+fn collect_fields<'a>(expr: &'a Expr, fields: &mut Vec<&'a str>) {
+  match expr {
+    Expr::Field(field) => fields.push(field),
+    Expr::Number(_) | Expr::Text(_) => {}
+    Expr::Neg(operand) => collect_fields(operand, fields),
+    Expr::Arith(lhs, _, rhs) => {
+      collect_fields(lhs, fields);
+      collect_fields(rhs, fields);
+    }
+    Expr::Concat(args) => args.iter().for_each(|arg| collect_fields(arg, fields)),
+  }
+}
+
+fn invalid(msg: String) -> SqlSanitizeError {
+  SqlSanitizeError::InvalidExpression(msg)
+}
+
+fn lookup<'a>(data: &'a Value, field: &str) -> Option<&'a Value> {
+  field.split('.').try_fold(data, |value, key| value.get(key))
+}
+
+// =============================================================================
+// Parsing
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Ident(String),
+  Number(String),
+  Text(String),
+  Op(ArithOp),
+  LParen,
+  RParen,
+  Comma,
+}
+
+impl std::fmt::Display for Token {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Ident(s) => write!(f, "'{}'", s),
+      Self::Number(n) => write!(f, "number {}", n),
+      Self::Text(_) => write!(f, "string literal"),
+      Self::Op(op) => write!(f, "'{}'", op.symbol()),
+      Self::LParen => write!(f, "'('"),
+      Self::RParen => write!(f, "')'"),
+      Self::Comma => write!(f, "','"),
+    }
+  }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, SqlSanitizeError> {
+  let mut tokens = Vec::new();
+  let mut chars = input.chars().peekable();
+
+  while let Some(&c) = chars.peek() {
+    match c {
+      c if c.is_whitespace() => {
+        chars.next();
+      }
+      '(' | ')' | ',' | '+' | '-' | '*' | '/' | '%' => {
+        chars.next();
+        tokens.push(match c {
+          '(' => Token::LParen,
+          ')' => Token::RParen,
+          ',' => Token::Comma,
+          '+' => Token::Op(ArithOp::Add),
+          '-' => Token::Op(ArithOp::Sub),
+          '*' => Token::Op(ArithOp::Mul),
+          '/' => Token::Op(ArithOp::Div),
+          _ => Token::Op(ArithOp::Mod),
+        });
+      }
+      '\'' => {
+        chars.next();
+        let mut text = String::new();
+        loop {
+          match chars.next() {
+            // '' is an escaped quote
+            Some('\'') if chars.peek() == Some(&'\'') => {
+              chars.next();
+              text.push('\'');
+            }
+            Some('\'') => break,
+            Some('\0') => return Err(SqlSanitizeError::NullByteInString),
+            Some(c) => text.push(c),
+            None => return Err(invalid("unterminated string literal".into())),
+          }
+        }
+        tokens.push(Token::Text(text));
+      }
+      c if c.is_ascii_digit() => {
+        let mut number = String::new();
+        while let Some(&c) = chars.peek() {
+          if !c.is_ascii_digit() && c != '.' {
+            break;
+          }
+          number.push(c);
+          chars.next();
+        }
+        if number.matches('.').count() > 1 || number.ends_with('.') {
+          return Err(invalid(format!("invalid number {}", number)));
+        }
+        tokens.push(Token::Number(number));
+      }
+      c if c.is_ascii_alphabetic() || c == '_' => {
+        let mut ident = String::new();
+        while let Some(&c) = chars.peek() {
+          if !c.is_ascii_alphanumeric() && c != '_' && c != '.' {
+            break;
+          }
+          ident.push(c);
+          chars.next();
+        }
+        tokens.push(Token::Ident(ident));
+      }
+      c => return Err(invalid(format!("unexpected character '{}'", c))),
+    }
+  }
+
+  Ok(tokens)
+}
+
+struct Parser<'a> {
+  tokens: &'a [Token],
+  pos: usize,
+  depth: usize,
+}
+
+impl<'a> Parser<'a> {
+  fn peek(&self) -> Option<&'a Token> {
+    self.tokens.get(self.pos)
+  }
+
+  fn next(&mut self) -> Option<&'a Token> {
+    let token = self.tokens.get(self.pos);
+    self.pos += 1;
+    token
+  }
+
+  fn expect(&mut self, expected: Token) -> Result<(), SqlSanitizeError> {
+    match self.next() {
+      Some(token) if *token == expected => Ok(()),
+      Some(token) => Err(invalid(format!("expected {}, got {}", expected, token))),
+      None => Err(invalid(format!("expected {}", expected))),
+    }
+  }
+
+  fn descend(&mut self) -> Result<(), SqlSanitizeError> {
+    self.depth += 1;
+    if self.depth > MAX_EXPRESSION_DEPTH {
+      return Err(invalid("expression is nested too deeply".into()));
+    }
+    Ok(())
+  }
+
+  /// expr := term (('+' | '-') term)*
+  fn expr(&mut self) -> Result<Expr, SqlSanitizeError> {
+    self.descend()?;
+    let mut lhs = self.term()?;
+    while let Some(Token::Op(op @ (ArithOp::Add | ArithOp::Sub))) = self.peek() {
+      self.pos += 1;
+      let rhs = self.term()?;
+      lhs = arith(lhs, *op, rhs)?;
+    }
+
+    self.depth -= 1;
+    Ok(lhs)
+  }
+
+  /// term := unary (('*' | '/' | '%') unary)*
+  fn term(&mut self) -> Result<Expr, SqlSanitizeError> {
+    let mut lhs = self.unary()?;
+    while let Some(Token::Op(op @ (ArithOp::Mul | ArithOp::Div | ArithOp::Mod))) = self.peek() {
+      self.pos += 1;
+      let rhs = self.unary()?;
+      lhs = arith(lhs, *op, rhs)?;
+    }
+    Ok(lhs)
+  }
+
+  /// unary := '-' unary | primary
+  fn unary(&mut self) -> Result<Expr, SqlSanitizeError> {
+    if self.peek() != Some(&Token::Op(ArithOp::Sub)) {
+      return self.primary();
+    }
+    self.pos += 1;
+    self.descend()?;
+    let operand = self.unary()?;
+    self.depth -= 1;
+    if !operand.is_numeric() {
+      return Err(invalid("'-' needs a numeric operand".into()));
+    }
+    Ok(Expr::Neg(Box::new(operand)))
+  }
+
+  /// primary := number | string | field | concat(expr, ...) | '(' expr ')'
+  fn primary(&mut self) -> Result<Expr, SqlSanitizeError> {
+    match self.next() {
+      Some(Token::Number(n)) => Ok(Expr::Number(n.clone())),
+      Some(Token::Text(s)) => Ok(Expr::Text(s.clone())),
+      Some(Token::LParen) => {
+        let expr = self.expr()?;
+        self.expect(Token::RParen)?;
+        Ok(expr)
+      }
+      Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+        if !name.eq_ignore_ascii_case("concat") {
+          return Err(invalid(format!("unknown function '{}'", name)));
+        }
+        self.pos += 1;
+        let mut args = vec![self.expr()?];
+        while self.peek() == Some(&Token::Comma) {
+          self.pos += 1;
+          args.push(self.expr()?);
+        }
+        self.expect(Token::RParen)?;
+        Ok(Expr::Concat(args))
+      }
+      Some(Token::Ident(name)) => {
+        validate_identifier(name)?;
+        Ok(Expr::Field(name.clone()))
+      }
+      Some(token) => Err(invalid(format!("unexpected {}", token))),
+      None => Err(invalid("unexpected end of expression".into())),
+    }
+  }
+}
+
+fn arith(lhs: Expr, op: ArithOp, rhs: Expr) -> Result<Expr, SqlSanitizeError> {
+  if !lhs.is_numeric() || !rhs.is_numeric() {
+    return Err(invalid(format!("'{}' needs numeric operands", op.symbol())));
+  }
+  Ok(Expr::Arith(Box::new(lhs), op, Box::new(rhs)))
+}
+
+// =============================================================================
+// SQL Generation
+// =============================================================================
+
+/// SQL for a numeric expression; non-numeric fields become NULL
+fn numeric_sql(expr: &Expr, dialect: SqlDialect) -> String {
+  match expr {
+    Expr::Field(field) => match dialect {
+      SqlDialect::Postgres => {
+        let value = dialect.json_value(field);
+        format!(
+          "(CASE WHEN jsonb_typeof({0}) = 'number' THEN ({0})::numeric END)",
+          value
+        )
+      }
+      SqlDialect::Sqlite => format!(
+        "(CASE WHEN json_type(data, '$.{0}') IN ('integer', 'real') THEN json_extract(data, '$.{0}') END)",
+        field
+      ),
+    },
+    Expr::Number(n) => n.clone(),
+    Expr::Neg(operand) => format!("(-{})", numeric_sql(operand, dialect)),
+    Expr::Arith(lhs, op, rhs) => {
+      let lhs = numeric_sql(lhs, dialect);
+      let rhs = numeric_sql(rhs, dialect);
+      match (op, dialect) {
+        // SQLite divides integers without a remainder
+        (ArithOp::Div, SqlDialect::Sqlite) => {
+          format!("(CAST({} AS REAL) / NULLIF({}, 0))", lhs, rhs)
+        }
+        (ArithOp::Div | ArithOp::Mod, _) => {
+          format!("({} {} NULLIF({}, 0))", lhs, op.symbol(), rhs)
+        }
+        _ => format!("({} {} {})", lhs, op.symbol(), rhs),
+      }
+    }
+    Expr::Text(_) | Expr::Concat(_) => unreachable!("checked by the parser"),
+  }
+}
+
+/// SQL for an expression used as text
+fn text_sql(expr: &Expr, dialect: SqlDialect) -> String {
+  match expr {
+    Expr::Field(field) => match dialect {
+      SqlDialect::Postgres => format!("(data #>> '{{{}}}')", field.replace('.', ",")),
+      SqlDialect::Sqlite => format!("json_extract(data, '$.{}')", field),
+    },
+    Expr::Text(s) => format!("'{}'", s.replace('\'', "''")),
+    Expr::Concat(args) => {
+      let args = args.iter().map(|arg| text_sql(arg, dialect));
+      match dialect {
+        SqlDialect::Postgres => format!("concat({})", args.collect::<Vec<_>>().join(", ")),
+        SqlDialect::Sqlite => format!(
+          "({})",
+          args
+            .map(|arg| format!("COALESCE({}, '')", arg))
+            .collect::<Vec<_>>()
+            .join(" || ")
+        ),
+      }
+    }
+    expr => numeric_sql(expr, dialect),
+  }
+}
+
+// =============================================================================
+// Evaluation
+// =============================================================================
+
+#[derive(Debug, Clone, Copy)]
+enum Num {
+  Int(i64),
+  Float(f64),
+}
+
+impl Num {
+  fn from_json(value: &Value) -> Option<Self> {
+    match value.as_i64() {
+      Some(i) => Some(Self::Int(i)),
+      None => value.as_f64().map(Self::Float),
+    }
+  }
+
+  fn as_f64(self) -> f64 {
+    match self {
+      Self::Int(i) => i as f64,
+      Self::Float(f) => f,
+    }
+  }
+
+  fn into_json(self) -> Value {
+    match self {
+      Self::Int(i) => i.into(),
+      Self::Float(f) => serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number),
+    }
+  }
+
+  fn to_text(self) -> String {
+    match self.into_json() {
+      Value::Null => String::new(),
+      value => value.to_string(),
+    }
+  }
+}
+
+fn eval_numeric(expr: &Expr, data: &Value) -> Option<Num> {
+  match expr {
+    Expr::Field(field) => lookup(data, field).and_then(Num::from_json),
+    Expr::Number(n) => n
+      .parse::<i64>()
+      .map(Num::Int)
+      .or_else(|_| n.parse::<f64>().map(Num::Float))
+      .ok(),
+    Expr::Neg(operand) => match eval_numeric(operand, data)? {
+      Num::Int(i) => Some(i.checked_neg().map_or(Num::Float(-(i as f64)), Num::Int)),
+      Num::Float(f) => Some(Num::Float(-f)),
+    },
+    Expr::Arith(lhs, op, rhs) => {
+      let lhs = eval_numeric(lhs, data)?;
+      let rhs = eval_numeric(rhs, data)?;
+      if matches!(op, ArithOp::Div | ArithOp::Mod) && rhs.as_f64() == 0.0 {
+        return None;
+      }
+      if let (Num::Int(a), Num::Int(b), false) = (lhs, rhs, *op == ArithOp::Div) {
+        let result = match op {
+          ArithOp::Add => a.checked_add(b),
+          ArithOp::Sub => a.checked_sub(b),
+          ArithOp::Mul => a.checked_mul(b),
+          _ => a.checked_rem(b),
+        };
+        if let Some(result) = result {
+          return Some(Num::Int(result));
+        }
+      }
+      let (a, b) = (lhs.as_f64(), rhs.as_f64());
+      Some(Num::Float(match op {
+        ArithOp::Add => a + b,
+        ArithOp::Sub => a - b,
+        ArithOp::Mul => a * b,
+        ArithOp::Div => a / b,
+        ArithOp::Mod => a % b,
+      }))
+    }
+    Expr::Text(_) | Expr::Concat(_) => None,
+  }
+}
+
+fn eval_text(expr: &Expr, data: &Value) -> String {
+  match expr {
+    Expr::Field(field) => match lookup(data, field) {
+      None | Some(Value::Null) => String::new(),
+      Some(Value::String(s)) => s.clone(),
+      Some(value) => value.to_string(),
+    },
+    Expr::Text(s) => s.clone(),
+    Expr::Concat(args) => args.iter().map(|arg| eval_text(arg, data)).collect(),
+    expr => eval_numeric(expr, data).map_or_else(String::new, Num::to_text),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn parse(s: &str) -> ProjectionField {
+    ProjectionField::parse(s).unwrap()
+  }
+
+  #[test]
+  fn test_parse_plain_fields() {
+    let field = parse("address.city");
+    assert_eq!(field.name, "address.city");
+    assert!(field.is_field());
+  }
+
+  #[test]
+  fn test_parse_computed_fields() {
+    let field = parse("age * 12 as age_months");
+    assert_eq!(field.name, "age_months");
+    assert!(!field.is_field());
+
+    let field = parse("concat(first, ' ', last) AS full_name");
+    assert_eq!(field.name, "full_name");
+
+    assert_eq!(parse("(a + b) * -2 as x").name, "x");
+    assert_eq!(parse("'it''s' as quote").eval(&json!({})), json!("it's"));
+  }
+
+  #[test]
+  fn test_parse_rejects_outside_allowlist() {
+    for input in [
+      "age * 12",
+      "age * 12 as",
+      "age * 12 as a.b",
+      "age * 12 as select",
+      "upper(name) as n",
+      "name || 'x' as n",
+      "name; DROP TABLE documents as n",
+      "concat(first, last as n",
+      "'abc' * 2 as n",
+      "-concat(a) as n",
+      "age as x extra",
+      "from",
+      "'unterminated as n",
+      "1.2.3 as n",
+    ] {
+      assert!(
+        ProjectionField::parse(input).is_err(),
+        "accepted: {}",
+        input
+      );
+    }
+
+    let deep = format!("{}1{} as n", "(".repeat(40), ")".repeat(40));
+    assert!(ProjectionField::parse(&deep).is_err());
+    assert!(ProjectionField::parse(&format!("{}1 as n", "-".repeat(40))).is_err());
+  }
+
+  #[test]
+  fn test_parse_projection_limits() {
+    assert_eq!(
+      parse_projection(&[]),
+      Err(SqlSanitizeError::EmptyProjection)
+    );
+    let many: Vec<String> = (0..=MAX_PROJECTION_FIELDS)
+      .map(|i| format!("f{}", i))
+      .collect();
+    assert_eq!(
+      parse_projection(&many),
+      Err(SqlSanitizeError::TooManyProjectionFields(
+        MAX_PROJECTION_FIELDS + 1
+      ))
+    );
+  }
+
+  #[test]
+  fn test_to_sql() {
+    let field = parse("age * 12 as age_months");
+    assert_eq!(
+      field.to_sql(SqlDialect::Postgres),
+      "((CASE WHEN jsonb_typeof(data->'age') = 'number' THEN (data->'age')::numeric END) * 12)"
+    );
+    assert!(field
+      .to_sql(SqlDialect::Sqlite)
+      .contains("json_type(data, '$.age') IN ('integer', 'real')"));
+
+    let field = parse("concat(first, ' ', name.last) as full_name");
+    assert_eq!(
+      field.to_sql(SqlDialect::Postgres),
+      "concat((data #>> '{first}'), ' ', (data #>> '{name,last}'))"
+    );
+    assert_eq!(
+      field.to_sql(SqlDialect::Sqlite),
+      "(COALESCE(json_extract(data, '$.first'), '') || COALESCE(' ', '') || COALESCE(json_extract(data, '$.name.last'), ''))"
+    );
+
+    let field = parse("a / b as ratio");
+    assert!(field.to_sql(SqlDialect::Postgres).contains("NULLIF("));
+    assert!(field.to_sql(SqlDialect::Sqlite).contains("CAST("));
+  }
+
+  #[test]
+  fn test_eval() {
+    let data = json!({"first": "Ada", "last": "Lovelace", "age": 36, "score": 7, "tags": ["x"]});
+
+    assert_eq!(parse("age * 12 as m").eval(&data), json!(432));
+    assert_eq!(parse("score / 2 as half").eval(&data), json!(3.5));
+    assert_eq!(parse("score % 4 as r").eval(&data), json!(3));
+    assert_eq!(parse("-age + 1 as n").eval(&data), json!(-35));
+    assert_eq!(parse("age / 0 as n").eval(&data), Value::Null);
+    assert_eq!(parse("first * 2 as n").eval(&data), Value::Null);
+    assert_eq!(parse("missing + 1 as n").eval(&data), Value::Null);
+    assert_eq!(
+      parse("concat(first, ' ', last, ' (', age + 1, ')') as n").eval(&data),
+      json!("Ada Lovelace (37)")
+    );
+    assert_eq!(
+      parse("concat(first, missing) as n").eval(&data),
+      json!("Ada")
+    );
+    assert_eq!(parse("tags").eval(&data), json!(["x"]));
+  }
+}
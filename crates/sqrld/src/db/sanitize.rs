@@ -2,12 +2,19 @@
 //!
 //! This module provides functions for safely handling user input in SQL queries.
 
+use std::collections::HashSet;
+
+use crate::types::GeoPoint;
+
 /// Maximum length for identifiers (collection names, field names)
 pub const MAX_IDENTIFIER_LENGTH: usize = 255;
 
 /// Maximum length for string values in queries
 pub const MAX_STRING_VALUE_LENGTH: usize = 65535;
 
+/// Maximum number of fields in a projection (jsonb_build_object takes at most 100 arguments)
+pub const MAX_PROJECTION_FIELDS: usize = 50;
+
 /// Validates that a string is a safe SQL identifier (collection name, field name).
 /// Only allows alphanumeric characters, underscores, and dots (for nested fields).
 /// Returns an error if the identifier is invalid.
@@ -80,6 +87,60 @@ pub fn validate_collection_name(s: &str) -> Result<(), SqlSanitizeError> {
   Ok(())
 }
 
+/// Tables of the SquirrelDB schema, reserved as collection names by default
+/// so that dumps and database tooling never mix them up with user data
+pub const INTERNAL_TABLE_NAMES: &[&str] = &[
+  "admin_auth_tokens",
+  "admin_sessions",
+  "admin_users",
+  "api_tokens",
+  "change_queue",
+  "client_kv",
+  "collection_defaults",
+  "documents",
+  "feature_settings",
+  "idempotency_keys",
+  "project_limits",
+  "project_members",
+  "projects",
+  "rate_limits",
+  "soft_delete_collections",
+  "storage_access_keys",
+  "storage_buckets",
+  "storage_multipart_parts",
+  "storage_multipart_uploads",
+  "storage_objects",
+  "subscription_filters",
+];
+
+/// Collection names clients may not write to
+#[derive(Debug, Clone, Default)]
+pub struct ReservedCollections(HashSet<String>);
+
+impl ReservedCollections {
+  pub fn new<I, S>(names: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    Self(
+      names
+        .into_iter()
+        .map(|n| n.as_ref().trim().to_lowercase())
+        .filter(|n| !n.is_empty())
+        .collect(),
+    )
+  }
+
+  /// Reject a write that would create or add to a reserved collection
+  pub fn check(&self, collection: &str) -> Result<(), SqlSanitizeError> {
+    if self.0.contains(collection) {
+      return Err(SqlSanitizeError::ReservedCollection(collection.to_string()));
+    }
+    Ok(())
+  }
+}
+
 /// Escapes a string value for safe inclusion in SQL.
 /// Handles single quotes, backslashes, and null bytes.
 pub fn escape_string(s: &str) -> Result<String, SqlSanitizeError> {
@@ -143,6 +204,27 @@ pub fn validate_limit(limit: usize) -> Result<(), SqlSanitizeError> {
   Ok(())
 }
 
+/// Validates a geo point (latitude -90..=90, longitude -180..=180).
+pub fn validate_geo_point(point: GeoPoint) -> Result<(), SqlSanitizeError> {
+  if !(-90.0..=90.0).contains(&point.lat) || !(-180.0..=180.0).contains(&point.lng) {
+    return Err(SqlSanitizeError::InvalidGeoPoint(point.lat, point.lng));
+  }
+  Ok(())
+}
+
+/// Validates a distance in meters.
+pub fn validate_distance(meters: f64) -> Result<(), SqlSanitizeError> {
+  if !meters.is_finite() || meters < 0.0 {
+    return Err(SqlSanitizeError::InvalidNumeric(meters.to_string()));
+  }
+  Ok(())
+}
+
+/// Validates the fields and computed expressions of a projection.
+pub fn validate_projection(fields: &[String]) -> Result<(), SqlSanitizeError> {
+  super::projection::parse_projection(fields).map(|_| ())
+}
+
 /// Validates an ORDER BY direction.
 pub fn validate_order_direction(dir: &str) -> Result<&'static str, SqlSanitizeError> {
   match dir.to_uppercase().as_str() {
@@ -165,6 +247,192 @@ pub fn validate_operator(op: &str) -> Result<&'static str, SqlSanitizeError> {
   }
 }
 
+/// Words a compiled filter may contain outside string literals: the `data`
+/// column, the keywords, casts and functions the query compilers emit, and
+/// the `value` column of SQLite's `json_each`
+const FILTER_WORDS: &[&str] = &[
+  "and",
+  "as",
+  "asin",
+  "boolean",
+  "cast",
+  "cos",
+  "data",
+  "escape",
+  "exists",
+  "false",
+  "from",
+  "in",
+  "is",
+  "json_array_length",
+  "json_each",
+  "json_extract",
+  "jsonb",
+  "jsonb_array_length",
+  "least",
+  "like",
+  "min",
+  "not",
+  "null",
+  "numeric",
+  "or",
+  "power",
+  "radians",
+  "real",
+  "select",
+  "sin",
+  "sqrt",
+  "true",
+  "value",
+  "where",
+];
+
+/// Checks SQL from the query compilers before a backend appends it to a
+/// WHERE clause. Outside string literals, only the words in `FILTER_WORDS`,
+/// numbers, `$n`/`?n` placeholders, parentheses, commas and operators are
+/// allowed, so a filter can't reach other columns or tables. Statement
+/// separators, comments, quoted identifiers and unbalanced parentheses are
+/// rejected; backends still wrap the filter in parentheses so that an `OR`
+/// in it can't escape the project and collection conditions.
+pub fn validate_filter_sql(sql: &str) -> Result<(), SqlSanitizeError> {
+  let invalid = |reason: String| Err(SqlSanitizeError::InvalidFilter(reason));
+  let bytes = sql.as_bytes();
+  let mut depth = 0usize;
+  let mut i = 0;
+  while i < bytes.len() {
+    let c = bytes[i];
+    match c {
+      b'\'' => {
+        // A doubled quote is an escaped quote inside the literal
+        i += 1;
+        loop {
+          match bytes.get(i) {
+            None => return invalid("unterminated string".into()),
+            Some(b'\'') if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+            Some(b'\'') => break,
+            Some(_) => i += 1,
+          }
+        }
+        i += 1;
+      }
+      b'-' if bytes.get(i + 1) == Some(&b'-') => return invalid("comments are not allowed".into()),
+      b'/' if bytes.get(i + 1) == Some(&b'*') => return invalid("comments are not allowed".into()),
+      b'$' | b'?' if bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+          i += 1;
+        }
+      }
+      b'0'..=b'9' => {
+        let start = i;
+        while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+          i += 1;
+        }
+        if sql[start..i].parse::<f64>().is_err() {
+          return invalid(format!("invalid number '{}'", &sql[start..i]));
+        }
+      }
+      c if c.is_ascii_alphabetic() || c == b'_' => {
+        let start = i;
+        while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+          i += 1;
+        }
+        let word = &sql[start..i];
+        if !FILTER_WORDS.contains(&word.to_ascii_lowercase().as_str()) {
+          return invalid(format!("'{}' is not allowed", word));
+        }
+      }
+      b'(' => {
+        depth += 1;
+        i += 1;
+      }
+      b')' => {
+        depth = match depth.checked_sub(1) {
+          Some(depth) => depth,
+          None => return invalid("unbalanced parentheses".into()),
+        };
+        i += 1;
+      }
+      b',' | b'=' | b'!' | b'<' | b'>' | b'-' | b'+' | b'*' | b'/' | b'%' | b':' | b'?' | b'@' => {
+        i += 1
+      }
+      c if c.is_ascii_whitespace() => i += 1,
+      _ => {
+        let c = sql[i..].chars().next().unwrap_or_default();
+        return invalid(format!("unexpected '{}'", c));
+      }
+    }
+  }
+  if depth > 0 {
+    return invalid("unbalanced parentheses".into());
+  }
+  Ok(())
+}
+
+/// Counts the statements in a SQL string. Semicolons inside quotes,
+/// dollar-quoted strings and comments don't separate statements, and empty
+/// statements are not counted.
+pub fn count_statements(sql: &str) -> usize {
+  let bytes = sql.as_bytes();
+  let mut count = 0;
+  let mut has_content = false;
+  let mut i = 0;
+  while i < bytes.len() {
+    match bytes[i] {
+      quote @ (b'\'' | b'"' | b'`') => {
+        // A doubled quote closes and reopens the string, which is equivalent
+        has_content = true;
+        i += 1;
+        while i < bytes.len() && bytes[i] != quote {
+          i += 1;
+        }
+      }
+      b'-' if bytes.get(i + 1) == Some(&b'-') => {
+        while i < bytes.len() && bytes[i] != b'\n' {
+          i += 1;
+        }
+      }
+      b'/' if bytes.get(i + 1) == Some(&b'*') => {
+        i += 2;
+        while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+          i += 1;
+        }
+        i += 1;
+      }
+      b'$' => {
+        has_content = true;
+        let mut j = i + 1;
+        while j < bytes.len() && (bytes[j].is_ascii_alphanumeric() || bytes[j] == b'_') {
+          j += 1;
+        }
+        // $tag$ or $$ opens a dollar-quoted string; $1 is a parameter
+        let is_tag =
+          j < bytes.len() && bytes[j] == b'$' && (j == i + 1 || !bytes[i + 1].is_ascii_digit());
+        if is_tag {
+          let tag = &sql[i..=j];
+          i = match sql[j + 1..].find(tag) {
+            Some(pos) => j + pos + tag.len(),
+            None => bytes.len(),
+          };
+        }
+      }
+      b';' => {
+        if has_content {
+          count += 1;
+        }
+        has_content = false;
+      }
+      c if c.is_ascii_whitespace() => {}
+      _ => has_content = true,
+    }
+    i += 1;
+  }
+  if has_content {
+    count += 1;
+  }
+  count
+}
+
 /// SQL sanitization errors
 #[derive(Debug, Clone, PartialEq)]
 pub enum SqlSanitizeError {
@@ -175,12 +443,18 @@ pub enum SqlSanitizeError {
   InvalidCollectionChar(char),
   InvalidFieldPath(String),
   ReservedKeyword(String),
+  ReservedCollection(String),
   StringTooLong(usize),
   NullByteInString,
   InvalidNumeric(String),
   LimitTooLarge(usize, usize),
   InvalidOrderDirection(String),
   InvalidOperator(String),
+  EmptyProjection,
+  TooManyProjectionFields(usize),
+  InvalidExpression(String),
+  InvalidGeoPoint(f64, f64),
+  InvalidFilter(String),
 }
 
 impl std::fmt::Display for SqlSanitizeError {
@@ -213,6 +487,9 @@ impl std::fmt::Display for SqlSanitizeError {
       }
       Self::InvalidFieldPath(s) => write!(f, "Invalid field path: {}", s),
       Self::ReservedKeyword(s) => write!(f, "'{}' is a reserved SQL keyword", s),
+      Self::ReservedCollection(s) => {
+        write!(f, "Collection name '{}' is reserved", s)
+      }
       Self::StringTooLong(len) => {
         write!(f, "String too long: {} > {}", len, MAX_STRING_VALUE_LENGTH)
       }
@@ -223,6 +500,19 @@ impl std::fmt::Display for SqlSanitizeError {
         write!(f, "Invalid order direction '{}', must be ASC or DESC", s)
       }
       Self::InvalidOperator(s) => write!(f, "Invalid operator: {}", s),
+      Self::EmptyProjection => write!(f, "Projection must select at least one field"),
+      Self::TooManyProjectionFields(len) => {
+        write!(
+          f,
+          "Too many projected fields: {} > {}",
+          len, MAX_PROJECTION_FIELDS
+        )
+      }
+      Self::InvalidExpression(s) => write!(f, "Invalid expression: {}", s),
+      Self::InvalidGeoPoint(lat, lng) => {
+        write!(f, "Invalid geo point: lat {}, lng {}", lat, lng)
+      }
+      Self::InvalidFilter(reason) => write!(f, "Invalid compiled filter: {}", reason),
     }
   }
 }
@@ -326,6 +616,60 @@ mod tests {
     assert!(validate_identifier("trailing.").is_err());
   }
 
+  #[test]
+  fn test_validate_projection() {
+    let fields = |f: &[&str]| f.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    assert!(validate_projection(&fields(&["name", "email", "address.city"])).is_ok());
+    assert_eq!(
+      validate_projection(&[]),
+      Err(SqlSanitizeError::EmptyProjection)
+    );
+    assert!(validate_projection(&fields(&["name", "x') || ('"])).is_err());
+    assert!(validate_projection(&fields(&["from"])).is_err());
+
+    let many: Vec<String> = (0..=MAX_PROJECTION_FIELDS)
+      .map(|i| format!("f{}", i))
+      .collect();
+    assert_eq!(
+      validate_projection(&many),
+      Err(SqlSanitizeError::TooManyProjectionFields(
+        MAX_PROJECTION_FIELDS + 1
+      ))
+    );
+  }
+
+  #[test]
+  fn test_validate_geo_point() {
+    assert!(validate_geo_point(GeoPoint {
+      lat: 40.7,
+      lng: -74.0
+    })
+    .is_ok());
+    assert!(validate_geo_point(GeoPoint {
+      lat: -90.0,
+      lng: 180.0
+    })
+    .is_ok());
+    assert!(validate_geo_point(GeoPoint {
+      lat: 91.0,
+      lng: 0.0
+    })
+    .is_err());
+    assert!(validate_geo_point(GeoPoint {
+      lat: 0.0,
+      lng: -181.0
+    })
+    .is_err());
+    assert!(validate_geo_point(GeoPoint {
+      lat: f64::NAN,
+      lng: 0.0
+    })
+    .is_err());
+    assert!(validate_distance(5000.0).is_ok());
+    assert!(validate_distance(-1.0).is_err());
+    assert!(validate_distance(f64::INFINITY).is_err());
+  }
+
   #[test]
   fn test_validate_collection_name() {
     assert!(validate_collection_name("users").is_ok());
@@ -337,6 +681,19 @@ mod tests {
     assert!(validate_collection_name("user-data").is_err()); // dash
   }
 
+  #[test]
+  fn test_reserved_collections() {
+    let reserved = ReservedCollections::new(INTERNAL_TABLE_NAMES.iter().chain(&[" Audit "]));
+    assert!(reserved.check("users").is_ok());
+    assert!(matches!(
+      reserved.check("documents"),
+      Err(SqlSanitizeError::ReservedCollection(_))
+    ));
+    assert!(reserved.check("change_queue").is_err());
+    assert!(reserved.check("audit").is_err());
+    assert!(ReservedCollections::default().check("documents").is_ok());
+  }
+
   #[test]
   fn test_escape_string() {
     assert_eq!(escape_string("hello").unwrap(), "hello");
@@ -382,4 +739,64 @@ mod tests {
     assert!(validate_identifier("' OR '1'='1").is_err());
     assert!(validate_collection_name("users/**/OR/**/1=1").is_err());
   }
+
+  #[test]
+  fn test_count_statements() {
+    assert_eq!(count_statements(""), 0);
+    assert_eq!(count_statements("  ;; -- nothing\n"), 0);
+    assert_eq!(count_statements("SELECT 1"), 1);
+    assert_eq!(count_statements("SELECT 1;  -- trailing"), 1);
+    assert_eq!(count_statements("SELECT 1; SELECT 2"), 2);
+    assert_eq!(count_statements("SELECT 'a;b', \"c;d\" FROM t"), 1);
+    assert_eq!(count_statements("SELECT 'it''s; fine'"), 1);
+    assert_eq!(count_statements("SELECT 1 /* ; */ -- ;\n"), 1);
+    assert_eq!(count_statements("SELECT $$a;b$$, $x$c;d$x$"), 1);
+    assert_eq!(
+      count_statements("SELECT $1; DELETE FROM t WHERE id = $2"),
+      2
+    );
+    assert_eq!(count_statements("SELECT 1; DROP TABLE documents; --"), 2);
+  }
+
+  #[test]
+  fn test_validate_filter_sql() {
+    for sql in [
+      "data->>'name' = 'Alice'",
+      "(data->'address'->>'city' = 'it''s; -- fine' AND (data->'age')::numeric >= 18)",
+      "NOT (data->>'role' IN ('admin', 'owner'))",
+      "data->'tags' ? 'rust' OR data->'scores' @> '[5]'::jsonb",
+      "data->'n' > $3::jsonb",
+      "json_extract(data, '$.n') <= ?3",
+      "CAST(json_extract(data, '$.score') AS REAL) > -1.5",
+      "EXISTS(SELECT 1 FROM json_each(json_extract(data, '$.tags')) WHERE value = 'x')",
+      "data->>'name' LIKE 'a\\%%' ESCAPE '\\'",
+    ] {
+      assert!(validate_filter_sql(sql).is_ok(), "{}", sql);
+    }
+
+    for sql in [
+      "true; DROP TABLE documents",
+      "data->>'name' = 'x' -- AND deleted_at IS NULL",
+      "data->>'name' = 'x' /* */",
+      "project_id IS NOT NULL",
+      "deleted_at IS NOT NULL",
+      "EXISTS(SELECT 1 FROM admin_users)",
+      "data->>'name' = 'unterminated",
+      "\"data\" IS NOT NULL",
+      "data->>'a' = $$x$$",
+      "data->>'a' || 'b' = 'ab'",
+      "pg_sleep(10) IS NULL",
+      "true) OR (true",
+      "(data IS NOT NULL",
+    ] {
+      assert!(
+        matches!(
+          validate_filter_sql(sql),
+          Err(SqlSanitizeError::InvalidFilter(_))
+        ),
+        "{}",
+        sql
+      );
+    }
+  }
 }
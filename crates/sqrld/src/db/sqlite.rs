@@ -1,21 +1,38 @@
 use async_trait::async_trait;
 use chrono::Utc;
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
 use tokio_rusqlite::Connection;
 use uuid::Uuid;
 
+use super::backend::check_document_quota;
 use super::backend::{
-  AdminRole, AdminSession, AdminUser, ApiTokenInfo, DatabaseBackend, SqlDialect,
-  StorageAccessKeyInfo,
+  ActiveQuery, AdminRole, AdminSession, AdminTokenPurpose, AdminUser, ApiTokenInfo,
+  ChangeRetention, CollectionStats, DatabaseBackend, DocumentBatches, DocumentCount, DocumentEdit,
+  DocumentWrite, ListenerHeartbeat, ProjectLimits, RawSqlResult, ReadOptions, SoftDeleteSettings,
+  SqlDialect, StorageAccessKeyInfo, WriteOptions, STATS_SAMPLE_SIZE, STATS_TOP_FIELDS,
+};
+use super::defaults::{with_defaults, CollectionDefaults};
+use super::encryption::FieldEncryption;
+use super::idempotency::IdempotencyClaim;
+use super::projection::parse_projection;
+use super::sanitize::{
+  validate_collection_name, validate_filter_sql, validate_identifier, validate_limit,
+  ReservedCollections,
+};
+use crate::storage::{
+  MultipartPart, MultipartUpload, ObjectAcl, ObjectTags, StorageBucket, StorageObject,
 };
-use super::sanitize::{validate_collection_name, validate_identifier, validate_limit};
-use crate::storage::{MultipartPart, MultipartUpload, ObjectAcl, StorageBucket, StorageObject};
 use crate::types::{
-  Change, ChangeOperation, Document, OrderBySpec, OrderDirection, Project, ProjectMember,
-  ProjectRole, DEFAULT_PROJECT_ID,
+  Change, ChangeOperation, Document, OrderBySpec, Project, ProjectMember, ProjectRole,
+  DEFAULT_PROJECT_ID,
 };
 
+/// How often the change listener polls change_queue
+const CHANGE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 const PRAGMAS: &str = r#"
 PRAGMA journal_mode = WAL;
 PRAGMA synchronous = NORMAL;
@@ -23,6 +40,7 @@ PRAGMA cache_size = -64000;
 PRAGMA temp_store = MEMORY;
 PRAGMA mmap_size = 268435456;
 PRAGMA page_size = 4096;
+PRAGMA foreign_keys = ON;
 "#;
 
 const SCHEMA: &str = r#"
@@ -32,11 +50,13 @@ CREATE TABLE IF NOT EXISTS documents (
     collection TEXT NOT NULL,
     data TEXT NOT NULL,
     created_at TEXT NOT NULL,
-    updated_at TEXT NOT NULL
+    updated_at TEXT NOT NULL,
+    deleted_at TEXT
 ) WITHOUT ROWID;
 CREATE INDEX IF NOT EXISTS idx_documents_collection ON documents(collection);
 CREATE INDEX IF NOT EXISTS idx_documents_project ON documents(project_id);
 CREATE INDEX IF NOT EXISTS idx_documents_project_collection ON documents(project_id, collection);
+CREATE INDEX IF NOT EXISTS idx_documents_list_order ON documents(project_id, collection, created_at, id);
 
 CREATE TABLE IF NOT EXISTS change_queue (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -51,22 +71,48 @@ CREATE TABLE IF NOT EXISTS change_queue (
 CREATE INDEX IF NOT EXISTS idx_change_queue_id ON change_queue(id);
 CREATE INDEX IF NOT EXISTS idx_change_queue_project ON change_queue(project_id);
 CREATE INDEX IF NOT EXISTS idx_change_queue_collection ON change_queue(collection);
+CREATE INDEX IF NOT EXISTS idx_change_queue_document ON change_queue(document_id, id);
 
 CREATE TRIGGER IF NOT EXISTS documents_insert AFTER INSERT ON documents BEGIN
     INSERT INTO change_queue (project_id, collection, document_id, operation, new_data, changed_at)
     VALUES (NEW.project_id, NEW.collection, NEW.id, 'INSERT', NEW.data, datetime('now'));
 END;
 
-CREATE TRIGGER IF NOT EXISTS documents_update AFTER UPDATE ON documents BEGIN
+-- Recreated on startup so older databases pick up the collection and
+-- soft-delete checks. Changes to soft-deleted documents (renames, purges) are
+-- not announced; subscribers already saw them deleted.
+DROP TRIGGER IF EXISTS documents_update;
+CREATE TRIGGER documents_update AFTER UPDATE ON documents WHEN OLD.collection = NEW.collection AND OLD.deleted_at IS NULL AND NEW.deleted_at IS NULL BEGIN
     INSERT INTO change_queue (project_id, collection, document_id, operation, old_data, new_data, changed_at)
     VALUES (NEW.project_id, NEW.collection, NEW.id, 'UPDATE', OLD.data, NEW.data, datetime('now'));
 END;
 
-CREATE TRIGGER IF NOT EXISTS documents_delete AFTER DELETE ON documents BEGIN
+-- A document moved by a collection rename leaves one collection and enters another
+DROP TRIGGER IF EXISTS documents_move;
+CREATE TRIGGER documents_move AFTER UPDATE OF collection ON documents WHEN OLD.collection <> NEW.collection AND OLD.deleted_at IS NULL BEGIN
+    INSERT INTO change_queue (project_id, collection, document_id, operation, old_data, changed_at)
+    VALUES (OLD.project_id, OLD.collection, OLD.id, 'DELETE', OLD.data, datetime('now'));
+    INSERT INTO change_queue (project_id, collection, document_id, operation, new_data, changed_at)
+    VALUES (NEW.project_id, NEW.collection, NEW.id, 'INSERT', NEW.data, datetime('now'));
+END;
+
+DROP TRIGGER IF EXISTS documents_delete;
+CREATE TRIGGER documents_delete AFTER DELETE ON documents WHEN OLD.deleted_at IS NULL BEGIN
+    INSERT INTO change_queue (project_id, collection, document_id, operation, old_data, changed_at)
+    VALUES (OLD.project_id, OLD.collection, OLD.id, 'DELETE', OLD.data, datetime('now'));
+END;
+
+-- Soft deletes and restores look like a delete and an insert to subscribers
+CREATE TRIGGER IF NOT EXISTS documents_soft_delete AFTER UPDATE OF deleted_at ON documents WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL BEGIN
     INSERT INTO change_queue (project_id, collection, document_id, operation, old_data, changed_at)
     VALUES (OLD.project_id, OLD.collection, OLD.id, 'DELETE', OLD.data, datetime('now'));
 END;
 
+CREATE TRIGGER IF NOT EXISTS documents_undelete AFTER UPDATE OF deleted_at ON documents WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL BEGIN
+    INSERT INTO change_queue (project_id, collection, document_id, operation, new_data, changed_at)
+    VALUES (NEW.project_id, NEW.collection, NEW.id, 'INSERT', NEW.data, datetime('now'));
+END;
+
 CREATE TABLE IF NOT EXISTS api_tokens (
     id TEXT PRIMARY KEY,
     project_id TEXT NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000',
@@ -77,11 +123,164 @@ CREATE TABLE IF NOT EXISTS api_tokens (
 ) WITHOUT ROWID;
 CREATE INDEX IF NOT EXISTS idx_api_tokens_hash ON api_tokens(token_hash);
 CREATE INDEX IF NOT EXISTS idx_api_tokens_project ON api_tokens(project_id);
+
+-- S3 storage metadata. Timestamps are RFC 3339 text with fixed precision so
+-- they sort correctly; the Postgres sqrl_* storage functions are done in Rust.
+CREATE TABLE IF NOT EXISTS storage_buckets (
+    name TEXT PRIMARY KEY,
+    owner_id TEXT,
+    versioning_enabled INTEGER NOT NULL DEFAULT 0,
+    acl TEXT NOT NULL DEFAULT '{"grants": []}',
+    lifecycle_rules TEXT NOT NULL DEFAULT '[]',
+    quota_bytes INTEGER,
+    current_size INTEGER NOT NULL DEFAULT 0,
+    object_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS storage_objects (
+    bucket TEXT NOT NULL REFERENCES storage_buckets(name) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    version_id TEXT NOT NULL,
+    is_latest INTEGER NOT NULL DEFAULT 1,
+    etag TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    content_type TEXT NOT NULL DEFAULT 'application/octet-stream',
+    storage_path TEXT NOT NULL,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    acl TEXT NOT NULL DEFAULT '{"grants": []}',
+    is_delete_marker INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    tags TEXT NOT NULL DEFAULT '{}',
+    PRIMARY KEY (bucket, key, version_id)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS idx_storage_objects_latest ON storage_objects(bucket, key) WHERE is_latest = 1;
+
+CREATE TABLE IF NOT EXISTS storage_multipart_uploads (
+    upload_id TEXT PRIMARY KEY,
+    bucket TEXT NOT NULL REFERENCES storage_buckets(name) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    content_type TEXT,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    initiated_at TEXT NOT NULL
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS idx_storage_multipart_uploads_bucket ON storage_multipart_uploads(bucket, initiated_at);
+
+CREATE TABLE IF NOT EXISTS storage_multipart_parts (
+    upload_id TEXT NOT NULL REFERENCES storage_multipart_uploads(upload_id) ON DELETE CASCADE,
+    part_number INTEGER NOT NULL,
+    etag TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    storage_path TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (upload_id, part_number)
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS storage_access_keys (
+    access_key_id TEXT PRIMARY KEY,
+    secret_access_key TEXT NOT NULL,
+    owner_id TEXT,
+    name TEXT NOT NULL,
+    permissions TEXT NOT NULL DEFAULT '{"buckets": "*", "actions": "*"}',
+    created_at TEXT NOT NULL
+) WITHOUT ROWID;
+
+-- Insert idempotency keys; response is NULL while the insert is running
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    project_id TEXT NOT NULL,
+    collection TEXT NOT NULL,
+    key TEXT NOT NULL,
+    response TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (project_id, collection, key)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_created ON idempotency_keys(created_at);
+
+-- Per-project quotas (0 = unlimited)
+CREATE TABLE IF NOT EXISTS project_limits (
+    project_id TEXT PRIMARY KEY,
+    requests_per_second INTEGER NOT NULL DEFAULT 0,
+    burst_size INTEGER NOT NULL DEFAULT 0,
+    max_documents INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL
+) WITHOUT ROWID;
+
+-- Collections whose deletes set documents.deleted_at instead of removing rows
+CREATE TABLE IF NOT EXISTS soft_delete_collections (
+    project_id TEXT NOT NULL,
+    collection TEXT NOT NULL,
+    retention_secs INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (project_id, collection)
+) WITHOUT ROWID;
+
+-- Values inserts fill in for fields a document leaves out
+CREATE TABLE IF NOT EXISTS collection_defaults (
+    project_id TEXT NOT NULL,
+    collection TEXT NOT NULL,
+    defaults TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (project_id, collection)
+) WITHOUT ROWID;
+
+-- Small JSON values clients store by namespace and key
+CREATE TABLE IF NOT EXISTS client_kv (
+    project_id TEXT NOT NULL,
+    namespace TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    expires_at TEXT,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (project_id, namespace, key)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS idx_client_kv_expires ON client_kv(expires_at) WHERE expires_at IS NOT NULL;
 "#;
 
+/// Add columns introduced after a database was created. Runs before `SCHEMA`,
+/// whose triggers refer to them.
+fn migrate(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+  add_missing_column(conn, "documents", "deleted_at", "TEXT")?;
+  add_missing_column(
+    conn,
+    "storage_objects",
+    "tags",
+    "TEXT NOT NULL DEFAULT '{}'",
+  )?;
+  Ok(())
+}
+
+/// Add `column` to `table` if the table exists without it
+fn add_missing_column(
+  conn: &rusqlite::Connection,
+  table: &str,
+  column: &str,
+  definition: &str,
+) -> rusqlite::Result<()> {
+  let missing: bool = conn.query_row(
+    "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1) \
+     AND NOT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+    params![table, column],
+    |row| row.get(0),
+  )?;
+  if missing {
+    conn.execute_batch(&format!(
+      "ALTER TABLE {} ADD COLUMN {} {}",
+      table, column, definition
+    ))?;
+  }
+  Ok(())
+}
+
+/// Random version 4 UUID in SQL, for copies made with INSERT ... SELECT
+const SQL_UUID_V4: &str = "lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2) || '-' || substr('89ab', 1 + (abs(random()) % 4), 1) || substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6)))";
+
 pub struct SqliteBackend {
   conn: Connection,
   change_tx: broadcast::Sender<Change>,
+  heartbeat: Arc<ListenerHeartbeat>,
+  encryption: Arc<FieldEncryption>,
+  retention: ChangeRetention,
+  reserved: ReservedCollections,
 }
 
 impl SqliteBackend {
@@ -98,12 +297,89 @@ impl SqliteBackend {
       .await?;
 
     let (change_tx, _) = broadcast::channel(4096);
-    Ok(Self { conn, change_tx })
+    Ok(Self {
+      conn,
+      change_tx,
+      heartbeat: Arc::new(ListenerHeartbeat::new(CHANGE_POLL_INTERVAL)),
+      encryption: Arc::default(),
+      retention: ChangeRetention::default(),
+      reserved: ReservedCollections::default(),
+    })
+  }
+
+  /// Encrypt and decrypt document fields as configured
+  pub fn with_field_encryption(mut self, encryption: Arc<FieldEncryption>) -> Self {
+    self.encryption = encryption;
+    self
+  }
+
+  /// Refuse inserts into, and copies or renames to, these collection names
+  pub fn with_reserved_collections(mut self, reserved: ReservedCollections) -> Self {
+    self.reserved = reserved;
+    self
+  }
+
+  /// Keep change_queue rows as configured
+  pub fn with_change_retention(mut self, retention: ChangeRetention) -> Self {
+    self.retention = retention;
+    self
   }
 
   pub async fn in_memory() -> Result<Self, anyhow::Error> {
     Self::new(":memory:").await
   }
+
+  /// Run a statement that fills the empty collection `to` from `from`
+  /// (parameters: project, from, to) in a transaction, rolled back if the
+  /// project ends up with more than `max_documents` documents (0 = no quota)
+  async fn fill_collection(
+    &self,
+    project_id: Uuid,
+    from: &str,
+    to: &str,
+    sql: String,
+    max_documents: u64,
+  ) -> Result<u64, anyhow::Error> {
+    validate_collection_name(from)?;
+    validate_collection_name(to)?;
+    self.reserved.check(to)?;
+    if from == to {
+      anyhow::bail!("Source and target collection are the same");
+    }
+    self.encryption.check_move(from, to)?;
+
+    let project_id_str = project_id.to_string();
+    let from_col = from.to_string();
+    let to_col = to.to_string();
+    // A refused fill is returned inside Ok, dropping (rolling back) the transaction
+    self
+      .conn
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        let exists: bool = tx.query_row(
+          "SELECT EXISTS(SELECT 1 FROM documents WHERE project_id = ?1 AND collection = ?2)",
+          params![project_id_str, to_col],
+          |row| row.get(0),
+        )?;
+        if exists {
+          return Ok(Err(anyhow::anyhow!(
+            "Collection '{}' already exists",
+            to_col
+          )));
+        }
+        let count = tx.execute(&sql, params![project_id_str, from_col, to_col])?;
+        if max_documents > 0 {
+          let documents = count_documents(&tx, &project_id_str)?;
+          if let Err(e) = check_document_quota(project_id, documents, max_documents) {
+            return Ok(Err(e));
+          }
+        }
+        tx.commit()?;
+        Ok(Ok(count as u64))
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?
+  }
 }
 
 #[async_trait]
@@ -112,10 +388,17 @@ impl DatabaseBackend for SqliteBackend {
     SqlDialect::Sqlite
   }
 
+  fn field_encryption(&self) -> Arc<FieldEncryption> {
+    self.encryption.clone()
+  }
+
   async fn init_schema(&self) -> Result<(), anyhow::Error> {
     self
       .conn
-      .call(|conn| conn.execute_batch(SCHEMA).map_err(|e| e.into()))
+      .call(|conn| {
+        migrate(conn)?;
+        conn.execute_batch(SCHEMA).map_err(|e| e.into())
+      })
       .await?;
     tracing::info!("SQLite schema initialized");
     Ok(())
@@ -129,7 +412,10 @@ impl DatabaseBackend for SqliteBackend {
           .execute_batch(
             "DROP TRIGGER IF EXISTS documents_insert;
          DROP TRIGGER IF EXISTS documents_update;
+         DROP TRIGGER IF EXISTS documents_move;
          DROP TRIGGER IF EXISTS documents_delete;
+         DROP TRIGGER IF EXISTS documents_soft_delete;
+         DROP TRIGGER IF EXISTS documents_undelete;
          DROP TABLE IF EXISTS change_queue;
          DROP TABLE IF EXISTS documents;",
           )
@@ -181,6 +467,117 @@ impl DatabaseBackend for SqliteBackend {
     Ok(false)
   }
 
+  async fn get_project_limits(
+    &self,
+    project_id: Uuid,
+  ) -> Result<Option<ProjectLimits>, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            "SELECT requests_per_second, burst_size, max_documents FROM project_limits WHERE project_id = ?1",
+            params![project_id_str],
+            |row| {
+              Ok(ProjectLimits {
+                requests_per_second: row.get(0)?,
+                burst_size: row.get(1)?,
+                max_documents: row.get::<_, i64>(2)? as u64,
+              })
+            },
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn list_project_limits(&self) -> Result<Vec<(Uuid, ProjectLimits)>, anyhow::Error> {
+    self
+      .conn
+      .call(|conn| {
+        let mut stmt = conn.prepare(
+          "SELECT project_id, requests_per_second, burst_size, max_documents FROM project_limits",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut limits = Vec::new();
+        while let Some(row) = rows.next()? {
+          let id: String = row.get(0)?;
+          let Ok(id) = Uuid::parse_str(&id) else {
+            continue;
+          };
+          limits.push((
+            id,
+            ProjectLimits {
+              requests_per_second: row.get(1)?,
+              burst_size: row.get(2)?,
+              max_documents: row.get::<_, i64>(3)? as u64,
+            },
+          ));
+        }
+        Ok(limits)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn set_project_limits(
+    &self,
+    project_id: Uuid,
+    limits: &ProjectLimits,
+  ) -> Result<(), anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let limits = *limits;
+    let now = storage_timestamp();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "INSERT INTO project_limits (project_id, requests_per_second, burst_size, max_documents, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5)
+           ON CONFLICT(project_id) DO UPDATE SET requests_per_second = excluded.requests_per_second,
+             burst_size = excluded.burst_size, max_documents = excluded.max_documents,
+             updated_at = excluded.updated_at",
+          params![
+            project_id_str,
+            limits.requests_per_second,
+            limits.burst_size,
+            limits.max_documents.min(i64::MAX as u64) as i64,
+            now
+          ],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn delete_project_limits(&self, project_id: Uuid) -> Result<bool, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        let n = conn.execute(
+          "DELETE FROM project_limits WHERE project_id = ?1",
+          params![project_id_str],
+        )?;
+        Ok(n > 0)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn count_project_documents(&self, project_id: Uuid) -> Result<u64, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    self
+      .conn
+      .call(move |conn| Ok(count_documents(conn, &project_id_str)?))
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
   // =========================================================================
   // Project Membership Methods - SQLite stubs
   // =========================================================================
@@ -236,32 +633,161 @@ impl DatabaseBackend for SqliteBackend {
     collection: &str,
     data: serde_json::Value,
   ) -> Result<Document, anyhow::Error> {
+    self
+      .insert_with_options(project_id, collection, data, WriteOptions::default())
+      .await
+  }
+
+  async fn insert_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    data: serde_json::Value,
+    options: WriteOptions,
+  ) -> Result<Document, anyhow::Error> {
+    self
+      .write_document(
+        project_id,
+        collection,
+        DocumentWrite::Insert { data, options },
+        false,
+      )
+      .await?
+      .map(|(doc, _)| doc)
+      .ok_or_else(|| anyhow::anyhow!("Insert returned no document"))
+  }
+
+  async fn write_document(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    write: DocumentWrite,
+    track_change: bool,
+  ) -> Result<Option<(Document, Option<i64>)>, anyhow::Error> {
     // Validate collection name (defense in depth - query is parameterized)
     validate_collection_name(collection)?;
 
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-    let data_str = serde_json::to_string(&data)?;
-    let now_str = now.to_rfc3339();
     let col = collection.to_string();
-    let id_str = id.to_string();
     let project_id_str = project_id.to_string();
+    // Each write runs in a single call on the one connection, so the change
+    // read after it is the write's own
+    let written = match write {
+      DocumentWrite::Insert { data, options } => {
+        self.reserved.check(collection)?;
+        let data = with_defaults(self, project_id, collection, data, &options, true).await?;
+
+        let id = Uuid::new_v4();
+        let (created_at, updated_at) = options.insert_timestamps(Utc::now());
+        let data_str =
+          serde_json::to_string(&self.encryption.encrypt(collection, id, data.clone())?)?;
+        let created_str = created_at.to_rfc3339();
+        let updated_str = updated_at.to_rfc3339();
+        let id_str = id.to_string();
+
+        let max_documents = options.max_documents;
+        // A refused insert is returned inside Ok. The count and the insert
+        // share the one connection, so no other insert can come between.
+        let change_id = self.conn.call(move |conn| {
+          if max_documents > 0 {
+            let documents = count_documents(conn, &project_id_str)?;
+            if let Err(e) = check_document_quota(project_id, documents + 1, max_documents) {
+              return Ok(Err(e));
+            }
+          }
+          conn.execute(
+            "INSERT INTO documents (id, project_id, collection, data, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id_str, project_id_str, col, data_str, created_str, updated_str],
+          )?;
+          Ok(Ok(document_change_id(conn, &id_str, track_change)?))
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))??;
+
+        // Returned as written, so there is nothing to decrypt
+        let doc = Document {
+          id,
+          project_id,
+          collection: collection.into(),
+          data,
+          created_at,
+          updated_at,
+          deleted_at: None,
+        };
+        return Ok(Some((doc, change_id)));
+      }
+      DocumentWrite::Update { id, data, options } => {
+        let id_str = id.to_string();
+        let data = with_defaults(self, project_id, collection, data, &options, false).await?;
+        let data_str = serde_json::to_string(&self.encryption.encrypt(collection, id, data)?)?;
+        // NULL keeps the current updated_at
+        let now_str = (!options.no_touch).then(|| Utc::now().to_rfc3339());
+
+        self
+          .conn
+          .call(move |conn| {
+            let changed = conn.execute(
+              "UPDATE documents SET data = ?1, updated_at = COALESCE(?2, updated_at) WHERE project_id = ?3 AND collection = ?4 AND id = ?5 AND deleted_at IS NULL",
+              params![data_str, now_str, project_id_str, col, id_str],
+            )?;
+            if changed == 0 {
+              return Ok(None);
+            }
+
+            let doc = conn
+              .prepare_cached(
+                "SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE id = ?1",
+              )?
+              .query_row(params![id_str], row_to_doc)?;
+            Ok(Some((doc, document_change_id(conn, &id_str, track_change)?)))
+          })
+          .await
+      }
+      DocumentWrite::Delete { id } => {
+        let id_str = id.to_string();
+        self.conn.call(move |conn| {
+          let mut doc = match conn
+            .prepare_cached("SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE project_id = ?1 AND collection = ?2 AND id = ?3 AND deleted_at IS NULL")?
+            .query_row(params![project_id_str, col, id_str], row_to_doc)
+            .optional()?
+          {
+            Some(doc) => doc,
+            None => return Ok(None),
+          };
+          if soft_delete_enabled(conn, &project_id_str, &col)? {
+            let now = Utc::now();
+            conn.execute("UPDATE documents SET deleted_at = ?4 WHERE project_id = ?1 AND collection = ?2 AND id = ?3", params![project_id_str, col, id_str, format_timestamp(now)])?;
+            doc.deleted_at = Some(now);
+          } else {
+            conn.execute("DELETE FROM documents WHERE project_id = ?1 AND collection = ?2 AND id = ?3", params![project_id_str, col, id_str])?;
+          }
+          Ok(Some((doc, document_change_id(conn, &id_str, track_change)?)))
+        }).await
+      }
+      DocumentWrite::Restore { id } => {
+        let id_str = id.to_string();
+        self
+          .conn
+          .call(move |conn| {
+            let changed = conn.execute(
+              "UPDATE documents SET deleted_at = NULL WHERE project_id = ?1 AND collection = ?2 AND id = ?3 AND deleted_at IS NOT NULL",
+              params![project_id_str, col, id_str],
+            )?;
+            if changed == 0 {
+              return Ok(None);
+            }
+            let doc = conn.query_row(
+              "SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE id = ?1",
+              params![id_str],
+              row_to_doc,
+            )?;
+            Ok(Some((doc, document_change_id(conn, &id_str, track_change)?)))
+          })
+          .await
+      }
+    };
 
-    self.conn.call(move |conn| {
-      conn.execute(
-        "INSERT INTO documents (id, project_id, collection, data, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![id_str, project_id_str, col, data_str, now_str, now_str],
-      ).map_err(|e| e.into())
-    }).await?;
-
-    Ok(Document {
-      id,
-      project_id,
-      collection: collection.into(),
-      data,
-      created_at: now,
-      updated_at: now,
-    })
+    written
+      .map_err(|e| anyhow::anyhow!("{}", e))?
+      .map(|(doc, change_id)| Ok((self.encryption.decrypt_document(doc)?, change_id)))
+      .transpose()
   }
 
   async fn get(
@@ -269,6 +795,18 @@ impl DatabaseBackend for SqliteBackend {
     project_id: Uuid,
     collection: &str,
     id: Uuid,
+  ) -> Result<Option<Document>, anyhow::Error> {
+    self
+      .get_with_options(project_id, collection, id, ReadOptions::default())
+      .await
+  }
+
+  async fn get_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+    options: ReadOptions,
   ) -> Result<Option<Document>, anyhow::Error> {
     // Validate collection name (defense in depth - query is parameterized)
     validate_collection_name(collection)?;
@@ -278,14 +816,43 @@ impl DatabaseBackend for SqliteBackend {
     let project_id_str = project_id.to_string();
 
     self.conn.call(move |conn| {
-      let mut stmt = conn.prepare_cached("SELECT id, project_id, collection, data, created_at, updated_at FROM documents WHERE project_id = ?1 AND collection = ?2 AND id = ?3")?;
-      let mut rows = stmt.query(params![project_id_str, col, id_str])?;
+      let mut stmt = conn.prepare_cached("SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE project_id = ?1 AND collection = ?2 AND id = ?3 AND (?4 OR deleted_at IS NULL)")?;
+      let mut rows = stmt.query(params![project_id_str, col, id_str, options.with_deleted])?;
       if let Some(row) = rows.next()? {
         Ok(Some(row_to_doc(row)?))
       } else {
         Ok(None)
       }
-    }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }).await.map_err(|e| anyhow::anyhow!("{}", e))?
+    .map(|doc| self.encryption.decrypt_document(doc))
+    .transpose()
+  }
+
+  async fn get_many(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    ids: &[Uuid],
+    options: ReadOptions,
+  ) -> Result<Vec<Document>, anyhow::Error> {
+    validate_collection_name(collection)?;
+
+    let col = collection.to_string();
+    let ids_json = serde_json::to_string(ids)?;
+    let project_id_str = project_id.to_string();
+
+    self.conn.call(move |conn| {
+      let mut stmt = conn.prepare_cached("SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE project_id = ?1 AND collection = ?2 AND id IN (SELECT value FROM json_each(?3)) AND (?4 OR deleted_at IS NULL)")?;
+      let mut rows = stmt.query(params![project_id_str, col, ids_json, options.with_deleted])?;
+      let mut docs = Vec::new();
+      while let Some(row) = rows.next()? {
+        docs.push(row_to_doc(row)?);
+      }
+      Ok(docs)
+    }).await.map_err(|e| anyhow::anyhow!("{}", e))?
+    .into_iter()
+    .map(|doc| self.encryption.decrypt_document(doc))
+    .collect()
   }
 
   async fn update(
@@ -295,79 +862,699 @@ impl DatabaseBackend for SqliteBackend {
     id: Uuid,
     data: serde_json::Value,
   ) -> Result<Option<Document>, anyhow::Error> {
-    // Validate collection name (defense in depth - query is parameterized)
+    self
+      .update_with_options(project_id, collection, id, data, WriteOptions::default())
+      .await
+  }
+
+  async fn update_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+    data: serde_json::Value,
+    options: WriteOptions,
+  ) -> Result<Option<Document>, anyhow::Error> {
+    let write = DocumentWrite::Update { id, data, options };
+    let written = self
+      .write_document(project_id, collection, write, false)
+      .await?;
+    Ok(written.map(|(doc, _)| doc))
+  }
+
+  async fn edit_document(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+    edit: DocumentEdit,
+    options: WriteOptions,
+  ) -> Result<Option<Document>, anyhow::Error> {
     validate_collection_name(collection)?;
 
     let col = collection.to_string();
     let id_str = id.to_string();
     let project_id_str = project_id.to_string();
-    let data_str = serde_json::to_string(&data)?;
-    let now = Utc::now();
-    let now_str = now.to_rfc3339();
+    let encryption = self.encryption.clone();
+    // NULL keeps the current updated_at
+    let now_str = (!options.no_touch).then(|| Utc::now().to_rfc3339());
 
+    // A failed edit is returned inside Ok, dropping (rolling back) the transaction
     self
       .conn
       .call(move |conn| {
-        let changed = conn.execute(
-          "UPDATE documents SET data = ?1, updated_at = ?2 WHERE project_id = ?3 AND collection = ?4 AND id = ?5",
+        let tx = conn.transaction()?;
+        let current = tx
+          .query_row(
+            "SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE project_id = ?1 AND collection = ?2 AND id = ?3 AND deleted_at IS NULL",
+            params![project_id_str, col, id_str],
+            row_to_doc,
+          )
+          .optional()?;
+        let Some(current) = current else {
+          return Ok(Ok(None));
+        };
+        let data = encryption
+          .decrypt_document(current)
+          .and_then(|doc| edit(doc.data))
+          .and_then(|data| encryption.encrypt(&col, id, data))
+          .and_then(|data| Ok(serde_json::to_string(&data)?));
+        let data_str = match data {
+          Ok(data) => data,
+          Err(e) => return Ok(Err(e)),
+        };
+        tx.execute(
+          "UPDATE documents SET data = ?1, updated_at = COALESCE(?2, updated_at) WHERE project_id = ?3 AND collection = ?4 AND id = ?5",
           params![data_str, now_str, project_id_str, col, id_str],
         )?;
-        if changed == 0 {
-          return Ok(None);
-        }
-
-        let mut stmt = conn.prepare_cached(
-          "SELECT id, project_id, collection, data, created_at, updated_at FROM documents WHERE id = ?1",
+        let doc = tx.query_row(
+          "SELECT id, project_id, collection, data, created_at, updated_at, deleted_at FROM documents WHERE id = ?1",
+          params![id_str],
+          row_to_doc,
         )?;
-        let mut rows = stmt.query(params![id_str])?;
-        if let Some(row) = rows.next()? {
-          Ok(Some(row_to_doc(row)?))
-        } else {
-          Ok(None)
-        }
+        tx.commit()?;
+        Ok(Ok(Some(doc)))
       })
       .await
-      .map_err(|e| anyhow::anyhow!("{}", e))
+      .map_err(|e| anyhow::anyhow!("{}", e))??
+      .map(|doc| self.encryption.decrypt_document(doc))
+      .transpose()
   }
 
-  async fn delete(
-    &self,
-    project_id: Uuid,
-    collection: &str,
-    id: Uuid,
-  ) -> Result<Option<Document>, anyhow::Error> {
-    // Validate collection name (defense in depth - query is parameterized)
-    validate_collection_name(collection)?;
+  async fn restore_document(&self, doc: &Document) -> Result<(), anyhow::Error> {
+    validate_collection_name(&doc.collection)?;
 
-    let col = collection.to_string();
-    let id_str = id.to_string();
-    let project_id_str = project_id.to_string();
+    let id_str = doc.id.to_string();
+    let project_id_str = doc.project_id.to_string();
+    let col = doc.collection.clone();
+    let data_str =
+      serde_json::to_string(&self.encryption.encrypt(&col, doc.id, doc.data.clone())?)?;
+    let created_str = doc.created_at.to_rfc3339();
+    let updated_str = doc.updated_at.to_rfc3339();
+    let deleted_str = doc.deleted_at.map(format_timestamp);
 
     self.conn.call(move |conn| {
-      let mut stmt = conn.prepare_cached("SELECT id, project_id, collection, data, created_at, updated_at FROM documents WHERE project_id = ?1 AND collection = ?2 AND id = ?3")?;
-      let mut rows = stmt.query(params![project_id_str.clone(), col.clone(), id_str.clone()])?;
-      let doc = if let Some(row) = rows.next()? { Some(row_to_doc(row)?) } else { return Ok(None) };
-      drop(rows);
-      drop(stmt);
-      conn.execute("DELETE FROM documents WHERE project_id = ?1 AND collection = ?2 AND id = ?3", params![project_id_str, col, id_str])?;
-      Ok(doc)
-    }).await.map_err(|e| anyhow::anyhow!("{}", e))
+      conn.execute(
+        "INSERT OR REPLACE INTO documents (id, project_id, collection, data, created_at, updated_at, deleted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![id_str, project_id_str, col, data_str, created_str, updated_str, deleted_str],
+      ).map_err(|e| e.into())
+    }).await?;
+    Ok(())
   }
 
-  async fn list(
+  async fn restore_documents(&self, docs: &[Document], wipe: bool) -> Result<(), anyhow::Error> {
+    let mut rows = Vec::with_capacity(docs.len());
+    for doc in docs {
+      validate_collection_name(&doc.collection)?;
+      rows.push((
+        doc.id.to_string(),
+        doc.project_id.to_string(),
+        doc.collection.clone(),
+        serde_json::to_string(&self.encryption.encrypt(
+          &doc.collection,
+          doc.id,
+          doc.data.clone(),
+        )?)?,
+        doc.created_at.to_rfc3339(),
+        doc.updated_at.to_rfc3339(),
+        doc.deleted_at.map(format_timestamp),
+      ));
+    }
+
+    self
+      .conn
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        if wipe {
+          tx.execute("DELETE FROM documents", [])?;
+        }
+        {
+          let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO documents (id, project_id, collection, data, created_at, updated_at, deleted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+          )?;
+          for (id, project_id, col, data, created, updated, deleted) in rows {
+            stmt.execute(params![id, project_id, col, data, created, updated, deleted])?;
+          }
+        }
+        tx.commit()?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn get_soft_delete(
     &self,
     project_id: Uuid,
     collection: &str,
-    filter: Option<&str>,
-    order: Option<&OrderBySpec>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-  ) -> Result<Vec<Document>, anyhow::Error> {
-    // Validate collection name
+  ) -> Result<Option<SoftDeleteSettings>, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            "SELECT retention_secs FROM soft_delete_collections WHERE project_id = ?1 AND collection = ?2",
+            params![project_id_str, col],
+            |row| {
+              Ok(SoftDeleteSettings {
+                retention_secs: row.get::<_, i64>(0)? as u64,
+              })
+            },
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn list_soft_delete(
+    &self,
+  ) -> Result<Vec<(Uuid, String, SoftDeleteSettings)>, anyhow::Error> {
+    self
+      .conn
+      .call(|conn| {
+        let mut stmt = conn.prepare_cached(
+          "SELECT project_id, collection, retention_secs FROM soft_delete_collections ORDER BY project_id, collection",
+        )?;
+        let rows = stmt
+          .query_map([], |row| {
+            Ok((
+              row
+                .get::<_, String>(0)?
+                .parse()
+                .unwrap_or(DEFAULT_PROJECT_ID),
+              row.get(1)?,
+              SoftDeleteSettings {
+                retention_secs: row.get::<_, i64>(2)? as u64,
+              },
+            ))
+          })?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn set_soft_delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    settings: SoftDeleteSettings,
+  ) -> Result<(), anyhow::Error> {
+    validate_collection_name(collection)?;
+
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    let now = storage_timestamp();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "INSERT INTO soft_delete_collections (project_id, collection, retention_secs, updated_at)
+           VALUES (?1, ?2, ?3, ?4)
+           ON CONFLICT(project_id, collection) DO UPDATE SET retention_secs = excluded.retention_secs,
+             updated_at = excluded.updated_at",
+          params![
+            project_id_str,
+            col,
+            settings.retention_secs.min(i64::MAX as u64) as i64,
+            now
+          ],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn disable_soft_delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<bool, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        let n = conn.execute(
+          "DELETE FROM soft_delete_collections WHERE project_id = ?1 AND collection = ?2",
+          params![project_id_str, col],
+        )?;
+        Ok(n > 0)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn get_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<Option<CollectionDefaults>, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    let defaults: Option<String> = self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            "SELECT defaults FROM collection_defaults WHERE project_id = ?1 AND collection = ?2",
+            params![project_id_str, col],
+            |row| row.get(0),
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(defaults.map(|d| serde_json::from_str(&d)).transpose()?)
+  }
+
+  async fn set_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    defaults: &CollectionDefaults,
+  ) -> Result<(), anyhow::Error> {
+    validate_collection_name(collection)?;
+    defaults.validate()?;
+
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    let defaults_str = serde_json::to_string(defaults)?;
+    let now = storage_timestamp();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "INSERT INTO collection_defaults (project_id, collection, defaults, updated_at)
+           VALUES (?1, ?2, ?3, ?4)
+           ON CONFLICT(project_id, collection) DO UPDATE SET defaults = excluded.defaults,
+             updated_at = excluded.updated_at",
+          params![project_id_str, col, defaults_str, now],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn delete_collection_defaults(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<bool, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        let n = conn.execute(
+          "DELETE FROM collection_defaults WHERE project_id = ?1 AND collection = ?2",
+          params![project_id_str, col],
+        )?;
+        Ok(n > 0)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn get_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+  ) -> Result<Option<serde_json::Value>, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let namespace = namespace.to_string();
+    let key = key.to_string();
+    let now = storage_timestamp();
+    let value: Option<String> = self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            "SELECT value FROM client_kv WHERE project_id = ?1 AND namespace = ?2 AND key = ?3
+               AND (expires_at IS NULL OR expires_at > ?4)",
+            params![project_id_str, namespace, key, now],
+            |row| row.get(0),
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(value.map(|v| serde_json::from_str(&v)).transpose()?)
+  }
+
+  async fn set_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+    value: &serde_json::Value,
+    ttl: Option<Duration>,
+  ) -> Result<(), anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let namespace = namespace.to_string();
+    let key = key.to_string();
+    let value_str = serde_json::to_string(value)?;
+    let expires_at = ttl
+      .and_then(|ttl| chrono::Duration::from_std(ttl).ok())
+      .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+      .map(format_timestamp);
+    let now = storage_timestamp();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "INSERT INTO client_kv (project_id, namespace, key, value, expires_at, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+           ON CONFLICT(project_id, namespace, key) DO UPDATE SET value = excluded.value,
+             expires_at = excluded.expires_at, updated_at = excluded.updated_at",
+          params![project_id_str, namespace, key, value_str, expires_at, now],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn delete_kv(
+    &self,
+    project_id: Uuid,
+    namespace: &str,
+    key: &str,
+  ) -> Result<bool, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let namespace = namespace.to_string();
+    let key = key.to_string();
+    let now = storage_timestamp();
+    self
+      .conn
+      .call(move |conn| {
+        let n = conn.execute(
+          "DELETE FROM client_kv WHERE project_id = ?1 AND namespace = ?2 AND key = ?3
+             AND (expires_at IS NULL OR expires_at > ?4)",
+          params![project_id_str, namespace, key, now],
+        )?;
+        Ok(n > 0)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn purge_expired_kv(&self) -> Result<u64, anyhow::Error> {
+    let now = storage_timestamp();
+    self
+      .conn
+      .call(move |conn| {
+        let n = conn.execute("DELETE FROM client_kv WHERE expires_at <= ?1", params![now])?;
+        Ok(n as u64)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn restore_deleted(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+  ) -> Result<Option<Document>, anyhow::Error> {
+    let write = DocumentWrite::Restore { id };
+    let written = self
+      .write_document(project_id, collection, write, false)
+      .await?;
+    Ok(written.map(|(doc, _)| doc))
+  }
+
+  async fn purge_deleted(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    retention: Duration,
+  ) -> Result<u64, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    let cutoff = expiry_cutoff(retention);
+    self
+      .conn
+      .call(move |conn| {
+        let n = conn.execute(
+          "DELETE FROM documents WHERE project_id = ?1 AND collection = ?2 AND deleted_at < ?3",
+          params![project_id_str, col, cutoff],
+        )?;
+        Ok(n as u64)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn delete_many(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    ids: &[Uuid],
+  ) -> Result<Vec<Uuid>, anyhow::Error> {
+    validate_collection_name(collection)?;
+
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    let ids = ids.to_vec();
+    self
+      .conn
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        let mut deleted = Vec::new();
+        {
+          let deleted_at = soft_delete_enabled(&tx, &project_id_str, &col)?.then(storage_timestamp);
+          let mut stmt = tx.prepare_cached(match deleted_at {
+            Some(_) => "UPDATE documents SET deleted_at = ?4 WHERE project_id = ?1 AND collection = ?2 AND id = ?3 AND deleted_at IS NULL",
+            None => "DELETE FROM documents WHERE project_id = ?1 AND collection = ?2 AND id = ?3",
+          })?;
+          for id in ids {
+            let id_str = id.to_string();
+            let changed = match &deleted_at {
+              Some(at) => stmt.execute(params![project_id_str, col, id_str, at])?,
+              None => stmt.execute(params![project_id_str, col, id_str])?,
+            };
+            if changed > 0 {
+              deleted.push(id);
+            }
+          }
+        }
+        tx.commit()?;
+        Ok(deleted)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn drop_collection(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<u64, anyhow::Error> {
     validate_collection_name(collection)?;
 
-    // Validate order field if present
-    if let Some(o) = order {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        let deleted = match soft_delete_enabled(&tx, &project_id_str, &col)? {
+          true => tx.execute(
+            "UPDATE documents SET deleted_at = ?3 WHERE project_id = ?1 AND collection = ?2 AND deleted_at IS NULL",
+            params![project_id_str, col, storage_timestamp()],
+          )?,
+          false => tx.execute(
+            "DELETE FROM documents WHERE project_id = ?1 AND collection = ?2",
+            params![project_id_str, col],
+          )?,
+        };
+        tx.commit()?;
+        Ok(deleted as u64)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn claim_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+    ttl: Duration,
+  ) -> Result<IdempotencyClaim, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    let key = key.to_string();
+    let cutoff = expiry_cutoff(ttl);
+    let existing = self
+      .conn
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        tx.execute(
+          "DELETE FROM idempotency_keys WHERE project_id = ?1 AND collection = ?2 AND key = ?3 AND created_at < ?4",
+          params![project_id_str, col, key, cutoff],
+        )?;
+        let claimed = tx.execute(
+          "INSERT OR IGNORE INTO idempotency_keys (project_id, collection, key, created_at) VALUES (?1, ?2, ?3, ?4)",
+          params![project_id_str, col, key, storage_timestamp()],
+        )?;
+        let existing = if claimed == 1 {
+          None
+        } else {
+          Some(tx.query_row(
+            "SELECT response FROM idempotency_keys WHERE project_id = ?1 AND collection = ?2 AND key = ?3",
+            params![project_id_str, col, key],
+            |row| row.get::<_, Option<String>>(0),
+          )?)
+        };
+        tx.commit()?;
+        Ok(existing)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(match existing {
+      None => IdempotencyClaim::Claimed,
+      Some(Some(response)) => IdempotencyClaim::Completed(serde_json::from_str(&response)?),
+      Some(None) => IdempotencyClaim::InProgress,
+    })
+  }
+
+  async fn complete_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+    response: &serde_json::Value,
+  ) -> Result<(), anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    let key = key.to_string();
+    let response = serde_json::to_string(response)?;
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "UPDATE idempotency_keys SET response = ?4 WHERE project_id = ?1 AND collection = ?2 AND key = ?3",
+          params![project_id_str, col, key, response],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn release_idempotency_key(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    key: &str,
+  ) -> Result<(), anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    let key = key.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "DELETE FROM idempotency_keys WHERE project_id = ?1 AND collection = ?2 AND key = ?3",
+          params![project_id_str, col, key],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn purge_idempotency_keys(&self, ttl: Duration) -> Result<u64, anyhow::Error> {
+    let cutoff = expiry_cutoff(ttl);
+    self
+      .conn
+      .call(move |conn| {
+        let n = conn.execute(
+          "DELETE FROM idempotency_keys WHERE created_at < ?1",
+          params![cutoff],
+        )?;
+        Ok(n as u64)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn delete(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    id: Uuid,
+  ) -> Result<Option<Document>, anyhow::Error> {
+    let write = DocumentWrite::Delete { id };
+    let written = self
+      .write_document(project_id, collection, write, false)
+      .await?;
+    Ok(written.map(|(doc, _)| doc))
+  }
+
+  async fn list(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    order: Option<&OrderBySpec>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+  ) -> Result<Vec<Document>, anyhow::Error> {
+    self
+      .list_with_options(
+        project_id,
+        collection,
+        projection,
+        filter,
+        &[],
+        order.map(std::slice::from_ref).unwrap_or_default(),
+        limit,
+        offset,
+        ReadOptions::default(),
+      )
+      .await
+  }
+
+  async fn list_with_options(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+  ) -> Result<Vec<Document>, anyhow::Error> {
+    // Validate collection name
+    validate_collection_name(collection)?;
+    self
+      .encryption
+      .check_sql(SqlDialect::Sqlite, collection, filter, order)?;
+
+    // Validate projected fields and computed expressions
+    let projection = projection.map(parse_projection).transpose()?;
+    if let Some(fields) = &projection {
+      self.encryption.check_projection(collection, fields)?;
+    }
+
+    // Validate order fields if present
+    for o in order {
       validate_identifier(&o.field)?;
     }
 
@@ -383,52 +1570,190 @@ impl DatabaseBackend for SqliteBackend {
       }
     }
 
-    let col = collection.to_string();
-    let project_id_str = project_id.to_string();
+    let mut values = vec![
+      rusqlite::types::Value::Text(project_id.to_string()),
+      rusqlite::types::Value::Text(collection.to_string()),
+    ];
+    values.extend(params.iter().map(sqlite_param));
+    let data = match projection {
+      Some(fields) => SqlDialect::Sqlite.json_projection(&fields),
+      None => "data".to_string(),
+    };
     let mut sql = String::with_capacity(256);
-    sql.push_str(
-      "SELECT id, project_id, collection, data, created_at, updated_at FROM documents WHERE project_id = ?1 AND collection = ?2",
-    );
+    sql.push_str(&format!(
+      "SELECT id, project_id, collection, {}, created_at, updated_at, deleted_at FROM documents WHERE project_id = ?1 AND collection = ?2",
+      data
+    ));
+    if !options.with_deleted {
+      sql.push_str(" AND deleted_at IS NULL");
+    }
 
-    // Filter is pre-validated by query compiler
+    // Filter comes from the query compiler; check it only touches `data`
     if let Some(f) = filter {
-      sql.push_str(" AND ");
-      sql.push_str(f);
+      validate_filter_sql(f)?;
+      sql.push_str(&format!(" AND ({})", f));
     }
 
-    if let Some(o) = order {
-      let dir = if o.direction == OrderDirection::Desc {
-        "DESC"
-      } else {
-        "ASC"
-      };
-      sql.push_str(" ORDER BY json_extract(data, '$.");
-      sql.push_str(&o.field);
-      sql.push_str("') ");
-      sql.push_str(dir);
+    sql.push_str(&SqlDialect::Sqlite.order_by(order)?);
+
+    if let Some(l) = limit {
+      sql.push_str(&format!(" LIMIT {}", l));
+    }
+
+    if let Some(o) = offset {
+      sql.push_str(&format!(" OFFSET {}", o));
+    }
+
+    self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(values.iter()))?;
+        let mut docs = Vec::with_capacity(limit.unwrap_or(100));
+        while let Some(row) = rows.next()? {
+          docs.push(row_to_doc(row)?);
+        }
+        Ok(docs)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?
+      .into_iter()
+      .map(|doc| self.encryption.decrypt_document(doc))
+      .collect()
+  }
+
+  async fn list_stream(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    projection: Option<&[String]>,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+    batch_size: usize,
+  ) -> Result<DocumentBatches, anyhow::Error> {
+    use futures_util::StreamExt;
+
+    // The single connection can't stay busy while a client reads slowly, so
+    // the result is read in full and handed out in batches
+    let docs = self
+      .list_with_options(
+        project_id, collection, projection, filter, params, order, limit, offset, options,
+      )
+      .await?;
+    Ok(
+      futures_util::stream::iter(docs)
+        .chunks(batch_size.max(1))
+        .map(Ok)
+        .boxed(),
+    )
+  }
+
+  async fn estimate_list_cost(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    order: &[OrderBySpec],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    options: ReadOptions,
+  ) -> Result<f64, anyhow::Error> {
+    // SQLite has no cost model, so estimate the documents read instead. A
+    // filter or sort reads the whole collection unless the plan uses an
+    // index on a field; otherwise reading stops at the limit.
+    let total = self
+      .count_documents(project_id, collection, None, &[], false, options)
+      .await?
+      .total;
+
+    let mut values = vec![
+      rusqlite::types::Value::Text(project_id.to_string()),
+      rusqlite::types::Value::Text(collection.to_string()),
+    ];
+    values.extend(params.iter().map(sqlite_param));
+    let mut sql =
+      String::from("SELECT id FROM documents WHERE project_id = ?1 AND collection = ?2");
+    if !options.with_deleted {
+      sql.push_str(" AND deleted_at IS NULL");
+    }
+    if let Some(f) = filter {
+      validate_filter_sql(f)?;
+      sql.push_str(&format!(" AND ({})", f));
     }
+    sql.push_str(&SqlDialect::Sqlite.order_by(order)?);
+    let plan: Vec<String> = self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+        let details = stmt
+          .query_map(rusqlite::params_from_iter(values.iter()), |row| row.get(3))?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(details)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let field_index = plan
+      .iter()
+      .filter_map(|detail| plan_index(detail))
+      .any(|name| !SCHEMA_DOCUMENT_INDEXES.contains(&name));
+    let reads_all = (filter.is_some() || !order.is_empty()) && !field_index;
+    let rows = match limit {
+      Some(limit) if !reads_all => total.min((limit + offset.unwrap_or(0)) as u64),
+      _ => total,
+    };
+    Ok(rows as f64)
+  }
 
-    if let Some(l) = limit {
-      sql.push_str(&format!(" LIMIT {}", l));
+  async fn count_documents(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    filter: Option<&str>,
+    params: &[serde_json::Value],
+    _approximate: bool,
+    options: ReadOptions,
+  ) -> Result<DocumentCount, anyhow::Error> {
+    validate_collection_name(collection)?;
+    self
+      .encryption
+      .check_sql(SqlDialect::Sqlite, collection, filter, &[])?;
+
+    let mut values = vec![
+      rusqlite::types::Value::Text(project_id.to_string()),
+      rusqlite::types::Value::Text(collection.to_string()),
+    ];
+    values.extend(params.iter().map(sqlite_param));
+    let mut sql =
+      String::from("SELECT COUNT(*) FROM documents WHERE project_id = ?1 AND collection = ?2");
+    if !options.with_deleted {
+      sql.push_str(" AND deleted_at IS NULL");
     }
-
-    if let Some(o) = offset {
-      sql.push_str(&format!(" OFFSET {}", o));
+    if let Some(f) = filter {
+      validate_filter_sql(f)?;
+      sql.push_str(&format!(" AND ({})", f));
     }
 
-    self
+    // SQLite keeps no row estimates, so the count is always exact
+    let total = self
       .conn
       .call(move |conn| {
-        let mut stmt = conn.prepare(&sql)?;
-        let mut rows = stmt.query(params![project_id_str, col])?;
-        let mut docs = Vec::with_capacity(limit.unwrap_or(100));
-        while let Some(row) = rows.next()? {
-          docs.push(row_to_doc(row)?);
-        }
-        Ok(docs)
+        let n: i64 = conn.query_row(&sql, rusqlite::params_from_iter(values.iter()), |row| {
+          row.get(0)
+        })?;
+        Ok(n as u64)
       })
       .await
-      .map_err(|e| anyhow::anyhow!("{}", e))
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(DocumentCount {
+      total,
+      approximate: false,
+    })
   }
 
   async fn list_collections(&self, project_id: Uuid) -> Result<Vec<String>, anyhow::Error> {
@@ -450,19 +1775,189 @@ impl DatabaseBackend for SqliteBackend {
       .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
+  async fn collection_counts(&self, project_id: Uuid) -> Result<Vec<(String, u64)>, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare_cached(
+          "SELECT collection, COUNT(*) - COUNT(deleted_at) FROM documents WHERE project_id = ?1 GROUP BY collection ORDER BY collection",
+        )?;
+        let mut rows = stmt.query(params![project_id_str])?;
+        let mut counts = Vec::new();
+        while let Some(row) = rows.next()? {
+          counts.push((row.get(0)?, row.get::<_, i64>(1)? as u64));
+        }
+        Ok(counts)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn collection_stats(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+  ) -> Result<CollectionStats, anyhow::Error> {
+    validate_collection_name(collection)?;
+
+    let project_id_str = project_id.to_string();
+    let col = collection.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        let (count, bytes): (i64, i64) = conn.query_row(
+          "SELECT COUNT(*), COALESCE(SUM(length(CAST(data AS BLOB))), 0) FROM documents WHERE project_id = ?1 AND collection = ?2 AND deleted_at IS NULL",
+          params![project_id_str, col],
+          |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut stmt = conn.prepare_cached(
+          "SELECT j.key, COUNT(*) FROM (SELECT data FROM documents WHERE project_id = ?1 AND collection = ?2 AND deleted_at IS NULL ORDER BY random() LIMIT ?3) s, json_each(s.data) j WHERE json_type(s.data) = 'object' GROUP BY j.key ORDER BY COUNT(*) DESC, j.key LIMIT ?4",
+        )?;
+        let fields = stmt
+          .query_map(
+            params![
+              project_id_str,
+              col,
+              STATS_SAMPLE_SIZE as i64,
+              STATS_TOP_FIELDS as i64
+            ],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64)),
+          )?
+          .collect::<Result<Vec<_>, _>>()?;
+
+        let sampled = count.min(STATS_SAMPLE_SIZE as i64);
+        Ok(CollectionStats::new(
+          count as u64,
+          bytes as u64,
+          sampled as u64,
+          fields,
+        ))
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn rename_collection(
+    &self,
+    project_id: Uuid,
+    from: &str,
+    to: &str,
+  ) -> Result<u64, anyhow::Error> {
+    let sql = "UPDATE documents SET collection = ?3 WHERE project_id = ?1 AND collection = ?2";
+    self
+      .fill_collection(project_id, from, to, sql.to_string(), 0)
+      .await
+  }
+
+  async fn copy_collection(
+    &self,
+    project_id: Uuid,
+    from: &str,
+    to: &str,
+    max_documents: u64,
+  ) -> Result<u64, anyhow::Error> {
+    // The timestamp is generated here, so inlining it is safe
+    let now = Utc::now().to_rfc3339();
+    let sql = format!(
+      "INSERT INTO documents (id, project_id, collection, data, created_at, updated_at) SELECT {}, project_id, ?3, data, '{}', '{}' FROM documents WHERE project_id = ?1 AND collection = ?2 AND deleted_at IS NULL",
+      SQL_UUID_V4, now, now
+    );
+    self
+      .fill_collection(project_id, from, to, sql, max_documents)
+      .await
+  }
+
+  async fn list_changes(&self, after_id: i64, limit: usize) -> Result<Vec<Change>, anyhow::Error> {
+    let limit = limit as i64;
+    self.conn.call(move |conn| {
+      let mut stmt = conn.prepare_cached(
+        "SELECT id, project_id, collection, document_id, operation, old_data, new_data, changed_at FROM change_queue WHERE id > ?1 ORDER BY id LIMIT ?2"
+      )?;
+      let mut rows = stmt.query(params![after_id, limit])?;
+      let mut changes = Vec::new();
+      while let Some(row) = rows.next()? {
+        changes.extend(row_to_change(row)?);
+      }
+      Ok(changes)
+    }).await.map_err(|e| anyhow::anyhow!("{}", e))?
+    .into_iter()
+    .map(|change| self.encryption.decrypt_change(change))
+    .collect()
+  }
+
+  async fn document_history(
+    &self,
+    project_id: Uuid,
+    collection: &str,
+    document_id: Uuid,
+    after_id: i64,
+    limit: usize,
+  ) -> Result<Vec<Change>, anyhow::Error> {
+    let collection = collection.to_string();
+    let limit = limit as i64;
+    self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare_cached(
+          "SELECT id, project_id, collection, document_id, operation, old_data, new_data, changed_at FROM change_queue
+           WHERE document_id = ?3 AND project_id = ?1 AND collection = ?2 AND id > ?4
+           ORDER BY id LIMIT ?5",
+        )?;
+        let mut rows = stmt.query(params![
+          project_id.to_string(),
+          collection,
+          document_id.to_string(),
+          after_id,
+          limit
+        ])?;
+        let mut changes = Vec::new();
+        while let Some(row) = rows.next()? {
+          changes.extend(row_to_change(row)?);
+        }
+        Ok(changes)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?
+      .into_iter()
+      .map(|change| self.encryption.decrypt_change(change))
+      .collect()
+  }
+
+  async fn change_id_range(&self) -> Result<Option<(i64, i64)>, anyhow::Error> {
+    self
+      .conn
+      .call(|conn| {
+        let (min, max): (Option<i64>, Option<i64>) =
+          conn.query_row("SELECT MIN(id), MAX(id) FROM change_queue", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+          })?;
+        Ok(min.zip(max))
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
   fn subscribe_changes(&self) -> broadcast::Receiver<Change> {
     self.change_tx.subscribe()
   }
 
+  fn change_listener_heartbeat(&self) -> Arc<ListenerHeartbeat> {
+    self.heartbeat.clone()
+  }
+
   async fn start_change_listener(&self) -> Result<(), anyhow::Error> {
     let tx = self.change_tx.clone();
     let conn = self.conn.clone();
+    let heartbeat = self.heartbeat.clone();
+    let encryption = self.encryption.clone();
     tracing::info!("SQLite change listener started");
 
     tokio::spawn(async move {
       let mut last_id: i64 = 0;
       loop {
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        tokio::time::sleep(CHANGE_POLL_INTERVAL).await;
         let lid = last_id;
         let changes: Result<Vec<Change>, _> = conn.call(move |conn| {
           let mut stmt = conn.prepare_cached(
@@ -471,23 +1966,7 @@ impl DatabaseBackend for SqliteBackend {
           let mut rows = stmt.query(params![lid])?;
           let mut changes = Vec::with_capacity(100);
           while let Some(row) = rows.next()? {
-            let id: i64 = row.get(0)?;
-            let project_id_str: Option<String> = row.get(1)?;
-            let op_str: String = row.get(4)?;
-            let Ok(op) = op_str.parse::<ChangeOperation>() else { continue };
-            let old_data: Option<String> = row.get(5)?;
-            let new_data: Option<String> = row.get(6)?;
-            let changed_at_str: String = row.get(7)?;
-            changes.push(Change {
-              id,
-              project_id: project_id_str.and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_PROJECT_ID),
-              collection: row.get(2)?,
-              document_id: row.get::<_, String>(3)?.parse().unwrap_or_default(),
-              operation: op,
-              old_data: old_data.and_then(|s| serde_json::from_str(&s).ok()),
-              new_data: new_data.and_then(|s| serde_json::from_str(&s).ok()),
-              changed_at: chrono::DateTime::parse_from_rfc3339(&changed_at_str).map(|d| d.with_timezone(&Utc)).unwrap_or_else(|_| Utc::now()),
-            });
+            changes.extend(row_to_change(row)?);
           }
           Ok(changes)
         }).await;
@@ -495,23 +1974,25 @@ impl DatabaseBackend for SqliteBackend {
         if let Ok(changes) = changes {
           for change in changes {
             last_id = change.id;
-            let _ = tx.send(change);
+            encryption.broadcast_change(&tx, change);
           }
+          heartbeat.beat();
         }
       }
     });
 
     // Spawn cleanup task to prevent unbounded growth of change_queue
     let cleanup_conn = self.conn.clone();
+    let retention = self.retention;
     tokio::spawn(async move {
       loop {
-        // Run cleanup every 5 minutes
-        tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
-        // Keep only the last 10000 entries (or entries from the last hour)
-        let result: Result<usize, _> = cleanup_conn.call(|conn| {
+        tokio::time::sleep(retention.cleanup_interval).await;
+        // Keep the newest entries, and anything younger than max_age
+        let max_age = format!("-{} seconds", retention.max_age.as_secs());
+        let result: Result<usize, _> = cleanup_conn.call(move |conn| {
           conn.execute(
-            "DELETE FROM change_queue WHERE id < (SELECT MAX(id) - 10000 FROM change_queue) AND changed_at < datetime('now', '-1 hour')",
-            []
+            "DELETE FROM change_queue WHERE id < (SELECT MAX(id) - ?1 FROM change_queue) AND changed_at < datetime('now', ?2)",
+            params![retention.max_entries, max_age]
           ).map_err(|e| e.into())
         }).await;
         if let Ok(count) = result {
@@ -604,6 +2085,70 @@ impl DatabaseBackend for SqliteBackend {
       .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
+  async fn list_token_hashes(
+    &self,
+    project_id: Uuid,
+  ) -> Result<Vec<(ApiTokenInfo, String)>, anyhow::Error> {
+    let project_id_str = project_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare_cached(
+          "SELECT id, project_id, name, created_at, token_hash FROM api_tokens WHERE project_id = ?1 ORDER BY created_at",
+        )?;
+        let mut rows = stmt.query(params![project_id_str])?;
+        let mut tokens = Vec::new();
+        while let Some(row) = rows.next()? {
+          let id_str: String = row.get(0)?;
+          let proj_id_str: String = row.get(1)?;
+          let created_str: String = row.get(3)?;
+          let info = ApiTokenInfo {
+            id: id_str.parse().unwrap_or_default(),
+            project_id: proj_id_str.parse().unwrap_or_default(),
+            name: row.get(2)?,
+            created_at: chrono::DateTime::parse_from_rfc3339(&created_str)
+              .map(|d| d.with_timezone(&Utc))
+              .unwrap_or_else(|_| Utc::now()),
+          };
+          tokens.push((info, row.get(4)?));
+        }
+        Ok(tokens)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn get_token_by_hash(
+    &self,
+    token_hash: &str,
+  ) -> Result<Option<ApiTokenInfo>, anyhow::Error> {
+    let hash_owned = token_hash.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            "SELECT id, project_id, name, created_at FROM api_tokens WHERE token_hash = ?1",
+            params![hash_owned],
+            |row| {
+              let created_str: String = row.get(3)?;
+              Ok(ApiTokenInfo {
+                id: row.get::<_, String>(0)?.parse().unwrap_or_default(),
+                project_id: row.get::<_, String>(1)?.parse().unwrap_or_default(),
+                name: row.get(2)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_str)
+                  .map(|d| d.with_timezone(&Utc))
+                  .unwrap_or_else(|_| Utc::now()),
+              })
+            },
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
   async fn validate_token(&self, token_hash: &str) -> Result<Option<Uuid>, anyhow::Error> {
     let hash_owned = token_hash.to_string();
     self
@@ -673,205 +2218,792 @@ impl DatabaseBackend for SqliteBackend {
   }
 
   // =========================================================================
-  // S3 Storage Methods - SQLite stubs (not implemented)
+  // Raw SQL
+  // =========================================================================
+
+  async fn execute_raw_sql(
+    &self,
+    sql: &str,
+    params: &[serde_json::Value],
+    max_rows: usize,
+  ) -> Result<RawSqlResult, anyhow::Error> {
+    let sql = sql.to_string();
+    let values: Vec<rusqlite::types::Value> = params.iter().map(sqlite_param).collect();
+    self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare(&sql)?;
+        let params = rusqlite::params_from_iter(values.iter());
+        let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+        if columns.is_empty() {
+          let rows_affected = stmt.execute(params)?;
+          return Ok(RawSqlResult {
+            rows_affected: rows_affected as u64,
+            ..Default::default()
+          });
+        }
+
+        let mut result = RawSqlResult {
+          columns,
+          ..Default::default()
+        };
+        let mut rows = stmt.query(params)?;
+        while let Some(row) = rows.next()? {
+          if max_rows > 0 && result.rows.len() == max_rows {
+            result.truncated = true;
+            break;
+          }
+          let values = (0..result.columns.len())
+            .map(|i| row.get_ref(i).map(sqlite_value))
+            .collect::<Result<Vec<_>, _>>()?;
+          result.rows.push(values);
+        }
+        Ok(result)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn execute_raw_sql_batch(&self, sql: &str) -> Result<RawSqlResult, anyhow::Error> {
+    let sql = sql.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        // Count per statement; total_changes() would include trigger writes
+        let mut rows_affected = 0;
+        let mut batch = rusqlite::Batch::new(conn, &sql);
+        while let Some(mut stmt) = batch.next()? {
+          if stmt.column_count() > 0 {
+            let mut rows = stmt.raw_query();
+            while rows.next()?.is_some() {}
+          } else {
+            rows_affected += stmt.raw_execute()? as u64;
+          }
+        }
+        Ok(RawSqlResult {
+          rows_affected,
+          ..Default::default()
+        })
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn list_active_queries(&self) -> Result<Vec<ActiveQuery>, anyhow::Error> {
+    Ok(vec![])
+  }
+
+  async fn cancel_query(&self, _pid: i32) -> Result<bool, anyhow::Error> {
+    Ok(false)
+  }
+
+  async fn reindex_documents(&self) -> Result<Vec<String>, anyhow::Error> {
+    // SQLite has a single writer, so a rebuild holds up writes either way
+    self
+      .conn
+      .call(|conn| {
+        let indexes = conn
+          .prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'documents' ORDER BY name",
+          )?
+          .query_map([], |row| row.get::<_, String>(0))?
+          .collect::<Result<Vec<_>, _>>()?;
+        conn.execute_batch("REINDEX documents; ANALYZE documents;")?;
+        Ok(indexes)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  // =========================================================================
+  // S3 Storage Methods
   // =========================================================================
 
   async fn get_storage_access_key(
     &self,
-    _access_key_id: &str,
+    access_key_id: &str,
   ) -> Result<Option<(String, Option<Uuid>)>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let access_key_id = access_key_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            "SELECT secret_access_key, owner_id FROM storage_access_keys WHERE access_key_id = ?1",
+            params![access_key_id],
+            |row| Ok((row.get(0)?, parse_optional_uuid(row.get(1)?))),
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn create_storage_access_key(
     &self,
-    _access_key_id: &str,
-    _secret_key: &str,
-    _owner_id: Option<Uuid>,
-    _name: &str,
+    access_key_id: &str,
+    secret_key: &str,
+    owner_id: Option<Uuid>,
+    name: &str,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let access_key_id = access_key_id.to_string();
+    let secret_key = secret_key.to_string();
+    let owner_id = owner_id.map(|id| id.to_string());
+    let name = name.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "INSERT INTO storage_access_keys (access_key_id, secret_access_key, owner_id, name, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+          params![access_key_id, secret_key, owner_id, name, storage_timestamp()],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
-  async fn delete_storage_access_key(&self, _access_key_id: &str) -> Result<bool, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+  async fn delete_storage_access_key(&self, access_key_id: &str) -> Result<bool, anyhow::Error> {
+    let access_key_id = access_key_id.to_string();
+    let deleted = self
+      .conn
+      .call(move |conn| {
+        conn
+          .execute(
+            "DELETE FROM storage_access_keys WHERE access_key_id = ?1",
+            params![access_key_id],
+          )
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(deleted > 0)
   }
 
   async fn list_storage_access_keys(&self) -> Result<Vec<StorageAccessKeyInfo>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    self
+      .conn
+      .call(|conn| {
+        let mut stmt = conn.prepare_cached(
+          "SELECT access_key_id, owner_id, name, created_at FROM storage_access_keys ORDER BY created_at DESC",
+        )?;
+        let keys = stmt
+          .query_map([], |row| {
+            Ok(StorageAccessKeyInfo {
+              access_key_id: row.get(0)?,
+              owner_id: parse_optional_uuid(row.get(1)?),
+              name: row.get(2)?,
+              created_at: parse_timestamp(&row.get::<_, String>(3)?),
+            })
+          })?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(keys)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
-  async fn get_storage_bucket(&self, _name: &str) -> Result<Option<StorageBucket>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+  async fn get_storage_bucket(&self, name: &str) -> Result<Option<StorageBucket>, anyhow::Error> {
+    let name = name.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            &format!(
+              "SELECT {} FROM storage_buckets WHERE name = ?1",
+              BUCKET_COLUMNS
+            ),
+            params![name],
+            row_to_bucket,
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn create_storage_bucket(
     &self,
-    _name: &str,
-    _owner_id: Option<Uuid>,
+    name: &str,
+    owner_id: Option<Uuid>,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let name = name.to_string();
+    let owner_id = owner_id.map(|id| id.to_string());
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "INSERT INTO storage_buckets (name, owner_id, created_at) VALUES (?1, ?2, ?3)",
+          params![name, owner_id, storage_timestamp()],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
-  async fn delete_storage_bucket(&self, _name: &str) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+  async fn delete_storage_bucket(&self, name: &str) -> Result<(), anyhow::Error> {
+    let name = name.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        // Objects and multipart uploads go with the bucket (ON DELETE CASCADE)
+        conn.execute("DELETE FROM storage_buckets WHERE name = ?1", params![name])?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn list_storage_buckets(&self) -> Result<Vec<StorageBucket>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    self
+      .conn
+      .call(|conn| {
+        let mut stmt = conn.prepare_cached(&format!(
+          "SELECT {} FROM storage_buckets ORDER BY name",
+          BUCKET_COLUMNS
+        ))?;
+        let buckets = stmt
+          .query_map([], row_to_bucket)?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(buckets)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn update_storage_bucket_stats(
     &self,
-    _bucket: &str,
-    _size_delta: i64,
-    _count_delta: i64,
+    bucket: &str,
+    size_delta: i64,
+    count_delta: i64,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        update_bucket_stats(conn, &bucket, size_delta, count_delta)?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn get_storage_object(
     &self,
-    _bucket: &str,
-    _key: &str,
-    _version_id: Option<Uuid>,
+    bucket: &str,
+    key: &str,
+    version_id: Option<Uuid>,
   ) -> Result<Option<StorageObject>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    let version_id = version_id.map(|id| id.to_string());
+    self
+      .conn
+      .call(move |conn| {
+        let object = match version_id {
+          Some(vid) => conn.query_row(
+            &format!(
+              "SELECT {} FROM storage_objects WHERE bucket = ?1 AND key = ?2 AND version_id = ?3",
+              OBJECT_COLUMNS
+            ),
+            params![bucket, key, vid],
+            row_to_object,
+          ),
+          None => conn.query_row(
+            &format!(
+              "SELECT {} FROM storage_objects WHERE bucket = ?1 AND key = ?2 AND is_latest = 1",
+              OBJECT_COLUMNS
+            ),
+            params![bucket, key],
+            row_to_object,
+          ),
+        };
+        object.optional().map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn create_storage_object(
     &self,
-    _bucket: &str,
-    _key: &str,
-    _version_id: Uuid,
-    _etag: &str,
-    _size: i64,
-    _content_type: &str,
-    _storage_path: &str,
-    _metadata: serde_json::Value,
+    bucket: &str,
+    key: &str,
+    version_id: Uuid,
+    etag: &str,
+    size: i64,
+    content_type: &str,
+    storage_path: &str,
+    metadata: serde_json::Value,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let object = NewObject::new(
+      bucket,
+      key,
+      version_id,
+      etag,
+      size,
+      content_type,
+      storage_path,
+      &metadata,
+    );
+    self
+      .conn
+      .call(move |conn| {
+        object.insert(conn)?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn delete_storage_object(
     &self,
-    _bucket: &str,
-    _key: &str,
-    _version_id: Option<Uuid>,
+    bucket: &str,
+    key: &str,
+    version_id: Option<Uuid>,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    let version_id = version_id.map(|id| id.to_string());
+    self
+      .conn
+      .call(move |conn| {
+        match version_id {
+          Some(vid) => conn.execute(
+            "DELETE FROM storage_objects WHERE bucket = ?1 AND key = ?2 AND version_id = ?3",
+            params![bucket, key, vid],
+          )?,
+          None => conn.execute(
+            "DELETE FROM storage_objects WHERE bucket = ?1 AND key = ?2",
+            params![bucket, key],
+          )?,
+        };
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn create_storage_delete_marker(
     &self,
-    _bucket: &str,
-    _key: &str,
-    _version_id: Uuid,
+    bucket: &str,
+    key: &str,
+    version_id: Uuid,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    let version_id = version_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        // Same as sqrl_create_storage_delete_marker: unset latest and insert
+        // the marker in one transaction
+        let tx = conn.transaction()?;
+        tx.execute(
+          "UPDATE storage_objects SET is_latest = 0 WHERE bucket = ?1 AND key = ?2",
+          params![bucket, key],
+        )?;
+        tx.execute(
+          "INSERT INTO storage_objects (bucket, key, version_id, etag, size, storage_path, is_delete_marker, is_latest, created_at) VALUES (?1, ?2, ?3, '', 0, '', 1, 1, ?4)",
+          params![bucket, key, version_id, storage_timestamp()],
+        )?;
+        tx.commit()?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn unset_storage_object_latest(
     &self,
-    _bucket: &str,
-    _key: &str,
+    bucket: &str,
+    key: &str,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "UPDATE storage_objects SET is_latest = 0 WHERE bucket = ?1 AND key = ?2",
+          params![bucket, key],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn update_storage_object_acl(
     &self,
-    _bucket: &str,
-    _key: &str,
-    _acl: ObjectAcl,
+    bucket: &str,
+    key: &str,
+    acl: ObjectAcl,
+  ) -> Result<(), anyhow::Error> {
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    let acl = serde_json::to_string(&acl)?;
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "UPDATE storage_objects SET acl = ?3 WHERE bucket = ?1 AND key = ?2 AND is_latest = 1",
+          params![bucket, key, acl],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn get_storage_object_tags(
+    &self,
+    bucket: &str,
+    key: &str,
+  ) -> Result<ObjectTags, anyhow::Error> {
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    let tags: Option<String> = self
+      .conn
+      .call(move |conn| {
+        Ok(
+          conn
+            .prepare_cached(
+              "SELECT tags FROM storage_objects WHERE bucket = ?1 AND key = ?2 AND is_latest = 1",
+            )?
+            .query_row(params![bucket, key], |row| row.get(0))
+            .optional()?,
+        )
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(
+      tags
+        .map(|t| serde_json::from_str(&t))
+        .transpose()?
+        .unwrap_or_default(),
+    )
+  }
+
+  async fn set_storage_object_tags(
+    &self,
+    bucket: &str,
+    key: &str,
+    tags: &ObjectTags,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    let tags = serde_json::to_string(tags)?;
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "UPDATE storage_objects SET tags = ?3 WHERE bucket = ?1 AND key = ?2 AND is_latest = 1",
+          params![bucket, key, tags],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
+  }
+
+  async fn list_storage_objects_with_tags(
+    &self,
+    bucket: &str,
+    prefix: Option<&str>,
+    tags: &ObjectTags,
+  ) -> Result<Vec<StorageObject>, anyhow::Error> {
+    let bucket = bucket.to_string();
+    let prefix = prefix.unwrap_or("").to_string();
+    let tags = serde_json::to_string(tags)?;
+    self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare_cached(&format!(
+          "SELECT {} FROM storage_objects AS o
+           WHERE bucket = ?1 AND substr(key, 1, length(?2)) = ?2
+             AND is_latest = 1 AND is_delete_marker = 0
+             AND NOT EXISTS (
+               SELECT 1 FROM json_each(?3) AS want
+               WHERE NOT EXISTS (
+                 SELECT 1 FROM json_each(o.tags) AS have
+                 WHERE have.key = want.key AND have.value = want.value
+               )
+             )
+           ORDER BY key",
+          OBJECT_COLUMNS
+        ))?;
+        let objects = stmt
+          .query_map(params![bucket, prefix, tags], row_to_object)?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(objects)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn list_storage_objects(
     &self,
-    _bucket: &str,
-    _prefix: Option<&str>,
+    bucket: &str,
+    prefix: Option<&str>,
     _delimiter: Option<&str>,
-    _max_keys: i32,
-    _continuation_token: Option<&str>,
+    max_keys: i32,
+    continuation_token: Option<&str>,
   ) -> Result<(Vec<StorageObject>, bool, Option<String>), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let prefix = prefix.unwrap_or("").to_string();
+    let start_key = continuation_token.unwrap_or("").to_string();
+    let mut objects = self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare_cached(&format!(
+          "SELECT {} FROM storage_objects
+           WHERE bucket = ?1 AND substr(key, 1, length(?2)) = ?2 AND key > ?3 AND is_latest = 1 AND is_delete_marker = 0
+           ORDER BY key
+           LIMIT ?4",
+          OBJECT_COLUMNS
+        ))?;
+        let objects = stmt
+          .query_map(
+            params![bucket, prefix, start_key, max_keys + 1],
+            row_to_object,
+          )?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(objects)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let is_truncated = objects.len() > max_keys as usize;
+    objects.truncate(max_keys as usize);
+    let next_token = if is_truncated {
+      objects.last().map(|o| o.key.clone())
+    } else {
+      None
+    };
+
+    Ok((objects, is_truncated, next_token))
   }
 
   async fn list_storage_common_prefixes(
     &self,
-    _bucket: &str,
-    _prefix: Option<&str>,
-    _delimiter: Option<&str>,
+    bucket: &str,
+    prefix: Option<&str>,
+    delimiter: Option<&str>,
   ) -> Result<Vec<String>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let Some(delimiter) = delimiter else {
+      return Ok(vec![]);
+    };
+    let bucket = bucket.to_string();
+    let prefix = prefix.unwrap_or("").to_string();
+    let delimiter = delimiter.to_string();
+    self
+      .conn
+      .call(move |conn| common_prefixes(conn, &bucket, &prefix, &delimiter).map_err(|e| e.into()))
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn list_storage_object_versions(
     &self,
-    _bucket: &str,
-    _prefix: Option<&str>,
-    _max_keys: i32,
+    bucket: &str,
+    prefix: Option<&str>,
+    max_keys: i32,
   ) -> Result<(Vec<StorageObject>, bool, Option<String>), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let prefix = prefix.unwrap_or("").to_string();
+    let mut objects = self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare_cached(&format!(
+          "SELECT {} FROM storage_objects
+           WHERE bucket = ?1 AND substr(key, 1, length(?2)) = ?2
+           ORDER BY key, created_at DESC
+           LIMIT ?3",
+          OBJECT_COLUMNS
+        ))?;
+        let objects = stmt
+          .query_map(params![bucket, prefix, max_keys + 1], row_to_object)?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(objects)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let is_truncated = objects.len() > max_keys as usize;
+    objects.truncate(max_keys as usize);
+    Ok((objects, is_truncated, None))
   }
 
   async fn get_multipart_upload(
     &self,
-    _upload_id: Uuid,
+    upload_id: Uuid,
   ) -> Result<Option<MultipartUpload>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let upload_id = upload_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            &format!(
+              "SELECT {} FROM storage_multipart_uploads WHERE upload_id = ?1",
+              UPLOAD_COLUMNS
+            ),
+            params![upload_id],
+            row_to_upload,
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn create_multipart_upload(
     &self,
-    _upload_id: Uuid,
-    _bucket: &str,
-    _key: &str,
-    _content_type: Option<&str>,
-    _metadata: serde_json::Value,
+    upload_id: Uuid,
+    bucket: &str,
+    key: &str,
+    content_type: Option<&str>,
+    metadata: serde_json::Value,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let upload_id = upload_id.to_string();
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    let content_type = content_type.map(str::to_string);
+    let metadata = metadata.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "INSERT INTO storage_multipart_uploads (upload_id, bucket, key, content_type, metadata, initiated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+          params![upload_id, bucket, key, content_type, metadata, storage_timestamp()],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
-  async fn delete_multipart_upload(&self, _upload_id: Uuid) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+  async fn delete_multipart_upload(&self, upload_id: Uuid) -> Result<(), anyhow::Error> {
+    let upload_id = upload_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "DELETE FROM storage_multipart_uploads WHERE upload_id = ?1",
+          params![upload_id],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn list_multipart_uploads(
     &self,
-    _bucket: &str,
-    _max_uploads: i32,
+    bucket: &str,
+    max_uploads: i32,
   ) -> Result<(Vec<MultipartUpload>, bool), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let mut uploads = self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare_cached(&format!(
+          "SELECT {} FROM storage_multipart_uploads WHERE bucket = ?1 ORDER BY initiated_at LIMIT ?2",
+          UPLOAD_COLUMNS
+        ))?;
+        let uploads = stmt
+          .query_map(params![bucket, max_uploads + 1], row_to_upload)?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(uploads)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let is_truncated = uploads.len() > max_uploads as usize;
+    uploads.truncate(max_uploads as usize);
+    Ok((uploads, is_truncated))
   }
 
   async fn get_multipart_part(
     &self,
-    _upload_id: Uuid,
-    _part_number: i32,
+    upload_id: Uuid,
+    part_number: i32,
   ) -> Result<Option<MultipartPart>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let upload_id = upload_id.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn
+          .query_row(
+            &format!(
+              "SELECT {} FROM storage_multipart_parts WHERE upload_id = ?1 AND part_number = ?2",
+              PART_COLUMNS
+            ),
+            params![upload_id, part_number],
+            row_to_part,
+          )
+          .optional()
+          .map_err(|e| e.into())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn upsert_multipart_part(
     &self,
-    _upload_id: Uuid,
-    _part_number: i32,
-    _etag: &str,
-    _size: i64,
-    _storage_path: &str,
+    upload_id: Uuid,
+    part_number: i32,
+    etag: &str,
+    size: i64,
+    storage_path: &str,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let upload_id = upload_id.to_string();
+    let etag = etag.to_string();
+    let storage_path = storage_path.to_string();
+    self
+      .conn
+      .call(move |conn| {
+        conn.execute(
+          "INSERT INTO storage_multipart_parts (upload_id, part_number, etag, size, storage_path, created_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+           ON CONFLICT (upload_id, part_number) DO UPDATE SET etag = ?3, size = ?4, storage_path = ?5",
+          params![upload_id, part_number, etag, size, storage_path, storage_timestamp()],
+        )?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn list_multipart_parts(
     &self,
-    _upload_id: Uuid,
-    _max_parts: i32,
+    upload_id: Uuid,
+    max_parts: i32,
   ) -> Result<(Vec<MultipartPart>, bool), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let upload_id = upload_id.to_string();
+    let mut parts = self
+      .conn
+      .call(move |conn| {
+        let mut stmt = conn.prepare_cached(&format!(
+          "SELECT {} FROM storage_multipart_parts WHERE upload_id = ?1 ORDER BY part_number LIMIT ?2",
+          PART_COLUMNS
+        ))?;
+        let parts = stmt
+          .query_map(params![upload_id, max_parts + 1], row_to_part)?
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(parts)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let is_truncated = parts.len() > max_parts as usize;
+    parts.truncate(max_parts as usize);
+    Ok((parts, is_truncated))
   }
 
   // =========================================================================
@@ -926,10 +3058,39 @@ impl DatabaseBackend for SqliteBackend {
     Ok(None)
   }
 
+  async fn get_admin_user_by_oidc(
+    &self,
+    _issuer: &str,
+    _subject: &str,
+  ) -> Result<Option<AdminUser>, anyhow::Error> {
+    Ok(None)
+  }
+
+  async fn create_oidc_admin_user(
+    &self,
+    _username: &str,
+    _email: Option<&str>,
+    _password_hash: &str,
+    _role: AdminRole,
+    _issuer: &str,
+    _subject: &str,
+  ) -> Result<AdminUser, anyhow::Error> {
+    anyhow::bail!("Admin authentication requires PostgreSQL backend")
+  }
+
   async fn list_admin_users(&self) -> Result<Vec<AdminUser>, anyhow::Error> {
     Ok(vec![])
   }
 
+  async fn list_admin_users_paged(
+    &self,
+    _search: Option<&str>,
+    _limit: Option<usize>,
+    _offset: Option<usize>,
+  ) -> Result<(Vec<AdminUser>, u64), anyhow::Error> {
+    Ok((vec![], 0))
+  }
+
   async fn delete_admin_user(&self, _id: Uuid) -> Result<bool, anyhow::Error> {
     Ok(false)
   }
@@ -950,6 +3111,28 @@ impl DatabaseBackend for SqliteBackend {
     Ok(false)
   }
 
+  async fn set_admin_user_email_verified(&self, _id: Uuid) -> Result<bool, anyhow::Error> {
+    Ok(false)
+  }
+
+  async fn create_admin_auth_token(
+    &self,
+    _user_id: Uuid,
+    _purpose: AdminTokenPurpose,
+    _token_hash: &str,
+    _expires_at: chrono::DateTime<chrono::Utc>,
+  ) -> Result<(), anyhow::Error> {
+    anyhow::bail!("Admin authentication requires PostgreSQL backend")
+  }
+
+  async fn consume_admin_auth_token(
+    &self,
+    _purpose: AdminTokenPurpose,
+    _token_hash: &str,
+  ) -> Result<Option<Uuid>, anyhow::Error> {
+    Ok(None)
+  }
+
   // =========================================================================
   // Admin Sessions - Stubs for SQLite
   // =========================================================================
@@ -983,55 +3166,185 @@ impl DatabaseBackend for SqliteBackend {
   }
 
   // =========================================================================
-  // S3 Atomic Operations (stubs - S3 not supported on SQLite)
+  // S3 Atomic Operations (Rust equivalents of the Postgres sqrl_* functions,
+  // each run in a single transaction)
   // =========================================================================
 
   async fn create_storage_object_with_stats(
     &self,
-    _bucket: &str,
-    _key: &str,
-    _version_id: Uuid,
-    _etag: &str,
-    _size: i64,
-    _content_type: &str,
-    _storage_path: &str,
-    _metadata: serde_json::Value,
+    bucket: &str,
+    key: &str,
+    version_id: Uuid,
+    etag: &str,
+    size: i64,
+    content_type: &str,
+    storage_path: &str,
+    metadata: serde_json::Value,
   ) -> Result<(), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let object = NewObject::new(
+      bucket,
+      key,
+      version_id,
+      etag,
+      size,
+      content_type,
+      storage_path,
+      &metadata,
+    );
+    self
+      .conn
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        object.insert(&tx)?;
+        update_bucket_stats(&tx, &object.bucket, object.size, 1)?;
+        tx.commit()?;
+        Ok(())
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn delete_storage_object_with_stats(
     &self,
-    _bucket: &str,
-    _key: &str,
-    _version_id: Option<Uuid>,
+    bucket: &str,
+    key: &str,
+    version_id: Option<Uuid>,
   ) -> Result<Option<(String, i64)>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let key = key.to_string();
+    let version_id = version_id.map(|id| id.to_string());
+    self
+      .conn
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        let deleted: Option<(String, i64)> = match &version_id {
+          Some(vid) => tx
+            .query_row(
+              "DELETE FROM storage_objects WHERE bucket = ?1 AND key = ?2 AND version_id = ?3 RETURNING storage_path, size",
+              params![bucket, key, vid],
+              |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?,
+          None => tx
+            .query_row(
+              "DELETE FROM storage_objects WHERE bucket = ?1 AND key = ?2 AND is_latest = 1 RETURNING storage_path, size",
+              params![bucket, key],
+              |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?,
+        };
+        if let Some((_, size)) = &deleted {
+          update_bucket_stats(&tx, &bucket, -size, -1)?;
+        }
+        tx.commit()?;
+        Ok(deleted)
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn replace_storage_object(
     &self,
-    _bucket: &str,
-    _key: &str,
-    _version_id: Uuid,
-    _etag: &str,
-    _size: i64,
-    _content_type: &str,
-    _storage_path: &str,
-    _metadata: serde_json::Value,
+    bucket: &str,
+    key: &str,
+    version_id: Uuid,
+    etag: &str,
+    size: i64,
+    content_type: &str,
+    storage_path: &str,
+    metadata: serde_json::Value,
   ) -> Result<Option<String>, anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let object = NewObject::new(
+      bucket,
+      key,
+      version_id,
+      etag,
+      size,
+      content_type,
+      storage_path,
+      &metadata,
+    );
+    self
+      .conn
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        // Old object info for file cleanup
+        let old: Option<(String, i64)> = tx
+          .query_row(
+            "SELECT storage_path, size FROM storage_objects WHERE bucket = ?1 AND key = ?2 AND is_latest = 1",
+            params![object.bucket, object.key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+          )
+          .optional()?;
+        tx.execute(
+          "UPDATE storage_objects SET is_latest = 0 WHERE bucket = ?1 AND key = ?2",
+          params![object.bucket, object.key],
+        )?;
+        object.insert(&tx)?;
+        // A replacement only changes the size; a new key also adds to the count
+        match &old {
+          Some((_, old_size)) => {
+            update_bucket_stats(&tx, &object.bucket, object.size - old_size, 0)?
+          }
+          None => update_bucket_stats(&tx, &object.bucket, object.size, 1)?,
+        }
+        tx.commit()?;
+        Ok(old.map(|(path, _)| path))
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))
   }
 
   async fn list_storage_objects_with_prefixes(
     &self,
-    _bucket: &str,
-    _prefix: Option<&str>,
-    _delimiter: Option<&str>,
-    _max_keys: i32,
-    _continuation_token: Option<&str>,
+    bucket: &str,
+    prefix: Option<&str>,
+    delimiter: Option<&str>,
+    max_keys: i32,
+    continuation_token: Option<&str>,
   ) -> Result<(Vec<StorageObject>, Vec<String>, bool, Option<String>), anyhow::Error> {
-    anyhow::bail!("S3 storage is not supported with SQLite backend")
+    let bucket = bucket.to_string();
+    let prefix = prefix.unwrap_or("").to_string();
+    let delimiter = delimiter.map(str::to_string);
+    let start_key = continuation_token.unwrap_or("").to_string();
+    let (mut objects, prefixes) = self
+      .conn
+      .call(move |conn| {
+        // Objects directly under the prefix (not below a delimiter)
+        let mut stmt = conn.prepare_cached(&format!(
+          "SELECT {} FROM storage_objects
+           WHERE bucket = ?1 AND substr(key, 1, length(?2)) = ?2 AND key > ?3
+             AND is_latest = 1 AND is_delete_marker = 0
+             AND (?4 IS NULL OR instr(substr(key, length(?2) + 1), ?4) = 0)
+           ORDER BY key
+           LIMIT ?5",
+          OBJECT_COLUMNS
+        ))?;
+        let objects = stmt
+          .query_map(
+            params![bucket, prefix, start_key, delimiter, max_keys + 1],
+            row_to_object,
+          )?
+          .collect::<Result<Vec<_>, _>>()?;
+
+        let prefixes = match &delimiter {
+          Some(delimiter) => common_prefixes(conn, &bucket, &prefix, delimiter)?,
+          None => vec![],
+        };
+        Ok((objects, prefixes))
+      })
+      .await
+      .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let is_truncated = objects.len() > max_keys as usize;
+    objects.truncate(max_keys as usize);
+    let next_token = if is_truncated {
+      objects.last().map(|o| o.key.clone())
+    } else {
+      None
+    };
+
+    Ok((objects, prefixes, is_truncated, next_token))
   }
 }
 
@@ -1042,6 +3355,7 @@ fn row_to_doc(row: &rusqlite::Row) -> Result<Document, rusqlite::Error> {
   let data_str: String = row.get(3)?;
   let created_str: String = row.get(4)?;
   let updated_str: String = row.get(5)?;
+  let deleted_str: Option<String> = row.get(6)?;
   Ok(Document {
     id: id_str.parse().unwrap_or_default(),
     project_id: project_id_str.parse().unwrap_or(DEFAULT_PROJECT_ID),
@@ -1053,5 +3367,303 @@ fn row_to_doc(row: &rusqlite::Row) -> Result<Document, rusqlite::Error> {
     updated_at: chrono::DateTime::parse_from_rfc3339(&updated_str)
       .map(|d| d.with_timezone(&Utc))
       .unwrap_or_else(|_| Utc::now()),
+    deleted_at: deleted_str.as_deref().map(parse_timestamp),
+  })
+}
+
+/// A change_queue row, or None if its operation is unknown
+fn row_to_change(row: &rusqlite::Row) -> Result<Option<Change>, rusqlite::Error> {
+  let project_id_str: Option<String> = row.get(1)?;
+  let op_str: String = row.get(4)?;
+  let Ok(op) = op_str.parse::<ChangeOperation>() else {
+    return Ok(None);
+  };
+  let old_data: Option<String> = row.get(5)?;
+  let new_data: Option<String> = row.get(6)?;
+  let changed_at_str: String = row.get(7)?;
+  Ok(Some(Change {
+    id: row.get(0)?,
+    project_id: project_id_str
+      .and_then(|s| s.parse().ok())
+      .unwrap_or(DEFAULT_PROJECT_ID),
+    collection: row.get(2)?,
+    document_id: row.get::<_, String>(3)?.parse().unwrap_or_default(),
+    operation: op,
+    old_data: old_data.and_then(|s| serde_json::from_str(&s).ok()),
+    new_data: new_data.and_then(|s| serde_json::from_str(&s).ok()),
+    changed_at: parse_change_timestamp(&changed_at_str),
+  }))
+}
+
+/// The change triggers record `datetime('now')`: UTC, without an offset
+fn parse_change_timestamp(s: &str) -> chrono::DateTime<Utc> {
+  chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+    .map(|d| d.and_utc())
+    .unwrap_or_else(|_| parse_timestamp(s))
+}
+
+/// Whether deletes in a collection only set `deleted_at`
+fn soft_delete_enabled(
+  conn: &rusqlite::Connection,
+  project_id: &str,
+  collection: &str,
+) -> rusqlite::Result<bool> {
+  conn
+    .prepare_cached(
+      "SELECT EXISTS(SELECT 1 FROM soft_delete_collections WHERE project_id = ?1 AND collection = ?2)",
+    )?
+    .query_row(params![project_id, collection], |row| row.get(0))
+}
+
+/// Number of documents in a project
+fn count_documents(conn: &rusqlite::Connection, project_id: &str) -> rusqlite::Result<u64> {
+  conn
+    .prepare_cached("SELECT COUNT(*) FROM documents WHERE project_id = ?1")?
+    .query_row(params![project_id], |row| row.get::<_, i64>(0))
+    .map(|n| n as u64)
+}
+
+/// Newest change recorded for a document when `track_change` is set
+fn document_change_id(
+  conn: &rusqlite::Connection,
+  id: &str,
+  track_change: bool,
+) -> rusqlite::Result<Option<i64>> {
+  if !track_change {
+    return Ok(None);
+  }
+  conn
+    .prepare_cached("SELECT MAX(id) FROM change_queue WHERE document_id = ?1")?
+    .query_row(params![id], |row| row.get(0))
+}
+
+// =========================================================================
+// Storage helpers
+// =========================================================================
+
+const BUCKET_COLUMNS: &str = "name, owner_id, versioning_enabled, acl, lifecycle_rules, quota_bytes, current_size, object_count, created_at";
+
+const OBJECT_COLUMNS: &str = "bucket, key, version_id, is_latest, etag, size, content_type, storage_path, metadata, acl, is_delete_marker, created_at";
+
+const UPLOAD_COLUMNS: &str = "upload_id, bucket, key, content_type, metadata, initiated_at";
+
+const PART_COLUMNS: &str = "upload_id, part_number, etag, size, storage_path, created_at";
+
+/// Storage timestamps use a fixed precision so they sort as text
+fn storage_timestamp() -> String {
+  format_timestamp(Utc::now())
+}
+
+fn format_timestamp(t: chrono::DateTime<Utc>) -> String {
+  t.to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+}
+
+/// Timestamp before which a row written with `storage_timestamp` is older than `ttl`
+fn expiry_cutoff(ttl: Duration) -> String {
+  let cutoff = chrono::Duration::from_std(ttl)
+    .ok()
+    .and_then(|ttl| Utc::now().checked_sub_signed(ttl))
+    .unwrap_or(chrono::DateTime::<Utc>::MIN_UTC);
+  format_timestamp(cutoff)
+}
+
+fn parse_timestamp(s: &str) -> chrono::DateTime<Utc> {
+  chrono::DateTime::parse_from_rfc3339(s)
+    .map(|d| d.with_timezone(&Utc))
+    .unwrap_or_else(|_| Utc::now())
+}
+
+fn parse_optional_uuid(s: Option<String>) -> Option<Uuid> {
+  s.and_then(|s| s.parse().ok())
+}
+
+fn parse_json(s: &str) -> serde_json::Value {
+  serde_json::from_str(s).unwrap_or_default()
+}
+
+fn row_to_bucket(row: &rusqlite::Row) -> Result<StorageBucket, rusqlite::Error> {
+  Ok(StorageBucket {
+    name: row.get(0)?,
+    owner_id: parse_optional_uuid(row.get(1)?),
+    versioning_enabled: row.get(2)?,
+    acl: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+    lifecycle_rules: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+    quota_bytes: row.get(5)?,
+    current_size: row.get(6)?,
+    object_count: row.get(7)?,
+    created_at: parse_timestamp(&row.get::<_, String>(8)?),
+  })
+}
+
+fn row_to_object(row: &rusqlite::Row) -> Result<StorageObject, rusqlite::Error> {
+  Ok(StorageObject {
+    bucket: row.get(0)?,
+    key: row.get(1)?,
+    version_id: row.get::<_, String>(2)?.parse().unwrap_or_default(),
+    is_latest: row.get(3)?,
+    etag: row.get(4)?,
+    size: row.get(5)?,
+    content_type: row.get(6)?,
+    storage_path: row.get(7)?,
+    metadata: parse_json(&row.get::<_, String>(8)?),
+    acl: serde_json::from_str(&row.get::<_, String>(9)?).unwrap_or_default(),
+    is_delete_marker: row.get(10)?,
+    created_at: parse_timestamp(&row.get::<_, String>(11)?),
+  })
+}
+
+fn row_to_upload(row: &rusqlite::Row) -> Result<MultipartUpload, rusqlite::Error> {
+  Ok(MultipartUpload {
+    upload_id: row.get::<_, String>(0)?.parse().unwrap_or_default(),
+    bucket: row.get(1)?,
+    key: row.get(2)?,
+    content_type: row.get(3)?,
+    metadata: parse_json(&row.get::<_, String>(4)?),
+    initiated_at: parse_timestamp(&row.get::<_, String>(5)?),
   })
 }
+
+fn row_to_part(row: &rusqlite::Row) -> Result<MultipartPart, rusqlite::Error> {
+  Ok(MultipartPart {
+    upload_id: row.get::<_, String>(0)?.parse().unwrap_or_default(),
+    part_number: row.get(1)?,
+    etag: row.get(2)?,
+    size: row.get(3)?,
+    storage_path: row.get(4)?,
+    created_at: parse_timestamp(&row.get::<_, String>(5)?),
+  })
+}
+
+/// A storage object row to insert
+struct NewObject {
+  bucket: String,
+  key: String,
+  version_id: String,
+  etag: String,
+  size: i64,
+  content_type: String,
+  storage_path: String,
+  metadata: String,
+}
+
+impl NewObject {
+  #[allow(clippy::too_many_arguments)]
+  fn new(
+    bucket: &str,
+    key: &str,
+    version_id: Uuid,
+    etag: &str,
+    size: i64,
+    content_type: &str,
+    storage_path: &str,
+    metadata: &serde_json::Value,
+  ) -> Self {
+    Self {
+      bucket: bucket.to_string(),
+      key: key.to_string(),
+      version_id: version_id.to_string(),
+      etag: etag.to_string(),
+      size,
+      content_type: content_type.to_string(),
+      storage_path: storage_path.to_string(),
+      metadata: metadata.to_string(),
+    }
+  }
+
+  fn insert(&self, conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+      "INSERT INTO storage_objects (bucket, key, version_id, etag, size, content_type, storage_path, metadata, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+      params![
+        self.bucket,
+        self.key,
+        self.version_id,
+        self.etag,
+        self.size,
+        self.content_type,
+        self.storage_path,
+        self.metadata,
+        storage_timestamp()
+      ],
+    )?;
+    Ok(())
+  }
+}
+
+fn update_bucket_stats(
+  conn: &rusqlite::Connection,
+  bucket: &str,
+  size_delta: i64,
+  count_delta: i64,
+) -> Result<(), rusqlite::Error> {
+  conn.execute(
+    "UPDATE storage_buckets SET current_size = current_size + ?2, object_count = object_count + ?3 WHERE name = ?1",
+    params![bucket, size_delta, count_delta],
+  )?;
+  Ok(())
+}
+
+/// Distinct key prefixes up to and including the first delimiter after `prefix`
+fn common_prefixes(
+  conn: &rusqlite::Connection,
+  bucket: &str,
+  prefix: &str,
+  delimiter: &str,
+) -> Result<Vec<String>, rusqlite::Error> {
+  let mut stmt = conn.prepare_cached(
+    "SELECT DISTINCT substr(key, 1, length(?2) + instr(substr(key, length(?2) + 1), ?3) + length(?3) - 1) AS common_prefix
+     FROM storage_objects
+     WHERE bucket = ?1 AND substr(key, 1, length(?2)) = ?2 AND is_latest = 1
+       AND instr(substr(key, length(?2) + 1), ?3) > 0
+     ORDER BY common_prefix",
+  )?;
+  let prefixes = stmt
+    .query_map(params![bucket, prefix, delimiter], |row| row.get(0))?
+    .collect::<Result<Vec<_>, _>>()?;
+  Ok(prefixes)
+}
+
+/// Indexes the schema creates on `documents`. Any other index in a query
+/// plan was added for a document field.
+const SCHEMA_DOCUMENT_INDEXES: &[&str] = &[
+  "idx_documents_collection",
+  "idx_documents_project",
+  "idx_documents_project_collection",
+  "idx_documents_list_order",
+];
+
+/// Name of the index a line of `EXPLAIN QUERY PLAN` output uses, if any
+/// (automatic indexes have none)
+fn plan_index(detail: &str) -> Option<&str> {
+  let (_, rest) = detail.split_once("INDEX ")?;
+  rest
+    .split_whitespace()
+    .next()
+    .filter(|name| !name.starts_with('('))
+}
+
+/// Bind a JSON value as a SQLite parameter (arrays and objects as JSON text)
+fn sqlite_param(value: &serde_json::Value) -> rusqlite::types::Value {
+  use rusqlite::types::Value;
+  match value {
+    serde_json::Value::Null => Value::Null,
+    serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+    serde_json::Value::Number(n) => match n.as_i64() {
+      Some(i) => Value::Integer(i),
+      None => Value::Real(n.as_f64().unwrap_or_default()),
+    },
+    serde_json::Value::String(s) => Value::Text(s.clone()),
+    other => Value::Text(other.to_string()),
+  }
+}
+
+/// Convert a SQLite column value to JSON (blobs as hex)
+fn sqlite_value(value: rusqlite::types::ValueRef) -> serde_json::Value {
+  use rusqlite::types::ValueRef;
+  match value {
+    ValueRef::Null => serde_json::Value::Null,
+    ValueRef::Integer(i) => i.into(),
+    ValueRef::Real(f) => f.into(),
+    ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+    ValueRef::Blob(b) => hex::encode(b).into(),
+  }
+}
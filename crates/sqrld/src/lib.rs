@@ -8,6 +8,8 @@ pub mod security;
 #[cfg(feature = "server")]
 pub mod backup;
 #[cfg(feature = "server")]
+pub mod bench;
+#[cfg(feature = "server")]
 pub mod cache;
 #[cfg(feature = "server")]
 pub mod db;
@@ -23,6 +25,8 @@ pub mod server;
 pub mod storage;
 #[cfg(feature = "server")]
 pub mod subscriptions;
+#[cfg(feature = "server")]
+pub mod webhooks;
 
 // Re-export types from the types crate for convenience
 pub use types;
@@ -1,6 +1,11 @@
-use clap::Parser;
-use squirreldb::db::{DatabaseBackend, PostgresBackend, SqliteBackend};
-use squirreldb::server::{BackendType, Daemon, ServerConfig};
+use clap::{Parser, Subcommand};
+use squirreldb::backup::{export_archive, import_archive, migrate_backend, BackupFeature};
+use squirreldb::bench::{self, BenchOptions, OperationMix};
+use squirreldb::db::{
+  ChangeRetention, DatabaseBackend, DocumentCompression, FieldEncryption, PoolSettings,
+  PostgresBackend, ReservedCollections, SqliteBackend, TableMaintenance,
+};
+use squirreldb::server::{BackendType, ConfigLoader, Daemon, LogLevelSetter, ServerConfig};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -15,6 +20,12 @@ struct Args {
   pg_url: Option<String>,
   #[arg(long, env = "SQUIRRELDB_SQLITE_PATH")]
   sqlite: Option<String>,
+  /// Keep all data in memory; nothing is saved
+  #[arg(long, env = "SQUIRRELDB_MEMORY")]
+  memory: bool,
+  /// Base directory for relative data paths
+  #[arg(long, env = "SQUIRRELDB_DATA_DIR")]
+  data_dir: Option<String>,
   #[arg(short, long)]
   port: Option<u16>,
   #[arg(long)]
@@ -23,12 +34,329 @@ struct Args {
   config: Option<String>,
   #[arg(long)]
   log_level: Option<String>,
+  #[command(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Restore a local backup into the database (run while the server is stopped)
+  Restore {
+    /// Backup ID (as shown in the admin UI / backup list)
+    #[arg(long)]
+    id: String,
+    /// Replace existing documents if the database is not empty
+    #[arg(long)]
+    force: bool,
+  },
+  /// Write the whole database to a compressed archive that any backend can import
+  Export {
+    /// Archive file to create
+    #[arg(long)]
+    out: String,
+    /// Also export admin users and API tokens (with their hashes)
+    #[arg(long)]
+    include_auth: bool,
+  },
+  /// Load an archive written by `export` (run while the server is stopped)
+  Import {
+    /// Archive file to read
+    #[arg(long = "in", value_name = "PATH")]
+    input: String,
+    /// Import into a database that already has documents
+    #[arg(long)]
+    force: bool,
+  },
+  /// Copy all data from one database into another, empty one, e.g. from SQLite
+  /// to PostgreSQL (run while the server is stopped)
+  MigrateBackend {
+    /// Source database (`sqlite://<path>` or `postgres://...`)
+    #[arg(long)]
+    from: String,
+    /// Destination database (`sqlite://<path>` or `postgres://...`)
+    #[arg(long)]
+    to: String,
+  },
+  /// Run a mix of inserts, queries and updates against a running server and
+  /// report throughput and latency. The collection it uses is dropped afterwards.
+  Bench {
+    /// WebSocket address (default: this config's host and port)
+    #[arg(long)]
+    url: Option<String>,
+    /// API or admin token, when auth is enabled
+    #[arg(long, env = "SQUIRRELDB_TOKEN")]
+    token: Option<String>,
+    /// Total number of operations
+    #[arg(long, default_value_t = 10_000)]
+    operations: usize,
+    /// Number of parallel connections
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+    /// Weights of inserts, queries and updates
+    #[arg(long, default_value = "40:40:20")]
+    mix: OperationMix,
+  },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-  let args = Args::parse();
+  let mut args = Args::parse();
+  let command = args.command.take();
+  let args = Arc::new(args);
+
+  let config = load_config(&args)?;
+
+  // RUST_LOG takes precedence over logging.level, at startup and on reload
+  let env_filter = tracing_subscriber::EnvFilter::try_from_default_env().ok();
+  let level_from_env = env_filter.is_some();
+  let (filter, filter_handle) = tracing_subscriber::reload::Layer::new(
+    env_filter.unwrap_or_else(|| config.logging.level.clone().into()),
+  );
+  tracing_subscriber::registry()
+    .with(filter)
+    .with(tracing_subscriber::fmt::layer())
+    .init();
+
+  if let Some(Command::Bench {
+    url,
+    token,
+    operations,
+    concurrency,
+    mix,
+  }) = command
+  {
+    let url = url.unwrap_or_else(|| {
+      let host = match config.server.host.as_str() {
+        "0.0.0.0" | "::" => "127.0.0.1",
+        host => host,
+      };
+      format!("{}:{}", host, config.server.ports.http)
+    });
+    println!(
+      "Running {} operations on {} connection(s) against {}",
+      operations, concurrency, url
+    );
+    let report = bench::run(BenchOptions {
+      url,
+      token,
+      operations,
+      concurrency,
+      mix,
+    })
+    .await?;
+    print!("{}", report.render());
+    return Ok(());
+  }
+
+  let data_dir = config.data_dir();
+  std::fs::create_dir_all(&data_dir)?;
+  tracing::info!("Data directory: {}", data_dir.display());
+  if config.backend == BackendType::Sqlite {
+    tracing::info!("SQLite database: {}", config.sqlite.path);
+  }
+  if config.backend == BackendType::Memory {
+    tracing::warn!("In-memory database: all data is lost when the server stops");
+  }
+  if config.features.storage {
+    tracing::info!("Object storage path: {}", config.storage.storage_path);
+  }
+  if config.features.caching && config.caching.snapshot.enabled {
+    tracing::info!("Cache snapshot path: {}", config.caching.snapshot.path);
+  }
+  if config.features.backup {
+    tracing::info!("Local backup path: {}", config.backup.local_path);
+  }
+
+  let encryption = Arc::new(FieldEncryption::from_config(&config.encryption)?);
+  let retention = ChangeRetention {
+    max_entries: config.change_queue.max_entries,
+    max_age: Duration::from_secs(config.change_queue.max_age_secs),
+    cleanup_interval: Duration::from_secs(config.change_queue.cleanup_interval_secs.max(1)),
+  };
+  let reserved = ReservedCollections::new(&config.collections.reserved);
+
+  if let Some(Command::MigrateBackend { from, to }) = command {
+    let source = open_backend_url(&from, &config, encryption.clone()).await?;
+    let target = open_backend_url(&to, &config, encryption).await?;
+    source.init_schema().await?;
+    target.init_schema().await?;
+    let summary = migrate_backend(&source, &target).await?;
+    println!(
+      "Migrated {} document(s) in {} collection(s) across {} project(s), {} user(s), {} token(s); document counts match",
+      summary.documents, summary.collections, summary.projects, summary.users, summary.tokens
+    );
+    return Ok(());
+  }
+  let backend: Arc<dyn DatabaseBackend> = match config.backend {
+    BackendType::Postgres => Arc::new(
+      PostgresBackend::with_pool_settings(
+        &config.postgres.url,
+        config.postgres.max_connections,
+        pool_settings(&config)?,
+      )?
+      .with_statement_cache_size(config.postgres.statement_cache_size)
+      .with_field_encryption(encryption)
+      .with_change_retention(retention)
+      .with_reserved_collections(reserved)
+      .with_compression(document_compression(&config)?)
+      .with_table_maintenance(
+        config
+          .postgres
+          .maintenance
+          .enabled
+          .then(|| TableMaintenance {
+            interval: Duration::from_secs(config.postgres.maintenance.interval_secs.max(1)),
+            vacuum: config.postgres.maintenance.vacuum,
+          }),
+      ),
+    ),
+    BackendType::Sqlite => Arc::new(
+      SqliteBackend::new(&config.sqlite.path)
+        .await?
+        .with_field_encryption(encryption)
+        .with_change_retention(retention)
+        .with_reserved_collections(reserved),
+    ),
+    BackendType::Memory => Arc::new(
+      SqliteBackend::in_memory()
+        .await?
+        .with_field_encryption(encryption)
+        .with_change_retention(retention)
+        .with_reserved_collections(reserved),
+    ),
+  };
+
+  if let Some(Command::Restore { id, force }) = command {
+    backend.init_schema().await?;
+    let summary = BackupFeature::new()
+      .restore_backup(&backend, &config, &id, force)
+      .await?
+      .ok_or_else(|| {
+        anyhow::anyhow!("Backup '{}' not found in {}", id, config.backup.local_path)
+      })?;
+    println!(
+      "Restored {} document(s) in {} collection(s) across {} project(s)",
+      summary.documents, summary.collections, summary.projects
+    );
+    return Ok(());
+  }
+
+  if let Some(Command::Export { out, include_auth }) = command {
+    backend.init_schema().await?;
+    let file = std::io::BufWriter::new(std::fs::File::create(&out)?);
+    let summary = export_archive(&backend, file, include_auth).await?;
+    println!(
+      "Exported {} document(s) in {} collection(s) across {} project(s), {} user(s), {} token(s) to {}",
+      summary.documents, summary.collections, summary.projects, summary.users, summary.tokens, out
+    );
+    return Ok(());
+  }
+
+  if let Some(Command::Import { input, force }) = command {
+    backend.init_schema().await?;
+    let file = std::fs::File::open(&input)?;
+    let summary = import_archive(&backend, file, force).await?;
+    println!(
+      "Imported {} document(s) in {} collection(s) across {} project(s), {} user(s), {} token(s)",
+      summary.documents, summary.collections, summary.projects, summary.users, summary.tokens
+    );
+    return Ok(());
+  }
+
+  // SIGHUP re-reads the same sources with the same overrides
+  let loader: ConfigLoader = Arc::new(move || load_config(&args));
+  let set_log_level: LogLevelSetter = Arc::new(move |level: &str| {
+    if level_from_env {
+      anyhow::bail!("RUST_LOG is set and takes precedence");
+    }
+    filter_handle.reload(tracing_subscriber::EnvFilter::try_new(level)?)?;
+    Ok(())
+  });
+
+  let daemon = Arc::new(Daemon::new(config, backend).with_reload(loader, set_log_level));
+  let daemon_clone = daemon.clone();
+
+  // Handle shutdown signals (SIGINT, SIGTERM)
+  tokio::spawn(async move {
+    shutdown_signal().await;
+    daemon_clone.shutdown();
+
+    // Give servers time to drain connections
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    tracing::info!("Shutdown complete");
+    std::process::exit(0);
+  });
+
+  daemon.run().await
+}
+
+/// Open the database a `sqlite://` or `postgres://` URL points to
+async fn open_backend_url(
+  url: &str,
+  config: &ServerConfig,
+  encryption: Arc<FieldEncryption>,
+) -> Result<Arc<dyn DatabaseBackend>, anyhow::Error> {
+  if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+    return Ok(Arc::new(
+      PostgresBackend::with_pool_settings(
+        url,
+        config.postgres.max_connections,
+        pool_settings(config)?,
+      )?
+      .with_statement_cache_size(config.postgres.statement_cache_size)
+      .with_field_encryption(encryption)
+      .with_compression(document_compression(config)?),
+    ));
+  }
+  if let Some(path) = url.strip_prefix("sqlite://") {
+    return Ok(Arc::new(
+      SqliteBackend::new(path)
+        .await?
+        .with_field_encryption(encryption),
+    ));
+  }
+  anyhow::bail!(
+    "Unsupported database URL '{}': use sqlite://<path> or postgres://...",
+    url
+  )
+}
 
+/// PostgreSQL document compression from `postgres.compression`, if enabled
+fn document_compression(
+  config: &ServerConfig,
+) -> Result<Option<DocumentCompression>, anyhow::Error> {
+  let section = &config.postgres.compression;
+  if !section.enabled {
+    return Ok(None);
+  }
+  let method = section
+    .method
+    .parse()
+    .map_err(|e| anyhow::anyhow!("Invalid postgres.compression.method: {}", e))?;
+  Ok(Some(DocumentCompression::new(
+    section.threshold_bytes,
+    method,
+  )?))
+}
+
+/// PostgreSQL connection pool checkout behaviour from `postgres.pool`
+fn pool_settings(config: &ServerConfig) -> Result<PoolSettings, anyhow::Error> {
+  let section = &config.postgres.pool;
+  let timeout = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+  Ok(PoolSettings {
+    recycling: section
+      .recycling
+      .parse()
+      .map_err(|e| anyhow::anyhow!("Invalid postgres.pool.recycling: {}", e))?,
+    min_idle: section.min_idle,
+    wait_timeout: timeout(section.wait_timeout_secs),
+    create_timeout: timeout(section.create_timeout_secs),
+    recycle_timeout: timeout(section.recycle_timeout_secs),
+  })
+}
+
+/// Load the config file and apply command-line and environment overrides
+fn load_config(args: &Args) -> Result<ServerConfig, anyhow::Error> {
   // Load config: explicit path > auto-detect > defaults
   let mut config = if let Some(path) = &args.config {
     ServerConfig::from_file(path)?
@@ -37,22 +365,28 @@ async fn main() -> Result<(), anyhow::Error> {
   };
 
   // CLI args override config file
-  if let Some(url) = args.pg_url {
-    config.postgres.url = url;
+  if let Some(url) = &args.pg_url {
+    config.postgres.url = url.clone();
     config.backend = BackendType::Postgres;
   }
-  if let Some(path) = args.sqlite {
-    config.sqlite.path = path;
+  if let Some(path) = &args.sqlite {
+    config.sqlite.path = path.clone();
     config.backend = BackendType::Sqlite;
   }
+  if args.memory {
+    config.backend = BackendType::Memory;
+  }
+  if let Some(dir) = &args.data_dir {
+    config.data_dir = dir.clone();
+  }
   if let Some(port) = args.port {
     config.server.ports.http = port;
   }
-  if let Some(host) = args.host {
-    config.server.host = host;
+  if let Some(host) = &args.host {
+    config.server.host = host.clone();
   }
-  if let Some(level) = args.log_level {
-    config.logging.level = level;
+  if let Some(level) = &args.log_level {
+    config.logging.level = level.clone();
   }
 
   // Environment variable overrides
@@ -68,38 +402,13 @@ async fn main() -> Result<(), anyhow::Error> {
   if let Ok(val) = std::env::var("SQRL_BACKUP_ENABLED") {
     config.features.backup = val.to_lowercase() == "true" || val == "1";
   }
+  if let Ok(val) = std::env::var("SQRL_WEBHOOKS_ENABLED") {
+    config.features.webhooks = val.to_lowercase() == "true" || val == "1";
+  }
 
-  tracing_subscriber::registry()
-    .with(
-      tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| config.logging.level.clone().into()),
-    )
-    .with(tracing_subscriber::fmt::layer())
-    .init();
-
-  let backend: Arc<dyn DatabaseBackend> = match config.backend {
-    BackendType::Postgres => Arc::new(PostgresBackend::new(
-      &config.postgres.url,
-      config.postgres.max_connections,
-    )?),
-    BackendType::Sqlite => Arc::new(SqliteBackend::new(&config.sqlite.path).await?),
-  };
-
-  let daemon = Arc::new(Daemon::new(config, backend));
-  let daemon_clone = daemon.clone();
-
-  // Handle shutdown signals (SIGINT, SIGTERM)
-  tokio::spawn(async move {
-    shutdown_signal().await;
-    daemon_clone.shutdown();
-
-    // Give servers time to drain connections
-    tokio::time::sleep(Duration::from_secs(5)).await;
-    tracing::info!("Shutdown complete");
-    std::process::exit(0);
-  });
-
-  daemon.run().await
+  // Anchor relative data paths to the data directory, not the launch directory
+  config.resolve_paths();
+  Ok(config)
 }
 
 async fn shutdown_signal() {
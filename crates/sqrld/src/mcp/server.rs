@@ -120,7 +120,7 @@ impl McpServer {
   async fn query(&self, params: Parameters<QueryParams>) -> Result<CallToolResult, McpError> {
     let result = self
       .engine_pool
-      .execute(&params.0.query, self.backend.as_ref())
+      .execute(&params.0.query, DEFAULT_PROJECT_ID, self.backend.as_ref())
       .await
       .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
@@ -0,0 +1,216 @@
+//! Opt-in query result cache backed by the cache feature's store
+//!
+//! Results of queries sent with `cache: true` are stored in the active
+//! `CacheStore` under `sqrl:query:{project}:{collection}:{hash}`. Entries for a
+//! collection are dropped whenever the change stream reports a write to it;
+//! the keys cached for each collection are tracked so that doesn't need a
+//! scan of the store.
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::cache::{CacheStore, CacheValue};
+use crate::types::{Change, QueryInput, QuerySpec};
+
+/// Prefix for query cache keys in the cache store
+const KEY_PREFIX: &str = "sqrl:query:";
+
+/// Default lifetime of a cached query result
+pub const DEFAULT_QUERY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// A query run and cached ahead of client requests, so the first dashboard
+/// load after a restart doesn't hit a cold cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupQuery {
+  /// Query string or structured query, as sent in a `query` message
+  pub query: QueryInput,
+  /// Project to run against (default project if unset)
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub project_id: Option<Uuid>,
+  /// Lifetime of the cached result in seconds (default: 60)
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub ttl: Option<u64>,
+}
+
+/// Outcome of warming a list of queries
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WarmupReport {
+  /// Queries whose results were cached
+  pub warmed: usize,
+  /// Queries that can't be cached (changefeeds), or the cache was off
+  pub skipped: usize,
+  /// Queries that failed, by position in the list
+  pub errors: Vec<WarmupError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WarmupError {
+  pub index: usize,
+  pub error: String,
+}
+
+/// Snapshot of the invalidation state of a collection, taken before a query
+/// runs so results that raced with a write are not cached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheGeneration {
+  epoch: u64,
+  collection: u64,
+}
+
+/// Query result cache stored through the cache feature
+#[derive(Default)]
+pub struct QueryCache {
+  store: RwLock<Option<Arc<dyn CacheStore>>>,
+  /// Bumped when every entry is dropped
+  epoch: AtomicU64,
+  /// (project, collection) -> invalidation count
+  generations: Mutex<HashMap<(Uuid, String), u64>>,
+  /// (project, collection) -> keys cached through this instance and when
+  /// they expire
+  keys: Mutex<HashMap<(Uuid, String), HashMap<String, Instant>>>,
+}
+
+impl QueryCache {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Attach or detach the cache store (follows the cache feature's lifecycle)
+  pub fn set_store(&self, store: Option<Arc<dyn CacheStore>>) {
+    *self.store.write() = store;
+  }
+
+  /// Whether a cache store is attached
+  pub fn is_enabled(&self) -> bool {
+    self.store.read().is_some()
+  }
+
+  fn store(&self) -> Option<Arc<dyn CacheStore>> {
+    self.store.read().clone()
+  }
+
+  /// Cache key for a compiled query: the project, the collection and a hash of
+  /// the compiled SQL and its parameters
+  pub fn key(project_id: Uuid, spec: &QuerySpec) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(spec).unwrap_or_default());
+    format!(
+      "{}{}:{}:{:x}",
+      KEY_PREFIX,
+      project_id,
+      spec.table,
+      hasher.finalize()
+    )
+  }
+
+  /// Current invalidation state of a collection
+  pub fn generation(&self, project_id: Uuid, collection: &str) -> CacheGeneration {
+    CacheGeneration {
+      epoch: self.epoch.load(Ordering::SeqCst),
+      collection: self
+        .generations
+        .lock()
+        .get(&(project_id, collection.to_string()))
+        .copied()
+        .unwrap_or(0),
+    }
+  }
+
+  /// Look up a cached result
+  pub async fn get(&self, key: &str) -> Option<serde_json::Value> {
+    let store = self.store()?;
+    match store.get(key).await?.value {
+      CacheValue::Json(value) => Some(value),
+      // Proxy mode hands values back as strings
+      CacheValue::String(s) => serde_json::from_str(&s).ok(),
+      _ => None,
+    }
+  }
+
+  /// Cache a result, unless the collection changed since `generation` was taken
+  pub async fn put(
+    &self,
+    key: &str,
+    project_id: Uuid,
+    collection: &str,
+    generation: CacheGeneration,
+    value: serde_json::Value,
+    ttl: Duration,
+  ) {
+    let Some(store) = self.store() else {
+      return;
+    };
+    if self.generation(project_id, collection) != generation {
+      return;
+    }
+    if let Err(e) = store.set(key, CacheValue::Json(value), Some(ttl)).await {
+      tracing::debug!("Failed to cache query result: {}", e);
+      return;
+    }
+    {
+      let now = Instant::now();
+      let mut keys = self.keys.lock();
+      let cached = keys
+        .entry((project_id, collection.to_string()))
+        .or_default();
+      cached.retain(|_, expires| *expires > now);
+      cached.insert(key.to_string(), now + ttl);
+    }
+    // A write may have landed while storing; drop the entry if so
+    if self.generation(project_id, collection) != generation {
+      store.delete(key).await;
+    }
+  }
+
+  /// Drop cached results for a collection
+  pub async fn invalidate(&self, project_id: Uuid, collection: &str) {
+    let id = (project_id, collection.to_string());
+    *self.generations.lock().entry(id.clone()).or_default() += 1;
+
+    let cached = self.keys.lock().remove(&id);
+    if let (Some(store), Some(cached)) = (self.store(), cached) {
+      for key in cached.into_keys() {
+        store.delete(&key).await;
+      }
+    }
+  }
+
+  /// Drop every cached query result, including ones this instance didn't
+  /// store (a previous run's snapshot, or other nodes in proxy mode). Scans
+  /// the store, so it's kept for startup and missed changes.
+  pub async fn invalidate_all(&self) {
+    self.epoch.fetch_add(1, Ordering::SeqCst);
+    self.keys.lock().clear();
+
+    if let Some(store) = self.store() {
+      for key in store.keys(&format!("{}*", KEY_PREFIX)).await {
+        store.delete(&key).await;
+      }
+    }
+  }
+
+  /// Invalidate entries from the change stream
+  pub async fn process_changes(&self, mut rx: broadcast::Receiver<Change>) {
+    loop {
+      match rx.recv().await {
+        Ok(change) => self.invalidate(change.project_id, &change.collection).await,
+        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+          // We can't tell which collections the missed changes touched
+          tracing::warn!(
+            "Query cache missed {} change(s), dropping all entries",
+            skipped
+          );
+          self.invalidate_all().await;
+        }
+        Err(broadcast::error::RecvError::Closed) => break,
+      }
+    }
+  }
+}
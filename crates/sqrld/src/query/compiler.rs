@@ -1,6 +1,6 @@
 use crate::db::sanitize::{escape_string, validate_identifier, validate_numeric};
-use crate::db::SqlDialect;
-use crate::types::CompiledFilter;
+use crate::db::{SqlDialect, FILTER_PARAM_OFFSET};
+use crate::types::{CompiledFilter, FilterSpec};
 
 pub struct QueryCompiler {
   dialect: SqlDialect,
@@ -11,14 +11,35 @@ impl QueryCompiler {
     Self { dialect }
   }
 
+  pub fn dialect(&self) -> SqlDialect {
+    self.dialect
+  }
+
   pub fn compile_predicate(&self, js: &str) -> CompiledFilter {
     self
-      .try_compile_to_sql(js)
+      .try_compile_to_sql(js, &mut Vec::new())
       .map(CompiledFilter::Sql)
       .unwrap_or_else(|| CompiledFilter::Js(js.into()))
   }
 
-  fn try_compile_to_sql(&self, js: &str) -> Option<String> {
+  /// Compile a filter function, turning comparisons with `$1`, `$2`, ...
+  /// into statement placeholders. The JS code is kept for filters that
+  /// can't be compiled.
+  pub fn compile_filter(&self, js: &str) -> FilterSpec {
+    let mut params = Vec::new();
+    let compiled_sql = self.try_compile_to_sql(js, &mut params);
+    if compiled_sql.is_none() {
+      params.clear();
+    }
+    FilterSpec {
+      js_code: js.into(),
+      compiled_sql,
+      params,
+    }
+  }
+
+  /// `params` collects the query parameter behind each placeholder written
+  fn try_compile_to_sql(&self, js: &str, params: &mut Vec<usize>) -> Option<String> {
     let code = js.trim();
     let parts: Vec<&str> = code.splitn(2, "=>").collect();
     if parts.len() != 2 {
@@ -32,7 +53,7 @@ impl QueryCompiler {
     }
 
     // Try to compile the expression (supports logical operators)
-    self.compile_expression(body, param)
+    self.compile_expression(body, param, params)
   }
 
   /// Generate SQL for accessing a JSON array field (for array operations)
@@ -148,29 +169,29 @@ impl QueryCompiler {
   }
 
   /// Compile a JS expression to SQL, handling logical operators && and ||
-  fn compile_expression(&self, expr: &str, param: &str) -> Option<String> {
+  fn compile_expression(&self, expr: &str, param: &str, params: &mut Vec<usize>) -> Option<String> {
     let expr = expr.trim();
 
     // Handle parenthesized expressions
     if expr.starts_with('(') && expr.ends_with(')') {
       let inner = &expr[1..expr.len() - 1];
       return self
-        .compile_expression(inner, param)
+        .compile_expression(inner, param, params)
         .map(|s| format!("({})", s));
     }
 
     // Try to split on logical OR (||) - lowest precedence
-    if let Some(sql) = self.try_split_logical(expr, param, "||", "OR") {
+    if let Some(sql) = self.try_split_logical(expr, param, params, "||", "OR") {
       return Some(sql);
     }
 
     // Try to split on logical AND (&&)
-    if let Some(sql) = self.try_split_logical(expr, param, "&&", "AND") {
+    if let Some(sql) = self.try_split_logical(expr, param, params, "&&", "AND") {
       return Some(sql);
     }
 
     // Try to compile as a simple comparison
-    self.compile_comparison(expr, param)
+    self.compile_comparison(expr, param, params)
   }
 
   /// Try to split expression on a logical operator
@@ -178,6 +199,7 @@ impl QueryCompiler {
     &self,
     expr: &str,
     param: &str,
+    params: &mut Vec<usize>,
     js_op: &str,
     sql_op: &str,
   ) -> Option<String> {
@@ -212,17 +234,24 @@ impl QueryCompiler {
     // Add the last part
     parts.push(expr[last_byte_pos..].trim());
 
-    // Compile each part
-    let sql_parts: Option<Vec<String>> = parts
-      .into_iter()
-      .map(|p| self.compile_expression(p, param))
-      .collect();
+    // Compile each part, forgetting the placeholders of a failed attempt
+    let bound = params.len();
+    let mut sql_parts = Vec::with_capacity(parts.len());
+    for part in parts {
+      match self.compile_expression(part, param, params) {
+        Some(sql) => sql_parts.push(sql),
+        None => {
+          params.truncate(bound);
+          return None;
+        }
+      }
+    }
 
-    sql_parts.map(|p| p.join(&format!(" {} ", sql_op)))
+    Some(sql_parts.join(&format!(" {} ", sql_op)))
   }
 
   /// Compile a single comparison expression
-  fn compile_comparison(&self, expr: &str, param: &str) -> Option<String> {
+  fn compile_comparison(&self, expr: &str, param: &str, params: &mut Vec<usize>) -> Option<String> {
     let prefix = format!("{}.", param);
 
     // Handle negation: !doc.field
@@ -259,7 +288,7 @@ impl QueryCompiler {
 
     // Try to parse as comparison with possibly nested field
     if let Some((field, op, value)) = parse_comparison_nested(rest) {
-      return self.generate_sql(&field, &op, &value);
+      return self.generate_sql(&field, &op, &value, params);
     }
 
     // Handle boolean field access (e.g., doc.active)
@@ -367,7 +396,13 @@ impl QueryCompiler {
     None
   }
 
-  fn generate_sql(&self, field: &str, op: &str, value: &str) -> Option<String> {
+  fn generate_sql(
+    &self,
+    field: &str,
+    op: &str,
+    value: &str,
+    params: &mut Vec<usize>,
+  ) -> Option<String> {
     // Validate field name to prevent injection
     if validate_identifier(field).is_err() {
       return None;
@@ -400,6 +435,16 @@ impl QueryCompiler {
       });
     }
 
+    // Query parameter - bound by the backend, never written into the SQL
+    if let Some(index) = parse_param_ref(value) {
+      params.push(index);
+      return Some(self.dialect.json_param_compare(
+        field,
+        sql_op,
+        FILTER_PARAM_OFFSET + params.len(),
+      ));
+    }
+
     // String value - properly escape using sanitize module
     if (value.starts_with('"') && value.ends_with('"'))
       || (value.starts_with('\'') && value.ends_with('\''))
@@ -460,6 +505,68 @@ fn parse_comparison_nested(s: &str) -> Option<(String, String, String)> {
   None
 }
 
+/// Index of a query parameter reference like `$1`
+fn parse_param_ref(value: &str) -> Option<usize> {
+  let digits = value.strip_prefix('$')?;
+  if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+  digits.parse().ok().filter(|&n| n >= 1)
+}
+
+/// Deepest nesting of brackets in JS code, not counting those inside string
+/// literals
+pub fn filter_depth(js: &str) -> usize {
+  let mut depth = 0usize;
+  let mut max = 0;
+  let mut quote = None;
+  let mut chars = js.chars();
+  while let Some(c) = chars.next() {
+    match (quote, c) {
+      (Some(_), '\\') => {
+        chars.next();
+      }
+      (Some(q), c) if c == q => quote = None,
+      (Some(_), _) => {}
+      (None, '\'' | '"' | '`') => quote = Some(c),
+      (None, '(' | '[' | '{') => {
+        depth += 1;
+        max = max.max(depth);
+      }
+      (None, ')' | ']' | '}') => depth = depth.saturating_sub(1),
+      _ => {}
+    }
+  }
+  max
+}
+
+/// Values to bind for a compiled filter's placeholders, in order
+pub fn bind_params(
+  filter: &FilterSpec,
+  params: &[serde_json::Value],
+) -> Result<Vec<serde_json::Value>, anyhow::Error> {
+  filter
+    .params
+    .iter()
+    .map(|&index| match params.get(index - 1) {
+      Some(
+        value @ (serde_json::Value::String(_)
+        | serde_json::Value::Number(_)
+        | serde_json::Value::Bool(_)),
+      ) => Ok(value.clone()),
+      Some(_) => anyhow::bail!(
+        "Query parameter ${} must be a string, number or boolean",
+        index
+      ),
+      None => anyhow::bail!(
+        "Query parameter ${} is missing ({} given)",
+        index,
+        params.len()
+      ),
+    })
+    .collect()
+}
+
 /// Extract string value from quoted string (returns inner content)
 fn extract_string_value(value: &str) -> Option<&str> {
   if (value.starts_with('"') && value.ends_with('"'))
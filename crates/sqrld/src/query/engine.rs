@@ -1,16 +1,29 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use futures_util::stream::{BoxStream, StreamExt};
 use lru::LruCache;
 use parking_lot::Mutex;
 
-use super::{QueryCompiler, StructuredCompiler};
-use crate::db::{DatabaseBackend, SqlDialect};
+use super::{
+  bind_params, filter_depth, QueryCache, QueryCompiler, StructuredCompiler, WarmupError,
+  WarmupQuery, WarmupReport, DEFAULT_QUERY_CACHE_TTL,
+};
+use crate::db::{
+  parse_projection, validate_identifier, validate_projection, DatabaseBackend, ReadOptions,
+  SqlDialect,
+};
+use crate::server::QueryCostAction;
 use crate::types::{
-  ChangesOptions, CompiledFilter, Document, FilterSpec, OrderBySpec, OrderDirection, QuerySpec,
-  StructuredQuery, DEFAULT_PROJECT_ID,
+  ChangesOptions, Document, FilterSpec, GeoNear, GeoPoint, OrderBySpec, OrderDirection, QueryInput,
+  QuerySpec, StructuredQuery, DEFAULT_PROJECT_ID,
 };
 use rquickjs::{Context, Function, Runtime, Value};
+use uuid::Uuid;
+
+/// Rows read at a time when a JS filter decides which of them match
+const JS_FILTER_BATCH_SIZE: usize = 1000;
 
 /// Cached query result with expiration
 struct CachedResult {
@@ -27,6 +40,20 @@ pub struct QueryEnginePool {
   result_cache: Mutex<LruCache<String, CachedResult>>,
   result_cache_ttl: Duration,
   structured_compiler: StructuredCompiler,
+  query_cache: Arc<QueryCache>,
+  /// Rows returned by a query without an explicit limit (0 = unlimited)
+  max_result_rows: usize,
+  /// Estimated cost above which `query_cost_action` applies (0 = no limit)
+  max_query_cost: f64,
+  query_cost_action: QueryCostAction,
+}
+
+/// Result of a query run through the pool
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+  pub data: serde_json::Value,
+  /// The query had no limit and more rows than `max_result_rows` matched
+  pub truncated: bool,
 }
 
 impl QueryEnginePool {
@@ -49,12 +76,16 @@ impl QueryEnginePool {
       result_cache: Mutex::new(LruCache::new(std::num::NonZeroUsize::new(256).unwrap())),
       result_cache_ttl,
       structured_compiler: StructuredCompiler::new(dialect),
+      query_cache: Arc::new(QueryCache::new()),
+      max_result_rows: 0,
+      max_query_cost: 0.0,
+      query_cost_action: QueryCostAction::default(),
     }
   }
 
-  /// Generate cache key for a query
-  fn cache_key(query: &str) -> String {
-    query.to_string()
+  /// Generate cache key for a query, scoped to the project it runs against
+  fn cache_key(project_id: Uuid, query: &str) -> String {
+    format!("{}:{}", project_id, query)
   }
 
   /// Get cached result if available and not expired
@@ -100,6 +131,11 @@ impl QueryEnginePool {
     }
   }
 
+  /// Opt-in query cache stored through the cache feature
+  pub fn query_cache(&self) -> Arc<QueryCache> {
+    self.query_cache.clone()
+  }
+
   /// Get the next engine in round-robin fashion.
   pub fn get(&self) -> impl std::ops::Deref<Target = QueryEngine> + '_ {
     let idx = self.next.fetch_add(1, Ordering::Relaxed) % self.engines.len();
@@ -129,109 +165,491 @@ impl QueryEnginePool {
     Ok(spec)
   }
 
-  /// Execute a query using a pooled engine with result caching.
-  pub async fn execute(
-    &self,
-    query: &str,
-    backend: &dyn DatabaseBackend,
-  ) -> Result<serde_json::Value, anyhow::Error> {
-    // Check cache for read queries (no changes subscription)
-    let cache_key = Self::cache_key(query);
-    let spec = self.parse_query(query)?;
+  /// Cap rows returned by queries without an explicit limit (0 = unlimited)
+  pub fn with_max_result_rows(mut self, max: usize) -> Self {
+    self.max_result_rows = max;
+    self
+  }
 
-    // Only cache read queries without changes subscription
-    let is_cacheable = spec.changes.is_none();
-    if is_cacheable {
-      if let Some(cached) = self.get_cached(&cache_key) {
-        return Ok(cached);
-      }
+  /// Reject filters nested more than `depth` levels, in JS and structured
+  /// queries (0 = unlimited)
+  pub fn with_max_filter_depth(mut self, depth: usize) -> Self {
+    for engine in &mut self.engines {
+      engine.get_mut().max_filter_depth = depth;
     }
+    self.structured_compiler = self.structured_compiler.with_max_depth(depth);
+    self
+  }
 
-    let sql_filter = spec.filter.as_ref().and_then(|f| f.compiled_sql.as_deref());
-    let project_id = spec.project_id.unwrap_or(DEFAULT_PROJECT_ID);
-    let mut docs = backend
-      .list(
+  /// Refuse or log queries whose estimated cost is over `max` (0 = no limit)
+  pub fn with_max_query_cost(mut self, max: f64, action: QueryCostAction) -> Self {
+    self.max_query_cost = max;
+    self.query_cost_action = action;
+    self
+  }
+
+  /// Estimate what reading the query's documents with `limit` would cost
+  /// and, when it is over `max_query_cost`, refuse it or log a warning.
+  /// The message names the fields an index would help with.
+  pub async fn check_cost(
+    &self,
+    spec: &QuerySpec,
+    params: &[serde_json::Value],
+    limit: Option<usize>,
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+  ) -> Result<(), anyhow::Error> {
+    if self.max_query_cost <= 0.0 {
+      return Ok(());
+    }
+    let filter = spec.filter.as_ref().and_then(|f| f.compiled_sql.as_deref());
+    let cost = backend
+      .estimate_list_cost(
         project_id,
         &spec.table,
-        sql_filter,
-        spec.order_by.as_ref(),
-        spec.limit,
+        filter,
+        params,
+        &spec.order_by,
+        limit,
         spec.offset,
+        ReadOptions {
+          with_deleted: spec.with_deleted,
+        },
       )
       .await?;
+    if cost <= self.max_query_cost {
+      return Ok(());
+    }
 
-    // JS filtering - use batch evaluation for performance
-    if let Some(ref f) = spec.filter {
-      if f.compiled_sql.is_none() {
-        let engine = self.get();
-        docs = engine.js_filter_batch(&docs, &f.js_code)?;
+    let mut fields = filter.map(filter_fields).unwrap_or_default();
+    for o in &spec.order_by {
+      if !fields.contains(&o.field) {
+        fields.push(o.field.clone());
       }
     }
-
-    // JS mapping
-    let result = if let Some(ref m) = spec.map {
-      let engine = self.get();
-      engine.js_map_batch(&docs, m)?
-    } else {
-      serde_json::to_value(&docs)?
+    let hint = match fields.as_slice() {
+      [] => "a limit would make it cheaper".to_string(),
+      fields => format!(
+        "an index on {} or a limit would make it cheaper",
+        fields
+          .iter()
+          .map(|f| format!("'{}'", f))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
     };
-
-    // Cache the result
-    if is_cacheable {
-      self.put_cached(cache_key, result.clone());
+    let message = format!(
+      "Query on '{}' has an estimated cost of {:.0}, over the limit of {:.0}; {}",
+      spec.table, cost, self.max_query_cost, hint
+    );
+    match self.query_cost_action {
+      QueryCostAction::Reject => Err(anyhow::anyhow!(message)),
+      QueryCostAction::Warn => {
+        tracing::warn!("{}", message);
+        Ok(())
+      }
     }
+  }
 
-    Ok(result)
+  /// Execute a query against a project using a pooled engine with result caching.
+  pub async fn execute(
+    &self,
+    query: &str,
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+  ) -> Result<serde_json::Value, anyhow::Error> {
+    let input = QueryInput::Script(query.to_string());
+    let result = self.run(&input, project_id, backend, None).await?;
+    Ok(result.data)
   }
 
-  /// Parse a structured query into a QuerySpec (no JS evaluation)
-  pub fn parse_structured(&self, query: &StructuredQuery) -> Result<QuerySpec, anyhow::Error> {
-    self.structured_compiler.compile(query)
+  /// Execute a query through the query cache.
+  ///
+  /// Falls back to normal execution when the cache feature is not running or
+  /// the query subscribes to changes.
+  pub async fn execute_cached(
+    &self,
+    query: &QueryInput,
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+    ttl: Duration,
+  ) -> Result<serde_json::Value, anyhow::Error> {
+    let result = self.run(query, project_id, backend, Some(ttl)).await?;
+    Ok(result.data)
   }
 
-  /// Execute a structured query (no JS evaluation, direct SQL compilation)
+  /// Execute a structured query against a project (no JS evaluation, direct SQL compilation)
   pub async fn execute_structured(
     &self,
     query: &StructuredQuery,
+    project_id: Uuid,
     backend: &dyn DatabaseBackend,
   ) -> Result<serde_json::Value, anyhow::Error> {
-    // Generate cache key from the structured query
-    let cache_key = serde_json::to_string(query)?;
+    let input = QueryInput::Structured(query.clone());
+    let result = self.run(&input, project_id, backend, None).await?;
+    Ok(result.data)
+  }
+
+  /// Execute a query, reporting whether the result was cut at `max_result_rows`.
+  ///
+  /// With `cache_ttl` the result goes through the query cache when the cache
+  /// feature is running; otherwise the short-lived local result cache is used.
+  pub async fn run(
+    &self,
+    query: &QueryInput,
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+    cache_ttl: Option<Duration>,
+  ) -> Result<QueryResult, anyhow::Error> {
+    self
+      .run_with_params(query, &[], project_id, backend, cache_ttl)
+      .await
+  }
+
+  /// Execute a query with values for the `$1`, `$2`, ... parameters of its
+  /// filter. The parsed query is cached without them, so repeated queries
+  /// with different values parse once.
+  pub async fn run_with_params(
+    &self,
+    query: &QueryInput,
+    params: &[serde_json::Value],
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+    cache_ttl: Option<Duration>,
+  ) -> Result<QueryResult, anyhow::Error> {
+    let mut spec = match query {
+      QueryInput::Structured(q) => self.parse_structured(q)?,
+      QueryInput::Script(script) => self.parse_query(script)?,
+    };
+    if !params.is_empty() {
+      if query.is_structured() {
+        anyhow::bail!("Query parameters can only be used with query strings");
+      }
+      spec.params = params.to_vec();
+    }
+    let is_cacheable = is_cacheable(&spec, backend);
+
+    if let Some(ttl) = cache_ttl.filter(|_| is_cacheable && self.query_cache.is_enabled()) {
+      let key = QueryCache::key(project_id, &spec);
+      if let Some(cached) = self.query_cache.get(&key).await {
+        return Ok(self.finish(&spec, cached));
+      }
+
+      let generation = self.query_cache.generation(project_id, &spec.table);
+      let data = self.run_spec(&spec, project_id, backend).await?;
+      self
+        .query_cache
+        .put(&key, project_id, &spec.table, generation, data.clone(), ttl)
+        .await;
+      return Ok(self.finish(&spec, data));
+    }
 
-    // Only cache read queries without changes subscription
-    let is_cacheable = query.changes.is_none();
+    let cache_key = match query {
+      QueryInput::Structured(q) => Self::cache_key(project_id, &serde_json::to_string(q)?),
+      QueryInput::Script(script) if params.is_empty() => Self::cache_key(project_id, script),
+      QueryInput::Script(script) => Self::cache_key(
+        project_id,
+        &format!("{}:{}", script, serde_json::to_string(params)?),
+      ),
+    };
     if is_cacheable {
       if let Some(cached) = self.get_cached(&cache_key) {
-        return Ok(cached);
+        return Ok(self.finish(&spec, cached));
       }
     }
 
-    // Compile structured query to QuerySpec
-    let spec = self.parse_structured(query)?;
+    let data = self.run_spec(&spec, project_id, backend).await?;
+    if is_cacheable {
+      self.put_cached(cache_key, data.clone());
+    }
+    Ok(self.finish(&spec, data))
+  }
+
+  /// Run a parsed query without the result caches, so the result reflects
+  /// every write so far
+  pub async fn run_parsed(
+    &self,
+    spec: &QuerySpec,
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+  ) -> Result<QueryResult, anyhow::Error> {
+    let data = self.run_spec(spec, project_id, backend).await?;
+    Ok(self.finish(spec, data))
+  }
+
+  /// Run a query, returning its rows as a stream of JSON arrays of up to
+  /// `batch_size` rows each, read from the database as the stream is
+  /// polled. Streamed results bypass the result caches and aren't cut at
+  /// `max_result_rows`. With a JS filter, the limit and offset apply to the
+  /// matching rows, so batches can be shorter than `batch_size`.
+  pub async fn stream_with_params(
+    &self,
+    query: &QueryInput,
+    params: &[serde_json::Value],
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+    batch_size: usize,
+  ) -> Result<BoxStream<'_, Result<serde_json::Value, anyhow::Error>>, anyhow::Error> {
+    let mut spec = match query {
+      QueryInput::Structured(q) => self.parse_structured(q)?,
+      QueryInput::Script(script) => self.parse_query(script)?,
+    };
+    if !params.is_empty() {
+      if query.is_structured() {
+        anyhow::bail!("Query parameters can only be used with query strings");
+      }
+      spec.params = params.to_vec();
+    }
+    if spec.changes.is_some() {
+      anyhow::bail!("Changefeeds can't be streamed; subscribe instead");
+    }
 
-    // Execute against backend
     let sql_filter = spec.filter.as_ref().and_then(|f| f.compiled_sql.as_deref());
-    let project_id = spec.project_id.unwrap_or(DEFAULT_PROJECT_ID);
-    let docs = backend
-      .list(
+    let sql_params = sql_params(&spec)?;
+    backend
+      .field_encryption()
+      .check_query(backend.dialect(), &spec)?;
+    let js_filter = spec.filter.as_ref().filter(|f| f.compiled_sql.is_none());
+    // The database can't tell which rows a JS filter matches, so it reads
+    // them all and the limit and offset are applied after filtering
+    let (sql_limit, sql_offset) = match js_filter {
+      Some(_) => (None, None),
+      None => (spec.limit, spec.offset),
+    };
+    self
+      .check_cost(&spec, &sql_params, sql_limit, project_id, backend)
+      .await?;
+    let batches = backend
+      .list_stream(
         project_id,
         &spec.table,
+        spec.projection.as_deref().filter(|_| js_filter.is_none()),
         sql_filter,
-        spec.order_by.as_ref(),
-        spec.limit,
-        spec.offset,
+        &sql_params,
+        &spec.order_by,
+        sql_limit,
+        sql_offset,
+        ReadOptions {
+          with_deleted: spec.with_deleted,
+        },
+        batch_size,
       )
       .await?;
 
-    // No JS filtering needed for structured queries - SQL handles it all
-    let result = serde_json::to_value(&docs)?;
+    // Filter and map each batch as it arrives, as `run_spec` does for the
+    // whole result. The state is the matches still to skip and to send.
+    let window = match js_filter {
+      Some(_) => (spec.offset.unwrap_or(0), spec.limit),
+      None => (0, None),
+    };
+    Ok(
+      batches
+        .scan(window, move |(skip, remaining), batch| {
+          if *remaining == Some(0) {
+            return futures_util::future::ready(None);
+          }
+          let result = (|| -> Result<serde_json::Value, anyhow::Error> {
+            let mut docs = batch?;
+            if let Some(f) = spec.filter.as_ref().filter(|f| f.compiled_sql.is_none()) {
+              docs = self
+                .get()
+                .js_filter_batch(&docs, &f.js_code, &spec.params)?;
+              take_window(&mut docs, skip, remaining);
+              if let Some(ref fields) = spec.projection {
+                project_documents(&mut docs, fields)?;
+              }
+            }
+            match spec.map {
+              Some(ref m) => self.get().js_map_batch(&docs, m, &spec.params),
+              None => Ok(serde_json::to_value(&docs)?),
+            }
+          })();
+          futures_util::future::ready(Some(result))
+        })
+        .boxed(),
+    )
+  }
 
-    // Cache the result
-    if is_cacheable {
-      self.put_cached(cache_key, result.clone());
+  /// Run a query and store its result in the query cache under the same key
+  /// `run` looks up, replacing any cached entry. Returns false when nothing
+  /// was stored: the cache is off, the query is a changefeed or its
+  /// collection has encrypted fields.
+  pub async fn warm(
+    &self,
+    query: &QueryInput,
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+    ttl: Duration,
+  ) -> Result<bool, anyhow::Error> {
+    let spec = match query {
+      QueryInput::Structured(q) => self.parse_structured(q)?,
+      QueryInput::Script(script) => self.parse_query(script)?,
+    };
+    if !is_cacheable(&spec, backend) || !self.query_cache.is_enabled() {
+      return Ok(false);
     }
 
-    Ok(result)
+    let key = QueryCache::key(project_id, &spec);
+    let generation = self.query_cache.generation(project_id, &spec.table);
+    let data = self.run_spec(&spec, project_id, backend).await?;
+    self
+      .query_cache
+      .put(&key, project_id, &spec.table, generation, data, ttl)
+      .await;
+    Ok(true)
+  }
+
+  /// Warm the query cache with each query in turn. Failures are reported and
+  /// don't stop the rest.
+  pub async fn warm_all(
+    &self,
+    queries: &[WarmupQuery],
+    backend: &dyn DatabaseBackend,
+  ) -> WarmupReport {
+    let mut report = WarmupReport::default();
+    for (index, warmup) in queries.iter().enumerate() {
+      let ttl = warmup
+        .ttl
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_QUERY_CACHE_TTL);
+      let project_id = warmup.project_id.unwrap_or(DEFAULT_PROJECT_ID);
+      match self.warm(&warmup.query, project_id, backend, ttl).await {
+        Ok(true) => report.warmed += 1,
+        Ok(false) => report.skipped += 1,
+        Err(e) => report.errors.push(WarmupError {
+          index,
+          error: e.to_string(),
+        }),
+      }
+    }
+    report
+  }
+
+  /// Limit to fetch for a query: its own, or one past the server cap so a cut
+  /// result can be detected
+  fn fetch_limit(&self, spec: &QuerySpec) -> Option<usize> {
+    spec
+      .limit
+      .or((self.max_result_rows > 0).then(|| self.max_result_rows + 1))
+  }
+
+  /// Cut a result fetched with `fetch_limit` down to the server cap
+  fn finish(&self, spec: &QuerySpec, mut data: serde_json::Value) -> QueryResult {
+    let mut truncated = false;
+    if spec.limit.is_none() && self.max_result_rows > 0 {
+      if let Some(rows) = data.as_array_mut() {
+        if rows.len() > self.max_result_rows {
+          rows.truncate(self.max_result_rows);
+          truncated = true;
+        }
+      }
+    }
+    QueryResult { data, truncated }
+  }
+
+  /// Run a parsed query against the backend
+  async fn run_spec(
+    &self,
+    spec: &QuerySpec,
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+  ) -> Result<serde_json::Value, anyhow::Error> {
+    let sql_filter = spec.filter.as_ref().and_then(|f| f.compiled_sql.as_deref());
+    let params = sql_params(spec)?;
+    backend
+      .field_encryption()
+      .check_query(backend.dialect(), spec)?;
+    // A JS filter needs whole documents, so project after filtering
+    let js_filter = spec.filter.as_ref().filter(|f| f.compiled_sql.is_none());
+    let mut docs = match js_filter {
+      Some(f) => {
+        self
+          .check_cost(spec, &params, None, project_id, backend)
+          .await?;
+        let mut docs = self
+          .js_filtered(spec, &f.js_code, &params, project_id, backend)
+          .await?;
+        if let Some(ref fields) = spec.projection {
+          project_documents(&mut docs, fields)?;
+        }
+        docs
+      }
+      None => {
+        self
+          .check_cost(spec, &params, self.fetch_limit(spec), project_id, backend)
+          .await?;
+        backend
+          .list_with_options(
+            project_id,
+            &spec.table,
+            spec.projection.as_deref(),
+            sql_filter,
+            &params,
+            &spec.order_by,
+            self.fetch_limit(spec),
+            spec.offset,
+            ReadOptions {
+              with_deleted: spec.with_deleted,
+            },
+          )
+          .await?
+      }
+    };
+
+    // JS mapping
+    if let Some(ref m) = spec.map {
+      let engine = self.get();
+      engine.js_map_batch(&docs, m, &spec.params)
+    } else {
+      Ok(serde_json::to_value(&docs)?)
+    }
+  }
+
+  /// Rows passing a JS filter, read in batches until `fetch_limit` matches
+  /// past the offset are found or the collection runs out. The database
+  /// can't apply the limit and offset itself, as it can't tell which rows
+  /// match.
+  async fn js_filtered(
+    &self,
+    spec: &QuerySpec,
+    js_code: &str,
+    params: &[serde_json::Value],
+    project_id: Uuid,
+    backend: &dyn DatabaseBackend,
+  ) -> Result<Vec<Document>, anyhow::Error> {
+    let mut batches = backend
+      .list_stream(
+        project_id,
+        &spec.table,
+        None,
+        None,
+        params,
+        &spec.order_by,
+        None,
+        None,
+        ReadOptions {
+          with_deleted: spec.with_deleted,
+        },
+        JS_FILTER_BATCH_SIZE,
+      )
+      .await?;
+    let mut skip = spec.offset.unwrap_or(0);
+    let mut remaining = self.fetch_limit(spec);
+    let mut docs = Vec::new();
+    while remaining != Some(0) {
+      let Some(batch) = batches.next().await else {
+        break;
+      };
+      let mut matched = self.get().js_filter_batch(&batch?, js_code, &spec.params)?;
+      take_window(&mut matched, &mut skip, &mut remaining);
+      docs.extend(matched);
+    }
+    Ok(docs)
+  }
+
+  /// Parse a structured query into a QuerySpec (no JS evaluation)
+  pub fn parse_structured(&self, query: &StructuredQuery) -> Result<QuerySpec, anyhow::Error> {
+    self.structured_compiler.compile(query)
   }
 
   /// Get pool size.
@@ -240,6 +658,17 @@ impl QueryEnginePool {
   }
 }
 
+/// Whether a query's result may be cached: not a changefeed, and not from a
+/// collection with encrypted fields, whose results are decrypted and would
+/// leave those fields in plaintext in the cache store
+fn is_cacheable(spec: &QuerySpec, backend: &dyn DatabaseBackend) -> bool {
+  spec.changes.is_none()
+    && backend
+      .field_encryption()
+      .encrypted_fields(&spec.table)
+      .is_empty()
+}
+
 impl Default for QueryEnginePool {
   fn default() -> Self {
     Self::with_cache_ttl(num_cpus(), SqlDialect::Postgres, Duration::from_secs(5))
@@ -256,6 +685,8 @@ fn num_cpus() -> usize {
 pub struct QueryEngine {
   runtime: Runtime,
   compiler: QueryCompiler,
+  /// Deepest bracket nesting accepted in a filter (0 = unlimited)
+  max_filter_depth: usize,
 }
 
 impl QueryEngine {
@@ -266,9 +697,16 @@ impl QueryEngine {
     Self {
       runtime,
       compiler: QueryCompiler::new(dialect),
+      max_filter_depth: 0,
     }
   }
 
+  /// Reject filters with brackets nested more than `depth` levels (0 = unlimited)
+  pub fn with_max_filter_depth(mut self, depth: usize) -> Self {
+    self.max_filter_depth = depth;
+    self
+  }
+
   pub fn parse_query(&self, query: &str) -> Result<QuerySpec, anyhow::Error> {
     let ctx = Context::full(&self.runtime)?;
     ctx.with(|ctx| {
@@ -281,25 +719,68 @@ impl QueryEngine {
         .as_str()
         .ok_or_else(|| anyhow::anyhow!("Missing table"))?
         .into();
-      let filter = v["filter"].as_str().map(|js| {
-        let compiled = self.compiler.compile_predicate(js);
-        FilterSpec {
-          js_code: js.into(),
-          compiled_sql: match compiled {
-            CompiledFilter::Sql(s) | CompiledFilter::Hybrid { sql: s, .. } => Some(s),
-            _ => None,
-          },
+      let js_filter = v["filter"].as_str();
+      if let Some(js) = js_filter {
+        if self.max_filter_depth > 0 && filter_depth(js) > self.max_filter_depth {
+          anyhow::bail!(
+            "Filter is nested more than {} levels deep",
+            self.max_filter_depth
+          );
         }
-      });
+      }
+      let mut filter = js_filter.map(|js| self.compiler.compile_filter(js));
+      if let Some(near) = v["near"].as_object() {
+        let field = near["field"]
+          .as_str()
+          .ok_or_else(|| anyhow::anyhow!("near() takes a field name"))?;
+        validate_identifier(field)?;
+        let near: GeoNear = serde_json::from_value(serde_json::Value::Object(near.clone()))
+          .map_err(|_| anyhow::anyhow!("near() takes a field, lat, lng and max distance"))?;
+        let near_sql = self.compiler.dialect().geo_near(field, &near)?;
+        filter = Some(match filter {
+          None => FilterSpec {
+            js_code: String::new(),
+            compiled_sql: Some(near_sql),
+            params: Vec::new(),
+          },
+          Some(FilterSpec {
+            js_code,
+            compiled_sql: Some(sql),
+            params,
+          }) => FilterSpec {
+            js_code,
+            compiled_sql: Some(format!("({}) AND {}", sql, near_sql)),
+            params,
+          },
+          Some(_) => anyhow::bail!("near() needs a filter that can be compiled to SQL"),
+        });
+      }
       let map = v["map"].as_str().map(Into::into);
-      let order_by = v["orderBy"].as_object().map(|o| OrderBySpec {
-        field: o["field"].as_str().unwrap_or("id").into(),
-        direction: if o["direction"].as_str() == Some("desc") {
-          OrderDirection::Desc
-        } else {
-          OrderDirection::Asc
-        },
-      });
+      let order_by = v["orderBy"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|o| -> Result<OrderBySpec, anyhow::Error> {
+          let field = o["field"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("orderBy() takes a field name"))?;
+          validate_identifier(field)?;
+          Ok(OrderBySpec {
+            field: field.into(),
+            direction: if o["direction"].as_str() == Some("desc") {
+              OrderDirection::Desc
+            } else {
+              OrderDirection::Asc
+            },
+            near: o
+              .get("near")
+              .map(|p| serde_json::from_value::<GeoPoint>(p.clone()))
+              .transpose()
+              .map_err(|_| anyhow::anyhow!("orderByDistance() takes a field, lat and lng"))?,
+          })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
       let limit = v["limit"].as_u64().map(|n| n as usize);
       let offset = v["skip"]
         .as_u64()
@@ -308,6 +789,20 @@ impl QueryEngine {
       let changes = v["changes"].is_object().then(|| ChangesOptions {
         include_initial: v["changes"]["includeInitial"].as_bool().unwrap_or(false),
       });
+      let projection = v["select"]
+        .as_array()
+        .map(|fields| {
+          fields
+            .iter()
+            .map(|f| f.as_str().map(String::from))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow::anyhow!("select() takes field names"))
+        })
+        .transpose()?;
+      if let Some(ref fields) = projection {
+        validate_projection(fields)?;
+      }
+      let with_deleted = v["withDeleted"].as_bool().unwrap_or(false);
 
       Ok(QuerySpec {
         project_id: None,
@@ -318,6 +813,9 @@ impl QueryEngine {
         limit,
         offset,
         changes,
+        projection,
+        with_deleted,
+        params: Vec::new(),
       })
     })
   }
@@ -329,21 +827,34 @@ impl QueryEngine {
   ) -> Result<serde_json::Value, anyhow::Error> {
     let spec = self.parse_query(query)?;
     let sql_filter = spec.filter.as_ref().and_then(|f| f.compiled_sql.as_deref());
+    let params = sql_params(&spec)?;
     let project_id = spec.project_id.unwrap_or(DEFAULT_PROJECT_ID);
+    let js_filter = spec.filter.as_ref().filter(|f| f.compiled_sql.is_none());
+    if let Some(f) = js_filter {
+      backend
+        .field_encryption()
+        .check_script(&spec.table, &f.js_code)?;
+    }
     let mut docs = backend
-      .list(
+      .list_with_options(
         project_id,
         &spec.table,
+        spec.projection.as_deref().filter(|_| js_filter.is_none()),
         sql_filter,
-        spec.order_by.as_ref(),
+        &params,
+        &spec.order_by,
         spec.limit,
         spec.offset,
+        ReadOptions {
+          with_deleted: spec.with_deleted,
+        },
       )
       .await?;
 
-    if let Some(ref f) = spec.filter {
-      if f.compiled_sql.is_none() {
-        docs = self.js_filter(&docs, &f.js_code)?;
+    if let Some(f) = js_filter {
+      docs = self.js_filter(&docs, &f.js_code)?;
+      if let Some(ref fields) = spec.projection {
+        project_documents(&mut docs, fields)?;
       }
     }
     if let Some(ref m) = spec.map {
@@ -354,7 +865,7 @@ impl QueryEngine {
   }
 
   fn js_filter(&self, docs: &[Document], code: &str) -> Result<Vec<Document>, anyhow::Error> {
-    self.js_filter_batch(docs, code)
+    self.js_filter_batch(docs, code, &[])
   }
 
   /// Batch filter: compile the function once, call for each document.
@@ -365,10 +876,13 @@ impl QueryEngine {
   /// - `$created_at`: creation timestamp
   /// - `$updated_at`: update timestamp
   /// - All fields from `data` are accessible directly (e.g., `r.username`)
+  ///
+  /// `params` are available to the function as `$1`, `$2`, ...
   pub fn js_filter_batch(
     &self,
     docs: &[Document],
     code: &str,
+    params: &[serde_json::Value],
   ) -> Result<Vec<Document>, anyhow::Error> {
     if docs.is_empty() {
       return Ok(Vec::new());
@@ -376,6 +890,7 @@ impl QueryEngine {
 
     let ctx = Context::full(&self.runtime)?;
     ctx.with(|ctx| {
+      ctx.eval::<(), _>(declare_params(params)?)?;
       // Compile the filter function once
       let filter_fn: Function = ctx.eval(format!("({})", code))?;
       let json_parse: Function = ctx.eval("JSON.parse")?;
@@ -418,7 +933,7 @@ impl QueryEngine {
   }
 
   fn js_map(&self, docs: &[Document], code: &str) -> Result<serde_json::Value, anyhow::Error> {
-    self.js_map_batch(docs, code)
+    self.js_map_batch(docs, code, &[])
   }
 
   /// Batch map: compile the function once, call for each document.
@@ -429,10 +944,13 @@ impl QueryEngine {
   /// - `$created_at`: creation timestamp
   /// - `$updated_at`: update timestamp
   /// - All fields from `data` are accessible directly (e.g., `r.username`)
+  ///
+  /// `params` are available to the function as `$1`, `$2`, ...
   pub fn js_map_batch(
     &self,
     docs: &[Document],
     code: &str,
+    params: &[serde_json::Value],
   ) -> Result<serde_json::Value, anyhow::Error> {
     if docs.is_empty() {
       return Ok(serde_json::Value::Array(Vec::new()));
@@ -440,6 +958,7 @@ impl QueryEngine {
 
     let ctx = Context::full(&self.runtime)?;
     ctx.with(|ctx| {
+      ctx.eval::<(), _>(declare_params(params)?)?;
       // Compile the map function once
       let map_fn: Function = ctx.eval(format!("({})", code))?;
       let json_parse: Function = ctx.eval("JSON.parse")?;
@@ -460,6 +979,84 @@ impl QueryEngine {
   }
 }
 
+/// Values to bind for a query's SQL filter (none when it runs in JS)
+fn sql_params(spec: &QuerySpec) -> Result<Vec<serde_json::Value>, anyhow::Error> {
+  match spec.filter.as_ref().filter(|f| f.compiled_sql.is_some()) {
+    Some(filter) => bind_params(filter, &spec.params),
+    None => Ok(Vec::new()),
+  }
+}
+
+/// Document fields a compiled filter reads, in order of first use: the
+/// paths of `data->'a'->>'b'` (PostgreSQL) and `'$.a.b'` (SQLite)
+fn filter_fields(sql: &str) -> Vec<String> {
+  let mut fields: Vec<String> = Vec::new();
+  let mut push = |field: String| {
+    if !field.is_empty() && !fields.contains(&field) {
+      fields.push(field);
+    }
+  };
+  for (start, _) in sql.match_indices("'$.") {
+    let rest = &sql[start + 3..];
+    push(rest[..rest.find('\'').unwrap_or(rest.len())].to_string());
+  }
+  for (start, _) in sql.match_indices("data->") {
+    let mut rest = &sql[start + 4..];
+    let mut path = Vec::new();
+    while let Some(after) = rest
+      .strip_prefix("->>'")
+      .or_else(|| rest.strip_prefix("->'"))
+    {
+      let Some(end) = after.find('\'') else {
+        break;
+      };
+      path.push(&after[..end]);
+      rest = &after[end + 1..];
+    }
+    push(path.join("."));
+  }
+  fields
+}
+
+/// JS declaring query parameters as `$1`, `$2`, ... (JSON is valid JS)
+fn declare_params(params: &[serde_json::Value]) -> Result<String, anyhow::Error> {
+  let mut js = String::new();
+  for (i, value) in params.iter().enumerate() {
+    js.push_str(&format!(
+      "var ${} = {};\n",
+      i + 1,
+      serde_json::to_string(value)?
+    ));
+  }
+  Ok(js)
+}
+
+/// Drop the first `skip` of a batch of matches and keep at most `remaining`
+/// of the rest, counting both down for the batches that follow
+fn take_window(docs: &mut Vec<Document>, skip: &mut usize, remaining: &mut Option<usize>) {
+  let skipped = (*skip).min(docs.len());
+  docs.drain(..skipped);
+  *skip -= skipped;
+  if let Some(remaining) = remaining {
+    docs.truncate(*remaining);
+    *remaining -= docs.len();
+  }
+}
+
+/// Reduce documents to the projected fields (the same shape
+/// `SqlDialect::json_projection` builds in the database)
+fn project_documents(docs: &mut [Document], fields: &[String]) -> Result<(), anyhow::Error> {
+  let fields = parse_projection(fields)?;
+  for doc in docs {
+    let data: serde_json::Map<String, serde_json::Value> = fields
+      .iter()
+      .map(|field| (field.name.clone(), field.eval(&doc.data)))
+      .collect();
+    doc.data = data.into();
+  }
+  Ok(())
+}
+
 impl Default for QueryEngine {
   fn default() -> Self {
     Self::new(SqlDialect::Postgres)
@@ -468,17 +1065,21 @@ impl Default for QueryEngine {
 
 const QUERY_BUILDER_JS: &str = r#"
 class QueryBuilder {
-  constructor() { this._table = null; this._filter = null; this._map = null; this._orderBy = null; this._limit = null; this._skip = null; this._changes = null; }
+  constructor() { this._table = null; this._filter = null; this._map = null; this._orderBy = []; this._limit = null; this._skip = null; this._changes = null; this._select = null; this._near = null; this._withDeleted = false; }
   table(n) { this._table = n; return this; }
   filter(fn) { this._filter = fn.toString(); return this; }
   map(fn) { this._map = fn.toString(); return this; }
-  orderBy(f, d) { this._orderBy = { field: f, direction: d || 'asc' }; return this; }
+  orderBy(f, d) { this._orderBy.push({ field: f, direction: d || 'asc' }); return this; }
+  near(f, lat, lng, maxDistance) { this._near = { field: f, lat: lat, lng: lng, maxDistance: maxDistance }; return this; }
+  orderByDistance(f, lat, lng, d) { this._orderBy.push({ field: f, direction: d || 'asc', near: { lat: lat, lng: lng } }); return this; }
   limit(n) { this._limit = n; return this; }
   skip(n) { this._skip = n; return this; }
   offset(n) { this._skip = n; return this; }
   changes(o) { this._changes = o || {}; return this; }
+  select(...f) { this._select = f.flat(); return this; }
+  withDeleted() { this._withDeleted = true; return this; }
   run() { return this; }
-  toJSON() { return { table: this._table, filter: this._filter, map: this._map, orderBy: this._orderBy, limit: this._limit, skip: this._skip, changes: this._changes }; }
+  toJSON() { return { table: this._table, filter: this._filter, map: this._map, orderBy: this._orderBy, limit: this._limit, skip: this._skip, changes: this._changes, select: this._select, near: this._near, withDeleted: this._withDeleted }; }
 }
 const db = { table: (n) => new QueryBuilder().table(n), tableCreate: (n) => ({ _action: 'createTable', table: n, run: function() { return this; }, toJSON: function() { return this; } }), tableDrop: (n) => ({ _action: 'dropTable', table: n, run: function() { return this; }, toJSON: function() { return this; } }) };
 "#;
@@ -1,7 +1,13 @@
+mod cache;
 mod compiler;
 mod engine;
+mod schema;
 mod structured;
 
-pub use compiler::QueryCompiler;
-pub use engine::{QueryEngine, QueryEnginePool};
+pub use cache::{
+  CacheGeneration, QueryCache, WarmupError, WarmupQuery, WarmupReport, DEFAULT_QUERY_CACHE_TTL,
+};
+pub use compiler::{bind_params, filter_depth, QueryCompiler};
+pub use engine::{QueryEngine, QueryEnginePool, QueryResult};
+pub use schema::{FieldSchema, FieldType, ResultSchema, SCHEMA_SAMPLE_SIZE};
 pub use structured::StructuredCompiler;
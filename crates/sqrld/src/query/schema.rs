@@ -0,0 +1,103 @@
+//! Field metadata inferred from query results, so generic clients can render
+//! consistent columns even when some documents omit fields.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Document;
+
+/// Documents inspected when inferring a result schema
+pub const SCHEMA_SAMPLE_SIZE: usize = 1000;
+
+/// JSON type of a field value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+  String,
+  Integer,
+  Number,
+  Boolean,
+  Object,
+  Array,
+}
+
+impl FieldType {
+  /// Type of a non-null value
+  fn of(value: &serde_json::Value) -> Option<Self> {
+    use serde_json::Value;
+    match value {
+      Value::Null => None,
+      Value::Bool(_) => Some(Self::Boolean),
+      Value::Number(n) if n.is_i64() || n.is_u64() => Some(Self::Integer),
+      Value::Number(_) => Some(Self::Number),
+      Value::String(_) => Some(Self::String),
+      Value::Array(_) => Some(Self::Array),
+      Value::Object(_) => Some(Self::Object),
+    }
+  }
+}
+
+/// A top-level document field seen in the sample
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldSchema {
+  pub name: String,
+  /// Types the field was seen with, in order of first appearance
+  pub types: Vec<FieldType>,
+  /// Missing or null in at least one sampled document
+  pub nullable: bool,
+  /// Sampled documents with a non-null value for the field
+  pub count: usize,
+}
+
+/// Inferred schema of a query result
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResultSchema {
+  /// Documents the schema was inferred from
+  pub sampled: usize,
+  /// Fields in order of first appearance
+  pub fields: Vec<FieldSchema>,
+}
+
+impl ResultSchema {
+  /// Infer field names, types and nullability from the first
+  /// `SCHEMA_SAMPLE_SIZE` documents
+  pub fn infer(docs: &[Document]) -> Self {
+    let sample = &docs[..docs.len().min(SCHEMA_SAMPLE_SIZE)];
+    let mut fields: Vec<FieldSchema> = Vec::new();
+    for doc in sample {
+      let Some(obj) = doc.data.as_object() else {
+        continue;
+      };
+      for (name, value) in obj {
+        let index = match fields.iter().position(|f| &f.name == name) {
+          Some(i) => i,
+          None => {
+            fields.push(FieldSchema {
+              name: name.clone(),
+              types: Vec::new(),
+              nullable: false,
+              count: 0,
+            });
+            fields.len() - 1
+          }
+        };
+        let field = &mut fields[index];
+        match FieldType::of(value) {
+          Some(ty) => {
+            field.count += 1;
+            if !field.types.contains(&ty) {
+              field.types.push(ty);
+            }
+          }
+          None => field.nullable = true,
+        }
+      }
+    }
+    for field in &mut fields {
+      field.nullable |= field.count < sample.len();
+    }
+    Self {
+      sampled: sample.len(),
+      fields,
+    }
+  }
+}
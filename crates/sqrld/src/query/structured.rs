@@ -1,4 +1,6 @@
-use crate::db::sanitize::{escape_string, validate_identifier, validate_numeric};
+use crate::db::sanitize::{
+  escape_string, validate_identifier, validate_numeric, validate_projection,
+};
 use crate::db::SqlDialect;
 use crate::types::{
   ChangesOptions, FieldCondition, FilterOperator, FilterSpec, LogicalFilter, OrderBySpec,
@@ -8,11 +10,22 @@ use crate::types::{
 /// Compiler for structured queries (no JS evaluation needed)
 pub struct StructuredCompiler {
   dialect: SqlDialect,
+  /// Deepest nesting of logical operators accepted in a filter (0 = unlimited)
+  max_depth: usize,
 }
 
 impl StructuredCompiler {
   pub fn new(dialect: SqlDialect) -> Self {
-    Self { dialect }
+    Self {
+      dialect,
+      max_depth: 0,
+    }
+  }
+
+  /// Reject filters with logical operators nested more than `depth` levels (0 = unlimited)
+  pub fn with_max_depth(mut self, depth: usize) -> Self {
+    self.max_depth = depth;
+    self
   }
 
   /// Convert a StructuredQuery to a QuerySpec
@@ -23,20 +36,20 @@ impl StructuredCompiler {
       .map(|f| self.compile_filter(f))
       .transpose()?;
 
-    let order_by = query.sort.as_ref().and_then(|specs| {
-      specs.first().map(|s| OrderBySpec {
-        field: s.field.clone(),
-        direction: match s.direction {
-          StructuredSortDirection::Asc => OrderDirection::Asc,
-          StructuredSortDirection::Desc => OrderDirection::Desc,
-        },
-      })
-    });
+    let order_by = query
+      .sort
+      .as_deref()
+      .map(sort_specs_to_order_by)
+      .unwrap_or_default();
 
     let changes = query.changes.as_ref().map(|c| ChangesOptions {
       include_initial: c.include_initial,
     });
 
+    if let Some(fields) = &query.select {
+      validate_projection(fields)?;
+    }
+
     Ok(QuerySpec {
       project_id: None,
       table: query.table.clone(),
@@ -46,6 +59,9 @@ impl StructuredCompiler {
       limit: query.limit,
       offset: query.skip,
       changes,
+      projection: query.select.clone(),
+      with_deleted: query.with_deleted,
+      params: Vec::new(),
     })
   }
 
@@ -55,13 +71,23 @@ impl StructuredCompiler {
     Ok(FilterSpec {
       js_code: String::new(),
       compiled_sql: Some(sql),
+      params: Vec::new(),
     })
   }
 
   /// Convert a StructuredFilter to SQL WHERE clause
   fn filter_to_sql(&self, filter: &StructuredFilter) -> Result<String, anyhow::Error> {
+    self.nested_filter_to_sql(filter, 0)
+  }
+
+  /// `depth` is the number of logical operators around `filter`
+  fn nested_filter_to_sql(
+    &self,
+    filter: &StructuredFilter,
+    depth: usize,
+  ) -> Result<String, anyhow::Error> {
     match filter {
-      StructuredFilter::Logical(logical) => self.logical_to_sql(logical),
+      StructuredFilter::Logical(logical) => self.logical_to_sql(logical, depth + 1),
       StructuredFilter::Fields(fields) => {
         let parts: Result<Vec<String>, _> = fields
           .iter()
@@ -80,20 +106,29 @@ impl StructuredCompiler {
   }
 
   /// Convert logical operators to SQL
-  fn logical_to_sql(&self, logical: &LogicalFilter) -> Result<String, anyhow::Error> {
+  fn logical_to_sql(&self, logical: &LogicalFilter, depth: usize) -> Result<String, anyhow::Error> {
+    if self.max_depth > 0 && depth > self.max_depth {
+      anyhow::bail!("Filter is nested more than {} levels deep", self.max_depth);
+    }
     match logical {
       LogicalFilter::And(filters) => {
-        let parts: Result<Vec<String>, _> = filters.iter().map(|f| self.filter_to_sql(f)).collect();
+        let parts: Result<Vec<String>, _> = filters
+          .iter()
+          .map(|f| self.nested_filter_to_sql(f, depth))
+          .collect();
         let parts = parts?;
         Ok(format!("({})", parts.join(" AND ")))
       }
       LogicalFilter::Or(filters) => {
-        let parts: Result<Vec<String>, _> = filters.iter().map(|f| self.filter_to_sql(f)).collect();
+        let parts: Result<Vec<String>, _> = filters
+          .iter()
+          .map(|f| self.nested_filter_to_sql(f, depth))
+          .collect();
         let parts = parts?;
         Ok(format!("({})", parts.join(" OR ")))
       }
       LogicalFilter::Not(filter) => {
-        let inner = self.filter_to_sql(filter)?;
+        let inner = self.nested_filter_to_sql(filter, depth)?;
         Ok(format!("NOT ({})", inner))
       }
     }
@@ -128,6 +163,7 @@ impl StructuredCompiler {
       FilterOperator::StartsWith(s) => self.like_sql(field, s, "", "%"),
       FilterOperator::EndsWith(s) => self.like_sql(field, s, "%", ""),
       FilterOperator::Exists(exists) => self.exists_sql(field, *exists),
+      FilterOperator::Near(near) => self.dialect.geo_near(field, near),
     }
   }
 
@@ -285,16 +321,19 @@ impl Default for StructuredCompiler {
   }
 }
 
-/// Convert a list of SortSpec to the first OrderBySpec (for compatibility)
-#[allow(dead_code)]
-pub fn sort_specs_to_order_by(specs: &[SortSpec]) -> Option<OrderBySpec> {
-  specs.first().map(|s| OrderBySpec {
-    field: s.field.clone(),
-    direction: match s.direction {
-      StructuredSortDirection::Asc => OrderDirection::Asc,
-      StructuredSortDirection::Desc => OrderDirection::Desc,
-    },
-  })
+/// Convert a list of SortSpec to the OrderBySpecs of a query
+pub fn sort_specs_to_order_by(specs: &[SortSpec]) -> Vec<OrderBySpec> {
+  specs
+    .iter()
+    .map(|s| OrderBySpec {
+      field: s.field.clone(),
+      direction: match s.direction {
+        StructuredSortDirection::Asc => OrderDirection::Asc,
+        StructuredSortDirection::Desc => OrderDirection::Desc,
+      },
+      near: s.near,
+    })
+    .collect()
 }
 
 #[cfg(test)]
@@ -424,10 +463,13 @@ mod tests {
       sort: Some(vec![SortSpec {
         field: "name".to_string(),
         direction: StructuredSortDirection::Asc,
+        near: None,
       }]),
       limit: Some(10),
       skip: Some(5),
       changes: None,
+      select: Some(vec!["name".to_string(), "email".to_string()]),
+      with_deleted: false,
     };
 
     let spec = compiler.compile(&query).unwrap();
@@ -436,7 +478,98 @@ mod tests {
     assert!(spec.filter.as_ref().unwrap().compiled_sql.is_some());
     assert_eq!(spec.limit, Some(10));
     assert_eq!(spec.offset, Some(5));
-    assert!(spec.order_by.is_some());
-    assert_eq!(spec.order_by.as_ref().unwrap().field, "name");
+    assert_eq!(spec.order_by.len(), 1);
+    assert_eq!(spec.order_by[0].field, "name");
+    assert_eq!(
+      spec.projection,
+      Some(vec!["name".to_string(), "email".to_string()])
+    );
+  }
+
+  #[test]
+  fn compile_rejects_invalid_select() {
+    let compiler = pg_compiler();
+    let query: StructuredQuery =
+      serde_json::from_str(r#"{"table": "users", "select": ["name", "x') || ('"]}"#).unwrap();
+    assert!(compiler.compile(&query).is_err());
+  }
+
+  #[test]
+  fn compile_filter_from_json_escapes_values() {
+    // findOne takes the filter as a JSON query parameter
+    let compiler = sqlite_compiler();
+    let filter: StructuredFilter = serde_json::from_str(r#"{"email": "x' OR '1'='1"}"#).unwrap();
+    let sql = compiler.filter_to_sql(&filter).unwrap();
+    assert!(sql.ends_with("= 'x'' OR ''1''=''1'"));
+
+    let filter: StructuredFilter = serde_json::from_str(r#"{"a') OR 1=1 --": 1}"#).unwrap();
+    assert!(compiler.filter_to_sql(&filter).is_err());
+  }
+
+  #[test]
+  fn compile_near_filter_and_distance_sort() {
+    let query: StructuredQuery = serde_json::from_str(
+      r#"{
+        "table": "users",
+        "filter": {"location": {"$near": {"lat": 40.7, "lng": -74.0, "maxDistance": 5000}}},
+        "sort": [{"field": "location", "near": {"lat": 40.7, "lng": -74.0}}]
+      }"#,
+    )
+    .unwrap();
+
+    let spec = pg_compiler().compile(&query).unwrap();
+    let sql = spec.filter.unwrap().compiled_sql.unwrap();
+    assert!(sql.contains("asin(least(sqrt("));
+    assert!(sql.contains("(data->'location'->'lat')::numeric"));
+    assert!(sql.ends_with("<= 5000"));
+    assert!(spec.order_by[0].near.is_some());
+
+    let spec = sqlite_compiler().compile(&query).unwrap();
+    let sql = spec.filter.unwrap().compiled_sql.unwrap();
+    assert!(sql.contains("asin(min(sqrt("));
+    assert!(sql.contains("json_extract(data, '$.location.lng')"));
+  }
+
+  #[test]
+  fn compile_near_rejects_invalid_points() {
+    let compiler = pg_compiler();
+    for filter in [
+      r#"{"location": {"$near": {"lat": 95, "lng": 0, "maxDistance": 10}}}"#,
+      r#"{"location": {"$near": {"lat": 0, "lng": 0, "maxDistance": -10}}}"#,
+    ] {
+      let filter: StructuredFilter = serde_json::from_str(filter).unwrap();
+      assert!(compiler.filter_to_sql(&filter).is_err());
+    }
+  }
+
+  #[test]
+  fn compile_rejects_deeply_nested_filter() {
+    let compiler = pg_compiler().with_max_depth(3);
+    let filter: StructuredFilter =
+      serde_json::from_str(r#"{"$and": [{"$or": [{"$not": {"a": 1}}, {"b": 2}]}]}"#).unwrap();
+    assert!(compiler.filter_to_sql(&filter).is_ok());
+
+    let filter: StructuredFilter =
+      serde_json::from_str(r#"{"$and": [{"$or": [{"$not": {"$not": {"a": 1}}}, {"b": 2}]}]}"#)
+        .unwrap();
+    let err = compiler.filter_to_sql(&filter).unwrap_err();
+    assert_eq!(err.to_string(), "Filter is nested more than 3 levels deep");
+
+    let mut filter = StructuredFilter::Fields(HashMap::new());
+    for _ in 0..200 {
+      filter = StructuredFilter::Logical(LogicalFilter::Not(Box::new(filter)));
+    }
+    let query = StructuredQuery {
+      table: "users".into(),
+      filter: Some(filter),
+      sort: None,
+      limit: None,
+      skip: None,
+      changes: None,
+      select: None,
+      with_deleted: false,
+    };
+    assert!(pg_compiler().with_max_depth(32).compile(&query).is_err());
+    assert!(pg_compiler().compile(&query).is_ok());
   }
 }
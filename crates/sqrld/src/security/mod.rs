@@ -112,25 +112,70 @@ impl std::fmt::Display for ObjectKeyError {
 
 impl std::error::Error for ObjectKeyError {}
 
+/// Content-Security-Policy sent unless configured otherwise. Permissive
+/// enough for the admin UI's WASM bundle, and forbids framing.
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; script-src 'self' 'unsafe-inline' 'unsafe-eval' 'wasm-unsafe-eval'; style-src 'self' 'unsafe-inline'; img-src 'self' data: blob:; connect-src 'self' ws: wss:; font-src 'self' data:; object-src 'none'; frame-ancestors 'none';";
+
+/// X-Frame-Options sent unless configured otherwise
+pub const DEFAULT_FRAME_OPTIONS: &str = "DENY";
+
 /// Security headers middleware for HTTP responses.
 /// Adds standard security headers to all responses.
 #[cfg(feature = "server")]
 pub mod headers {
+  use axum::http::header::InvalidHeaderValue;
   use axum::http::{header, HeaderValue, Request, Response};
   use std::future::Future;
   use std::pin::Pin;
   use std::task::{Context, Poll};
   use tower::{Layer, Service};
 
+  use super::{DEFAULT_CONTENT_SECURITY_POLICY, DEFAULT_FRAME_OPTIONS};
+
   /// Layer that adds security headers to all responses
-  #[derive(Clone, Default)]
-  pub struct SecurityHeadersLayer;
+  #[derive(Clone)]
+  pub struct SecurityHeadersLayer {
+    content_security_policy: Option<HeaderValue>,
+    frame_options: Option<HeaderValue>,
+  }
+
+  impl Default for SecurityHeadersLayer {
+    fn default() -> Self {
+      Self {
+        content_security_policy: Some(HeaderValue::from_static(DEFAULT_CONTENT_SECURITY_POLICY)),
+        frame_options: Some(HeaderValue::from_static(DEFAULT_FRAME_OPTIONS)),
+      }
+    }
+  }
+
+  impl SecurityHeadersLayer {
+    /// Use the given Content-Security-Policy and X-Frame-Options values; an
+    /// empty value leaves that header out
+    pub fn new(
+      content_security_policy: &str,
+      frame_options: &str,
+    ) -> Result<Self, InvalidHeaderValue> {
+      let value = |v: &str| {
+        (!v.trim().is_empty())
+          .then(|| HeaderValue::from_str(v.trim()))
+          .transpose()
+      };
+      Ok(Self {
+        content_security_policy: value(content_security_policy)?,
+        frame_options: value(frame_options)?,
+      })
+    }
+  }
 
   impl<S> Layer<S> for SecurityHeadersLayer {
     type Service = SecurityHeadersService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-      SecurityHeadersService { inner }
+      SecurityHeadersService {
+        inner,
+        content_security_policy: self.content_security_policy.clone(),
+        frame_options: self.frame_options.clone(),
+      }
     }
   }
 
@@ -138,6 +183,8 @@ pub mod headers {
   #[derive(Clone)]
   pub struct SecurityHeadersService<S> {
     inner: S,
+    content_security_policy: Option<HeaderValue>,
+    frame_options: Option<HeaderValue>,
   }
 
   impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SecurityHeadersService<S>
@@ -157,6 +204,8 @@ pub mod headers {
 
     fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
       let mut inner = self.inner.clone();
+      let content_security_policy = self.content_security_policy.clone();
+      let frame_options = self.frame_options.clone();
       Box::pin(async move {
         let mut response = inner.call(req).await?;
         let headers = response.headers_mut();
@@ -168,7 +217,9 @@ pub mod headers {
         );
 
         // Prevent clickjacking
-        headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+        if let Some(frame_options) = frame_options {
+          headers.insert(header::X_FRAME_OPTIONS, frame_options);
+        }
 
         // XSS protection (legacy, but still useful for older browsers)
         headers.insert(
@@ -183,12 +234,9 @@ pub mod headers {
         );
 
         // Content Security Policy (permissive for admin UI)
-        headers.insert(
-          header::CONTENT_SECURITY_POLICY,
-          HeaderValue::from_static(
-            "default-src 'self'; script-src 'self' 'unsafe-inline' 'unsafe-eval' 'wasm-unsafe-eval'; style-src 'self' 'unsafe-inline'; img-src 'self' data: blob:; connect-src 'self' ws: wss:; font-src 'self' data:; object-src 'none'; frame-ancestors 'none';",
-          ),
-        );
+        if let Some(csp) = content_security_policy {
+          headers.insert(header::CONTENT_SECURITY_POLICY, csp);
+        }
 
         // Permissions policy (formerly Feature-Policy)
         headers.insert(
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+
+use crate::db::INTERNAL_TABLE_NAMES;
+use crate::query::WarmupQuery;
+use crate::types::{ServerInfo, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
 
 /// Expand environment variables in a string.
 /// Supports $VAR_NAME and ${VAR_NAME} syntax.
@@ -52,10 +56,22 @@ pub enum BackendType {
   #[default]
   Postgres,
   Sqlite,
+  /// SQLite on an in-memory database; nothing survives a restart
+  Memory,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServerConfig {
+  /// Base directory for data files. Relative paths elsewhere in the config
+  /// (SQLite database, object storage, cache snapshot, local backups, seed
+  /// file) are resolved against it; absolute paths are used as given.
+  /// Defaults to the working directory.
+  #[serde(default)]
+  pub data_dir: String,
+  /// Archive or NDJSON file loaded at startup when the database holds no
+  /// documents (relative to `data_dir`)
+  #[serde(default)]
+  pub seed_file: Option<String>,
   #[serde(default)]
   pub server: ServerSection,
   #[serde(default)]
@@ -78,6 +94,23 @@ pub struct ServerConfig {
   pub caching: CachingSection,
   #[serde(default)]
   pub backup: BackupSection,
+  #[serde(default)]
+  pub encryption: EncryptionSection,
+  #[serde(default)]
+  pub change_queue: ChangeQueueSection,
+  #[serde(default)]
+  pub collections: CollectionsSection,
+}
+
+/// Field-level encryption of sensitive document fields
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionSection {
+  /// Hex-encoded 256-bit key; encrypted fields use AES-256-GCM
+  #[serde(default)]
+  pub key: Option<String>,
+  /// Collection name -> dotted paths of the fields to encrypt
+  #[serde(default)]
+  pub collections: HashMap<String, Vec<String>>,
 }
 
 /// Feature toggle configuration
@@ -92,6 +125,9 @@ pub struct FeaturesSection {
   /// Enable automatic database backups
   #[serde(default)]
   pub backup: bool,
+  /// Enable outbound webhook notifications
+  #[serde(default)]
+  pub webhooks: bool,
 }
 
 /// Object storage configuration
@@ -186,6 +222,30 @@ pub struct CachingSection {
   /// Snapshot configuration
   #[serde(default)]
   pub snapshot: CacheSnapshotSection,
+
+  /// Testing aids (off by default; not for production)
+  #[serde(default)]
+  pub debug: CacheDebugSection,
+
+  /// Queries run and cached whenever the cache starts
+  #[serde(default)]
+  pub warmup: Vec<WarmupQuery>,
+}
+
+/// Cache testing aids for exercising client timeouts and retries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheDebugSection {
+  /// Accept DEBUG commands such as `DEBUG SLEEP seconds`
+  #[serde(default)]
+  pub commands: bool,
+
+  /// Artificial delay added before a command is executed, in milliseconds
+  #[serde(default)]
+  pub latency_ms: u64,
+
+  /// Percentage of commands (0-100) that get the artificial delay
+  #[serde(default)]
+  pub latency_percent: f64,
 }
 
 /// Cache snapshot persistence configuration
@@ -232,6 +292,8 @@ impl Default for CachingSection {
       eviction: default_cache_eviction(),
       default_ttl: 0,
       snapshot: CacheSnapshotSection::default(),
+      debug: CacheDebugSection::default(),
+      warmup: Vec::new(),
     }
   }
 }
@@ -243,6 +305,10 @@ pub struct BackupSection {
   #[serde(default = "default_backup_interval")]
   pub interval: u64,
 
+  /// Cron expression for automatic backups, in UTC (overrides interval when set)
+  #[serde(default)]
+  pub schedule: Option<String>,
+
   /// Maximum number of backups to retain (default: 7)
   #[serde(default = "default_backup_retention")]
   pub retention: u32,
@@ -251,9 +317,39 @@ pub struct BackupSection {
   #[serde(default = "default_backup_path")]
   pub local_path: String,
 
+  /// Storage bucket for backups (used when storage is enabled)
+  #[serde(default = "default_backup_storage_bucket")]
+  pub storage_bucket: String,
+
   /// Storage bucket path prefix for backups (used when storage is enabled)
   #[serde(default = "default_backup_storage_path")]
   pub storage_path: String,
+
+  /// Write incremental backups from the change queue between full baselines
+  #[serde(default)]
+  pub incremental: bool,
+
+  /// Take a full baseline every N backups when incremental (default: 24)
+  #[serde(default = "default_backup_full_every")]
+  pub full_every: u32,
+
+  /// Compression applied to backup files (default: none)
+  #[serde(default)]
+  pub compression: BackupCompression,
+
+  /// Hex-encoded 256-bit key; when set, backup files are encrypted with AES-256-GCM
+  #[serde(default)]
+  pub encryption_key: Option<String>,
+}
+
+/// Compression applied to backup files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupCompression {
+  #[default]
+  None,
+  Gzip,
+  Zstd,
 }
 
 fn default_backup_interval() -> u64 {
@@ -268,17 +364,31 @@ fn default_backup_path() -> String {
   "./backup".to_string()
 }
 
+fn default_backup_storage_bucket() -> String {
+  "backups".to_string()
+}
+
 fn default_backup_storage_path() -> String {
   "backups".to_string()
 }
 
+fn default_backup_full_every() -> u32 {
+  24
+}
+
 impl Default for BackupSection {
   fn default() -> Self {
     Self {
       interval: default_backup_interval(),
+      schedule: None,
       retention: default_backup_retention(),
       local_path: default_backup_path(),
+      storage_bucket: default_backup_storage_bucket(),
       storage_path: default_backup_storage_path(),
+      incremental: false,
+      full_every: default_backup_full_every(),
+      compression: BackupCompression::None,
+      encryption_key: None,
     }
   }
 }
@@ -295,9 +405,115 @@ pub struct ServerSection {
   /// Use ["*"] for permissive mode, or specify origins like ["http://localhost:3000"]
   #[serde(default)]
   pub cors_origins: Vec<String>,
+  /// Reverse proxies whose `X-Forwarded-For`/`X-Real-IP` headers are
+  /// trusted for the client address. Other peers are identified by their
+  /// socket address.
+  #[serde(default)]
+  pub trusted_proxies: Vec<std::net::IpAddr>,
   /// Enable admin UI (default: true)
   #[serde(default = "default_true")]
   pub admin: bool,
+  /// TLS for the WebSocket, TCP and admin/REST listeners
+  #[serde(default)]
+  pub tls: TlsSection,
+  /// Owner-only raw SQL endpoint (`POST /api/sql`)
+  #[serde(default)]
+  pub raw_sql: RawSqlSection,
+  /// Security headers on admin UI and REST API responses
+  #[serde(default)]
+  pub security_headers: SecurityHeadersSection,
+  /// Server identity sent to WebSocket and TCP clients on connect
+  #[serde(default)]
+  pub banner: BannerSection,
+}
+
+/// Connection banner announcing the server name, version and protocols
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BannerSection {
+  #[serde(default = "default_true")]
+  pub enabled: bool,
+  /// Greeting included in the banner
+  #[serde(default)]
+  pub message: Option<String>,
+}
+
+impl Default for BannerSection {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      message: None,
+    }
+  }
+}
+
+/// Security headers for the admin UI and REST API. The defaults forbid
+/// framing; relax them to embed the admin UI in another site.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityHeadersSection {
+  /// Content-Security-Policy value ("" = don't send the header)
+  #[serde(default = "default_content_security_policy")]
+  pub content_security_policy: String,
+  /// X-Frame-Options value, e.g. DENY or SAMEORIGIN ("" = don't send the
+  /// header)
+  #[serde(default = "default_frame_options")]
+  pub frame_options: String,
+}
+
+fn default_content_security_policy() -> String {
+  crate::security::DEFAULT_CONTENT_SECURITY_POLICY.to_string()
+}
+
+fn default_frame_options() -> String {
+  crate::security::DEFAULT_FRAME_OPTIONS.to_string()
+}
+
+impl Default for SecurityHeadersSection {
+  fn default() -> Self {
+    Self {
+      content_security_policy: default_content_security_policy(),
+      frame_options: default_frame_options(),
+    }
+  }
+}
+
+/// Raw SQL escape hatch for maintenance statements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawSqlSection {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Accept several `;`-separated statements (run without parameters)
+  #[serde(default)]
+  pub allow_multiple_statements: bool,
+  /// Statements each owner may run per minute (0 = unlimited)
+  #[serde(default = "default_raw_sql_per_minute")]
+  pub requests_per_minute: u32,
+}
+
+fn default_raw_sql_per_minute() -> u32 {
+  10
+}
+
+impl Default for RawSqlSection {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      allow_multiple_statements: false,
+      requests_per_minute: default_raw_sql_per_minute(),
+    }
+  }
+}
+
+/// TLS termination configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsSection {
+  #[serde(default)]
+  pub enabled: bool,
+  /// PEM certificate chain
+  #[serde(default)]
+  pub cert_path: String,
+  /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+  #[serde(default)]
+  pub key_path: String,
 }
 
 fn default_host() -> String {
@@ -311,7 +527,12 @@ impl Default for ServerSection {
       ports: PortsSection::default(),
       protocols: ProtocolsSection::default(),
       cors_origins: vec!["*".to_string()], // Permissive by default for development
+      trusted_proxies: Vec::new(),
       admin: true,
+      tls: TlsSection::default(),
+      raw_sql: RawSqlSection::default(),
+      security_headers: SecurityHeadersSection::default(),
+      banner: BannerSection::default(),
     }
   }
 }
@@ -382,12 +603,219 @@ impl Default for ProtocolsSection {
   }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthSection {
   #[serde(default)]
   pub enabled: bool,
   #[serde(default)]
   pub admin_token: Option<String>,
+  /// Require TCP clients to authenticate even when `enabled` is false
+  #[serde(default)]
+  pub tcp_require_auth: bool,
+  #[serde(default)]
+  pub password_hashing: PasswordHashingSection,
+  /// Seconds a password reset token stays valid
+  #[serde(default = "default_reset_token_ttl_secs")]
+  pub reset_token_ttl_secs: u64,
+  /// Seconds before another password reset can be requested for the same
+  /// user
+  #[serde(default = "default_reset_cooldown_secs")]
+  pub reset_cooldown_secs: u64,
+  /// Seconds an email verification token stays valid
+  #[serde(default = "default_verification_token_ttl_secs")]
+  pub verification_token_ttl_secs: u64,
+  #[serde(default)]
+  pub notifications: AuthNotificationsSection,
+  #[serde(default)]
+  pub oidc: OidcSection,
+  /// Name of the project tokens are created in when the request doesn't
+  /// pick one (default: the built-in default project). Created with the
+  /// first owner account if it doesn't exist.
+  #[serde(default)]
+  pub default_project: Option<String>,
+}
+fn default_reset_token_ttl_secs() -> u64 {
+  3600 // 1 hour
+}
+fn default_reset_cooldown_secs() -> u64 {
+  60
+}
+fn default_verification_token_ttl_secs() -> u64 {
+  86_400 // 1 day
+}
+impl Default for AuthSection {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      admin_token: None,
+      tcp_require_auth: false,
+      password_hashing: PasswordHashingSection::default(),
+      reset_token_ttl_secs: default_reset_token_ttl_secs(),
+      reset_cooldown_secs: default_reset_cooldown_secs(),
+      verification_token_ttl_secs: default_verification_token_ttl_secs(),
+      notifications: AuthNotificationsSection::default(),
+      oidc: OidcSection::default(),
+      default_project: None,
+    }
+  }
+}
+
+/// Where password reset and email verification tokens are sent. There is
+/// no built-in mailer; point the webhook at your own delivery service.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthNotificationsSection {
+  /// Write tokens to the server log (only for trusted, single-operator setups)
+  #[serde(default)]
+  pub log: bool,
+  /// URL that receives each token as a JSON POST
+  #[serde(default)]
+  pub webhook_url: Option<String>,
+  /// Signs webhook bodies with HMAC-SHA256 in `X-Squirrel-Signature`
+  #[serde(default)]
+  pub webhook_secret: Option<String>,
+}
+
+/// Sign-in with JWTs from an external OpenID Connect provider. Tokens are
+/// checked against the provider's published keys and mapped to an admin
+/// user by a claim.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcSection {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Expected `iss` claim
+  #[serde(default)]
+  pub issuer: String,
+  /// URL of the provider's JSON Web Key Set
+  #[serde(default)]
+  pub jwks_url: String,
+  /// Expected `aud` claim, usually the client ID registered for SquirrelDB.
+  /// Required when OIDC is enabled.
+  #[serde(default)]
+  pub audience: Option<String>,
+  /// Claim holding the admin username
+  #[serde(default = "default_oidc_username_claim")]
+  pub username_claim: String,
+  /// Claim holding the email stored on provisioned users
+  #[serde(default = "default_oidc_email_claim")]
+  pub email_claim: String,
+  /// Create an admin user the first time an unknown identity signs in
+  #[serde(default)]
+  pub auto_provision: bool,
+  /// Role given to provisioned users: `member`, `admin` or `owner`
+  #[serde(default = "default_oidc_role")]
+  pub default_role: String,
+  /// Seconds the fetched keys are cached
+  #[serde(default = "default_jwks_cache_secs")]
+  pub jwks_cache_secs: u64,
+}
+fn default_oidc_username_claim() -> String {
+  "preferred_username".into()
+}
+fn default_oidc_email_claim() -> String {
+  "email".into()
+}
+fn default_oidc_role() -> String {
+  "member".into()
+}
+fn default_jwks_cache_secs() -> u64 {
+  3600 // 1 hour
+}
+impl Default for OidcSection {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      issuer: String::new(),
+      jwks_url: String::new(),
+      audience: None,
+      username_claim: default_oidc_username_claim(),
+      email_claim: default_oidc_email_claim(),
+      auto_provision: false,
+      default_role: default_oidc_role(),
+      jwks_cache_secs: default_jwks_cache_secs(),
+    }
+  }
+}
+
+impl AuthNotificationsSection {
+  /// Whether tokens have anywhere to go
+  pub fn is_configured(&self) -> bool {
+    self.log || self.webhook_url.is_some()
+  }
+}
+
+/// Key derivation function used for admin user passwords
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordAlgorithm {
+  #[default]
+  Argon2id,
+  Bcrypt,
+  Scrypt,
+}
+
+/// How admin user passwords are hashed. Hashes stored with another
+/// algorithm or weaker parameters keep working and are replaced on the
+/// user's next login.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordHashingSection {
+  #[serde(default)]
+  pub algorithm: PasswordAlgorithm,
+  /// Argon2id memory cost in KiB
+  #[serde(default = "default_argon2_memory_kib")]
+  pub argon2_memory_kib: u32,
+  /// Argon2id passes over memory
+  #[serde(default = "default_argon2_iterations")]
+  pub argon2_iterations: u32,
+  /// Argon2id lanes
+  #[serde(default = "default_argon2_parallelism")]
+  pub argon2_parallelism: u32,
+  /// bcrypt cost, the log2 of the number of rounds (4-31)
+  #[serde(default = "default_bcrypt_cost")]
+  pub bcrypt_cost: u32,
+  /// scrypt CPU/memory cost as log2(N)
+  #[serde(default = "default_scrypt_log_n")]
+  pub scrypt_log_n: u8,
+  /// scrypt block size
+  #[serde(default = "default_scrypt_r")]
+  pub scrypt_r: u32,
+  /// scrypt parallelism
+  #[serde(default = "default_scrypt_p")]
+  pub scrypt_p: u32,
+}
+fn default_argon2_memory_kib() -> u32 {
+  19_456 // 19 MiB
+}
+fn default_argon2_iterations() -> u32 {
+  2
+}
+fn default_argon2_parallelism() -> u32 {
+  1
+}
+fn default_bcrypt_cost() -> u32 {
+  12
+}
+fn default_scrypt_log_n() -> u8 {
+  17
+}
+fn default_scrypt_r() -> u32 {
+  8
+}
+fn default_scrypt_p() -> u32 {
+  1
+}
+impl Default for PasswordHashingSection {
+  fn default() -> Self {
+    Self {
+      algorithm: PasswordAlgorithm::default(),
+      argon2_memory_kib: default_argon2_memory_kib(),
+      argon2_iterations: default_argon2_iterations(),
+      argon2_parallelism: default_argon2_parallelism(),
+      bcrypt_cost: default_bcrypt_cost(),
+      scrypt_log_n: default_scrypt_log_n(),
+      scrypt_r: default_scrypt_r(),
+      scrypt_p: default_scrypt_p(),
+    }
+  }
 }
 
 /// Rate limiting and resource limits configuration
@@ -397,6 +825,10 @@ pub struct LimitsSection {
   #[serde(default = "default_max_connections_per_ip")]
   pub max_connections_per_ip: u32,
 
+  /// Maximum open client connections across the server (0 = unlimited)
+  #[serde(default = "default_max_connections_total")]
+  pub max_connections_total: u32,
+
   /// Maximum requests per second per client (0 = unlimited)
   #[serde(default = "default_requests_per_second")]
   pub requests_per_second: u32,
@@ -416,11 +848,104 @@ pub struct LimitsSection {
   /// Maximum message size in bytes
   #[serde(default = "default_max_message_size")]
   pub max_message_size: usize,
+
+  /// Maximum outbound messages queued per WebSocket client before it is
+  /// disconnected as a slow consumer
+  #[serde(default = "default_max_send_queue")]
+  pub max_send_queue: usize,
+
+  /// Maximum serialized size of a single document in bytes (0 = unlimited)
+  #[serde(default = "default_max_document_bytes")]
+  pub max_document_bytes: usize,
+
+  /// Deepest nesting of objects and arrays accepted in a document (0 = unlimited)
+  #[serde(default = "default_max_document_depth")]
+  pub max_document_depth: usize,
+
+  /// Deepest nesting of logical operators in a structured filter, or of
+  /// brackets in a JS filter, accepted in a query (0 = unlimited)
+  #[serde(default = "default_max_filter_depth")]
+  pub max_filter_depth: usize,
+
+  /// Largest request body accepted by the admin API's JSON endpoints, in
+  /// bytes (0 = unlimited). REST routes use `max_document_bytes` instead.
+  #[serde(default = "default_max_request_body_bytes")]
+  pub max_request_body_bytes: usize,
+
+  /// Largest file upload accepted by the admin API, in bytes (0 = unlimited)
+  #[serde(default = "default_max_upload_bytes")]
+  pub max_upload_bytes: usize,
+
+  /// Largest total size of an HTTP request's headers, names and values
+  /// together, in bytes (0 = unlimited)
+  #[serde(default = "default_max_header_bytes")]
+  pub max_header_bytes: usize,
+
+  /// Rows returned by a query without an explicit limit (0 = unlimited).
+  /// Cut results are flagged `truncated` so clients know to paginate.
+  #[serde(default = "default_max_result_rows")]
+  pub max_result_rows: usize,
+
+  /// Watched queries a client may hold at once (0 = unlimited)
+  #[serde(default = "default_max_watches_per_client")]
+  pub max_watches_per_client: usize,
+
+  /// Log client requests that take longer than this many milliseconds (0 = off)
+  #[serde(default)]
+  pub slow_query_ms: u64,
+
+  /// Seconds an insert idempotency key is remembered; a retry after this
+  /// inserts again
+  #[serde(default = "default_idempotency_key_ttl_secs")]
+  pub idempotency_key_ttl_secs: u64,
+
+  /// What to do with JSON numbers in client messages and documents that
+  /// can't be stored exactly
+  #[serde(default)]
+  pub inexact_numbers: InexactNumbers,
+
+  /// Estimated cost above which a query is handled by `query_cost_action`
+  /// (0 = no limit). PostgreSQL estimates in planner cost units, SQLite in
+  /// documents read.
+  #[serde(default)]
+  pub max_query_cost: f64,
+
+  /// What happens to a query estimated over `max_query_cost`
+  #[serde(default)]
+  pub query_cost_action: QueryCostAction,
+}
+
+/// Handling of JSON numbers that would lose precision: integers outside the
+/// 64-bit range and decimals with more digits than a double holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InexactNumbers {
+  /// Store the nearest double
+  #[default]
+  Allow,
+  /// Refuse the message or request
+  Reject,
+  /// Store the number as a string holding its original digits
+  String,
+}
+
+/// Handling of queries estimated to cost more than `max_query_cost`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryCostAction {
+  /// Refuse the query before it runs
+  #[default]
+  Reject,
+  /// Run the query and log a warning
+  Warn,
 }
 
 fn default_max_connections_per_ip() -> u32 {
   100
 }
+fn default_max_connections_total() -> u32 {
+  10_000
+}
 fn default_requests_per_second() -> u32 {
   100
 }
@@ -436,16 +961,61 @@ fn default_max_concurrent_queries() -> u32 {
 fn default_max_message_size() -> usize {
   16 * 1024 * 1024 // 16 MB
 }
+fn default_max_send_queue() -> usize {
+  1024
+}
+fn default_max_document_bytes() -> usize {
+  1024 * 1024 // 1 MB
+}
+fn default_max_document_depth() -> usize {
+  64
+}
+fn default_max_filter_depth() -> usize {
+  32
+}
+fn default_max_request_body_bytes() -> usize {
+  1024 * 1024 // 1 MB
+}
+fn default_max_upload_bytes() -> usize {
+  100 * 1024 * 1024 // 100 MB
+}
+fn default_max_header_bytes() -> usize {
+  16 * 1024 // 16 KB
+}
+fn default_max_result_rows() -> usize {
+  10_000
+}
+fn default_max_watches_per_client() -> usize {
+  100
+}
+fn default_idempotency_key_ttl_secs() -> u64 {
+  86_400 // 24 hours
+}
 
 impl Default for LimitsSection {
   fn default() -> Self {
     Self {
       max_connections_per_ip: default_max_connections_per_ip(),
+      max_connections_total: default_max_connections_total(),
       requests_per_second: default_requests_per_second(),
       burst_size: default_burst_size(),
       query_timeout_ms: default_query_timeout_ms(),
       max_concurrent_queries: default_max_concurrent_queries(),
       max_message_size: default_max_message_size(),
+      max_send_queue: default_max_send_queue(),
+      max_document_bytes: default_max_document_bytes(),
+      max_document_depth: default_max_document_depth(),
+      max_filter_depth: default_max_filter_depth(),
+      max_request_body_bytes: default_max_request_body_bytes(),
+      max_upload_bytes: default_max_upload_bytes(),
+      max_header_bytes: default_max_header_bytes(),
+      max_result_rows: default_max_result_rows(),
+      max_watches_per_client: default_max_watches_per_client(),
+      slow_query_ms: 0,
+      idempotency_key_ttl_secs: default_idempotency_key_ttl_secs(),
+      inexact_numbers: InexactNumbers::default(),
+      max_query_cost: 0.0,
+      query_cost_action: QueryCostAction::default(),
     }
   }
 }
@@ -456,6 +1026,18 @@ pub struct PostgresSection {
   pub url: String,
   #[serde(default = "default_max_conn")]
   pub max_connections: usize,
+  /// Distinct SQL texts kept as prepared statements (0 disables)
+  #[serde(default = "default_statement_cache_size")]
+  pub statement_cache_size: usize,
+  /// Periodic ANALYZE/VACUUM of the busiest tables
+  #[serde(default)]
+  pub maintenance: PgMaintenanceSection,
+  /// Compression of large documents
+  #[serde(default)]
+  pub compression: PgCompressionSection,
+  /// Connection checks and timeouts
+  #[serde(default)]
+  pub pool: PgPoolSection,
 }
 fn default_pg_url() -> String {
   "postgres://localhost/squirreldb".into()
@@ -463,11 +1045,173 @@ fn default_pg_url() -> String {
 fn default_max_conn() -> usize {
   20
 }
+fn default_statement_cache_size() -> usize {
+  256
+}
 impl Default for PostgresSection {
   fn default() -> Self {
     Self {
       url: default_pg_url(),
       max_connections: default_max_conn(),
+      statement_cache_size: default_statement_cache_size(),
+      maintenance: PgMaintenanceSection::default(),
+      compression: PgCompressionSection::default(),
+      pool: PgPoolSection::default(),
+    }
+  }
+}
+
+/// Background `ANALYZE` of the documents and change_queue tables, for
+/// deployments where autovacuum doesn't keep planner statistics fresh.
+/// Off by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PgMaintenanceSection {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Seconds between runs
+  #[serde(default = "default_pg_maintenance_interval")]
+  pub interval_secs: u64,
+  /// Run `VACUUM (ANALYZE)` instead of a plain `ANALYZE`
+  #[serde(default)]
+  pub vacuum: bool,
+}
+fn default_pg_maintenance_interval() -> u64 {
+  3600
+}
+impl Default for PgMaintenanceSection {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      interval_secs: default_pg_maintenance_interval(),
+      vacuum: false,
+    }
+  }
+}
+
+/// Compression of documents whose row is larger than `threshold_bytes`,
+/// done by PostgreSQL's TOAST so queries see plain JSONB. Off by default,
+/// which leaves the server's own settings in place.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PgCompressionSection {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Row size above which document data is compressed (128-8160)
+  #[serde(default = "default_pg_compression_threshold")]
+  pub threshold_bytes: u32,
+  /// `lz4` (PostgreSQL 14+) or `pglz`
+  #[serde(default = "default_pg_compression_method")]
+  pub method: String,
+}
+fn default_pg_compression_threshold() -> u32 {
+  2048
+}
+fn default_pg_compression_method() -> String {
+  "lz4".into()
+}
+impl Default for PgCompressionSection {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      threshold_bytes: default_pg_compression_threshold(),
+      method: default_pg_compression_method(),
+    }
+  }
+}
+
+/// How pooled connections are checked and how long pool operations may
+/// take. A timeout of 0 waits indefinitely.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PgPoolSection {
+  /// `verified` runs an empty query before reusing a connection and
+  /// replaces it if that fails; `fast` skips the round trip
+  #[serde(default = "default_pg_pool_recycling")]
+  pub recycling: String,
+  /// Connections opened at startup (at most `max_connections`)
+  #[serde(default)]
+  pub min_idle: usize,
+  /// Wait for a free connection when all are in use
+  #[serde(default = "default_pg_pool_wait_timeout")]
+  pub wait_timeout_secs: u64,
+  /// Opening a new connection
+  #[serde(default = "default_pg_pool_create_timeout")]
+  pub create_timeout_secs: u64,
+  /// Checking a connection before reuse
+  #[serde(default = "default_pg_pool_recycle_timeout")]
+  pub recycle_timeout_secs: u64,
+}
+fn default_pg_pool_recycling() -> String {
+  "verified".into()
+}
+fn default_pg_pool_wait_timeout() -> u64 {
+  30
+}
+fn default_pg_pool_create_timeout() -> u64 {
+  10
+}
+fn default_pg_pool_recycle_timeout() -> u64 {
+  5
+}
+impl Default for PgPoolSection {
+  fn default() -> Self {
+    Self {
+      recycling: default_pg_pool_recycling(),
+      min_idle: 0,
+      wait_timeout_secs: default_pg_pool_wait_timeout(),
+      create_timeout_secs: default_pg_pool_create_timeout(),
+      recycle_timeout_secs: default_pg_pool_recycle_timeout(),
+    }
+  }
+}
+
+/// Rules for collection names
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionsSection {
+  /// Names clients can't insert into or copy/rename a collection to.
+  /// Defaults to the server's own table names; an empty list allows all.
+  #[serde(default = "default_reserved_collections")]
+  pub reserved: Vec<String>,
+}
+fn default_reserved_collections() -> Vec<String> {
+  INTERNAL_TABLE_NAMES.iter().map(|n| n.to_string()).collect()
+}
+impl Default for CollectionsSection {
+  fn default() -> Self {
+    Self {
+      reserved: default_reserved_collections(),
+    }
+  }
+}
+
+/// How long recorded changes are kept for replay and history. A change is
+/// removed once it is both outside the newest `max_entries` and older than
+/// `max_age_secs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeQueueSection {
+  /// Newest changes always kept, regardless of age
+  #[serde(default = "default_change_max_entries")]
+  pub max_entries: u32,
+  /// Changes younger than this many seconds are always kept
+  #[serde(default = "default_change_max_age_secs")]
+  pub max_age_secs: u64,
+  /// Seconds between cleanup runs
+  #[serde(default = "default_change_cleanup_interval_secs")]
+  pub cleanup_interval_secs: u64,
+}
+fn default_change_max_entries() -> u32 {
+  10_000
+}
+fn default_change_max_age_secs() -> u64 {
+  3600 // 1 hour
+}
+fn default_change_cleanup_interval_secs() -> u64 {
+  300 // 5 minutes
+}
+impl Default for ChangeQueueSection {
+  fn default() -> Self {
+    Self {
+      max_entries: default_change_max_entries(),
+      max_age_secs: default_change_max_age_secs(),
+      cleanup_interval_secs: default_change_cleanup_interval_secs(),
     }
   }
 }
@@ -521,6 +1265,36 @@ impl ServerConfig {
     Ok(None)
   }
 
+  /// Absolute form of `data_dir` (relative to the working directory)
+  pub fn data_dir(&self) -> PathBuf {
+    absolute(Path::new(&self.data_dir))
+  }
+
+  /// Resolve a configured path against `data_dir` unless it is absolute
+  pub fn resolve_path(&self, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+      return path.to_path_buf();
+    }
+    normalize(&self.data_dir().join(path))
+  }
+
+  /// Rewrite the data file paths in the config to absolute paths. Safe to
+  /// call more than once.
+  pub fn resolve_paths(&mut self) {
+    let resolve =
+      |config: &Self, path: &str| config.resolve_path(path).to_string_lossy().into_owned();
+    if self.sqlite.path != ":memory:" {
+      self.sqlite.path = resolve(self, &self.sqlite.path);
+    }
+    self.storage.storage_path = resolve(self, &self.storage.storage_path);
+    self.caching.snapshot.path = resolve(self, &self.caching.snapshot.path);
+    self.backup.local_path = resolve(self, &self.backup.local_path);
+    if let Some(seed_file) = &self.seed_file {
+      self.seed_file = Some(resolve(self, seed_file));
+    }
+  }
+
   pub fn address(&self) -> String {
     format!("{}:{}", self.server.host, self.server.ports.http)
   }
@@ -544,4 +1318,47 @@ impl ServerConfig {
   pub fn cache_address(&self) -> String {
     format!("{}:{}", self.server.host, self.caching.port)
   }
+
+  /// Identity announced to clients on connect, or None if the banner is off
+  pub fn server_info(&self) -> Option<ServerInfo> {
+    if !self.server.banner.enabled {
+      return None;
+    }
+    let protocols = &self.server.protocols;
+    let protocols = [
+      ("rest", protocols.rest),
+      ("websocket", protocols.websocket),
+      ("sse", protocols.sse),
+      ("tcp", protocols.tcp),
+      ("mcp", protocols.mcp),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| name.to_string())
+    .collect();
+    Some(ServerInfo {
+      name: "SquirrelDB".to_string(),
+      version: env!("CARGO_PKG_VERSION").to_string(),
+      min_protocol_version: MIN_PROTOCOL_VERSION,
+      max_protocol_version: PROTOCOL_VERSION,
+      protocols,
+      message: self.server.banner.message.clone(),
+    })
+  }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+  if path.is_absolute() {
+    return normalize(path);
+  }
+  let cwd = std::env::current_dir().unwrap_or_default();
+  normalize(&cwd.join(path))
+}
+
+/// Drop `.` components so resolved paths read cleanly in logs
+fn normalize(path: &Path) -> PathBuf {
+  path
+    .components()
+    .filter(|c| !matches!(c, Component::CurDir))
+    .collect()
 }
@@ -0,0 +1,144 @@
+//! Open data-plane connections.
+//!
+//! The WebSocket, TCP and admin servers register each client once it is
+//! authenticated, so the admin API can list who is connected and force a
+//! misbehaving client off without restarting the server.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// An open connection as shown by the admin API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConnectionInfo {
+  /// Client ID, shared with the subscription manager
+  pub id: Uuid,
+  /// `websocket` or `tcp`
+  pub protocol: &'static str,
+  pub ip: IpAddr,
+  pub connected_at: DateTime<Utc>,
+  /// Project that data operations currently run against
+  pub project_id: Uuid,
+}
+
+struct Entry {
+  info: ConnectionInfo,
+  disconnect: Arc<Notify>,
+}
+
+/// Registry of open connections shared by the servers
+#[derive(Clone, Default)]
+pub struct Connections(Arc<RwLock<HashMap<Uuid, Entry>>>);
+
+impl Connections {
+  /// Register a connection until the returned handle is dropped
+  pub fn register(
+    &self,
+    id: Uuid,
+    protocol: &'static str,
+    ip: IpAddr,
+    project_id: Uuid,
+  ) -> ConnectionHandle {
+    let disconnect = Arc::new(Notify::new());
+    self.0.write().insert(
+      id,
+      Entry {
+        info: ConnectionInfo {
+          id,
+          protocol,
+          ip,
+          connected_at: Utc::now(),
+          project_id,
+        },
+        disconnect: disconnect.clone(),
+      },
+    );
+    ConnectionHandle {
+      id,
+      connections: self.clone(),
+      disconnect,
+    }
+  }
+
+  /// Open connections, oldest first
+  pub fn list(&self) -> Vec<ConnectionInfo> {
+    let mut list: Vec<_> = self.0.read().values().map(|e| e.info.clone()).collect();
+    list.sort_by_key(|info| info.connected_at);
+    list
+  }
+
+  pub fn get(&self, id: Uuid) -> Option<ConnectionInfo> {
+    self.0.read().get(&id).map(|e| e.info.clone())
+  }
+
+  /// Record the project a connection switched to
+  pub fn set_project(&self, id: Uuid, project_id: Uuid) {
+    if let Some(entry) = self.0.write().get_mut(&id) {
+      entry.info.project_id = project_id;
+    }
+  }
+
+  /// Ask a connection to close. Returns false if it isn't open.
+  pub fn disconnect(&self, id: Uuid) -> bool {
+    match self.0.read().get(&id) {
+      Some(entry) => {
+        entry.disconnect.notify_one();
+        true
+      }
+      None => false,
+    }
+  }
+}
+
+/// A registered connection, removed from the registry when dropped
+pub struct ConnectionHandle {
+  id: Uuid,
+  connections: Connections,
+  disconnect: Arc<Notify>,
+}
+
+impl ConnectionHandle {
+  /// Resolves once the connection has been told to close
+  pub async fn disconnected(&self) {
+    self.disconnect.notified().await
+  }
+}
+
+impl Drop for ConnectionHandle {
+  fn drop(&mut self) {
+    self.connections.0.write().remove(&self.id);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_connections_register_and_disconnect() {
+    let connections = Connections::default();
+    let id = Uuid::new_v4();
+    let ip: IpAddr = "10.0.0.1".parse().unwrap();
+    let handle = connections.register(id, "tcp", ip, Uuid::nil());
+    assert_eq!(connections.list().len(), 1);
+
+    let project = Uuid::new_v4();
+    connections.set_project(id, project);
+    assert_eq!(connections.get(id).unwrap().project_id, project);
+
+    assert!(connections.disconnect(id));
+    tokio::time::timeout(std::time::Duration::from_secs(1), handle.disconnected())
+      .await
+      .expect("disconnect was not signalled");
+
+    drop(handle);
+    assert!(connections.list().is_empty());
+    assert!(!connections.disconnect(id));
+  }
+}
@@ -2,9 +2,12 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::broadcast;
 
-use super::{RateLimiter, ServerConfig, TcpServer, WebSocketServer};
+use super::{
+  Connections, CorsOrigins, Maintenance, RateLimiter, ServerConfig, ServerTls, TcpServer,
+  WebSocketServer,
+};
 use crate::admin::{emit_log, AdminServer};
-use crate::backup::BackupFeature;
+use crate::backup::{seed_database, BackupFeature};
 use crate::cache::{CacheConfig, CacheFeature};
 use crate::db::DatabaseBackend;
 use crate::features::{AppState, FeatureRegistry};
@@ -12,6 +15,13 @@ use crate::mcp::McpServer;
 use crate::query::QueryEnginePool;
 use crate::storage::{StorageConfig, StorageFeature};
 use crate::subscriptions::SubscriptionManager;
+use crate::webhooks::WebhookFeature;
+
+/// Re-reads the daemon's configuration, including command-line overrides
+pub type ConfigLoader = Arc<dyn Fn() -> Result<ServerConfig, anyhow::Error> + Send + Sync>;
+
+/// Replaces the active log filter
+pub type LogLevelSetter = Arc<dyn Fn(&str) -> Result<(), anyhow::Error> + Send + Sync>;
 
 pub struct Daemon {
   config: ServerConfig,
@@ -21,6 +31,11 @@ pub struct Daemon {
   rate_limiter: Arc<RateLimiter>,
   shutdown_tx: broadcast::Sender<()>,
   feature_registry: Arc<FeatureRegistry>,
+  cors_origins: CorsOrigins,
+  maintenance: Maintenance,
+  connections: Connections,
+  config_loader: Option<ConfigLoader>,
+  log_level_setter: Option<LogLevelSetter>,
 }
 
 impl Daemon {
@@ -30,7 +45,15 @@ impl Daemon {
     let pool_size = std::thread::available_parallelism()
       .map(|n| n.get())
       .unwrap_or(4);
-    let engine_pool = Arc::new(QueryEnginePool::new(pool_size, backend.dialect()));
+    let engine_pool = Arc::new(
+      QueryEnginePool::new(pool_size, backend.dialect())
+        .with_max_result_rows(config.limits.max_result_rows)
+        .with_max_filter_depth(config.limits.max_filter_depth)
+        .with_max_query_cost(
+          config.limits.max_query_cost,
+          config.limits.query_cost_action,
+        ),
+    );
     tracing::info!("QueryEngine pool created with {} engines", pool_size);
 
     // Create rate limiter
@@ -59,6 +82,12 @@ impl Daemon {
     let backup_feature = Arc::new(BackupFeature::new());
     feature_registry.register(backup_feature);
 
+    // Register webhook feature
+    let webhook_feature = Arc::new(WebhookFeature::new());
+    feature_registry.register(webhook_feature);
+
+    let cors_origins = CorsOrigins::new(config.server.cors_origins.clone());
+
     Self {
       config,
       backend: backend.clone(),
@@ -67,9 +96,22 @@ impl Daemon {
       rate_limiter,
       shutdown_tx,
       feature_registry,
+      cors_origins,
+      maintenance: Maintenance::default(),
+      connections: Connections::default(),
+      config_loader: None,
+      log_level_setter: None,
     }
   }
 
+  /// Enable config reload on SIGHUP. `loader` re-reads the configuration and
+  /// `set_log_level` applies a changed `logging.level`.
+  pub fn with_reload(mut self, loader: ConfigLoader, set_log_level: LogLevelSetter) -> Self {
+    self.config_loader = Some(loader);
+    self.log_level_setter = Some(set_log_level);
+    self
+  }
+
   /// Trigger graceful shutdown of all servers
   pub fn shutdown(&self) {
     tracing::info!("Initiating graceful shutdown...");
@@ -84,6 +126,31 @@ impl Daemon {
     );
     self.backend.init_schema().await?;
     emit_log("info", "squirreldb::daemon", "Database schema initialized");
+    if let Some(path) = &self.config.seed_file {
+      match seed_database(&self.backend, std::path::Path::new(path)).await? {
+        Some(summary) => emit_log(
+          "info",
+          "squirreldb::daemon",
+          &format!(
+            "Seeded {} documents in {} collections from {}",
+            summary.documents, summary.collections, path
+          ),
+        ),
+        None => tracing::debug!("Database has data; seed file {} not loaded", path),
+      }
+    }
+    self
+      .rate_limiter
+      .load_project_limits(self.backend.as_ref())
+      .await?;
+    self.maintenance.load(self.backend.as_ref()).await?;
+    if self.maintenance.settings().enabled {
+      emit_log(
+        "warn",
+        "squirreldb::daemon",
+        "Maintenance mode is on: data writes are refused",
+      );
+    }
 
     emit_log("info", "squirreldb::daemon", "Starting change listener...");
     self.backend.start_change_listener().await?;
@@ -95,6 +162,13 @@ impl Daemon {
       subs.process_changes(change_rx).await;
     });
 
+    // Drop cached query results for collections as they change
+    let change_rx = self.backend.subscribe_changes();
+    let query_cache = self.engine_pool.query_cache();
+    tokio::spawn(async move {
+      query_cache.process_changes(change_rx).await;
+    });
+
     // Start rate limiter cleanup task
     let cleanup_limiter = self.rate_limiter.clone();
     tokio::spawn(async move {
@@ -104,6 +178,83 @@ impl Daemon {
       }
     });
 
+    // Drop expired insert idempotency keys
+    let purge_backend = self.backend.clone();
+    let purge_limiter = self.rate_limiter.clone();
+    tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        let ttl = purge_limiter.idempotency_key_ttl();
+        match purge_backend.purge_idempotency_keys(ttl).await {
+          Ok(0) => {}
+          Ok(n) => tracing::debug!("Purged {} expired idempotency keys", n),
+          Err(e) => tracing::warn!("Failed to purge idempotency keys: {}", e),
+        }
+      }
+    });
+
+    // Drop expired client key-value entries
+    let purge_backend = self.backend.clone();
+    tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        match purge_backend.purge_expired_kv().await {
+          Ok(0) => {}
+          Ok(n) => tracing::debug!("Purged {} expired key-value entries", n),
+          Err(e) => tracing::warn!("Failed to purge key-value entries: {}", e),
+        }
+      }
+    });
+
+    // Remove soft-deleted documents kept longer than their collection's retention
+    let purge_backend = self.backend.clone();
+    tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        let collections = match purge_backend.list_soft_delete().await {
+          Ok(collections) => collections,
+          Err(e) => {
+            tracing::warn!("Failed to list soft-delete collections: {}", e);
+            continue;
+          }
+        };
+        for (project_id, collection, settings) in collections {
+          if settings.retention_secs == 0 {
+            continue;
+          }
+          let retention = Duration::from_secs(settings.retention_secs);
+          match purge_backend
+            .purge_deleted(project_id, &collection, retention)
+            .await
+          {
+            Ok(0) => {}
+            Ok(n) => tracing::debug!("Purged {} soft-deleted documents from '{}'", n, collection),
+            Err(e) => tracing::warn!(
+              "Failed to purge soft-deleted documents from '{}': {}",
+              collection,
+              e
+            ),
+          }
+        }
+      }
+    });
+
+    self.reload_on_sighup()?;
+
+    // Load TLS certificates for the client-facing listeners
+    let tls = ServerTls::from_section(&self.config.server.tls)?;
+    if let Some(tls) = &tls {
+      tls.reload_on_sighup()?;
+      emit_log(
+        "info",
+        "squirreldb::tls",
+        &format!(
+          "TLS enabled with certificate {} (reloaded on SIGHUP)",
+          self.config.server.tls.cert_path
+        ),
+      );
+    }
+
     // Start admin UI server with shutdown signal (if enabled)
     if self.config.server.admin {
       let admin = AdminServer::new(
@@ -115,7 +266,11 @@ impl Daemon {
         self.config.clone(),
         self.feature_registry.clone(),
         self.rate_limiter.clone(),
-      );
+      )
+      .with_tls(tls.clone())
+      .with_cors_origins(self.cors_origins.clone())
+      .with_maintenance(self.maintenance.clone())
+      .with_connections(self.connections.clone());
       let admin_addr = self.config.admin_address();
       emit_log(
         "info",
@@ -141,7 +296,10 @@ impl Daemon {
         self.rate_limiter.clone(),
         self.shutdown_tx.subscribe(),
         self.config.clone(),
-      );
+      )
+      .with_tls(tls.clone())
+      .with_maintenance(self.maintenance.clone())
+      .with_connections(self.connections.clone());
       let tcp_addr = self.config.tcp_address();
       emit_log(
         "info",
@@ -242,13 +400,15 @@ impl Daemon {
         tracing::error!("Failed to start backup feature: {}", e);
       } else {
         let location = if self.config.features.storage {
-          format!("S3: /{}", self.config.backup.storage_path)
+          format!(
+            "S3: {}/{}",
+            self.config.backup.storage_bucket, self.config.backup.storage_path
+          )
         } else {
           self.config.backup.local_path.clone()
         };
         tracing::info!(
-          "SquirrelDB Backup enabled (interval: {}s, retention: {}, storage: {})",
-          self.config.backup.interval,
+          "SquirrelDB Backup enabled (retention: {}, storage: {})",
           self.config.backup.retention,
           location
         );
@@ -258,6 +418,22 @@ impl Daemon {
       tracing::info!("Backup feature disabled");
     }
 
+    // Start webhook feature if enabled
+    if self.config.features.webhooks {
+      let app_state = Arc::new(AppState {
+        backend: self.backend.clone(),
+        engine_pool: self.engine_pool.clone(),
+        config: self.config.clone(),
+      });
+      emit_log("info", "squirreldb::webhooks", "Starting webhook service");
+      if let Err(e) = self.feature_registry.start("webhooks", app_state).await {
+        tracing::error!("Failed to start webhook feature: {}", e);
+      }
+    } else {
+      emit_log("warn", "squirreldb::webhooks", "Webhook feature disabled");
+      tracing::info!("Webhook feature disabled");
+    }
+
     // Start MCP SSE server if enabled
     if self.config.server.protocols.mcp {
       let mcp_addr = self.config.mcp_address();
@@ -288,7 +464,11 @@ impl Daemon {
         self.rate_limiter.clone(),
         self.shutdown_tx.subscribe(),
         self.config.clone(),
-      );
+      )
+      .with_tls(tls)
+      .with_maintenance(self.maintenance.clone())
+      .with_connections(self.connections.clone())
+      .with_cors_origins(self.cors_origins.clone());
       emit_log(
         "info",
         "squirreldb::websocket",
@@ -305,4 +485,101 @@ impl Daemon {
       }
     }
   }
+
+  /// Re-read the configuration whenever the process receives SIGHUP
+  #[cfg(unix)]
+  fn reload_on_sighup(&self) -> Result<(), anyhow::Error> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Some(loader) = self.config_loader.clone() else {
+      return Ok(());
+    };
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut running = self.config.clone();
+    let rate_limiter = self.rate_limiter.clone();
+    let cors_origins = self.cors_origins.clone();
+    let set_log_level = self.log_level_setter.clone();
+    tokio::spawn(async move {
+      while hangup.recv().await.is_some() {
+        match loader() {
+          Ok(new) => apply_reload(
+            &mut running,
+            &new,
+            &rate_limiter,
+            &cors_origins,
+            set_log_level.as_ref(),
+          ),
+          Err(e) => report(
+            "error",
+            &format!("Config reload failed, keeping current settings: {}", e),
+          ),
+        }
+      }
+    });
+    Ok(())
+  }
+
+  #[cfg(not(unix))]
+  fn reload_on_sighup(&self) -> Result<(), anyhow::Error> {
+    Ok(())
+  }
+}
+
+/// Apply the reloadable settings from `new` and report what changed.
+/// `running` keeps the startup values of settings that need a restart, so
+/// they are reported on every reload until the server is restarted.
+fn apply_reload(
+  running: &mut ServerConfig,
+  new: &ServerConfig,
+  rate_limiter: &RateLimiter,
+  cors_origins: &CorsOrigins,
+  set_log_level: Option<&LogLevelSetter>,
+) {
+  let changes = running.reload_changes(new);
+  if changes.is_empty() {
+    report("info", "Config reloaded, no changes");
+    return;
+  }
+
+  let previous_level = running.logging.level.clone();
+  running.apply_reloadable(new);
+  if running.logging.level != previous_level {
+    if let Some(set_log_level) = set_log_level {
+      if let Err(e) = set_log_level(&running.logging.level) {
+        report(
+          "error",
+          &format!(
+            "Could not apply log level '{}', keeping '{}': {}",
+            running.logging.level, previous_level, e
+          ),
+        );
+        running.logging.level = previous_level;
+      }
+    }
+  }
+  rate_limiter.update_limits(running.limits.clone());
+  cors_origins.set(running.server.cors_origins.clone());
+
+  for change in &changes.applied {
+    report("info", &format!("Config reload applied {}", change));
+  }
+  if !changes.pending_restart.is_empty() {
+    report(
+      "warn",
+      &format!(
+        "Config changes pending restart: {}",
+        changes.pending_restart.join(", ")
+      ),
+    );
+  }
+}
+
+/// Log to both tracing and the admin log stream
+fn report(level: &str, message: &str) {
+  match level {
+    "error" => tracing::error!("{}", message),
+    "warn" => tracing::warn!("{}", message),
+    _ => tracing::info!("{}", message),
+  }
+  emit_log(level, "squirreldb::daemon", message);
 }
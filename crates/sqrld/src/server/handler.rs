@@ -1,15 +1,53 @@
+use futures_util::StreamExt;
+use parking_lot::RwLock;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
-use crate::db::DatabaseBackend;
-use crate::query::QueryEnginePool;
-use crate::subscriptions::SubscriptionManager;
-use crate::types::{ClientMessage, QueryInput, ServerMessage, DEFAULT_PROJECT_ID};
+use super::connections::Connections;
+use super::maintenance::Maintenance;
+use super::rate_limiter::{check_document_depth, check_document_size, RateLimitError, RateLimiter};
+use crate::db::{
+  insert_idempotent, DatabaseBackend, DocumentWrite, IdempotentInsert, WriteOptions,
+  DEFAULT_IDEMPOTENCY_TTL,
+};
+use crate::query::{QueryEnginePool, QueryResult, DEFAULT_QUERY_CACHE_TTL};
+use crate::subscriptions::{OwnWrite, SubscriptionManager};
+use crate::types::{
+  ClientMessage, Document, ErrorCode, QueryInput, ServerInfo, ServerMessage, DEFAULT_PROJECT_ID,
+  MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+
+/// Rows per `resultchunk` message of a streamed query
+pub const STREAM_CHUNK_ROWS: usize = 500;
 
 pub struct MessageHandler {
   backend: Arc<dyn DatabaseBackend>,
   subs: Arc<SubscriptionManager>,
   engine_pool: Arc<QueryEnginePool>,
+  /// Project the client's token is bound to (None = unrestricted)
+  bound_project: Option<Uuid>,
+  /// Project that data operations currently run against
+  current_project: RwLock<Uuid>,
+  /// API token behind the connection, recorded as the author of writes
+  author: Option<Uuid>,
+  /// Protocol version agreed with `hello` (None = not negotiated, version 1)
+  protocol_version: RwLock<Option<u32>>,
+  /// Largest document accepted by insert/update (0 = unlimited)
+  max_document_bytes: usize,
+  /// Deepest nesting accepted in inserted and updated documents (0 = unlimited)
+  max_document_depth: usize,
+  /// How long insert idempotency keys are remembered
+  idempotency_ttl: Duration,
+  /// Enforces per-project request rates and document quotas
+  rate_limiter: Option<Arc<RateLimiter>>,
+  /// Refuses messages while the server is in maintenance mode
+  maintenance: Maintenance,
+  /// Identity included in `hello` replies (None = banner off)
+  server_info: Option<ServerInfo>,
+  /// Open connections, kept up to date when the client switches project
+  connections: Connections,
 }
 
 impl MessageHandler {
@@ -22,133 +60,571 @@ impl MessageHandler {
       backend,
       subs,
       engine_pool,
+      bound_project: None,
+      current_project: RwLock::new(DEFAULT_PROJECT_ID),
+      author: None,
+      protocol_version: RwLock::new(None),
+      max_document_bytes: 0,
+      max_document_depth: 0,
+      idempotency_ttl: DEFAULT_IDEMPOTENCY_TTL,
+      rate_limiter: None,
+      maintenance: Maintenance::default(),
+      server_info: None,
+      connections: Connections::default(),
     }
   }
 
-  /// Execute a query, routing to structured or JS execution based on input type
-  async fn execute_query(&self, query: &QueryInput) -> Result<serde_json::Value, anyhow::Error> {
-    match query {
-      QueryInput::Structured(q) => {
-        self
-          .engine_pool
-          .execute_structured(q, self.backend.as_ref())
-          .await
+  /// Restrict this handler to a single project, as resolved from the client's token.
+  /// Passing None leaves the handler unrestricted (admin token or auth disabled).
+  pub fn with_project(mut self, project_id: Option<Uuid>) -> Self {
+    self.bound_project = project_id;
+    *self.current_project.get_mut() = project_id.unwrap_or(DEFAULT_PROJECT_ID);
+    self
+  }
+
+  /// Record `author` (an API token ID) for `$user` collection defaults
+  pub fn with_author(mut self, author: Option<Uuid>) -> Self {
+    self.author = author;
+    self
+  }
+
+  /// Reject inserted or updated documents larger than `limit` bytes (0 = unlimited)
+  pub fn with_max_document_bytes(mut self, limit: usize) -> Self {
+    self.max_document_bytes = limit;
+    self
+  }
+
+  /// Reject inserted or updated documents nested more than `limit` levels deep (0 = unlimited)
+  pub fn with_max_document_depth(mut self, limit: usize) -> Self {
+    self.max_document_depth = limit;
+    self
+  }
+
+  /// Remember insert idempotency keys for `ttl`
+  pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+    self.idempotency_ttl = ttl;
+    self
+  }
+
+  /// Apply the limiter's per-project rates and document quotas
+  pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+    self.rate_limiter = Some(rate_limiter);
+    self
+  }
+
+  /// Refuse writes (and optionally reads) while maintenance mode is on
+  pub fn with_maintenance(mut self, maintenance: Maintenance) -> Self {
+    self.maintenance = maintenance;
+    self
+  }
+
+  /// Announce the server's identity in `hello` replies
+  pub fn with_server_info(mut self, server_info: Option<ServerInfo>) -> Self {
+    self.server_info = server_info;
+    self
+  }
+
+  /// Record project switches in the shared connection registry
+  pub fn with_connections(mut self, connections: Connections) -> Self {
+    self.connections = connections;
+    self
+  }
+
+  /// Project that data operations currently run against
+  pub fn project_id(&self) -> Uuid {
+    *self.current_project.read()
+  }
+
+  /// Protocol version in use on this connection
+  pub fn protocol_version(&self) -> u32 {
+    self.protocol_version.read().unwrap_or(MIN_PROTOCOL_VERSION)
+  }
+
+  /// Agree on the protocol version: the newest one both sides speak. Only
+  /// allowed as the first message of a connection.
+  fn negotiate_version(&self, client_version: u32) -> Result<u32, (ErrorCode, String)> {
+    let mut agreed = self.protocol_version.write();
+    if agreed.is_some() {
+      return Err((
+        ErrorCode::BadRequest,
+        "Protocol version must be negotiated with the first message".to_string(),
+      ));
+    }
+    if client_version < MIN_PROTOCOL_VERSION {
+      return Err((
+        ErrorCode::VersionUnsupported,
+        format!(
+          "Protocol version {} is not supported; server supports {} to {}",
+          client_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+        ),
+      ));
+    }
+    let version = client_version.min(PROTOCOL_VERSION);
+    *agreed = Some(version);
+    Ok(version)
+  }
+
+  /// Switch the active project, rejecting projects outside the token's scope
+  async fn select_project(&self, project_id: Uuid) -> Result<(), (ErrorCode, String)> {
+    if let Some(bound) = self.bound_project {
+      if bound != project_id {
+        return Err((
+          ErrorCode::Forbidden,
+          format!(
+            "Access denied: this connection is limited to project {}",
+            bound
+          ),
+        ));
       }
-      QueryInput::Script(script) => {
-        self
-          .engine_pool
-          .execute(script, self.backend.as_ref())
-          .await
+    } else if project_id != DEFAULT_PROJECT_ID {
+      match self.backend.get_project(project_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+          return Err((
+            ErrorCode::NotFound,
+            format!("Project {} not found", project_id),
+          ))
+        }
+        Err(e) => return Err((ErrorCode::Internal, e.to_string())),
       }
     }
+
+    *self.current_project.write() = project_id;
+    Ok(())
+  }
+
+  /// Execute a query, routing to structured or JS execution based on input type
+  async fn execute_query(
+    &self,
+    query: &QueryInput,
+    params: &[serde_json::Value],
+    cache_ttl: Option<Duration>,
+  ) -> Result<QueryResult, anyhow::Error> {
+    self
+      .engine_pool
+      .run_with_params(
+        query,
+        params,
+        self.project_id(),
+        self.backend.as_ref(),
+        cache_ttl,
+      )
+      .await
   }
 
   /// Parse a query into a QuerySpec, routing based on input type
   fn parse_query(&self, query: &QueryInput) -> Result<crate::types::QuerySpec, anyhow::Error> {
-    match query {
+    let mut spec = match query {
       QueryInput::Structured(q) => self.engine_pool.parse_structured(q),
       QueryInput::Script(script) => self.engine_pool.parse_query(script),
+    }?;
+    spec.project_id = Some(self.project_id());
+    Ok(spec)
+  }
+
+  /// Apply a write, reading the change it recorded in the same transaction
+  /// when the client has read-your-writes subscriptions
+  async fn write(
+    &self,
+    own_write: &Option<OwnWrite<'_>>,
+    collection: &str,
+    write: DocumentWrite,
+  ) -> Result<Option<(Document, Option<i64>)>, anyhow::Error> {
+    self
+      .backend
+      .write_document(self.project_id(), collection, write, own_write.is_some())
+      .await
+  }
+
+  /// The active project's document quota, checked by the backend inside
+  /// the insert's transaction
+  fn document_quota(&self) -> u64 {
+    self
+      .rate_limiter
+      .as_ref()
+      .map_or(0, |limiter| limiter.document_quota(self.project_id()))
+  }
+
+  /// Refusal of a message before it runs: wrong protocol version,
+  /// maintenance mode, rate limits or an oversized document
+  fn check_message(&self, msg: &ClientMessage) -> Option<ServerMessage> {
+    if !matches!(msg, ClientMessage::Hello { .. }) {
+      // Without a leading hello the connection stays on version 1
+      let version = *self
+        .protocol_version
+        .write()
+        .get_or_insert(MIN_PROTOCOL_VERSION);
+      if msg.min_version() > version {
+        let error = ServerMessage::error(
+          msg.id().to_string(),
+          format!(
+            "Message needs protocol version {}, connection uses {}",
+            msg.min_version(),
+            version
+          ),
+        )
+        .with_code(ErrorCode::VersionUnsupported);
+        return Some(error);
+      }
+    }
+    if let Some(refusal) = self.maintenance.check_message(msg) {
+      return Some(
+        ServerMessage::error(msg.id().to_string(), refusal.to_string())
+          .with_code(ErrorCode::Unavailable),
+      );
     }
+    if let Some(limiter) = &self.rate_limiter {
+      if !matches!(msg, ClientMessage::Ping { .. }) {
+        if let Err(e) = limiter.check_project_request(self.project_id()) {
+          return Some(
+            ServerMessage::error(msg.id().to_string(), e.to_string())
+              .with_code(ErrorCode::RateLimited),
+          );
+        }
+      }
+    }
+    if let ClientMessage::Insert { id, data, .. } | ClientMessage::Update { id, data, .. } = msg {
+      if let Err(e) = check_document_size(data, self.max_document_bytes) {
+        return Some(
+          ServerMessage::error(id.clone(), e.to_string()).with_code(ErrorCode::PayloadTooLarge),
+        );
+      }
+      if let Err(e) = check_document_depth(data, self.max_document_depth) {
+        return Some(
+          ServerMessage::error(id.clone(), e.to_string()).with_code(ErrorCode::BadRequest),
+        );
+      }
+    }
+    None
+  }
+
+  /// Run a query with `stream` set, passing each `resultchunk` and the final
+  /// `resultend` (or an error) to `send`. Rows are read from the database
+  /// only as `send` completes, so a slow client holds back the query rather
+  /// than filling memory. Other messages get their single reply. Returns
+  /// false once `send` does, when the client is gone.
+  pub async fn stream_query<F, Fut>(&self, client_id: Uuid, msg: ClientMessage, mut send: F) -> bool
+  where
+    F: FnMut(ServerMessage) -> Fut,
+    Fut: Future<Output = bool>,
+  {
+    if let Some(refusal) = self.check_message(&msg) {
+      return send(refusal).await;
+    }
+    let ClientMessage::Query {
+      id, query, params, ..
+    } = msg
+    else {
+      return send(self.handle_checked(client_id, msg).await).await;
+    };
+
+    let mut chunks = match self
+      .engine_pool
+      .stream_with_params(
+        &query,
+        &params,
+        self.project_id(),
+        self.backend.as_ref(),
+        STREAM_CHUNK_ROWS,
+      )
+      .await
+    {
+      Ok(chunks) => chunks,
+      Err(e) => {
+        return send(ServerMessage::error(id, e.to_string()).with_code(ErrorCode::InvalidQuery))
+          .await
+      }
+    };
+    let mut rows = 0;
+    while let Some(chunk) = chunks.next().await {
+      let chunk = match chunk {
+        Ok(chunk) => chunk,
+        Err(e) => {
+          return send(ServerMessage::error(id, e.to_string()).with_code(ErrorCode::InvalidQuery))
+            .await
+        }
+      };
+      rows += chunk.as_array().map_or(0, |a| a.len() as u64);
+      if !send(ServerMessage::result_chunk(&id, chunk)).await {
+        return false;
+      }
+    }
+    send(ServerMessage::result_end(id, rows)).await
   }
 
   pub async fn handle(&self, client_id: Uuid, msg: ClientMessage) -> ServerMessage {
+    if let Some(refusal) = self.check_message(&msg) {
+      return refusal;
+    }
+    self.handle_checked(client_id, msg).await
+  }
+
+  /// Run a message that passed `check_message`
+  async fn handle_checked(&self, client_id: Uuid, msg: ClientMessage) -> ServerMessage {
     match msg {
-      ClientMessage::Query { id, query } => match self.execute_query(&query).await {
-        Ok(data) => ServerMessage::result(id, data),
-        Err(e) => ServerMessage::error(id, e.to_string()),
+      ClientMessage::Query { id, stream, .. } if stream => {
+        ServerMessage::error(id, "Streamed results are only available over WebSocket")
+          .with_code(ErrorCode::BadRequest)
+      }
+      ClientMessage::Query {
+        id,
+        query,
+        cache,
+        cache_ttl,
+        params,
+        ..
+      } => {
+        let ttl = cache.then(|| {
+          cache_ttl
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_QUERY_CACHE_TTL)
+        });
+        match self.execute_query(&query, &params, ttl).await {
+          Ok(result) => ServerMessage::query_result(id, result.data, result.truncated),
+          Err(e) => ServerMessage::error(id, e.to_string()).with_code(ErrorCode::InvalidQuery),
+        }
+      }
+      ClientMessage::Subscribe {
+        id,
+        query,
+        read_your_writes,
+        ids,
+      } => match self.parse_query(&query) {
+        Ok(spec) if spec.filter.as_ref().is_some_and(|f| !f.params.is_empty()) => {
+          ServerMessage::error(id, "Subscriptions can't use query parameters")
+            .with_code(ErrorCode::InvalidQuery)
+        }
+        Ok(spec) => {
+          let encryption = self.backend.field_encryption();
+          if let Err(e) = encryption.check_query(self.backend.dialect(), &spec) {
+            return ServerMessage::error(id, e.to_string()).with_code(ErrorCode::InvalidQuery);
+          }
+          match self
+            .subs
+            .add_subscription(client_id, id.clone(), spec, read_your_writes, ids)
+            .await
+          {
+            Ok(compiled) => ServerMessage::subscribed(id, compiled),
+            Err(e) => ServerMessage::error(id, e.to_string()),
+          }
+        }
+        Err(e) => ServerMessage::error(id, e.to_string()).with_code(ErrorCode::InvalidQuery),
       },
-      ClientMessage::Subscribe { id, query } => match self.parse_query(&query) {
+      ClientMessage::Watch { id, query } => match self.parse_query(&query) {
+        Ok(spec) if spec.filter.as_ref().is_some_and(|f| !f.params.is_empty()) => {
+          ServerMessage::error(id, "Watches can't use query parameters")
+            .with_code(ErrorCode::InvalidQuery)
+        }
+        Ok(spec) if spec.map.is_some() || spec.changes.is_some() => {
+          ServerMessage::error(id, "Watched queries can't use map() or changes()")
+            .with_code(ErrorCode::InvalidQuery)
+        }
         Ok(spec) => {
-          self
+          let encryption = self.backend.field_encryption();
+          if let Err(e) = encryption.check_query(self.backend.dialect(), &spec) {
+            return ServerMessage::error(id, e.to_string()).with_code(ErrorCode::InvalidQuery);
+          }
+          let compiled = spec
+            .filter
+            .as_ref()
+            .is_some_and(|f| f.compiled_sql.is_some());
+          let max_watches = self
+            .rate_limiter
+            .as_ref()
+            .map_or(0, |limiter| limiter.max_watches_per_client());
+          match self
             .subs
-            .add_subscription(client_id, id.clone(), spec)
-            .await;
-          ServerMessage::subscribed(id)
+            .add_watch(
+              client_id,
+              id.clone(),
+              spec,
+              max_watches,
+              self.engine_pool.clone(),
+              self.backend.clone(),
+            )
+            .await
+          {
+            Ok(()) => ServerMessage::subscribed(id, compiled),
+            Err((code, e)) => ServerMessage::error(id, e).with_code(code),
+          }
         }
-        Err(e) => ServerMessage::error(id, e.to_string()),
+        Err(e) => ServerMessage::error(id, e.to_string()).with_code(ErrorCode::InvalidQuery),
       },
       ClientMessage::Unsubscribe { id } => {
         self.subs.remove_subscription(client_id, &id).await;
         ServerMessage::Unsubscribed { id }
       }
       ClientMessage::SelectProject { id, project_id } => {
-        // TODO: Store project context for this client
-        ServerMessage::ProjectSelected { id, project_id }
+        match self.select_project(project_id).await {
+          Ok(()) => {
+            self.connections.set_project(client_id, project_id);
+            ServerMessage::ProjectSelected { id, project_id }
+          }
+          Err((code, e)) => ServerMessage::error(id, e).with_code(code),
+        }
       }
       ClientMessage::Insert {
         id,
         collection,
         data,
-      } => match self
-        .backend
-        .insert(DEFAULT_PROJECT_ID, &collection, data)
-        .await
-      {
-        Ok(doc) => {
-          // Invalidate cache for this table after write
-          self.engine_pool.invalidate_table(&collection);
-          match serde_json::to_value(doc) {
-            Ok(v) => ServerMessage::result(id, v),
-            Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
+        idempotency_key,
+        preserve_created_at,
+        no_touch,
+      } => {
+        let options = WriteOptions {
+          preserve_created_at,
+          no_touch,
+          author: self.author,
+          max_documents: self.document_quota(),
+          ..Default::default()
+        };
+        let own_write = self.subs.begin_write(client_id);
+        let inserted = match idempotency_key {
+          Some(key) => {
+            insert_idempotent(
+              self.backend.as_ref(),
+              self.project_id(),
+              &collection,
+              data,
+              options,
+              &key,
+              self.idempotency_ttl,
+            )
+            .await
           }
+          None => self
+            .write(
+              &own_write,
+              &collection,
+              DocumentWrite::Insert { data, options },
+            )
+            .await
+            .and_then(|written| {
+              let (doc, change_id) =
+                written.ok_or_else(|| anyhow::anyhow!("Insert returned no document"))?;
+              Ok(IdempotentInsert::Inserted(doc, change_id))
+            }),
+        };
+        match inserted {
+          Ok(IdempotentInsert::Inserted(doc, change_id)) => {
+            // Invalidate cache for this table after write
+            self.engine_pool.invalidate_table(&collection);
+            let change_id = observe_write(own_write, change_id);
+            match serde_json::to_value(doc) {
+              Ok(v) => ServerMessage::result(id, v).with_change_id(change_id),
+              Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
+            }
+          }
+          Ok(IdempotentInsert::Replayed(v)) => ServerMessage::result(id, v),
+          Ok(IdempotentInsert::InProgress) => ServerMessage::error(
+            id,
+            "An insert with this idempotency key is still in progress",
+          )
+          .with_code(ErrorCode::Conflict),
+          Err(e) if e.downcast_ref::<RateLimitError>().is_some() => {
+            ServerMessage::error(id, e.to_string()).with_code(ErrorCode::RateLimited)
+          }
+          Err(e) => ServerMessage::error(id, e.to_string()),
         }
-        Err(e) => ServerMessage::error(id, e.to_string()),
-      },
+      }
       ClientMessage::Update {
         id,
         collection,
         document_id,
         data,
-      } => match self
-        .backend
-        .update(DEFAULT_PROJECT_ID, &collection, document_id, data)
-        .await
-      {
-        Ok(Some(doc)) => {
-          // Invalidate cache for this table after write
-          self.engine_pool.invalidate_table(&collection);
-          match serde_json::to_value(doc) {
-            Ok(v) => ServerMessage::result(id, v),
-            Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
+        no_touch,
+        apply_defaults,
+      } => {
+        let own_write = self.subs.begin_write(client_id);
+        let options = WriteOptions {
+          no_touch,
+          apply_defaults,
+          author: self.author,
+          ..Default::default()
+        };
+        let write = DocumentWrite::Update {
+          id: document_id,
+          data,
+          options,
+        };
+        match self.write(&own_write, &collection, write).await {
+          Ok(Some((doc, change_id))) => {
+            // Invalidate cache for this table after write
+            self.engine_pool.invalidate_table(&collection);
+            let change_id = observe_write(own_write, change_id);
+            match serde_json::to_value(doc) {
+              Ok(v) => ServerMessage::result(id, v).with_change_id(change_id),
+              Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
+            }
           }
+          Ok(None) => ServerMessage::error(
+            id,
+            format!(
+              "Document {} not found in collection '{}'",
+              document_id, collection
+            ),
+          )
+          .with_code(ErrorCode::NotFound),
+          Err(e) => ServerMessage::error(id, e.to_string()),
         }
-        Ok(None) => ServerMessage::error(
-          id,
-          format!(
-            "Document {} not found in collection '{}'",
-            document_id, collection
-          ),
-        ),
-        Err(e) => ServerMessage::error(id, e.to_string()),
-      },
+      }
       ClientMessage::Delete {
         id,
         collection,
         document_id,
-      } => match self
-        .backend
-        .delete(DEFAULT_PROJECT_ID, &collection, document_id)
-        .await
-      {
-        Ok(Some(doc)) => {
-          // Invalidate cache for this table after write
-          self.engine_pool.invalidate_table(&collection);
-          match serde_json::to_value(doc) {
-            Ok(v) => ServerMessage::result(id, v),
-            Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
+      } => {
+        let own_write = self.subs.begin_write(client_id);
+        let write = DocumentWrite::Delete { id: document_id };
+        match self.write(&own_write, &collection, write).await {
+          Ok(Some((doc, change_id))) => {
+            // Invalidate cache for this table after write
+            self.engine_pool.invalidate_table(&collection);
+            let change_id = observe_write(own_write, change_id);
+            match serde_json::to_value(doc) {
+              Ok(v) => ServerMessage::result(id, v).with_change_id(change_id),
+              Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
+            }
           }
+          Ok(None) => ServerMessage::error(
+            id,
+            format!(
+              "Document {} not found in collection '{}'",
+              document_id, collection
+            ),
+          )
+          .with_code(ErrorCode::NotFound),
+          Err(e) => ServerMessage::error(id, e.to_string()),
         }
-        Ok(None) => ServerMessage::error(
-          id,
-          format!(
-            "Document {} not found in collection '{}'",
-            document_id, collection
-          ),
-        ),
-        Err(e) => ServerMessage::error(id, e.to_string()),
-      },
+      }
+      ClientMessage::Restore {
+        id,
+        collection,
+        document_id,
+      } => {
+        let own_write = self.subs.begin_write(client_id);
+        let write = DocumentWrite::Restore { id: document_id };
+        match self.write(&own_write, &collection, write).await {
+          Ok(Some((doc, change_id))) => {
+            self.engine_pool.invalidate_table(&collection);
+            let change_id = observe_write(own_write, change_id);
+            match serde_json::to_value(doc) {
+              Ok(v) => ServerMessage::result(id, v).with_change_id(change_id),
+              Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
+            }
+          }
+          Ok(None) => ServerMessage::error(
+            id,
+            format!(
+              "No deleted document {} in collection '{}'",
+              document_id, collection
+            ),
+          )
+          .with_code(ErrorCode::NotFound),
+          Err(e) => ServerMessage::error(id, e.to_string()),
+        }
+      }
       ClientMessage::ListCollections { id } => {
-        match self.backend.list_collections(DEFAULT_PROJECT_ID).await {
+        match self.backend.list_collections(self.project_id()).await {
           Ok(cols) => match serde_json::to_value(cols) {
             Ok(v) => ServerMessage::result(id, v),
             Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
@@ -157,13 +633,40 @@ impl MessageHandler {
         }
       }
       ClientMessage::ListProjects { id } => match self.backend.list_projects().await {
-        Ok(projects) => match serde_json::to_value(projects) {
-          Ok(v) => ServerMessage::result(id, v),
-          Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
-        },
+        Ok(mut projects) => {
+          // Scoped tokens only see their own project
+          if let Some(bound) = self.bound_project {
+            projects.retain(|p| p.id == bound);
+          }
+          match serde_json::to_value(projects) {
+            Ok(v) => ServerMessage::result(id, v),
+            Err(e) => ServerMessage::error(id, format!("Serialization error: {}", e)),
+          }
+        }
         Err(e) => ServerMessage::error(id, e.to_string()),
       },
       ClientMessage::Ping { id } => ServerMessage::pong(id),
+      ClientMessage::Hello { id, version } => match self.negotiate_version(version) {
+        Ok(version) => ServerMessage::Hello {
+          id,
+          version,
+          server: self.server_info.clone(),
+        },
+        Err((code, e)) => ServerMessage::error(id, e).with_code(code),
+      },
+      // Authentication happens when the connection is set up
+      ClientMessage::Authenticate { id, .. } => {
+        ServerMessage::error(id, "Connection is already authenticated")
+          .with_code(ErrorCode::BadRequest)
+      }
     }
   }
 }
+
+/// Record the change a write produced as seen by the client's
+/// read-your-writes subscriptions. Returns the change ID when the client has
+/// such subscriptions.
+fn observe_write(own_write: Option<OwnWrite<'_>>, change_id: Option<i64>) -> Option<i64> {
+  own_write?.finish(change_id);
+  change_id
+}
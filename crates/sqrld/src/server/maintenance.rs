@@ -0,0 +1,200 @@
+//! Maintenance mode.
+//!
+//! While enabled, data-plane writes over REST, WebSocket and TCP are refused
+//! with `503 Service Unavailable` (or an `unavailable` error) so the database
+//! can be migrated or upgraded safely. Reads can optionally be refused too.
+//! The admin API keeps working so the switch can be flipped back. The state
+//! is persisted in the `maintenance` feature settings and survives restarts.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::db::DatabaseBackend;
+use crate::types::ClientMessage;
+
+/// Feature settings row holding the maintenance state
+pub const MAINTENANCE_FEATURE: &str = "maintenance";
+
+/// `Retry-After` sent when none is configured
+pub const DEFAULT_RETRY_AFTER_SECS: u64 = 60;
+
+fn default_retry_after_secs() -> u64 {
+  DEFAULT_RETRY_AFTER_SECS
+}
+
+/// Maintenance mode settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Refuse reads as well as writes
+  #[serde(default)]
+  pub block_reads: bool,
+  /// Seconds clients are told to wait before retrying
+  #[serde(default = "default_retry_after_secs")]
+  pub retry_after_secs: u64,
+  /// Shown to clients in the error message
+  #[serde(default)]
+  pub message: Option<String>,
+}
+
+impl Default for MaintenanceSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      block_reads: false,
+      retry_after_secs: DEFAULT_RETRY_AFTER_SECS,
+      message: None,
+    }
+  }
+}
+
+/// Maintenance state shared by the servers, replaceable at runtime
+#[derive(Clone, Default)]
+pub struct Maintenance(Arc<RwLock<MaintenanceSettings>>);
+
+impl Maintenance {
+  pub fn new(settings: MaintenanceSettings) -> Self {
+    Self(Arc::new(RwLock::new(settings)))
+  }
+
+  pub fn settings(&self) -> MaintenanceSettings {
+    self.0.read().clone()
+  }
+
+  pub fn set(&self, settings: MaintenanceSettings) {
+    *self.0.write() = settings;
+  }
+
+  /// Load the persisted state, leaving maintenance off if none is stored
+  pub async fn load(&self, backend: &dyn DatabaseBackend) -> Result<(), anyhow::Error> {
+    if let Some((enabled, settings)) = backend.get_feature_settings(MAINTENANCE_FEATURE).await? {
+      let mut settings: MaintenanceSettings = serde_json::from_value(settings).unwrap_or_default();
+      settings.enabled = enabled;
+      self.set(settings);
+    }
+    Ok(())
+  }
+
+  /// Store `settings` and apply them
+  pub async fn save(
+    &self,
+    backend: &dyn DatabaseBackend,
+    settings: MaintenanceSettings,
+  ) -> Result<(), anyhow::Error> {
+    backend
+      .update_feature_settings(
+        MAINTENANCE_FEATURE,
+        settings.enabled,
+        serde_json::to_value(&settings)?,
+      )
+      .await?;
+    self.set(settings);
+    Ok(())
+  }
+
+  /// Why a request is refused, or None if it may run. `write` tells whether
+  /// the request changes data.
+  pub fn check(&self, write: bool) -> Option<MaintenanceRefusal> {
+    let settings = self.0.read();
+    if !settings.enabled || !(write || settings.block_reads) {
+      return None;
+    }
+    Some(MaintenanceRefusal {
+      message: settings
+        .message
+        .clone()
+        .unwrap_or_else(|| "Server is in maintenance mode".to_string()),
+      retry_after: Duration::from_secs(settings.retry_after_secs),
+    })
+  }
+
+  /// Check a client message, treating inserts, updates, deletes and restores
+  /// as writes. Pings and project selection are always allowed.
+  pub fn check_message(&self, msg: &ClientMessage) -> Option<MaintenanceRefusal> {
+    match msg {
+      ClientMessage::Ping { .. }
+      | ClientMessage::Hello { .. }
+      | ClientMessage::Authenticate { .. }
+      | ClientMessage::SelectProject { .. }
+      | ClientMessage::Unsubscribe { .. } => None,
+      ClientMessage::Insert { .. }
+      | ClientMessage::Update { .. }
+      | ClientMessage::Delete { .. }
+      | ClientMessage::Restore { .. } => self.check(true),
+      _ => self.check(false),
+    }
+  }
+}
+
+/// A request refused because of maintenance mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceRefusal {
+  pub message: String,
+  pub retry_after: Duration,
+}
+
+impl std::fmt::Display for MaintenanceRefusal {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{}; retry after {}s",
+      self.message,
+      self.retry_after.as_secs()
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_maintenance_check() {
+    let maintenance = Maintenance::default();
+    assert!(maintenance.check(true).is_none());
+
+    maintenance.set(MaintenanceSettings {
+      enabled: true,
+      retry_after_secs: 30,
+      ..Default::default()
+    });
+    let refusal = maintenance.check(true).unwrap();
+    assert_eq!(refusal.retry_after, Duration::from_secs(30));
+    assert_eq!(
+      refusal.to_string(),
+      "Server is in maintenance mode; retry after 30s"
+    );
+    assert!(maintenance.check(false).is_none());
+
+    maintenance.set(MaintenanceSettings {
+      enabled: true,
+      block_reads: true,
+      message: Some("Upgrading".to_string()),
+      ..Default::default()
+    });
+    assert_eq!(maintenance.check(false).unwrap().message, "Upgrading");
+  }
+
+  #[test]
+  fn test_maintenance_check_message() {
+    let maintenance = Maintenance::new(MaintenanceSettings {
+      enabled: true,
+      ..Default::default()
+    });
+    let insert: ClientMessage = serde_json::from_value(serde_json::json!({
+      "type": "insert", "id": "1", "collection": "c", "data": {}
+    }))
+    .unwrap();
+    let ping: ClientMessage =
+      serde_json::from_value(serde_json::json!({"type": "ping", "id": "2"})).unwrap();
+    let list: ClientMessage =
+      serde_json::from_value(serde_json::json!({"type": "listcollections", "id": "3"})).unwrap();
+    assert!(maintenance.check_message(&insert).is_some());
+    assert!(maintenance.check_message(&ping).is_none());
+    assert!(maintenance.check_message(&list).is_none());
+  }
+}
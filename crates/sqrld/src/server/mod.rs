@@ -1,16 +1,36 @@
 mod config;
+mod connections;
 mod daemon;
 mod handler;
+mod maintenance;
+mod numbers;
 mod rate_limiter;
+mod reload;
 mod tcp;
+mod tls;
+mod trace;
 mod websocket;
 
 pub use config::{
-  AuthSection, BackendType, CachingSection, FeaturesSection, LimitsSection, PortsSection,
-  ProtocolsSection, ServerConfig, StorageSection,
+  AuthNotificationsSection, AuthSection, BackendType, BackupCompression, BackupSection,
+  BannerSection, CachingSection, EncryptionSection, FeaturesSection, InexactNumbers, LimitsSection,
+  OidcSection, PasswordAlgorithm, PasswordHashingSection, PortsSection, ProtocolsSection,
+  QueryCostAction, RawSqlSection, SecurityHeadersSection, ServerConfig, StorageSection, TlsSection,
 };
-pub use daemon::Daemon;
-pub use handler::MessageHandler;
-pub use rate_limiter::{QueryPermit, RateLimitError, RateLimiter};
-pub use tcp::TcpServer;
+pub use connections::{ConnectionHandle, ConnectionInfo, Connections};
+pub use daemon::{ConfigLoader, Daemon, LogLevelSetter};
+pub use handler::{MessageHandler, STREAM_CHUNK_ROWS};
+pub use maintenance::{
+  Maintenance, MaintenanceRefusal, MaintenanceSettings, DEFAULT_RETRY_AFTER_SECS,
+  MAINTENANCE_FEATURE,
+};
+pub use numbers::{apply_inexact_numbers, decode_client_message, InexactNumber};
+pub use rate_limiter::{
+  ConnectionPermit, QueryPermit, RateLimitError, RateLimitStatus, RateLimiter,
+};
+pub use reload::{ConfigChanges, CorsOrigins, RELOADABLE};
+pub use tcp::{Encoding, TcpServer};
+pub use tls::{MaybeTlsStream, ServerListener, ServerTls};
+pub use trace::{accept_trace_id, current_trace_id, new_trace_id, with_trace_id, TRACE_ID_HEADER};
 pub use websocket::WebSocketServer;
+pub(crate) use websocket::{queue_message, Clients as WsClients, SEND_TIMEOUT};
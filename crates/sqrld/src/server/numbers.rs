@@ -0,0 +1,234 @@
+//! Exact handling of large and high-precision JSON numbers.
+//!
+//! serde_json parses every number into an `i64`, `u64` or `f64`, so an
+//! integer outside the 64-bit range or a decimal with more digits than a
+//! double holds silently changes on the way in. The raw JSON text is checked
+//! before parsing, and such numbers are kept, rejected or turned into strings
+//! as `limits.inexact_numbers` says.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use super::config::InexactNumbers;
+use crate::types::{ClientMessage, ErrorCode, ServerMessage};
+
+/// A number in a message that can't be stored exactly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InexactNumber(pub String);
+
+impl fmt::Display for InexactNumber {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Number {} cannot be stored exactly; send it as a string",
+      self.0
+    )
+  }
+}
+
+impl std::error::Error for InexactNumber {}
+
+/// Apply `mode` to the numbers in JSON `text`. Returns the text unchanged
+/// unless it holds a number that would lose precision.
+pub fn apply_inexact_numbers(
+  text: &str,
+  mode: InexactNumbers,
+) -> Result<Cow<'_, str>, InexactNumber> {
+  if mode == InexactNumbers::Allow {
+    return Ok(Cow::Borrowed(text));
+  }
+
+  let bytes = text.as_bytes();
+  let mut out: Option<String> = None;
+  let mut copied = 0;
+  let mut i = 0;
+  while i < bytes.len() {
+    match bytes[i] {
+      b'"' => i = skip_string(bytes, i),
+      b'-' | b'0'..=b'9' => {
+        let end = number_end(bytes, i);
+        let literal = &text[i..end];
+        if !is_exact(literal) {
+          if mode == InexactNumbers::Reject {
+            return Err(InexactNumber(literal.to_string()));
+          }
+          let out = out.get_or_insert_with(|| String::with_capacity(text.len() + 16));
+          out.push_str(&text[copied..i]);
+          out.push('"');
+          out.push_str(literal);
+          out.push('"');
+          copied = end;
+        }
+        i = end;
+      }
+      _ => i += 1,
+    }
+  }
+
+  Ok(match out {
+    Some(mut out) => {
+      out.push_str(&text[copied..]);
+      Cow::Owned(out)
+    }
+    None => Cow::Borrowed(text),
+  })
+}
+
+/// Parse a JSON client message, applying `mode` to its numbers. Failures come
+/// back as the error response to send.
+pub fn decode_client_message(
+  text: &str,
+  mode: InexactNumbers,
+) -> Result<ClientMessage, ServerMessage> {
+  let text = apply_inexact_numbers(text, mode).map_err(|e| {
+    // Numbers out of f64 range would fail the parse, so read the id from the
+    // string form
+    let id = apply_inexact_numbers(text, InexactNumbers::String)
+      .ok()
+      .and_then(|t| serde_json::from_str::<serde_json::Value>(&t).ok())
+      .and_then(|v| v.get("id").and_then(|id| id.as_str()).map(String::from))
+      .unwrap_or_else(|| "0".to_string());
+    ServerMessage::error(id, e.to_string()).with_code(ErrorCode::InvalidMessage)
+  })?;
+  serde_json::from_str(&text).map_err(|e| ServerMessage::parse_error(&text, &e))
+}
+
+/// Index just past the string starting at `start` (an opening quote)
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+  let mut i = start + 1;
+  while i < bytes.len() {
+    match bytes[i] {
+      b'\\' => i += 2,
+      b'"' => return i + 1,
+      _ => i += 1,
+    }
+  }
+  bytes.len()
+}
+
+/// Index just past the number literal starting at `start`
+fn number_end(bytes: &[u8], start: usize) -> usize {
+  let mut i = start + 1;
+  while i < bytes.len() && matches!(bytes[i], b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') {
+    i += 1;
+  }
+  i
+}
+
+/// Whether serde_json parses `literal` to a number with the same value
+fn is_exact(literal: &str) -> bool {
+  if !literal.contains(['.', 'e', 'E']) {
+    let digits = literal.trim_start_matches('-');
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+      // Malformed: leave it for the JSON parser to report
+      return true;
+    }
+    return literal.parse::<i64>().is_ok() || literal.parse::<u64>().is_ok();
+  }
+  match literal.parse::<f64>() {
+    // The shortest repr of the parsed double has the same digits exactly
+    // when no precision was lost
+    Ok(f) if f.is_finite() => {
+      significant_digits(literal) == significant_digits(&format!("{:e}", f))
+    }
+    // Malformed: leave it for the JSON parser to report
+    Err(_) => true,
+    Ok(_) => false,
+  }
+}
+
+/// Significant digits of a decimal literal and the power of ten of the first
+/// one, so equal values compare equal however they are written
+fn significant_digits(literal: &str) -> (String, i64) {
+  let literal = literal.trim_start_matches('-');
+  let (mantissa, exp) = match literal.find(['e', 'E']) {
+    Some(i) => (&literal[..i], literal[i + 1..].parse::<i64>().unwrap_or(0)),
+    None => (literal, 0),
+  };
+  let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+  let digits = format!("{}{}", int, frac);
+  let trimmed = digits.trim_start_matches('0');
+  let leading_zeros = (digits.len() - trimmed.len()) as i64;
+  let significant = trimmed.trim_end_matches('0');
+  if significant.is_empty() {
+    return (String::new(), 0);
+  }
+  (
+    significant.to_string(),
+    exp + int.len() as i64 - leading_zeros,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_exact_numbers() {
+    for n in [
+      "0",
+      "-0",
+      "42",
+      "9007199254740993",
+      "-9223372036854775808",
+      "18446744073709551615",
+      "1.5",
+      "1.50",
+      "0.1",
+      "-2.5e-3",
+      "1e3",
+      "0.0",
+    ] {
+      assert!(is_exact(n), "{} should be exact", n);
+    }
+    for n in [
+      "18446744073709551616",
+      "-9223372036854775809",
+      "0.1000000000000000055",
+      "3.14159265358979323846",
+      "1e400",
+    ] {
+      assert!(!is_exact(n), "{} should be inexact", n);
+    }
+  }
+
+  #[test]
+  fn test_apply_inexact_numbers() {
+    let text = r#"{"id":"1","big":123456789012345678901234,"s":"99999999999999999999999","n":7}"#;
+    assert_eq!(
+      apply_inexact_numbers(text, InexactNumbers::Allow).unwrap(),
+      text
+    );
+    assert_eq!(
+      apply_inexact_numbers(text, InexactNumbers::String).unwrap(),
+      r#"{"id":"1","big":"123456789012345678901234","s":"99999999999999999999999","n":7}"#
+    );
+    assert_eq!(
+      apply_inexact_numbers(text, InexactNumbers::Reject).unwrap_err(),
+      InexactNumber("123456789012345678901234".to_string())
+    );
+
+    // Nothing to change: no copy
+    let text = r#"{"a":[1,2.5,-3e2],"b":"x\"1e999"}"#;
+    assert!(matches!(
+      apply_inexact_numbers(text, InexactNumbers::Reject).unwrap(),
+      Cow::Borrowed(_)
+    ));
+  }
+
+  #[test]
+  fn test_decode_client_message_rejects_inexact() {
+    let text = r#"{"type":"insert","id":"i1","collection":"c","data":{"v":1e400}}"#;
+    match decode_client_message(text, InexactNumbers::Reject) {
+      Err(ServerMessage::Error { id, code, .. }) => {
+        assert_eq!(id, "i1");
+        assert_eq!(code, Some(ErrorCode::InvalidMessage));
+      }
+      other => panic!("Expected an error, got {:?}", other),
+    }
+    match decode_client_message(text, InexactNumbers::String) {
+      Ok(ClientMessage::Insert { data, .. }) => assert_eq!(data["v"], "1e400"),
+      other => panic!("Expected an insert, got {:?}", other),
+    }
+  }
+}
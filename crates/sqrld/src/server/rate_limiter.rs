@@ -1,8 +1,9 @@
 //! Rate limiting and connection management.
 //!
 //! Provides:
-//! - Connection limits per IP address
+//! - Connection limits per IP address and across the server
 //! - Request rate limiting using token bucket algorithm
+//! - Per-project request rates and document quotas
 //! - Concurrent query limiting per client
 //! - Optional PostgreSQL backend for distributed rate limiting
 
@@ -15,19 +16,28 @@ use std::time::{Duration, Instant};
 use parking_lot::RwLock;
 use uuid::Uuid;
 
-use super::config::LimitsSection;
-use crate::db::DatabaseBackend;
+use super::config::{InexactNumbers, LimitsSection};
+use crate::db::{DatabaseBackend, ProjectLimits};
 
 /// Rate limiter for managing connections and request rates.
 /// Supports both in-memory (single-instance) and PostgreSQL-backed (distributed) modes.
 pub struct RateLimiter {
-  config: LimitsSection,
+  /// Current limits; replaced on config reload
+  config: RwLock<LimitsSection>,
   /// Connections per IP: IP -> count (in-memory fallback)
   connections: RwLock<HashMap<IpAddr, u32>>,
+  /// Open connections across all IPs, updated under the `connections` lock
+  total_connections: AtomicU32,
   /// Token buckets per IP: IP -> TokenBucket (in-memory fallback)
   buckets: RwLock<HashMap<IpAddr, TokenBucket>>,
   /// Concurrent queries per client: client_id -> count
   concurrent_queries: RwLock<HashMap<Uuid, Arc<AtomicU32>>>,
+  /// Per-user token buckets for rate-limited admin operations
+  user_buckets: RwLock<HashMap<Uuid, TokenBucket>>,
+  /// Quotas for projects that have them, mirrored from the `project_limits` table
+  project_limits: RwLock<HashMap<Uuid, ProjectLimits>>,
+  /// Token buckets per project: project_id -> TokenBucket
+  project_buckets: RwLock<HashMap<Uuid, TokenBucket>>,
   /// Optional database backend for distributed rate limiting
   backend: Option<Arc<dyn DatabaseBackend>>,
 }
@@ -50,6 +60,16 @@ impl TokenBucket {
     }
   }
 
+  /// Bucket allowing `n` operations per minute, all available at once.
+  fn per_minute(n: u32) -> Self {
+    Self {
+      tokens: n as f64,
+      last_update: Instant::now(),
+      rate: n as f64 / 60.0,
+      capacity: n as f64,
+    }
+  }
+
   /// Try to consume a token. Returns true if successful.
   fn try_consume(&mut self) -> bool {
     self.refill();
@@ -61,6 +81,16 @@ impl TokenBucket {
     }
   }
 
+  /// Budget left after refilling
+  fn status(&mut self) -> RateLimitStatus {
+    self.refill();
+    RateLimitStatus {
+      limit: self.capacity as u32,
+      remaining: self.tokens.floor() as u32,
+      reset: Duration::from_secs_f64((self.capacity - self.tokens) / self.rate),
+    }
+  }
+
   /// Refill tokens based on elapsed time.
   fn refill(&mut self) {
     let now = Instant::now();
@@ -73,10 +103,14 @@ impl TokenBucket {
 impl RateLimiter {
   pub fn new(config: LimitsSection) -> Self {
     Self {
-      config,
+      config: RwLock::new(config),
       connections: RwLock::new(HashMap::new()),
+      total_connections: AtomicU32::new(0),
       buckets: RwLock::new(HashMap::new()),
       concurrent_queries: RwLock::new(HashMap::new()),
+      user_buckets: RwLock::new(HashMap::new()),
+      project_limits: RwLock::new(HashMap::new()),
+      project_buckets: RwLock::new(HashMap::new()),
       backend: None,
     }
   }
@@ -84,57 +118,73 @@ impl RateLimiter {
   /// Create a RateLimiter with a database backend for distributed rate limiting
   pub fn with_backend(config: LimitsSection, backend: Arc<dyn DatabaseBackend>) -> Self {
     Self {
-      config,
+      config: RwLock::new(config),
       connections: RwLock::new(HashMap::new()),
+      total_connections: AtomicU32::new(0),
       buckets: RwLock::new(HashMap::new()),
       concurrent_queries: RwLock::new(HashMap::new()),
+      user_buckets: RwLock::new(HashMap::new()),
+      project_limits: RwLock::new(HashMap::new()),
+      project_buckets: RwLock::new(HashMap::new()),
       backend: Some(backend),
     }
   }
 
-  /// Check if a new connection from this IP is allowed.
-  /// If allowed, increments the connection count and returns Ok.
+  /// Check if a new connection from this IP is allowed, both against the
+  /// per-IP limit and the server-wide limit.
+  /// If allowed, increments the connection counts and returns Ok; every
+  /// successful call must be paired with `release_connection`.
   /// If not allowed, returns Err with a message.
-  /// Uses PostgreSQL for distributed tracking when backend is available.
   pub fn check_connection(&self, ip: IpAddr) -> Result<(), RateLimitError> {
-    if self.config.max_connections_per_ip == 0 {
-      return Ok(()); // Unlimited
-    }
+    let (per_ip, total) = {
+      let config = self.config.read();
+      (config.max_connections_per_ip, config.max_connections_total)
+    };
 
     // Use in-memory tracking (PostgreSQL tracking is async, used separately)
     let mut conns = self.connections.write();
-    let count = conns.entry(ip).or_insert(0);
-
-    if *count >= self.config.max_connections_per_ip {
-      return Err(RateLimitError::TooManyConnections {
-        ip,
-        limit: self.config.max_connections_per_ip,
-      });
+    if total > 0 && self.total_connections.load(Ordering::SeqCst) >= total {
+      return Err(RateLimitError::TooManyConnectionsTotal { limit: total });
+    }
+    let count = conns.get(&ip).copied().unwrap_or(0);
+    if per_ip > 0 && count >= per_ip {
+      return Err(RateLimitError::TooManyConnections { ip, limit: per_ip });
     }
 
-    *count += 1;
+    conns.insert(ip, count + 1);
+    self.total_connections.fetch_add(1, Ordering::SeqCst);
     Ok(())
   }
 
+  /// Check a new connection and return a guard that releases it on drop.
+  pub fn acquire_connection(
+    self: &Arc<Self>,
+    ip: IpAddr,
+  ) -> Result<ConnectionPermit, RateLimitError> {
+    self.check_connection(ip)?;
+    Ok(ConnectionPermit {
+      limiter: self.clone(),
+      ip,
+    })
+  }
+
+  /// Number of open connections across all IPs.
+  pub fn open_connections(&self) -> u32 {
+    self.total_connections.load(Ordering::SeqCst)
+  }
+
   /// Async version of check_connection that uses PostgreSQL for distributed tracking
   pub async fn check_connection_async(&self, ip: IpAddr) -> Result<(), RateLimitError> {
-    if self.config.max_connections_per_ip == 0 {
+    let limit = self.config.read().max_connections_per_ip;
+    if limit == 0 {
       return Ok(()); // Unlimited
     }
 
     // Try PostgreSQL-backed check first for distributed tracking
     if let Some(ref backend) = self.backend {
-      match backend
-        .connection_acquire(ip, self.config.max_connections_per_ip)
-        .await
-      {
+      match backend.connection_acquire(ip, limit).await {
         Ok(allowed) if allowed => return Ok(()),
-        Ok(_) => {
-          return Err(RateLimitError::TooManyConnections {
-            ip,
-            limit: self.config.max_connections_per_ip,
-          })
-        }
+        Ok(_) => return Err(RateLimitError::TooManyConnections { ip, limit }),
         Err(e) => {
           // Log error and fall back to in-memory
           tracing::warn!("PostgreSQL rate limit check failed, using in-memory: {}", e);
@@ -155,6 +205,7 @@ impl RateLimiter {
       if *count == 0 {
         conns.remove(&ip);
       }
+      self.total_connections.fetch_sub(1, Ordering::SeqCst);
     }
   }
 
@@ -174,14 +225,18 @@ impl RateLimiter {
   /// Check if a request is allowed under rate limiting.
   /// Returns Ok if allowed, Err if rate limited.
   pub fn check_request(&self, ip: IpAddr) -> Result<(), RateLimitError> {
-    if self.config.requests_per_second == 0 {
+    let (rate, burst) = {
+      let config = self.config.read();
+      (config.requests_per_second, config.burst_size)
+    };
+    if rate == 0 {
       return Ok(()); // Unlimited
     }
 
     let mut buckets = self.buckets.write();
     let bucket = buckets
       .entry(ip)
-      .or_insert_with(|| TokenBucket::new(self.config.requests_per_second, self.config.burst_size));
+      .or_insert_with(|| TokenBucket::new(rate, burst));
 
     if bucket.try_consume() {
       Ok(())
@@ -193,23 +248,41 @@ impl RateLimiter {
     }
   }
 
+  /// Request budget of an IP, or None when requests aren't rate limited.
+  /// An IP that hasn't made a request yet has the full burst available.
+  pub fn request_status(&self, ip: IpAddr) -> Option<RateLimitStatus> {
+    let (rate, burst) = {
+      let config = self.config.read();
+      (config.requests_per_second, config.burst_size)
+    };
+    if rate == 0 {
+      return None;
+    }
+    let status = match self.buckets.write().get_mut(&ip) {
+      Some(bucket) => bucket.status(),
+      None => TokenBucket::new(rate, burst).status(),
+    };
+    Some(status)
+  }
+
   /// Async version of check_request that uses PostgreSQL for distributed rate limiting
   pub async fn check_request_async(&self, ip: IpAddr) -> Result<(), RateLimitError> {
-    if self.config.requests_per_second == 0 {
+    let (rate, burst) = {
+      let config = self.config.read();
+      (config.requests_per_second, config.burst_size)
+    };
+    if rate == 0 {
       return Ok(()); // Unlimited
     }
 
     // Try PostgreSQL-backed check first for distributed rate limiting
     if let Some(ref backend) = self.backend {
-      match backend
-        .rate_limit_check(ip, self.config.requests_per_second, self.config.burst_size)
-        .await
-      {
+      match backend.rate_limit_check(ip, rate, burst).await {
         Ok(allowed) if allowed => return Ok(()),
         Ok(_) => {
           return Err(RateLimitError::RateLimited {
             ip,
-            retry_after: Duration::from_secs_f64(1.0 / self.config.requests_per_second as f64),
+            retry_after: Duration::from_secs_f64(1.0 / rate as f64),
           })
         }
         Err(e) => {
@@ -223,9 +296,100 @@ impl RateLimiter {
     self.check_request(ip)
   }
 
+  /// Check a per-user allowance of `per_minute` operations (0 = unlimited).
+  pub fn check_user_request(&self, user_id: Uuid, per_minute: u32) -> Result<(), RateLimitError> {
+    if per_minute == 0 {
+      return Ok(());
+    }
+
+    let mut buckets = self.user_buckets.write();
+    let bucket = buckets
+      .entry(user_id)
+      .or_insert_with(|| TokenBucket::per_minute(per_minute));
+
+    if bucket.try_consume() {
+      Ok(())
+    } else {
+      Err(RateLimitError::UserRateLimited {
+        user_id,
+        retry_after: Duration::from_secs_f64(1.0 / bucket.rate),
+      })
+    }
+  }
+
+  /// Load the stored per-project limits, replacing any already held.
+  pub async fn load_project_limits(
+    &self,
+    backend: &dyn DatabaseBackend,
+  ) -> Result<(), anyhow::Error> {
+    let limits = backend.list_project_limits().await?;
+    *self.project_limits.write() = limits.into_iter().collect();
+    self.project_buckets.write().clear();
+    Ok(())
+  }
+
+  /// Limits for a project, if it has any.
+  pub fn project_limits(&self, project_id: Uuid) -> Option<ProjectLimits> {
+    self.project_limits.read().get(&project_id).copied()
+  }
+
+  /// Set a project's limits. Its token bucket is reset so the new rate
+  /// applies to the next request.
+  pub fn set_project_limits(&self, project_id: Uuid, limits: ProjectLimits) {
+    self.project_limits.write().insert(project_id, limits);
+    self.project_buckets.write().remove(&project_id);
+  }
+
+  /// Remove a project's limits.
+  pub fn remove_project_limits(&self, project_id: Uuid) {
+    self.project_limits.write().remove(&project_id);
+    self.project_buckets.write().remove(&project_id);
+  }
+
+  /// Check a request against the project's rate, shared by every client
+  /// using the project. Projects without limits are unlimited.
+  pub fn check_project_request(&self, project_id: Uuid) -> Result<(), RateLimitError> {
+    let Some(limits) = self.project_limits(project_id) else {
+      return Ok(());
+    };
+    let rate = limits.requests_per_second;
+    if rate == 0 {
+      return Ok(());
+    }
+    let burst = if limits.burst_size == 0 {
+      rate
+    } else {
+      limits.burst_size
+    };
+
+    let mut buckets = self.project_buckets.write();
+    let bucket = buckets
+      .entry(project_id)
+      .or_insert_with(|| TokenBucket::new(rate, burst));
+
+    if bucket.try_consume() {
+      Ok(())
+    } else {
+      Err(RateLimitError::ProjectRateLimited {
+        project_id,
+        limit: rate,
+        retry_after: Duration::from_secs_f64(1.0 / bucket.rate),
+      })
+    }
+  }
+
+  /// The project's document quota, 0 when it has none. Writes pass this to
+  /// the backend, which checks it in the same transaction as the insert.
+  pub fn document_quota(&self, project_id: Uuid) -> u64 {
+    self
+      .project_limits(project_id)
+      .map_or(0, |l| l.max_documents)
+  }
+
   /// Get a query permit for a client. Returns a guard that releases the permit on drop.
   pub fn acquire_query_permit(&self, client_id: Uuid) -> Result<QueryPermit, RateLimitError> {
-    if self.config.max_concurrent_queries == 0 {
+    let limit = self.config.read().max_concurrent_queries;
+    if limit == 0 {
       return Ok(QueryPermit {
         counter: None,
         client_id,
@@ -241,12 +405,9 @@ impl RateLimiter {
     };
 
     let current = counter.fetch_add(1, Ordering::SeqCst);
-    if current >= self.config.max_concurrent_queries {
+    if current >= limit {
       counter.fetch_sub(1, Ordering::SeqCst);
-      return Err(RateLimitError::TooManyConcurrentQueries {
-        client_id,
-        limit: self.config.max_concurrent_queries,
-      });
+      return Err(RateLimitError::TooManyConcurrentQueries { client_id, limit });
     }
 
     Ok(QueryPermit {
@@ -257,16 +418,78 @@ impl RateLimiter {
 
   /// Get the query timeout duration.
   pub fn query_timeout(&self) -> Option<Duration> {
-    if self.config.query_timeout_ms == 0 {
-      None
-    } else {
-      Some(Duration::from_millis(self.config.query_timeout_ms))
+    match self.config.read().query_timeout_ms {
+      0 => None,
+      ms => Some(Duration::from_millis(ms)),
+    }
+  }
+
+  /// Get the slow query logging threshold (None = disabled).
+  pub fn slow_query_threshold(&self) -> Option<Duration> {
+    match self.config.read().slow_query_ms {
+      0 => None,
+      ms => Some(Duration::from_millis(ms)),
     }
   }
 
+  /// Whether a request that took `elapsed` should be logged as slow.
+  pub fn is_slow(&self, elapsed: Duration) -> bool {
+    self
+      .slow_query_threshold()
+      .is_some_and(|threshold| elapsed >= threshold)
+  }
+
+  /// Get the max watched queries per client (0 = unlimited).
+  pub fn max_watches_per_client(&self) -> usize {
+    self.config.read().max_watches_per_client
+  }
+
   /// Get the max message size.
   pub fn max_message_size(&self) -> usize {
-    self.config.max_message_size
+    self.config.read().max_message_size
+  }
+
+  /// Get the outbound queue capacity for a WebSocket client.
+  pub fn max_send_queue(&self) -> usize {
+    self.config.read().max_send_queue.max(1)
+  }
+
+  /// Get the max document size (0 = unlimited).
+  pub fn max_document_bytes(&self) -> usize {
+    self.config.read().max_document_bytes
+  }
+
+  /// How long an insert idempotency key is remembered
+  pub fn idempotency_key_ttl(&self) -> Duration {
+    Duration::from_secs(self.config.read().idempotency_key_ttl_secs)
+  }
+
+  pub fn inexact_numbers(&self) -> InexactNumbers {
+    self.config.read().inexact_numbers
+  }
+
+  /// Get the max document nesting depth (0 = unlimited).
+  pub fn max_document_depth(&self) -> usize {
+    self.config.read().max_document_depth
+  }
+
+  /// Check a document against the configured size limit.
+  pub fn check_document_size(&self, data: &serde_json::Value) -> Result<(), RateLimitError> {
+    check_document_size(data, self.max_document_bytes())
+  }
+
+  /// Check a document against the configured size and depth limits.
+  pub fn check_document(&self, data: &serde_json::Value) -> Result<(), RateLimitError> {
+    check_document_size(data, self.max_document_bytes())?;
+    check_document_depth(data, self.max_document_depth())
+  }
+
+  /// Replace the limits on a running server.
+  /// Per-IP token buckets are reset so new rates apply immediately;
+  /// connection and query counts carry over.
+  pub fn update_limits(&self, config: LimitsSection) {
+    *self.config.write() = config;
+    self.buckets.write().clear();
   }
 
   /// Clean up stale entries (call periodically).
@@ -280,6 +503,18 @@ impl RateLimiter {
         || bucket.tokens < bucket.capacity
     });
 
+    let mut user_buckets = self.user_buckets.write();
+    user_buckets.retain(|_, bucket| {
+      bucket.refill();
+      bucket.tokens < bucket.capacity
+    });
+
+    let mut project_buckets = self.project_buckets.write();
+    project_buckets.retain(|_, bucket| {
+      bucket.refill();
+      bucket.tokens < bucket.capacity
+    });
+
     // Remove empty connection entries (shouldn't happen, but just in case)
     let mut conns = self.connections.write();
     conns.retain(|_, count| *count > 0);
@@ -290,6 +525,62 @@ impl RateLimiter {
   }
 }
 
+/// Check that a document serializes to at most `limit` bytes (0 = unlimited).
+pub fn check_document_size(data: &serde_json::Value, limit: usize) -> Result<(), RateLimitError> {
+  if limit == 0 {
+    return Ok(());
+  }
+  let size = serde_json::to_vec(data).map(|v| v.len()).unwrap_or(0);
+  if size > limit {
+    return Err(RateLimitError::PayloadTooLarge { size, limit });
+  }
+  Ok(())
+}
+
+/// Check that objects and arrays in a document nest at most `limit` levels
+/// deep (0 = unlimited). Walks the document without recursion.
+pub fn check_document_depth(data: &serde_json::Value, limit: usize) -> Result<(), RateLimitError> {
+  if limit == 0 {
+    return Ok(());
+  }
+  let mut stack = vec![(data, 0)];
+  while let Some((value, depth)) = stack.pop() {
+    let children: Box<dyn Iterator<Item = &serde_json::Value>> = match value {
+      serde_json::Value::Object(map) => Box::new(map.values()),
+      serde_json::Value::Array(items) => Box::new(items.iter()),
+      _ => continue,
+    };
+    if depth + 1 > limit {
+      return Err(RateLimitError::DocumentTooDeep { limit });
+    }
+    stack.extend(children.map(|child| (child, depth + 1)));
+  }
+  Ok(())
+}
+
+/// Request budget of a client, as sent in `X-RateLimit-*` headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+  /// Requests allowed in a burst
+  pub limit: u32,
+  /// Requests that can be made right now
+  pub remaining: u32,
+  /// Time until the full burst is available again
+  pub reset: Duration,
+}
+
+/// RAII guard for a connection slot.
+pub struct ConnectionPermit {
+  limiter: Arc<RateLimiter>,
+  ip: IpAddr,
+}
+
+impl Drop for ConnectionPermit {
+  fn drop(&mut self) {
+    self.limiter.release_connection(self.ip);
+  }
+}
+
 /// RAII guard for query permits.
 pub struct QueryPermit {
   counter: Option<Arc<AtomicU32>>,
@@ -308,10 +599,42 @@ impl Drop for QueryPermit {
 /// Rate limit errors.
 #[derive(Debug, Clone)]
 pub enum RateLimitError {
-  TooManyConnections { ip: IpAddr, limit: u32 },
-  RateLimited { ip: IpAddr, retry_after: Duration },
-  TooManyConcurrentQueries { client_id: Uuid, limit: u32 },
+  TooManyConnections {
+    ip: IpAddr,
+    limit: u32,
+  },
+  TooManyConnectionsTotal {
+    limit: u32,
+  },
+  RateLimited {
+    ip: IpAddr,
+    retry_after: Duration,
+  },
+  UserRateLimited {
+    user_id: Uuid,
+    retry_after: Duration,
+  },
+  ProjectRateLimited {
+    project_id: Uuid,
+    limit: u32,
+    retry_after: Duration,
+  },
+  ProjectQuotaExceeded {
+    project_id: Uuid,
+    limit: u64,
+  },
+  TooManyConcurrentQueries {
+    client_id: Uuid,
+    limit: u32,
+  },
   QueryTimeout,
+  PayloadTooLarge {
+    size: usize,
+    limit: usize,
+  },
+  DocumentTooDeep {
+    limit: usize,
+  },
 }
 
 impl std::fmt::Display for RateLimitError {
@@ -324,9 +647,34 @@ impl std::fmt::Display for RateLimitError {
           ip, limit
         )
       }
-      Self::RateLimited { retry_after, .. } => {
+      Self::TooManyConnectionsTotal { limit } => {
+        write!(
+          f,
+          "Server connection limit reached: limit is {} connections",
+          limit
+        )
+      }
+      Self::RateLimited { retry_after, .. } | Self::UserRateLimited { retry_after, .. } => {
         write!(f, "Rate limited, retry after {:?}", retry_after)
       }
+      Self::ProjectRateLimited {
+        project_id,
+        limit,
+        retry_after,
+      } => {
+        write!(
+          f,
+          "Project {} exceeded its quota of {} requests per second, retry after {:?}",
+          project_id, limit, retry_after
+        )
+      }
+      Self::ProjectQuotaExceeded { project_id, limit } => {
+        write!(
+          f,
+          "Project {} reached its quota of {} documents",
+          project_id, limit
+        )
+      }
       Self::TooManyConcurrentQueries { limit, .. } => {
         write!(
           f,
@@ -335,6 +683,20 @@ impl std::fmt::Display for RateLimitError {
         )
       }
       Self::QueryTimeout => write!(f, "Query execution timed out"),
+      Self::PayloadTooLarge { size, limit } => {
+        write!(
+          f,
+          "Payload too large: document is {} bytes, limit is {} bytes",
+          size, limit
+        )
+      }
+      Self::DocumentTooDeep { limit } => {
+        write!(
+          f,
+          "Document too deep: objects and arrays nest more than {} levels",
+          limit
+        )
+      }
     }
   }
 }
@@ -343,17 +705,33 @@ impl std::error::Error for RateLimitError {}
 
 #[cfg(test)]
 mod tests {
+  use super::super::config::QueryCostAction;
   use super::*;
   use std::net::Ipv4Addr;
 
   fn test_config() -> LimitsSection {
     LimitsSection {
       max_connections_per_ip: 2,
+      max_connections_total: 3,
       requests_per_second: 10,
       burst_size: 5,
       query_timeout_ms: 1000,
       max_concurrent_queries: 3,
       max_message_size: 1024,
+      max_send_queue: 16,
+      max_document_bytes: 64,
+      max_document_depth: 4,
+      max_filter_depth: 8,
+      max_request_body_bytes: 0,
+      max_upload_bytes: 0,
+      max_header_bytes: 0,
+      max_result_rows: 100,
+      max_watches_per_client: 0,
+      slow_query_ms: 0,
+      idempotency_key_ttl_secs: 86400,
+      inexact_numbers: InexactNumbers::Allow,
+      max_query_cost: 0.0,
+      query_cost_action: QueryCostAction::Reject,
     }
   }
 
@@ -376,6 +754,30 @@ mod tests {
     assert!(limiter.check_connection(ip).is_ok());
   }
 
+  #[test]
+  fn test_total_connection_limit() {
+    let limiter = Arc::new(RateLimiter::new(test_config()));
+    let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let c = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+
+    let first = limiter.acquire_connection(a).unwrap();
+    let _second = limiter.acquire_connection(a).unwrap();
+    let _third = limiter.acquire_connection(b).unwrap();
+    assert_eq!(limiter.open_connections(), 3);
+
+    // A fresh IP is refused once the server-wide limit is reached
+    assert!(matches!(
+      limiter.acquire_connection(c),
+      Err(RateLimitError::TooManyConnectionsTotal { limit: 3 })
+    ));
+
+    // Dropping a permit frees its slot
+    drop(first);
+    assert_eq!(limiter.open_connections(), 2);
+    assert!(limiter.acquire_connection(c).is_ok());
+  }
+
   #[test]
   fn test_rate_limiting() {
     let limiter = RateLimiter::new(test_config());
@@ -390,6 +792,94 @@ mod tests {
     assert!(limiter.check_request(ip).is_err());
   }
 
+  #[test]
+  fn test_request_status() {
+    let limiter = RateLimiter::new(test_config());
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+
+    let status = limiter.request_status(ip).unwrap();
+    assert_eq!(status.limit, 5);
+    assert_eq!(status.remaining, 5);
+    assert_eq!(status.reset, Duration::ZERO);
+
+    limiter.check_request(ip).unwrap();
+    limiter.check_request(ip).unwrap();
+    let status = limiter.request_status(ip).unwrap();
+    assert_eq!(status.remaining, 3);
+    assert!(status.reset > Duration::ZERO);
+
+    limiter.update_limits(LimitsSection {
+      requests_per_second: 0,
+      ..test_config()
+    });
+    assert!(limiter.request_status(ip).is_none());
+  }
+
+  #[test]
+  fn test_user_rate_limiting() {
+    let limiter = RateLimiter::new(test_config());
+    let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+
+    assert!(limiter.check_user_request(alice, 2).is_ok());
+    assert!(limiter.check_user_request(alice, 2).is_ok());
+    assert!(matches!(
+      limiter.check_user_request(alice, 2),
+      Err(RateLimitError::UserRateLimited { .. })
+    ));
+
+    // Buckets are per user, and 0 disables the limit
+    assert!(limiter.check_user_request(bob, 2).is_ok());
+    assert!(limiter.check_user_request(alice, 0).is_ok());
+  }
+
+  #[test]
+  fn test_project_rate_limiting() {
+    let limiter = RateLimiter::new(test_config());
+    let (limited, other) = (Uuid::new_v4(), Uuid::new_v4());
+    limiter.set_project_limits(
+      limited,
+      ProjectLimits {
+        requests_per_second: 2,
+        burst_size: 0,
+        max_documents: 0,
+      },
+    );
+
+    // Burst defaults to the rate
+    assert!(limiter.check_project_request(limited).is_ok());
+    assert!(limiter.check_project_request(limited).is_ok());
+    match limiter.check_project_request(limited) {
+      Err(e @ RateLimitError::ProjectRateLimited { .. }) => {
+        assert!(e.to_string().contains("quota of 2 requests per second"))
+      }
+      other => panic!("expected ProjectRateLimited, got {:?}", other),
+    }
+
+    // Projects without limits are unlimited
+    for _ in 0..100 {
+      assert!(limiter.check_project_request(other).is_ok());
+    }
+
+    limiter.remove_project_limits(limited);
+    assert!(limiter.check_project_request(limited).is_ok());
+  }
+
+  #[test]
+  fn test_project_document_quota() {
+    let limiter = RateLimiter::new(test_config());
+    let project = Uuid::new_v4();
+    assert_eq!(limiter.document_quota(project), 0);
+
+    limiter.set_project_limits(
+      project,
+      ProjectLimits {
+        max_documents: 10,
+        ..Default::default()
+      },
+    );
+    assert_eq!(limiter.document_quota(project), 10);
+  }
+
   #[test]
   fn test_concurrent_queries() {
     let limiter = RateLimiter::new(test_config());
@@ -414,11 +904,26 @@ mod tests {
   fn test_unlimited() {
     let config = LimitsSection {
       max_connections_per_ip: 0,
+      max_connections_total: 0,
       requests_per_second: 0,
       burst_size: 0,
       query_timeout_ms: 0,
       max_concurrent_queries: 0,
       max_message_size: 0,
+      max_send_queue: 0,
+      max_document_bytes: 0,
+      max_document_depth: 0,
+      max_filter_depth: 0,
+      max_request_body_bytes: 0,
+      max_upload_bytes: 0,
+      max_header_bytes: 0,
+      max_result_rows: 0,
+      max_watches_per_client: 0,
+      slow_query_ms: 0,
+      idempotency_key_ttl_secs: 86400,
+      inexact_numbers: InexactNumbers::Allow,
+      max_query_cost: 0.0,
+      query_cost_action: QueryCostAction::Reject,
     };
     let limiter = RateLimiter::new(config);
     let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
@@ -430,5 +935,77 @@ mod tests {
       assert!(limiter.check_request(ip).is_ok());
       assert!(limiter.acquire_query_permit(client_id).is_ok());
     }
+    let big = serde_json::json!({ "data": "x".repeat(1 << 20) });
+    assert!(limiter.check_document_size(&big).is_ok());
+  }
+
+  #[test]
+  fn test_document_size_limit() {
+    let limiter = RateLimiter::new(test_config());
+
+    assert!(limiter
+      .check_document_size(&serde_json::json!({ "name": "small" }))
+      .is_ok());
+
+    let big = serde_json::json!({ "data": "x".repeat(64) });
+    match limiter.check_document_size(&big) {
+      Err(RateLimitError::PayloadTooLarge { size, limit }) => {
+        assert_eq!(size, 75);
+        assert_eq!(limit, 64);
+      }
+      other => panic!("expected PayloadTooLarge, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_document_depth_limit() {
+    let limiter = RateLimiter::new(test_config());
+
+    let nested = serde_json::json!({ "a": { "b": [{ "c": 1 }] } });
+    assert!(limiter.check_document(&nested).is_ok());
+
+    let deep = serde_json::json!({ "a": { "b": [{ "c": [1] }] } });
+    assert!(matches!(
+      limiter.check_document(&deep),
+      Err(RateLimitError::DocumentTooDeep { limit: 4 })
+    ));
+
+    let mut deepest = serde_json::json!(1);
+    for _ in 0..1000 {
+      deepest = serde_json::json!([deepest]);
+    }
+    assert!(check_document_depth(&deepest, 64).is_err());
+    assert!(check_document_depth(&deepest, 1000).is_ok());
+    assert!(check_document_depth(&deepest, 0).is_ok());
+  }
+
+  #[test]
+  fn test_update_limits() {
+    let limiter = RateLimiter::new(test_config());
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+
+    for _ in 0..5 {
+      assert!(limiter.check_request(ip).is_ok());
+    }
+    assert!(limiter.check_request(ip).is_err());
+    assert_eq!(limiter.slow_query_threshold(), None);
+    assert!(!limiter.is_slow(Duration::from_secs(3600)));
+
+    // New limits apply to the next request; the exhausted bucket is reset
+    limiter.update_limits(LimitsSection {
+      burst_size: 20,
+      slow_query_ms: 250,
+      ..test_config()
+    });
+    for _ in 0..20 {
+      assert!(limiter.check_request(ip).is_ok());
+    }
+    assert!(limiter.check_request(ip).is_err());
+    assert_eq!(
+      limiter.slow_query_threshold(),
+      Some(Duration::from_millis(250))
+    );
+    assert!(!limiter.is_slow(Duration::from_millis(249)));
+    assert!(limiter.is_slow(Duration::from_millis(250)));
   }
 }
@@ -0,0 +1,145 @@
+//! Live configuration reload.
+//!
+//! On SIGHUP the daemon re-reads its config file and compares it with the
+//! running configuration. Settings listed in [`RELOADABLE`] are applied in
+//! place without touching open connections; any other change is reported as
+//! pending until the next restart.
+
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use serde_json::Value;
+
+use super::ServerConfig;
+
+/// Settings that can be changed on a running server
+pub const RELOADABLE: &[&str] = &[
+  "server.cors_origins",
+  "logging.level",
+  "limits.max_connections_per_ip",
+  "limits.max_connections_total",
+  "limits.requests_per_second",
+  "limits.burst_size",
+  "limits.query_timeout_ms",
+  "limits.max_concurrent_queries",
+  "limits.max_watches_per_client",
+  "limits.slow_query_ms",
+  "limits.idempotency_key_ttl_secs",
+  "limits.inexact_numbers",
+];
+
+/// Differences between the running config and a freshly loaded one
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ConfigChanges {
+  /// Reloadable settings that changed, as `key: old -> new`
+  pub applied: Vec<String>,
+  /// Changed settings that only take effect after a restart (keys only, so
+  /// secrets are never logged)
+  pub pending_restart: Vec<String>,
+}
+
+impl ConfigChanges {
+  pub fn is_empty(&self) -> bool {
+    self.applied.is_empty() && self.pending_restart.is_empty()
+  }
+}
+
+impl ServerConfig {
+  /// Compare this (running) config with `new`, split into changes that can
+  /// be applied live and changes that need a restart
+  pub fn reload_changes(&self, new: &ServerConfig) -> ConfigChanges {
+    let old = serde_json::to_value(self).unwrap_or(Value::Null);
+    let new = serde_json::to_value(new).unwrap_or(Value::Null);
+    let mut changed = Vec::new();
+    diff_values("", &old, &new, &mut changed);
+
+    let mut changes = ConfigChanges::default();
+    for (key, old, new) in changed {
+      if RELOADABLE.contains(&key.as_str()) {
+        changes.applied.push(format!("{}: {} -> {}", key, old, new));
+      } else {
+        changes.pending_restart.push(key);
+      }
+    }
+    changes
+  }
+
+  /// Copy the reloadable settings from `new` into this config
+  pub fn apply_reloadable(&mut self, new: &ServerConfig) {
+    self.server.cors_origins = new.server.cors_origins.clone();
+    self.logging.level = new.logging.level.clone();
+    self.limits.max_connections_per_ip = new.limits.max_connections_per_ip;
+    self.limits.max_connections_total = new.limits.max_connections_total;
+    self.limits.requests_per_second = new.limits.requests_per_second;
+    self.limits.burst_size = new.limits.burst_size;
+    self.limits.query_timeout_ms = new.limits.query_timeout_ms;
+    self.limits.max_concurrent_queries = new.limits.max_concurrent_queries;
+    self.limits.max_watches_per_client = new.limits.max_watches_per_client;
+    self.limits.slow_query_ms = new.limits.slow_query_ms;
+    self.limits.idempotency_key_ttl_secs = new.limits.idempotency_key_ttl_secs;
+    self.limits.inexact_numbers = new.limits.inexact_numbers;
+  }
+}
+
+/// Collect the dotted paths of leaf values that differ. Arrays are compared
+/// as a whole.
+fn diff_values(path: &str, old: &Value, new: &Value, out: &mut Vec<(String, Value, Value)>) {
+  match (old, new) {
+    (Value::Object(a), Value::Object(b)) => {
+      let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+      keys.sort();
+      keys.dedup();
+      for key in keys {
+        let child = if path.is_empty() {
+          key.clone()
+        } else {
+          format!("{}.{}", path, key)
+        };
+        diff_values(
+          &child,
+          a.get(key).unwrap_or(&Value::Null),
+          b.get(key).unwrap_or(&Value::Null),
+          out,
+        );
+      }
+    }
+    _ if old != new => out.push((path.to_string(), old.clone(), new.clone())),
+    _ => {}
+  }
+}
+
+/// CORS origins for the HTTP server, replaceable at runtime.
+/// An empty list or `*` allows any origin.
+#[derive(Clone, Default)]
+pub struct CorsOrigins(Arc<RwLock<Vec<String>>>);
+
+impl CorsOrigins {
+  pub fn new(origins: Vec<String>) -> Self {
+    Self(Arc::new(RwLock::new(origins)))
+  }
+
+  pub fn set(&self, origins: Vec<String>) {
+    *self.0.write() = origins;
+  }
+
+  /// Whether a request from `origin` may be answered cross-origin
+  pub fn allows(&self, origin: &str) -> bool {
+    let origins = self.0.read();
+    origins.is_empty() || origins.iter().any(|o| o == "*" || o == origin)
+  }
+
+  /// Whether a WebSocket upgrade with this `Origin` header may proceed.
+  /// Requests without one come from non-browser clients and are let through,
+  /// as are same-origin pages (the origin's host and port match the `Host`
+  /// header), such as the admin dashboard.
+  pub fn allows_upgrade(&self, origin: Option<&str>, host: Option<&str>) -> bool {
+    let Some(origin) = origin else {
+      return true;
+    };
+    let same_origin = match (origin.split_once("://"), host) {
+      (Some((_, authority)), Some(host)) => authority.eq_ignore_ascii_case(host),
+      _ => false,
+    };
+    same_origin || self.allows(origin)
+  }
+}
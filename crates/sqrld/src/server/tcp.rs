@@ -10,12 +10,22 @@
 //! - Auth Token Length: 2 bytes BE
 //! - Auth Token: variable UTF-8
 //!
+//! When auth is required (`auth.enabled` or `auth.tcp_require_auth`), a token
+//! sent in the handshake is validated immediately. Without one, the first
+//! request must be `{"type":"authenticate","id":"...","token":"..."}`; any other
+//! message or an invalid token closes the connection.
+//!
 //! Server → Client:
-//! - Status: 1 byte (0x00=success, 0x01=version mismatch, 0x02=auth failed)
+//! - Status: 1 byte (0x00=success, 0x01=version mismatch, 0x02=auth failed,
+//!   0x03=connection limit reached)
 //! - Version: 1 byte
 //! - Flags: 1 byte
 //! - Session ID: 16 bytes UUID
 //!
+//! Once the connection is authenticated the server sends a notification
+//! frame with its banner, a `hello` message naming the server, its version
+//! and enabled protocols. `server.banner.enabled: false` turns it off.
+//!
 //! ## Message Framing
 //! - Length: 4 bytes BE (max 16MB)
 //! - Message Type: 1 byte (0x01=request, 0x02=response, 0x03=notification)
@@ -25,23 +35,37 @@
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{
+  AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, ReadHalf, WriteHalf,
+};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, RwLock};
 use uuid::Uuid;
 
-use super::{MessageHandler, RateLimiter, ServerConfig};
+use super::tls::accept_stream;
+use super::trace::{new_trace_id, with_trace_id};
+use super::websocket::{handle_limited, validate_client_token, TokenGrant, REJECT_TIMEOUT};
+use super::{
+  decode_client_message, Connections, Maintenance, MaybeTlsStream, MessageHandler, RateLimiter,
+  ServerConfig, ServerTls,
+};
 use crate::db::DatabaseBackend;
 use crate::query::QueryEnginePool;
 use crate::subscriptions::SubscriptionManager;
-use crate::types::{ClientMessage, ServerMessage};
+use crate::types::{ClientMessage, ErrorCode, ServerMessage};
 
 /// Protocol constants
 pub const MAGIC: &[u8; 4] = b"SQRL";
 pub const PROTOCOL_VERSION: u8 = 0x01;
 pub const MAX_MESSAGE_SIZE: u32 = 16 * 1024 * 1024; // 16MB
 
+/// How long a client has to send its `authenticate` message
+const AUTH_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Handshake status codes
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -49,6 +73,7 @@ pub enum HandshakeStatus {
   Success = 0x00,
   VersionMismatch = 0x01,
   AuthFailed = 0x02,
+  ConnectionLimit = 0x03,
 }
 
 /// Message types
@@ -81,6 +106,38 @@ pub enum Encoding {
   Json = 0x02,
 }
 
+impl Encoding {
+  /// Serialize a protocol message.
+  ///
+  /// MessagePack payloads encode structs as maps and UUIDs/timestamps as
+  /// strings, so the tagged message enums decode exactly like their JSON form.
+  pub fn encode<T: Serialize>(self, msg: &T) -> Result<Vec<u8>, anyhow::Error> {
+    match self {
+      Self::MessagePack => {
+        let mut buf = Vec::new();
+        msg.serialize(
+          &mut rmp_serde::Serializer::new(&mut buf)
+            .with_struct_map()
+            .with_human_readable(),
+        )?;
+        Ok(buf)
+      }
+      Self::Json => Ok(serde_json::to_vec(msg)?),
+    }
+  }
+
+  /// Deserialize a protocol message
+  pub fn decode<T: DeserializeOwned>(self, data: &[u8]) -> Result<T, anyhow::Error> {
+    match self {
+      Self::MessagePack => {
+        let mut deserializer = rmp_serde::Deserializer::from_read_ref(data).with_human_readable();
+        Ok(T::deserialize(&mut deserializer)?)
+      }
+      Self::Json => Ok(serde_json::from_slice(data)?),
+    }
+  }
+}
+
 impl TryFrom<u8> for Encoding {
   type Error = ();
   fn try_from(v: u8) -> Result<Self, Self::Error> {
@@ -121,6 +178,17 @@ impl From<ProtocolFlags> for u8 {
   }
 }
 
+/// Authentication state of a connection after the handshake
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ConnectionAuth {
+  /// Auth is not required
+  Open,
+  /// The first request must be `authenticate`
+  Pending,
+  /// Authenticated with an API token, or the admin token (None)
+  Authenticated(Option<TokenGrant>),
+}
+
 type Clients = Arc<RwLock<HashMap<Uuid, mpsc::UnboundedSender<ServerMessage>>>>;
 
 pub struct TcpServer {
@@ -131,6 +199,9 @@ pub struct TcpServer {
   clients: Clients,
   shutdown_rx: broadcast::Receiver<()>,
   config: ServerConfig,
+  tls: Option<Arc<ServerTls>>,
+  maintenance: Maintenance,
+  connections: Connections,
 }
 
 impl TcpServer {
@@ -150,12 +221,37 @@ impl TcpServer {
       clients: Arc::new(RwLock::new(HashMap::new())),
       shutdown_rx,
       config,
+      tls: None,
+      maintenance: Maintenance::default(),
+      connections: Connections::default(),
     }
   }
 
+  /// Terminate TLS on accepted connections
+  pub fn with_tls(mut self, tls: Option<Arc<ServerTls>>) -> Self {
+    self.tls = tls;
+    self
+  }
+
+  /// Share the maintenance switch with the admin API
+  pub fn with_maintenance(mut self, maintenance: Maintenance) -> Self {
+    self.maintenance = maintenance;
+    self
+  }
+
+  /// Share the connection registry with the admin API
+  pub fn with_connections(mut self, connections: Connections) -> Self {
+    self.connections = connections;
+    self
+  }
+
   pub async fn run(mut self, addr: &str) -> Result<(), anyhow::Error> {
     let listener = TcpListener::bind(addr).await?;
-    tracing::info!("TCP wire protocol listening on {}", addr);
+    tracing::info!(
+      "TCP wire protocol listening on {}{}",
+      addr,
+      if self.tls.is_some() { " (TLS)" } else { "" }
+    );
 
     // Spawn task to forward subscription messages to clients
     let clients = self.clients.clone();
@@ -174,9 +270,13 @@ impl TcpServer {
         Ok((stream, peer)) = listener.accept() => {
           let peer_ip = peer.ip();
 
-          // Check connection rate limit
+          // Check connection limits
           if let Err(e) = self.rate_limiter.check_connection(peer_ip) {
             tracing::warn!("TCP connection rejected from {}: {}", peer_ip, e);
+            let tls = self.tls.clone();
+            tokio::spawn(async move {
+              let _ = tokio::time::timeout(REJECT_TIMEOUT, reject_client(stream, tls)).await;
+            });
             continue;
           }
 
@@ -187,9 +287,13 @@ impl TcpServer {
           let rate_limiter = self.rate_limiter.clone();
           let clients = self.clients.clone();
           let config = self.config.clone();
+          let maintenance = self.maintenance.clone();
+          let connections = self.connections.clone();
+          let tls = self.tls.clone();
           tokio::spawn(async move {
             let result = handle_client(
               stream,
+              tls,
               peer_ip,
               backend,
               subs,
@@ -197,6 +301,8 @@ impl TcpServer {
               rate_limiter.clone(),
               clients,
               config,
+              maintenance,
+              connections,
             ).await;
             rate_limiter.release_connection(peer_ip);
             if let Err(e) = result {
@@ -215,10 +321,11 @@ impl TcpServer {
 }
 
 /// Handle handshake from client
-async fn handle_handshake(
-  stream: &mut TcpStream,
+async fn handle_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+  stream: &mut S,
+  backend: &Arc<dyn DatabaseBackend>,
   config: &ServerConfig,
-) -> Result<(Uuid, Encoding), anyhow::Error> {
+) -> Result<(Uuid, Encoding, ConnectionAuth), anyhow::Error> {
   // Read magic
   let mut magic = [0u8; 4];
   stream.read_exact(&mut magic).await?;
@@ -229,14 +336,7 @@ async fn handle_handshake(
   // Read version
   let version = stream.read_u8().await?;
   if version != PROTOCOL_VERSION {
-    // Send version mismatch response
-    stream
-      .write_u8(HandshakeStatus::VersionMismatch as u8)
-      .await?;
-    stream.write_u8(PROTOCOL_VERSION).await?;
-    stream.write_u8(0).await?;
-    stream.write_all(&[0u8; 16]).await?;
-    stream.flush().await?;
+    write_handshake_failure(stream, HandshakeStatus::VersionMismatch).await?;
     anyhow::bail!(
       "Protocol version mismatch: client={}, server={}",
       version,
@@ -256,27 +356,21 @@ async fn handle_handshake(
   }
   let auth_token = String::from_utf8(token_bytes).unwrap_or_default();
 
-  // Validate auth if enabled
-  if config.auth.enabled {
-    // Check admin token first
-    let valid_admin = config
-      .auth
-      .admin_token
-      .as_ref()
-      .is_some_and(|t| !t.is_empty() && t == &auth_token);
-
-    // If admin token doesn't match, could check against token store
-    // For now, only admin_token is supported for TCP protocol
-    if !valid_admin {
-      // Send auth failed response
-      stream.write_u8(HandshakeStatus::AuthFailed as u8).await?;
-      stream.write_u8(PROTOCOL_VERSION).await?;
-      stream.write_u8(0).await?;
-      stream.write_all(&[0u8; 16]).await?;
-      stream.flush().await?;
-      anyhow::bail!("Authentication failed: invalid or missing token");
+  // Validate auth if required; without a token the client authenticates
+  // with its first message instead
+  let auth = if !config.auth.enabled && !config.auth.tcp_require_auth {
+    ConnectionAuth::Open
+  } else if auth_token.is_empty() {
+    ConnectionAuth::Pending
+  } else {
+    match validate_client_token(backend, config, &auth_token).await {
+      Ok(grant) => ConnectionAuth::Authenticated(grant),
+      Err(e) => {
+        write_handshake_failure(stream, HandshakeStatus::AuthFailed).await?;
+        anyhow::bail!("Authentication failed: {}", e);
+      }
     }
-  }
+  };
 
   // Generate session ID
   let session_id = Uuid::new_v4();
@@ -300,12 +394,92 @@ async fn handle_handshake(
     session_id,
     encoding
   );
-  Ok((session_id, encoding))
+  Ok((session_id, encoding, auth))
+}
+
+/// Answer a handshake with a failure status and no session
+async fn write_handshake_failure<S: AsyncWrite + Unpin>(
+  stream: &mut S,
+  status: HandshakeStatus,
+) -> Result<(), anyhow::Error> {
+  stream.write_u8(status as u8).await?;
+  stream.write_u8(PROTOCOL_VERSION).await?;
+  stream.write_u8(0).await?;
+  stream.write_all(&[0u8; 16]).await?;
+  stream.flush().await?;
+  Ok(())
+}
+
+/// Refuse a connection over the connection limits: read the client's
+/// handshake, then answer with `ConnectionLimit`
+async fn reject_client(
+  stream: TcpStream,
+  tls: Option<Arc<ServerTls>>,
+) -> Result<(), anyhow::Error> {
+  let mut stream = accept_stream(tls.as_deref(), stream).await?;
+
+  let mut magic = [0u8; 4];
+  stream.read_exact(&mut magic).await?;
+  if &magic != MAGIC {
+    anyhow::bail!("Invalid magic bytes");
+  }
+  let _version = stream.read_u8().await?;
+  let _flags = stream.read_u8().await?;
+  let token_len = stream.read_u16().await?;
+  let mut token = vec![0u8; token_len as usize];
+  stream.read_exact(&mut token).await?;
+
+  write_handshake_failure(&mut stream, HandshakeStatus::ConnectionLimit).await
+}
+
+/// Wait for the `authenticate` message from a client that sent no token in
+/// the handshake. Returns what the token grants, or an error if the client
+/// must be disconnected.
+async fn authenticate_connection<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+  reader: &mut BufReader<R>,
+  writer: &mut BufWriter<W>,
+  encoding: Encoding,
+  backend: &Arc<dyn DatabaseBackend>,
+  config: &ServerConfig,
+) -> Result<Option<TokenGrant>, anyhow::Error> {
+  let (msg_type, frame_encoding, payload) = tokio::time::timeout(AUTH_TIMEOUT, read_frame(reader))
+    .await
+    .map_err(|_| anyhow::anyhow!("Authentication timeout"))??;
+
+  let msg = match msg_type {
+    MessageType::Request => frame_encoding.decode::<ClientMessage>(&payload).ok(),
+    _ => None,
+  };
+  let (id, result) = match msg {
+    Some(ClientMessage::Authenticate { id, token }) => {
+      let result = validate_client_token(backend, config, &token).await;
+      (id, result)
+    }
+    other => (
+      other.as_ref().map_or("0", |m| m.id()).to_string(),
+      Err("Authentication required: send an authenticate message first".to_string()),
+    ),
+  };
+
+  let resp = match &result {
+    Ok(grant) => ServerMessage::result(
+      id,
+      serde_json::json!({
+        "authenticated": true,
+        "project_id": grant.map(|g| g.project_id),
+      }),
+    ),
+    Err(e) => ServerMessage::error(id, e.clone()).with_code(ErrorCode::Unauthorized),
+  };
+  let payload = encoding.encode(&resp)?;
+  write_frame(writer, MessageType::Response, encoding, &payload).await?;
+
+  result.map_err(|e| anyhow::anyhow!("Authentication failed: {}", e))
 }
 
 /// Read a framed message
-async fn read_frame(
-  reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+async fn read_frame<R: AsyncRead + Unpin>(
+  reader: &mut BufReader<R>,
 ) -> Result<(MessageType, Encoding, Vec<u8>), anyhow::Error> {
   // Read length (4 bytes BE)
   let length = reader.read_u32().await?;
@@ -332,8 +506,8 @@ async fn read_frame(
 }
 
 /// Write a framed message
-async fn write_frame(
-  writer: &mut BufWriter<tokio::net::tcp::OwnedWriteHalf>,
+async fn write_frame<W: AsyncWrite + Unpin>(
+  writer: &mut BufWriter<W>,
   msg_type: MessageType,
   encoding: Encoding,
   payload: &[u8],
@@ -349,26 +523,11 @@ async fn write_frame(
   Ok(())
 }
 
-/// Serialize a message with the given encoding
-fn serialize_message(msg: &ServerMessage, encoding: Encoding) -> Result<Vec<u8>, anyhow::Error> {
-  match encoding {
-    Encoding::MessagePack => Ok(rmp_serde::to_vec(msg)?),
-    Encoding::Json => Ok(serde_json::to_vec(msg)?),
-  }
-}
-
-/// Deserialize a message with the given encoding
-fn deserialize_message(data: &[u8], encoding: Encoding) -> Result<ClientMessage, anyhow::Error> {
-  match encoding {
-    Encoding::MessagePack => Ok(rmp_serde::from_slice(data)?),
-    Encoding::Json => Ok(serde_json::from_slice(data)?),
-  }
-}
-
 /// Handle a single TCP client connection
 #[allow(clippy::too_many_arguments)]
 async fn handle_client(
-  mut stream: TcpStream,
+  stream: TcpStream,
+  tls: Option<Arc<ServerTls>>,
   peer_ip: IpAddr,
   backend: Arc<dyn DatabaseBackend>,
   subs: Arc<SubscriptionManager>,
@@ -376,28 +535,63 @@ async fn handle_client(
   rate_limiter: Arc<RateLimiter>,
   clients: Clients,
   config: ServerConfig,
+  maintenance: Maintenance,
+  connections: Connections,
 ) -> Result<(), anyhow::Error> {
+  let mut stream = accept_stream(tls.as_deref(), stream).await?;
+
   // Perform handshake
-  let (client_id, encoding) = handle_handshake(&mut stream, &config).await?;
+  let (client_id, encoding, auth) = handle_handshake(&mut stream, &backend, &config).await?;
 
   // Split stream for concurrent read/write
-  let (read_half, write_half) = stream.into_split();
+  let (read_half, write_half): (ReadHalf<MaybeTlsStream>, WriteHalf<MaybeTlsStream>) =
+    tokio::io::split(stream);
   let mut reader = BufReader::new(read_half);
   let mut writer = BufWriter::new(write_half);
 
+  let grant = match auth {
+    ConnectionAuth::Open => None,
+    ConnectionAuth::Authenticated(grant) => grant,
+    ConnectionAuth::Pending => {
+      match authenticate_connection(&mut reader, &mut writer, encoding, &backend, &config).await {
+        Ok(grant) => grant,
+        Err(e) => {
+          tracing::warn!("TCP auth failed from {}: {}", peer_ip, e);
+          return Ok(());
+        }
+      }
+    }
+  };
+
+  // Announce the server before any responses
+  let server_info = config.server_info();
+  if let Some(info) = server_info.clone() {
+    let payload = encoding.encode(&ServerMessage::banner(info))?;
+    write_frame(&mut writer, MessageType::Notification, encoding, &payload).await?;
+  }
+
   // Create channel for sending messages to this client
   let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
   clients.write().await.insert(client_id, tx);
 
   // Create message handler
-  let handler = MessageHandler::new(backend, subs.clone(), engine_pool);
-  let query_timeout = rate_limiter.query_timeout();
+  let handler = MessageHandler::new(backend, subs.clone(), engine_pool)
+    .with_project(grant.map(|g| g.project_id))
+    .with_author(grant.map(|g| g.token_id))
+    .with_max_document_bytes(rate_limiter.max_document_bytes())
+    .with_max_document_depth(rate_limiter.max_document_depth())
+    .with_idempotency_ttl(rate_limiter.idempotency_key_ttl())
+    .with_rate_limiter(rate_limiter.clone())
+    .with_maintenance(maintenance)
+    .with_server_info(server_info)
+    .with_connections(connections.clone());
+  let connection = connections.register(client_id, "tcp", peer_ip, handler.project_id());
 
   // Spawn task to write outgoing messages
   let write_encoding = encoding;
   let write_task = tokio::spawn(async move {
     while let Some(msg) = rx.recv().await {
-      let payload = match serialize_message(&msg, write_encoding) {
+      let payload = match write_encoding.encode(&msg) {
         Ok(p) => p,
         Err(e) => {
           tracing::error!("Failed to serialize message: {}", e);
@@ -407,7 +601,7 @@ async fn handle_client(
 
       // Determine message type based on ServerMessage variant
       let msg_type = match &msg {
-        ServerMessage::Change { .. } => MessageType::Notification,
+        ServerMessage::Change { .. } | ServerMessage::Diff { .. } => MessageType::Notification,
         _ => MessageType::Response,
       };
 
@@ -420,7 +614,14 @@ async fn handle_client(
 
   // Read and process incoming messages
   loop {
-    match read_frame(&mut reader).await {
+    let frame = tokio::select! {
+      frame = read_frame(&mut reader) => frame,
+      _ = connection.disconnected() => {
+        tracing::info!("TCP client {} disconnected by an admin", client_id);
+        break;
+      }
+    };
+    match frame {
       Ok((msg_type, frame_encoding, payload)) => {
         if msg_type != MessageType::Request {
           tracing::warn!("Unexpected message type from client: {:?}", msg_type);
@@ -430,7 +631,8 @@ async fn handle_client(
         // Check request rate limit
         if let Err(e) = rate_limiter.check_request(peer_ip) {
           tracing::debug!("Rate limited request from {}: {}", peer_ip, e);
-          let error_msg = ServerMessage::error("0", format!("Rate limited: {}", e));
+          let error_msg = ServerMessage::error("0", format!("Rate limited: {}", e))
+            .with_code(ErrorCode::RateLimited);
           if let Some(tx) = clients.read().await.get(&client_id) {
             let _ = tx.send(error_msg);
           }
@@ -438,12 +640,20 @@ async fn handle_client(
         }
 
         // Deserialize the request
-        let client_msg = match deserialize_message(&payload, frame_encoding) {
+        let decoded = match (frame_encoding, std::str::from_utf8(&payload)) {
+          (Encoding::Json, Ok(text)) => decode_client_message(text, rate_limiter.inexact_numbers()),
+          _ => frame_encoding
+            .decode::<ClientMessage>(&payload)
+            .map_err(|e| {
+              ServerMessage::error("0", format!("Invalid message: {}", e))
+                .with_code(ErrorCode::InvalidMessage)
+            }),
+        };
+        let client_msg = match decoded {
           Ok(m) => m,
-          Err(e) => {
-            tracing::debug!("Failed to deserialize message: {}", e);
+          Err(error_msg) => {
+            tracing::debug!("Failed to deserialize message: {:?}", error_msg);
             // Send error response
-            let error_msg = ServerMessage::error("0", format!("Invalid message: {}", e));
             if let Some(tx) = clients.read().await.get(&client_id) {
               let _ = tx.send(error_msg);
             }
@@ -458,7 +668,8 @@ async fn handle_client(
           Ok(p) => p,
           Err(e) => {
             tracing::debug!("Query limit exceeded for {}: {}", client_id, e);
-            let error_msg = ServerMessage::error(&msg_id, e.to_string());
+            let error_msg =
+              ServerMessage::error(&msg_id, e.to_string()).with_code(ErrorCode::RateLimited);
             if let Some(tx) = clients.read().await.get(&client_id) {
               let _ = tx.send(error_msg);
             }
@@ -466,18 +677,14 @@ async fn handle_client(
           }
         };
 
-        // Handle the message with optional timeout
-        let resp = if let Some(timeout) = query_timeout {
-          match tokio::time::timeout(timeout, handler.handle(client_id, client_msg)).await {
-            Ok(r) => r,
-            Err(_) => {
-              tracing::warn!("Query timeout for client {}", client_id);
-              ServerMessage::error(&msg_id, "Query execution timed out")
-            }
-          }
-        } else {
-          handler.handle(client_id, client_msg).await
-        };
+        // Handle the message with optional timeout, under its own trace ID
+        let trace_id = new_trace_id();
+        let resp = with_trace_id(
+          trace_id.clone(),
+          handle_limited(&handler, &rate_limiter, client_id, client_msg),
+        )
+        .await
+        .with_trace_id(&trace_id);
 
         drop(permit); // Release query permit
 
@@ -540,4 +747,158 @@ mod tests {
     assert_eq!(Encoding::try_from(0x02), Ok(Encoding::Json));
     assert_eq!(Encoding::try_from(0x99), Err(()));
   }
+
+  async fn test_backend() -> Arc<dyn DatabaseBackend> {
+    let backend = crate::db::SqliteBackend::in_memory().await.unwrap();
+    backend.init_schema().await.unwrap();
+    Arc::new(backend)
+  }
+
+  fn auth_config() -> ServerConfig {
+    let mut config = ServerConfig::default();
+    config.auth.tcp_require_auth = true;
+    config.auth.admin_token = Some("admin-secret".to_string());
+    config
+  }
+
+  /// Run a handshake with the given token, returning the server result and
+  /// the status byte the client received
+  async fn run_handshake(
+    backend: &Arc<dyn DatabaseBackend>,
+    config: &ServerConfig,
+    token: &str,
+  ) -> (Result<(Uuid, Encoding, ConnectionAuth), anyhow::Error>, u8) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let token = token.to_string();
+    let client = tokio::spawn(async move {
+      let mut stream = TcpStream::connect(addr).await.unwrap();
+      stream.write_all(MAGIC).await.unwrap();
+      stream.write_u8(PROTOCOL_VERSION).await.unwrap();
+      stream.write_u8(0x02).await.unwrap();
+      stream.write_u16(token.len() as u16).await.unwrap();
+      stream.write_all(token.as_bytes()).await.unwrap();
+      stream.read_u8().await.unwrap()
+    });
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let result = handle_handshake(&mut stream, backend, config).await;
+    (result, client.await.unwrap())
+  }
+
+  #[tokio::test]
+  async fn test_handshake_auth_not_required() {
+    let backend = test_backend().await;
+    let (result, status) = run_handshake(&backend, &ServerConfig::default(), "").await;
+    assert_eq!(status, HandshakeStatus::Success as u8);
+    assert_eq!(result.unwrap().2, ConnectionAuth::Open);
+  }
+
+  #[tokio::test]
+  async fn test_handshake_token_validation() {
+    let backend = test_backend().await;
+    let config = auth_config();
+
+    let (result, status) = run_handshake(&backend, &config, "admin-secret").await;
+    assert_eq!(status, HandshakeStatus::Success as u8);
+    assert_eq!(result.unwrap().2, ConnectionAuth::Authenticated(None));
+
+    let (result, status) = run_handshake(&backend, &config, "wrong").await;
+    assert_eq!(status, HandshakeStatus::AuthFailed as u8);
+    assert!(result.is_err());
+
+    // No token defers authentication to the first message
+    let (result, status) = run_handshake(&backend, &config, "").await;
+    assert_eq!(status, HandshakeStatus::Success as u8);
+    assert_eq!(result.unwrap().2, ConnectionAuth::Pending);
+  }
+
+  #[tokio::test]
+  async fn test_reject_over_connection_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = tokio::spawn(async move {
+      let mut stream = TcpStream::connect(addr).await.unwrap();
+      stream.write_all(MAGIC).await.unwrap();
+      stream.write_u8(PROTOCOL_VERSION).await.unwrap();
+      stream.write_u8(0x02).await.unwrap();
+      stream.write_u16(5).await.unwrap();
+      stream.write_all(b"token").await.unwrap();
+      stream.read_u8().await.unwrap()
+    });
+    let (stream, _) = listener.accept().await.unwrap();
+    reject_client(stream, None).await.unwrap();
+    assert_eq!(
+      client.await.unwrap(),
+      HandshakeStatus::ConnectionLimit as u8
+    );
+  }
+
+  /// Send one request frame and run `authenticate_connection` against it
+  async fn run_authenticate(
+    backend: &Arc<dyn DatabaseBackend>,
+    config: &ServerConfig,
+    msg: ClientMessage,
+  ) -> (Result<Option<Uuid>, anyhow::Error>, ServerMessage) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = tokio::spawn(async move {
+      let stream = TcpStream::connect(addr).await.unwrap();
+      let (read_half, write_half) = stream.into_split();
+      let mut writer = BufWriter::new(write_half);
+      let payload = serde_json::to_vec(&msg).unwrap();
+      write_frame(&mut writer, MessageType::Request, Encoding::Json, &payload)
+        .await
+        .unwrap();
+      let (_, _, payload) = read_frame(&mut BufReader::new(read_half)).await.unwrap();
+      serde_json::from_slice::<ServerMessage>(&payload).unwrap()
+    });
+    let (stream, _) = listener.accept().await.unwrap();
+    let (read_half, write_half) = stream.into_split();
+    let result = authenticate_connection(
+      &mut BufReader::new(read_half),
+      &mut BufWriter::new(write_half),
+      Encoding::Json,
+      backend,
+      config,
+    )
+    .await;
+    (result, client.await.unwrap())
+  }
+
+  #[tokio::test]
+  async fn test_authenticate_message() {
+    let backend = test_backend().await;
+    let config = auth_config();
+
+    let msg = ClientMessage::Authenticate {
+      id: "auth".into(),
+      token: "admin-secret".into(),
+    };
+    let (result, resp) = run_authenticate(&backend, &config, msg).await;
+    assert_eq!(result.unwrap(), None);
+    assert!(matches!(resp, ServerMessage::Result { id, .. } if id == "auth"));
+
+    let msg = ClientMessage::Authenticate {
+      id: "auth".into(),
+      token: "wrong".into(),
+    };
+    let (result, resp) = run_authenticate(&backend, &config, msg).await;
+    assert!(result.is_err());
+    assert!(matches!(resp, ServerMessage::Error { id, .. } if id == "auth"));
+  }
+
+  #[tokio::test]
+  async fn test_authenticate_rejects_other_first_message() {
+    let backend = test_backend().await;
+    let msg = ClientMessage::Ping { id: "p1".into() };
+    let (result, resp) = run_authenticate(&backend, &auth_config(), msg).await;
+    assert!(result.is_err());
+    match resp {
+      ServerMessage::Error { id, error, .. } => {
+        assert_eq!(id, "p1");
+        assert!(error.contains("Authentication required"));
+      }
+      other => panic!("Expected error, got {:?}", other),
+    }
+  }
 }
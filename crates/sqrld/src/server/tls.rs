@@ -0,0 +1,289 @@
+//! TLS termination for the client-facing listeners.
+//!
+//! Certificates are loaded from the PEM files in `server.tls` and can be
+//! reloaded at runtime (SIGHUP) without restarting; connections already
+//! established keep the certificate they negotiated with.
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::rustls;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use super::TlsSection;
+
+/// How long a client has to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Completed handshakes waiting to be picked up by the HTTP server
+const ACCEPT_QUEUE: usize = 64;
+
+/// Reloadable TLS server configuration
+pub struct ServerTls {
+  section: TlsSection,
+  config: RwLock<Arc<rustls::ServerConfig>>,
+}
+
+impl ServerTls {
+  /// Load certificates if TLS is enabled
+  pub fn from_section(section: &TlsSection) -> Result<Option<Arc<Self>>, anyhow::Error> {
+    if !section.enabled {
+      return Ok(None);
+    }
+    Ok(Some(Arc::new(Self::load(section)?)))
+  }
+
+  /// Load the certificate chain and private key
+  pub fn load(section: &TlsSection) -> Result<Self, anyhow::Error> {
+    Ok(Self {
+      section: section.clone(),
+      config: RwLock::new(build_config(section)?),
+    })
+  }
+
+  /// Re-read the certificate files. On error the current certificate is kept.
+  pub fn reload(&self) -> Result<(), anyhow::Error> {
+    let config = build_config(&self.section)?;
+    *self.config.write() = config;
+    tracing::info!("Reloaded TLS certificate from {}", self.section.cert_path);
+    Ok(())
+  }
+
+  /// Current rustls configuration
+  pub fn server_config(&self) -> Arc<rustls::ServerConfig> {
+    self.config.read().clone()
+  }
+
+  /// Perform the server side of the TLS handshake
+  pub async fn accept(&self, stream: TcpStream) -> Result<MaybeTlsStream, anyhow::Error> {
+    let acceptor = TlsAcceptor::from(self.server_config());
+    let stream = tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream))
+      .await
+      .map_err(|_| anyhow::anyhow!("TLS handshake timed out"))??;
+    Ok(MaybeTlsStream::Tls(Box::new(stream)))
+  }
+
+  /// Reload certificates whenever the process receives SIGHUP
+  #[cfg(unix)]
+  pub fn reload_on_sighup(self: &Arc<Self>) -> Result<(), anyhow::Error> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    let tls = self.clone();
+    tokio::spawn(async move {
+      while hangup.recv().await.is_some() {
+        if let Err(e) = tls.reload() {
+          tracing::error!("Failed to reload TLS certificate: {}", e);
+        }
+      }
+    });
+    Ok(())
+  }
+
+  #[cfg(not(unix))]
+  pub fn reload_on_sighup(self: &Arc<Self>) -> Result<(), anyhow::Error> {
+    Ok(())
+  }
+}
+
+/// Wrap an accepted connection, terminating TLS if configured
+pub(super) async fn accept_stream(
+  tls: Option<&ServerTls>,
+  stream: TcpStream,
+) -> Result<MaybeTlsStream, anyhow::Error> {
+  match tls {
+    Some(tls) => tls.accept(stream).await,
+    None => Ok(MaybeTlsStream::Plain(stream)),
+  }
+}
+
+fn build_config(section: &TlsSection) -> Result<Arc<rustls::ServerConfig>, anyhow::Error> {
+  if section.cert_path.is_empty() || section.key_path.is_empty() {
+    anyhow::bail!("TLS is enabled but cert_path or key_path is not set");
+  }
+
+  let certs = rustls_pemfile::certs(&mut BufReader::new(open(&section.cert_path)?))
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| anyhow::anyhow!("Invalid certificate {}: {}", section.cert_path, e))?;
+  if certs.is_empty() {
+    anyhow::bail!("No certificates found in {}", section.cert_path);
+  }
+
+  let key = rustls_pemfile::private_key(&mut BufReader::new(open(&section.key_path)?))
+    .map_err(|e| anyhow::anyhow!("Invalid private key {}: {}", section.key_path, e))?
+    .ok_or_else(|| anyhow::anyhow!("No private key found in {}", section.key_path))?;
+
+  let config =
+    rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+      .with_safe_default_protocol_versions()?
+      .with_no_client_auth()
+      .with_single_cert(certs, key)
+      .map_err(|e| anyhow::anyhow!("Certificate and key do not match: {}", e))?;
+  Ok(Arc::new(config))
+}
+
+fn open(path: &str) -> Result<File, anyhow::Error> {
+  File::open(path).map_err(|e| anyhow::anyhow!("Cannot open {}: {}", path, e))
+}
+
+/// A client connection, with or without TLS
+pub enum MaybeTlsStream {
+  Plain(TcpStream),
+  Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for MaybeTlsStream {
+  fn poll_read(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      Self::Plain(s) => Pin::new(s).poll_read(cx, buf),
+      Self::Tls(s) => Pin::new(s.as_mut()).poll_read(cx, buf),
+    }
+  }
+}
+
+impl AsyncWrite for MaybeTlsStream {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    match self.get_mut() {
+      Self::Plain(s) => Pin::new(s).poll_write(cx, buf),
+      Self::Tls(s) => Pin::new(s.as_mut()).poll_write(cx, buf),
+    }
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      Self::Plain(s) => Pin::new(s).poll_flush(cx),
+      Self::Tls(s) => Pin::new(s.as_mut()).poll_flush(cx),
+    }
+  }
+
+  fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      Self::Plain(s) => Pin::new(s).poll_shutdown(cx),
+      Self::Tls(s) => Pin::new(s.as_mut()).poll_shutdown(cx),
+    }
+  }
+
+  fn poll_write_vectored(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    bufs: &[io::IoSlice<'_>],
+  ) -> Poll<io::Result<usize>> {
+    match self.get_mut() {
+      Self::Plain(s) => Pin::new(s).poll_write_vectored(cx, bufs),
+      Self::Tls(s) => Pin::new(s.as_mut()).poll_write_vectored(cx, bufs),
+    }
+  }
+
+  fn is_write_vectored(&self) -> bool {
+    match self {
+      Self::Plain(s) => s.is_write_vectored(),
+      Self::Tls(s) => s.is_write_vectored(),
+    }
+  }
+}
+
+/// Listener for the axum servers that terminates TLS when configured.
+///
+/// Handshakes run in their own tasks so a slow client can't hold up others.
+pub struct ServerListener {
+  local_addr: SocketAddr,
+  inner: ListenerInner,
+}
+
+enum ListenerInner {
+  Plain(TcpListener),
+  Tls(mpsc::Receiver<(MaybeTlsStream, SocketAddr)>),
+}
+
+impl ServerListener {
+  pub async fn bind(addr: &str, tls: Option<Arc<ServerTls>>) -> Result<Self, anyhow::Error> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    let Some(tls) = tls else {
+      return Ok(Self {
+        local_addr,
+        inner: ListenerInner::Plain(listener),
+      });
+    };
+
+    let (tx, rx) = mpsc::channel(ACCEPT_QUEUE);
+    tokio::spawn(async move {
+      loop {
+        // Stop accepting once the server has dropped the listener
+        let (stream, peer) = tokio::select! {
+          _ = tx.closed() => break,
+          accepted = listener.accept() => match accepted {
+            Ok(conn) => conn,
+            Err(e) => {
+              tracing::debug!("Accept error: {}", e);
+              tokio::time::sleep(Duration::from_millis(100)).await;
+              continue;
+            }
+          },
+        };
+        let tls = tls.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+          match tls.accept(stream).await {
+            Ok(stream) => {
+              let _ = tx.send((stream, peer)).await;
+            }
+            Err(e) => tracing::debug!("TLS handshake with {} failed: {}", peer, e),
+          }
+        });
+      }
+    });
+
+    Ok(Self {
+      local_addr,
+      inner: ListenerInner::Tls(rx),
+    })
+  }
+
+  /// Whether connections are TLS-terminated
+  pub fn is_tls(&self) -> bool {
+    matches!(self.inner, ListenerInner::Tls(_))
+  }
+}
+
+impl axum::serve::Listener for ServerListener {
+  type Io = MaybeTlsStream;
+  type Addr = SocketAddr;
+
+  async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+    match &mut self.inner {
+      ListenerInner::Plain(listener) => loop {
+        match listener.accept().await {
+          Ok((stream, peer)) => return (MaybeTlsStream::Plain(stream), peer),
+          Err(e) => {
+            tracing::debug!("Accept error: {}", e);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+          }
+        }
+      },
+      ListenerInner::Tls(rx) => match rx.recv().await {
+        Some(conn) => conn,
+        // The acceptor task is gone; wait for graceful shutdown
+        None => std::future::pending().await,
+      },
+    }
+  }
+
+  fn local_addr(&self) -> io::Result<Self::Addr> {
+    Ok(self.local_addr)
+  }
+}
@@ -0,0 +1,80 @@
+//! Correlation IDs for requests and client messages.
+//!
+//! Every HTTP request and every client message is handled inside a trace
+//! scope: a `request` tracing span carrying `trace_id`, plus a task-local copy
+//! of the ID that `emit_log` and error responses read. Work spawned onto other
+//! tasks does not inherit the scope.
+
+use std::future::Future;
+
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// HTTP header carrying the trace ID (accepted on requests, set on responses)
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// Longest trace ID accepted from a client
+const MAX_TRACE_ID_LEN: usize = 64;
+
+tokio::task_local! {
+  static TRACE_ID: String;
+}
+
+/// Generate a new trace ID (16 hex characters)
+pub fn new_trace_id() -> String {
+  let mut id = Uuid::new_v4().simple().to_string();
+  id.truncate(16);
+  id
+}
+
+/// Accept a trace ID supplied by a client or proxy, if it is safe to log
+pub fn accept_trace_id(id: &str) -> Option<String> {
+  let valid = !id.is_empty()
+    && id.len() <= MAX_TRACE_ID_LEN
+    && id
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+  valid.then(|| id.to_string())
+}
+
+/// Trace ID of the request or message currently being handled
+pub fn current_trace_id() -> Option<String> {
+  TRACE_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Run `fut` inside a trace scope for `trace_id`
+pub async fn with_trace_id<F: Future>(trace_id: String, fut: F) -> F::Output {
+  let span = tracing::info_span!("request", trace_id = %trace_id);
+  TRACE_ID.scope(trace_id, fut.instrument(span)).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_new_trace_id() {
+    let a = new_trace_id();
+    let b = new_trace_id();
+    assert_eq!(a.len(), 16);
+    assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(a, b);
+  }
+
+  #[test]
+  fn test_accept_trace_id() {
+    assert_eq!(accept_trace_id("abc-123_X"), Some("abc-123_X".to_string()));
+    assert_eq!(accept_trace_id(""), None);
+    assert_eq!(accept_trace_id("bad id"), None);
+    assert_eq!(accept_trace_id("line\nbreak"), None);
+    assert_eq!(accept_trace_id(&"a".repeat(65)), None);
+  }
+
+  #[tokio::test]
+  async fn test_trace_scope() {
+    assert_eq!(current_trace_id(), None);
+    let seen = with_trace_id("t1".to_string(), async { current_trace_id() }).await;
+    assert_eq!(seen.as_deref(), Some("t1"));
+    assert_eq!(current_trace_id(), None);
+  }
+}
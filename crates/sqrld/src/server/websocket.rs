@@ -3,18 +3,33 @@ use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 use tokio_tungstenite::tungstenite::Message;
 use uuid::Uuid;
 
-use super::{MessageHandler, RateLimiter, ServerConfig};
+use super::tls::accept_stream;
+use super::trace::{new_trace_id, with_trace_id};
+use super::{
+  decode_client_message, Connections, CorsOrigins, Maintenance, MessageHandler, RateLimitError,
+  RateLimiter, ServerConfig, ServerTls,
+};
 use crate::db::DatabaseBackend;
 use crate::query::QueryEnginePool;
 use crate::subscriptions::SubscriptionManager;
-use crate::types::{ClientMessage, ServerMessage};
+use crate::types::{ClientMessage, ErrorCode, ServerMessage};
 
-type Clients = Arc<RwLock<HashMap<Uuid, mpsc::UnboundedSender<ServerMessage>>>>;
+pub(crate) type Clients = Arc<RwLock<HashMap<Uuid, mpsc::Sender<ServerMessage>>>>;
+
+/// How long a single outbound frame may take before the client is considered stalled
+pub(crate) const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a refused connection may take to receive its rejection
+pub(super) const REJECT_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct WebSocketServer {
   backend: Arc<dyn DatabaseBackend>,
@@ -24,6 +39,10 @@ pub struct WebSocketServer {
   clients: Clients,
   shutdown_rx: broadcast::Receiver<()>,
   config: ServerConfig,
+  tls: Option<Arc<ServerTls>>,
+  maintenance: Maintenance,
+  connections: Connections,
+  cors_origins: CorsOrigins,
 }
 
 impl WebSocketServer {
@@ -35,6 +54,7 @@ impl WebSocketServer {
     shutdown_rx: broadcast::Receiver<()>,
     config: ServerConfig,
   ) -> Self {
+    let cors_origins = CorsOrigins::new(config.server.cors_origins.clone());
     Self {
       backend,
       subs,
@@ -43,21 +63,51 @@ impl WebSocketServer {
       clients: Arc::new(RwLock::new(HashMap::new())),
       shutdown_rx,
       config,
+      tls: None,
+      maintenance: Maintenance::default(),
+      connections: Connections::default(),
+      cors_origins,
     }
   }
 
+  /// Terminate TLS on accepted connections (wss://)
+  pub fn with_tls(mut self, tls: Option<Arc<ServerTls>>) -> Self {
+    self.tls = tls;
+    self
+  }
+
+  /// Share the maintenance switch with the admin API
+  pub fn with_maintenance(mut self, maintenance: Maintenance) -> Self {
+    self.maintenance = maintenance;
+    self
+  }
+
+  /// Share the connection registry with the admin API
+  pub fn with_connections(mut self, connections: Connections) -> Self {
+    self.connections = connections;
+    self
+  }
+
+  /// Share the reloadable origin allowlist with the admin API
+  pub fn with_cors_origins(mut self, cors_origins: CorsOrigins) -> Self {
+    self.cors_origins = cors_origins;
+    self
+  }
+
   pub async fn run(mut self, addr: &str) -> Result<(), anyhow::Error> {
     let listener = TcpListener::bind(addr).await?;
-    tracing::info!("WebSocket listening on {}", addr);
+    tracing::info!(
+      "WebSocket listening on {}{}",
+      addr,
+      if self.tls.is_some() { " (TLS)" } else { "" }
+    );
 
     let clients = self.clients.clone();
     let subs = self.subs.clone();
     tokio::spawn(async move {
       let mut rx = subs.subscribe_to_outgoing();
       while let Ok((client_id, msg)) = rx.recv().await {
-        if let Some(tx) = clients.read().await.get(&client_id) {
-          let _ = tx.send(msg);
-        }
+        queue_message(&clients, client_id, msg).await;
       }
     });
 
@@ -66,9 +116,10 @@ impl WebSocketServer {
         Ok((stream, peer)) = listener.accept() => {
           let peer_ip = peer.ip();
 
-          // Check connection rate limit
+          // Check connection limits
           if let Err(e) = self.rate_limiter.check_connection(peer_ip) {
             tracing::warn!("Connection rejected from {}: {}", peer_ip, e);
+            tokio::spawn(reject_client(stream, self.tls.clone(), e));
             continue;
           }
 
@@ -78,8 +129,12 @@ impl WebSocketServer {
           let rate_limiter = self.rate_limiter.clone();
           let clients = self.clients.clone();
           let config = self.config.clone();
+          let maintenance = self.maintenance.clone();
+          let connections = self.connections.clone();
+          let cors_origins = self.cors_origins.clone();
           tokio::spawn(handle_client(
             stream,
+            self.tls.clone(),
             peer_ip,
             backend,
             subs,
@@ -87,6 +142,9 @@ impl WebSocketServer {
             rate_limiter,
             clients,
             config,
+            maintenance,
+            connections,
+            cors_origins,
           ));
         }
         _ = self.shutdown_rx.recv() => break,
@@ -96,6 +154,63 @@ impl WebSocketServer {
   }
 }
 
+/// Refuse a connection over the connection limits by failing the WebSocket
+/// handshake with an HTTP error that carries the reason
+async fn reject_client(stream: TcpStream, tls: Option<Arc<ServerTls>>, err: RateLimitError) {
+  let status = match err {
+    RateLimitError::TooManyConnectionsTotal { .. } => StatusCode::SERVICE_UNAVAILABLE,
+    _ => StatusCode::TOO_MANY_REQUESTS,
+  };
+  let reason = err.to_string();
+  let reject = async move {
+    let Ok(stream) = accept_stream(tls.as_deref(), stream).await else {
+      return;
+    };
+    let callback = |_: &Request, _: Response| -> Result<Response, ErrorResponse> {
+      let mut resp = ErrorResponse::new(Some(reason));
+      *resp.status_mut() = status;
+      Err(resp)
+    };
+    let _ = tokio_tungstenite::accept_hdr_async(stream, callback).await;
+  };
+  let _ = tokio::time::timeout(REJECT_TIMEOUT, reject).await;
+}
+
+/// Queue a message for a client without blocking. A client whose outbound
+/// queue is full is a slow consumer: it is removed from the client map, which
+/// closes its channel and lets the connection wind down.
+/// Returns false if the message could not be queued.
+pub(crate) async fn queue_message(clients: &Clients, client_id: Uuid, msg: ServerMessage) -> bool {
+  let result = match clients.read().await.get(&client_id) {
+    Some(tx) => tx.try_send(msg),
+    None => return false,
+  };
+
+  match result {
+    Ok(()) => true,
+    Err(mpsc::error::TrySendError::Full(_)) => {
+      tracing::warn!(
+        "Disconnecting slow WebSocket client {}: send queue full",
+        client_id
+      );
+      clients.write().await.remove(&client_id);
+      false
+    }
+    Err(mpsc::error::TrySendError::Closed(_)) => false,
+  }
+}
+
+/// Queue a message for a client, waiting while its outbound queue is full.
+/// Streamed results use this so rows are only read as fast as the client
+/// takes them. Returns false once the client is gone.
+async fn send_message(clients: &Clients, client_id: Uuid, msg: ServerMessage) -> bool {
+  let tx = clients.read().await.get(&client_id).cloned();
+  match tx {
+    Some(tx) => tx.send(msg).await.is_ok(),
+    None => false,
+  }
+}
+
 /// Hash a token using SHA-256 for validation
 fn hash_token(token: &str) -> String {
   let mut hasher = Sha256::new();
@@ -103,13 +218,22 @@ fn hash_token(token: &str) -> String {
   format!("{:x}", hasher.finalize())
 }
 
+/// What a valid API token grants a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TokenGrant {
+  /// Project the token is bound to
+  pub project_id: Uuid,
+  /// Recorded as the author of the connection's writes
+  pub token_id: Uuid,
+}
+
 /// Authenticate a WebSocket client
-/// Returns Ok(project_id) if authentication is successful, or None if auth is disabled
+/// Returns Ok(grant) if authentication is successful, or None if auth is disabled
 async fn authenticate_client(
   backend: &Arc<dyn DatabaseBackend>,
   config: &ServerConfig,
   first_message: Option<&str>,
-) -> Result<Option<Uuid>, String> {
+) -> Result<Option<TokenGrant>, String> {
   // If auth is disabled, allow all connections
   if !config.auth.enabled {
     return Ok(None);
@@ -139,25 +263,73 @@ async fn authenticate_client(
       .to_string()
   })?;
 
+  validate_client_token(backend, config, &token).await
+}
+
+/// Validate a data-plane token (admin token or API token).
+/// Returns the project and ID of an API token, or None for the admin token
+pub(super) async fn validate_client_token(
+  backend: &Arc<dyn DatabaseBackend>,
+  config: &ServerConfig,
+  token: &str,
+) -> Result<Option<TokenGrant>, String> {
   // Check if it's the admin token
   if let Some(ref admin_token) = config.auth.admin_token {
-    if !admin_token.is_empty() && crate::security::constant_time_compare(&token, admin_token) {
+    if !admin_token.is_empty() && crate::security::constant_time_compare(token, admin_token) {
       return Ok(None); // Admin token grants access to all projects
     }
   }
 
   // Validate as API token
-  let token_hash = hash_token(&token);
-  match backend.validate_token(&token_hash).await {
-    Ok(Some(project_id)) => Ok(Some(project_id)),
+  let token_hash = hash_token(token);
+  match backend.get_token_by_hash(&token_hash).await {
+    Ok(Some(token)) => Ok(Some(TokenGrant {
+      project_id: token.project_id,
+      token_id: token.id,
+    })),
     Ok(None) => Err("Invalid token".to_string()),
     Err(e) => Err(format!("Authentication error: {}", e)),
   }
 }
 
+/// Handle a client message under the current query timeout, logging it when
+/// it runs past the slow query threshold. Limits are read per message so
+/// config reloads reach open connections.
+pub(super) async fn handle_limited(
+  handler: &MessageHandler,
+  rate_limiter: &RateLimiter,
+  client_id: Uuid,
+  msg: ClientMessage,
+) -> ServerMessage {
+  let msg_id = msg.id().to_string();
+  let started = Instant::now();
+  let resp = match rate_limiter.query_timeout() {
+    Some(timeout) => match tokio::time::timeout(timeout, handler.handle(client_id, msg)).await {
+      Ok(r) => r,
+      Err(_) => {
+        tracing::warn!("Query timeout for client {}", client_id);
+        ServerMessage::error(&msg_id, "Query execution timed out").with_code(ErrorCode::Timeout)
+      }
+    },
+    None => handler.handle(client_id, msg).await,
+  };
+
+  let elapsed = started.elapsed();
+  if rate_limiter.is_slow(elapsed) {
+    tracing::warn!(
+      "Slow request {} from client {} took {}ms",
+      msg_id,
+      client_id,
+      elapsed.as_millis()
+    );
+  }
+  resp
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn handle_client(
   stream: TcpStream,
+  tls: Option<Arc<ServerTls>>,
   peer_ip: IpAddr,
   backend: Arc<dyn DatabaseBackend>,
   subs: Arc<SubscriptionManager>,
@@ -165,18 +337,60 @@ async fn handle_client(
   rate_limiter: Arc<RateLimiter>,
   clients: Clients,
   config: ServerConfig,
+  maintenance: Maintenance,
+  connections: Connections,
+  cors_origins: CorsOrigins,
 ) {
-  let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+  // Frames larger than max_message_size are rejected by the protocol layer,
+  // which closes the connection
+  let max_message_size = rate_limiter.max_message_size();
+  let ws_config = if max_message_size > 0 {
+    WebSocketConfig::default()
+      .max_message_size(Some(max_message_size))
+      .max_frame_size(Some(max_message_size))
+  } else {
+    WebSocketConfig::default()
+  };
+  let stream = match accept_stream(tls.as_deref(), stream).await {
+    Ok(stream) => stream,
+    Err(e) => {
+      tracing::debug!("TLS handshake with {} failed: {}", peer_ip, e);
+      rate_limiter.release_connection(peer_ip);
+      return;
+    }
+  };
+  // Browsers send an Origin header; refuse pages outside server.cors_origins
+  let check_origin = |req: &Request, resp: Response| -> Result<Response, ErrorResponse> {
+    let origin = req
+      .headers()
+      .get("origin")
+      .map(|v| v.to_str().unwrap_or_default());
+    let host = req.headers().get("host").and_then(|v| v.to_str().ok());
+    if cors_origins.allows_upgrade(origin, host) {
+      return Ok(resp);
+    }
+    tracing::warn!(
+      "WebSocket upgrade from {} rejected: origin {} not allowed",
+      peer_ip,
+      origin.unwrap_or_default()
+    );
+    let mut resp = ErrorResponse::new(Some("Origin not allowed".to_string()));
+    *resp.status_mut() = StatusCode::FORBIDDEN;
+    Err(resp)
+  };
+  let Ok(ws) =
+    tokio_tungstenite::accept_hdr_async_with_config(stream, check_origin, Some(ws_config)).await
+  else {
     rate_limiter.release_connection(peer_ip);
     return;
   };
   let client_id = Uuid::new_v4();
   let (mut sink, mut stream) = ws.split();
-  let (tx, mut rx) = mpsc::unbounded_channel();
+  let (tx, mut rx) = mpsc::channel(rate_limiter.max_send_queue());
 
   // If auth is enabled, require authentication as first message
   let mut authenticated = !config.auth.enabled;
-  let mut _project_id: Option<Uuid> = None;
+  let mut grant: Option<TokenGrant> = None;
 
   if config.auth.enabled {
     // Wait for auth message with timeout
@@ -186,9 +400,9 @@ async fn handle_client(
     match auth_result {
       Ok(Some(Ok(Message::Text(text)))) => {
         match authenticate_client(&backend, &config, Some(&text)).await {
-          Ok(pid) => {
+          Ok(g) => {
             authenticated = true;
-            _project_id = pid;
+            grant = g;
             // Send auth success
             let success = serde_json::json!({"type": "AuthSuccess"});
             if sink
@@ -236,10 +450,23 @@ async fn handle_client(
   }
 
   clients.write().await.insert(client_id, tx);
-  let handler = MessageHandler::new(backend, subs.clone(), engine_pool);
-  let query_timeout = rate_limiter.query_timeout();
+  let server_info = config.server_info();
+  if let Some(info) = server_info.clone() {
+    queue_message(&clients, client_id, ServerMessage::banner(info)).await;
+  }
+  let handler = MessageHandler::new(backend, subs.clone(), engine_pool)
+    .with_project(grant.map(|g| g.project_id))
+    .with_author(grant.map(|g| g.token_id))
+    .with_max_document_bytes(rate_limiter.max_document_bytes())
+    .with_max_document_depth(rate_limiter.max_document_depth())
+    .with_idempotency_ttl(rate_limiter.idempotency_key_ttl())
+    .with_rate_limiter(rate_limiter.clone())
+    .with_maintenance(maintenance)
+    .with_server_info(server_info)
+    .with_connections(connections.clone());
+  let connection = connections.register(client_id, "websocket", peer_ip, handler.project_id());
 
-  let send_task = tokio::spawn(async move {
+  let mut send_task = tokio::spawn(async move {
     while let Some(msg) = rx.recv().await {
       let serialized = match serde_json::to_string(&msg) {
         Ok(s) => s,
@@ -248,55 +475,115 @@ async fn handle_client(
           continue;
         }
       };
-      if sink.send(Message::Text(serialized.into())).await.is_err() {
-        break;
+      match tokio::time::timeout(SEND_TIMEOUT, sink.send(Message::Text(serialized.into()))).await {
+        Ok(Ok(())) => {}
+        Ok(Err(_)) => break,
+        Err(_) => {
+          tracing::warn!("WebSocket client {} stalled, disconnecting", client_id);
+          break;
+        }
       }
     }
   });
 
-  while let Some(Ok(Message::Text(text))) = stream.next().await {
+  loop {
+    // Stop reading once the send side has gone away (stalled or dropped client)
+    let text = tokio::select! {
+      msg = stream.next() => match msg {
+        Some(Ok(Message::Text(text))) => text,
+        _ => break,
+      },
+      _ = &mut send_task => break,
+      _ = connection.disconnected() => {
+        tracing::info!("WebSocket client {} disconnected by an admin", client_id);
+        break;
+      }
+    };
+
     // Check request rate limit
     if let Err(e) = rate_limiter.check_request(peer_ip) {
       tracing::debug!("Rate limited request from {}: {}", peer_ip, e);
-      if let Some(tx) = clients.read().await.get(&client_id) {
-        let _ = tx.send(ServerMessage::error("0", format!("Rate limited: {}", e)));
+      if !queue_message(
+        &clients,
+        client_id,
+        ServerMessage::error("0", format!("Rate limited: {}", e)).with_code(ErrorCode::RateLimited),
+      )
+      .await
+      {
+        break;
       }
       continue;
     }
 
-    if let Ok(msg) = serde_json::from_str::<ClientMessage>(&text) {
-      let msg_id = msg.id().to_string();
-
-      // Acquire query permit
-      let permit = match rate_limiter.acquire_query_permit(client_id) {
-        Ok(p) => p,
-        Err(e) => {
-          tracing::debug!("Query limit exceeded for {}: {}", client_id, e);
-          if let Some(tx) = clients.read().await.get(&client_id) {
-            let _ = tx.send(ServerMessage::error(&msg_id, e.to_string()));
-          }
-          continue;
+    let msg = match decode_client_message(&text, rate_limiter.inexact_numbers()) {
+      Ok(msg) => msg,
+      Err(error_msg) => {
+        tracing::debug!("Invalid message from client {}: {:?}", client_id, error_msg);
+        if !queue_message(&clients, client_id, error_msg).await {
+          break;
         }
-      };
+        continue;
+      }
+    };
+    let msg_id = msg.id().to_string();
 
-      // Handle the message with optional timeout
-      let resp = if let Some(timeout) = query_timeout {
-        match tokio::time::timeout(timeout, handler.handle(client_id, msg)).await {
-          Ok(r) => r,
-          Err(_) => {
-            tracing::warn!("Query timeout for client {}", client_id);
-            ServerMessage::error(&msg_id, "Query execution timed out")
-          }
+    // Acquire query permit
+    let permit = match rate_limiter.acquire_query_permit(client_id) {
+      Ok(p) => p,
+      Err(e) => {
+        tracing::debug!("Query limit exceeded for {}: {}", client_id, e);
+        if !queue_message(
+          &clients,
+          client_id,
+          ServerMessage::error(&msg_id, e.to_string()).with_code(ErrorCode::RateLimited),
+        )
+        .await
+        {
+          break;
         }
-      } else {
-        handler.handle(client_id, msg).await
-      };
-
-      drop(permit); // Release query permit
+        continue;
+      }
+    };
 
-      if let Some(tx) = clients.read().await.get(&client_id) {
-        let _ = tx.send(resp);
+    // Streamed results go out chunk by chunk, without the query timeout
+    let trace_id = new_trace_id();
+    if matches!(msg, ClientMessage::Query { stream: true, .. }) {
+      let started = Instant::now();
+      let sent = with_trace_id(
+        trace_id.clone(),
+        handler.stream_query(client_id, msg, |resp| {
+          send_message(&clients, client_id, resp.with_trace_id(&trace_id))
+        }),
+      )
+      .await;
+      drop(permit);
+      let elapsed = started.elapsed();
+      if rate_limiter.is_slow(elapsed) {
+        tracing::warn!(
+          "Slow streamed query {} from client {} took {}ms",
+          msg_id,
+          client_id,
+          elapsed.as_millis()
+        );
+      }
+      if !sent {
+        break;
       }
+      continue;
+    }
+
+    // Handle the message with optional timeout, under its own trace ID
+    let resp = with_trace_id(
+      trace_id.clone(),
+      handle_limited(&handler, &rate_limiter, client_id, msg),
+    )
+    .await
+    .with_trace_id(&trace_id);
+
+    drop(permit); // Release query permit
+
+    if !queue_message(&clients, client_id, resp).await {
+      break;
     }
   }
 
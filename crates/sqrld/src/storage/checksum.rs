@@ -0,0 +1,54 @@
+//! Upload integrity checks.
+//!
+//! Clients can send `Content-MD5` or an `x-amz-checksum-*` header with a
+//! PutObject or UploadPart request. The body is hashed on arrival and the
+//! request is rejected with `BadDigest` if it doesn't match, so a corrupted
+//! upload is never stored.
+
+use axum::http::HeaderMap;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use super::error::{StorageError, StorageErrorCode};
+
+/// Checksum headers that are verified, with the algorithm name used in errors
+const CHECKSUM_HEADERS: [(&str, &str); 3] = [
+  ("content-md5", "Content-MD5"),
+  ("x-amz-checksum-crc32", "CRC32"),
+  ("x-amz-checksum-sha256", "SHA256"),
+];
+
+/// Check `body` against every checksum header the client sent
+pub fn verify_checksums(headers: &HeaderMap, body: &[u8]) -> Result<(), StorageError> {
+  for (header, algorithm) in CHECKSUM_HEADERS {
+    let Some(value) = headers.get(header) else {
+      continue;
+    };
+    let expected = value
+      .to_str()
+      .ok()
+      .and_then(|v| STANDARD.decode(v.trim()).ok())
+      .ok_or_else(|| {
+        StorageError::new(
+          StorageErrorCode::InvalidDigest,
+          format!("The {} you specified was invalid", algorithm),
+        )
+      })?;
+    let actual = match header {
+      "content-md5" => md5::compute(body).0.to_vec(),
+      "x-amz-checksum-crc32" => crc32fast::hash(body).to_be_bytes().to_vec(),
+      _ => Sha256::digest(body).to_vec(),
+    };
+    if expected != actual {
+      return Err(StorageError::new(
+        StorageErrorCode::BadDigest,
+        format!(
+          "The {} you specified did not match the calculated checksum",
+          algorithm
+        ),
+      ));
+    }
+  }
+  Ok(())
+}
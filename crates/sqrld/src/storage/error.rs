@@ -51,6 +51,7 @@ pub enum StorageErrorCode {
   InvalidSecurity,
   InvalidSOAPRequest,
   InvalidStorageClass,
+  InvalidTag,
   InvalidTargetBucketForLogging,
   InvalidToken,
   InvalidURI,
@@ -136,6 +137,7 @@ impl StorageErrorCode {
       Self::InvalidSecurity => "InvalidSecurity",
       Self::InvalidSOAPRequest => "InvalidSOAPRequest",
       Self::InvalidStorageClass => "InvalidStorageClass",
+      Self::InvalidTag => "InvalidTag",
       Self::InvalidTargetBucketForLogging => "InvalidTargetBucketForLogging",
       Self::InvalidToken => "InvalidToken",
       Self::InvalidURI => "InvalidURI",
@@ -204,6 +206,7 @@ impl StorageErrorCode {
       Self::InvalidPartOrder => StatusCode::BAD_REQUEST,
       Self::InvalidRange => StatusCode::RANGE_NOT_SATISFIABLE,
       Self::InvalidRequest => StatusCode::BAD_REQUEST,
+      Self::InvalidTag => StatusCode::BAD_REQUEST,
       Self::InvalidToken => StatusCode::BAD_REQUEST,
       Self::MalformedXML => StatusCode::BAD_REQUEST,
       Self::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
@@ -1,5 +1,6 @@
 mod auth;
 pub mod backend;
+pub mod checksum;
 pub mod config;
 pub mod error;
 mod filesystem;
@@ -1,6 +1,6 @@
 use axum::{
   extract::{Path, Query, State},
-  http::StatusCode,
+  http::{HeaderValue, StatusCode},
   response::{IntoResponse, Response},
 };
 use std::collections::HashMap;
@@ -106,26 +106,43 @@ pub async fn list_objects_or_operation(
   State(state): State<Arc<StorageState>>,
   Path(bucket): Path<String>,
   Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, StorageError> {
+  let region = state.config.region.clone();
+  let mut response = bucket_get(state, &bucket, params).await?;
+  // Clients use this to check they are talking to the right region
+  if let Ok(region) = HeaderValue::from_str(&region) {
+    response.headers_mut().insert("x-amz-bucket-region", region);
+  }
+  Ok(response)
+}
+
+async fn bucket_get(
+  state: Arc<StorageState>,
+  bucket: &str,
+  params: HashMap<String, String>,
 ) -> Result<Response, StorageError> {
   // Check for special operations
+  if params.contains_key("location") {
+    return get_bucket_location(state, bucket).await;
+  }
   if params.contains_key("versioning") {
-    return get_bucket_versioning(state, &bucket).await;
+    return get_bucket_versioning(state, bucket).await;
   }
   if params.contains_key("acl") {
-    return get_bucket_acl(state, &bucket).await;
+    return get_bucket_acl(state, bucket).await;
   }
   if params.contains_key("lifecycle") {
-    return get_bucket_lifecycle(state, &bucket).await;
+    return get_bucket_lifecycle(state, bucket).await;
   }
   if params.contains_key("uploads") {
-    return list_multipart_uploads(state, &bucket, params).await;
+    return list_multipart_uploads(state, bucket, params).await;
   }
   if params.contains_key("versions") {
-    return list_object_versions(state, &bucket, params).await;
+    return list_object_versions(state, bucket, params).await;
   }
 
   // Default: list objects
-  list_objects_v2(state, &bucket, params).await
+  list_objects_v2(state, bucket, params).await
 }
 
 /// GET /{bucket}?list-type=2 - List objects V2
@@ -135,7 +152,7 @@ async fn list_objects_v2(
   params: HashMap<String, String>,
 ) -> Result<Response, StorageError> {
   // Check bucket exists
-  state
+  let b = state
     .backend
     .get_storage_bucket(bucket)
     .await?
@@ -148,19 +165,38 @@ async fn list_objects_v2(
     .and_then(|s| s.parse().ok())
     .unwrap_or(1000);
   let continuation_token = params.get("continuation-token").cloned();
+  let start_after = params.get("start-after").cloned();
+  let fetch_owner = params.get("fetch-owner").is_some_and(|v| v == "true");
+
+  // The continuation token is the last key of the previous page, so it
+  // takes over from start-after once paging has begun
+  let start_key = continuation_token.as_deref().or(start_after.as_deref());
 
   // Use combined query for objects and prefixes (1 query instead of 2)
-  let (objects, common_prefixes, is_truncated, next_token) = state
+  let (objects, mut common_prefixes, is_truncated, next_token) = state
     .backend
     .list_storage_objects_with_prefixes(
       bucket,
       prefix.as_deref(),
       delimiter.as_deref(),
       max_keys,
-      continuation_token.as_deref(),
+      start_key,
     )
     .await?;
+  if let Some(start_key) = start_key {
+    common_prefixes.retain(|p| p.as_str() > start_key);
+  }
+
+  // Objects don't record an owner of their own; they belong to the bucket's
+  let owner = fetch_owner.then(|| Owner {
+    id: b
+      .owner_id
+      .map(|u| u.to_string())
+      .unwrap_or_else(|| "anonymous".to_string()),
+    display_name: None,
+  });
 
+  let key_count = (objects.len() + common_prefixes.len()) as i32;
   let response = ListObjectsResponse {
     name: bucket.to_string(),
     prefix,
@@ -175,7 +211,7 @@ async fn list_objects_v2(
         etag: o.etag,
         size: o.size,
         storage_class: "STANDARD".to_string(),
-        owner: None,
+        owner: owner.clone(),
       })
       .collect(),
     common_prefixes: common_prefixes
@@ -184,7 +220,8 @@ async fn list_objects_v2(
       .collect(),
     continuation_token,
     next_continuation_token: next_token,
-    key_count: 0, // Will be set after building contents
+    start_after,
+    key_count,
     encoding_type: None,
   };
 
@@ -192,6 +229,21 @@ async fn list_objects_v2(
   Ok((StatusCode::OK, [("Content-Type", "application/xml")], body).into_response())
 }
 
+/// GET /{bucket}?location - GetBucketLocation
+async fn get_bucket_location(
+  state: Arc<StorageState>,
+  bucket: &str,
+) -> Result<Response, StorageError> {
+  state
+    .backend
+    .get_storage_bucket(bucket)
+    .await?
+    .ok_or_else(|| StorageError::no_such_bucket(bucket))?;
+
+  let body = xml::location_constraint_xml(&state.config.region);
+  Ok((StatusCode::OK, [("Content-Type", "application/xml")], body).into_response())
+}
+
 /// GET /{bucket}?versioning
 async fn get_bucket_versioning(
   state: Arc<StorageState>,
@@ -297,6 +349,7 @@ async fn list_object_versions(
     .await?;
 
   // Build XML response for versions (simplified)
+  let key_count = objects.len() as i32;
   let response = ListObjectsResponse {
     name: bucket.to_string(),
     prefix,
@@ -317,7 +370,8 @@ async fn list_object_versions(
     common_prefixes: vec![],
     continuation_token: None,
     next_continuation_token: None,
-    key_count: 0,
+    start_after: None,
+    key_count,
     encoding_type: None,
   };
 
@@ -31,6 +31,6 @@ pub fn build_router(state: Arc<StorageState>) -> Router {
     .route("/{bucket}/{*key}", head(head_object))
     .route("/{bucket}/{*key}", delete(delete_object_or_operation))
     .route("/{bucket}/{*key}", post(post_object_operation))
-    .layer(SecurityHeadersLayer)
+    .layer(SecurityHeadersLayer::default())
     .with_state(state)
 }
@@ -1,12 +1,13 @@
 use axum::{
   body::Bytes,
-  http::StatusCode,
+  http::{HeaderMap, StatusCode},
   response::{IntoResponse, Response},
 };
 use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::storage::checksum;
 use crate::storage::error::StorageError;
 use crate::storage::server::StorageState;
 use crate::storage::types::*;
@@ -54,6 +55,7 @@ pub async fn upload_part(
   _bucket: &str,
   _key: &str,
   params: HashMap<String, String>,
+  headers: &HeaderMap,
   body: Bytes,
 ) -> Result<Response, StorageError> {
   let upload_id = params
@@ -88,6 +90,8 @@ pub async fn upload_part(
     ));
   }
 
+  checksum::verify_checksums(headers, &body)?;
+
   // Write part to storage
   let (storage_path, etag, size) = state
     .storage
@@ -9,6 +9,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::security::validate_object_key;
+use crate::storage::checksum;
 use crate::storage::error::StorageError;
 use crate::storage::server::StorageState;
 use crate::storage::types::*;
@@ -34,6 +35,9 @@ pub async fn put_object_or_operation(
   if params.contains_key("acl") {
     return put_object_acl(state, &bucket, &key, body).await;
   }
+  if params.contains_key("tagging") {
+    return put_object_tagging(state, &bucket, &key, body).await;
+  }
 
   // Check for copy operation
   if let Some(copy_source) = headers.get("x-amz-copy-source") {
@@ -44,7 +48,7 @@ pub async fn put_object_or_operation(
 
   // Check for multipart upload part
   if params.contains_key("partNumber") && params.contains_key("uploadId") {
-    return super::upload_part(state, &bucket, &key, params, body).await;
+    return super::upload_part(state, &bucket, &key, params, &headers, body).await;
   }
 
   // Default: put object
@@ -74,6 +78,19 @@ async fn put_object(
     ));
   }
 
+  checksum::verify_checksums(&headers, &body)?;
+
+  // Conditional writes. The check runs before the write rather than inside
+  // it, so two racing writers can both pass.
+  if headers.contains_key("if-match") || headers.contains_key("if-none-match") {
+    let current = state
+      .backend
+      .get_storage_object(bucket, key, None)
+      .await?
+      .filter(|o| !o.is_delete_marker);
+    check_preconditions(&headers, key, current.as_ref(), false)?;
+  }
+
   // Get content type
   let content_type = headers
     .get("content-type")
@@ -286,6 +303,9 @@ pub async fn get_object_or_operation(
   if params.contains_key("acl") {
     return get_object_acl(state, &bucket, &key).await;
   }
+  if params.contains_key("tagging") {
+    return get_object_tagging(state, &bucket, &key).await;
+  }
   if params.contains_key("uploadId") {
     return super::list_parts(state, &bucket, &key, params).await;
   }
@@ -326,6 +346,10 @@ async fn get_object(
     return Err(StorageError::no_such_key(key));
   }
 
+  if let Some(not_modified) = check_preconditions(&headers, key, Some(&object), true)? {
+    return Ok(not_modified);
+  }
+
   // Parse range header if present
   let range = headers
     .get("range")
@@ -386,11 +410,71 @@ async fn get_object_acl(
   Ok((StatusCode::OK, [("Content-Type", "application/xml")], body).into_response())
 }
 
+/// PUT /{bucket}/{key}?tagging - Replace the object's tags
+async fn put_object_tagging(
+  state: Arc<StorageState>,
+  bucket: &str,
+  key: &str,
+  body: Bytes,
+) -> Result<Response, StorageError> {
+  state
+    .backend
+    .get_storage_object(bucket, key, None)
+    .await?
+    .ok_or_else(|| StorageError::no_such_key(key))?;
+
+  let tags = xml::parse_tagging_xml(&String::from_utf8_lossy(&body))?;
+  state
+    .backend
+    .set_storage_object_tags(bucket, key, &tags)
+    .await?;
+
+  Ok(StatusCode::OK.into_response())
+}
+
+/// GET /{bucket}/{key}?tagging
+async fn get_object_tagging(
+  state: Arc<StorageState>,
+  bucket: &str,
+  key: &str,
+) -> Result<Response, StorageError> {
+  state
+    .backend
+    .get_storage_object(bucket, key, None)
+    .await?
+    .ok_or_else(|| StorageError::no_such_key(key))?;
+
+  let tags = state.backend.get_storage_object_tags(bucket, key).await?;
+  let body = xml::tagging_xml(&tags);
+  Ok((StatusCode::OK, [("Content-Type", "application/xml")], body).into_response())
+}
+
+/// DELETE /{bucket}/{key}?tagging
+async fn delete_object_tagging(
+  state: Arc<StorageState>,
+  bucket: &str,
+  key: &str,
+) -> Result<Response, StorageError> {
+  state
+    .backend
+    .get_storage_object(bucket, key, None)
+    .await?
+    .ok_or_else(|| StorageError::no_such_key(key))?;
+
+  state
+    .backend
+    .set_storage_object_tags(bucket, key, &ObjectTags::new())
+    .await?;
+
+  Ok(StatusCode::NO_CONTENT.into_response())
+}
+
 /// HEAD /{bucket}/{key}
 pub async fn head_object(
   State(state): State<Arc<StorageState>>,
   Path((bucket, key)): Path<(String, String)>,
   Query(params): Query<HashMap<String, String>>,
+  headers: HeaderMap,
 ) -> Result<Response, StorageError> {
   // Validate object key to prevent path traversal attacks
   validate_object_key(&key).map_err(|e| {
@@ -423,6 +507,10 @@ pub async fn head_object(
     return Err(StorageError::no_such_key(&key));
   }
 
+  if let Some(not_modified) = check_preconditions(&headers, &key, Some(&object), true)? {
+    return Ok(not_modified);
+  }
+
   Ok(
     (
       StatusCode::OK,
@@ -455,6 +543,9 @@ pub async fn delete_object_or_operation(
   if params.contains_key("uploadId") {
     return super::abort_multipart_upload(state, &bucket, &key, params).await;
   }
+  if params.contains_key("tagging") {
+    return delete_object_tagging(state, &bucket, &key).await;
+  }
 
   // Default: delete object
   delete_object(state, &bucket, &key, params).await
@@ -551,6 +642,60 @@ pub async fn post_object_operation(
   ))
 }
 
+/// Evaluate `If-Match` and `If-None-Match` against the current object,
+/// `None` if there is none. A read whose `If-None-Match` matches gets the
+/// returned `304 Not Modified`; any other failed condition is an error.
+fn check_preconditions(
+  headers: &HeaderMap,
+  key: &str,
+  current: Option<&StorageObject>,
+  read: bool,
+) -> Result<Option<Response>, StorageError> {
+  let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+  let precondition_failed = || {
+    StorageError::new(
+      crate::storage::error::StorageErrorCode::PreconditionFailed,
+      "At least one of the pre-conditions you specified did not hold",
+    )
+  };
+
+  if let Some(if_match) = header("if-match") {
+    let Some(object) = current else {
+      return Err(StorageError::no_such_key(key));
+    };
+    if !etag_matches(if_match, &object.etag) {
+      return Err(precondition_failed());
+    }
+  }
+
+  if let Some(if_none_match) = header("if-none-match") {
+    if let Some(object) = current {
+      if etag_matches(if_none_match, &object.etag) {
+        if !read {
+          return Err(precondition_failed());
+        }
+        return Ok(Some(
+          (
+            StatusCode::NOT_MODIFIED,
+            [("ETag", format!("\"{}\"", object.etag))],
+          )
+            .into_response(),
+        ));
+      }
+    }
+  }
+
+  Ok(None)
+}
+
+/// Whether an `If-Match`/`If-None-Match` list names `etag` (or is `*`)
+fn etag_matches(header: &str, etag: &str) -> bool {
+  header.split(',').any(|candidate| {
+    let candidate = candidate.trim();
+    candidate == "*" || candidate.trim_start_matches("W/").trim_matches('"') == etag
+  })
+}
+
 /// Parse Range header
 fn parse_range(header: &str) -> Option<(u64, Option<u64>)> {
   let header = header.strip_prefix("bytes=")?;
@@ -561,3 +706,77 @@ fn parse_range(header: &str) -> Option<(u64, Option<u64>)> {
     .and_then(|s| if s.is_empty() { None } else { s.parse().ok() });
   Some((start, end))
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn object(etag: &str) -> StorageObject {
+    StorageObject {
+      bucket: "b".to_string(),
+      key: "k".to_string(),
+      version_id: Uuid::new_v4(),
+      is_latest: true,
+      etag: etag.to_string(),
+      size: 0,
+      content_type: "text/plain".to_string(),
+      storage_path: "/tmp/k".to_string(),
+      metadata: serde_json::json!({}),
+      acl: ObjectAcl::default(),
+      is_delete_marker: false,
+      created_at: chrono::Utc::now(),
+    }
+  }
+
+  fn headers(name: &'static str, value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(name, value.parse().unwrap());
+    headers
+  }
+
+  #[test]
+  fn test_etag_matches() {
+    assert!(etag_matches("\"abc\"", "abc"));
+    assert!(etag_matches("\"x\", W/\"abc\"", "abc"));
+    assert!(etag_matches("*", "abc"));
+    assert!(!etag_matches("\"x\"", "abc"));
+  }
+
+  #[test]
+  fn test_check_preconditions() {
+    let current = object("abc");
+
+    // Create-only writes fail once the object exists
+    let create_only = headers("if-none-match", "*");
+    assert!(check_preconditions(&create_only, "k", None, false)
+      .unwrap()
+      .is_none());
+    let err = check_preconditions(&create_only, "k", Some(&current), false).unwrap_err();
+    assert_eq!(
+      err.code,
+      crate::storage::error::StorageErrorCode::PreconditionFailed
+    );
+
+    // If-Match needs the current ETag, and an existing object
+    let if_match = headers("if-match", "\"abc\"");
+    assert!(check_preconditions(&if_match, "k", Some(&current), false)
+      .unwrap()
+      .is_none());
+    assert!(
+      check_preconditions(&headers("if-match", "\"old\""), "k", Some(&current), false).is_err()
+    );
+    let err = check_preconditions(&if_match, "k", None, false).unwrap_err();
+    assert_eq!(err.code, crate::storage::error::StorageErrorCode::NoSuchKey);
+
+    // Reads get 304 when the ETag still matches
+    let response = check_preconditions(
+      &headers("if-none-match", "\"abc\""),
+      "k",
+      Some(&current),
+      true,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+  }
+}
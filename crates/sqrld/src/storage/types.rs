@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -140,10 +142,32 @@ pub struct LifecycleRule {
   pub id: String,
   pub enabled: bool,
   pub prefix: Option<String>,
+  /// Only objects carrying all of these tags are affected
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub tags: ObjectTags,
   pub expiration_days: Option<i32>,
   pub noncurrent_version_expiration_days: Option<i32>,
 }
 
+impl LifecycleRule {
+  /// Whether the rule applies to an object with this key and tags
+  pub fn matches(&self, key: &str, tags: &ObjectTags) -> bool {
+    self.enabled
+      && self.prefix.as_deref().is_none_or(|p| key.starts_with(p))
+      && self.tags.iter().all(|(k, v)| tags.get(k) == Some(v))
+  }
+}
+
+/// Object tags, key -> value
+pub type ObjectTags = BTreeMap<String, String>;
+
+/// Most tags an object can carry
+pub const MAX_OBJECT_TAGS: usize = 10;
+/// Longest tag key, in characters
+pub const MAX_TAG_KEY_LEN: usize = 128;
+/// Longest tag value, in characters
+pub const MAX_TAG_VALUE_LEN: usize = 256;
+
 /// Response for list buckets operation
 #[derive(Debug, Clone, Serialize)]
 pub struct ListBucketsResponse {
@@ -177,6 +201,8 @@ pub struct ListObjectsResponse {
   pub common_prefixes: Vec<CommonPrefix>,
   pub continuation_token: Option<String>,
   pub next_continuation_token: Option<String>,
+  pub start_after: Option<String>,
+  /// Keys plus common prefixes in this page
   pub key_count: i32,
   pub encoding_type: Option<String>,
 }
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 
+use super::error::{StorageError, StorageErrorCode};
 use super::types::*;
 
 /// Build XML for ListAllMyBucketsResult
@@ -78,6 +79,13 @@ pub fn list_objects_v2_xml(response: &ListObjectsResponse) -> String {
     ));
   }
 
+  if let Some(ref start_after) = response.start_after {
+    xml.push_str(&format!(
+      "  <StartAfter>{}</StartAfter>\n",
+      escape_xml(start_after)
+    ));
+  }
+
   for obj in &response.contents {
     xml.push_str("  <Contents>\n");
     xml.push_str(&format!("    <Key>{}</Key>\n", escape_xml(&obj.key)));
@@ -239,6 +247,18 @@ pub fn versioning_config_xml(enabled: bool) -> String {
   )
 }
 
+/// Build XML for LocationConstraint (GetBucketLocation). S3 reports
+/// us-east-1 as an empty constraint.
+pub fn location_constraint_xml(region: &str) -> String {
+  if region == "us-east-1" {
+    return "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<LocationConstraint xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"/>".to_string();
+  }
+  format!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<LocationConstraint xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">{}</LocationConstraint>",
+    escape_xml(region)
+  )
+}
+
 /// Build XML for DeleteResult
 pub fn delete_result_xml(result: &DeleteResult) -> String {
   let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
@@ -348,8 +368,26 @@ pub fn lifecycle_config_xml(rules: &[LifecycleRule]) -> String {
       "    <Status>{}</Status>\n",
       if rule.enabled { "Enabled" } else { "Disabled" }
     ));
-    if let Some(ref prefix) = rule.prefix {
-      xml.push_str(&format!("    <Prefix>{}</Prefix>\n", escape_xml(prefix)));
+    if rule.tags.is_empty() {
+      if let Some(ref prefix) = rule.prefix {
+        xml.push_str(&format!("    <Prefix>{}</Prefix>\n", escape_xml(prefix)));
+      }
+    } else {
+      xml.push_str("    <Filter>\n      <And>\n");
+      if let Some(ref prefix) = rule.prefix {
+        xml.push_str(&format!(
+          "        <Prefix>{}</Prefix>\n",
+          escape_xml(prefix)
+        ));
+      }
+      for (key, value) in &rule.tags {
+        xml.push_str(&format!(
+          "        <Tag><Key>{}</Key><Value>{}</Value></Tag>\n",
+          escape_xml(key),
+          escape_xml(value)
+        ));
+      }
+      xml.push_str("      </And>\n    </Filter>\n");
     }
     if let Some(days) = rule.expiration_days {
       xml.push_str("    <Expiration>\n");
@@ -371,6 +409,77 @@ pub fn lifecycle_config_xml(rules: &[LifecycleRule]) -> String {
   xml
 }
 
+/// Build XML for Tagging (GetObjectTagging)
+pub fn tagging_xml(tags: &ObjectTags) -> String {
+  let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+  xml.push_str("<Tagging xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\n");
+  xml.push_str("  <TagSet>\n");
+  for (key, value) in tags {
+    xml.push_str("    <Tag>\n");
+    xml.push_str(&format!("      <Key>{}</Key>\n", escape_xml(key)));
+    xml.push_str(&format!("      <Value>{}</Value>\n", escape_xml(value)));
+    xml.push_str("    </Tag>\n");
+  }
+  xml.push_str("  </TagSet>\n");
+  xml.push_str("</Tagging>");
+  xml
+}
+
+/// Parse a Tagging request body (PutObjectTagging), enforcing S3's limits
+/// on the number and length of tags
+pub fn parse_tagging_xml(xml: &str) -> Result<ObjectTags, StorageError> {
+  let tagging = regex::Regex::new(r"(?s)<Tagging[^>]*>.*</Tagging>")
+    .map_err(|_| StorageError::internal_error("Regex error"))?;
+  if !tagging.is_match(xml) {
+    return Err(StorageError::new(
+      StorageErrorCode::MalformedXML,
+      "Expected a Tagging document",
+    ));
+  }
+  let tag = regex::Regex::new(
+    r"(?s)<Tag>\s*<Key>([^<]*)</Key>\s*<Value>([^<]*)</Value>\s*</Tag>|<Tag>\s*<Value>([^<]*)</Value>\s*<Key>([^<]*)</Key>\s*</Tag>",
+  )
+  .map_err(|_| StorageError::internal_error("Regex error"))?;
+
+  let mut tags = ObjectTags::new();
+  for cap in tag.captures_iter(xml) {
+    let (key, value) = match (cap.get(1), cap.get(2)) {
+      (Some(key), Some(value)) => (key.as_str(), value.as_str()),
+      _ => (&cap[4], &cap[3]),
+    };
+    let (key, value) = (unescape_xml(key), unescape_xml(value));
+    if key.is_empty() || key.chars().count() > MAX_TAG_KEY_LEN {
+      return Err(StorageError::new(
+        StorageErrorCode::InvalidTag,
+        format!("Tag keys must be 1 to {} characters", MAX_TAG_KEY_LEN),
+      ));
+    }
+    if value.chars().count() > MAX_TAG_VALUE_LEN {
+      return Err(StorageError::new(
+        StorageErrorCode::InvalidTag,
+        format!(
+          "Tag values must be at most {} characters",
+          MAX_TAG_VALUE_LEN
+        ),
+      ));
+    }
+    if tags.insert(key.clone(), value).is_some() {
+      return Err(StorageError::new(
+        StorageErrorCode::InvalidTag,
+        format!("Cannot provide multiple tags with the same key '{}'", key),
+      ));
+    }
+  }
+
+  if tags.len() > MAX_OBJECT_TAGS {
+    return Err(StorageError::new(
+      StorageErrorCode::InvalidTag,
+      format!("Object tags cannot be greater than {}", MAX_OBJECT_TAGS),
+    ));
+  }
+  Ok(tags)
+}
+
 fn permission_to_str(p: Permission) -> &'static str {
   match p {
     Permission::FullControl => "FULL_CONTROL",
@@ -381,6 +490,14 @@ fn permission_to_str(p: Permission) -> &'static str {
   }
 }
 
+fn unescape_xml(s: &str) -> String {
+  s.replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&apos;", "'")
+    .replace("&quot;", "\"")
+    .replace("&amp;", "&")
+}
+
 fn escape_xml(s: &str) -> String {
   s.replace('&', "&amp;")
     .replace('<', "&lt;")
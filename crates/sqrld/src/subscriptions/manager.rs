@@ -1,17 +1,67 @@
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rquickjs::{Context, Runtime};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
+use super::watch::Watch;
 use crate::db::DatabaseBackend;
-use crate::types::{Change, ChangeEvent, ChangeOperation, Document, QuerySpec, ServerMessage};
+use crate::query::QueryEnginePool;
+use crate::types::{
+  Change, ChangeEvent, ChangeOperation, Document, ErrorCode, QuerySpec, ServerMessage,
+};
+
+/// Change IDs remembered per client for read-your-writes, newest kept
+const OBSERVED_CHANGES_LIMIT: usize = 1024;
 
 #[derive(Clone)]
 struct Subscription {
   id: String,
   query: QuerySpec,
+  /// Skip changes the client saw in its own write responses
+  read_your_writes: bool,
+  /// Documents the subscription is limited to, empty for all
+  ids: HashSet<Uuid>,
+}
+
+/// A client's own writes, tracked for its read-your-writes subscriptions
+#[derive(Default)]
+struct OwnWrites {
+  /// Writes whose change ID isn't known yet
+  pending: usize,
+  /// Change IDs returned to the client in write responses
+  observed: BTreeSet<i64>,
+  /// Changes held back until the pending writes finish:
+  /// (subscription ID, change ID, event)
+  held: Vec<(String, i64, ChangeEvent)>,
+}
+
+/// A write in progress for a client with read-your-writes subscriptions.
+///
+/// Changes for the client are held back until the write is finished with
+/// the change ID it produced, so the change can't overtake the response.
+/// Dropping it unfinished releases the held changes.
+pub struct OwnWrite<'a> {
+  subs: &'a SubscriptionManager,
+  client: Uuid,
+  finished: bool,
+}
+
+impl OwnWrite<'_> {
+  /// Record the change the write produced (None if there was none)
+  pub fn finish(mut self, change_id: Option<i64>) {
+    self.finished = true;
+    self.subs.finish_write(self.client, change_id);
+  }
+}
+
+impl Drop for OwnWrite<'_> {
+  fn drop(&mut self) {
+    if !self.finished {
+      self.subs.finish_write(self.client, None);
+    }
+  }
 }
 
 /// Manages subscriptions with O(1) lookup by collection.
@@ -27,6 +77,10 @@ pub struct SubscriptionManager {
   runtime: Runtime,
   /// Optional database backend for registering subscription filters in PostgreSQL
   backend: Option<Arc<dyn DatabaseBackend>>,
+  /// Client ID -> writes seen by its read-your-writes subscriptions
+  own_writes: Mutex<HashMap<Uuid, OwnWrites>>,
+  /// Client ID -> (Watch ID -> Watch)
+  watches: RwLock<HashMap<Uuid, HashMap<String, Arc<Watch>>>>,
 }
 
 impl SubscriptionManager {
@@ -40,6 +94,8 @@ impl SubscriptionManager {
       out_tx,
       runtime,
       backend: None,
+      own_writes: Mutex::new(HashMap::new()),
+      watches: RwLock::new(HashMap::new()),
     }
   }
 
@@ -54,6 +110,8 @@ impl SubscriptionManager {
       out_tx,
       runtime,
       backend: Some(backend),
+      own_writes: Mutex::new(HashMap::new()),
+      watches: RwLock::new(HashMap::new()),
     }
   }
 
@@ -61,9 +119,31 @@ impl SubscriptionManager {
     self.out_tx.subscribe()
   }
 
-  /// Add a subscription and optionally register its SQL filter in PostgreSQL
-  pub async fn add_subscription(&self, client: Uuid, id: String, query: QuerySpec) {
+  /// Add a subscription and optionally register its SQL filter in PostgreSQL.
+  /// With `read_your_writes`, changes the client saw in its own write
+  /// responses (see `begin_write`) aren't delivered again. A non-empty
+  /// `ids` limits the subscription to changes of those documents.
+  ///
+  /// Returns whether the filter was compiled to SQL. Fails, without adding
+  /// the subscription, if `id` is one of the client's watches or if the
+  /// filter can't be registered.
+  pub async fn add_subscription(
+    &self,
+    client: Uuid,
+    id: String,
+    query: QuerySpec,
+    read_your_writes: bool,
+    ids: Vec<Uuid>,
+  ) -> Result<bool, anyhow::Error> {
     let collection = query.table.clone();
+    if self
+      .watches
+      .read()
+      .get(&client)
+      .is_some_and(|w| w.contains_key(&id))
+    {
+      anyhow::bail!("Subscription ID '{}' is already in use", id);
+    }
 
     // Extract compiled SQL filter if available (for PostgreSQL-side filtering)
     let compiled_sql = query
@@ -72,14 +152,14 @@ impl SubscriptionManager {
       .and_then(|f| f.compiled_sql.as_ref())
       .map(|s| s.as_str());
 
+    let compiled = compiled_sql.is_some();
+
     // Register filter in PostgreSQL for server-side filtering (if backend available)
     if let Some(ref backend) = self.backend {
-      if let Err(e) = backend
+      backend
         .add_subscription_filter(client, &id, &collection, compiled_sql)
         .await
-      {
-        tracing::warn!("Failed to register subscription filter in DB: {}", e);
-      }
+        .map_err(|e| anyhow::anyhow!("Failed to register subscription filter: {}", e))?;
     }
 
     // Add to main subscriptions map
@@ -88,6 +168,8 @@ impl SubscriptionManager {
       Subscription {
         id: id.clone(),
         query,
+        read_your_writes,
+        ids: ids.into_iter().collect(),
       },
     );
 
@@ -98,10 +180,95 @@ impl SubscriptionManager {
       .entry(collection)
       .or_default()
       .push((client, id));
+    Ok(compiled)
+  }
+
+  /// Watch a query for `client`. The first `diff`, adding the current
+  /// result, is sent before this returns; later ones follow changes to the
+  /// query's collection. Fails, without adding the watch, if `id` is already
+  /// one of the client's subscriptions or watches, if the client holds
+  /// `max_watches` watches already (0 = unlimited), or if the query can't run.
+  pub async fn add_watch(
+    &self,
+    client: Uuid,
+    id: String,
+    query: QuerySpec,
+    max_watches: usize,
+    engine_pool: Arc<QueryEnginePool>,
+    backend: Arc<dyn DatabaseBackend>,
+  ) -> Result<(), (ErrorCode, String)> {
+    let watch = Arc::new(Watch::new(client, id.clone(), query, engine_pool, backend));
+    {
+      let subs = self.subs.read();
+      let mut watches = self.watches.write();
+      let held = watches.get(&client);
+      if held.is_some_and(|w| w.contains_key(&id))
+        || subs.get(&client).is_some_and(|s| s.contains_key(&id))
+      {
+        return Err((
+          ErrorCode::Conflict,
+          format!("Subscription ID '{}' is already in use", id),
+        ));
+      }
+      if max_watches > 0 && held.map_or(0, HashMap::len) >= max_watches {
+        return Err((
+          ErrorCode::RateLimited,
+          format!(
+            "Too many watched queries: limit is {} per client",
+            max_watches
+          ),
+        ));
+      }
+      // Registered before the first run so no change in between is missed
+      watches
+        .entry(client)
+        .or_default()
+        .insert(id.clone(), watch.clone());
+    }
+    if let Err(e) = watch.refresh(&self.out_tx).await {
+      self.remove_watch(client, &id);
+      return Err((ErrorCode::InvalidQuery, e.to_string()));
+    }
+    Ok(())
+  }
+
+  fn remove_watch(&self, client: Uuid, id: &str) {
+    let mut watches = self.watches.write();
+    if let Some(client_watches) = watches.get_mut(&client) {
+      if let Some(watch) = client_watches.remove(id) {
+        watch.stop();
+      }
+      if client_watches.is_empty() {
+        watches.remove(&client);
+      }
+    }
+  }
+
+  /// Queue a refresh of each watch `change` can affect
+  fn refresh_watches(&self, change: &Change) {
+    let watches = self.watches.read();
+    for watch in watches.values().flat_map(HashMap::values) {
+      if !watch.affected_by(change) || !watch.queue() {
+        continue;
+      }
+      let watch = watch.clone();
+      let out_tx = self.out_tx.clone();
+      tokio::spawn(async move {
+        if let Err(e) = watch.refresh(&out_tx).await {
+          tracing::warn!("Failed to refresh watch '{}': {}", watch.id, e);
+          let _ = out_tx.send((
+            watch.client,
+            ServerMessage::error(&watch.id, e.to_string()).with_code(ErrorCode::InvalidQuery),
+          ));
+        }
+      });
+    }
   }
 
-  /// Remove a subscription and unregister its filter from PostgreSQL
+  /// Remove a subscription or watch and unregister its filter from PostgreSQL
   pub async fn remove_subscription(&self, client: Uuid, id: &str) {
+    self.remove_watch(client, id);
+
     // Remove filter from PostgreSQL
     if let Some(ref backend) = self.backend {
       if let Err(e) = backend.remove_subscription_filter(client, id).await {
@@ -137,6 +304,11 @@ impl SubscriptionManager {
       }
     }
 
+    self.own_writes.lock().remove(&client);
+    if let Some(watches) = self.watches.write().remove(&client) {
+      watches.values().for_each(|watch| watch.stop());
+    }
+
     let mut subs = self.subs.write();
     if let Some(client_subs) = subs.remove(&client) {
       // Remove all subscriptions from collection index
@@ -152,8 +324,16 @@ impl SubscriptionManager {
     }
   }
 
+  /// Number of subscriptions and watches a client holds
+  pub fn subscription_count(&self, client: Uuid) -> usize {
+    self.subs.read().get(&client).map_or(0, HashMap::len)
+      + self.watches.read().get(&client).map_or(0, HashMap::len)
+  }
+
   pub async fn process_changes(&self, mut rx: broadcast::Receiver<Change>) {
     while let Ok(change) = rx.recv().await {
+      self.refresh_watches(&change);
+
       // Use the collection index for O(S) lookup instead of O(N×M) iteration
       let index = self.collection_index.read();
       let Some(subscriptions) = index.get(&change.collection) else {
@@ -165,11 +345,18 @@ impl SubscriptionManager {
       for (client_id, sub_id) in subscriptions {
         if let Some(client_subs) = subs.get(client_id) {
           if let Some(sub) = client_subs.get(sub_id) {
+            if !sub.ids.is_empty() && !sub.ids.contains(&change.document_id) {
+              continue;
+            }
             if self.matches(&sub.query, &change) {
               if let Some(evt) = self.to_event(&sub.query, &change) {
-                let _ = self
-                  .out_tx
-                  .send((*client_id, ServerMessage::change(&sub.id, evt)));
+                if sub.read_your_writes {
+                  self.deliver_unobserved(*client_id, &sub.id, change.id, evt);
+                } else {
+                  let _ = self
+                    .out_tx
+                    .send((*client_id, ServerMessage::change(&sub.id, evt)));
+                }
               }
             }
           }
@@ -178,7 +365,80 @@ impl SubscriptionManager {
     }
   }
 
+  /// Whether the client has a read-your-writes subscription
+  pub fn has_read_your_writes(&self, client: Uuid) -> bool {
+    self
+      .subs
+      .read()
+      .get(&client)
+      .is_some_and(|subs| subs.values().any(|s| s.read_your_writes))
+  }
+
+  /// Start a write by `client`, or None if it has no read-your-writes
+  /// subscription to protect
+  pub fn begin_write(&self, client: Uuid) -> Option<OwnWrite<'_>> {
+    if !self.has_read_your_writes(client) {
+      return None;
+    }
+    self.own_writes.lock().entry(client).or_default().pending += 1;
+    Some(OwnWrite {
+      subs: self,
+      client,
+      finished: false,
+    })
+  }
+
+  /// Record a finished write and release the held changes once no other
+  /// write is pending
+  fn finish_write(&self, client: Uuid, change_id: Option<i64>) {
+    let mut own_writes = self.own_writes.lock();
+    let Some(writes) = own_writes.get_mut(&client) else {
+      return;
+    };
+    if let Some(change_id) = change_id {
+      writes.observed.insert(change_id);
+      if writes.observed.len() > OBSERVED_CHANGES_LIMIT {
+        writes.observed.pop_first();
+      }
+    }
+    writes.pending = writes.pending.saturating_sub(1);
+    if writes.pending > 0 {
+      return;
+    }
+    for (sub_id, change_id, evt) in std::mem::take(&mut writes.held) {
+      if !writes.observed.contains(&change_id) {
+        let _ = self.out_tx.send((
+          client,
+          ServerMessage::change(sub_id, evt).with_change_id(Some(change_id)),
+        ));
+      }
+    }
+  }
+
+  /// Deliver a change to a read-your-writes subscription unless the client
+  /// saw it in a write response, holding it while a write is pending
+  fn deliver_unobserved(&self, client: Uuid, sub_id: &str, change_id: i64, evt: ChangeEvent) {
+    let mut own_writes = self.own_writes.lock();
+    if let Some(writes) = own_writes.get_mut(&client) {
+      if writes.observed.contains(&change_id) {
+        return;
+      }
+      if writes.pending > 0 {
+        writes.held.push((sub_id.to_string(), change_id, evt));
+        return;
+      }
+    }
+    let _ = self.out_tx.send((
+      client,
+      ServerMessage::change(sub_id, evt).with_change_id(Some(change_id)),
+    ));
+  }
+
   fn matches(&self, query: &QuerySpec, change: &Change) -> bool {
+    // Never deliver changes from another project
+    if query.project_id.is_some_and(|p| p != change.project_id) {
+      return false;
+    }
     let Some(filter) = &query.filter else {
       return true;
     };
@@ -241,6 +501,7 @@ impl SubscriptionManager {
             data,
             created_at: change.changed_at,
             updated_at: change.changed_at,
+            deleted_at: None,
           },
         })
       }
@@ -256,6 +517,7 @@ impl SubscriptionManager {
             data: new,
             created_at: change.changed_at,
             updated_at: change.changed_at,
+            deleted_at: None,
           },
         })
       }
@@ -269,6 +531,7 @@ impl SubscriptionManager {
             data,
             created_at: change.changed_at,
             updated_at: change.changed_at,
+            deleted_at: None,
           },
         })
       }
@@ -1,3 +1,4 @@
 mod manager;
+mod watch;
 
-pub use manager::SubscriptionManager;
+pub use manager::{OwnWrite, SubscriptionManager};
@@ -0,0 +1,270 @@
+//! Watched queries: results kept up to date for a client
+//!
+//! A watch re-runs its query after changes to its collection and sends the
+//! client the steps from the result it holds to the new one. Changes that
+//! arrive while a refresh is queued are covered by that refresh, so a burst
+//! of writes costs one or two runs rather than one per write.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use uuid::Uuid;
+
+use crate::db::DatabaseBackend;
+use crate::query::QueryEnginePool;
+use crate::types::{Change, QuerySpec, ResultDiff, ServerMessage, DEFAULT_PROJECT_ID};
+
+pub(super) struct Watch {
+  pub client: Uuid,
+  pub id: String,
+  spec: QuerySpec,
+  project_id: Uuid,
+  engine_pool: Arc<QueryEnginePool>,
+  backend: Arc<dyn DatabaseBackend>,
+  /// Result the client holds, None before the first refresh
+  rows: Mutex<Option<Vec<serde_json::Value>>>,
+  /// A refresh is queued and hasn't started yet
+  queued: AtomicBool,
+  /// The client unsubscribed; refreshes in flight send nothing
+  stopped: AtomicBool,
+}
+
+impl Watch {
+  pub fn new(
+    client: Uuid,
+    id: String,
+    spec: QuerySpec,
+    engine_pool: Arc<QueryEnginePool>,
+    backend: Arc<dyn DatabaseBackend>,
+  ) -> Self {
+    Self {
+      client,
+      id,
+      project_id: spec.project_id.unwrap_or(DEFAULT_PROJECT_ID),
+      spec,
+      engine_pool,
+      backend,
+      rows: Mutex::new(None),
+      queued: AtomicBool::new(false),
+      stopped: AtomicBool::new(false),
+    }
+  }
+
+  /// Whether `change` can alter the result
+  pub fn affected_by(&self, change: &Change) -> bool {
+    change.collection == self.spec.table && change.project_id == self.project_id
+  }
+
+  /// Mark a refresh as queued. Returns false when one already is.
+  pub fn queue(&self) -> bool {
+    !self.queued.swap(true, Ordering::SeqCst)
+  }
+
+  pub fn stop(&self) {
+    self.stopped.store(true, Ordering::SeqCst);
+  }
+
+  /// Re-run the query and send the client a `diff` from the result it holds
+  /// to the new one. The first refresh always sends one, adding every row.
+  pub async fn refresh(
+    &self,
+    out_tx: &broadcast::Sender<(Uuid, ServerMessage)>,
+  ) -> Result<(), anyhow::Error> {
+    // Held until the diff is sent, so diffs go out in the order they apply
+    let mut rows = self.rows.lock().await;
+    self.queued.store(false, Ordering::SeqCst);
+    let result = self
+      .engine_pool
+      .run_parsed(&self.spec, self.project_id, self.backend.as_ref())
+      .await?;
+    let serde_json::Value::Array(new) = result.data else {
+      anyhow::bail!("Watched query on '{}' didn't return rows", self.spec.table);
+    };
+    let diff = diff_results(rows.as_deref().unwrap_or_default(), &new);
+    if self.stopped.load(Ordering::SeqCst) || (rows.is_some() && diff.is_empty()) {
+      return Ok(());
+    }
+    *rows = Some(new);
+    let _ = out_tx.send((self.client, ServerMessage::diff(&self.id, diff)));
+    Ok(())
+  }
+}
+
+/// Document ID of a result row
+fn row_id(row: &serde_json::Value) -> Uuid {
+  row
+    .get("id")
+    .and_then(|id| id.as_str())
+    .and_then(|id| id.parse().ok())
+    .unwrap_or_default()
+}
+
+/// Steps that turn the rows `old` into `new`, matching rows by document ID.
+/// Rows that left are removed first, then each position of `new` is filled
+/// in turn by adding, moving or updating a row. Moves aren't minimal: a row
+/// moved to the end shows up as the rows after it each moving up one.
+fn diff_results(old: &[serde_json::Value], new: &[serde_json::Value]) -> Vec<ResultDiff> {
+  let new_ids: HashSet<Uuid> = new.iter().map(row_id).collect();
+  let mut diff = Vec::new();
+
+  // Removing from the end keeps the indexes of the rows before valid
+  let mut kept = Vec::new();
+  for (index, row) in old.iter().enumerate().rev() {
+    let id = row_id(row);
+    if new_ids.contains(&id) {
+      kept.push((id, row));
+    } else {
+      diff.push(ResultDiff::Remove { index, id });
+    }
+  }
+  kept.reverse();
+
+  // After the steps for `new[..index]` the rows are those, followed by the
+  // kept rows not placed yet in their old order. A kept row is therefore at
+  // `index` plus the number of unplaced rows before it.
+  let mut positions: HashMap<Uuid, usize> = HashMap::with_capacity(kept.len());
+  for (pos, (id, _)) in kept.iter().enumerate() {
+    positions.entry(*id).or_insert(pos);
+  }
+  let mut unplaced = Unplaced::new(kept.len());
+  for (index, row) in new.iter().enumerate() {
+    let id = row_id(row);
+    match positions.remove(&id) {
+      Some(pos) => {
+        let from = index + unplaced.before(pos);
+        unplaced.place(pos);
+        if from != index {
+          diff.push(ResultDiff::Move {
+            from,
+            to: index,
+            document: row.clone(),
+          });
+        } else if kept[pos].1 != row {
+          diff.push(ResultDiff::Update {
+            index,
+            document: row.clone(),
+          });
+        }
+      }
+      None => diff.push(ResultDiff::Add {
+        index,
+        document: row.clone(),
+      }),
+    }
+  }
+
+  // Rows sharing an ID (e.g. none) can be left over past the end
+  for pos in (0..kept.len()).rev() {
+    if unplaced.before(pos + 1) > unplaced.before(pos) {
+      diff.push(ResultDiff::Remove {
+        index: new.len() + unplaced.before(pos),
+        id: kept[pos].0,
+      });
+    }
+  }
+  diff
+}
+
+/// Which kept rows are still unplaced, as a Fenwick tree over their old
+/// positions, so counting the unplaced rows before one takes O(log n)
+struct Unplaced {
+  tree: Vec<usize>,
+}
+
+impl Unplaced {
+  /// `len` rows, all unplaced
+  fn new(len: usize) -> Self {
+    let mut tree = vec![0; len + 1];
+    for i in 1..=len {
+      tree[i] += 1;
+      let parent = i + (i & i.wrapping_neg());
+      if parent <= len {
+        tree[parent] += tree[i];
+      }
+    }
+    Self { tree }
+  }
+
+  /// Number of unplaced rows at positions before `pos`
+  fn before(&self, pos: usize) -> usize {
+    let mut count = 0;
+    let mut i = pos;
+    while i > 0 {
+      count += self.tree[i];
+      i &= i - 1;
+    }
+    count
+  }
+
+  fn place(&mut self, pos: usize) {
+    let mut i = pos + 1;
+    while i < self.tree.len() {
+      self.tree[i] -= 1;
+      i += i & i.wrapping_neg();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn row(n: u128, value: i64) -> serde_json::Value {
+    json!({"id": Uuid::from_u128(n), "data": {"value": value}})
+  }
+
+  /// Apply `diff` the way a client would
+  fn apply(mut rows: Vec<serde_json::Value>, diff: &[ResultDiff]) -> Vec<serde_json::Value> {
+    for step in diff {
+      match step {
+        ResultDiff::Add { index, document } => rows.insert(*index, document.clone()),
+        ResultDiff::Remove { index, id } => {
+          assert_eq!(row_id(&rows.remove(*index)), *id);
+        }
+        ResultDiff::Move { from, to, document } => {
+          rows.remove(*from);
+          rows.insert(*to, document.clone());
+        }
+        ResultDiff::Update { index, document } => rows[*index] = document.clone(),
+      }
+    }
+    rows
+  }
+
+  #[test]
+  fn test_diff_results() {
+    let old = vec![row(1, 1), row(2, 2), row(3, 3)];
+    assert!(diff_results(&old, &old).is_empty());
+
+    let cases = [
+      vec![],
+      vec![row(1, 1), row(2, 2), row(3, 3), row(4, 4)],
+      vec![row(4, 4), row(1, 1), row(3, 3)],
+      vec![row(3, 3), row(2, 2), row(1, 1)],
+      vec![row(1, 1), row(2, 20), row(3, 3)],
+      vec![row(2, 20), row(5, 5)],
+      vec![row(5, 5), row(3, 30), row(6, 6), row(1, 1), row(2, 2)],
+    ];
+    for new in cases {
+      let diff = diff_results(&old, &new);
+      assert_eq!(apply(old.clone(), &diff), new);
+    }
+
+    assert_eq!(
+      diff_results(&old, &[row(1, 1), row(3, 3)]),
+      vec![ResultDiff::Remove {
+        index: 1,
+        id: Uuid::from_u128(2)
+      }]
+    );
+    assert_eq!(
+      diff_results(&old, &[row(1, 1), row(2, 20), row(3, 3)]),
+      vec![ResultDiff::Update {
+        index: 1,
+        document: row(2, 20)
+      }]
+    );
+  }
+}
@@ -0,0 +1,132 @@
+//! Webhook configuration
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::filter::matches_filter;
+use crate::types::{Change, ChangeOperation, StructuredFilter};
+
+/// Webhook feature settings (stored as the "webhooks" feature settings)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSettings {
+  /// Endpoints that receive change notifications
+  #[serde(default)]
+  pub endpoints: Vec<WebhookEndpoint>,
+
+  /// Delivery attempts after the first one fails
+  #[serde(default = "default_max_retries")]
+  pub max_retries: u32,
+
+  /// Delay before the first retry, doubled on every attempt
+  #[serde(default = "default_initial_backoff_ms")]
+  pub initial_backoff_ms: u64,
+
+  /// Upper bound for the retry delay
+  #[serde(default = "default_max_backoff_ms")]
+  pub max_backoff_ms: u64,
+
+  /// Per-request timeout
+  #[serde(default = "default_timeout_ms")]
+  pub timeout_ms: u64,
+}
+
+fn default_max_retries() -> u32 {
+  5
+}
+
+fn default_initial_backoff_ms() -> u64 {
+  500
+}
+
+fn default_max_backoff_ms() -> u64 {
+  60_000
+}
+
+fn default_timeout_ms() -> u64 {
+  10_000
+}
+
+impl Default for WebhookSettings {
+  fn default() -> Self {
+    Self {
+      endpoints: Vec::new(),
+      max_retries: default_max_retries(),
+      initial_backoff_ms: default_initial_backoff_ms(),
+      max_backoff_ms: default_max_backoff_ms(),
+      timeout_ms: default_timeout_ms(),
+    }
+  }
+}
+
+impl WebhookSettings {
+  /// Delay before retry number `attempt` (1-based), with exponential growth
+  pub fn backoff(&self, attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(20);
+    let ms = self
+      .initial_backoff_ms
+      .saturating_mul(factor)
+      .min(self.max_backoff_ms);
+    Duration::from_millis(ms)
+  }
+
+  /// Validate endpoint definitions
+  pub fn validate(&self) -> Result<(), String> {
+    for endpoint in &self.endpoints {
+      if !endpoint.url.starts_with("http://") && !endpoint.url.starts_with("https://") {
+        return Err(format!(
+          "Webhook URL must start with http:// or https://: {}",
+          endpoint.url
+        ));
+      }
+    }
+    Ok(())
+  }
+}
+
+/// A single webhook destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+  /// URL that receives POSTed change payloads
+  pub url: String,
+
+  /// Only deliver changes in this project (all projects if unset)
+  #[serde(default)]
+  pub project_id: Option<Uuid>,
+
+  /// Only deliver changes in this collection (all collections if unset)
+  #[serde(default)]
+  pub collection: Option<String>,
+
+  /// Only deliver changes whose document matches this filter
+  #[serde(default)]
+  pub filter: Option<StructuredFilter>,
+
+  /// Secret used to sign payloads with HMAC-SHA256
+  #[serde(default)]
+  pub secret: Option<String>,
+}
+
+impl WebhookEndpoint {
+  /// Check whether a change should be delivered to this endpoint
+  pub fn matches(&self, change: &Change) -> bool {
+    if self.project_id.is_some_and(|p| p != change.project_id) {
+      return false;
+    }
+    if self
+      .collection
+      .as_deref()
+      .is_some_and(|c| c != change.collection)
+    {
+      return false;
+    }
+    let Some(filter) = &self.filter else {
+      return true;
+    };
+    let data = match change.operation {
+      ChangeOperation::Delete => change.old_data.as_ref(),
+      _ => change.new_data.as_ref(),
+    };
+    data.is_some_and(|d| matches_filter(filter, d))
+  }
+}
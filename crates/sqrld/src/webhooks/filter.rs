@@ -0,0 +1,80 @@
+//! In-memory evaluation of structured filters against document data
+
+use serde_json::Value;
+
+use crate::db::EARTH_RADIUS_METERS;
+use crate::types::{FieldCondition, FilterOperator, GeoNear, LogicalFilter, StructuredFilter};
+
+/// Check whether a document matches a structured filter
+pub fn matches_filter(filter: &StructuredFilter, data: &Value) -> bool {
+  match filter {
+    StructuredFilter::Logical(LogicalFilter::And(filters)) => {
+      filters.iter().all(|f| matches_filter(f, data))
+    }
+    StructuredFilter::Logical(LogicalFilter::Or(filters)) => {
+      filters.iter().any(|f| matches_filter(f, data))
+    }
+    StructuredFilter::Logical(LogicalFilter::Not(f)) => !matches_filter(f, data),
+    StructuredFilter::Fields(fields) => fields
+      .iter()
+      .all(|(field, cond)| matches_condition(lookup(data, field), cond)),
+  }
+}
+
+/// Resolve a dotted field path (e.g. "address.city")
+fn lookup<'a>(data: &'a Value, field: &str) -> Option<&'a Value> {
+  field
+    .split('.')
+    .try_fold(data, |value, part| value.get(part))
+}
+
+fn matches_condition(value: Option<&Value>, cond: &FieldCondition) -> bool {
+  let value = value.filter(|v| !v.is_null());
+  match cond {
+    FieldCondition::Value(expected) => equals(value, expected),
+    FieldCondition::Operator(op) => match op {
+      FilterOperator::Eq(expected) => equals(value, expected),
+      FilterOperator::Ne(expected) => !equals(value, expected),
+      FilterOperator::Gt(n) => compare(value, n).is_some_and(|o| o.is_gt()),
+      FilterOperator::Gte(n) => compare(value, n).is_some_and(|o| o.is_ge()),
+      FilterOperator::Lt(n) => compare(value, n).is_some_and(|o| o.is_lt()),
+      FilterOperator::Lte(n) => compare(value, n).is_some_and(|o| o.is_le()),
+      FilterOperator::In(values) => values.iter().any(|v| equals(value, v)),
+      FilterOperator::NotIn(values) => !values.iter().any(|v| equals(value, v)),
+      FilterOperator::Contains(s) => as_str(value).is_some_and(|v| v.contains(s.as_str())),
+      FilterOperator::StartsWith(s) => as_str(value).is_some_and(|v| v.starts_with(s.as_str())),
+      FilterOperator::EndsWith(s) => as_str(value).is_some_and(|v| v.ends_with(s.as_str())),
+      FilterOperator::Exists(exists) => value.is_some() == *exists,
+      FilterOperator::Near(near) => distance(value, near).is_some_and(|d| d <= near.max_distance),
+    },
+  }
+}
+
+fn equals(value: Option<&Value>, expected: &Value) -> bool {
+  match (value, expected) {
+    (None, Value::Null) => true,
+    (Some(Value::Number(a)), Value::Number(b)) => a.as_f64() == b.as_f64(),
+    (Some(v), e) => v == e,
+    (None, _) => false,
+  }
+}
+
+fn compare(value: Option<&Value>, expected: &Value) -> Option<std::cmp::Ordering> {
+  let a = value?.as_f64()?;
+  let b = expected.as_f64()?;
+  a.partial_cmp(&b)
+}
+
+/// Haversine distance in meters from a `{ lat, lng }` value
+fn distance(value: Option<&Value>, near: &GeoNear) -> Option<f64> {
+  let value = value?;
+  let (lat, lng) = (value.get("lat")?.as_f64()?, value.get("lng")?.as_f64()?);
+  let (lat1, lat2) = (near.lat.to_radians(), lat.to_radians());
+  let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+    + lat1.cos() * lat2.cos() * ((lng - near.lng).to_radians() / 2.0).sin().powi(2);
+  Some(2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin())
+}
+
+fn as_str(value: Option<&Value>) -> Option<&str> {
+  value?.as_str()
+}
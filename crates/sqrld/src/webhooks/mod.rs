@@ -0,0 +1,12 @@
+//! Outbound webhook notifications
+//!
+//! Delivers document changes as signed JSON POST requests to external
+//! endpoints, with per-endpoint project, collection and structured filters.
+
+mod config;
+mod filter;
+mod service;
+
+pub use config::{WebhookEndpoint, WebhookSettings};
+pub use filter::matches_filter;
+pub use service::{sign_payload, WebhookFeature, WebhookPayload, SIGNATURE_HEADER};
@@ -0,0 +1,282 @@
+//! Webhook delivery service
+//!
+//! Subscribes to the change stream and POSTs matching changes to the
+//! configured endpoints, retrying failed deliveries with exponential backoff.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock, Semaphore};
+use uuid::Uuid;
+
+use super::config::{WebhookEndpoint, WebhookSettings};
+use crate::features::{AppState, Feature};
+use crate::types::Change;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Maximum number of deliveries in flight at once
+const MAX_IN_FLIGHT: usize = 64;
+
+/// Header carrying the payload signature
+pub const SIGNATURE_HEADER: &str = "X-Squirrel-Signature";
+
+/// Body POSTed to webhook endpoints
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+  /// Unique delivery ID (stable across retries)
+  pub id: Uuid,
+  pub sent_at: DateTime<Utc>,
+  pub change: &'a Change,
+}
+
+/// Sign a payload with HMAC-SHA256, returning the header value ("sha256=<hex>")
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+  let mut mac =
+    HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+  mac.update(body);
+  format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Delivery counters shared with the delivery tasks
+#[derive(Default)]
+struct DeliveryStats {
+  delivered: AtomicU64,
+  failed: AtomicU64,
+}
+
+/// Webhook feature for outbound change notifications
+pub struct WebhookFeature {
+  running: AtomicBool,
+  shutdown_tx: RwLock<Option<mpsc::Sender<()>>>,
+  stats: Arc<DeliveryStats>,
+}
+
+impl Default for WebhookFeature {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl WebhookFeature {
+  pub fn new() -> Self {
+    Self {
+      running: AtomicBool::new(false),
+      shutdown_tx: RwLock::new(None),
+      stats: Arc::new(DeliveryStats::default()),
+    }
+  }
+
+  /// Number of successfully delivered notifications
+  pub fn delivered(&self) -> u64 {
+    self.stats.delivered.load(Ordering::Relaxed)
+  }
+
+  /// Number of notifications dropped after exhausting retries
+  pub fn failed(&self) -> u64 {
+    self.stats.failed.load(Ordering::Relaxed)
+  }
+
+  /// Load webhook settings from the database
+  async fn load_settings(state: &AppState) -> Result<WebhookSettings, anyhow::Error> {
+    let settings = match state.backend.get_feature_settings("webhooks").await? {
+      Some((_, value)) if !value.is_null() => serde_json::from_value(value)
+        .map_err(|e| anyhow::anyhow!("Invalid webhook settings: {}", e))?,
+      _ => WebhookSettings::default(),
+    };
+    settings.validate().map_err(|e| anyhow::anyhow!(e))?;
+    Ok(settings)
+  }
+}
+
+/// Deliver a change to an endpoint, retrying with exponential backoff
+async fn deliver(
+  client: reqwest::Client,
+  endpoint: WebhookEndpoint,
+  settings: Arc<WebhookSettings>,
+  change: Arc<Change>,
+  stats: Arc<DeliveryStats>,
+) {
+  let payload = WebhookPayload {
+    id: Uuid::new_v4(),
+    sent_at: Utc::now(),
+    change: &change,
+  };
+  let body = match serde_json::to_vec(&payload) {
+    Ok(b) => b,
+    Err(e) => {
+      tracing::error!("Failed to serialize webhook payload: {}", e);
+      stats.failed.fetch_add(1, Ordering::Relaxed);
+      return;
+    }
+  };
+  let signature = endpoint.secret.as_deref().map(|s| sign_payload(s, &body));
+  let event = serde_json::to_value(change.operation)
+    .ok()
+    .and_then(|v| v.as_str().map(str::to_string))
+    .unwrap_or_default();
+
+  let mut attempt = 0;
+  loop {
+    let mut request = client
+      .post(&endpoint.url)
+      .timeout(Duration::from_millis(settings.timeout_ms))
+      .header(reqwest::header::CONTENT_TYPE, "application/json")
+      .header("X-Squirrel-Event", &event)
+      .header("X-Squirrel-Delivery", payload.id.to_string())
+      .body(body.clone());
+    if let Some(ref sig) = signature {
+      request = request.header(SIGNATURE_HEADER, sig);
+    }
+
+    let (retryable, reason) = match request.send().await {
+      Ok(resp) if resp.status().is_success() => {
+        stats.delivered.fetch_add(1, Ordering::Relaxed);
+        return;
+      }
+      Ok(resp) => {
+        let status = resp.status();
+        let retryable = status.is_server_error()
+          || status == reqwest::StatusCode::REQUEST_TIMEOUT
+          || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+        (retryable, format!("HTTP {}", status))
+      }
+      Err(e) => (true, e.to_string()),
+    };
+
+    attempt += 1;
+    if !retryable || attempt > settings.max_retries {
+      tracing::warn!(
+        "Webhook delivery {} to {} failed after {} attempt(s): {}",
+        payload.id,
+        endpoint.url,
+        attempt,
+        reason
+      );
+      stats.failed.fetch_add(1, Ordering::Relaxed);
+      return;
+    }
+
+    let delay = settings.backoff(attempt);
+    tracing::debug!(
+      "Webhook delivery {} to {} failed ({}), retrying in {:?}",
+      payload.id,
+      endpoint.url,
+      reason,
+      delay
+    );
+    tokio::time::sleep(delay).await;
+  }
+}
+
+#[async_trait]
+impl Feature for WebhookFeature {
+  fn name(&self) -> &str {
+    "webhooks"
+  }
+
+  fn description(&self) -> &str {
+    "Outbound webhook notifications for document changes"
+  }
+
+  async fn start(&self, state: Arc<AppState>) -> Result<(), anyhow::Error> {
+    if self.running.load(Ordering::SeqCst) {
+      return Ok(());
+    }
+
+    let settings = Arc::new(Self::load_settings(&state).await?);
+    if settings.endpoints.is_empty() {
+      tracing::warn!("Webhook feature started without any endpoints configured");
+    }
+
+    let client = reqwest::Client::builder()
+      .user_agent(concat!("SquirrelDB-Webhooks/", env!("CARGO_PKG_VERSION")))
+      .build()?;
+
+    self.running.store(true, Ordering::SeqCst);
+
+    let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+    {
+      let mut guard = self.shutdown_tx.write().await;
+      *guard = Some(shutdown_tx);
+    }
+
+    let mut changes = state.backend.subscribe_changes();
+    let stats = self.stats.clone();
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+
+    tokio::spawn(async move {
+      tracing::info!(
+        "Webhook service started ({} endpoint(s))",
+        settings.endpoints.len()
+      );
+
+      loop {
+        tokio::select! {
+          result = changes.recv() => {
+            let change = match result {
+              Ok(change) => Arc::new(change),
+              Err(broadcast::error::RecvError::Lagged(n)) => {
+                tracing::warn!("Webhook service lagged, {} change(s) not delivered", n);
+                continue;
+              }
+              Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            for endpoint in settings.endpoints.iter().filter(|e| e.matches(&change)) {
+              let Ok(permit) = in_flight.clone().acquire_owned().await else {
+                break;
+              };
+              let client = client.clone();
+              let endpoint = endpoint.clone();
+              let settings = settings.clone();
+              let change = change.clone();
+              let stats = stats.clone();
+              tokio::spawn(async move {
+                deliver(client, endpoint, settings, change, stats).await;
+                drop(permit);
+              });
+            }
+          }
+          _ = shutdown_rx.recv() => {
+            tracing::info!("Webhook service shutting down");
+            break;
+          }
+        }
+      }
+    });
+
+    tracing::info!("Webhook feature started");
+    Ok(())
+  }
+
+  async fn stop(&self) -> Result<(), anyhow::Error> {
+    self.running.store(false, Ordering::SeqCst);
+
+    // Take the sender out of the lock before awaiting
+    let tx = {
+      let mut guard = self.shutdown_tx.write().await;
+      guard.take()
+    };
+
+    if let Some(tx) = tx {
+      let _ = tx.send(()).await;
+    }
+
+    tracing::info!("Webhook feature stopped");
+    Ok(())
+  }
+
+  fn is_running(&self) -> bool {
+    self.running.load(Ordering::SeqCst)
+  }
+
+  fn as_any(&self) -> &dyn std::any::Any {
+    self
+  }
+}
@@ -0,0 +1,765 @@
+//! Backup tests - schedules, stored settings, restore, incremental backups, compression and encryption
+
+use chrono::{TimeZone, Timelike, Utc};
+use serde_json::json;
+use squirreldb::backup::{
+  apply_settings, backup_extension, backup_id_from_filename, database_is_empty, decode_backup,
+  encode_backup, export_archive, import_archive, migrate_backend, parse_backup_header,
+  parse_backup_sql, parse_cron, parse_encryption_key, restore_backup_chain, restore_backup_sql,
+  seed_database, BackupChain, BackupFeature, BackupKind, BackupSchedule,
+};
+use squirreldb::db::{DatabaseBackend, SoftDeleteSettings, SqliteBackend};
+use squirreldb::server::{BackupCompression, BackupSection, ServerConfig};
+use std::sync::Arc;
+use types::DEFAULT_PROJECT_ID;
+use uuid::Uuid;
+
+async fn sqlite_backend() -> Arc<dyn DatabaseBackend> {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  Arc::new(backend)
+}
+
+// =============================================================================
+// Schedule Tests
+// =============================================================================
+
+#[test]
+fn test_interval_schedule() {
+  let section = BackupSection {
+    interval: 600,
+    ..Default::default()
+  };
+  let schedule = BackupSchedule::from_section(&section).unwrap();
+  let now = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+  assert_eq!(
+    schedule.next_after(now),
+    Some(Utc.with_ymd_and_hms(2024, 1, 15, 12, 10, 0).unwrap())
+  );
+}
+
+#[test]
+fn test_cron_schedule_overrides_interval() {
+  let section = BackupSection {
+    interval: 60,
+    schedule: Some("0 2 * * *".to_string()),
+    ..Default::default()
+  };
+  let schedule = BackupSchedule::from_section(&section).unwrap();
+  assert!(matches!(schedule, BackupSchedule::Cron(_)));
+
+  // Daily at 02:00 UTC
+  let now = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+  let next = schedule.next_after(now).unwrap();
+  assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 16, 2, 0, 0).unwrap());
+}
+
+#[test]
+fn test_empty_cron_falls_back_to_interval() {
+  let section = BackupSection {
+    schedule: Some("  ".to_string()),
+    ..Default::default()
+  };
+  let schedule = BackupSchedule::from_section(&section).unwrap();
+  assert!(matches!(schedule, BackupSchedule::Interval(3600)));
+}
+
+#[test]
+fn test_parse_cron_accepts_five_and_six_fields() {
+  assert!(parse_cron("*/15 * * * *").is_ok());
+  assert!(parse_cron("0 30 2 * * Sun").is_ok());
+  let schedule = parse_cron("30 3 * * *").unwrap();
+  let next = schedule.upcoming(Utc).next().unwrap();
+  assert_eq!((next.hour(), next.minute(), next.second()), (3, 30, 0));
+}
+
+#[test]
+fn test_parse_cron_rejects_invalid() {
+  assert!(parse_cron("every day").is_err());
+  assert!(parse_cron("61 * * * *").is_err());
+}
+
+#[test]
+fn test_invalid_cron_schedule_errors() {
+  let section = BackupSection {
+    schedule: Some("not a cron".to_string()),
+    ..Default::default()
+  };
+  assert!(BackupSchedule::from_section(&section).is_err());
+}
+
+// =============================================================================
+// Stored Settings Tests
+// =============================================================================
+
+#[test]
+fn test_apply_settings_overrides_config() {
+  let mut section = BackupSection::default();
+  apply_settings(
+    &mut section,
+    &json!({"interval": 120, "retention": 3, "schedule": "0 4 * * *"}),
+  );
+  assert_eq!(section.interval, 120);
+  assert_eq!(section.retention, 3);
+  assert_eq!(section.schedule.as_deref(), Some("0 4 * * *"));
+}
+
+#[test]
+fn test_apply_settings_storage_location() {
+  let mut section = BackupSection::default();
+  assert_eq!(section.storage_bucket, "backups");
+  assert_eq!(section.storage_path, "backups");
+
+  apply_settings(
+    &mut section,
+    &json!({"storage_bucket": "offsite", "storage_path": "prod/db"}),
+  );
+  assert_eq!(section.storage_bucket, "offsite");
+  assert_eq!(section.storage_path, "prod/db");
+}
+
+#[test]
+fn test_apply_settings_null_schedule_clears_cron() {
+  let mut section = BackupSection {
+    schedule: Some("0 4 * * *".to_string()),
+    ..Default::default()
+  };
+  apply_settings(&mut section, &json!({"schedule": null}));
+  assert!(section.schedule.is_none());
+
+  // Missing key leaves the schedule untouched
+  section.schedule = Some("0 4 * * *".to_string());
+  apply_settings(&mut section, &json!({"retention": 5}));
+  assert_eq!(section.schedule.as_deref(), Some("0 4 * * *"));
+}
+
+// =============================================================================
+// Restore Tests
+// =============================================================================
+
+const BACKUP_SQL: &str = "-- SquirrelDB Backup
+-- Created: 2024-01-15T12:00:00+00:00
+-- Backend: Sqlite
+-- 
+
+-- Projects
+-- Project: default (00000000-0000-0000-0000-000000000000)
+
+-- Collection: default.users
+INSERT INTO users (id, data, created_at, updated_at) VALUES ('6f0c2a51-51c4-4c55-9a53-0f1d7f1f2c1a', '{\"name\":\"O''Brien\"}', '2024-01-15T10:00:00+00:00', '2024-01-15T11:00:00+00:00');
+-- Project: other (a2d1f6a4-0d2b-4a56-8f0a-6c1c8b8e2f11)
+
+-- Collection: other.orders
+INSERT INTO orders (id, data, created_at, updated_at) VALUES ('0b9e4bd0-5a0e-4d3c-9a6c-3a3c9c8f7e21', '{\"total\":42}', '2024-01-15T10:30:00+00:00', '2024-01-15T10:30:00+00:00');
+";
+
+#[test]
+fn test_parse_backup_sql() {
+  let docs = parse_backup_sql(BACKUP_SQL).unwrap();
+  assert_eq!(docs.len(), 2);
+
+  assert_eq!(docs[0].project_id, DEFAULT_PROJECT_ID);
+  assert_eq!(docs[0].collection, "users");
+  assert_eq!(docs[0].data, json!({"name": "O'Brien"}));
+  assert_eq!(
+    docs[0].updated_at,
+    Utc.with_ymd_and_hms(2024, 1, 15, 11, 0, 0).unwrap()
+  );
+
+  assert_eq!(
+    docs[1].project_id,
+    "a2d1f6a4-0d2b-4a56-8f0a-6c1c8b8e2f11"
+      .parse::<Uuid>()
+      .unwrap()
+  );
+  assert_eq!(docs[1].collection, "orders");
+}
+
+#[test]
+fn test_parse_backup_sql_rejects_malformed_insert() {
+  let sql = "INSERT INTO users (id, data, created_at, updated_at) VALUES ('abc', '{}');";
+  assert!(parse_backup_sql(sql).is_err());
+}
+
+#[tokio::test]
+async fn test_restore_into_empty_database() {
+  let backend = sqlite_backend().await;
+  assert!(database_is_empty(&backend).await.unwrap());
+
+  let summary = restore_backup_sql(&backend, BACKUP_SQL, false)
+    .await
+    .unwrap();
+  assert_eq!(summary.documents, 2);
+  assert_eq!(summary.collections, 2);
+  assert_eq!(summary.projects, 2);
+
+  let id: Uuid = "6f0c2a51-51c4-4c55-9a53-0f1d7f1f2c1a".parse().unwrap();
+  let doc = backend
+    .get(DEFAULT_PROJECT_ID, "users", id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(doc.data["name"], "O'Brien");
+  assert_eq!(
+    doc.created_at,
+    Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap()
+  );
+}
+
+#[tokio::test]
+async fn test_restore_refuses_non_empty_database_without_force() {
+  let backend = sqlite_backend().await;
+  backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Existing"}))
+    .await
+    .unwrap();
+
+  assert!(restore_backup_sql(&backend, BACKUP_SQL, false)
+    .await
+    .is_err());
+  // Nothing was touched
+  assert_eq!(
+    backend
+      .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
+      .await
+      .unwrap()
+      .len(),
+    1
+  );
+}
+
+#[tokio::test]
+async fn test_restore_with_force_replaces_documents() {
+  let backend = sqlite_backend().await;
+  backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Existing"}))
+    .await
+    .unwrap();
+
+  restore_backup_sql(&backend, BACKUP_SQL, true)
+    .await
+    .unwrap();
+
+  let users = backend
+    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
+    .await
+    .unwrap();
+  assert_eq!(users.len(), 1);
+  assert_eq!(users[0].data["name"], "O'Brien");
+}
+
+#[tokio::test]
+async fn test_backup_round_trip() {
+  let dir = tempfile::tempdir().unwrap();
+  let mut config = ServerConfig::default();
+  config.backup.local_path = dir.path().to_string_lossy().to_string();
+
+  let source = sqlite_backend().await;
+  source
+    .insert(DEFAULT_PROJECT_ID, "notes", json!({"text": "it's a test"}))
+    .await
+    .unwrap();
+
+  let feature = BackupFeature::new();
+  let info = feature.create_backup(&source, &config).await.unwrap();
+  assert!(info.filename.contains(&info.id));
+
+  // Only the exact ID names the backup, not a prefix of it
+  let target = sqlite_backend().await;
+  assert!(feature
+    .restore_backup(&target, &config, &info.id[..8], false)
+    .await
+    .unwrap()
+    .is_none());
+
+  let summary = feature
+    .restore_backup(&target, &config, &info.id, false)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(summary.documents, 1);
+
+  let notes = target
+    .list(DEFAULT_PROJECT_ID, "notes", None, None, None, None, None)
+    .await
+    .unwrap();
+  assert_eq!(notes[0].data["text"], "it's a test");
+
+  assert!(feature
+    .restore_backup(&target, &config, "missing0", true)
+    .await
+    .unwrap()
+    .is_none());
+}
+
+#[tokio::test]
+async fn test_retention_and_delete_local_backups() {
+  let dir = tempfile::tempdir().unwrap();
+  let mut config = ServerConfig::default();
+  config.backup.local_path = dir.path().to_string_lossy().to_string();
+  config.backup.retention = 2;
+
+  let backend = sqlite_backend().await;
+  let feature = BackupFeature::new();
+  let mut created = Vec::new();
+  for _ in 0..3 {
+    created.push(feature.create_backup(&backend, &config).await.unwrap());
+  }
+
+  let listed = feature.list_backups(&backend, &config).await.unwrap();
+  assert_eq!(listed.len(), 2);
+  assert!(listed.iter().all(|b| b.backend == "local"));
+  assert!(!listed.iter().any(|b| b.id == created[0].id));
+
+  assert!(feature
+    .delete_backup(&backend, &config, &created[2].id)
+    .await
+    .unwrap());
+  assert!(!feature
+    .delete_backup(&backend, &config, &created[2].id)
+    .await
+    .unwrap());
+  assert_eq!(
+    feature.list_backups(&backend, &config).await.unwrap().len(),
+    1
+  );
+}
+
+// =============================================================================
+// Incremental Backup Tests
+// =============================================================================
+
+#[test]
+fn test_parse_backup_header() {
+  let header = parse_backup_header(
+    "-- SquirrelDB Incremental Backup\n-- Since-Change-Id: 10\n-- Change-Id: 25\n-- \n\nDELETE FROM users WHERE id = '6f0c2a51-51c4-4c55-9a53-0f1d7f1f2c1a';\n",
+  );
+  assert_eq!(header.kind, BackupKind::Incremental);
+  assert_eq!(header.since_change_id, Some(10));
+  assert_eq!(header.change_id, Some(25));
+
+  // Backups from before incremental support are full backups
+  let header = parse_backup_header(BACKUP_SQL);
+  assert_eq!(header.kind, BackupKind::Full);
+  assert_eq!(header.change_id, None);
+}
+
+#[test]
+fn test_backup_chain_next_kind() {
+  let section = BackupSection {
+    incremental: true,
+    full_every: 3,
+    ..Default::default()
+  };
+  let mut chain = BackupChain::default();
+  assert_eq!(chain.next_kind(&section, Some((1, 10))), BackupKind::Full);
+
+  chain.record(BackupKind::Full, 10);
+  assert_eq!(
+    chain.next_kind(&section, Some((1, 20))),
+    BackupKind::Incremental
+  );
+  // Change queue pruned past the last backed-up change
+  assert_eq!(chain.next_kind(&section, Some((15, 20))), BackupKind::Full);
+
+  chain.record(BackupKind::Incremental, 20);
+  assert_eq!(
+    chain.next_kind(&section, Some((1, 30))),
+    BackupKind::Incremental
+  );
+  chain.record(BackupKind::Incremental, 30);
+  // Third backup in the chain: a new baseline is due
+  assert_eq!(chain.next_kind(&section, Some((1, 40))), BackupKind::Full);
+
+  let full_only = BackupSection::default();
+  assert_eq!(chain.next_kind(&full_only, Some((1, 40))), BackupKind::Full);
+}
+
+#[tokio::test]
+async fn test_restore_chain_rejects_gap() {
+  let backend = sqlite_backend().await;
+  let full = "-- SquirrelDB Backup\n-- Change-Id: 10\n-- \n\n";
+  let incremental =
+    "-- SquirrelDB Incremental Backup\n-- Since-Change-Id: 12\n-- Change-Id: 20\n-- \n\n";
+  assert!(restore_backup_chain(&backend, &[full, incremental], false)
+    .await
+    .is_err());
+  assert!(restore_backup_chain(&backend, &[incremental], false)
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_incremental_backup_round_trip() {
+  let dir = tempfile::tempdir().unwrap();
+  let mut config = ServerConfig::default();
+  config.backup.local_path = dir.path().to_string_lossy().to_string();
+  config.backup.incremental = true;
+
+  let source = sqlite_backend().await;
+  let kept = source
+    .insert(DEFAULT_PROJECT_ID, "notes", json!({"n": 1}))
+    .await
+    .unwrap();
+  let removed = source
+    .insert(DEFAULT_PROJECT_ID, "notes", json!({"n": 2}))
+    .await
+    .unwrap();
+
+  let feature = BackupFeature::new();
+  let full = feature.create_backup(&source, &config).await.unwrap();
+  assert_eq!(full.kind, "full");
+
+  source
+    .update(DEFAULT_PROJECT_ID, "notes", kept.id, json!({"n": 10}))
+    .await
+    .unwrap();
+  source
+    .delete(DEFAULT_PROJECT_ID, "notes", removed.id)
+    .await
+    .unwrap();
+  let added = source
+    .insert(DEFAULT_PROJECT_ID, "notes", json!({"n": 3}))
+    .await
+    .unwrap();
+
+  let incremental = feature.create_backup(&source, &config).await.unwrap();
+  assert_eq!(incremental.kind, "incremental");
+
+  let target = sqlite_backend().await;
+  let summary = feature
+    .restore_backup(&target, &config, &incremental.id, false)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(summary.backups, 2);
+  assert_eq!(summary.documents, 2);
+  assert_eq!(summary.deleted, 1);
+
+  let doc = target
+    .get(DEFAULT_PROJECT_ID, "notes", kept.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(doc.data["n"], 10);
+  assert!(target
+    .get(DEFAULT_PROJECT_ID, "notes", added.id)
+    .await
+    .unwrap()
+    .is_some());
+  assert!(target
+    .get(DEFAULT_PROJECT_ID, "notes", removed.id)
+    .await
+    .unwrap()
+    .is_none());
+}
+
+// =============================================================================
+// Compression and Encryption Tests
+// =============================================================================
+
+const TEST_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+#[test]
+fn test_encode_decode_round_trip() {
+  let key = parse_encryption_key(TEST_KEY).unwrap();
+  for compression in [
+    BackupCompression::None,
+    BackupCompression::Gzip,
+    BackupCompression::Zstd,
+  ] {
+    for key in [None, Some(&key)] {
+      let encoded = encode_backup(BACKUP_SQL, compression, key).unwrap();
+      assert_eq!(decode_backup(&encoded, key).unwrap(), BACKUP_SQL);
+    }
+  }
+}
+
+#[test]
+fn test_compression_shrinks_backup() {
+  let sql = BACKUP_SQL.repeat(50);
+  let gzip = encode_backup(&sql, BackupCompression::Gzip, None).unwrap();
+  let zstd = encode_backup(&sql, BackupCompression::Zstd, None).unwrap();
+  assert!(gzip.len() < sql.len() / 4);
+  assert!(zstd.len() < sql.len() / 4);
+}
+
+#[test]
+fn test_encrypted_backup_requires_correct_key() {
+  let key = parse_encryption_key(TEST_KEY).unwrap();
+  let encoded = encode_backup(BACKUP_SQL, BackupCompression::None, Some(&key)).unwrap();
+  assert!(!encoded.windows(6).any(|w| w == b"INSERT"));
+
+  assert!(decode_backup(&encoded, None).is_err());
+  let wrong = parse_encryption_key(&"ff".repeat(32)).unwrap();
+  assert!(decode_backup(&encoded, Some(&wrong)).is_err());
+}
+
+#[test]
+fn test_parse_encryption_key_validates_input() {
+  assert!(parse_encryption_key("abcd").is_err());
+  assert!(parse_encryption_key("not hex").is_err());
+}
+
+#[test]
+fn test_backup_filenames() {
+  assert_eq!(backup_extension(BackupCompression::None, false), ".sql");
+  assert_eq!(
+    backup_extension(BackupCompression::Gzip, true),
+    ".sql.gz.enc"
+  );
+  assert_eq!(
+    backup_id_from_filename("squirreldb_backup_20240115_143022_a1b2c3d4.sql"),
+    "a1b2c3d4"
+  );
+  assert_eq!(
+    backup_id_from_filename("squirreldb_incremental_20240115_143022_a1b2c3d4.sql.zst.enc"),
+    "a1b2c3d4"
+  );
+}
+
+#[tokio::test]
+async fn test_compressed_encrypted_backup_round_trip() {
+  let dir = tempfile::tempdir().unwrap();
+  let mut config = ServerConfig::default();
+  config.backup.local_path = dir.path().to_string_lossy().to_string();
+  config.backup.compression = BackupCompression::Zstd;
+  config.backup.encryption_key = Some(TEST_KEY.to_string());
+
+  let source = sqlite_backend().await;
+  source
+    .insert(DEFAULT_PROJECT_ID, "secrets", json!({"value": "hunter2"}))
+    .await
+    .unwrap();
+
+  let feature = BackupFeature::new();
+  let info = feature.create_backup(&source, &config).await.unwrap();
+  assert!(info.filename.ends_with(".sql.zst.enc"));
+  assert_eq!(info.compression, "zstd");
+  assert!(info.encrypted);
+
+  let listed = feature.list_backups(&source, &config).await.unwrap();
+  assert_eq!(listed.len(), 1);
+  assert_eq!(listed[0].compression, "zstd");
+  assert!(listed[0].encrypted);
+
+  let target = sqlite_backend().await;
+  let summary = feature
+    .restore_backup(&target, &config, &info.id, false)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(summary.documents, 1);
+
+  // Without the key the backup can't be read
+  config.backup.encryption_key = None;
+  assert!(feature
+    .restore_backup(&target, &config, &info.id, true)
+    .await
+    .is_err());
+}
+
+// =============================================================================
+// Archive Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_archive_round_trip() {
+  let source = sqlite_backend().await;
+  let note = source
+    .insert(DEFAULT_PROJECT_ID, "notes", json!({"text": "it's a test"}))
+    .await
+    .unwrap();
+  source
+    .set_soft_delete(DEFAULT_PROJECT_ID, "trash", SoftDeleteSettings::default())
+    .await
+    .unwrap();
+  let trashed = source
+    .insert(DEFAULT_PROJECT_ID, "trash", json!({"gone": true}))
+    .await
+    .unwrap();
+  source
+    .delete(DEFAULT_PROJECT_ID, "trash", trashed.id)
+    .await
+    .unwrap();
+  source
+    .create_token(DEFAULT_PROJECT_ID, "ci", "abc123")
+    .await
+    .unwrap();
+
+  let mut archive = Vec::new();
+  let exported = export_archive(&source, &mut archive, true).await.unwrap();
+  assert_eq!(exported.documents, 2);
+  assert_eq!(exported.collections, 2);
+  assert_eq!(exported.tokens, 1);
+
+  let target = sqlite_backend().await;
+  let imported = import_archive(&target, archive.as_slice(), false)
+    .await
+    .unwrap();
+  assert_eq!(imported.documents, 2);
+  assert_eq!(imported.tokens, 1);
+
+  let copy = target
+    .get(DEFAULT_PROJECT_ID, "notes", note.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(copy.data["text"], "it's a test");
+  assert_eq!(copy.created_at, note.created_at);
+  // Soft-deleted documents stay deleted
+  assert!(target
+    .get(DEFAULT_PROJECT_ID, "trash", trashed.id)
+    .await
+    .unwrap()
+    .is_none());
+  assert!(target
+    .get_soft_delete(DEFAULT_PROJECT_ID, "trash")
+    .await
+    .unwrap()
+    .is_some());
+  assert_eq!(
+    target.validate_token("abc123").await.unwrap(),
+    Some(DEFAULT_PROJECT_ID)
+  );
+
+  // Importing again needs force
+  assert!(import_archive(&target, archive.as_slice(), false)
+    .await
+    .is_err());
+  import_archive(&target, archive.as_slice(), true)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_archive_without_auth() {
+  let source = sqlite_backend().await;
+  source
+    .create_token(DEFAULT_PROJECT_ID, "ci", "abc123")
+    .await
+    .unwrap();
+
+  let mut archive = Vec::new();
+  let exported = export_archive(&source, &mut archive, false).await.unwrap();
+  assert_eq!(exported.tokens, 0);
+
+  let target = sqlite_backend().await;
+  import_archive(&target, archive.as_slice(), false)
+    .await
+    .unwrap();
+  assert!(target
+    .list_tokens(DEFAULT_PROJECT_ID)
+    .await
+    .unwrap()
+    .is_empty());
+}
+
+#[tokio::test]
+async fn test_import_rejects_other_files() {
+  let target = sqlite_backend().await;
+  let not_an_archive = zstd::encode_all(&b"-- SquirrelDB Backup\n"[..], 3).unwrap();
+  assert!(import_archive(&target, not_an_archive.as_slice(), false)
+    .await
+    .is_err());
+  assert!(import_archive(&target, &b"plain text"[..], false)
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_migrate_backend() {
+  let source = sqlite_backend().await;
+  for n in 0..3 {
+    source
+      .insert(DEFAULT_PROJECT_ID, "items", json!({ "n": n }))
+      .await
+      .unwrap();
+  }
+  source
+    .create_token(DEFAULT_PROJECT_ID, "ci", "abc123")
+    .await
+    .unwrap();
+
+  let target = sqlite_backend().await;
+  let summary = migrate_backend(&source, &target).await.unwrap();
+  assert_eq!(summary.documents, 3);
+  assert_eq!(summary.tokens, 1);
+  assert_eq!(
+    target
+      .count_project_documents(DEFAULT_PROJECT_ID)
+      .await
+      .unwrap(),
+    3
+  );
+
+  // The target must be empty
+  assert!(migrate_backend(&source, &target).await.is_err());
+}
+
+#[tokio::test]
+async fn test_seed_database() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("seed.ndjson");
+  let id = Uuid::new_v4();
+  std::fs::write(
+    &path,
+    format!(
+      "{}\n\n{}\n{}\n",
+      json!({"collection": "users", "data": {"name": "Alice"}}),
+      json!({"collection": "users", "id": id, "data": {"name": "Bob"}}),
+      json!({"collection": "posts", "data": {"title": "Hello"}}),
+    ),
+  )
+  .unwrap();
+
+  let backend = sqlite_backend().await;
+  let summary = seed_database(&backend, &path).await.unwrap().unwrap();
+  assert_eq!(summary.documents, 3);
+  assert_eq!(summary.collections, 2);
+  let bob = backend
+    .get(DEFAULT_PROJECT_ID, "users", id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(bob.data["name"], "Bob");
+
+  // A database with data is left alone
+  assert!(seed_database(&backend, &path).await.unwrap().is_none());
+  assert_eq!(
+    backend
+      .count_project_documents(DEFAULT_PROJECT_ID)
+      .await
+      .unwrap(),
+    3
+  );
+
+  // A malformed line fails before anything is inserted
+  std::fs::write(
+    &path,
+    "{\"collection\": \"users\", \"data\": {}}\nnot json\n",
+  )
+  .unwrap();
+  let empty = sqlite_backend().await;
+  let err = seed_database(&empty, &path).await.unwrap_err();
+  assert!(err.to_string().contains("line 2"));
+  assert!(database_is_empty(&empty).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_seed_database_from_archive() {
+  let source = sqlite_backend().await;
+  source
+    .insert(DEFAULT_PROJECT_ID, "items", json!({"n": 1}))
+    .await
+    .unwrap();
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("seed.sqrl");
+  export_archive(&source, std::fs::File::create(&path).unwrap(), false)
+    .await
+    .unwrap();
+
+  let target = sqlite_backend().await;
+  let summary = seed_database(&target, &path).await.unwrap().unwrap();
+  assert_eq!(summary.documents, 1);
+}
@@ -1,6 +1,6 @@
 //! Cache module tests
 
-use squirreldb::cache::config::parse_memory_size;
+use squirreldb::cache::config::{parse_memory_size, CacheDebugConfig};
 use squirreldb::cache::resp::{extract_command, parse_resp, RespParser};
 use squirreldb::cache::{
   CacheChange, CacheChangeOperation, CacheConfig, CacheEntry, CacheStore, CacheValue,
@@ -20,6 +20,25 @@ fn test_cache_config_defaults() {
   assert_eq!(config.eviction, EvictionPolicy::Lru);
   assert_eq!(config.default_ttl, 0);
   assert!(!config.snapshot.enabled);
+  assert_eq!(config.debug, CacheDebugConfig::default());
+  assert!(!config.debug.commands);
+}
+
+#[test]
+fn test_cache_debug_latency_injection() {
+  let mut debug = CacheDebugConfig::default();
+  assert_eq!(debug.injected_latency(), None);
+
+  debug.latency_ms = 250;
+  assert_eq!(debug.injected_latency(), None);
+
+  debug.latency_percent = 100.0;
+  for _ in 0..100 {
+    assert_eq!(debug.injected_latency(), Some(Duration::from_millis(250)));
+  }
+
+  debug.latency_ms = 0;
+  assert_eq!(debug.injected_latency(), None);
 }
 
 #[test]
@@ -154,6 +173,28 @@ fn test_resp_parser_incremental() {
   assert_eq!(result, RespValue::SimpleString("OK".to_string()));
 }
 
+#[test]
+fn test_resp_parser_pipelined() {
+  let mut parser = RespParser::new();
+
+  // Two complete commands and the start of a third in one read
+  parser.feed(b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\nPING\r\n*2\r\n$3\r\nGET");
+  let (cmd, args) = extract_command(&parser.parse().unwrap().unwrap()).unwrap();
+  assert_eq!(cmd, "SET");
+  assert_eq!(args, vec!["a", "1"]);
+  let (cmd, _) = extract_command(&parser.parse().unwrap().unwrap()).unwrap();
+  assert_eq!(cmd, "PING");
+  assert!(parser.parse().unwrap().is_none());
+  assert!(parser.has_pending());
+
+  parser.feed(b"\r\n$1\r\na\r\n");
+  let (cmd, args) = extract_command(&parser.parse().unwrap().unwrap()).unwrap();
+  assert_eq!(cmd, "GET");
+  assert_eq!(args, vec!["a"]);
+  assert!(parser.parse().unwrap().is_none());
+  assert!(!parser.has_pending());
+}
+
 // =============================================================================
 // CacheValue Tests
 // =============================================================================
@@ -290,6 +331,57 @@ async fn test_store_memory_tracking() {
   assert_eq!(stats.keys, 1);
 }
 
+#[tokio::test]
+async fn test_store_peek_introspection() {
+  let store = InMemoryCacheStore::new(1024 * 1024, EvictionPolicy::Lru, None);
+
+  store
+    .set("name", CacheValue::from("squirrel"), None)
+    .await
+    .unwrap();
+  store.set("n", CacheValue::from("42"), None).await.unwrap();
+  store
+    .set("doc", CacheValue::from(r#"{"a":[1,2]}"#), None)
+    .await
+    .unwrap();
+
+  let entry = store.peek("name").unwrap();
+  assert_eq!(entry.value.encoding(), "string");
+  assert_eq!(entry.size, "name".len() + "squirrel".len());
+  assert_eq!(store.peek("n").unwrap().value.encoding(), "int");
+  assert_eq!(store.peek("doc").unwrap().value.encoding(), "json");
+  assert!(store.peek("missing").is_none());
+
+  // Peeking is not an access
+  assert_eq!(entry.access_count, 0);
+  let stats = store.info().await;
+  assert_eq!(stats.hits, 0);
+  assert_eq!(stats.misses, 0);
+
+  // Sizes of all entries add up to the memory in use
+  let total: usize = ["name", "n", "doc"]
+    .iter()
+    .map(|k| store.peek(k).unwrap().size)
+    .sum();
+  assert_eq!(stats.memory_used, total);
+}
+
+#[tokio::test]
+async fn test_store_incr_keeps_size_in_step() {
+  let store = InMemoryCacheStore::new(1024 * 1024, EvictionPolicy::Lru, None);
+
+  store
+    .set("counter", CacheValue::String("10".to_string()), None)
+    .await
+    .unwrap();
+  store.incr("counter", 5).await.unwrap();
+
+  let entry = store.peek("counter").unwrap();
+  assert_eq!(entry.value, CacheValue::Integer(15));
+  assert_eq!(entry.size, "counter".len() + 8);
+  assert_eq!(store.info().await.memory_used, entry.size);
+}
+
 #[tokio::test]
 async fn test_store_lru_eviction() {
   // Small memory limit to force eviction (50 bytes)
@@ -432,3 +524,301 @@ async fn test_cache_change_subscription() {
   let subs = manager.get_subscribers("user:123");
   assert_eq!(subs.len(), 0);
 }
+
+// =============================================================================
+// Query Cache Tests
+// =============================================================================
+
+async fn query_cache_pool() -> (
+  std::sync::Arc<dyn squirreldb::db::DatabaseBackend>,
+  squirreldb::query::QueryEnginePool,
+  std::sync::Arc<InMemoryCacheStore>,
+) {
+  use squirreldb::db::{DatabaseBackend, SqliteBackend};
+
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let pool = squirreldb::query::QueryEnginePool::new(1, backend.dialect());
+  let store = std::sync::Arc::new(InMemoryCacheStore::new(
+    1024 * 1024,
+    EvictionPolicy::Lru,
+    None,
+  ));
+  pool.query_cache().set_store(Some(store.clone()));
+  (std::sync::Arc::new(backend), pool, store)
+}
+
+#[tokio::test]
+async fn test_query_cache_serves_until_invalidated() {
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let (backend, pool, store) = query_cache_pool().await;
+  let query = QueryInput::from("db.table(\"items\").run()");
+  let ttl = Duration::from_secs(60);
+
+  backend
+    .insert(DEFAULT_PROJECT_ID, "items", serde_json::json!({"n": 1}))
+    .await
+    .unwrap();
+  let first = pool
+    .execute_cached(&query, DEFAULT_PROJECT_ID, backend.as_ref(), ttl)
+    .await
+    .unwrap();
+  assert_eq!(first.as_array().unwrap().len(), 1);
+  assert_eq!(store.keys("sqrl:query:*").await.len(), 1);
+
+  // A write the cache hasn't heard about yet is not visible
+  backend
+    .insert(DEFAULT_PROJECT_ID, "items", serde_json::json!({"n": 2}))
+    .await
+    .unwrap();
+  let cached = pool
+    .execute_cached(&query, DEFAULT_PROJECT_ID, backend.as_ref(), ttl)
+    .await
+    .unwrap();
+  assert_eq!(cached.as_array().unwrap().len(), 1);
+
+  pool
+    .query_cache()
+    .invalidate(DEFAULT_PROJECT_ID, "items")
+    .await;
+  assert!(store.keys("sqrl:query:*").await.is_empty());
+  let fresh = pool
+    .execute_cached(&query, DEFAULT_PROJECT_ID, backend.as_ref(), ttl)
+    .await
+    .unwrap();
+  assert_eq!(fresh.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_query_cache_skips_encrypted_collections() {
+  use squirreldb::db::{DatabaseBackend, FieldEncryption, SqliteBackend};
+  use std::collections::HashMap;
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let fields = HashMap::from([("users".to_string(), vec!["ssn".to_string()])]);
+  let backend = SqliteBackend::in_memory()
+    .await
+    .unwrap()
+    .with_field_encryption(std::sync::Arc::new(FieldEncryption::new(&[7; 32], fields)));
+  backend.init_schema().await.unwrap();
+  let pool = squirreldb::query::QueryEnginePool::new(1, backend.dialect());
+  let store = std::sync::Arc::new(InMemoryCacheStore::new(
+    1024 * 1024,
+    EvictionPolicy::Lru,
+    None,
+  ));
+  pool.query_cache().set_store(Some(store.clone()));
+  backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      serde_json::json!({"name": "alice", "ssn": "123-45-6789"}),
+    )
+    .await
+    .unwrap();
+
+  let ttl = Duration::from_secs(60);
+  for table in ["users", "items"] {
+    pool
+      .execute_cached(
+        &QueryInput::from(format!("db.table(\"{}\").run()", table)),
+        DEFAULT_PROJECT_ID,
+        &backend,
+        ttl,
+      )
+      .await
+      .unwrap();
+  }
+  // Only the collection without encrypted fields was cached
+  let keys = store.keys("sqrl:query:*").await;
+  assert_eq!(keys.len(), 1);
+  assert!(keys[0].contains(":items:"));
+}
+
+#[tokio::test]
+async fn test_query_cache_invalidation_is_per_collection() {
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let (backend, pool, store) = query_cache_pool().await;
+  let ttl = Duration::from_secs(60);
+  for table in ["items", "users"] {
+    pool
+      .execute_cached(
+        &QueryInput::from(format!("db.table(\"{}\").run()", table)),
+        DEFAULT_PROJECT_ID,
+        backend.as_ref(),
+        ttl,
+      )
+      .await
+      .unwrap();
+  }
+  assert_eq!(store.keys("sqrl:query:*").await.len(), 2);
+
+  pool
+    .query_cache()
+    .invalidate(DEFAULT_PROJECT_ID, "items")
+    .await;
+  let remaining = store.keys("sqrl:query:*").await;
+  assert_eq!(remaining.len(), 1);
+  assert!(remaining[0].contains(":users:"));
+}
+
+#[tokio::test]
+async fn test_query_cache_invalidation_drops_only_tracked_keys() {
+  use squirreldb::query::QueryCache;
+  use uuid::Uuid;
+
+  let cache = QueryCache::new();
+  let store = std::sync::Arc::new(InMemoryCacheStore::new(
+    1024 * 1024,
+    EvictionPolicy::Lru,
+    None,
+  ));
+  cache.set_store(Some(store.clone()));
+  let project_id = Uuid::new_v4();
+  let ttl = Duration::from_secs(60);
+
+  let own = format!("sqrl:query:{}:items:own", project_id);
+  let generation = cache.generation(project_id, "items");
+  cache
+    .put(
+      &own,
+      project_id,
+      "items",
+      generation,
+      serde_json::json!([]),
+      ttl,
+    )
+    .await;
+  // Stored by a previous run or another node: left for invalidate_all
+  let foreign = format!("sqrl:query:{}:items:foreign", project_id);
+  store
+    .set(&foreign, CacheValue::Json(serde_json::json!([])), Some(ttl))
+    .await
+    .unwrap();
+
+  cache.invalidate(project_id, "items").await;
+  assert_eq!(store.keys("sqrl:query:*").await, vec![foreign]);
+
+  cache.invalidate_all().await;
+  assert!(store.keys("sqrl:query:*").await.is_empty());
+}
+
+#[tokio::test]
+async fn test_query_cache_skips_results_that_raced_a_write() {
+  use squirreldb::query::QueryCache;
+  use uuid::Uuid;
+
+  let cache = QueryCache::new();
+  let store = std::sync::Arc::new(InMemoryCacheStore::new(
+    1024 * 1024,
+    EvictionPolicy::Lru,
+    None,
+  ));
+  cache.set_store(Some(store));
+  let project_id = Uuid::new_v4();
+
+  let generation = cache.generation(project_id, "items");
+  cache.invalidate(project_id, "items").await;
+  cache
+    .put(
+      "sqrl:query:test",
+      project_id,
+      "items",
+      generation,
+      serde_json::json!([]),
+      Duration::from_secs(60),
+    )
+    .await;
+  assert!(cache.get("sqrl:query:test").await.is_none());
+}
+
+#[tokio::test]
+async fn test_query_cache_invalidated_by_change_stream() {
+  use types::{Change, ChangeOperation, QueryInput, DEFAULT_PROJECT_ID};
+
+  let (backend, pool, store) = query_cache_pool().await;
+  pool
+    .execute_cached(
+      &QueryInput::from("db.table(\"items\").run()"),
+      DEFAULT_PROJECT_ID,
+      backend.as_ref(),
+      Duration::from_secs(60),
+    )
+    .await
+    .unwrap();
+  assert_eq!(store.keys("sqrl:query:*").await.len(), 1);
+
+  let (tx, rx) = tokio::sync::broadcast::channel(16);
+  let query_cache = pool.query_cache();
+  tokio::spawn(async move { query_cache.process_changes(rx).await });
+  tx.send(Change {
+    id: 1,
+    project_id: DEFAULT_PROJECT_ID,
+    collection: "items".to_string(),
+    document_id: uuid::Uuid::new_v4(),
+    operation: ChangeOperation::Insert,
+    old_data: None,
+    new_data: Some(serde_json::json!({})),
+    changed_at: chrono::Utc::now(),
+  })
+  .unwrap();
+
+  for _ in 0..50 {
+    if store.keys("sqrl:query:*").await.is_empty() {
+      return;
+    }
+    tokio::time::sleep(Duration::from_millis(10)).await;
+  }
+  panic!("query cache entry was not invalidated");
+}
+
+#[tokio::test]
+async fn test_query_cache_warm() {
+  use squirreldb::query::{QueryEnginePool, WarmupQuery};
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let (backend, pool, store) = query_cache_pool().await;
+  backend
+    .insert(DEFAULT_PROJECT_ID, "items", serde_json::json!({"n": 1}))
+    .await
+    .unwrap();
+  let queries: Vec<WarmupQuery> = serde_json::from_value(serde_json::json!([
+    {"query": "db.table(\"items\").run()", "ttl": 120},
+    {"query": {"table": "users"}},
+    {"query": "db.table(\"items\").changes()"},
+    {"query": "db.table(\"users\").select()"},
+  ]))
+  .unwrap();
+
+  let report = pool.warm_all(&queries, backend.as_ref()).await;
+  assert_eq!(report.warmed, 2);
+  assert_eq!(report.skipped, 1);
+  assert_eq!(report.errors.len(), 1);
+  assert_eq!(report.errors[0].index, 3);
+  assert_eq!(store.keys("sqrl:query:*").await.len(), 2);
+
+  // Warmed entries are the ones a cached query reads, so a write the cache
+  // hasn't heard about is not visible yet
+  backend
+    .insert(DEFAULT_PROJECT_ID, "items", serde_json::json!({"n": 2}))
+    .await
+    .unwrap();
+  let cached = pool
+    .execute_cached(
+      &QueryInput::from("db.table(\"items\").run()"),
+      DEFAULT_PROJECT_ID,
+      backend.as_ref(),
+      Duration::from_secs(60),
+    )
+    .await
+    .unwrap();
+  assert_eq!(cached.as_array().unwrap().len(), 1);
+
+  // Nothing is stored without a cache
+  let uncached = QueryEnginePool::new(1, backend.dialect());
+  let report = uncached.warm_all(&queries[..1], backend.as_ref()).await;
+  assert_eq!(report.warmed, 0);
+  assert_eq!(report.skipped, 1);
+}
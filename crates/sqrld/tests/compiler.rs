@@ -1,6 +1,6 @@
 use squirreldb::db::SqlDialect;
-use squirreldb::query::QueryCompiler;
-use squirreldb::types::CompiledFilter;
+use squirreldb::query::{filter_depth, QueryCompiler};
+use squirreldb::types::{CompiledFilter, OrderBySpec, OrderDirection};
 
 #[test]
 fn test_compile_string_equality_double_quotes_postgres() {
@@ -183,3 +183,88 @@ fn test_sql_injection_prevention() {
     _ => panic!("Expected SQL filter"),
   }
 }
+
+// Tests for query parameters
+#[test]
+fn test_compile_query_params() {
+  let compiler = QueryCompiler::new(SqlDialect::Postgres);
+  let filter =
+    compiler.compile_filter("u => u.email === $1 && u.address.zip > $3 || u.role === $1");
+  assert_eq!(
+    filter.compiled_sql.unwrap(),
+    "data->'email' = $3::jsonb AND data->'address'->'zip' > $4::jsonb OR data->'role' = $5::jsonb"
+  );
+  assert_eq!(filter.params, vec![1, 3, 1]);
+
+  let compiler = QueryCompiler::new(SqlDialect::Sqlite);
+  let filter = compiler.compile_filter("u => u.email !== $2");
+  assert_eq!(
+    filter.compiled_sql.unwrap(),
+    "json_extract(data, '$.email') != ?3"
+  );
+  assert_eq!(filter.params, vec![2]);
+
+  // Left to JS: no placeholders, `$1` is a JS variable there
+  let filter = compiler.compile_filter("u => u.name.toLowerCase() === $1");
+  assert!(filter.compiled_sql.is_none());
+  assert!(filter.params.is_empty());
+
+  // Not a parameter reference
+  let filter = compiler.compile_filter("u => u.email === $0");
+  assert!(filter.compiled_sql.is_none());
+}
+
+// Tests for list ordering
+#[test]
+fn test_order_by_defaults_to_stable_order() {
+  assert_eq!(
+    SqlDialect::Postgres.order_by(&[]).unwrap(),
+    " ORDER BY created_at ASC, id ASC"
+  );
+  let order = OrderBySpec {
+    field: "address.city".to_string(),
+    direction: OrderDirection::Desc,
+    near: None,
+  };
+  assert_eq!(
+    SqlDialect::Postgres.order_by(&[order.clone()]).unwrap(),
+    " ORDER BY data->'address'->>'city' DESC, created_at DESC, id DESC"
+  );
+  assert_eq!(
+    SqlDialect::Sqlite.order_by(&[order.clone()]).unwrap(),
+    " ORDER BY json_extract(data, '$.address.city') DESC, created_at DESC, id DESC"
+  );
+
+  let injected = OrderBySpec {
+    field: "name'; DROP TABLE documents; --".to_string(),
+    ..order.clone()
+  };
+  assert!(SqlDialect::Sqlite.order_by(&[injected.clone()]).is_err());
+  // Every key is validated, not just the first
+  assert!(SqlDialect::Sqlite
+    .order_by(&[order.clone(), injected])
+    .is_err());
+
+  let status = OrderBySpec {
+    field: "status".to_string(),
+    direction: OrderDirection::Asc,
+    near: None,
+  };
+  assert_eq!(
+    SqlDialect::Postgres.order_by(&[status, order]).unwrap(),
+    " ORDER BY data->>'status' ASC, data->'address'->>'city' DESC, created_at DESC, id DESC"
+  );
+}
+
+#[test]
+fn test_filter_depth() {
+  assert_eq!(filter_depth("doc => doc.age > 21"), 0);
+  assert_eq!(
+    filter_depth("doc => (doc.a > 1 && (doc.b < 2 || doc.c))"),
+    2
+  );
+  assert_eq!(filter_depth("doc => doc.tags.includes([1, [2]])"), 3);
+  // Brackets in strings don't count
+  assert_eq!(filter_depth(r#"doc => doc.name === "((((\"((""#), 0);
+  assert_eq!(filter_depth("doc => doc.name === '[{'"), 0);
+}
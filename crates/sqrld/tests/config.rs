@@ -1,4 +1,4 @@
-use squirreldb::server::{BackendType, ServerConfig};
+use squirreldb::server::{BackendType, PasswordAlgorithm, QueryCostAction, ServerConfig};
 
 #[test]
 fn test_default_config() {
@@ -86,3 +86,254 @@ fn test_config_empty_yaml() {
   assert_eq!(config.server.ports.http, 8080);
   assert_eq!(config.backend, BackendType::Postgres);
 }
+
+#[test]
+fn test_config_banner() {
+  let config = ServerConfig::default();
+  let info = config.server_info().unwrap();
+  assert_eq!(info.name, "SquirrelDB");
+  assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+  assert_eq!(info.protocols, vec!["rest", "websocket", "tcp"]);
+  assert!(info.message.is_none());
+
+  let yaml = r#"
+server:
+  banner:
+    enabled: false
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert!(config.server_info().is_none());
+}
+
+#[test]
+fn test_config_change_queue() {
+  let config = ServerConfig::default();
+  assert_eq!(config.change_queue.max_entries, 10_000);
+  assert_eq!(config.change_queue.max_age_secs, 3600);
+  assert_eq!(config.change_queue.cleanup_interval_secs, 300);
+
+  let yaml = r#"
+change_queue:
+  max_age_secs: 86400
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.change_queue.max_age_secs, 86400);
+  assert_eq!(config.change_queue.max_entries, 10_000);
+}
+
+#[test]
+fn test_config_password_hashing() {
+  let config = ServerConfig::default();
+  let hashing = &config.auth.password_hashing;
+  assert_eq!(hashing.algorithm, PasswordAlgorithm::Argon2id);
+  assert_eq!(hashing.argon2_memory_kib, 19_456);
+  assert_eq!(hashing.bcrypt_cost, 12);
+
+  let yaml = r#"
+auth:
+  password_hashing:
+    algorithm: bcrypt
+    bcrypt_cost: 14
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  let hashing = &config.auth.password_hashing;
+  assert_eq!(hashing.algorithm, PasswordAlgorithm::Bcrypt);
+  assert_eq!(hashing.bcrypt_cost, 14);
+  assert_eq!(hashing.scrypt_log_n, 17);
+}
+
+#[test]
+fn test_config_auth_notifications() {
+  let config = ServerConfig::default();
+  assert_eq!(config.auth.reset_token_ttl_secs, 3600);
+  assert_eq!(config.auth.reset_cooldown_secs, 60);
+  assert_eq!(config.auth.verification_token_ttl_secs, 86_400);
+  assert!(!config.auth.notifications.is_configured());
+
+  let yaml = r#"
+auth:
+  reset_token_ttl_secs: 900
+  notifications:
+    webhook_url: https://mailer.internal/squirreldb
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.auth.reset_token_ttl_secs, 900);
+  assert!(config.auth.notifications.is_configured());
+  assert!(!config.auth.notifications.log);
+}
+
+#[test]
+fn test_config_query_cost() {
+  let config = ServerConfig::default();
+  assert_eq!(config.limits.max_query_cost, 0.0);
+  assert_eq!(config.limits.query_cost_action, QueryCostAction::Reject);
+
+  let yaml = r#"
+limits:
+  max_query_cost: 50000
+  query_cost_action: warn
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.limits.max_query_cost, 50_000.0);
+  assert_eq!(config.limits.query_cost_action, QueryCostAction::Warn);
+}
+
+#[test]
+fn test_config_nesting_depth() {
+  let config = ServerConfig::default();
+  assert_eq!(config.limits.max_document_depth, 64);
+  assert_eq!(config.limits.max_filter_depth, 32);
+
+  let yaml = r#"
+limits:
+  max_document_depth: 0
+  max_filter_depth: 8
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.limits.max_document_depth, 0);
+  assert_eq!(config.limits.max_filter_depth, 8);
+}
+
+#[test]
+fn test_config_request_size_limits() {
+  let config = ServerConfig::default();
+  assert_eq!(config.limits.max_request_body_bytes, 1024 * 1024);
+  assert_eq!(config.limits.max_upload_bytes, 100 * 1024 * 1024);
+  assert_eq!(config.limits.max_header_bytes, 16 * 1024);
+
+  let yaml = r#"
+limits:
+  max_request_body_bytes: 65536
+  max_upload_bytes: 0
+  max_header_bytes: 8192
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.limits.max_request_body_bytes, 65536);
+  assert_eq!(config.limits.max_upload_bytes, 0);
+  assert_eq!(config.limits.max_header_bytes, 8192);
+}
+
+#[test]
+fn test_config_default_project() {
+  let config = ServerConfig::default();
+  assert!(config.auth.default_project.is_none());
+
+  let yaml = r#"
+auth:
+  enabled: true
+  default_project: main
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.auth.default_project.as_deref(), Some("main"));
+}
+
+#[test]
+fn test_config_oidc() {
+  let config = ServerConfig::default();
+  assert!(!config.auth.oidc.enabled);
+  assert_eq!(config.auth.oidc.username_claim, "preferred_username");
+  assert_eq!(config.auth.oidc.default_role, "member");
+  assert!(!config.auth.oidc.auto_provision);
+
+  let yaml = r#"
+auth:
+  enabled: true
+  oidc:
+    enabled: true
+    issuer: https://id.example.com
+    jwks_url: https://id.example.com/.well-known/jwks.json
+    audience: squirreldb
+    username_claim: email
+    auto_provision: true
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  let oidc = &config.auth.oidc;
+  assert!(oidc.enabled);
+  assert_eq!(oidc.audience.as_deref(), Some("squirreldb"));
+  assert_eq!(oidc.username_claim, "email");
+  assert!(oidc.auto_provision);
+  assert_eq!(oidc.jwks_cache_secs, 3600);
+}
+
+#[test]
+fn test_config_postgres_maintenance() {
+  let config = ServerConfig::default();
+  assert!(!config.postgres.maintenance.enabled);
+  assert_eq!(config.postgres.maintenance.interval_secs, 3600);
+  assert!(!config.postgres.maintenance.vacuum);
+
+  let yaml = r#"
+postgres:
+  url: postgres://localhost/test
+  maintenance:
+    enabled: true
+    interval_secs: 900
+    vacuum: true
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert!(config.postgres.maintenance.enabled);
+  assert_eq!(config.postgres.maintenance.interval_secs, 900);
+  assert!(config.postgres.maintenance.vacuum);
+}
+
+#[test]
+fn test_config_reserved_collections() {
+  let config = ServerConfig::default();
+  assert!(config
+    .collections
+    .reserved
+    .iter()
+    .any(|n| n == "change_queue"));
+
+  let yaml = r#"
+collections:
+  reserved: [documents, audit]
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.collections.reserved, vec!["documents", "audit"]);
+}
+
+#[test]
+fn test_config_postgres_compression() {
+  let config = ServerConfig::default();
+  assert!(!config.postgres.compression.enabled);
+  assert_eq!(config.postgres.compression.threshold_bytes, 2048);
+  assert_eq!(config.postgres.compression.method, "lz4");
+
+  let yaml = r#"
+postgres:
+  url: postgres://localhost/test
+  compression:
+    enabled: true
+    threshold_bytes: 512
+    method: pglz
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert!(config.postgres.compression.enabled);
+  assert_eq!(config.postgres.compression.threshold_bytes, 512);
+  assert_eq!(config.postgres.compression.method, "pglz");
+}
+
+#[test]
+fn test_config_postgres_pool() {
+  let config = ServerConfig::default();
+  assert_eq!(config.postgres.pool.recycling, "verified");
+  assert_eq!(config.postgres.pool.min_idle, 0);
+  assert_eq!(config.postgres.pool.wait_timeout_secs, 30);
+  assert_eq!(config.postgres.pool.create_timeout_secs, 10);
+  assert_eq!(config.postgres.pool.recycle_timeout_secs, 5);
+
+  let yaml = r#"
+postgres:
+  url: postgres://localhost/test
+  pool:
+    recycling: fast
+    min_idle: 4
+    wait_timeout_secs: 0
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.postgres.pool.recycling, "fast");
+  assert_eq!(config.postgres.pool.min_idle, 4);
+  assert_eq!(config.postgres.pool.wait_timeout_secs, 0);
+  assert_eq!(config.postgres.pool.create_timeout_secs, 10);
+}
@@ -1,6 +1,8 @@
 //! Extended configuration tests - protocols, authentication, and edge cases
 
-use squirreldb::server::{AuthSection, BackendType, ProtocolsSection, ServerConfig};
+use squirreldb::server::{
+  AuthSection, BackendType, BackupCompression, CorsOrigins, ProtocolsSection, ServerConfig,
+};
 
 // =============================================================================
 // Protocol Configuration Tests
@@ -170,6 +172,8 @@ fn test_auth_section_serialization() {
   let auth = AuthSection {
     enabled: true,
     admin_token: Some("my-token".to_string()),
+    tcp_require_auth: false,
+    ..Default::default()
   };
 
   let yaml = serde_yaml::to_string(&auth).unwrap();
@@ -305,6 +309,13 @@ fn test_backend_type_sqlite() {
   assert_eq!(config.backend, BackendType::Sqlite);
 }
 
+#[test]
+fn test_backend_type_memory() {
+  let yaml = "backend: memory";
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.backend, BackendType::Memory);
+}
+
 #[test]
 fn test_backend_type_case_insensitive() {
   let cases = vec!["backend: POSTGRES", "backend: Sqlite", "backend: PostgreS"];
@@ -389,6 +400,197 @@ fn test_sqlite_config_paths() {
   }
 }
 
+// =============================================================================
+// Limits Configuration Tests
+// =============================================================================
+
+#[test]
+fn test_limits_message_defaults() {
+  let config = ServerConfig::default();
+  assert_eq!(config.limits.max_message_size, 16 * 1024 * 1024);
+  assert_eq!(config.limits.max_send_queue, 1024);
+}
+
+#[test]
+fn test_limits_message_from_yaml() {
+  let yaml = r#"
+limits:
+  max_message_size: 65536
+  max_send_queue: 32
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.limits.max_message_size, 65536);
+  assert_eq!(config.limits.max_send_queue, 32);
+}
+
+#[test]
+fn test_limits_max_document_bytes() {
+  let config = ServerConfig::default();
+  assert_eq!(config.limits.max_document_bytes, 1024 * 1024);
+
+  let yaml = r#"
+limits:
+  max_document_bytes: 0
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.limits.max_document_bytes, 0);
+}
+
+#[test]
+fn test_limits_max_result_rows() {
+  let config = ServerConfig::default();
+  assert_eq!(config.limits.max_result_rows, 10_000);
+
+  let yaml = r#"
+limits:
+  max_result_rows: 500
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.limits.max_result_rows, 500);
+}
+
+#[test]
+fn test_data_dir_resolves_relative_paths() {
+  let yaml = r#"
+data_dir: /var/lib/squirreldb
+sqlite:
+  path: squirreldb.db
+storage:
+  storage_path: ./objects
+caching:
+  snapshot:
+    path: /tmp/cache.snapshot
+seed_file: seed.ndjson
+"#;
+  let mut config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  config.resolve_paths();
+  assert_eq!(config.sqlite.path, "/var/lib/squirreldb/squirreldb.db");
+  assert_eq!(config.storage.storage_path, "/var/lib/squirreldb/objects");
+  // Absolute paths are kept
+  assert_eq!(config.caching.snapshot.path, "/tmp/cache.snapshot");
+  assert_eq!(config.backup.local_path, "/var/lib/squirreldb/backup");
+  assert_eq!(
+    config.seed_file.as_deref(),
+    Some("/var/lib/squirreldb/seed.ndjson")
+  );
+
+  // Resolving again changes nothing
+  let before = config.sqlite.path.clone();
+  config.resolve_paths();
+  assert_eq!(config.sqlite.path, before);
+}
+
+#[test]
+fn test_data_dir_defaults_to_working_directory() {
+  let mut config = ServerConfig::default();
+  config.sqlite.path = ":memory:".to_string();
+  config.resolve_paths();
+  let cwd = std::env::current_dir().unwrap();
+  assert_eq!(config.data_dir(), cwd);
+  assert_eq!(config.sqlite.path, ":memory:");
+  assert_eq!(
+    std::path::PathBuf::from(&config.storage.storage_path),
+    cwd.join("data/storage")
+  );
+}
+
+#[test]
+fn test_raw_sql_disabled_by_default() {
+  let config = ServerConfig::default();
+  assert!(!config.server.raw_sql.enabled);
+  assert!(!config.server.raw_sql.allow_multiple_statements);
+  assert_eq!(config.server.raw_sql.requests_per_minute, 10);
+
+  let yaml = r#"
+server:
+  raw_sql:
+    enabled: true
+    requests_per_minute: 2
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert!(config.server.raw_sql.enabled);
+  assert!(!config.server.raw_sql.allow_multiple_statements);
+  assert_eq!(config.server.raw_sql.requests_per_minute, 2);
+}
+
+#[test]
+fn test_security_headers_strict_by_default() {
+  let config = ServerConfig::default();
+  let headers = &config.server.security_headers;
+  assert_eq!(headers.frame_options, "DENY");
+  assert!(headers
+    .content_security_policy
+    .contains("frame-ancestors 'none'"));
+
+  let yaml = r#"
+server:
+  security_headers:
+    content_security_policy: "frame-ancestors https://portal.example.com"
+    frame_options: ""
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  let headers = &config.server.security_headers;
+  assert_eq!(
+    headers.content_security_policy,
+    "frame-ancestors https://portal.example.com"
+  );
+  assert_eq!(headers.frame_options, "");
+}
+
+// =============================================================================
+// Backup Configuration Tests
+// =============================================================================
+
+#[test]
+fn test_backup_schedule_default_none() {
+  let config = ServerConfig::default();
+  assert_eq!(config.backup.interval, 3600);
+  assert!(config.backup.schedule.is_none());
+}
+
+#[test]
+fn test_backup_schedule_from_yaml() {
+  let yaml = r#"
+backup:
+  schedule: "0 2 * * *"
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.backup.schedule.as_deref(), Some("0 2 * * *"));
+  assert_eq!(config.backup.interval, 3600);
+}
+
+#[test]
+fn test_backup_incremental_from_yaml() {
+  let config = ServerConfig::default();
+  assert!(!config.backup.incremental);
+  assert_eq!(config.backup.full_every, 24);
+
+  let yaml = r#"
+backup:
+  incremental: true
+  full_every: 12
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert!(config.backup.incremental);
+  assert_eq!(config.backup.full_every, 12);
+}
+
+#[test]
+fn test_backup_compression_from_yaml() {
+  let config = ServerConfig::default();
+  assert_eq!(config.backup.compression, BackupCompression::None);
+  assert!(config.backup.encryption_key.is_none());
+
+  let yaml = r#"
+backup:
+  compression: zstd
+  encryption_key: "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert_eq!(config.backup.compression, BackupCompression::Zstd);
+  assert!(config.backup.encryption_key.is_some());
+}
+
 // =============================================================================
 // Logging Configuration Tests
 // =============================================================================
@@ -410,6 +612,115 @@ fn test_logging_levels() {
   }
 }
 
+// =============================================================================
+// Reload Tests
+// =============================================================================
+
+#[test]
+fn test_reload_no_changes() {
+  let config = ServerConfig::default();
+  assert!(config.reload_changes(&config.clone()).is_empty());
+}
+
+#[test]
+fn test_reload_changes_split() {
+  let running = ServerConfig::default();
+  let yaml = r#"
+server:
+  cors_origins: ["https://app.example.com"]
+logging:
+  level: debug
+limits:
+  requests_per_second: 500
+  slow_query_ms: 200
+auth:
+  admin_token: "secret-token"
+"#;
+  let new: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  let changes = running.reload_changes(&new);
+
+  assert!(changes
+    .applied
+    .contains(&"logging.level: \"info\" -> \"debug\"".to_string()));
+  assert!(changes
+    .applied
+    .contains(&"limits.requests_per_second: 100 -> 500".to_string()));
+  assert!(changes
+    .applied
+    .iter()
+    .any(|c| c.starts_with("server.cors_origins:")));
+  assert!(changes
+    .applied
+    .iter()
+    .any(|c| c.starts_with("limits.slow_query_ms:")));
+
+  // Restart-only settings are listed by key, without their values
+  assert!(changes
+    .pending_restart
+    .contains(&"auth.admin_token".to_string()));
+  assert!(changes
+    .pending_restart
+    .iter()
+    .all(|key| !key.contains("secret-token")));
+}
+
+#[test]
+fn test_reload_pending_until_restart() {
+  let mut running = ServerConfig::default();
+  let mut new = running.clone();
+  new.server.ports.tcp = 9999;
+  new.limits.burst_size = 5;
+
+  let changes = running.reload_changes(&new);
+  assert_eq!(changes.applied, vec!["limits.burst_size: 50 -> 5"]);
+  assert_eq!(changes.pending_restart, vec!["server.ports.tcp"]);
+
+  // Applying keeps the running port, so it is reported again next time
+  running.apply_reloadable(&new);
+  assert_eq!(running.limits.burst_size, 5);
+  assert_eq!(
+    running.server.ports.tcp,
+    ServerConfig::default().server.ports.tcp
+  );
+  let changes = running.reload_changes(&new);
+  assert!(changes.applied.is_empty());
+  assert_eq!(changes.pending_restart, vec!["server.ports.tcp"]);
+}
+
+#[test]
+fn test_cors_origins_update() {
+  let origins = CorsOrigins::new(vec![]);
+  assert!(origins.allows("https://any.example.com"));
+
+  origins.set(vec!["https://app.example.com".to_string()]);
+  assert!(origins.allows("https://app.example.com"));
+  assert!(!origins.allows("https://evil.example.com"));
+
+  origins.set(vec!["*".to_string()]);
+  assert!(origins.allows("https://evil.example.com"));
+}
+
+#[test]
+fn test_cors_origins_websocket_upgrade() {
+  let origins = CorsOrigins::new(vec!["https://app.example.com".to_string()]);
+  assert!(origins.allows_upgrade(Some("https://app.example.com"), None));
+  assert!(!origins.allows_upgrade(Some("https://evil.example.com"), None));
+  // Non-browser clients send no Origin header
+  assert!(origins.allows_upgrade(None, None));
+}
+
+#[test]
+fn test_cors_origins_allow_same_origin_upgrade() {
+  let origins = CorsOrigins::new(vec!["https://app.example.com".to_string()]);
+  // The admin dashboard connects back to the host that served it
+  assert!(origins.allows_upgrade(Some("http://db.internal:8081"), Some("db.internal:8081")));
+  assert!(origins.allows_upgrade(Some("https://DB.internal"), Some("db.internal")));
+  // A different port is a different origin
+  assert!(!origins.allows_upgrade(Some("http://db.internal:3000"), Some("db.internal:8081")));
+  assert!(!origins.allows_upgrade(Some("https://evil.example.com"), Some("db.internal:8081")));
+  assert!(!origins.allows_upgrade(Some("https://evil.example.com"), None));
+}
+
 // =============================================================================
 // Edge Cases
 // =============================================================================
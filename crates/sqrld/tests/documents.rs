@@ -1,8 +1,12 @@
 //! Document operation tests - CRUD, filtering, ordering, pagination
 
 use serde_json::json;
-use squirreldb::db::{DatabaseBackend, SqliteBackend};
-use types::DEFAULT_PROJECT_ID;
+use squirreldb::db::{
+  CollectionDefaults, DatabaseBackend, DocumentEdit, ReadOptions, ReservedCollections,
+  SoftDeleteSettings, SqliteBackend, WriteOptions, INTERNAL_TABLE_NAMES,
+};
+use squirreldb::server::{decode_client_message, InexactNumbers, RateLimitError};
+use types::{ChangeOperation, ClientMessage, DEFAULT_PROJECT_ID};
 
 // =============================================================================
 // Insert Operations
@@ -534,6 +538,171 @@ async fn test_delete_twice() {
   assert!(second.is_none());
 }
 
+#[tokio::test]
+async fn test_get_many() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let a = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Alice"}))
+    .await
+    .unwrap();
+  let b = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Bob"}))
+    .await
+    .unwrap();
+  backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Carol"}))
+    .await
+    .unwrap();
+
+  let missing = uuid::Uuid::new_v4();
+  let mut docs = backend
+    .get_many(
+      DEFAULT_PROJECT_ID,
+      "users",
+      &[b.id, missing, a.id],
+      ReadOptions::default(),
+    )
+    .await
+    .unwrap();
+  docs.sort_by_key(|d| d.data["name"].as_str().unwrap().to_string());
+  assert_eq!(docs.len(), 2);
+  assert_eq!(docs[0].id, a.id);
+  assert_eq!(docs[1].data["name"], "Bob");
+
+  let docs = backend
+    .get_many(DEFAULT_PROJECT_ID, "posts", &[a.id], ReadOptions::default())
+    .await
+    .unwrap();
+  assert!(docs.is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_many() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let a = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Alice"}))
+    .await
+    .unwrap();
+  let b = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Bob"}))
+    .await
+    .unwrap();
+  let c = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Carol"}))
+    .await
+    .unwrap();
+  let last_id = backend.change_id_range().await.unwrap().unwrap().1;
+
+  let missing = uuid::Uuid::new_v4();
+  let deleted = backend
+    .delete_many(DEFAULT_PROJECT_ID, "users", &[a.id, missing, c.id])
+    .await
+    .unwrap();
+  assert_eq!(deleted, vec![a.id, c.id]);
+
+  let remaining = backend
+    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
+    .await
+    .unwrap();
+  assert_eq!(remaining.len(), 1);
+  assert_eq!(remaining[0].id, b.id);
+
+  // One change event per deleted document
+  let changes = backend.list_changes(last_id, 100).await.unwrap();
+  assert_eq!(changes.len(), 2);
+  assert!(changes
+    .iter()
+    .all(|c| c.operation == ChangeOperation::Delete));
+}
+
+#[tokio::test]
+async fn test_delete_many_other_collection() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Alice"}))
+    .await
+    .unwrap();
+
+  let deleted = backend
+    .delete_many(DEFAULT_PROJECT_ID, "posts", &[doc.id])
+    .await
+    .unwrap();
+  assert!(deleted.is_empty());
+  assert!(backend
+    .get(DEFAULT_PROJECT_ID, "users", doc.id)
+    .await
+    .unwrap()
+    .is_some());
+}
+
+#[tokio::test]
+async fn test_drop_collection() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  for n in 0..3 {
+    backend
+      .insert(DEFAULT_PROJECT_ID, "users", json!({"n": n}))
+      .await
+      .unwrap();
+  }
+  backend
+    .insert(DEFAULT_PROJECT_ID, "posts", json!({"title": "Hello"}))
+    .await
+    .unwrap();
+  let last_id = backend.change_id_range().await.unwrap().unwrap().1;
+
+  let deleted = backend
+    .drop_collection(DEFAULT_PROJECT_ID, "users")
+    .await
+    .unwrap();
+  assert_eq!(deleted, 3);
+  assert_eq!(
+    backend.list_collections(DEFAULT_PROJECT_ID).await.unwrap(),
+    vec!["posts".to_string()]
+  );
+
+  // Subscribers still see each document go
+  let changes = backend.list_changes(last_id, 100).await.unwrap();
+  assert_eq!(changes.len(), 3);
+  assert!(changes
+    .iter()
+    .all(|c| c.operation == ChangeOperation::Delete && c.collection == "users"));
+
+  // Soft-delete collections keep their documents, marked deleted
+  backend
+    .set_soft_delete(DEFAULT_PROJECT_ID, "posts", SoftDeleteSettings::default())
+    .await
+    .unwrap();
+  let deleted = backend
+    .drop_collection(DEFAULT_PROJECT_ID, "posts")
+    .await
+    .unwrap();
+  assert_eq!(deleted, 1);
+  let trashed = backend
+    .list_with_options(
+      DEFAULT_PROJECT_ID,
+      "posts",
+      None,
+      None,
+      &[],
+      &[],
+      None,
+      None,
+      ReadOptions { with_deleted: true },
+    )
+    .await
+    .unwrap();
+  assert_eq!(trashed.len(), 1);
+  assert!(trashed[0].deleted_at.is_some());
+}
+
 // =============================================================================
 // List Operations
 // =============================================================================
@@ -555,7 +724,7 @@ async fn test_list_all_documents() {
   }
 
   let docs = backend
-    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None)
+    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
     .await
     .unwrap();
   assert_eq!(docs.len(), 5);
@@ -567,7 +736,7 @@ async fn test_list_empty_collection() {
   backend.init_schema().await.unwrap();
 
   let docs = backend
-    .list(DEFAULT_PROJECT_ID, "empty", None, None, None, None)
+    .list(DEFAULT_PROJECT_ID, "empty", None, None, None, None, None)
     .await
     .unwrap();
   assert!(docs.is_empty());
@@ -586,7 +755,7 @@ async fn test_list_with_limit() {
   }
 
   let docs = backend
-    .list(DEFAULT_PROJECT_ID, "items", None, None, Some(5), None)
+    .list(DEFAULT_PROJECT_ID, "items", None, None, None, Some(5), None)
     .await
     .unwrap();
   assert_eq!(docs.len(), 5);
@@ -605,7 +774,15 @@ async fn test_list_limit_larger_than_count() {
   }
 
   let docs = backend
-    .list(DEFAULT_PROJECT_ID, "items", None, None, Some(100), None)
+    .list(
+      DEFAULT_PROJECT_ID,
+      "items",
+      None,
+      None,
+      None,
+      Some(100),
+      None,
+    )
     .await
     .unwrap();
   assert_eq!(docs.len(), 3);
@@ -644,7 +821,15 @@ async fn test_list_with_filter() {
   // Filter for age > 28 using SQLite syntax
   let filter = "CAST(json_extract(data, '$.age') AS REAL) > 28";
   let docs = backend
-    .list(DEFAULT_PROJECT_ID, "users", Some(filter), None, None, None)
+    .list(
+      DEFAULT_PROJECT_ID,
+      "users",
+      None,
+      Some(filter),
+      None,
+      None,
+      None,
+    )
     .await
     .unwrap();
 
@@ -670,18 +855,201 @@ async fn test_list_only_from_specified_collection() {
     .unwrap();
 
   let users = backend
-    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None)
+    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
     .await
     .unwrap();
   assert_eq!(users.len(), 2);
 
   let posts = backend
-    .list(DEFAULT_PROJECT_ID, "posts", None, None, None, None)
+    .list(DEFAULT_PROJECT_ID, "posts", None, None, None, None, None)
     .await
     .unwrap();
   assert_eq!(posts.len(), 1);
 }
 
+#[tokio::test]
+async fn test_list_with_projection() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      json!({"name": "Alice", "email": "alice@example.com", "active": true, "bio": "x".repeat(1000), "address": {"city": "Paris"}}),
+    )
+    .await
+    .unwrap();
+
+  let fields = vec![
+    "name".to_string(),
+    "active".to_string(),
+    "address.city".to_string(),
+    "missing".to_string(),
+  ];
+  let docs = backend
+    .list(
+      DEFAULT_PROJECT_ID,
+      "users",
+      Some(&fields),
+      None,
+      None,
+      None,
+      None,
+    )
+    .await
+    .unwrap();
+
+  assert_eq!(docs.len(), 1);
+  assert_eq!(
+    docs[0].data,
+    json!({"name": "Alice", "active": true, "address.city": "Paris", "missing": null})
+  );
+}
+
+#[tokio::test]
+async fn test_list_with_computed_fields() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      json!({"first": "Ada", "last": "Lovelace", "age": 36, "score": 7, "nickname": "countess"}),
+    )
+    .await
+    .unwrap();
+
+  let fields = vec![
+    "first".to_string(),
+    "age * 12 as age_months".to_string(),
+    "score / 2 as half".to_string(),
+    "score / 0 as nothing".to_string(),
+    "nickname + 1 as not_a_number".to_string(),
+    "concat(first, ' ', last, ' (', age, ')') as full_name".to_string(),
+  ];
+  let docs = backend
+    .list(
+      DEFAULT_PROJECT_ID,
+      "users",
+      Some(&fields),
+      None,
+      None,
+      None,
+      None,
+    )
+    .await
+    .unwrap();
+
+  assert_eq!(
+    docs[0].data,
+    json!({
+      "first": "Ada",
+      "age_months": 432,
+      "half": 3.5,
+      "nothing": null,
+      "not_a_number": null,
+      "full_name": "Ada Lovelace (36)"
+    })
+  );
+}
+
+#[tokio::test]
+async fn test_list_rejects_invalid_projection() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  for field in [
+    "name') || ('",
+    "age * 12",
+    "upper(name) as n",
+    "(SELECT 1) as n",
+  ] {
+    let fields = vec![field.to_string()];
+    let result = backend
+      .list(
+        DEFAULT_PROJECT_ID,
+        "users",
+        Some(&fields),
+        None,
+        None,
+        None,
+        None,
+      )
+      .await;
+    assert!(result.is_err(), "accepted: {}", field);
+  }
+}
+
+#[tokio::test]
+async fn test_count_documents() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let mut ids = Vec::new();
+  for n in 0..5 {
+    let doc = backend
+      .insert(DEFAULT_PROJECT_ID, "items", json!({ "n": n }))
+      .await
+      .unwrap();
+    ids.push(doc.id);
+  }
+  backend
+    .insert(DEFAULT_PROJECT_ID, "other", json!({ "n": 0 }))
+    .await
+    .unwrap();
+  backend
+    .set_soft_delete(DEFAULT_PROJECT_ID, "items", SoftDeleteSettings::default())
+    .await
+    .unwrap();
+  backend
+    .delete(DEFAULT_PROJECT_ID, "items", ids[0])
+    .await
+    .unwrap();
+
+  let count = backend
+    .count_documents(
+      DEFAULT_PROJECT_ID,
+      "items",
+      None,
+      &[],
+      true,
+      ReadOptions::default(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(count.total, 4);
+  assert!(!count.approximate);
+
+  let count = backend
+    .count_documents(
+      DEFAULT_PROJECT_ID,
+      "items",
+      None,
+      &[],
+      false,
+      ReadOptions { with_deleted: true },
+    )
+    .await
+    .unwrap();
+  assert_eq!(count.total, 5);
+
+  // Same filter as a listing
+  let count = backend
+    .count_documents(
+      DEFAULT_PROJECT_ID,
+      "items",
+      Some("json_extract(data, '$.n') >= 3"),
+      &[],
+      false,
+      ReadOptions::default(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(count.total, 2);
+}
+
 // =============================================================================
 // Collection Operations
 // =============================================================================
@@ -773,6 +1141,304 @@ async fn test_invalid_collection_names_rejected() {
   }
 }
 
+#[tokio::test]
+async fn test_collection_stats() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  for i in 0..4 {
+    let data = if i % 2 == 0 {
+      json!({"name": "a", "age": i})
+    } else {
+      json!({"name": "b"})
+    };
+    backend
+      .insert(DEFAULT_PROJECT_ID, "people", data)
+      .await
+      .unwrap();
+  }
+
+  let stats = backend
+    .collection_stats(DEFAULT_PROJECT_ID, "people")
+    .await
+    .unwrap();
+  assert_eq!(stats.document_count, 4);
+  assert_eq!(stats.sampled, 4);
+  assert!(stats.total_bytes > 0);
+  assert_eq!(stats.avg_document_bytes, stats.total_bytes as f64 / 4.0);
+
+  assert_eq!(stats.fields.len(), 2);
+  assert_eq!(stats.fields[0].field, "name");
+  assert_eq!(stats.fields[0].count, 4);
+  assert_eq!(stats.fields[0].frequency, 1.0);
+  assert_eq!(stats.fields[1].field, "age");
+  assert_eq!(stats.fields[1].frequency, 0.5);
+}
+
+#[tokio::test]
+async fn test_collection_stats_empty() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let stats = backend
+    .collection_stats(DEFAULT_PROJECT_ID, "missing")
+    .await
+    .unwrap();
+  assert_eq!(stats.document_count, 0);
+  assert_eq!(stats.avg_document_bytes, 0.0);
+  assert!(stats.fields.is_empty());
+}
+
+#[tokio::test]
+async fn test_rename_collection() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "drafts", json!({"title": "a"}))
+    .await
+    .unwrap();
+  backend
+    .insert(DEFAULT_PROJECT_ID, "drafts", json!({"title": "b"}))
+    .await
+    .unwrap();
+  let last_id = backend.change_id_range().await.unwrap().unwrap().1;
+
+  let renamed = backend
+    .rename_collection(DEFAULT_PROJECT_ID, "drafts", "posts")
+    .await
+    .unwrap();
+  assert_eq!(renamed, 2);
+
+  let collections = backend.list_collections(DEFAULT_PROJECT_ID).await.unwrap();
+  assert_eq!(collections, vec!["posts".to_string()]);
+  let moved = backend
+    .get(DEFAULT_PROJECT_ID, "posts", doc.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(moved.data["title"], "a");
+
+  // Each document leaves the old collection and enters the new one
+  let changes = backend.list_changes(last_id, 100).await.unwrap();
+  assert_eq!(changes.len(), 4);
+  let ops: Vec<_> = changes
+    .iter()
+    .map(|c| (c.collection.as_str(), c.operation))
+    .collect();
+  assert_eq!(
+    ops
+      .iter()
+      .filter(|o| **o == ("drafts", ChangeOperation::Delete))
+      .count(),
+    2
+  );
+  assert_eq!(
+    ops
+      .iter()
+      .filter(|o| **o == ("posts", ChangeOperation::Insert))
+      .count(),
+    2
+  );
+}
+
+#[tokio::test]
+async fn test_copy_collection() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "posts", json!({"title": "a"}))
+    .await
+    .unwrap();
+
+  let copied = backend
+    .copy_collection(DEFAULT_PROJECT_ID, "posts", "posts_backup", 0)
+    .await
+    .unwrap();
+  assert_eq!(copied, 1);
+
+  let original = backend
+    .list(DEFAULT_PROJECT_ID, "posts", None, None, None, None, None)
+    .await
+    .unwrap();
+  let copies = backend
+    .list(
+      DEFAULT_PROJECT_ID,
+      "posts_backup",
+      None,
+      None,
+      None,
+      None,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(original.len(), 1);
+  assert_eq!(copies.len(), 1);
+  assert_ne!(copies[0].id, doc.id);
+  assert_eq!(copies[0].id.get_version_num(), 4);
+  assert_eq!(copies[0].data, doc.data);
+}
+
+#[tokio::test]
+async fn test_document_quota_is_checked_with_the_write() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let options = WriteOptions {
+    max_documents: 3,
+    ..Default::default()
+  };
+
+  for n in 0..2 {
+    backend
+      .insert_with_options(DEFAULT_PROJECT_ID, "posts", json!({ "n": n }), options)
+      .await
+      .unwrap();
+  }
+
+  // Copying two more would make four
+  let err = backend
+    .copy_collection(DEFAULT_PROJECT_ID, "posts", "posts_backup", 3)
+    .await
+    .unwrap_err();
+  assert!(matches!(
+    err.downcast_ref::<RateLimitError>(),
+    Some(RateLimitError::ProjectQuotaExceeded { limit: 3, .. })
+  ));
+  assert_eq!(
+    backend
+      .count_project_documents(DEFAULT_PROJECT_ID)
+      .await
+      .unwrap(),
+    2
+  );
+
+  backend
+    .insert_with_options(DEFAULT_PROJECT_ID, "posts", json!({ "n": 2 }), options)
+    .await
+    .unwrap();
+  let err = backend
+    .insert_with_options(DEFAULT_PROJECT_ID, "posts", json!({ "n": 3 }), options)
+    .await
+    .unwrap_err();
+  assert!(err.downcast_ref::<RateLimitError>().is_some());
+  assert_eq!(
+    backend
+      .count_project_documents(DEFAULT_PROJECT_ID)
+      .await
+      .unwrap(),
+    3
+  );
+}
+
+#[tokio::test]
+async fn test_rename_and_copy_reject_existing_target() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  backend
+    .insert(DEFAULT_PROJECT_ID, "alpha", json!({}))
+    .await
+    .unwrap();
+  backend
+    .insert(DEFAULT_PROJECT_ID, "beta", json!({}))
+    .await
+    .unwrap();
+
+  assert!(backend
+    .rename_collection(DEFAULT_PROJECT_ID, "alpha", "beta")
+    .await
+    .is_err());
+  assert!(backend
+    .copy_collection(DEFAULT_PROJECT_ID, "alpha", "beta", 0)
+    .await
+    .is_err());
+  assert!(backend
+    .rename_collection(DEFAULT_PROJECT_ID, "alpha", "Not-Valid")
+    .await
+    .is_err());
+
+  // Nothing moved
+  let alpha = backend
+    .list(DEFAULT_PROJECT_ID, "alpha", None, None, None, None, None)
+    .await
+    .unwrap();
+  assert_eq!(alpha.len(), 1);
+}
+
+// =============================================================================
+// Number Precision Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_large_integer_round_trips_exactly() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  // 2^53 + 1: the first integer a double can't hold
+  let text =
+    r#"{"type":"insert","id":"i1","collection":"ledger","data":{"amount":9007199254740993}}"#;
+  let Ok(ClientMessage::Insert { data, .. }) = decode_client_message(text, InexactNumbers::Allow)
+  else {
+    panic!("Expected an insert");
+  };
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "ledger", data)
+    .await
+    .unwrap();
+
+  let stored = backend
+    .get(DEFAULT_PROJECT_ID, "ledger", doc.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(stored.data["amount"].as_u64(), Some(9007199254740993));
+  assert!(serde_json::to_string(&stored.data)
+    .unwrap()
+    .contains("9007199254740993"));
+
+  let listed = backend
+    .list(
+      DEFAULT_PROJECT_ID,
+      "ledger",
+      Some(&["amount".to_string()]),
+      Some("json_extract(data, '$.amount') = 9007199254740993"),
+      None,
+      None,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(listed.len(), 1);
+  assert_eq!(listed[0].data["amount"].as_u64(), Some(9007199254740993));
+}
+
+#[tokio::test]
+async fn test_inexact_numbers_stored_as_strings() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let text = r#"{"type":"insert","id":"i1","collection":"ledger","data":{"rate":0.1000000000000000055,"n":2}}"#;
+  let Ok(ClientMessage::Insert { data, .. }) = decode_client_message(text, InexactNumbers::String)
+  else {
+    panic!("Expected an insert");
+  };
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "ledger", data)
+    .await
+    .unwrap();
+  let stored = backend
+    .get(DEFAULT_PROJECT_ID, "ledger", doc.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(stored.data["rate"], "0.1000000000000000055");
+  assert_eq!(stored.data["n"], 2);
+
+  assert!(decode_client_message(text, InexactNumbers::Reject).is_err());
+}
+
 // =============================================================================
 // Document Metadata Tests
 // =============================================================================
@@ -795,6 +1461,362 @@ async fn test_document_has_timestamps() {
   assert!(updated_diff.num_seconds() < 60);
 }
 
+#[tokio::test]
+async fn test_insert_preserves_created_at() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let original: chrono::DateTime<chrono::Utc> = "2020-01-02T03:04:05Z".parse().unwrap();
+
+  let options = WriteOptions {
+    preserve_created_at: Some(original),
+    ..Default::default()
+  };
+  let doc = backend
+    .insert_with_options(
+      DEFAULT_PROJECT_ID,
+      "users",
+      json!({"name": "Alice"}),
+      options,
+    )
+    .await
+    .unwrap();
+  assert_eq!(doc.created_at, original);
+  assert!(doc.updated_at > original);
+
+  // no_touch keeps updated_at at the imported creation time
+  let options = WriteOptions {
+    preserve_created_at: Some(original),
+    no_touch: true,
+    ..Default::default()
+  };
+  let doc = backend
+    .insert_with_options(DEFAULT_PROJECT_ID, "users", json!({"name": "Bob"}), options)
+    .await
+    .unwrap();
+  let stored = backend
+    .get(DEFAULT_PROJECT_ID, "users", doc.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(stored.created_at, original);
+  assert_eq!(stored.updated_at, original);
+}
+
+#[tokio::test]
+async fn test_update_no_touch_keeps_updated_at() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Alice"}))
+    .await
+    .unwrap();
+  let options = WriteOptions {
+    no_touch: true,
+    ..Default::default()
+  };
+  let updated = backend
+    .update_with_options(
+      DEFAULT_PROJECT_ID,
+      "users",
+      doc.id,
+      json!({"name": "Alice", "tag": "vip"}),
+      options,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(updated.data["tag"], "vip");
+  assert_eq!(updated.updated_at, doc.updated_at);
+
+  let updated = backend
+    .update(DEFAULT_PROJECT_ID, "users", doc.id, json!({"name": "Bob"}))
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(updated.updated_at >= doc.updated_at);
+  assert_eq!(updated.created_at, doc.created_at);
+}
+
+#[tokio::test]
+async fn test_collection_defaults() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let defaults: CollectionDefaults = serde_json::from_value(json!({
+    "status": "pending",
+    "created_by": "$user",
+  }))
+  .unwrap();
+  backend
+    .set_collection_defaults(DEFAULT_PROJECT_ID, "orders", &defaults)
+    .await
+    .unwrap();
+
+  let author = uuid::Uuid::new_v4();
+  let options = WriteOptions {
+    author: Some(author),
+    ..Default::default()
+  };
+  let doc = backend
+    .insert_with_options(DEFAULT_PROJECT_ID, "orders", json!({"total": 5}), options)
+    .await
+    .unwrap();
+  assert_eq!(doc.data["status"], "pending");
+  assert_eq!(doc.data["created_by"], author.to_string());
+
+  // Fields the document sets win, and updates leave missing fields alone
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "orders", json!({"status": "paid"}))
+    .await
+    .unwrap();
+  assert_eq!(doc.data["status"], "paid");
+  assert!(doc.data.get("created_by").is_none());
+  let updated = backend
+    .update(DEFAULT_PROJECT_ID, "orders", doc.id, json!({"total": 1}))
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(updated.data.get("status").is_none());
+
+  let options = WriteOptions {
+    apply_defaults: true,
+    ..Default::default()
+  };
+  let updated = backend
+    .update_with_options(
+      DEFAULT_PROJECT_ID,
+      "orders",
+      doc.id,
+      json!({"total": 1}),
+      options,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(updated.data["status"], "pending");
+
+  assert!(backend
+    .delete_collection_defaults(DEFAULT_PROJECT_ID, "orders")
+    .await
+    .unwrap());
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "orders", json!({}))
+    .await
+    .unwrap();
+  assert!(doc.data.get("status").is_none());
+}
+
+#[tokio::test]
+async fn test_edit_document_with_json_patch() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let doc = backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      json!({"name": "Alice", "tags": ["a"], "version": 1}),
+    )
+    .await
+    .unwrap();
+
+  let json_patch = |ops: serde_json::Value| -> DocumentEdit {
+    let patch: json_patch::Patch = serde_json::from_value(ops).unwrap();
+    Box::new(move |mut data| {
+      json_patch::patch(&mut data, &patch)?;
+      Ok(data)
+    })
+  };
+
+  let patched = backend
+    .edit_document(
+      DEFAULT_PROJECT_ID,
+      "users",
+      doc.id,
+      json_patch(json!([
+        {"op": "test", "path": "/version", "value": 1},
+        {"op": "replace", "path": "/version", "value": 2},
+        {"op": "add", "path": "/tags/-", "value": "b"},
+        {"op": "move", "from": "/name", "path": "/display_name"},
+      ])),
+      WriteOptions::default(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(
+    patched.data,
+    json!({"display_name": "Alice", "tags": ["a", "b"], "version": 2})
+  );
+
+  // A failed test leaves the document as it was
+  let err = backend
+    .edit_document(
+      DEFAULT_PROJECT_ID,
+      "users",
+      doc.id,
+      json_patch(json!([
+        {"op": "remove", "path": "/tags"},
+        {"op": "test", "path": "/version", "value": 1},
+      ])),
+      WriteOptions::default(),
+    )
+    .await
+    .unwrap_err();
+  assert!(err.downcast_ref::<json_patch::PatchError>().is_some());
+  let current = backend
+    .get(DEFAULT_PROJECT_ID, "users", doc.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(current.data, patched.data);
+
+  assert!(backend
+    .edit_document(
+      DEFAULT_PROJECT_ID,
+      "users",
+      uuid::Uuid::new_v4(),
+      json_patch(json!([])),
+      WriteOptions::default(),
+    )
+    .await
+    .unwrap()
+    .is_none());
+}
+
+#[tokio::test]
+async fn test_edit_document_with_merge_patch() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let doc = backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      json!({"name": "Alice", "address": {"city": "Oslo", "zip": "0150"}, "draft": true}),
+    )
+    .await
+    .unwrap();
+  let last_id = backend.change_id_range().await.unwrap().unwrap().1;
+
+  let patch = json!({"address": {"zip": null, "street": "Main St"}, "draft": null});
+  let merged = backend
+    .edit_document(
+      DEFAULT_PROJECT_ID,
+      "users",
+      doc.id,
+      Box::new(move |mut data| {
+        json_patch::merge(&mut data, &patch);
+        Ok(data)
+      }),
+      WriteOptions::default(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+  let expected = json!({"name": "Alice", "address": {"city": "Oslo", "street": "Main St"}});
+  assert_eq!(merged.data, expected);
+
+  // Subscribers see the whole merged document
+  let changes = backend.list_changes(last_id, 100).await.unwrap();
+  assert_eq!(changes.len(), 1);
+  assert_eq!(changes[0].operation, ChangeOperation::Update);
+  assert_eq!(changes[0].new_data, Some(expected));
+}
+
+#[tokio::test]
+async fn test_reserved_collection_names() {
+  let backend = SqliteBackend::in_memory()
+    .await
+    .unwrap()
+    .with_reserved_collections(ReservedCollections::new(INTERNAL_TABLE_NAMES));
+  backend.init_schema().await.unwrap();
+
+  let err = backend
+    .insert(DEFAULT_PROJECT_ID, "documents", json!({"a": 1}))
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("reserved"));
+
+  backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"a": 1}))
+    .await
+    .unwrap();
+  assert!(backend
+    .copy_collection(DEFAULT_PROJECT_ID, "users", "change_queue", 0)
+    .await
+    .is_err());
+  assert!(backend
+    .rename_collection(DEFAULT_PROJECT_ID, "users", "projects")
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_client_kv() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let other_project = uuid::Uuid::new_v4();
+
+  backend
+    .set_kv(
+      DEFAULT_PROJECT_ID,
+      "prefs",
+      "theme",
+      &json!({"dark": true}),
+      None,
+    )
+    .await
+    .unwrap();
+  backend
+    .set_kv(DEFAULT_PROJECT_ID, "prefs", "theme", &json!("light"), None)
+    .await
+    .unwrap();
+  assert_eq!(
+    backend
+      .get_kv(DEFAULT_PROJECT_ID, "prefs", "theme")
+      .await
+      .unwrap(),
+    Some(json!("light"))
+  );
+  // Values are scoped to their project
+  assert!(backend
+    .get_kv(other_project, "prefs", "theme")
+    .await
+    .unwrap()
+    .is_none());
+
+  backend
+    .set_kv(
+      DEFAULT_PROJECT_ID,
+      "flags",
+      "beta",
+      &json!(true),
+      Some(std::time::Duration::from_millis(1)),
+    )
+    .await
+    .unwrap();
+  tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+  assert!(backend
+    .get_kv(DEFAULT_PROJECT_ID, "flags", "beta")
+    .await
+    .unwrap()
+    .is_none());
+  assert!(!backend
+    .delete_kv(DEFAULT_PROJECT_ID, "flags", "beta")
+    .await
+    .unwrap());
+  assert_eq!(backend.purge_expired_kv().await.unwrap(), 1);
+
+  assert!(backend
+    .delete_kv(DEFAULT_PROJECT_ID, "prefs", "theme")
+    .await
+    .unwrap());
+  assert!(backend
+    .get_kv(DEFAULT_PROJECT_ID, "prefs", "theme")
+    .await
+    .unwrap()
+    .is_none());
+}
+
 #[tokio::test]
 async fn test_document_id_is_uuid() {
   let backend = SqliteBackend::in_memory().await.unwrap();
@@ -855,7 +1877,7 @@ async fn test_concurrent_inserts() {
   }
 
   let docs = backend
-    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None)
+    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
     .await
     .unwrap();
   assert_eq!(docs.len(), 20);
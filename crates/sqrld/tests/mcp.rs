@@ -392,7 +392,7 @@ mod integration {
 
     // Query should find it
     let docs = backend
-      .list(DEFAULT_PROJECT_ID, "users", None, None, None, None)
+      .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
       .await
       .unwrap();
     assert_eq!(docs.len(), 1);
@@ -511,6 +511,7 @@ mod integration {
     let result = engine_pool
       .execute(
         "db.table(\"users\").filter(u => u.age > 28).run()",
+        DEFAULT_PROJECT_ID,
         backend.as_ref(),
       )
       .await
@@ -563,6 +564,7 @@ mod integration {
         None,
         None,
         None,
+        None,
       )
       .await
       .unwrap();
@@ -589,11 +591,27 @@ mod integration {
 
     // Verify isolation
     let a_docs = backend
-      .list(DEFAULT_PROJECT_ID, "collection_a", None, None, None, None)
+      .list(
+        DEFAULT_PROJECT_ID,
+        "collection_a",
+        None,
+        None,
+        None,
+        None,
+        None,
+      )
       .await
       .unwrap();
     let b_docs = backend
-      .list(DEFAULT_PROJECT_ID, "collection_b", None, None, None, None)
+      .list(
+        DEFAULT_PROJECT_ID,
+        "collection_b",
+        None,
+        None,
+        None,
+        None,
+        None,
+      )
       .await
       .unwrap();
 
@@ -869,6 +887,7 @@ mod query_engine {
     let result = engine_pool
       .execute(
         "db.table(\"users\").filter(u => u.role === \"admin\").run()",
+        DEFAULT_PROJECT_ID,
         backend.as_ref(),
       )
       .await
@@ -911,6 +930,7 @@ mod query_engine {
     let result = engine_pool
       .execute(
         "db.table(\"products\").filter(p => p.price >= 25).run()",
+        DEFAULT_PROJECT_ID,
         backend.as_ref(),
       )
       .await
@@ -933,6 +953,7 @@ mod query_engine {
     let result = engine_pool
       .execute(
         "db.table(\"data\").filter(d => d.value > 100).run()",
+        DEFAULT_PROJECT_ID,
         backend.as_ref(),
       )
       .await
@@ -941,6 +962,39 @@ mod query_engine {
     assert!(result.as_array().unwrap().is_empty());
   }
 
+  #[tokio::test]
+  async fn test_query_scoped_to_project() {
+    let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
+    backend.init_schema().await.unwrap();
+
+    let other_project = uuid::Uuid::new_v4();
+    backend
+      .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Alice"}))
+      .await
+      .unwrap();
+    backend
+      .insert(other_project, "users", json!({"name": "Bob"}))
+      .await
+      .unwrap();
+
+    let engine_pool = Arc::new(QueryEnginePool::new(1, backend.dialect()));
+    let query = "db.table(\"users\").run()";
+    let default_result = engine_pool
+      .execute(query, DEFAULT_PROJECT_ID, backend.as_ref())
+      .await
+      .unwrap();
+    let other_result = engine_pool
+      .execute(query, other_project, backend.as_ref())
+      .await
+      .unwrap();
+
+    // Same query text must not share cached results across projects
+    assert_eq!(default_result.as_array().unwrap().len(), 1);
+    assert_eq!(default_result[0]["data"]["name"], "Alice");
+    assert_eq!(other_result.as_array().unwrap().len(), 1);
+    assert_eq!(other_result[0]["data"]["name"], "Bob");
+  }
+
   #[tokio::test]
   async fn test_query_map_transform() {
     let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
@@ -959,6 +1013,7 @@ mod query_engine {
     let result = engine_pool
       .execute(
         "db.table(\"users\").map(u => ({ fullName: u.firstName + \" \" + u.lastName })).run()",
+        DEFAULT_PROJECT_ID,
         backend.as_ref(),
       )
       .await
@@ -1032,6 +1087,7 @@ mod errors {
     let result = engine_pool
       .execute(
         "db.table(\"test\").filter(u => {{{).run()",
+        DEFAULT_PROJECT_ID,
         backend.as_ref(),
       )
       .await;
@@ -1,4 +1,16 @@
-use squirreldb::types::{ClientMessage, ServerMessage};
+use std::sync::Arc;
+
+use squirreldb::db::{DatabaseBackend, SqliteBackend};
+use squirreldb::query::QueryEnginePool;
+use squirreldb::server::{
+  LimitsSection, MessageHandler, RateLimiter, ServerConfig, STREAM_CHUNK_ROWS,
+};
+use squirreldb::subscriptions::SubscriptionManager;
+use squirreldb::types::{
+  ChangeEvent, ClientMessage, ErrorCode, ResultDiff, ServerMessage, DEFAULT_PROJECT_ID,
+  PROTOCOL_VERSION,
+};
+use uuid::Uuid;
 
 #[test]
 fn test_client_message_roundtrip() {
@@ -6,14 +18,24 @@ fn test_client_message_roundtrip() {
     ClientMessage::Query {
       id: "1".into(),
       query: "db.table(\"test\").run()".into(),
+      cache: false,
+      cache_ttl: None,
+      params: Vec::new(),
+      stream: false,
     },
     ClientMessage::Subscribe {
       id: "2".into(),
       query: "db.table(\"test\").changes()".into(),
+      read_your_writes: false,
+      ids: Vec::new(),
     },
     ClientMessage::Unsubscribe { id: "3".into() },
     ClientMessage::ListCollections { id: "4".into() },
     ClientMessage::Ping { id: "5".into() },
+    ClientMessage::Watch {
+      id: "6".into(),
+      query: "db.table(\"test\").limit(10).run()".into(),
+    },
   ];
 
   for msg in messages {
@@ -29,6 +51,23 @@ fn test_server_message_roundtrip() {
   let json = serde_json::to_string(&result).unwrap();
   let parsed: ServerMessage = serde_json::from_str(&json).unwrap();
   assert!(matches!(parsed, ServerMessage::Result { id, .. } if id == "1"));
+
+  let diff = ServerMessage::diff(
+    "w1",
+    vec![ResultDiff::Move {
+      from: 2,
+      to: 0,
+      document: serde_json::json!({"id": "x"}),
+    }],
+  );
+  assert_eq!(
+    serde_json::to_value(&diff).unwrap(),
+    serde_json::json!({
+      "type": "diff",
+      "id": "w1",
+      "diff": [{"op": "move", "from": 2, "to": 0, "document": {"id": "x"}}]
+    })
+  );
 }
 
 #[test]
@@ -39,6 +78,8 @@ fn test_client_message_insert_with_uuid() {
     collection: "users".into(),
     document_id: doc_id,
     data: serde_json::json!({"name": "Alice"}),
+    no_touch: false,
+    apply_defaults: false,
   };
   let json = serde_json::to_string(&msg).unwrap();
   assert!(json.contains(&doc_id.to_string()));
@@ -57,6 +98,10 @@ fn test_message_type_tag() {
   let query = ClientMessage::Query {
     id: "1".into(),
     query: "test".into(),
+    cache: false,
+    cache_ttl: None,
+    params: Vec::new(),
+    stream: false,
   };
   let json = serde_json::to_string(&query).unwrap();
   assert!(json.contains(r#""type":"query""#));
@@ -65,3 +110,649 @@ fn test_message_type_tag() {
   let json = serde_json::to_string(&ping).unwrap();
   assert!(json.contains(r#""type":"ping""#));
 }
+
+// =============================================================================
+// Version Negotiation Tests
+// =============================================================================
+
+async fn test_handler() -> MessageHandler {
+  let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
+  backend.init_schema().await.unwrap();
+  let engine_pool = Arc::new(QueryEnginePool::new(1, backend.dialect()));
+  MessageHandler::new(backend, Arc::new(SubscriptionManager::new()), engine_pool)
+}
+
+#[tokio::test]
+async fn test_pinned_handler_refuses_select_project() {
+  let client = Uuid::new_v4();
+  let select = |project_id| ClientMessage::SelectProject {
+    id: "p1".into(),
+    project_id,
+  };
+
+  // A connection without credentials stays on the default project
+  let handler = test_handler().await.with_project(Some(DEFAULT_PROJECT_ID));
+  let resp = handler.handle(client, select(Uuid::new_v4())).await;
+  assert!(matches!(
+    resp,
+    ServerMessage::Error {
+      code: Some(ErrorCode::Forbidden),
+      ..
+    }
+  ));
+  let resp = handler.handle(client, select(DEFAULT_PROJECT_ID)).await;
+  assert!(matches!(resp, ServerMessage::ProjectSelected { .. }));
+
+  // Unrestricted handlers only refuse projects that don't exist
+  let resp = test_handler()
+    .await
+    .handle(client, select(Uuid::new_v4()))
+    .await;
+  assert!(matches!(
+    resp,
+    ServerMessage::Error {
+      code: Some(ErrorCode::NotFound),
+      ..
+    }
+  ));
+}
+
+#[test]
+fn test_hello_message_format() {
+  let hello: ClientMessage =
+    serde_json::from_str(r#"{"type":"hello","id":"h1","version":2}"#).unwrap();
+  assert!(matches!(hello, ClientMessage::Hello { version: 2, .. }));
+  assert_eq!(hello.id(), "h1");
+
+  let reply = ServerMessage::Hello {
+    id: "h1".into(),
+    version: 2,
+    server: None,
+  };
+  assert_eq!(
+    serde_json::to_value(&reply).unwrap(),
+    serde_json::json!({"type": "hello", "id": "h1", "version": 2})
+  );
+}
+
+#[tokio::test]
+async fn test_hello_negotiates_version() {
+  let handler = test_handler().await;
+  let client = Uuid::new_v4();
+
+  // A newer client is answered with the server's version
+  let resp = handler
+    .handle(
+      client,
+      ClientMessage::Hello {
+        id: "h1".into(),
+        version: PROTOCOL_VERSION + 5,
+      },
+    )
+    .await;
+  assert!(matches!(resp, ServerMessage::Hello { version, .. } if version == PROTOCOL_VERSION));
+  assert_eq!(handler.protocol_version(), PROTOCOL_VERSION);
+
+  // Only the first message may negotiate
+  let resp = handler
+    .handle(
+      client,
+      ClientMessage::Hello {
+        id: "h2".into(),
+        version: 1,
+      },
+    )
+    .await;
+  assert!(matches!(
+    resp,
+    ServerMessage::Error {
+      code: Some(ErrorCode::BadRequest),
+      ..
+    }
+  ));
+}
+
+#[tokio::test]
+async fn test_hello_includes_server_info() {
+  let info = ServerConfig::default().server_info();
+  let handler = test_handler().await.with_server_info(info.clone());
+  let resp = handler
+    .handle(
+      Uuid::new_v4(),
+      ClientMessage::Hello {
+        id: "h1".into(),
+        version: PROTOCOL_VERSION,
+      },
+    )
+    .await;
+  assert!(matches!(resp, ServerMessage::Hello { server, .. } if server == info));
+
+  let banner = serde_json::to_value(ServerMessage::banner(info.unwrap())).unwrap();
+  assert_eq!(banner["type"], "hello");
+  assert_eq!(banner["id"], "");
+  assert_eq!(banner["version"], 1);
+  assert_eq!(banner["server"]["name"], "SquirrelDB");
+  assert_eq!(banner["server"]["max_protocol_version"], PROTOCOL_VERSION);
+}
+
+#[tokio::test]
+async fn test_hello_version_unsupported() {
+  let handler = test_handler().await;
+  let resp = handler
+    .handle(
+      Uuid::new_v4(),
+      ClientMessage::Hello {
+        id: "h1".into(),
+        version: 0,
+      },
+    )
+    .await;
+  match resp {
+    ServerMessage::Error { id, code, .. } => {
+      assert_eq!(id, "h1");
+      assert_eq!(code, Some(ErrorCode::VersionUnsupported));
+    }
+    other => panic!("Expected an error, got {:?}", other),
+  }
+}
+
+#[tokio::test]
+async fn test_no_hello_uses_version_one() {
+  let handler = test_handler().await;
+  let client = Uuid::new_v4();
+  let resp = handler
+    .handle(client, ClientMessage::Ping { id: "p1".into() })
+    .await;
+  assert!(matches!(resp, ServerMessage::Pong { .. }));
+  assert_eq!(handler.protocol_version(), 1);
+
+  // Too late to negotiate once other messages were handled
+  let resp = handler
+    .handle(
+      client,
+      ClientMessage::Hello {
+        id: "h1".into(),
+        version: PROTOCOL_VERSION,
+      },
+    )
+    .await;
+  assert!(matches!(resp, ServerMessage::Error { .. }));
+}
+
+// =============================================================================
+// Read-Your-Writes Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_read_your_writes_skips_own_changes() {
+  let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
+  backend.init_schema().await.unwrap();
+  let engine_pool = Arc::new(QueryEnginePool::new(1, backend.dialect()));
+  let subs = Arc::new(SubscriptionManager::new());
+  let handler = MessageHandler::new(backend.clone(), subs.clone(), engine_pool);
+  let client = Uuid::new_v4();
+  let mut outgoing = subs.subscribe_to_outgoing();
+
+  let resp = handler
+    .handle(
+      client,
+      ClientMessage::Subscribe {
+        id: "s1".into(),
+        query: "db.table(\"users\").changes()".into(),
+        read_your_writes: true,
+        ids: Vec::new(),
+      },
+    )
+    .await;
+  assert!(matches!(resp, ServerMessage::Subscribed { .. }));
+
+  let resp = handler
+    .handle(
+      client,
+      serde_json::from_value(serde_json::json!({
+        "type": "insert", "id": "i1", "collection": "users", "data": {"name": "alice"}
+      }))
+      .unwrap(),
+    )
+    .await;
+  let ServerMessage::Result {
+    change_id: Some(own_change),
+    ..
+  } = resp
+  else {
+    panic!("Expected a result with a change ID, got {:?}", resp);
+  };
+
+  // A write by someone else is still delivered
+  backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      serde_json::json!({"name": "bob"}),
+    )
+    .await
+    .unwrap();
+  let changes = backend.list_changes(0, 10).await.unwrap();
+  assert_eq!(changes.len(), 2);
+  assert_eq!(changes[0].id, own_change);
+  let other_change = changes[1].id;
+
+  let (tx, rx) = tokio::sync::broadcast::channel(16);
+  for change in changes {
+    tx.send(change).unwrap();
+  }
+  drop(tx);
+  subs.process_changes(rx).await;
+
+  let (to, msg) = outgoing.try_recv().unwrap();
+  assert_eq!(to, client);
+  assert!(matches!(msg, ServerMessage::Change { change_id: Some(id), .. } if id == other_change));
+  assert!(outgoing.try_recv().is_err());
+}
+
+/// Next `diff` sent to `client`, waiting for watch refreshes to run
+async fn next_diff(
+  outgoing: &mut tokio::sync::broadcast::Receiver<(Uuid, ServerMessage)>,
+  client: Uuid,
+) -> Vec<ResultDiff> {
+  let (to, msg) = tokio::time::timeout(std::time::Duration::from_secs(5), outgoing.recv())
+    .await
+    .expect("no diff was sent")
+    .unwrap();
+  assert_eq!(to, client);
+  match msg {
+    ServerMessage::Diff { id, diff } => {
+      assert_eq!(id, "w1");
+      diff
+    }
+    other => panic!("Expected a diff, got {:?}", other),
+  }
+}
+
+/// Row ID in a diff step
+fn step_id(step: &ResultDiff) -> String {
+  match step {
+    ResultDiff::Add { document, .. }
+    | ResultDiff::Move { document, .. }
+    | ResultDiff::Update { document, .. } => document["id"].as_str().unwrap().to_string(),
+    ResultDiff::Remove { id, .. } => id.to_string(),
+  }
+}
+
+#[tokio::test]
+async fn test_watch_sends_result_diffs() {
+  let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
+  backend.init_schema().await.unwrap();
+  let engine_pool = Arc::new(QueryEnginePool::new(1, backend.dialect()));
+  let subs = Arc::new(SubscriptionManager::new());
+  let handler = MessageHandler::new(backend.clone(), subs.clone(), engine_pool);
+  let client = Uuid::new_v4();
+  let mut outgoing = subs.subscribe_to_outgoing();
+
+  let insert = |name: &'static str, score: i64| {
+    let backend = backend.clone();
+    async move {
+      backend
+        .insert(
+          DEFAULT_PROJECT_ID,
+          "scores",
+          serde_json::json!({"name": name, "score": score}),
+        )
+        .await
+        .unwrap()
+    }
+  };
+  let a = insert("a", 1).await;
+  let b = insert("b", 5).await;
+
+  let watch = ClientMessage::Watch {
+    id: "w1".into(),
+    query: "db.table(\"scores\").orderBy(\"score\", \"desc\").limit(2).run()".into(),
+  };
+  // Watching needs protocol version 3
+  let resp = handler.handle(client, watch.clone()).await;
+  assert!(matches!(
+    resp,
+    ServerMessage::Error {
+      code: Some(ErrorCode::VersionUnsupported),
+      ..
+    }
+  ));
+
+  let client = Uuid::new_v4();
+  let handler = MessageHandler::new(
+    backend.clone(),
+    subs.clone(),
+    Arc::new(QueryEnginePool::new(1, backend.dialect())),
+  );
+  handler
+    .handle(
+      client,
+      ClientMessage::Hello {
+        id: "h1".into(),
+        version: PROTOCOL_VERSION,
+      },
+    )
+    .await;
+  let resp = handler.handle(client, watch).await;
+  assert!(matches!(resp, ServerMessage::Subscribed { .. }));
+
+  // The first diff adds the current result
+  let diff = next_diff(&mut outgoing, client).await;
+  assert!(diff
+    .iter()
+    .all(|step| matches!(step, ResultDiff::Add { .. })));
+  assert_eq!(
+    diff.iter().map(step_id).collect::<Vec<_>>(),
+    vec![b.id.to_string(), a.id.to_string()]
+  );
+
+  // A new second place pushes `a` past the limit
+  let c = insert("c", 3).await;
+  feed_last_change(&subs, &backend).await;
+  assert_eq!(
+    next_diff(&mut outgoing, client).await,
+    vec![
+      ResultDiff::Remove { index: 1, id: a.id },
+      ResultDiff::Add {
+        index: 1,
+        document: backend_row(&backend, c.id).await,
+      },
+    ]
+  );
+
+  // Moving `c` to the top reorders the result
+  backend
+    .update(
+      DEFAULT_PROJECT_ID,
+      "scores",
+      c.id,
+      serde_json::json!({"name": "c", "score": 9}),
+    )
+    .await
+    .unwrap();
+  feed_last_change(&subs, &backend).await;
+  let diff = next_diff(&mut outgoing, client).await;
+  assert!(matches!(diff[0], ResultDiff::Move { from: 1, to: 0, .. }));
+  assert_eq!(step_id(&diff[0]), c.id.to_string());
+  assert_eq!(diff.len(), 1);
+
+  // Unwatched collections and unsubscribed watches get nothing
+  handler
+    .handle(client, ClientMessage::Unsubscribe { id: "w1".into() })
+    .await;
+  insert("d", 7).await;
+  feed_last_change(&subs, &backend).await;
+  tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+  assert!(outgoing.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_watch_ids_and_limit() {
+  let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
+  backend.init_schema().await.unwrap();
+  let subs = Arc::new(SubscriptionManager::new());
+  let limiter = Arc::new(RateLimiter::new(LimitsSection {
+    max_watches_per_client: 2,
+    ..Default::default()
+  }));
+  let handler = MessageHandler::new(
+    backend.clone(),
+    subs.clone(),
+    Arc::new(QueryEnginePool::new(1, backend.dialect())),
+  )
+  .with_rate_limiter(limiter);
+  let client = Uuid::new_v4();
+  handler
+    .handle(
+      client,
+      ClientMessage::Hello {
+        id: "h1".into(),
+        version: PROTOCOL_VERSION,
+      },
+    )
+    .await;
+  let watch = |id: &str| ClientMessage::Watch {
+    id: id.into(),
+    query: "db.table(\"scores\").run()".into(),
+  };
+  let error_code = |resp: ServerMessage| match resp {
+    ServerMessage::Error { code, .. } => code,
+    other => panic!("expected an error, got {:?}", other),
+  };
+
+  let resp = handler
+    .handle(
+      client,
+      ClientMessage::Subscribe {
+        id: "s1".into(),
+        query: "db.table(\"scores\").changes()".into(),
+        read_your_writes: false,
+        ids: Vec::new(),
+      },
+    )
+    .await;
+  assert!(matches!(resp, ServerMessage::Subscribed { .. }));
+  assert!(matches!(
+    handler.handle(client, watch("w1")).await,
+    ServerMessage::Subscribed { .. }
+  ));
+
+  // IDs already held by a subscription or watch are refused
+  for id in ["s1", "w1"] {
+    assert_eq!(
+      error_code(handler.handle(client, watch(id)).await),
+      Some(ErrorCode::Conflict)
+    );
+  }
+  assert_eq!(subs.subscription_count(client), 2);
+
+  assert!(matches!(
+    handler.handle(client, watch("w2")).await,
+    ServerMessage::Subscribed { .. }
+  ));
+  assert_eq!(
+    error_code(handler.handle(client, watch("w3")).await),
+    Some(ErrorCode::RateLimited)
+  );
+
+  // Unsubscribing frees a slot
+  handler
+    .handle(client, ClientMessage::Unsubscribe { id: "w1".into() })
+    .await;
+  assert!(matches!(
+    handler.handle(client, watch("w3")).await,
+    ServerMessage::Subscribed { .. }
+  ));
+}
+
+/// Pass the newest change to the subscription manager
+async fn feed_last_change(subs: &SubscriptionManager, backend: &SqliteBackend) {
+  let changes = backend.list_changes(0, 100).await.unwrap();
+  let (tx, rx) = tokio::sync::broadcast::channel(16);
+  tx.send(changes.last().unwrap().clone()).unwrap();
+  drop(tx);
+  subs.process_changes(rx).await;
+}
+
+/// A document as a watched query returns it
+async fn backend_row(backend: &SqliteBackend, id: Uuid) -> serde_json::Value {
+  let doc = backend
+    .get(DEFAULT_PROJECT_ID, "scores", id)
+    .await
+    .unwrap()
+    .unwrap();
+  serde_json::to_value(doc).unwrap()
+}
+
+#[tokio::test]
+async fn test_subscribe_to_document_ids() {
+  let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
+  backend.init_schema().await.unwrap();
+  let engine_pool = Arc::new(QueryEnginePool::new(1, backend.dialect()));
+  let subs = Arc::new(SubscriptionManager::new());
+  let handler = MessageHandler::new(backend.clone(), subs.clone(), engine_pool);
+  let client = Uuid::new_v4();
+  let mut outgoing = subs.subscribe_to_outgoing();
+
+  let alice = backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      serde_json::json!({"name": "alice"}),
+    )
+    .await
+    .unwrap();
+  backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      serde_json::json!({"name": "bob"}),
+    )
+    .await
+    .unwrap();
+
+  let resp = handler
+    .handle(
+      client,
+      ClientMessage::Subscribe {
+        id: "s1".into(),
+        query: "db.table(\"users\").changes()".into(),
+        read_your_writes: false,
+        ids: vec![alice.id],
+      },
+    )
+    .await;
+  assert!(matches!(resp, ServerMessage::Subscribed { .. }));
+
+  let changes = backend.list_changes(0, 10).await.unwrap();
+  assert_eq!(changes.len(), 2);
+  let (tx, rx) = tokio::sync::broadcast::channel(16);
+  for change in changes {
+    tx.send(change).unwrap();
+  }
+  drop(tx);
+  subs.process_changes(rx).await;
+
+  let (to, msg) = outgoing.try_recv().unwrap();
+  assert_eq!(to, client);
+  let ServerMessage::Change {
+    change: ChangeEvent::Insert { new },
+    ..
+  } = msg
+  else {
+    panic!("Expected an insert change, got {:?}", msg);
+  };
+  assert_eq!(new.id, alice.id);
+  assert!(outgoing.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_subscribed_reports_compiled_filter() {
+  let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
+  backend.init_schema().await.unwrap();
+  let engine_pool = Arc::new(QueryEnginePool::new(1, backend.dialect()));
+  let subs = Arc::new(SubscriptionManager::new());
+  let handler = MessageHandler::new(backend.clone(), subs.clone(), engine_pool);
+  let client = Uuid::new_v4();
+
+  let cases = [
+    (
+      r#"db.table("users").filter(u => u.age > 21).changes()"#,
+      true,
+    ),
+    (
+      r#"db.table("users").filter(u => u.name.toLowerCase() === "al").changes()"#,
+      false,
+    ),
+    (r#"db.table("users").changes()"#, false),
+  ];
+  for (n, (query, expected)) in cases.into_iter().enumerate() {
+    let resp = handler
+      .handle(
+        client,
+        ClientMessage::Subscribe {
+          id: format!("s{}", n),
+          query: query.into(),
+          read_your_writes: false,
+          ids: Vec::new(),
+        },
+      )
+      .await;
+    assert!(
+      matches!(resp, ServerMessage::Subscribed { compiled, .. } if compiled == expected),
+      "{}: {:?}",
+      query,
+      resp
+    );
+  }
+}
+
+#[tokio::test]
+async fn test_stream_query_sends_chunks() {
+  let backend = Arc::new(SqliteBackend::in_memory().await.unwrap());
+  backend.init_schema().await.unwrap();
+  let total = STREAM_CHUNK_ROWS * 2 + 7;
+  for n in 0..total {
+    backend
+      .insert(DEFAULT_PROJECT_ID, "items", serde_json::json!({ "n": n }))
+      .await
+      .unwrap();
+  }
+  let engine_pool = Arc::new(QueryEnginePool::new(1, backend.dialect()));
+  let handler = MessageHandler::new(backend, Arc::new(SubscriptionManager::new()), engine_pool);
+  let query = |stream| ClientMessage::Query {
+    id: "q1".into(),
+    query: r#"db.table("items").orderBy("n").run()"#.into(),
+    cache: false,
+    cache_ttl: None,
+    params: Vec::new(),
+    stream,
+  };
+
+  let mut sent = Vec::new();
+  let finished = handler
+    .stream_query(Uuid::new_v4(), query(true), |msg| {
+      sent.push(msg);
+      async { true }
+    })
+    .await;
+  assert!(finished);
+  let (end, chunks) = sent.split_last().unwrap();
+  assert!(
+    matches!(end, ServerMessage::ResultEnd { id, rows } if id == "q1" && *rows == total as u64)
+  );
+  let sizes: Vec<usize> = chunks
+    .iter()
+    .map(|msg| match msg {
+      ServerMessage::ResultChunk { data, .. } => data.as_array().unwrap().len(),
+      other => panic!("Expected a chunk, got {:?}", other),
+    })
+    .collect();
+  assert_eq!(sizes, vec![STREAM_CHUNK_ROWS, STREAM_CHUNK_ROWS, 7]);
+  let ServerMessage::ResultChunk { data, .. } = &chunks[2] else {
+    unreachable!()
+  };
+  assert_eq!(data[6]["data"]["n"], total - 1);
+
+  // Stops reading once the client is gone
+  let mut calls = 0;
+  let finished = handler
+    .stream_query(Uuid::new_v4(), query(true), |_| {
+      calls += 1;
+      async { false }
+    })
+    .await;
+  assert!(!finished);
+  assert_eq!(calls, 1);
+
+  // Without the streaming transport the flag is refused
+  let resp = handler.handle(Uuid::new_v4(), query(true)).await;
+  assert!(matches!(
+    resp,
+    ServerMessage::Error {
+      code: Some(ErrorCode::BadRequest),
+      ..
+    }
+  ));
+}
@@ -2,9 +2,10 @@
 
 use chrono::Utc;
 use serde_json::json;
+use squirreldb::server::Encoding;
 use squirreldb::types::{
-  Change, ChangeEvent, ChangeOperation, ChangesOptions, ClientMessage, Document, FilterSpec,
-  OrderBySpec, OrderDirection, QuerySpec, ServerMessage,
+  Change, ChangeEvent, ChangeOperation, ChangesOptions, ClientMessage, Document, ErrorCode,
+  FilterSpec, OrderBySpec, OrderDirection, QuerySpec, ServerMessage,
 };
 use types::DEFAULT_PROJECT_ID;
 use uuid::Uuid;
@@ -18,6 +19,10 @@ fn test_client_message_query_serialization() {
   let msg = ClientMessage::Query {
     id: "query-1".into(),
     query: "db.table(\"users\").run()".into(),
+    cache: false,
+    cache_ttl: None,
+    params: Vec::new(),
+    stream: false,
   };
 
   let json = serde_json::to_string(&msg).unwrap();
@@ -31,6 +36,8 @@ fn test_client_message_subscribe_serialization() {
   let msg = ClientMessage::Subscribe {
     id: "sub-1".into(),
     query: "db.table(\"users\").changes()".into(),
+    read_your_writes: false,
+    ids: Vec::new(),
   };
 
   let json = serde_json::to_string(&msg).unwrap();
@@ -53,6 +60,9 @@ fn test_client_message_insert_serialization() {
     id: "ins-1".into(),
     collection: "users".into(),
     data: json!({"name": "Alice"}),
+    idempotency_key: None,
+    preserve_created_at: None,
+    no_touch: false,
   };
 
   let json = serde_json::to_string(&msg).unwrap();
@@ -69,6 +79,8 @@ fn test_client_message_update_serialization() {
     collection: "users".into(),
     document_id: doc_id,
     data: json!({"name": "Alice Updated"}),
+    no_touch: false,
+    apply_defaults: false,
   };
 
   let json = serde_json::to_string(&msg).unwrap();
@@ -116,16 +128,25 @@ fn test_client_message_id_accessor() {
     ClientMessage::Query {
       id: "q1".into(),
       query: "".into(),
+      cache: false,
+      cache_ttl: None,
+      params: Vec::new(),
+      stream: false,
     },
     ClientMessage::Subscribe {
       id: "s1".into(),
       query: "".into(),
+      read_your_writes: false,
+      ids: Vec::new(),
     },
     ClientMessage::Unsubscribe { id: "u1".into() },
     ClientMessage::Insert {
       id: "i1".into(),
       collection: "".into(),
       data: json!({}),
+      idempotency_key: None,
+      preserve_created_at: None,
+      no_touch: false,
     },
     ClientMessage::ListCollections { id: "l1".into() },
     ClientMessage::Ping { id: "p1".into() },
@@ -148,7 +169,7 @@ fn test_client_message_query_deserialization() {
   let msg: ClientMessage = serde_json::from_str(json).unwrap();
 
   match msg {
-    ClientMessage::Query { id, query } => {
+    ClientMessage::Query { id, query, .. } => {
       assert_eq!(id, "q1");
       assert!(query.contains("users"));
     }
@@ -156,13 +177,54 @@ fn test_client_message_query_deserialization() {
   }
 }
 
+#[test]
+fn test_client_message_query_cache_fields() {
+  let json =
+    r#"{"type":"query","id":"q1","query":"db.table(\"users\").run()","cache":true,"cache_ttl":30}"#;
+  let msg: ClientMessage = serde_json::from_str(json).unwrap();
+
+  match &msg {
+    ClientMessage::Query {
+      cache, cache_ttl, ..
+    } => {
+      assert!(*cache);
+      assert_eq!(*cache_ttl, Some(30));
+    }
+    _ => panic!("Expected Query message"),
+  }
+
+  // Uncached queries serialize without the cache fields
+  let msg = ClientMessage::Query {
+    id: "q2".into(),
+    query: "db.table(\"users\").run()".into(),
+    cache: false,
+    cache_ttl: None,
+    params: Vec::new(),
+    stream: false,
+  };
+  let json = serde_json::to_string(&msg).unwrap();
+  assert!(!json.contains("cache"));
+}
+
+#[test]
+fn test_client_message_authenticate_deserialization() {
+  let json = r#"{"type":"authenticate","id":"a1","token":"sqrl_abc"}"#;
+  let msg: ClientMessage = serde_json::from_str(json).unwrap();
+
+  assert_eq!(msg.id(), "a1");
+  match msg {
+    ClientMessage::Authenticate { token, .. } => assert_eq!(token, "sqrl_abc"),
+    _ => panic!("Expected Authenticate message"),
+  }
+}
+
 #[test]
 fn test_client_message_subscribe_deserialization() {
   let json = r#"{"type":"subscribe","id":"s1","query":"db.table(\"users\").changes()"}"#;
   let msg: ClientMessage = serde_json::from_str(json).unwrap();
 
   match msg {
-    ClientMessage::Subscribe { id, query } => {
+    ClientMessage::Subscribe { id, query, .. } => {
       assert_eq!(id, "s1");
       assert!(query.contains("changes"));
     }
@@ -180,9 +242,15 @@ fn test_client_message_insert_deserialization() {
       id,
       collection,
       data,
+      idempotency_key,
+      preserve_created_at,
+      no_touch,
     } => {
       assert_eq!(id, "i1");
       assert_eq!(collection, "users");
+      assert_eq!(idempotency_key, None);
+      assert_eq!(preserve_created_at, None);
+      assert!(!no_touch);
       assert_eq!(data["name"], "Alice");
       assert_eq!(data["age"], 30);
     }
@@ -190,6 +258,47 @@ fn test_client_message_insert_deserialization() {
   }
 }
 
+#[test]
+fn test_client_message_insert_idempotency_key() {
+  let json =
+    r#"{"type":"insert","id":"i1","collection":"users","data":{},"idempotency_key":"order-42"}"#;
+  let msg: ClientMessage = serde_json::from_str(json).unwrap();
+
+  match &msg {
+    ClientMessage::Insert {
+      idempotency_key, ..
+    } => assert_eq!(idempotency_key.as_deref(), Some("order-42")),
+    _ => panic!("Expected Insert message"),
+  }
+  let json = serde_json::to_string(&msg).unwrap();
+  assert!(json.contains("\"idempotency_key\":\"order-42\""));
+}
+
+#[test]
+fn test_client_message_timestamp_options() {
+  let json = r#"{"type":"insert","id":"i1","collection":"users","data":{},"preserve_created_at":"2020-01-02T03:04:05Z","no_touch":true}"#;
+  match serde_json::from_str::<ClientMessage>(json).unwrap() {
+    ClientMessage::Insert {
+      preserve_created_at,
+      no_touch,
+      ..
+    } => {
+      assert_eq!(
+        preserve_created_at.map(|t| t.to_rfc3339()),
+        Some("2020-01-02T03:04:05+00:00".to_string())
+      );
+      assert!(no_touch);
+    }
+    _ => panic!("Expected Insert message"),
+  }
+
+  let json = r#"{"type":"update","id":"u1","collection":"users","document_id":"550e8400-e29b-41d4-a716-446655440000","data":{},"no_touch":true}"#;
+  match serde_json::from_str::<ClientMessage>(json).unwrap() {
+    ClientMessage::Update { no_touch, .. } => assert!(no_touch),
+    _ => panic!("Expected Update message"),
+  }
+}
+
 // =============================================================================
 // ServerMessage Tests
 // =============================================================================
@@ -200,7 +309,7 @@ fn test_server_message_result_constructor() {
   let msg = ServerMessage::result("req-1", data.clone());
 
   match msg {
-    ServerMessage::Result { id, data: d } => {
+    ServerMessage::Result { id, data: d, .. } => {
       assert_eq!(id, "req-1");
       assert_eq!(d, data);
     }
@@ -213,9 +322,16 @@ fn test_server_message_error_constructor() {
   let msg = ServerMessage::error("req-1", "Something went wrong");
 
   match msg {
-    ServerMessage::Error { id, error } => {
+    ServerMessage::Error {
+      id,
+      error,
+      code,
+      trace_id,
+    } => {
       assert_eq!(id, "req-1");
       assert_eq!(error, "Something went wrong");
+      assert_eq!(code, None);
+      assert_eq!(trace_id, None);
     }
     _ => panic!("Expected Error message"),
   }
@@ -239,6 +355,82 @@ fn test_server_message_error_serialization() {
   assert!(json.contains("\"type\":\"error\""));
   assert!(json.contains("\"id\":\"e1\""));
   assert!(json.contains("\"error\":\"Test error\""));
+  assert!(!json.contains("trace_id"));
+  assert!(!json.contains("code"));
+}
+
+#[test]
+fn test_server_message_parse_error() {
+  // Valid JSON, unknown message type: the request ID is kept
+  let text = r#"{"type":"frobnicate","id":"req-7"}"#;
+  let err = serde_json::from_str::<ClientMessage>(text).unwrap_err();
+  match ServerMessage::parse_error(text, &err) {
+    ServerMessage::Error {
+      id, error, code, ..
+    } => {
+      assert_eq!(id, "req-7");
+      assert_eq!(code, Some(ErrorCode::InvalidMessage));
+      assert!(error.starts_with("Invalid message: "));
+      assert!(error.contains("frobnicate"));
+    }
+    other => panic!("Expected Error message, got {:?}", other),
+  }
+
+  // Missing field
+  let text = r#"{"type":"insert","id":"req-8","collection":"users"}"#;
+  let err = serde_json::from_str::<ClientMessage>(text).unwrap_err();
+  let msg = ServerMessage::parse_error(text, &err);
+  let json = serde_json::to_value(&msg).unwrap();
+  assert_eq!(json["id"], "req-8");
+  assert_eq!(json["code"], "invalid_message");
+  assert!(json["error"].as_str().unwrap().contains("data"));
+
+  // Not JSON at all: no ID to recover
+  let text = "{not json";
+  let err = serde_json::from_str::<ClientMessage>(text).unwrap_err();
+  assert!(matches!(
+    ServerMessage::parse_error(text, &err),
+    ServerMessage::Error { id, code: Some(ErrorCode::InvalidJson), .. } if id == "0"
+  ));
+}
+
+#[test]
+fn test_error_code_serialization() {
+  let cases = [
+    (ErrorCode::BadRequest, "bad_request"),
+    (ErrorCode::InvalidQuery, "invalid_query"),
+    (ErrorCode::NotFound, "not_found"),
+    (ErrorCode::Unauthorized, "unauthorized"),
+    (ErrorCode::Forbidden, "forbidden"),
+    (ErrorCode::Conflict, "conflict"),
+    (ErrorCode::PayloadTooLarge, "payload_too_large"),
+    (ErrorCode::RateLimited, "rate_limited"),
+    (ErrorCode::Timeout, "timeout"),
+    (ErrorCode::Unavailable, "unavailable"),
+    (ErrorCode::VersionUnsupported, "version_unsupported"),
+    (ErrorCode::Internal, "internal"),
+  ];
+  for (code, name) in cases {
+    let msg = ServerMessage::error("e1", "failed").with_code(code);
+    let json = serde_json::to_value(&msg).unwrap();
+    assert_eq!(json["code"], name);
+    let parsed: ServerMessage = serde_json::from_value(json).unwrap();
+    assert!(matches!(parsed, ServerMessage::Error { code: Some(c), .. } if c == code));
+  }
+}
+
+#[test]
+fn test_server_message_error_trace_id() {
+  let msg = ServerMessage::error("e1", "Test error").with_trace_id("abc123");
+  let json = serde_json::to_string(&msg).unwrap();
+  assert!(json.contains("\"trace_id\":\"abc123\""));
+
+  let parsed: ServerMessage = serde_json::from_str(&json).unwrap();
+  assert!(matches!(parsed, ServerMessage::Error { trace_id: Some(t), .. } if t == "abc123"));
+
+  // Only errors carry a trace ID
+  let pong = ServerMessage::pong("p1").with_trace_id("abc123");
+  assert!(!serde_json::to_string(&pong).unwrap().contains("trace_id"));
 }
 
 #[test]
@@ -250,6 +442,7 @@ fn test_server_message_change_serialization() {
     data: json!({"name": "Alice"}),
     created_at: Utc::now(),
     updated_at: Utc::now(),
+    deleted_at: None,
   };
 
   let change_event = ChangeEvent::Insert { new: doc };
@@ -257,6 +450,7 @@ fn test_server_message_change_serialization() {
   let msg = ServerMessage::Change {
     id: "sub-1".into(),
     change: change_event,
+    change_id: None,
   };
 
   let json = serde_json::to_string(&msg).unwrap();
@@ -276,10 +470,24 @@ fn test_server_message_pong_serialization() {
 
 #[test]
 fn test_server_message_subscribed_serialization() {
-  let msg = ServerMessage::Subscribed { id: "sub-1".into() };
+  let msg = ServerMessage::Subscribed {
+    id: "sub-1".into(),
+    compiled: true,
+  };
   let json = serde_json::to_string(&msg).unwrap();
 
   assert!(json.contains("\"type\":\"subscribed\""));
+  assert!(json.contains("\"compiled\":true"));
+
+  // Servers before `compiled` was added
+  let msg: ServerMessage = serde_json::from_str(r#"{"type":"subscribed","id":"sub-1"}"#).unwrap();
+  assert!(matches!(
+    msg,
+    ServerMessage::Subscribed {
+      compiled: false,
+      ..
+    }
+  ));
 }
 
 #[test]
@@ -396,6 +604,7 @@ fn test_change_event_initial() {
     data: json!({"name": "Alice"}),
     created_at: Utc::now(),
     updated_at: Utc::now(),
+    deleted_at: None,
   };
 
   let event = ChangeEvent::Initial {
@@ -416,6 +625,7 @@ fn test_change_event_insert() {
     data: json!({"name": "Bob"}),
     created_at: Utc::now(),
     updated_at: Utc::now(),
+    deleted_at: None,
   };
 
   let event = ChangeEvent::Insert { new: doc };
@@ -434,6 +644,7 @@ fn test_change_event_update() {
     data: json!({"name": "Charlie", "age": 31}),
     created_at: Utc::now(),
     updated_at: Utc::now(),
+    deleted_at: None,
   };
 
   let event = ChangeEvent::Update {
@@ -456,6 +667,7 @@ fn test_change_event_delete() {
     data: json!({"name": "David"}),
     created_at: Utc::now(),
     updated_at: Utc::now(),
+    deleted_at: None,
   };
 
   let event = ChangeEvent::Delete { old: doc };
@@ -478,6 +690,7 @@ fn test_document_serialization() {
     data: json!({"name": "Alice", "age": 30}),
     created_at: chrono::Utc::now(),
     updated_at: chrono::Utc::now(),
+    deleted_at: None,
   };
 
   let json = serde_json::to_string(&doc).unwrap();
@@ -514,16 +727,19 @@ fn test_query_spec_defaults() {
     table: "users".into(),
     filter: None,
     map: None,
-    order_by: None,
+    order_by: Vec::new(),
     limit: None,
     offset: None,
     changes: None,
+    projection: None,
+    with_deleted: false,
+    params: Vec::new(),
   };
 
   assert_eq!(spec.table, "users");
   assert!(spec.filter.is_none());
   assert!(spec.map.is_none());
-  assert!(spec.order_by.is_none());
+  assert!(spec.order_by.is_empty());
   assert!(spec.limit.is_none());
   assert!(spec.offset.is_none());
   assert!(spec.changes.is_none());
@@ -537,23 +753,28 @@ fn test_query_spec_with_all_fields() {
     filter: Some(FilterSpec {
       js_code: "u => u.active".into(),
       compiled_sql: Some("active = true".into()),
+      params: Vec::new(),
     }),
     map: Some("u => u.name".into()),
-    order_by: Some(OrderBySpec {
+    order_by: vec![OrderBySpec {
       field: "name".into(),
       direction: OrderDirection::Asc,
-    }),
+      near: None,
+    }],
     limit: Some(10),
     offset: Some(5),
     changes: Some(ChangesOptions {
       include_initial: true,
     }),
+    projection: Some(vec!["name".into(), "email".into()]),
+    with_deleted: false,
+    params: Vec::new(),
   };
 
   assert_eq!(spec.table, "users");
   assert!(spec.filter.is_some());
   assert!(spec.map.is_some());
-  assert!(spec.order_by.is_some());
+  assert_eq!(spec.order_by.len(), 1);
   assert_eq!(spec.limit, Some(10));
   assert!(spec.changes.is_some());
 }
@@ -573,6 +794,7 @@ fn test_order_by_serialization() {
   let order = OrderBySpec {
     field: "created_at".into(),
     direction: OrderDirection::Desc,
+    near: None,
   };
 
   let json = serde_json::to_string(&order).unwrap();
@@ -625,22 +847,33 @@ fn test_client_message_roundtrip_all_types() {
     ClientMessage::Query {
       id: "1".into(),
       query: "test".into(),
+      cache: false,
+      cache_ttl: None,
+      params: Vec::new(),
+      stream: false,
     },
     ClientMessage::Subscribe {
       id: "2".into(),
       query: "changes".into(),
+      read_your_writes: false,
+      ids: Vec::new(),
     },
     ClientMessage::Unsubscribe { id: "3".into() },
     ClientMessage::Insert {
       id: "4".into(),
       collection: "col".into(),
       data: json!({"x": 1}),
+      idempotency_key: None,
+      preserve_created_at: None,
+      no_touch: false,
     },
     ClientMessage::Update {
       id: "5".into(),
       collection: "col".into(),
       document_id: Uuid::new_v4(),
       data: json!({"x": 2}),
+      no_touch: false,
+      apply_defaults: false,
     },
     ClientMessage::Delete {
       id: "6".into(),
@@ -665,7 +898,7 @@ fn test_server_message_roundtrip() {
   let parsed: ServerMessage = serde_json::from_str(&json).unwrap();
 
   match parsed {
-    ServerMessage::Result { id, data } => {
+    ServerMessage::Result { id, data, .. } => {
       assert_eq!(id, "r1");
       assert_eq!(data["data"], json!([1, 2, 3]));
     }
@@ -682,6 +915,10 @@ fn test_message_with_empty_id() {
   let msg = ClientMessage::Query {
     id: "".into(),
     query: "test".into(),
+    cache: false,
+    cache_ttl: None,
+    params: Vec::new(),
+    stream: false,
   };
 
   let json = serde_json::to_string(&msg).unwrap();
@@ -694,6 +931,10 @@ fn test_message_with_special_characters_in_id() {
   let msg = ClientMessage::Query {
     id: "id-with-special-chars_123.456".into(),
     query: "test".into(),
+    cache: false,
+    cache_ttl: None,
+    params: Vec::new(),
+    stream: false,
   };
 
   let json = serde_json::to_string(&msg).unwrap();
@@ -706,6 +947,10 @@ fn test_message_with_unicode_in_query() {
   let msg = ClientMessage::Query {
     id: "1".into(),
     query: "db.table(\"日本語\").run()".into(),
+    cache: false,
+    cache_ttl: None,
+    params: Vec::new(),
+    stream: false,
   };
 
   let json = serde_json::to_string(&msg).unwrap();
@@ -733,6 +978,7 @@ fn test_document_with_large_data() {
     data: serde_json::Value::Object(obj),
     created_at: chrono::Utc::now(),
     updated_at: chrono::Utc::now(),
+    deleted_at: None,
   };
 
   let json = serde_json::to_string(&doc).unwrap();
@@ -741,3 +987,114 @@ fn test_document_with_large_data() {
   assert_eq!(parsed.data["field_0"], "value_0");
   assert_eq!(parsed.data["field_999"], "value_999");
 }
+
+// =============================================================================
+// MessagePack Encoding Tests
+// =============================================================================
+
+fn all_client_messages() -> Vec<ClientMessage> {
+  let doc_id = Uuid::new_v4();
+  [
+    json!({"type": "authenticate", "id": "1", "token": "sqrl_abc"}),
+    json!({"type": "selectproject", "id": "2", "project_id": Uuid::new_v4()}),
+    json!({"type": "query", "id": "3", "query": "db.table(\"users\").run()"}),
+    json!({"type": "query", "id": "4", "query": "db.table(\"users\").run()", "cache": true, "cache_ttl": 30}),
+    json!({"type": "query", "id": "5", "query": {
+      "table": "users",
+      "filter": {"$and": [{"age": {"$gt": 21}}, {"status": "active"}]},
+      "sort": [{"field": "name", "direction": "desc"}],
+      "limit": 10
+    }}),
+    json!({"type": "subscribe", "id": "6", "query": {"table": "users", "changes": {"includeInitial": true}}}),
+    json!({"type": "unsubscribe", "id": "6"}),
+    json!({"type": "insert", "id": "7", "collection": "users", "data": {"name": "Alice", "tags": ["a", 1, null], "score": 1.5}}),
+    json!({"type": "update", "id": "8", "collection": "users", "document_id": doc_id, "data": {"name": "Bob"}}),
+    json!({"type": "delete", "id": "9", "collection": "users", "document_id": doc_id}),
+    json!({"type": "listcollections", "id": "10"}),
+    json!({"type": "listprojects", "id": "11"}),
+    json!({"type": "ping", "id": "12"}),
+  ]
+  .into_iter()
+  .map(|v| serde_json::from_value(v).unwrap())
+  .collect()
+}
+
+fn all_server_messages() -> Vec<ServerMessage> {
+  let doc = Document {
+    id: Uuid::new_v4(),
+    project_id: DEFAULT_PROJECT_ID,
+    collection: "users".into(),
+    data: json!({"name": "Alice", "nested": {"n": -3, "big": u64::MAX}}),
+    created_at: Utc::now(),
+    updated_at: Utc::now(),
+    deleted_at: None,
+  };
+  vec![
+    ServerMessage::result("1", json!([{"a": 1}, {"b": [true, null]}])),
+    ServerMessage::change(
+      "2",
+      ChangeEvent::Initial {
+        document: doc.clone(),
+      },
+    ),
+    ServerMessage::change("2", ChangeEvent::Insert { new: doc.clone() }),
+    ServerMessage::change(
+      "2",
+      ChangeEvent::Update {
+        old: json!({"name": "Al"}),
+        new: doc.clone(),
+      },
+    ),
+    ServerMessage::change("2", ChangeEvent::Delete { old: doc }),
+    ServerMessage::subscribed("3", false),
+    ServerMessage::Unsubscribed { id: "3".into() },
+    ServerMessage::ProjectSelected {
+      id: "4".into(),
+      project_id: Uuid::new_v4(),
+    },
+    ServerMessage::error("5", "Something went wrong"),
+    ServerMessage::pong("6"),
+  ]
+}
+
+#[test]
+fn test_msgpack_roundtrip_client_messages() {
+  for msg in all_client_messages() {
+    let bytes = Encoding::MessagePack.encode(&msg).unwrap();
+    let decoded: ClientMessage = Encoding::MessagePack.decode(&bytes).unwrap();
+    assert_eq!(
+      serde_json::to_value(&decoded).unwrap(),
+      serde_json::to_value(&msg).unwrap()
+    );
+  }
+}
+
+#[test]
+fn test_msgpack_roundtrip_server_messages() {
+  for msg in all_server_messages() {
+    let bytes = Encoding::MessagePack.encode(&msg).unwrap();
+    let decoded: ServerMessage = Encoding::MessagePack.decode(&bytes).unwrap();
+    assert_eq!(
+      serde_json::to_value(&decoded).unwrap(),
+      serde_json::to_value(&msg).unwrap()
+    );
+  }
+}
+
+#[test]
+fn test_msgpack_smaller_than_json() {
+  let rows: Vec<_> = (0..100)
+    .map(|i| json!({"id": i, "name": format!("user{}", i), "active": i % 2 == 0, "score": i * 10}))
+    .collect();
+  let msg = ServerMessage::result("1", json!(rows));
+  let msgpack = Encoding::MessagePack.encode(&msg).unwrap();
+  let json = Encoding::Json.encode(&msg).unwrap();
+  assert!(msgpack.len() < json.len());
+}
+
+#[test]
+fn test_msgpack_rejects_garbage() {
+  assert!(Encoding::MessagePack
+    .decode::<ClientMessage>(&[0xc1, 0x00])
+    .is_err());
+}
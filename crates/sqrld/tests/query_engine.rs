@@ -25,6 +25,25 @@ fn test_parse_query_with_filter() {
   assert!(filter.compiled_sql.is_some());
 }
 
+#[test]
+fn test_parse_query_rejects_deeply_nested_filter() {
+  let engine = QueryEngine::new(SqlDialect::Postgres).with_max_filter_depth(4);
+  assert!(engine
+    .parse_query(r#"db.table("users").filter(doc => ((doc.a > 1 || (doc.b < 2)))).run()"#)
+    .is_ok());
+
+  let nested = format!(
+    r#"db.table("users").filter(doc => {}doc.a > 1{}).run()"#,
+    "(".repeat(50),
+    ")".repeat(50)
+  );
+  let err = engine.parse_query(&nested).unwrap_err();
+  assert_eq!(err.to_string(), "Filter is nested more than 4 levels deep");
+  assert!(QueryEngine::new(SqlDialect::Postgres)
+    .parse_query(&nested)
+    .is_ok());
+}
+
 #[test]
 fn test_parse_query_with_map() {
   let engine = QueryEngine::new(SqlDialect::Postgres);
@@ -53,11 +72,41 @@ fn test_parse_query_with_order_by() {
     .parse_query(r#"db.table("posts").orderBy("created_at", "desc").run()"#)
     .unwrap();
   assert_eq!(spec.table, "posts");
-  let order = spec.order_by.unwrap();
+  let order = &spec.order_by[0];
   assert_eq!(order.field, "created_at");
   assert_eq!(order.direction, squirreldb::types::OrderDirection::Desc);
 }
 
+#[test]
+fn test_parse_query_with_near() {
+  let engine = QueryEngine::new(SqlDialect::Postgres);
+  let spec = engine
+    .parse_query(
+      r#"db.table("users").filter(u => u.active === true).near("location", 40.7, -74.0, 5000).orderByDistance("location", 40.7, -74.0).run()"#,
+    )
+    .unwrap();
+  let sql = spec.filter.unwrap().compiled_sql.unwrap();
+  assert!(sql.starts_with("(") && sql.contains(") AND ("));
+  assert!(sql.ends_with("<= 5000"));
+  let order = &spec.order_by[0];
+  assert_eq!(order.field, "location");
+  assert_eq!(
+    order.near,
+    Some(squirreldb::types::GeoPoint {
+      lat: 40.7,
+      lng: -74.0
+    })
+  );
+
+  // Missing distance or out-of-range coordinates are rejected
+  assert!(engine
+    .parse_query(r#"db.table("users").near("location", 40.7, -74.0).run()"#)
+    .is_err());
+  assert!(engine
+    .parse_query(r#"db.table("users").near("location", 140.7, -74.0, 10).run()"#)
+    .is_err());
+}
+
 #[test]
 fn test_parse_query_with_changes() {
   let engine = QueryEngine::new(SqlDialect::Postgres);
@@ -87,7 +136,7 @@ fn test_parse_complex_query() {
   assert_eq!(spec.table, "orders");
   assert!(spec.filter.is_some());
   assert_eq!(spec.limit, Some(50));
-  let order = spec.order_by.unwrap();
+  let order = &spec.order_by[0];
   assert_eq!(order.field, "created_at");
 }
 
@@ -97,3 +146,350 @@ fn test_parse_invalid_query_missing_table() {
   let result = engine.parse_query("db.run()");
   assert!(result.is_err());
 }
+
+#[tokio::test]
+async fn test_execute_select_with_js_filter() {
+  use squirreldb::db::{DatabaseBackend, SqliteBackend};
+  use squirreldb::query::QueryEnginePool;
+  use types::DEFAULT_PROJECT_ID;
+
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  for (name, email) in [("Alice", "alice@example.com"), ("Bob", "bob@example.com")] {
+    backend
+      .insert(
+        DEFAULT_PROJECT_ID,
+        "users",
+        serde_json::json!({"name": name, "email": email, "age": 30}),
+      )
+      .await
+      .unwrap();
+  }
+
+  // The filter can't be compiled to SQL, so it needs the whole document
+  // before the projection is applied
+  let pool = QueryEnginePool::new(1, backend.dialect());
+  let result = pool
+    .execute(
+      r#"db.table("users").filter(u => u.name.toLowerCase() === "alice").select("name", "age * 12 as age_months", "concat(name, ' <', email, '>') as contact")"#,
+      DEFAULT_PROJECT_ID,
+      &backend,
+    )
+    .await
+    .unwrap();
+
+  let docs = result.as_array().unwrap();
+  assert_eq!(docs.len(), 1);
+  assert_eq!(
+    docs[0]["data"],
+    serde_json::json!({"name": "Alice", "age_months": 360, "contact": "Alice <alice@example.com>"})
+  );
+}
+
+#[tokio::test]
+async fn test_query_params() {
+  use serde_json::json;
+  use squirreldb::db::{DatabaseBackend, SqliteBackend};
+  use squirreldb::query::QueryEnginePool;
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  for (name, age, admin) in [
+    ("Alice", 30, true),
+    ("Bob", 25, false),
+    ("O'Brien", 41, false),
+  ] {
+    backend
+      .insert(
+        DEFAULT_PROJECT_ID,
+        "users",
+        json!({"name": name, "age": age, "admin": admin}),
+      )
+      .await
+      .unwrap();
+  }
+  let pool = QueryEnginePool::new(1, backend.dialect());
+  let names = |data: serde_json::Value| -> Vec<String> {
+    let mut names: Vec<String> = data
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|d| d["data"]["name"].as_str().unwrap().to_string())
+      .collect();
+    names.sort();
+    names
+  };
+
+  // Compiled to SQL: values are bound, not written into the statement
+  let query =
+    QueryInput::Script(r#"db.table("users").filter(u => u.name === $1 || u.age > $2)"#.to_string());
+  let result = pool
+    .run_with_params(
+      &query,
+      &[json!("O'Brien"), json!(28)],
+      DEFAULT_PROJECT_ID,
+      &backend,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(names(result.data), vec!["Alice", "O'Brien"]);
+
+  // Same query text, new values: not served from the result cache
+  let result = pool
+    .run_with_params(
+      &query,
+      &[json!("Bob"), json!(100)],
+      DEFAULT_PROJECT_ID,
+      &backend,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(names(result.data), vec!["Bob"]);
+
+  let admins = QueryInput::Script(r#"db.table("users").filter(u => u.admin === $1)"#.to_string());
+  let result = pool
+    .run_with_params(&admins, &[json!(true)], DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  assert_eq!(names(result.data), vec!["Alice"]);
+
+  // JS filters see the parameters as variables
+  let query =
+    QueryInput::Script(r#"db.table("users").filter(u => u.name.toLowerCase() === $1)"#.to_string());
+  let result = pool
+    .run_with_params(&query, &[json!("bob")], DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  assert_eq!(names(result.data), vec!["Bob"]);
+
+  // Missing and non-scalar parameters are rejected
+  let query = QueryInput::Script(r#"db.table("users").filter(u => u.age > $2)"#.to_string());
+  let err = pool
+    .run_with_params(&query, &[json!(1)], DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("$2 is missing"));
+  let err = pool
+    .run_with_params(
+      &query,
+      &[json!(1), json!([1])],
+      DEFAULT_PROJECT_ID,
+      &backend,
+      None,
+    )
+    .await
+    .unwrap_err();
+  assert!(err
+    .to_string()
+    .contains("must be a string, number or boolean"));
+}
+
+#[tokio::test]
+async fn test_max_result_rows_truncates_unlimited_queries() {
+  use squirreldb::db::{DatabaseBackend, SqliteBackend};
+  use squirreldb::query::QueryEnginePool;
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  for i in 0..5 {
+    backend
+      .insert(DEFAULT_PROJECT_ID, "items", serde_json::json!({"n": i}))
+      .await
+      .unwrap();
+  }
+
+  let unlimited = QueryInput::Script(r#"db.table("items").run()"#.to_string());
+  let limited = QueryInput::Script(r#"db.table("items").limit(4).run()"#.to_string());
+
+  // No limit: cut at the cap and flagged
+  let pool = QueryEnginePool::new(1, backend.dialect()).with_max_result_rows(3);
+  let result = pool
+    .run(&unlimited, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  assert!(result.truncated);
+  assert_eq!(result.data.as_array().unwrap().len(), 3);
+
+  // An explicit limit is honored even above the cap
+  let result = pool
+    .run(&limited, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  assert!(!result.truncated);
+  assert_eq!(result.data.as_array().unwrap().len(), 4);
+
+  // Exactly at the cap is not truncated
+  let pool = QueryEnginePool::new(1, backend.dialect()).with_max_result_rows(5);
+  let result = pool
+    .run(&unlimited, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  assert!(!result.truncated);
+  assert_eq!(result.data.as_array().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn test_js_filter_applies_limits_to_matches() {
+  use futures_util::StreamExt;
+  use squirreldb::db::{DatabaseBackend, SqliteBackend};
+  use squirreldb::query::QueryEnginePool;
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  for n in 0..10 {
+    let tag = if n >= 6 { "Late" } else { "Early" };
+    backend
+      .insert(
+        DEFAULT_PROJECT_ID,
+        "items",
+        serde_json::json!({"n": n, "tag": tag}),
+      )
+      .await
+      .unwrap();
+  }
+  let ns = |data: &serde_json::Value| -> Vec<i64> {
+    data
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|d| d["data"]["n"].as_i64().unwrap())
+      .collect()
+  };
+
+  // The matches all sit past the cap, so they must be found after it
+  let pool = QueryEnginePool::new(1, backend.dialect()).with_max_result_rows(3);
+  let all = QueryInput::Script(
+    r#"db.table("items").filter(i => i.tag.toLowerCase() === "late").orderBy("n").run()"#
+      .to_string(),
+  );
+  let result = pool
+    .run(&all, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  assert!(result.truncated);
+  assert_eq!(ns(&result.data), vec![6, 7, 8]);
+
+  // Offset and limit count matching rows only
+  let page = QueryInput::Script(
+    r#"db.table("items").filter(i => i.tag.toLowerCase() === "late").orderBy("n").skip(1).limit(2).run()"#
+      .to_string(),
+  );
+  let result = pool
+    .run(&page, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  assert!(!result.truncated);
+  assert_eq!(ns(&result.data), vec![7, 8]);
+
+  let mut stream = pool
+    .stream_with_params(&page, &[], DEFAULT_PROJECT_ID, &backend, 2)
+    .await
+    .unwrap();
+  let mut streamed = Vec::new();
+  while let Some(batch) = stream.next().await {
+    streamed.extend(ns(&batch.unwrap()));
+  }
+  assert_eq!(streamed, vec![7, 8]);
+}
+
+#[tokio::test]
+async fn test_query_cost_limit() {
+  use squirreldb::db::{DatabaseBackend, SqliteBackend};
+  use squirreldb::query::QueryEnginePool;
+  use squirreldb::server::QueryCostAction;
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  for n in 0..20 {
+    backend
+      .insert(DEFAULT_PROJECT_ID, "items", serde_json::json!({ "n": n }))
+      .await
+      .unwrap();
+  }
+  let filtered =
+    QueryInput::Script(r#"db.table("items").filter(doc => doc.n > 15).limit(5).run()"#.to_string());
+  let limited = QueryInput::Script(r#"db.table("items").limit(5).run()"#.to_string());
+
+  // A filter without an index on the field reads the whole collection
+  let pool =
+    QueryEnginePool::new(1, backend.dialect()).with_max_query_cost(10.0, QueryCostAction::Reject);
+  let err = pool
+    .run(&filtered, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap_err()
+    .to_string();
+  assert!(err.contains("estimated cost of 20"), "{}", err);
+  assert!(err.contains("an index on 'n'"), "{}", err);
+
+  // Reading without a filter or sort stops at the limit
+  assert!(pool
+    .run(&limited, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .is_ok());
+
+  // Warn only logs
+  let pool =
+    QueryEnginePool::new(1, backend.dialect()).with_max_query_cost(10.0, QueryCostAction::Warn);
+  let result = pool
+    .run(&filtered, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  assert_eq!(result.data.as_array().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_near_filters_and_sorts_by_distance() {
+  use squirreldb::db::{DatabaseBackend, SqliteBackend};
+  use squirreldb::query::QueryEnginePool;
+  use types::{QueryInput, DEFAULT_PROJECT_ID};
+
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  for (name, lat, lng) in [
+    ("brooklyn", 40.6782, -73.9442),
+    ("midtown", 40.7549, -73.9840),
+    ("philadelphia", 39.9526, -75.1652),
+    ("downtown", 40.7075, -74.0113),
+  ] {
+    backend
+      .insert(
+        DEFAULT_PROJECT_ID,
+        "places",
+        serde_json::json!({"name": name, "location": {"lat": lat, "lng": lng}}),
+      )
+      .await
+      .unwrap();
+  }
+  backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "places",
+      serde_json::json!({"name": "nowhere"}),
+    )
+    .await
+    .unwrap();
+
+  let pool = QueryEnginePool::new(1, backend.dialect());
+  let query = QueryInput::Script(
+    r#"db.table("places").near("location", 40.7, -74.0, 10000).orderByDistance("location", 40.7, -74.0).run()"#
+      .to_string(),
+  );
+  let result = pool
+    .run(&query, DEFAULT_PROJECT_ID, &backend, None)
+    .await
+    .unwrap();
+  let names: Vec<&str> = result
+    .data
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|d| d["data"]["name"].as_str().unwrap())
+    .collect();
+  assert_eq!(names, vec!["downtown", "brooklyn", "midtown"]);
+}
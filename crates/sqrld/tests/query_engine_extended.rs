@@ -1,7 +1,8 @@
 //! Extended query engine tests - parsing, compilation, and edge cases
 
 use squirreldb::db::SqlDialect;
-use squirreldb::query::QueryEngine;
+use squirreldb::query::{FieldType, QueryEngine, ResultSchema};
+use types::{Document, DEFAULT_PROJECT_ID};
 
 // =============================================================================
 // Basic Query Parsing
@@ -13,7 +14,7 @@ fn test_parse_simple_run() {
   let spec = engine.parse_query("db.table(\"users\").run()").unwrap();
   assert_eq!(spec.table, "users");
   assert!(spec.filter.is_none());
-  assert!(spec.order_by.is_none());
+  assert!(spec.order_by.is_empty());
   assert!(spec.limit.is_none());
 }
 
@@ -130,8 +131,8 @@ fn test_parse_order_by_single_field() {
     .parse_query("db.table(\"users\").orderBy(\"name\")")
     .unwrap();
 
-  assert!(spec.order_by.is_some());
-  let order = spec.order_by.unwrap();
+  assert_eq!(spec.order_by.len(), 1);
+  let order = &spec.order_by[0];
   assert_eq!(order.field, "name");
 }
 
@@ -142,12 +143,37 @@ fn test_parse_order_by_asc() {
     .parse_query("db.table(\"users\").orderBy(\"name\", \"asc\")")
     .unwrap();
 
-  assert!(spec.order_by.is_some());
-  let order = spec.order_by.unwrap();
+  assert_eq!(spec.order_by.len(), 1);
+  let order = &spec.order_by[0];
   assert_eq!(order.field, "name");
   assert_eq!(order.direction, squirreldb::types::OrderDirection::Asc);
 }
 
+#[test]
+fn test_parse_order_by_multiple_fields() {
+  let engine = QueryEngine::new(SqlDialect::Sqlite);
+  let spec = engine
+    .parse_query("db.table(\"users\").orderBy(\"status\").orderBy(\"created_at\", \"desc\")")
+    .unwrap();
+
+  let fields: Vec<_> = spec
+    .order_by
+    .iter()
+    .map(|o| (o.field.as_str(), o.direction))
+    .collect();
+  assert_eq!(
+    fields,
+    vec![
+      ("status", squirreldb::types::OrderDirection::Asc),
+      ("created_at", squirreldb::types::OrderDirection::Desc),
+    ]
+  );
+
+  assert!(engine
+    .parse_query("db.table(\"users\").orderBy(\"status\").orderBy(\"x') --\")")
+    .is_err());
+}
+
 #[test]
 fn test_parse_order_by_desc() {
   let engine = QueryEngine::new(SqlDialect::Sqlite);
@@ -155,8 +181,8 @@ fn test_parse_order_by_desc() {
     .parse_query("db.table(\"users\").orderBy(\"created_at\", \"desc\")")
     .unwrap();
 
-  assert!(spec.order_by.is_some());
-  let order = spec.order_by.unwrap();
+  assert_eq!(spec.order_by.len(), 1);
+  let order = &spec.order_by[0];
   assert_eq!(order.field, "created_at");
   assert_eq!(order.direction, squirreldb::types::OrderDirection::Desc);
 }
@@ -214,7 +240,7 @@ fn test_parse_filter_order_limit() {
     .unwrap();
 
   assert!(spec.filter.is_some());
-  assert!(spec.order_by.is_some());
+  assert_eq!(spec.order_by.len(), 1);
   assert_eq!(spec.limit, Some(10));
 }
 
@@ -229,8 +255,8 @@ fn test_parse_all_operations() {
 
   assert_eq!(spec.table, "users");
   assert!(spec.filter.is_some());
-  assert!(spec.order_by.is_some());
-  assert_eq!(spec.order_by.as_ref().unwrap().field, "created_at");
+  assert_eq!(spec.order_by.len(), 1);
+  assert_eq!(spec.order_by[0].field, "created_at");
   assert_eq!(spec.limit, Some(20));
 }
 
@@ -294,6 +320,55 @@ fn test_parse_map_with_filter() {
   assert!(spec.map.is_some());
 }
 
+// =============================================================================
+// Field Projection
+// =============================================================================
+
+#[test]
+fn test_parse_select() {
+  let engine = QueryEngine::new(SqlDialect::Sqlite);
+  let spec = engine
+    .parse_query("db.table(\"users\").select(\"name\", \"email\").filter(u => u.age > 21)")
+    .unwrap();
+  assert_eq!(
+    spec.projection,
+    Some(vec!["name".to_string(), "email".to_string()])
+  );
+
+  // Arrays work too
+  let spec = engine
+    .parse_query("db.table(\"users\").select([\"address.city\"])")
+    .unwrap();
+  assert_eq!(spec.projection, Some(vec!["address.city".to_string()]));
+
+  let spec = engine.parse_query("db.table(\"users\")").unwrap();
+  assert!(spec.projection.is_none());
+}
+
+#[test]
+fn test_parse_select_rejects_invalid_fields() {
+  let engine = QueryEngine::new(SqlDialect::Sqlite);
+  assert!(engine
+    .parse_query("db.table(\"users\").select(\"name') || ('x\")")
+    .is_err());
+  assert!(engine.parse_query("db.table(\"users\").select()").is_err());
+  assert!(engine
+    .parse_query("db.table(\"users\").select(42)")
+    .is_err());
+}
+
+#[test]
+fn test_parse_with_deleted() {
+  let engine = QueryEngine::new(SqlDialect::Sqlite);
+  let spec = engine
+    .parse_query("db.table(\"orders\").withDeleted().run()")
+    .unwrap();
+  assert!(spec.with_deleted);
+
+  let spec = engine.parse_query("db.table(\"orders\").run()").unwrap();
+  assert!(!spec.with_deleted);
+}
+
 // =============================================================================
 // Error Cases
 // =============================================================================
@@ -513,3 +588,63 @@ fn test_parse_long_query() {
   let result = engine.parse_query(&query);
   let _ = result;
 }
+
+// =============================================================================
+// Result Schema Inference
+// =============================================================================
+
+fn doc(data: serde_json::Value) -> Document {
+  Document {
+    id: uuid::Uuid::new_v4(),
+    project_id: DEFAULT_PROJECT_ID,
+    collection: "users".to_string(),
+    data,
+    created_at: chrono::Utc::now(),
+    updated_at: chrono::Utc::now(),
+    deleted_at: None,
+  }
+}
+
+#[test]
+fn test_result_schema_infers_fields() {
+  let docs = vec![
+    doc(serde_json::json!({"name": "Alice", "age": 30, "score": 1.5})),
+    doc(serde_json::json!({"name": "Bob", "age": null, "tags": ["a"]})),
+    doc(serde_json::json!({"name": "Carol", "age": "unknown", "score": 2})),
+  ];
+  let schema = ResultSchema::infer(&docs);
+  assert_eq!(schema.sampled, 3);
+
+  let field = |name: &str| schema.fields.iter().find(|f| f.name == name).unwrap();
+  assert_eq!(field("name").types, vec![FieldType::String]);
+  assert!(!field("name").nullable);
+  assert_eq!(field("name").count, 3);
+
+  // Null and differently typed values
+  assert_eq!(
+    field("age").types,
+    vec![FieldType::Integer, FieldType::String]
+  );
+  assert!(field("age").nullable);
+  assert_eq!(field("age").count, 2);
+
+  // Fields some documents omit are nullable
+  assert_eq!(
+    field("score").types,
+    vec![FieldType::Number, FieldType::Integer]
+  );
+  assert!(field("score").nullable);
+  assert_eq!(field("tags").types, vec![FieldType::Array]);
+  assert_eq!(field("tags").count, 1);
+}
+
+#[test]
+fn test_result_schema_empty() {
+  let schema = ResultSchema::infer(&[]);
+  assert_eq!(schema.sampled, 0);
+  assert!(schema.fields.is_empty());
+  assert_eq!(
+    serde_json::to_value(&schema).unwrap(),
+    serde_json::json!({"sampled": 0, "fields": []})
+  );
+}
@@ -11,6 +11,7 @@ use squirreldb::db::sanitize::{
 };
 use squirreldb::db::{DatabaseBackend, SqlDialect, SqliteBackend};
 use squirreldb::query::QueryCompiler;
+use squirreldb::security::headers::SecurityHeadersLayer;
 use squirreldb::types::CompiledFilter;
 use types::DEFAULT_PROJECT_ID;
 
@@ -333,6 +334,86 @@ async fn test_backend_rejects_invalid_collection_names() {
   }
 }
 
+#[tokio::test]
+async fn test_backend_rejects_malicious_compiled_filter() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      serde_json::json!({"name": "Alice"}),
+    )
+    .await
+    .unwrap();
+
+  let malicious = vec![
+    "1=1; DROP TABLE documents",
+    "true -- ",
+    "true /* */",
+    "project_id IS NOT NULL",
+    "true) OR (true",
+    "EXISTS(SELECT 1 FROM api_tokens)",
+    "\"data\" IS NOT NULL",
+  ];
+  for filter in malicious {
+    let result = backend
+      .list(
+        DEFAULT_PROJECT_ID,
+        "users",
+        None,
+        Some(filter),
+        None,
+        None,
+        None,
+      )
+      .await;
+    assert!(result.is_err(), "Filter '{}' should be rejected", filter);
+  }
+  assert_eq!(
+    backend
+      .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
+      .await
+      .unwrap()
+      .len(),
+    1
+  );
+}
+
+#[tokio::test]
+async fn test_compiled_or_filter_stays_in_project() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let other_project = uuid::Uuid::new_v4();
+  for project_id in [DEFAULT_PROJECT_ID, other_project] {
+    backend
+      .insert(project_id, "users", serde_json::json!({"name": "Alice"}))
+      .await
+      .unwrap();
+  }
+
+  let compiler = QueryCompiler::new(SqlDialect::Sqlite);
+  let CompiledFilter::Sql(sql) =
+    compiler.compile_predicate(r#"doc => doc.name === "Bob" || doc.name === "Alice""#)
+  else {
+    panic!("Expected SQL compilation");
+  };
+  let docs = backend
+    .list(
+      DEFAULT_PROJECT_ID,
+      "users",
+      None,
+      Some(&sql),
+      None,
+      None,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(docs.len(), 1);
+  assert_eq!(docs[0].project_id, DEFAULT_PROJECT_ID);
+}
+
 // =============================================================================
 // Query Compiler Security Tests
 // =============================================================================
@@ -405,6 +486,53 @@ fn test_compiler_null_handling() {
   }
 }
 
+// =============================================================================
+// Security Headers Tests
+// =============================================================================
+
+async fn response_headers(layer: SecurityHeadersLayer) -> axum::http::HeaderMap {
+  use tower::ServiceExt;
+
+  let app = axum::Router::new()
+    .route("/", axum::routing::get(|| async { "ok" }))
+    .layer(layer);
+  let req = axum::http::Request::builder()
+    .uri("/")
+    .body(axum::body::Body::empty())
+    .unwrap();
+  app.oneshot(req).await.unwrap().headers().clone()
+}
+
+#[tokio::test]
+async fn test_security_headers_default() {
+  let headers = response_headers(SecurityHeadersLayer::default()).await;
+  assert_eq!(headers["x-frame-options"], "DENY");
+  assert!(headers["content-security-policy"]
+    .to_str()
+    .unwrap()
+    .contains("frame-ancestors 'none'"));
+  assert_eq!(headers["x-content-type-options"], "nosniff");
+}
+
+#[tokio::test]
+async fn test_security_headers_configurable() {
+  let layer = SecurityHeadersLayer::new("frame-ancestors https://portal.example.com", "").unwrap();
+  let headers = response_headers(layer).await;
+  assert!(headers.get("x-frame-options").is_none());
+  assert_eq!(
+    headers["content-security-policy"],
+    "frame-ancestors https://portal.example.com"
+  );
+  // Unrelated headers are unaffected
+  assert_eq!(headers["x-content-type-options"], "nosniff");
+
+  let headers = response_headers(SecurityHeadersLayer::new("", "SAMEORIGIN").unwrap()).await;
+  assert!(headers.get("content-security-policy").is_none());
+  assert_eq!(headers["x-frame-options"], "SAMEORIGIN");
+
+  assert!(SecurityHeadersLayer::new("bad\nvalue", "").is_err());
+}
+
 // =============================================================================
 // Edge Cases
 // =============================================================================
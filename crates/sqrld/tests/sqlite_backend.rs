@@ -1,6 +1,16 @@
 use serde_json::json;
-use squirreldb::db::{DatabaseBackend, SqlDialect, SqliteBackend};
-use types::DEFAULT_PROJECT_ID;
+use squirreldb::db::{
+  insert_idempotent, DatabaseBackend, DocumentWrite, FieldEncryption, IdempotencyClaim,
+  IdempotentInsert, ListenerHeartbeat, ProjectLimits, ReadOptions, SoftDeleteSettings, SqlDialect,
+  SqliteBackend, WriteOptions,
+};
+use squirreldb::query::QueryEnginePool;
+use squirreldb::storage::ObjectTags;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use types::{ChangeOperation, OrderBySpec, OrderDirection, QueryInput, DEFAULT_PROJECT_ID};
+use uuid::Uuid;
 
 #[tokio::test]
 async fn test_sqlite_backend_init_schema() {
@@ -104,13 +114,13 @@ async fn test_sqlite_backend_list() {
     .unwrap();
 
   let users = backend
-    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None)
+    .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
     .await
     .unwrap();
   assert_eq!(users.len(), 2);
 
   let posts = backend
-    .list(DEFAULT_PROJECT_ID, "posts", None, None, None, None)
+    .list(DEFAULT_PROJECT_ID, "posts", None, None, None, None, None)
     .await
     .unwrap();
   assert_eq!(posts.len(), 1);
@@ -129,12 +139,115 @@ async fn test_sqlite_backend_list_with_limit() {
   }
 
   let items = backend
-    .list(DEFAULT_PROJECT_ID, "items", None, None, Some(5), None)
+    .list(DEFAULT_PROJECT_ID, "items", None, None, None, Some(5), None)
     .await
     .unwrap();
   assert_eq!(items.len(), 5);
 }
 
+#[tokio::test]
+async fn test_sqlite_backend_list_pagination_is_stable() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  // Every document has the same sort key, so only the tie-breaker orders them
+  for i in 0..20 {
+    backend
+      .insert(DEFAULT_PROJECT_ID, "items", json!({"group": 1, "index": i}))
+      .await
+      .unwrap();
+  }
+
+  let order = OrderBySpec {
+    field: "group".to_string(),
+    direction: OrderDirection::Asc,
+    near: None,
+  };
+  for order in [None, Some(&order)] {
+    let mut ids = Vec::new();
+    for page in 0..4 {
+      let docs = backend
+        .list(
+          DEFAULT_PROJECT_ID,
+          "items",
+          None,
+          None,
+          order,
+          Some(5),
+          Some(page * 5),
+        )
+        .await
+        .unwrap();
+      ids.extend(docs.into_iter().map(|d| d.id));
+    }
+    let all: Vec<_> = backend
+      .list(DEFAULT_PROJECT_ID, "items", None, None, order, None, None)
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|d| d.id)
+      .collect();
+    assert_eq!(ids, all);
+  }
+}
+
+#[tokio::test]
+async fn test_sqlite_backend_list_multiple_order_fields() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  for (status, rank) in [("open", 1), ("closed", 3), ("open", 2), ("closed", 1)] {
+    backend
+      .insert(
+        DEFAULT_PROJECT_ID,
+        "tickets",
+        json!({"status": status, "rank": rank}),
+      )
+      .await
+      .unwrap();
+  }
+
+  let order = [
+    OrderBySpec {
+      field: "status".to_string(),
+      direction: OrderDirection::Asc,
+      near: None,
+    },
+    OrderBySpec {
+      field: "rank".to_string(),
+      direction: OrderDirection::Desc,
+      near: None,
+    },
+  ];
+  let docs = backend
+    .list_with_options(
+      DEFAULT_PROJECT_ID,
+      "tickets",
+      None,
+      None,
+      &[],
+      &order,
+      None,
+      None,
+      ReadOptions::default(),
+    )
+    .await
+    .unwrap();
+  let keys: Vec<_> = docs
+    .iter()
+    .map(|d| {
+      (
+        d.data["status"].as_str().unwrap(),
+        d.data["rank"].as_i64().unwrap(),
+      )
+    })
+    .collect();
+  assert_eq!(
+    keys,
+    vec![("closed", 3), ("closed", 1), ("open", 2), ("open", 1)]
+  );
+}
+
 #[tokio::test]
 async fn test_sqlite_backend_list_collections() {
   let backend = SqliteBackend::in_memory().await.unwrap();
@@ -160,6 +273,48 @@ async fn test_sqlite_backend_list_collections() {
   assert!(collections.contains(&"comments".to_string()));
 }
 
+#[tokio::test]
+async fn test_sqlite_backend_collection_counts() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  for n in 0..3 {
+    backend
+      .insert(DEFAULT_PROJECT_ID, "users", json!({"n": n}))
+      .await
+      .unwrap();
+  }
+  let post = backend
+    .insert(DEFAULT_PROJECT_ID, "posts", json!({"title": "Hello"}))
+    .await
+    .unwrap();
+  let other_project = Uuid::new_v4();
+  backend
+    .insert(other_project, "users", json!({"n": 0}))
+    .await
+    .unwrap();
+
+  let counts = backend.collection_counts(DEFAULT_PROJECT_ID).await.unwrap();
+  assert_eq!(
+    counts,
+    vec![("posts".to_string(), 1), ("users".to_string(), 3)]
+  );
+
+  backend
+    .set_soft_delete(DEFAULT_PROJECT_ID, "posts", SoftDeleteSettings::default())
+    .await
+    .unwrap();
+  backend
+    .delete(DEFAULT_PROJECT_ID, "posts", post.id)
+    .await
+    .unwrap();
+  let counts = backend.collection_counts(DEFAULT_PROJECT_ID).await.unwrap();
+  assert_eq!(
+    counts,
+    vec![("posts".to_string(), 0), ("users".to_string(), 3)]
+  );
+}
+
 #[tokio::test]
 async fn test_sqlite_backend_filter() {
   let backend = SqliteBackend::in_memory().await.unwrap();
@@ -193,7 +348,15 @@ async fn test_sqlite_backend_filter() {
   // Filter by age > 28 using SQLite JSON syntax
   let filter = "CAST(json_extract(data, '$.age') AS REAL) > 28";
   let users = backend
-    .list(DEFAULT_PROJECT_ID, "users", Some(filter), None, None, None)
+    .list(
+      DEFAULT_PROJECT_ID,
+      "users",
+      None,
+      Some(filter),
+      None,
+      None,
+      None,
+    )
     .await
     .unwrap();
   assert_eq!(users.len(), 2);
@@ -316,3 +479,1042 @@ async fn test_sqlite_backend_token_name_unique() {
     .await;
   assert!(result.is_err());
 }
+
+// =============================================================================
+// Idempotency Key Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_sqlite_idempotency_key_lifecycle() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let ttl = Duration::from_secs(60);
+
+  let claim = backend
+    .claim_idempotency_key(DEFAULT_PROJECT_ID, "orders", "k1", ttl)
+    .await
+    .unwrap();
+  assert_eq!(claim, IdempotencyClaim::Claimed);
+  let claim = backend
+    .claim_idempotency_key(DEFAULT_PROJECT_ID, "orders", "k1", ttl)
+    .await
+    .unwrap();
+  assert_eq!(claim, IdempotencyClaim::InProgress);
+
+  // Keys are scoped to the collection
+  let claim = backend
+    .claim_idempotency_key(DEFAULT_PROJECT_ID, "invoices", "k1", ttl)
+    .await
+    .unwrap();
+  assert_eq!(claim, IdempotencyClaim::Claimed);
+
+  backend
+    .complete_idempotency_key(DEFAULT_PROJECT_ID, "orders", "k1", &json!({"ok": true}))
+    .await
+    .unwrap();
+  let claim = backend
+    .claim_idempotency_key(DEFAULT_PROJECT_ID, "orders", "k1", ttl)
+    .await
+    .unwrap();
+  assert_eq!(claim, IdempotencyClaim::Completed(json!({"ok": true})));
+
+  // A released key can be claimed again
+  backend
+    .release_idempotency_key(DEFAULT_PROJECT_ID, "invoices", "k1")
+    .await
+    .unwrap();
+  let claim = backend
+    .claim_idempotency_key(DEFAULT_PROJECT_ID, "invoices", "k1", ttl)
+    .await
+    .unwrap();
+  assert_eq!(claim, IdempotencyClaim::Claimed);
+}
+
+#[tokio::test]
+async fn test_sqlite_idempotency_key_expiry() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  backend
+    .claim_idempotency_key(DEFAULT_PROJECT_ID, "orders", "k1", Duration::from_secs(60))
+    .await
+    .unwrap();
+  tokio::time::sleep(Duration::from_millis(20)).await;
+
+  // Expired keys count as unused and are purged
+  let claim = backend
+    .claim_idempotency_key(
+      DEFAULT_PROJECT_ID,
+      "orders",
+      "k1",
+      Duration::from_millis(10),
+    )
+    .await
+    .unwrap();
+  assert_eq!(claim, IdempotencyClaim::Claimed);
+  assert_eq!(
+    backend
+      .purge_idempotency_keys(Duration::from_secs(60))
+      .await
+      .unwrap(),
+    0
+  );
+  tokio::time::sleep(Duration::from_millis(20)).await;
+  assert_eq!(
+    backend
+      .purge_idempotency_keys(Duration::from_millis(10))
+      .await
+      .unwrap(),
+    1
+  );
+}
+
+#[tokio::test]
+async fn test_sqlite_write_document_returns_change_id() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let write = DocumentWrite::Insert {
+    data: json!({"n": 1}),
+    options: WriteOptions::default(),
+  };
+  let (doc, inserted) = backend
+    .write_document(DEFAULT_PROJECT_ID, "orders", write, true)
+    .await
+    .unwrap()
+    .unwrap();
+  let write = DocumentWrite::Update {
+    id: doc.id,
+    data: json!({"n": 2}),
+    options: WriteOptions::default(),
+  };
+  let (_, updated) = backend
+    .write_document(DEFAULT_PROJECT_ID, "orders", write, true)
+    .await
+    .unwrap()
+    .unwrap();
+  let write = DocumentWrite::Delete { id: doc.id };
+  let (_, deleted) = backend
+    .write_document(DEFAULT_PROJECT_ID, "orders", write, true)
+    .await
+    .unwrap()
+    .unwrap();
+
+  let changes = backend.list_changes(0, 10).await.unwrap();
+  let ids: Vec<_> = changes.iter().map(|c| Some(c.id)).collect();
+  assert_eq!(ids, [inserted, updated, deleted]);
+
+  // Without tracking no change ID is read, and a missing document is None
+  let write = DocumentWrite::Delete { id: doc.id };
+  assert!(backend
+    .write_document(DEFAULT_PROJECT_ID, "orders", write, false)
+    .await
+    .unwrap()
+    .is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_insert_idempotent_replays() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let ttl = Duration::from_secs(60);
+
+  let first = insert_idempotent(
+    &backend,
+    DEFAULT_PROJECT_ID,
+    "orders",
+    json!({"n": 1}),
+    WriteOptions::default(),
+    "k1",
+    ttl,
+  )
+  .await
+  .unwrap();
+  let IdempotentInsert::Inserted(doc, change_id) = first else {
+    panic!("Expected the first insert to run");
+  };
+  let changes = backend.list_changes(0, 10).await.unwrap();
+  assert_eq!(change_id, Some(changes[0].id));
+  let retry = insert_idempotent(
+    &backend,
+    DEFAULT_PROJECT_ID,
+    "orders",
+    json!({"n": 2}),
+    WriteOptions::default(),
+    "k1",
+    ttl,
+  )
+  .await
+  .unwrap();
+  match retry {
+    IdempotentInsert::Replayed(response) => {
+      assert_eq!(response["id"], doc.id.to_string());
+      assert_eq!(response["data"]["n"], 1);
+    }
+    other => panic!("Expected a replay, got {:?}", other),
+  }
+  let docs = backend
+    .list(DEFAULT_PROJECT_ID, "orders", None, None, None, None, None)
+    .await
+    .unwrap();
+  assert_eq!(docs.len(), 1);
+
+  // Invalid keys are rejected before anything is inserted
+  assert!(insert_idempotent(
+    &backend,
+    DEFAULT_PROJECT_ID,
+    "orders",
+    json!({}),
+    WriteOptions::default(),
+    "",
+    ttl
+  )
+  .await
+  .is_err());
+}
+
+// =============================================================================
+// Change Listener Heartbeat Tests
+// =============================================================================
+
+#[test]
+fn test_listener_heartbeat() {
+  let heartbeat = ListenerHeartbeat::new(Duration::from_secs(10));
+  assert!(heartbeat.last_beat().is_none());
+  assert!(!heartbeat.is_alive());
+  assert_eq!(heartbeat.stale_after(), Duration::from_secs(20));
+
+  heartbeat.beat();
+  assert!(heartbeat.last_beat().is_some());
+  assert!(heartbeat.is_alive());
+
+  // Fast pollers still get a few seconds of slack
+  let heartbeat = ListenerHeartbeat::new(Duration::from_millis(50));
+  assert_eq!(heartbeat.stale_after(), Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_sqlite_change_listener_heartbeat() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let heartbeat = backend.change_listener_heartbeat();
+  assert!(!heartbeat.is_alive());
+
+  backend.start_change_listener().await.unwrap();
+  for _ in 0..50 {
+    if heartbeat.is_alive() {
+      return;
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+  }
+  panic!("change listener never reported a heartbeat");
+}
+
+#[tokio::test]
+async fn test_sqlite_in_memory_change_listener_broadcasts() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let mut changes = backend.subscribe_changes();
+  backend.start_change_listener().await.unwrap();
+
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "alice"}))
+    .await
+    .unwrap();
+  let change = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+    .await
+    .expect("change listener never broadcast the insert")
+    .unwrap();
+  assert_eq!(change.document_id, doc.id);
+  assert_eq!(change.operation, ChangeOperation::Insert);
+}
+
+#[tokio::test]
+async fn test_sqlite_field_encryption() {
+  let fields = HashMap::from([("users".to_string(), vec!["ssn".to_string()])]);
+  let backend = SqliteBackend::in_memory()
+    .await
+    .unwrap()
+    .with_field_encryption(Arc::new(FieldEncryption::new(&[7; 32], fields)));
+  backend.init_schema().await.unwrap();
+
+  let doc = backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      json!({"name": "alice", "ssn": "123-45-6789"}),
+    )
+    .await
+    .unwrap();
+  assert_eq!(doc.data["ssn"], "123-45-6789");
+
+  // Stored as ciphertext
+  let result = backend
+    .execute_raw_sql(
+      "SELECT json_extract(data, '$.ssn') FROM documents WHERE id = ?1",
+      &[json!(doc.id.to_string())],
+      0,
+    )
+    .await
+    .unwrap();
+  let stored = result.rows[0][0].as_str().unwrap();
+  assert!(stored.contains("$encrypted"));
+  assert!(!stored.contains("123-45-6789"));
+
+  // Read back as plaintext
+  let fetched = backend
+    .get(DEFAULT_PROJECT_ID, "users", doc.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(fetched.data["ssn"], "123-45-6789");
+  let updated = backend
+    .update(
+      DEFAULT_PROJECT_ID,
+      "users",
+      doc.id,
+      json!({"name": "alice", "ssn": "987-65-4321"}),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(updated.data["ssn"], "987-65-4321");
+  let changes = backend.list_changes(0, 10).await.unwrap();
+  assert_eq!(changes[1].new_data.as_ref().unwrap()["ssn"], "987-65-4321");
+
+  // Filtering on the encrypted field is refused, other fields still work
+  let filter = "json_extract(data, '$.ssn') = 'x'";
+  assert!(backend
+    .list(
+      DEFAULT_PROJECT_ID,
+      "users",
+      None,
+      Some(filter),
+      None,
+      None,
+      None
+    )
+    .await
+    .is_err());
+  let filter = "json_extract(data, '$.name') = 'alice'";
+  let docs = backend
+    .list(
+      DEFAULT_PROJECT_ID,
+      "users",
+      None,
+      Some(filter),
+      None,
+      None,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(docs[0].data["ssn"], "987-65-4321");
+
+  // Clients can't write ciphertext, and it can't be moved to another document
+  let stored: serde_json::Value = serde_json::from_str(stored).unwrap();
+  assert!(backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      json!({"name": "mallory", "ssn": stored.clone()}),
+    )
+    .await
+    .is_err());
+  let other = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "bob"}))
+    .await
+    .unwrap();
+  backend
+    .execute_raw_sql(
+      "UPDATE documents SET data = json_set(data, '$.ssn', json(?1)) WHERE id = ?2",
+      &[json!(stored.to_string()), json!(other.id.to_string())],
+      0,
+    )
+    .await
+    .unwrap();
+  assert!(backend
+    .get(DEFAULT_PROJECT_ID, "users", other.id)
+    .await
+    .is_err());
+
+  // Nor renamed or copied into another collection
+  assert!(backend
+    .copy_collection(DEFAULT_PROJECT_ID, "users", "users_copy", 0)
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_queries_on_encrypted_fields_are_refused() {
+  let fields = HashMap::from([("users".to_string(), vec!["ssn".to_string()])]);
+  let backend = SqliteBackend::in_memory()
+    .await
+    .unwrap()
+    .with_field_encryption(Arc::new(FieldEncryption::new(&[7; 32], fields)));
+  backend.init_schema().await.unwrap();
+  backend
+    .insert(
+      DEFAULT_PROJECT_ID,
+      "users",
+      json!({"name": "alice", "ssn": "123-45-6789"}),
+    )
+    .await
+    .unwrap();
+  let pool = QueryEnginePool::new(1, backend.dialect());
+
+  for query in [
+    r#"db.table("users").filter(u => u.ssn === "123-45-6789").run()"#,
+    r#"db.table("users").orderBy("ssn").run()"#,
+  ] {
+    let err = pool
+      .run(&QueryInput::from(query), DEFAULT_PROJECT_ID, &backend, None)
+      .await
+      .unwrap_err();
+    assert!(err.to_string().contains("encrypted"), "{}: {}", query, err);
+    assert!(pool
+      .stream_with_params(
+        &QueryInput::from(query),
+        &[],
+        DEFAULT_PROJECT_ID,
+        &backend,
+        10
+      )
+      .await
+      .is_err());
+  }
+
+  let result = pool
+    .run(
+      &QueryInput::from(r#"db.table("users").filter(u => u.name === "alice").run()"#),
+      DEFAULT_PROJECT_ID,
+      &backend,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(result.data[0]["data"]["ssn"], "123-45-6789");
+}
+
+// =============================================================================
+// Raw SQL Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_sqlite_raw_sql_binds_parameters() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  for name in ["alice", "bob", "carol"] {
+    backend
+      .insert(DEFAULT_PROJECT_ID, "users", json!({"name": name}))
+      .await
+      .unwrap();
+  }
+
+  let result = backend
+    .execute_raw_sql(
+      "SELECT json_extract(data, '$.name') AS name FROM documents WHERE collection = ?1 ORDER BY name",
+      &[json!("users")],
+      2,
+    )
+    .await
+    .unwrap();
+  assert_eq!(result.columns, vec!["name"]);
+  assert_eq!(result.rows, vec![vec![json!("alice")], vec![json!("bob")]]);
+  assert!(result.truncated);
+
+  // A bound value is never parsed as SQL
+  let result = backend
+    .execute_raw_sql(
+      "DELETE FROM documents WHERE json_extract(data, '$.name') = ?1",
+      &[json!("x' OR '1'='1")],
+      0,
+    )
+    .await
+    .unwrap();
+  assert_eq!(result.rows_affected, 0);
+
+  let result = backend
+    .execute_raw_sql(
+      "DELETE FROM documents WHERE json_extract(data, '$.name') = ?1",
+      &[json!("bob")],
+      0,
+    )
+    .await
+    .unwrap();
+  assert!(result.columns.is_empty());
+  assert_eq!(result.rows_affected, 1);
+
+  // Several statements only run through the batch path
+  assert!(backend
+    .execute_raw_sql("SELECT 1; SELECT 2", &[], 0)
+    .await
+    .is_err());
+  let result = backend
+    .execute_raw_sql_batch(
+      "DELETE FROM documents WHERE json_extract(data, '$.name') = 'alice'; DELETE FROM documents WHERE json_extract(data, '$.name') = 'carol';",
+    )
+    .await
+    .unwrap();
+  assert_eq!(result.rows_affected, 2);
+}
+
+// =============================================================================
+// Storage Metadata Tests
+// =============================================================================
+
+async fn put_object(backend: &SqliteBackend, key: &str, size: i64) -> Uuid {
+  let version_id = Uuid::new_v4();
+  backend
+    .create_storage_object_with_stats(
+      "files",
+      key,
+      version_id,
+      "etag",
+      size,
+      "text/plain",
+      &format!("/data/{}", version_id),
+      json!({}),
+    )
+    .await
+    .unwrap();
+  version_id
+}
+
+#[tokio::test]
+async fn test_sqlite_storage_objects_and_stats() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  backend.create_storage_bucket("files", None).await.unwrap();
+  assert!(backend.create_storage_bucket("files", None).await.is_err());
+  assert_eq!(backend.list_storage_buckets().await.unwrap().len(), 1);
+
+  put_object(&backend, "a/1.txt", 10).await;
+  put_object(&backend, "a/2.txt", 20).await;
+  put_object(&backend, "b.txt", 30).await;
+
+  let bucket = backend.get_storage_bucket("files").await.unwrap().unwrap();
+  assert_eq!(bucket.object_count, 3);
+  assert_eq!(bucket.current_size, 60);
+
+  // Delimited listing splits direct objects from common prefixes
+  let (objects, prefixes, truncated, _) = backend
+    .list_storage_objects_with_prefixes("files", None, Some("/"), 100, None)
+    .await
+    .unwrap();
+  assert_eq!(objects.len(), 1);
+  assert_eq!(objects[0].key, "b.txt");
+  assert_eq!(prefixes, vec!["a/".to_string()]);
+  assert!(!truncated);
+
+  // Prefix matching is case-sensitive, and pages continue after the token
+  let (objects, _, truncated, token) = backend
+    .list_storage_objects_with_prefixes("files", Some("a/"), None, 1, None)
+    .await
+    .unwrap();
+  assert_eq!(objects[0].key, "a/1.txt");
+  assert!(truncated);
+  let (objects, _, truncated, _) = backend
+    .list_storage_objects_with_prefixes("files", Some("a/"), None, 1, token.as_deref())
+    .await
+    .unwrap();
+  assert_eq!(objects[0].key, "a/2.txt");
+  assert!(!truncated);
+  let (objects, _, _) = backend
+    .list_storage_objects("files", Some("A/"), None, 100, None)
+    .await
+    .unwrap();
+  assert!(objects.is_empty());
+
+  // Replacing keeps the count and returns the old file for cleanup
+  let old = backend
+    .get_storage_object("files", "b.txt", None)
+    .await
+    .unwrap()
+    .unwrap();
+  let replaced = backend
+    .replace_storage_object(
+      "files",
+      "b.txt",
+      Uuid::new_v4(),
+      "etag2",
+      5,
+      "text/plain",
+      "/data/new",
+      json!({}),
+    )
+    .await
+    .unwrap();
+  assert_eq!(replaced, Some(old.storage_path));
+  let bucket = backend.get_storage_bucket("files").await.unwrap().unwrap();
+  assert_eq!(bucket.object_count, 3);
+  assert_eq!(bucket.current_size, 35);
+
+  let deleted = backend
+    .delete_storage_object_with_stats("files", "b.txt", None)
+    .await
+    .unwrap();
+  assert_eq!(deleted, Some(("/data/new".to_string(), 5)));
+  assert_eq!(
+    backend
+      .delete_storage_object_with_stats("files", "b.txt", None)
+      .await
+      .unwrap(),
+    None
+  );
+  let bucket = backend.get_storage_bucket("files").await.unwrap().unwrap();
+  assert_eq!(bucket.object_count, 2);
+  assert_eq!(bucket.current_size, 30);
+
+  // A delete marker hides the object but keeps its versions
+  let marker = Uuid::new_v4();
+  backend
+    .create_storage_delete_marker("files", "a/1.txt", marker)
+    .await
+    .unwrap();
+  let latest = backend
+    .get_storage_object("files", "a/1.txt", None)
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(latest.is_delete_marker);
+  assert_eq!(latest.version_id, marker);
+  let (versions, _, _) = backend
+    .list_storage_object_versions("files", Some("a/1"), 100)
+    .await
+    .unwrap();
+  assert_eq!(versions.len(), 2);
+  assert!(versions[0].is_delete_marker);
+}
+
+#[tokio::test]
+async fn test_sqlite_document_history() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+
+  let doc = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "alice"}))
+    .await
+    .unwrap();
+  let other = backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "bob"}))
+    .await
+    .unwrap();
+  backend
+    .update(
+      DEFAULT_PROJECT_ID,
+      "users",
+      doc.id,
+      json!({"name": "alicia"}),
+    )
+    .await
+    .unwrap();
+  backend
+    .delete(DEFAULT_PROJECT_ID, "users", doc.id)
+    .await
+    .unwrap();
+
+  let history = backend
+    .document_history(DEFAULT_PROJECT_ID, "users", doc.id, 0, 100)
+    .await
+    .unwrap();
+  let operations: Vec<_> = history.iter().map(|c| c.operation).collect();
+  assert_eq!(
+    operations,
+    vec![
+      ChangeOperation::Insert,
+      ChangeOperation::Update,
+      ChangeOperation::Delete
+    ]
+  );
+  assert_eq!(history[1].old_data.as_ref().unwrap()["name"], "alice");
+  assert_eq!(history[1].new_data.as_ref().unwrap()["name"], "alicia");
+  assert!(history.iter().all(|c| c.document_id == doc.id));
+  assert!(history[0].changed_at <= history[2].changed_at);
+  assert!(chrono::Utc::now() - history[0].changed_at < chrono::Duration::minutes(1));
+
+  // Paging continues after the last change seen
+  let rest = backend
+    .document_history(DEFAULT_PROJECT_ID, "users", doc.id, history[0].id, 1)
+    .await
+    .unwrap();
+  assert_eq!(rest.len(), 1);
+  assert_eq!(rest[0].id, history[1].id);
+
+  // Other collections and projects don't share the history
+  assert!(backend
+    .document_history(DEFAULT_PROJECT_ID, "people", doc.id, 0, 100)
+    .await
+    .unwrap()
+    .is_empty());
+  assert_eq!(
+    backend
+      .document_history(DEFAULT_PROJECT_ID, "users", other.id, 0, 100)
+      .await
+      .unwrap()
+      .len(),
+    1
+  );
+}
+
+#[tokio::test]
+async fn test_sqlite_storage_object_tags() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  backend.create_storage_bucket("files", None).await.unwrap();
+  put_object(&backend, "logs/1.txt", 10).await;
+  put_object(&backend, "logs/2.txt", 10).await;
+
+  assert!(backend
+    .get_storage_object_tags("files", "logs/1.txt")
+    .await
+    .unwrap()
+    .is_empty());
+
+  let tags: ObjectTags = [("env", "dev"), ("team", "core")]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect();
+  backend
+    .set_storage_object_tags("files", "logs/1.txt", &tags)
+    .await
+    .unwrap();
+  assert_eq!(
+    backend
+      .get_storage_object_tags("files", "logs/1.txt")
+      .await
+      .unwrap(),
+    tags
+  );
+
+  // Objects must carry every requested tag
+  let filter: ObjectTags = [("env".to_string(), "dev".to_string())].into();
+  let objects = backend
+    .list_storage_objects_with_tags("files", Some("logs/"), &filter)
+    .await
+    .unwrap();
+  assert_eq!(objects.len(), 1);
+  assert_eq!(objects[0].key, "logs/1.txt");
+  let filter: ObjectTags = [("env".to_string(), "prod".to_string())].into();
+  assert!(backend
+    .list_storage_objects_with_tags("files", None, &filter)
+    .await
+    .unwrap()
+    .is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_storage_multipart_and_access_keys() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  backend.create_storage_bucket("files", None).await.unwrap();
+
+  let upload_id = Uuid::new_v4();
+  backend
+    .create_multipart_upload(upload_id, "files", "big.bin", None, json!({"a": "b"}))
+    .await
+    .unwrap();
+  backend
+    .upsert_multipart_part(upload_id, 1, "e1", 5, "/parts/1")
+    .await
+    .unwrap();
+  backend
+    .upsert_multipart_part(upload_id, 1, "e1b", 6, "/parts/1b")
+    .await
+    .unwrap();
+  backend
+    .upsert_multipart_part(upload_id, 2, "e2", 7, "/parts/2")
+    .await
+    .unwrap();
+
+  let upload = backend
+    .get_multipart_upload(upload_id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(upload.key, "big.bin");
+  assert_eq!(upload.metadata, json!({"a": "b"}));
+  let (parts, truncated) = backend.list_multipart_parts(upload_id, 10).await.unwrap();
+  assert_eq!(parts.len(), 2);
+  assert_eq!(parts[0].etag, "e1b");
+  assert!(!truncated);
+
+  // Dropping the bucket removes its uploads and their parts
+  backend.delete_storage_bucket("files").await.unwrap();
+  assert!(backend
+    .get_multipart_upload(upload_id)
+    .await
+    .unwrap()
+    .is_none());
+  assert!(backend
+    .get_multipart_part(upload_id, 2)
+    .await
+    .unwrap()
+    .is_none());
+
+  let owner = Uuid::new_v4();
+  backend
+    .create_storage_access_key("AKIDEXAMPLE", "secret", Some(owner), "ci")
+    .await
+    .unwrap();
+  assert_eq!(
+    backend.get_storage_access_key("AKIDEXAMPLE").await.unwrap(),
+    Some(("secret".to_string(), Some(owner)))
+  );
+  assert_eq!(backend.list_storage_access_keys().await.unwrap().len(), 1);
+  assert!(backend
+    .delete_storage_access_key("AKIDEXAMPLE")
+    .await
+    .unwrap());
+  assert!(backend
+    .get_storage_access_key("AKIDEXAMPLE")
+    .await
+    .unwrap()
+    .is_none());
+}
+
+// =============================================================================
+// Project Limits Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_sqlite_project_limits() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let project = Uuid::new_v4();
+  assert!(backend.get_project_limits(project).await.unwrap().is_none());
+
+  let limits = ProjectLimits {
+    requests_per_second: 50,
+    burst_size: 100,
+    max_documents: 1000,
+  };
+  backend.set_project_limits(project, &limits).await.unwrap();
+  assert_eq!(
+    backend.get_project_limits(project).await.unwrap(),
+    Some(limits)
+  );
+
+  // Setting again replaces the row
+  let raised = ProjectLimits {
+    max_documents: 5000,
+    ..limits
+  };
+  backend.set_project_limits(project, &raised).await.unwrap();
+  assert_eq!(
+    backend.list_project_limits().await.unwrap(),
+    vec![(project, raised)]
+  );
+
+  assert!(backend.delete_project_limits(project).await.unwrap());
+  assert!(!backend.delete_project_limits(project).await.unwrap());
+  assert!(backend.list_project_limits().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_count_project_documents() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  for collection in ["a", "a", "b"] {
+    backend
+      .insert(DEFAULT_PROJECT_ID, collection, json!({}))
+      .await
+      .unwrap();
+  }
+  assert_eq!(
+    backend
+      .count_project_documents(DEFAULT_PROJECT_ID)
+      .await
+      .unwrap(),
+    3
+  );
+  assert_eq!(
+    backend
+      .count_project_documents(Uuid::new_v4())
+      .await
+      .unwrap(),
+    0
+  );
+}
+
+#[tokio::test]
+async fn test_sqlite_soft_delete() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  let with_deleted = ReadOptions { with_deleted: true };
+  backend
+    .set_soft_delete(
+      DEFAULT_PROJECT_ID,
+      "orders",
+      SoftDeleteSettings { retention_secs: 0 },
+    )
+    .await
+    .unwrap();
+  assert_eq!(
+    backend
+      .get_soft_delete(DEFAULT_PROJECT_ID, "orders")
+      .await
+      .unwrap(),
+    Some(SoftDeleteSettings { retention_secs: 0 })
+  );
+
+  let a = backend
+    .insert(DEFAULT_PROJECT_ID, "orders", json!({"n": 1}))
+    .await
+    .unwrap();
+  let b = backend
+    .insert(DEFAULT_PROJECT_ID, "orders", json!({"n": 2}))
+    .await
+    .unwrap();
+
+  // Delete marks the document and hides it from reads
+  let deleted = backend
+    .delete(DEFAULT_PROJECT_ID, "orders", a.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(deleted.deleted_at.is_some());
+  assert!(backend
+    .get(DEFAULT_PROJECT_ID, "orders", a.id)
+    .await
+    .unwrap()
+    .is_none());
+  let hidden = backend
+    .get_with_options(DEFAULT_PROJECT_ID, "orders", a.id, with_deleted)
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(hidden.deleted_at.is_some());
+  let listed = backend
+    .list(DEFAULT_PROJECT_ID, "orders", None, None, None, None, None)
+    .await
+    .unwrap();
+  assert_eq!(listed.len(), 1);
+  let all = backend
+    .list_with_options(
+      DEFAULT_PROJECT_ID,
+      "orders",
+      None,
+      None,
+      &[],
+      &[],
+      None,
+      None,
+      with_deleted,
+    )
+    .await
+    .unwrap();
+  assert_eq!(all.len(), 2);
+
+  // Deleted documents can't be updated or deleted again
+  assert!(backend
+    .update(DEFAULT_PROJECT_ID, "orders", a.id, json!({"n": 3}))
+    .await
+    .unwrap()
+    .is_none());
+  assert!(backend
+    .delete(DEFAULT_PROJECT_ID, "orders", a.id)
+    .await
+    .unwrap()
+    .is_none());
+
+  let restored = backend
+    .restore_deleted(DEFAULT_PROJECT_ID, "orders", a.id)
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(restored.deleted_at.is_none());
+  assert_eq!(restored.data, json!({"n": 1}));
+  assert!(backend
+    .restore_deleted(DEFAULT_PROJECT_ID, "orders", a.id)
+    .await
+    .unwrap()
+    .is_none());
+
+  // Subscribers see the soft delete and the restore as a delete and an insert
+  let ops: Vec<_> = backend
+    .list_changes(0, 100)
+    .await
+    .unwrap()
+    .into_iter()
+    .filter(|c| c.document_id == a.id)
+    .map(|c| c.operation)
+    .collect();
+  assert_eq!(
+    ops,
+    vec![
+      ChangeOperation::Insert,
+      ChangeOperation::Delete,
+      ChangeOperation::Insert
+    ]
+  );
+
+  // Purging removes soft-deleted documents past their retention, silently
+  let deleted = backend
+    .delete_many(DEFAULT_PROJECT_ID, "orders", &[a.id, b.id])
+    .await
+    .unwrap();
+  assert_eq!(deleted.len(), 2);
+  let (_, last_change) = backend.change_id_range().await.unwrap().unwrap();
+  assert_eq!(
+    backend
+      .purge_deleted(DEFAULT_PROJECT_ID, "orders", Duration::from_secs(3600))
+      .await
+      .unwrap(),
+    0
+  );
+  assert_eq!(
+    backend
+      .purge_deleted(DEFAULT_PROJECT_ID, "orders", Duration::ZERO)
+      .await
+      .unwrap(),
+    2
+  );
+  assert!(backend
+    .list_changes(last_change, 100)
+    .await
+    .unwrap()
+    .is_empty());
+
+  // Without soft delete, deletes remove documents again
+  assert!(backend
+    .disable_soft_delete(DEFAULT_PROJECT_ID, "orders")
+    .await
+    .unwrap());
+  assert!(backend.list_soft_delete().await.unwrap().is_empty());
+  let c = backend
+    .insert(DEFAULT_PROJECT_ID, "orders", json!({"n": 4}))
+    .await
+    .unwrap();
+  backend
+    .delete(DEFAULT_PROJECT_ID, "orders", c.id)
+    .await
+    .unwrap();
+  assert!(backend
+    .get_with_options(DEFAULT_PROJECT_ID, "orders", c.id, with_deleted)
+    .await
+    .unwrap()
+    .is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_backend_reindex_documents() {
+  let backend = SqliteBackend::in_memory().await.unwrap();
+  backend.init_schema().await.unwrap();
+  backend
+    .insert(DEFAULT_PROJECT_ID, "users", json!({"name": "Alice"}))
+    .await
+    .unwrap();
+
+  let indexes = backend.reindex_documents().await.unwrap();
+  assert!(indexes.contains(&"idx_documents_list_order".to_string()));
+  assert_eq!(
+    backend
+      .list(DEFAULT_PROJECT_ID, "users", None, None, None, None, None)
+      .await
+      .unwrap()
+      .len(),
+    1
+  );
+}
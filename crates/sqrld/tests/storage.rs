@@ -318,7 +318,8 @@ fn test_list_objects_v2_xml() {
     }],
     continuation_token: None,
     next_continuation_token: None,
-    key_count: 1,
+    start_after: None,
+    key_count: 2,
     encoding_type: None,
   };
 
@@ -332,6 +333,44 @@ fn test_list_objects_v2_xml() {
   assert!(xml_output.contains("<Size>1024</Size>"));
   assert!(xml_output.contains("<CommonPrefixes>"));
   assert!(xml_output.contains("<Prefix>folder/subfolder/</Prefix>"));
+  assert!(xml_output.contains("<KeyCount>2</KeyCount>"));
+  assert!(xml_output.contains("<IsTruncated>false</IsTruncated>"));
+  assert!(!xml_output.contains("<StartAfter>"));
+}
+
+#[test]
+fn test_list_objects_v2_xml_start_after_and_owner() {
+  let response = ListObjectsResponse {
+    name: "test-bucket".to_string(),
+    prefix: None,
+    delimiter: None,
+    max_keys: 1,
+    is_truncated: true,
+    contents: vec![ObjectInfo {
+      key: "b.txt".to_string(),
+      last_modified: Utc::now(),
+      etag: "abc123".to_string(),
+      size: 1,
+      storage_class: "STANDARD".to_string(),
+      owner: Some(Owner {
+        id: "anonymous".to_string(),
+        display_name: None,
+      }),
+    }],
+    common_prefixes: vec![],
+    continuation_token: None,
+    next_continuation_token: Some("b.txt".to_string()),
+    start_after: Some("a.txt".to_string()),
+    key_count: 1,
+    encoding_type: None,
+  };
+
+  let xml_output = xml::list_objects_v2_xml(&response);
+  assert!(xml_output.contains("<StartAfter>a.txt</StartAfter>"));
+  assert!(xml_output.contains("<NextContinuationToken>b.txt</NextContinuationToken>"));
+  assert!(xml_output.contains("<IsTruncated>true</IsTruncated>"));
+  assert!(xml_output.contains("<KeyCount>1</KeyCount>"));
+  assert!(xml_output.contains("<Owner>\n      <ID>anonymous</ID>"));
 }
 
 #[test]
@@ -430,6 +469,68 @@ fn test_versioning_config_xml_enabled() {
   assert!(xml_output.contains("<Status>Enabled</Status>"));
 }
 
+#[test]
+fn test_location_constraint_xml() {
+  let xml_output = xml::location_constraint_xml("eu-west-1");
+  assert!(xml_output.contains(">eu-west-1</LocationConstraint>"));
+
+  // us-east-1 is reported as an empty constraint
+  let xml_output = xml::location_constraint_xml("us-east-1");
+  assert!(xml_output.ends_with("/>"));
+}
+
+#[test]
+fn test_tagging_xml_round_trip() {
+  let body = "<Tagging><TagSet><Tag><Key>env</Key><Value>a&amp;b</Value></Tag>\
+              <Tag><Key>team</Key><Value>core</Value></Tag></TagSet></Tagging>";
+  let tags = xml::parse_tagging_xml(body).unwrap();
+  assert_eq!(tags.len(), 2);
+  assert_eq!(tags["env"], "a&b");
+
+  let xml_output = xml::tagging_xml(&tags);
+  assert!(xml_output.contains("<Key>env</Key>"));
+  assert!(xml_output.contains("<Value>a&amp;b</Value>"));
+  assert_eq!(xml::parse_tagging_xml(&xml_output).unwrap(), tags);
+}
+
+#[test]
+fn test_parse_tagging_xml_limits() {
+  let tag = |k: &str| format!("<Tag><Key>{}</Key><Value>v</Value></Tag>", k);
+  let duplicate = format!(
+    "<Tagging><TagSet>{}{}</TagSet></Tagging>",
+    tag("a"),
+    tag("a")
+  );
+  let err = xml::parse_tagging_xml(&duplicate).unwrap_err();
+  assert_eq!(err.code, StorageErrorCode::InvalidTag);
+
+  let many: String = (0..11).map(|i| tag(&i.to_string())).collect();
+  let too_many = format!("<Tagging><TagSet>{}</TagSet></Tagging>", many);
+  assert!(xml::parse_tagging_xml(&too_many).is_err());
+
+  let err = xml::parse_tagging_xml("<Other/>").unwrap_err();
+  assert_eq!(err.code, StorageErrorCode::MalformedXML);
+}
+
+#[test]
+fn test_lifecycle_rule_matches_tags() {
+  let rule = LifecycleRule {
+    id: "tmp".to_string(),
+    enabled: true,
+    prefix: Some("logs/".to_string()),
+    tags: [("env".to_string(), "dev".to_string())].into(),
+    expiration_days: Some(1),
+    noncurrent_version_expiration_days: None,
+  };
+  let dev: ObjectTags = [("env".to_string(), "dev".to_string())].into();
+  assert!(rule.matches("logs/a", &dev));
+  assert!(!rule.matches("logs/a", &ObjectTags::new()));
+  assert!(!rule.matches("data/a", &dev));
+
+  let xml_output = xml::lifecycle_config_xml(&[rule]);
+  assert!(xml_output.contains("<Tag><Key>env</Key><Value>dev</Value></Tag>"));
+}
+
 #[test]
 fn test_versioning_config_xml_suspended() {
   let xml_output = xml::versioning_config_xml(false);
@@ -485,6 +586,7 @@ fn test_lifecycle_config_xml() {
       id: "rule-1".to_string(),
       enabled: true,
       prefix: Some("logs/".to_string()),
+      tags: Default::default(),
       expiration_days: Some(30),
       noncurrent_version_expiration_days: Some(7),
     },
@@ -492,6 +594,7 @@ fn test_lifecycle_config_xml() {
       id: "rule-2".to_string(),
       enabled: false,
       prefix: None,
+      tags: Default::default(),
       expiration_days: Some(90),
       noncurrent_version_expiration_days: None,
     },
@@ -616,3 +719,35 @@ fn test_grantee_serialize() {
   assert!(json.contains("\"type\":\"Group\""));
   assert!(json.contains("\"uri\":"));
 }
+
+// =============================================================================
+// Checksum Tests
+// =============================================================================
+
+#[test]
+fn test_verify_checksums() {
+  use axum::http::HeaderMap;
+  use squirreldb::storage::checksum::verify_checksums;
+
+  let body = b"hello world";
+  assert!(verify_checksums(&HeaderMap::new(), body).is_ok());
+
+  let mut headers = HeaderMap::new();
+  headers.insert("content-md5", "XrY7u+Ae7tCTyyK7j1rNww==".parse().unwrap());
+  headers.insert("x-amz-checksum-crc32", "DUoRhQ==".parse().unwrap());
+  headers.insert(
+    "x-amz-checksum-sha256",
+    "uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek="
+      .parse()
+      .unwrap(),
+  );
+  assert!(verify_checksums(&headers, body).is_ok());
+
+  let err = verify_checksums(&headers, b"hello w0rld").unwrap_err();
+  assert_eq!(err.code, StorageErrorCode::BadDigest);
+
+  let mut headers = HeaderMap::new();
+  headers.insert("content-md5", "not base64!".parse().unwrap());
+  let err = verify_checksums(&headers, body).unwrap_err();
+  assert_eq!(err.code, StorageErrorCode::InvalidDigest);
+}
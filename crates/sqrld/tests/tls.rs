@@ -0,0 +1,166 @@
+//! TLS tests - certificate loading, reloading and TLS-terminating listeners
+
+use axum::serve::Listener;
+use squirreldb::server::{ServerConfig, ServerListener, ServerTls, TlsSection};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_rustls::rustls;
+
+fn generate_cert() -> rcgen::CertifiedKey {
+  rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+}
+
+fn write_cert(dir: &Path, cert: &rcgen::CertifiedKey) -> TlsSection {
+  let cert_path = dir.join("cert.pem");
+  let key_path = dir.join("key.pem");
+  std::fs::write(&cert_path, cert.cert.pem()).unwrap();
+  std::fs::write(&key_path, cert.key_pair.serialize_pem()).unwrap();
+  TlsSection {
+    enabled: true,
+    cert_path: cert_path.to_string_lossy().into_owned(),
+    key_path: key_path.to_string_lossy().into_owned(),
+  }
+}
+
+// =============================================================================
+// Config Tests
+// =============================================================================
+
+#[test]
+fn test_tls_disabled_by_default() {
+  let config = ServerConfig::default();
+  assert!(!config.server.tls.enabled);
+  assert!(ServerTls::from_section(&config.server.tls)
+    .unwrap()
+    .is_none());
+}
+
+#[test]
+fn test_tls_from_yaml() {
+  let yaml = r#"
+server:
+  tls:
+    enabled: true
+    cert_path: /etc/squirreldb/cert.pem
+    key_path: /etc/squirreldb/key.pem
+"#;
+  let config: ServerConfig = serde_yaml::from_str(yaml).unwrap();
+  assert!(config.server.tls.enabled);
+  assert_eq!(config.server.tls.cert_path, "/etc/squirreldb/cert.pem");
+  assert_eq!(config.server.tls.key_path, "/etc/squirreldb/key.pem");
+}
+
+// =============================================================================
+// Certificate Loading Tests
+// =============================================================================
+
+#[test]
+fn test_tls_load_certificate() {
+  let dir = tempfile::tempdir().unwrap();
+  let section = write_cert(dir.path(), &generate_cert());
+  assert!(ServerTls::from_section(&section).unwrap().is_some());
+}
+
+#[test]
+fn test_tls_load_errors() {
+  let dir = tempfile::tempdir().unwrap();
+
+  let missing = TlsSection {
+    enabled: true,
+    ..Default::default()
+  };
+  assert!(ServerTls::load(&missing).is_err());
+
+  let mut section = write_cert(dir.path(), &generate_cert());
+  section.key_path = dir.path().join("nope.pem").to_string_lossy().into_owned();
+  assert!(ServerTls::load(&section).is_err());
+
+  // A certificate file without any certificates
+  let section = write_cert(dir.path(), &generate_cert());
+  std::fs::write(&section.cert_path, "not a certificate").unwrap();
+  assert!(ServerTls::load(&section).is_err());
+}
+
+#[test]
+fn test_tls_reload_keeps_certificate_on_error() {
+  let dir = tempfile::tempdir().unwrap();
+  let section = write_cert(dir.path(), &generate_cert());
+  let tls = ServerTls::load(&section).unwrap();
+  let original = tls.server_config();
+
+  // A new certificate is picked up
+  write_cert(dir.path(), &generate_cert());
+  tls.reload().unwrap();
+  let reloaded = tls.server_config();
+  assert!(!Arc::ptr_eq(&original, &reloaded));
+
+  // A broken certificate leaves the current one in place
+  std::fs::write(&section.key_path, "garbage").unwrap();
+  assert!(tls.reload().is_err());
+  assert!(Arc::ptr_eq(&reloaded, &tls.server_config()));
+}
+
+// =============================================================================
+// Listener Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_tls_listener_handshake() {
+  let dir = tempfile::tempdir().unwrap();
+  let cert = generate_cert();
+  let section = write_cert(dir.path(), &cert);
+  let tls = ServerTls::from_section(&section).unwrap();
+
+  let mut listener = ServerListener::bind("127.0.0.1:0", tls).await.unwrap();
+  assert!(listener.is_tls());
+  let addr = listener.local_addr().unwrap();
+
+  let mut roots = rustls::RootCertStore::empty();
+  roots.add(cert.cert.der().clone()).unwrap();
+  let client_config =
+    rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+      .with_safe_default_protocol_versions()
+      .unwrap()
+      .with_root_certificates(roots)
+      .with_no_client_auth();
+
+  let client = tokio::spawn(async move {
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+    let server_name = rustls::pki_types::ServerName::try_from("localhost").unwrap();
+    let mut stream = connector.connect(server_name, stream).await.unwrap();
+    stream.write_all(b"ping").await.unwrap();
+    stream.flush().await.unwrap();
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf).await.unwrap();
+    buf
+  });
+
+  let (mut stream, _) = listener.accept().await;
+  let mut buf = [0u8; 4];
+  stream.read_exact(&mut buf).await.unwrap();
+  assert_eq!(&buf, b"ping");
+  stream.write_all(b"pong").await.unwrap();
+  stream.flush().await.unwrap();
+
+  assert_eq!(&client.await.unwrap(), b"pong");
+}
+
+#[tokio::test]
+async fn test_plain_listener() {
+  let mut listener = ServerListener::bind("127.0.0.1:0", None).await.unwrap();
+  assert!(!listener.is_tls());
+  let addr = listener.local_addr().unwrap();
+
+  let client = tokio::spawn(async move {
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"ping").await.unwrap();
+  });
+
+  let (mut stream, _) = listener.accept().await;
+  let mut buf = [0u8; 4];
+  stream.read_exact(&mut buf).await.unwrap();
+  assert_eq!(&buf, b"ping");
+  client.await.unwrap();
+}
@@ -26,6 +26,10 @@ fn test_client_message_serialize() {
   let msg = ClientMessage::Query {
     id: "1".into(),
     query: "db.table(\"users\").run()".into(),
+    cache: false,
+    cache_ttl: None,
+    params: Vec::new(),
+    stream: false,
   };
   let json = serde_json::to_string(&msg).unwrap();
   assert!(json.contains("\"type\":\"query\""));
@@ -39,7 +43,7 @@ fn test_server_message_constructors() {
 
   let error = ServerMessage::error("2", "something went wrong");
   assert!(
-    matches!(error, ServerMessage::Error { id, error } if id == "2" && error == "something went wrong")
+    matches!(error, ServerMessage::Error { id, error, .. } if id == "2" && error == "something went wrong")
   );
 }
 
@@ -50,10 +54,13 @@ fn test_query_spec_defaults() {
     table: "users".into(),
     filter: None,
     map: None,
-    order_by: None,
+    order_by: Vec::new(),
     limit: None,
     offset: None,
     changes: None,
+    projection: None,
+    with_deleted: false,
+    params: Vec::new(),
   };
   assert_eq!(spec.table, "users");
   assert!(spec.filter.is_none());
@@ -0,0 +1,233 @@
+//! Webhook feature tests - settings, change matching, filters, signing
+
+use chrono::Utc;
+use serde_json::json;
+use squirreldb::features::Feature;
+use squirreldb::webhooks::{
+  matches_filter, sign_payload, WebhookEndpoint, WebhookFeature, WebhookSettings,
+};
+use std::time::Duration;
+use types::{Change, ChangeOperation, StructuredFilter, DEFAULT_PROJECT_ID};
+use uuid::Uuid;
+
+fn change(collection: &str, operation: ChangeOperation, data: serde_json::Value) -> Change {
+  let (old_data, new_data) = match operation {
+    ChangeOperation::Delete => (Some(data), None),
+    _ => (None, Some(data)),
+  };
+  Change {
+    id: 1,
+    project_id: DEFAULT_PROJECT_ID,
+    collection: collection.to_string(),
+    document_id: Uuid::new_v4(),
+    operation,
+    old_data,
+    new_data,
+    changed_at: Utc::now(),
+  }
+}
+
+fn filter(value: serde_json::Value) -> StructuredFilter {
+  serde_json::from_value(value).unwrap()
+}
+
+// =============================================================================
+// Settings Tests
+// =============================================================================
+
+#[test]
+fn test_webhook_settings_defaults() {
+  let settings: WebhookSettings = serde_json::from_value(json!({})).unwrap();
+  assert!(settings.endpoints.is_empty());
+  assert_eq!(settings.max_retries, 5);
+  assert_eq!(settings.initial_backoff_ms, 500);
+  assert_eq!(settings.timeout_ms, 10_000);
+}
+
+#[test]
+fn test_webhook_settings_from_json() {
+  let settings: WebhookSettings = serde_json::from_value(json!({
+    "endpoints": [{
+      "url": "https://example.com/hook",
+      "collection": "orders",
+      "filter": {"status": {"$eq": "paid"}},
+      "secret": "s3cret"
+    }],
+    "max_retries": 2
+  }))
+  .unwrap();
+
+  assert_eq!(settings.endpoints.len(), 1);
+  assert_eq!(settings.endpoints[0].collection.as_deref(), Some("orders"));
+  assert!(settings.endpoints[0].filter.is_some());
+  assert_eq!(settings.max_retries, 2);
+  assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn test_webhook_settings_rejects_invalid_url() {
+  let settings: WebhookSettings = serde_json::from_value(json!({
+    "endpoints": [{"url": "ftp://example.com"}]
+  }))
+  .unwrap();
+  assert!(settings.validate().is_err());
+}
+
+#[test]
+fn test_webhook_backoff_is_exponential_and_capped() {
+  let settings = WebhookSettings {
+    initial_backoff_ms: 100,
+    max_backoff_ms: 1000,
+    ..Default::default()
+  };
+  assert_eq!(settings.backoff(1), Duration::from_millis(100));
+  assert_eq!(settings.backoff(2), Duration::from_millis(200));
+  assert_eq!(settings.backoff(3), Duration::from_millis(400));
+  assert_eq!(settings.backoff(10), Duration::from_millis(1000));
+}
+
+// =============================================================================
+// Endpoint Matching Tests
+// =============================================================================
+
+#[test]
+fn test_endpoint_matches_collection() {
+  let endpoint = WebhookEndpoint {
+    url: "https://example.com".to_string(),
+    project_id: None,
+    collection: Some("orders".to_string()),
+    filter: None,
+    secret: None,
+  };
+  assert!(endpoint.matches(&change("orders", ChangeOperation::Insert, json!({}))));
+  assert!(!endpoint.matches(&change("users", ChangeOperation::Insert, json!({}))));
+}
+
+#[test]
+fn test_endpoint_matches_project() {
+  let endpoint = WebhookEndpoint {
+    url: "https://example.com".to_string(),
+    project_id: Some(Uuid::new_v4()),
+    collection: None,
+    filter: None,
+    secret: None,
+  };
+  assert!(!endpoint.matches(&change("orders", ChangeOperation::Insert, json!({}))));
+}
+
+#[test]
+fn test_endpoint_filter_uses_old_data_for_deletes() {
+  let endpoint = WebhookEndpoint {
+    url: "https://example.com".to_string(),
+    project_id: None,
+    collection: None,
+    filter: Some(filter(json!({"status": "paid"}))),
+    secret: None,
+  };
+  let paid = json!({"status": "paid"});
+  assert!(endpoint.matches(&change("orders", ChangeOperation::Insert, paid.clone())));
+  assert!(endpoint.matches(&change("orders", ChangeOperation::Delete, paid)));
+  assert!(!endpoint.matches(&change(
+    "orders",
+    ChangeOperation::Update,
+    json!({"status": "pending"})
+  )));
+}
+
+// =============================================================================
+// Filter Evaluation Tests
+// =============================================================================
+
+#[test]
+fn test_filter_comparison_operators() {
+  let doc = json!({"age": 30, "name": "Alice"});
+  assert!(matches_filter(&filter(json!({"age": {"$gt": 21}})), &doc));
+  assert!(matches_filter(&filter(json!({"age": {"$lte": 30}})), &doc));
+  assert!(!matches_filter(&filter(json!({"age": {"$lt": 30}})), &doc));
+  assert!(matches_filter(
+    &filter(json!({"name": {"$ne": "Bob"}})),
+    &doc
+  ));
+}
+
+#[test]
+fn test_filter_near_operator() {
+  let doc = json!({"location": {"lat": 40.7075, "lng": -74.0113}});
+  let near = |meters: u32| {
+    filter(json!({"location": {"$near": {"lat": 40.7, "lng": -74.0, "maxDistance": meters}}}))
+  };
+  assert!(matches_filter(&near(2000), &doc));
+  assert!(!matches_filter(&near(1000), &doc));
+  assert!(!matches_filter(&near(2000), &json!({"location": "nyc"})));
+}
+
+#[test]
+fn test_filter_string_and_set_operators() {
+  let doc = json!({"email": "alice@example.com", "role": "admin"});
+  assert!(matches_filter(
+    &filter(json!({"email": {"$endsWith": "@example.com"}})),
+    &doc
+  ));
+  assert!(matches_filter(
+    &filter(json!({"role": {"$in": ["admin", "owner"]}})),
+    &doc
+  ));
+  assert!(!matches_filter(
+    &filter(json!({"role": {"$nin": ["admin"]}})),
+    &doc
+  ));
+  assert!(matches_filter(
+    &filter(json!({"phone": {"$exists": false}})),
+    &doc
+  ));
+}
+
+#[test]
+fn test_filter_logical_and_nested_fields() {
+  let doc = json!({"address": {"city": "Tokyo"}, "active": true});
+  assert!(matches_filter(
+    &filter(json!({"$and": [{"address.city": "Tokyo"}, {"active": true}]})),
+    &doc
+  ));
+  assert!(matches_filter(
+    &filter(json!({"$or": [{"address.city": "Paris"}, {"active": true}]})),
+    &doc
+  ));
+  assert!(!matches_filter(
+    &filter(json!({"$not": {"address.city": "Tokyo"}})),
+    &doc
+  ));
+}
+
+// =============================================================================
+// Signing Tests
+// =============================================================================
+
+#[test]
+fn test_sign_payload_hmac_sha256() {
+  // RFC 4231 test case 2
+  let sig = sign_payload("Jefe", b"what do ya want for nothing?");
+  assert_eq!(
+    sig,
+    "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+  );
+}
+
+#[test]
+fn test_sign_payload_depends_on_secret() {
+  let body = br#"{"id":"1"}"#;
+  assert_ne!(sign_payload("a", body), sign_payload("b", body));
+}
+
+// =============================================================================
+// Feature Tests
+// =============================================================================
+
+#[test]
+fn test_webhook_feature_metadata() {
+  let feature = WebhookFeature::new();
+  assert_eq!(feature.name(), "webhooks");
+  assert!(!feature.is_running());
+  assert_eq!(feature.delivered(), 0);
+  assert_eq!(feature.failed(), 0);
+}
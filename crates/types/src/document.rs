@@ -10,4 +10,8 @@ pub struct Document {
   pub data: serde_json::Value,
   pub created_at: DateTime<Utc>,
   pub updated_at: DateTime<Utc>,
+  /// When the document was soft-deleted (only set on documents read with
+  /// `with_deleted` from a soft-delete collection)
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<DateTime<Utc>>,
 }
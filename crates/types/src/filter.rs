@@ -15,6 +15,12 @@ pub struct StructuredQuery {
   pub skip: Option<usize>,
   #[serde(default)]
   pub changes: Option<ChangesSpec>,
+  /// Fields to return instead of the whole document
+  #[serde(default)]
+  pub select: Option<Vec<String>>,
+  /// Include soft-deleted documents
+  #[serde(default)]
+  pub with_deleted: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +28,35 @@ pub struct SortSpec {
   pub field: String,
   #[serde(default)]
   pub direction: SortDirection,
+  /// Sort by distance from this point instead of by the field's value
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub near: Option<GeoPoint>,
+}
+
+/// A point on the globe, stored in documents as `{ "lat": .., "lng": .. }`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+  pub lat: f64,
+  pub lng: f64,
+}
+
+/// Argument of the `$near` operator
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoNear {
+  pub lat: f64,
+  pub lng: f64,
+  /// Maximum great-circle distance in meters
+  #[serde(rename = "maxDistance")]
+  pub max_distance: f64,
+}
+
+impl GeoNear {
+  pub fn point(&self) -> GeoPoint {
+    GeoPoint {
+      lat: self.lat,
+      lng: self.lng,
+    }
+  }
 }
 
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
@@ -96,6 +131,9 @@ pub enum FilterOperator {
   EndsWith(String),
   #[serde(rename = "$exists")]
   Exists(bool),
+  /// Geo point field within `maxDistance` meters of `{ lat, lng }`
+  #[serde(rename = "$near")]
+  Near(GeoNear),
 }
 
 impl StructuredQuery {
@@ -159,4 +197,38 @@ mod tests {
     assert!(query.is_changes());
     assert!(query.changes.as_ref().unwrap().include_initial);
   }
+
+  #[test]
+  fn deserialize_near_filter_and_distance_sort() {
+    let json = r#"{
+      "table": "users",
+      "filter": {"location": {"$near": {"lat": 40.7, "lng": -74.0, "maxDistance": 5000}}},
+      "sort": [{"field": "location", "near": {"lat": 40.7, "lng": -74.0}}]
+    }"#;
+    let query: StructuredQuery = serde_json::from_str(json).unwrap();
+    let Some(StructuredFilter::Fields(fields)) = &query.filter else {
+      panic!("expected field filter");
+    };
+    match &fields["location"] {
+      FieldCondition::Operator(FilterOperator::Near(near)) => {
+        assert_eq!(
+          near.point(),
+          GeoPoint {
+            lat: 40.7,
+            lng: -74.0
+          }
+        );
+        assert_eq!(near.max_distance, 5000.0);
+      }
+      other => panic!("expected $near, got {:?}", other),
+    }
+    let sort = &query.sort.unwrap()[0];
+    assert_eq!(
+      sort.near,
+      Some(GeoPoint {
+        lat: 40.7,
+        lng: -74.0
+      })
+    );
+  }
 }
@@ -8,11 +8,14 @@ mod query;
 pub use change::{Change, ChangeNotification, ChangeOperation};
 pub use document::Document;
 pub use filter::{
-  ChangesSpec, FieldCondition, FilterOperator, LogicalFilter,
+  ChangesSpec, FieldCondition, FilterOperator, GeoNear, GeoPoint, LogicalFilter,
   SortDirection as StructuredSortDirection, SortSpec, StructuredFilter, StructuredQuery,
 };
 pub use project::{Project, ProjectMember, ProjectRole, DEFAULT_PROJECT_ID};
-pub use protocol::{ChangeEvent, ClientMessage, QueryInput, ServerMessage};
+pub use protocol::{
+  ChangeEvent, ClientMessage, ErrorCode, QueryInput, ResultDiff, ServerInfo, ServerMessage,
+  MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
 pub use query::{
   ChangesOptions, CompiledFilter, FilterSpec, OrderBySpec, OrderDirection, QuerySpec,
 };
@@ -1,8 +1,16 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::{Document, StructuredQuery};
 
+/// Newest message protocol version the server speaks. Clients that don't
+/// negotiate with `hello` get version 1.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// Oldest message protocol version the server still accepts
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
 /// Query input - either a JS string (legacy) or a structured query object
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -75,6 +83,17 @@ impl From<StructuredQuery> for QueryInput {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum ClientMessage {
+  /// Negotiate the protocol version; sent first, with the newest version
+  /// the client supports
+  Hello {
+    id: String,
+    version: u32,
+  },
+  /// Authenticate the connection with an API token (TCP protocol)
+  Authenticate {
+    id: String,
+    token: String,
+  },
   SelectProject {
     id: String,
     project_id: Uuid,
@@ -82,10 +101,41 @@ pub enum ClientMessage {
   Query {
     id: String,
     query: QueryInput,
+    /// Serve the result from the query cache when the cache feature is running
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    cache: bool,
+    /// Seconds a cached result stays valid (default: 60)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_ttl: Option<u64>,
+    /// Values for `$1`, `$2`, ... in the query's filter, bound as SQL
+    /// parameters instead of being written into the query text
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    params: Vec<serde_json::Value>,
+    /// Send the rows in `resultchunk` messages as they are read, ending with
+    /// `resultend`, instead of one `result`. Streamed results skip the query
+    /// cache and the server's `max_result_rows` cap.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
   },
   Subscribe {
     id: String,
     query: QueryInput,
+    /// Skip changes this connection already saw in the response to its own
+    /// insert, update, delete or restore. Write responses then carry the
+    /// `change_id` they produced, and change events carry theirs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    read_your_writes: bool,
+    /// Only forward changes to these documents (default: all documents
+    /// matching the query)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ids: Vec<Uuid>,
+  },
+  /// Keep the result of a query up to date: `diff` messages bring the
+  /// client's copy in line with the query's current result, in order and
+  /// within its limit. Stopped with `unsubscribe`.
+  Watch {
+    id: String,
+    query: QueryInput,
   },
   Unsubscribe {
     id: String,
@@ -94,18 +144,40 @@ pub enum ClientMessage {
     id: String,
     collection: String,
     data: serde_json::Value,
+    /// Retrying with the same key returns the first insert's result instead
+    /// of inserting again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idempotency_key: Option<String>,
+    /// Keep this original creation time instead of the current time (imports)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preserve_created_at: Option<DateTime<Utc>>,
+    /// Set `updated_at` to `created_at` instead of the current time
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    no_touch: bool,
   },
   Update {
     id: String,
     collection: String,
     document_id: Uuid,
     data: serde_json::Value,
+    /// Keep the document's current `updated_at`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    no_touch: bool,
+    /// Fill in the collection's default values for missing fields
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    apply_defaults: bool,
   },
   Delete {
     id: String,
     collection: String,
     document_id: Uuid,
   },
+  /// Bring back a soft-deleted document
+  Restore {
+    id: String,
+    collection: String,
+    document_id: Uuid,
+  },
   ListCollections {
     id: String,
   },
@@ -120,52 +192,226 @@ pub enum ClientMessage {
 impl ClientMessage {
   pub fn id(&self) -> &str {
     match self {
-      Self::SelectProject { id, .. }
+      Self::Hello { id, .. }
+      | Self::Authenticate { id, .. }
+      | Self::SelectProject { id, .. }
       | Self::Query { id, .. }
       | Self::Subscribe { id, .. }
+      | Self::Watch { id, .. }
       | Self::Unsubscribe { id }
       | Self::Insert { id, .. }
       | Self::Update { id, .. }
       | Self::Delete { id, .. }
+      | Self::Restore { id, .. }
       | Self::ListCollections { id }
       | Self::ListProjects { id }
       | Self::Ping { id } => id,
     }
   }
+
+  /// Protocol version that introduced this message
+  pub fn min_version(&self) -> u32 {
+    match self {
+      Self::Hello { .. } | Self::Restore { .. } => 2,
+      Self::Watch { .. } => 3,
+      _ => 1,
+    }
+  }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum ServerMessage {
-  Result { id: String, data: serde_json::Value },
-  Change { id: String, change: ChangeEvent },
-  Subscribed { id: String },
-  Unsubscribed { id: String },
-  ProjectSelected { id: String, project_id: Uuid },
-  Error { id: String, error: String },
-  Pong { id: String },
+  Result {
+    id: String,
+    data: serde_json::Value,
+    /// The query had no limit and was cut at the server's `max_result_rows`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    /// Change recorded by this write, for read-your-writes subscriptions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    change_id: Option<i64>,
+  },
+  /// Some of the rows of a streamed query, in order
+  ResultChunk {
+    id: String,
+    data: serde_json::Value,
+  },
+  /// A streamed query sent all its rows
+  ResultEnd {
+    id: String,
+    /// Rows sent across all chunks
+    rows: u64,
+  },
+  Change {
+    id: String,
+    change: ChangeEvent,
+    /// ID of the change, for read-your-writes subscriptions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    change_id: Option<i64>,
+  },
+  /// Steps that update a watched result, applied in order
+  Diff {
+    id: String,
+    diff: Vec<ResultDiff>,
+  },
+  /// The subscription is registered and will receive changes
+  Subscribed {
+    id: String,
+    /// The filter was compiled to SQL and runs in the database. False when
+    /// it is evaluated for each change in the server's JavaScript runtime,
+    /// or when there is no filter.
+    #[serde(default)]
+    compiled: bool,
+  },
+  Unsubscribed {
+    id: String,
+  },
+  ProjectSelected {
+    id: String,
+    project_id: Uuid,
+  },
+  Error {
+    id: String,
+    error: String,
+    /// Machine-readable category of the error, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    code: Option<ErrorCode>,
+    /// Correlation ID of the failed request, for matching server logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trace_id: Option<String>,
+  },
+  Pong {
+    id: String,
+  },
+  /// Reply to `hello` with the version used for the rest of the connection.
+  /// Also sent unprompted (with an empty `id`) as the connection banner.
+  Hello {
+    id: String,
+    version: u32,
+    /// Server identity, unless the banner is turned off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    server: Option<ServerInfo>,
+  },
+}
+
+/// Server identity announced in the connection banner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerInfo {
+  pub name: String,
+  pub version: String,
+  /// Oldest message protocol version accepted
+  pub min_protocol_version: u32,
+  /// Newest message protocol version accepted
+  pub max_protocol_version: u32,
+  /// Enabled client protocols, e.g. `websocket` and `tcp`
+  pub protocols: Vec<String>,
+  /// Greeting configured by the operator
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub message: Option<String>,
 }
 
 impl ServerMessage {
+  /// Unprompted `hello` announcing the server on a new connection, which
+  /// speaks version 1 until the client negotiates
+  pub fn banner(server: ServerInfo) -> Self {
+    Self::Hello {
+      id: String::new(),
+      version: MIN_PROTOCOL_VERSION,
+      server: Some(server),
+    }
+  }
   pub fn result(id: impl Into<String>, data: serde_json::Value) -> Self {
     Self::Result {
       id: id.into(),
       data,
+      truncated: false,
+      change_id: None,
+    }
+  }
+  /// Query result, flagged when it was cut at the server's row cap
+  pub fn query_result(id: impl Into<String>, data: serde_json::Value, truncated: bool) -> Self {
+    Self::Result {
+      id: id.into(),
+      data,
+      truncated,
+      change_id: None,
+    }
+  }
+  pub fn result_chunk(id: impl Into<String>, data: serde_json::Value) -> Self {
+    Self::ResultChunk {
+      id: id.into(),
+      data,
+    }
+  }
+  pub fn result_end(id: impl Into<String>, rows: u64) -> Self {
+    Self::ResultEnd {
+      id: id.into(),
+      rows,
     }
   }
   pub fn error(id: impl Into<String>, error: impl Into<String>) -> Self {
     Self::Error {
       id: id.into(),
       error: error.into(),
+      code: None,
+      trace_id: None,
     }
   }
-  pub fn subscribed(id: impl Into<String>) -> Self {
-    Self::Subscribed { id: id.into() }
+  /// Error response for a text message that isn't a valid `ClientMessage`.
+  /// The `id` is recovered from the raw JSON when possible so the client can
+  /// match the error to its request.
+  pub fn parse_error(text: &str, err: &serde_json::Error) -> Self {
+    let id = serde_json::from_str::<serde_json::Value>(text)
+      .ok()
+      .and_then(|v| v.get("id").and_then(|id| id.as_str()).map(String::from))
+      .unwrap_or_else(|| "0".to_string());
+    let code = if err.is_data() {
+      ErrorCode::InvalidMessage
+    } else {
+      ErrorCode::InvalidJson
+    };
+    Self::error(id, format!("Invalid message: {}", err)).with_code(code)
+  }
+  /// Attach an error code to an error response; other messages are unchanged
+  pub fn with_code(mut self, error_code: ErrorCode) -> Self {
+    if let Self::Error { code, .. } = &mut self {
+      *code = Some(error_code);
+    }
+    self
+  }
+  /// Attach a trace ID to an error response; other messages are unchanged
+  pub fn with_trace_id(mut self, id: &str) -> Self {
+    if let Self::Error { trace_id, .. } = &mut self {
+      *trace_id = Some(id.to_string());
+    }
+    self
+  }
+  pub fn subscribed(id: impl Into<String>, compiled: bool) -> Self {
+    Self::Subscribed {
+      id: id.into(),
+      compiled,
+    }
   }
   pub fn change(id: impl Into<String>, change: ChangeEvent) -> Self {
     Self::Change {
       id: id.into(),
       change,
+      change_id: None,
+    }
+  }
+  /// Attach a change ID to a result or change event; other messages are
+  /// unchanged
+  pub fn with_change_id(mut self, id: Option<i64>) -> Self {
+    if let Self::Result { change_id, .. } | Self::Change { change_id, .. } = &mut self {
+      *change_id = id;
+    }
+    self
+  }
+  pub fn diff(id: impl Into<String>, diff: Vec<ResultDiff>) -> Self {
+    Self::Diff {
+      id: id.into(),
+      diff,
     }
   }
   pub fn pong(id: impl Into<String>) -> Self {
@@ -173,6 +419,69 @@ impl ServerMessage {
   }
 }
 
+/// One step in updating a watched result. Indexes refer to the result as
+/// left by the steps before it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum ResultDiff {
+  /// `document` entered the result at `index`
+  Add {
+    index: usize,
+    document: serde_json::Value,
+  },
+  /// The document `id` at `index` left the result
+  Remove { index: usize, id: Uuid },
+  /// The document at `from` moved to `to`; `document` is its current value
+  Move {
+    from: usize,
+    to: usize,
+    document: serde_json::Value,
+  },
+  /// The document at `index` changed without moving
+  Update {
+    index: usize,
+    document: serde_json::Value,
+  },
+}
+
+/// Machine-readable error categories carried by `ServerMessage::Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+  /// The message is not valid JSON
+  InvalidJson,
+  /// The message is JSON but not a known client message: unknown `type`,
+  /// or a missing or mistyped field
+  InvalidMessage,
+  /// The request is well-formed but not valid in this state or with these
+  /// values
+  BadRequest,
+  /// The query could not be parsed or run
+  InvalidQuery,
+  /// The document, project or other target does not exist
+  NotFound,
+  /// Missing or invalid credentials
+  Unauthorized,
+  /// The credentials don't allow this operation
+  Forbidden,
+  /// The request clashes with the current state, e.g. an idempotency key
+  /// still in use
+  Conflict,
+  /// A document or request body is over the configured size limit
+  PayloadTooLarge,
+  /// Too many requests or concurrent queries; retry later
+  RateLimited,
+  /// The operation ran past the query timeout
+  Timeout,
+  /// The server can't take the request right now
+  Unavailable,
+  /// The client's protocol version is too old, or the message needs a
+  /// newer version than the one negotiated
+  VersionUnsupported,
+  /// Unexpected server-side failure
+  Internal,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum ChangeEvent {
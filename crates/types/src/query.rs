@@ -2,28 +2,48 @@ use serde::{Deserialize, Serialize};
 
 use uuid::Uuid;
 
+use crate::filter::GeoPoint;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuerySpec {
   pub project_id: Option<Uuid>,
   pub table: String,
   pub filter: Option<FilterSpec>,
   pub map: Option<String>,
-  pub order_by: Option<OrderBySpec>,
+  /// Sort keys, most significant first
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub order_by: Vec<OrderBySpec>,
   pub limit: Option<usize>,
   pub offset: Option<usize>,
   pub changes: Option<ChangesOptions>,
+  /// Fields to return instead of the whole document (dotted paths allowed)
+  #[serde(default)]
+  pub projection: Option<Vec<String>>,
+  /// Include soft-deleted documents
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub with_deleted: bool,
+  /// Values for the `$1`, `$2`, ... parameters referenced by the filter
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub params: Vec<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilterSpec {
   pub js_code: String,
   pub compiled_sql: Option<String>,
+  /// Query parameter (1-based) bound to each placeholder of `compiled_sql`,
+  /// in order
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub params: Vec<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderBySpec {
   pub field: String,
   pub direction: OrderDirection,
+  /// Order by distance from this point instead of by the field's value
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub near: Option<GeoPoint>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]